@@ -0,0 +1,327 @@
+use sqlx::{
+    mysql::MySqlConnectOptions, postgres::PgConnectOptions, sqlite::SqliteConnectOptions,
+    ConnectOptions, Connection,
+};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::str::FromStr;
+
+use crate::{
+    config::Config, connectors::connection_url::split_table, fanout::FanoutJob, safety, Result,
+    TinyEtlError,
+};
+
+/// A table of the source database and the tables its foreign keys reference
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CatalogTable {
+    pub name: String,
+    pub references: Vec<String>,
+}
+
+/// Read the tables of a SQLite, PostgreSQL (current schema) or MySQL
+/// (current database) source and their foreign keys from its catalog
+pub async fn read_catalog(connection_string: &str) -> Result<Vec<CatalogTable>> {
+    let (url, _) = split_table(connection_string)?;
+    let (tables, foreign_keys): (Vec<String>, Vec<(String, String)>) =
+        if url.starts_with("postgres://") || url.starts_with("postgresql://") {
+            let options = PgConnectOptions::from_str(&url).map_err(|e| {
+                TinyEtlError::Connection(format!("Invalid PostgreSQL connection string: {}", e))
+            })?;
+            let mut conn = options.connect().await?;
+            let tables = sqlx::query_scalar(
+                "SELECT table_name::text FROM information_schema.tables \
+                 WHERE table_schema = current_schema() AND table_type = 'BASE TABLE'",
+            )
+            .fetch_all(&mut conn)
+            .await?;
+            let foreign_keys = sqlx::query_as(
+                "SELECT child.relname::text, parent.relname::text FROM pg_constraint c \
+                 JOIN pg_class child ON child.oid = c.conrelid \
+                 JOIN pg_class parent ON parent.oid = c.confrelid \
+                 WHERE c.contype = 'f' AND child.relnamespace = current_schema()::regnamespace",
+            )
+            .fetch_all(&mut conn)
+            .await?;
+            conn.close().await?;
+            (tables, foreign_keys)
+        } else if url.starts_with("mysql://") {
+            let options = MySqlConnectOptions::from_str(&url).map_err(|e| {
+                TinyEtlError::Connection(format!("Invalid MySQL connection string: {}", e))
+            })?;
+            let mut conn = options.connect().await?;
+            let tables = sqlx::query_scalar(
+                "SELECT CAST(table_name AS CHAR) FROM information_schema.tables \
+                 WHERE table_schema = DATABASE() AND table_type = 'BASE TABLE'",
+            )
+            .fetch_all(&mut conn)
+            .await?;
+            let foreign_keys = sqlx::query_as(
+                "SELECT CAST(table_name AS CHAR), CAST(referenced_table_name AS CHAR) \
+                 FROM information_schema.key_column_usage \
+                 WHERE table_schema = DATABASE() AND referenced_table_name IS NOT NULL",
+            )
+            .fetch_all(&mut conn)
+            .await?;
+            conn.close().await?;
+            (tables, foreign_keys)
+        } else if !url.contains("://") || url.starts_with("sqlite://") {
+            let path = url
+                .trim_start_matches("sqlite://")
+                .trim_start_matches("sqlite:");
+            let mut conn = SqliteConnectOptions::new()
+                .filename(path)
+                .read_only(true)
+                .connect()
+                .await
+                .map_err(|e| {
+                    TinyEtlError::Connection(format!(
+                        "Failed to open SQLite database '{}': {}",
+                        path, e
+                    ))
+                })?;
+            let tables: Vec<String> = sqlx::query_scalar(
+                "SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%'",
+            )
+            .fetch_all(&mut conn)
+            .await?;
+            let mut foreign_keys = Vec::new();
+            for table in &tables {
+                let parents: Vec<String> =
+                    sqlx::query_scalar("SELECT \"table\" FROM pragma_foreign_key_list(?)")
+                        .bind(table)
+                        .fetch_all(&mut conn)
+                        .await?;
+                foreign_keys.extend(parents.into_iter().map(|parent| (table.clone(), parent)));
+            }
+            conn.close().await?;
+            (tables, foreign_keys)
+        } else {
+            return Err(TinyEtlError::Configuration(format!(
+                "--all-tables reads the catalog of SQLite, PostgreSQL and MySQL sources, not {}",
+                safety::display_target(connection_string)
+            )));
+        };
+
+    let mut references: BTreeMap<String, BTreeSet<String>> = tables
+        .into_iter()
+        .map(|table| (table, BTreeSet::new()))
+        .collect();
+    for (child, parent) in foreign_keys {
+        if let Some(parents) = references.get_mut(&child) {
+            parents.insert(parent);
+        }
+    }
+    Ok(references
+        .into_iter()
+        .map(|(name, parents)| CatalogTable {
+            name,
+            references: parents.into_iter().collect(),
+        })
+        .collect())
+}
+
+/// Order the tables so each comes after the tables it references, by name
+/// where the foreign keys leave a choice. References to tables outside the
+/// list and to the table itself don't constrain the order. Tables whose
+/// foreign keys form a cycle can't be ordered: that is an error, unless
+/// `allow_cycles` (foreign key checks are off), which puts them last.
+pub fn load_order(tables: &[CatalogTable], allow_cycles: bool) -> Result<Vec<String>> {
+    let mut waiting_on: HashMap<&str, BTreeSet<&str>> = tables
+        .iter()
+        .map(|table| {
+            let parents = table
+                .references
+                .iter()
+                .map(String::as_str)
+                .filter(|parent| *parent != table.name && tables.iter().any(|t| t.name == *parent))
+                .collect();
+            (table.name.as_str(), parents)
+        })
+        .collect();
+
+    let mut order = Vec::new();
+    let mut ready: BTreeSet<&str> = waiting_on
+        .iter()
+        .filter(|(_, parents)| parents.is_empty())
+        .map(|(table, _)| *table)
+        .collect();
+    while let Some(table) = ready.pop_first() {
+        waiting_on.remove(table);
+        order.push(table.to_string());
+        for (child, parents) in waiting_on.iter_mut() {
+            if parents.remove(table) && parents.is_empty() {
+                ready.insert(*child);
+            }
+        }
+    }
+
+    let mut cyclic: Vec<&str> = waiting_on.into_keys().collect();
+    cyclic.sort();
+    if !cyclic.is_empty() && !allow_cycles {
+        return Err(TinyEtlError::Configuration(format!(
+            "The foreign keys of {} form or depend on a cycle, so none of them can be loaded first; \
+             load them with --disable-foreign-keys",
+            cyclic.join(", ")
+        )));
+    }
+    order.extend(cyclic.into_iter().map(String::from));
+    Ok(order)
+}
+
+/// One job per table, copying it to the table of the same name in the target
+/// database. The run's report file is the consolidated one, not each job's.
+pub fn table_jobs(config: &Config, order: &[String]) -> Result<Vec<FanoutJob>> {
+    let per_table = [
+        ("--query", config.query.is_some()),
+        ("--schema-file", config.schema_file.is_some()),
+        ("--incremental-column", config.incremental_column.is_some()),
+        ("--split-column", config.split_column.is_some()),
+        ("--checkpoint-file", config.checkpoint_file.is_some()),
+    ];
+    if let Some((option, _)) = per_table.iter().find(|(_, set)| *set) {
+        return Err(TinyEtlError::Configuration(format!(
+            "--all-tables can't be combined with {}, which applies to a single table",
+            option
+        )));
+    }
+    for (end, url) in [("source", &config.source), ("target", &config.target)] {
+        if split_table(url)?.1.is_some() {
+            return Err(TinyEtlError::Configuration(format!(
+                "With --all-tables the {} names a database, not a table: {}",
+                end,
+                safety::display_target(url)
+            )));
+        }
+    }
+    let target = config.target.to_lowercase();
+    if !safety::is_database(&config.target)
+        && !target.ends_with(".db")
+        && !target.ends_with(".duckdb")
+    {
+        return Err(TinyEtlError::Configuration(format!(
+            "--all-tables copies tables into a database target, not {}",
+            safety::display_target(&config.target)
+        )));
+    }
+
+    Ok(order
+        .iter()
+        .map(|table| FanoutJob {
+            params: HashMap::from([("table".to_string(), table.clone())]),
+            config: Config {
+                source: format!("{}#{}", config.source, table),
+                target: format!("{}#{}", config.target, table),
+                all_tables: false,
+                report_file: None,
+                ..config.clone()
+            },
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(name: &str, references: &[&str]) -> CatalogTable {
+        CatalogTable {
+            name: name.to_string(),
+            references: references.iter().map(|r| r.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_tables_load_after_the_tables_they_reference() {
+        let tables = [
+            table("order_items", &["orders", "products"]),
+            table("orders", &["customers"]),
+            table("customers", &[]),
+            table("products", &["products", "archive.products"]),
+            table("employees", &["employees"]),
+        ];
+        assert_eq!(
+            load_order(&tables, false).unwrap(),
+            [
+                "customers",
+                "employees",
+                "orders",
+                "products",
+                "order_items"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_cycles_need_foreign_keys_off() {
+        let tables = [
+            table("a", &["b"]),
+            table("b", &["a"]),
+            table("c", &[]),
+            table("d", &["a"]),
+        ];
+        let error = load_order(&tables, false).unwrap_err().to_string();
+        assert!(error.contains("of a, b, d form"), "{}", error);
+        assert_eq!(load_order(&tables, true).unwrap(), ["c", "a", "b", "d"]);
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_catalog_and_table_jobs() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("shop.db");
+        let source = path.to_str().unwrap().to_string();
+        let mut conn = SqliteConnectOptions::new()
+            .filename(&path)
+            .create_if_missing(true)
+            .connect()
+            .await
+            .unwrap();
+        for statement in [
+            "CREATE TABLE orders (id INTEGER PRIMARY KEY, customer_id INTEGER REFERENCES customers(id))",
+            "CREATE TABLE customers (id INTEGER PRIMARY KEY)",
+            "CREATE TABLE items (order_id INTEGER REFERENCES orders(id), parent INTEGER REFERENCES items(rowid))",
+        ] {
+            sqlx::query(statement).execute(&mut conn).await.unwrap();
+        }
+        conn.close().await.unwrap();
+
+        let tables = read_catalog(&source).await.unwrap();
+        assert_eq!(
+            tables,
+            [
+                table("customers", &[]),
+                table("items", &["items", "orders"]),
+                table("orders", &["customers"]),
+            ]
+        );
+        let order = load_order(&tables, false).unwrap();
+        assert_eq!(order, ["customers", "orders", "items"]);
+
+        let config = Config {
+            source: source.clone(),
+            target: "postgres://etl@db/shop".to_string(),
+            all_tables: true,
+            report_file: Some("report.json".to_string()),
+            ..Default::default()
+        };
+        let jobs = table_jobs(&config, &order).unwrap();
+        assert_eq!(jobs[1].config.source, format!("{}#orders", source));
+        assert_eq!(jobs[1].config.target, "postgres://etl@db/shop#orders");
+        assert_eq!(jobs[1].params["table"], "orders");
+        assert!(!jobs[1].config.all_tables && jobs[1].config.report_file.is_none());
+
+        let single = Config {
+            target: "postgres://etl@db/shop#orders".to_string(),
+            ..config.clone()
+        };
+        assert!(table_jobs(&single, &order).is_err());
+        let file = Config {
+            target: "out.csv".to_string(),
+            ..config.clone()
+        };
+        assert!(table_jobs(&file, &order).is_err());
+        let query = Config {
+            query: Some("SELECT 1".to_string()),
+            ..config
+        };
+        assert!(table_jobs(&query, &order).is_err());
+    }
+}
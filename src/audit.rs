@@ -0,0 +1,200 @@
+use chrono::{DateTime, Utc};
+use std::fmt::Display;
+use tracing::{info, warn};
+
+use crate::{
+    config::{Config, IfExists},
+    connectors::create_target_from_url,
+    redact::redact,
+    schema::{Column, DataType, Row, Schema, Value},
+    transfer::{TransferEngine, TransferStats},
+    Result,
+};
+
+/// One run as recorded in the `audit_table`
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuditRecord {
+    pub job_name: String,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    pub rows_written: usize,
+    /// `succeeded`, `failed` or `cancelled`
+    pub status: &'static str,
+    pub error: Option<String>,
+    /// Merkle root of the rows written, with `row_hash`
+    pub row_hash_root: Option<String>,
+}
+
+impl AuditRecord {
+    pub fn new<E: Display>(
+        config: &Config,
+        started_at: DateTime<Utc>,
+        outcome: &std::result::Result<TransferStats, E>,
+    ) -> Self {
+        let (rows_written, status, error) = match outcome {
+            Ok(stats) if stats.cancelled => (stats.total_rows, "cancelled", None),
+            Ok(stats) => (stats.total_rows, "succeeded", None),
+            Err(e) => (0, "failed", Some(redact(&e.to_string()))),
+        };
+        let row_hash_root = outcome
+            .as_ref()
+            .ok()
+            .and_then(|stats| stats.row_hash_root.clone());
+        Self {
+            job_name: job_name(config),
+            started_at,
+            finished_at: Utc::now(),
+            rows_written,
+            status,
+            error,
+            row_hash_root,
+        }
+    }
+
+    pub fn schema() -> Schema {
+        let column = |name: &str, data_type: DataType, nullable: bool| Column {
+            name: name.to_string(),
+            data_type,
+            nullable,
+        };
+        Schema {
+            columns: vec![
+                column("job_name", DataType::String, false),
+                column("started_at", DataType::DateTime, false),
+                column("finished_at", DataType::DateTime, false),
+                column("rows_written", DataType::Integer, false),
+                column("status", DataType::String, false),
+                column("error", DataType::String, true),
+                column("row_hash_root", DataType::String, true),
+            ],
+            estimated_rows: Some(1),
+            primary_key_candidate: None,
+        }
+    }
+
+    pub fn row(&self) -> Row {
+        Row::from([
+            ("job_name".to_string(), Value::String(self.job_name.clone())),
+            ("started_at".to_string(), Value::Date(self.started_at)),
+            ("finished_at".to_string(), Value::Date(self.finished_at)),
+            (
+                "rows_written".to_string(),
+                Value::Integer(self.rows_written as i64),
+            ),
+            ("status".to_string(), Value::String(self.status.to_string())),
+            (
+                "error".to_string(),
+                self.error.clone().map_or(Value::Null, Value::String),
+            ),
+            (
+                "row_hash_root".to_string(),
+                self.row_hash_root
+                    .clone()
+                    .map_or(Value::Null, Value::String),
+            ),
+        ])
+    }
+}
+
+/// The `job_name`, or the name of the target table or file
+pub fn job_name(config: &Config) -> String {
+    config
+        .job_name
+        .clone()
+        .unwrap_or_else(|| TransferEngine::extract_table_name(&config.target))
+}
+
+/// Append a record to an audit table URL, creating the table on first use
+pub async fn write(audit_table: &str, record: &AuditRecord) -> Result<()> {
+    let table_name = TransferEngine::extract_table_name(audit_table);
+    let mut target = create_target_from_url(audit_table).await?;
+    target.connect().await?;
+    let exists = target.exists(&table_name).await?;
+    TransferEngine::prepare_target(
+        target.as_mut(),
+        &table_name,
+        &AuditRecord::schema(),
+        exists,
+        IfExists::Append,
+    )
+    .await?;
+    target.write_batch(&[record.row()]).await?;
+    target.finalize().await
+}
+
+/// Record a finished run in the config's `audit_table`, if it has one.
+/// Previews and dry runs aren't recorded, and a failure to record is only
+/// logged, so auditing never changes the outcome of the run.
+pub async fn record_run<E: Display>(
+    config: &Config,
+    started_at: DateTime<Utc>,
+    outcome: &std::result::Result<TransferStats, E>,
+) {
+    let Some(audit_table) = &config.audit_table else {
+        return;
+    };
+    if config.preview.is_some() || config.dry_run {
+        return;
+    }
+    let record = AuditRecord::new(config, started_at, outcome);
+    match write(audit_table, &record).await {
+        Ok(()) => info!("Run of {} recorded in the audit table", record.job_name),
+        Err(e) => warn!("Failed to record the run in the audit table: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connectors::create_source_from_url;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_runs_are_appended_to_the_audit_table() {
+        let dir = TempDir::new().unwrap();
+        let db = dir.path().join("audit.db");
+        let config = Config {
+            target: "orders.csv".to_string(),
+            audit_table: Some(format!("{}#etl_runs", db.display())),
+            ..Config::default()
+        };
+        assert_eq!(job_name(&config), "orders");
+
+        let stats = TransferStats {
+            total_rows: 42,
+            row_hash_root: Some("ab".repeat(32)),
+            ..TransferStats::default()
+        };
+        record_run(&config, Utc::now(), &Ok::<_, String>(stats)).await;
+        let config = Config {
+            job_name: Some("nightly_orders".to_string()),
+            ..config
+        };
+        record_run(
+            &config,
+            Utc::now(),
+            &Err::<TransferStats, _>("connection refused"),
+        )
+        .await;
+
+        let mut source = create_source_from_url(&format!("{}#etl_runs", db.display()))
+            .await
+            .unwrap();
+        source.connect().await.unwrap();
+        let mut rows = source.read_batch(10).await.unwrap();
+        rows.sort_by_key(|row| matches!(row["status"], Value::String(ref s) if s == "failed"));
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0]["job_name"], Value::String("orders".to_string()));
+        assert_eq!(rows[0]["rows_written"], Value::Integer(42));
+        assert_eq!(rows[0]["error"], Value::Null);
+        assert_eq!(rows[0]["row_hash_root"], Value::String("ab".repeat(32)));
+        assert_eq!(
+            rows[1]["job_name"],
+            Value::String("nightly_orders".to_string())
+        );
+        assert_eq!(
+            rows[1]["error"],
+            Value::String("connection refused".to_string())
+        );
+    }
+}
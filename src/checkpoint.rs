@@ -0,0 +1,126 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::{config::Config, safety::display_target, Result, TinyEtlError};
+
+/// Progress of a transfer, saved to the checkpoint file (JSON) after every
+/// batch written so `--resume` can continue an interrupted run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    /// Source and target of the run, with passwords masked
+    pub source: String,
+    pub target: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub query: Option<String>,
+    /// Source rows consumed by the batches written so far
+    pub rows_read: usize,
+    pub rows_written: usize,
+    /// Rows the target rejected with `--bisect-failed-batches`
+    #[serde(default)]
+    pub rows_rejected: usize,
+    /// Size of the target after the last batch, when the target reports one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bytes_written: Option<u64>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Checkpoint {
+    /// Checkpoint of a run that hasn't written anything yet
+    pub fn new(config: &Config) -> Self {
+        Self {
+            source: display_target(&config.source),
+            target: display_target(&config.target),
+            query: config.query.clone(),
+            rows_read: 0,
+            rows_written: 0,
+            rows_rejected: 0,
+            bytes_written: None,
+            updated_at: Utc::now(),
+        }
+    }
+
+    /// Load the checkpoint file; `None` when there is none, i.e. the last
+    /// run completed or never started
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Option<Self>> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = std::fs::read_to_string(path)?;
+        serde_json::from_str(&content).map(Some).map_err(|e| {
+            TinyEtlError::Configuration(format!(
+                "Invalid checkpoint file {}: {}",
+                path.display(),
+                e
+            ))
+        })
+    }
+
+    /// Write the checkpoint file, replacing it atomically
+    pub fn save<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        self.updated_at = Utc::now();
+        let mut temp = path.as_os_str().to_owned();
+        temp.push(".tmp");
+        std::fs::write(&temp, serde_json::to_string_pretty(self)?)?;
+        std::fs::rename(&temp, path)?;
+        Ok(())
+    }
+
+    /// Remove the checkpoint file once the run it tracks has completed
+    pub fn remove<P: AsRef<Path>>(path: P) -> Result<()> {
+        match std::fs::remove_file(path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Fails when the checkpoint was saved by a run of another transfer
+    pub fn check_run(&self, config: &Config) -> Result<()> {
+        let run = Self::new(config);
+        if (&self.source, &self.target, &self.query) != (&run.source, &run.target, &run.query) {
+            return Err(TinyEtlError::Configuration(format!(
+                "The checkpoint is of a transfer from {} to {}, not this one",
+                self.source, self.target
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checkpoint_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("orders.checkpoint.json");
+        let config = Config {
+            source: "postgres://etl:secret@db/shop#orders".to_string(),
+            target: "orders.jsonl".to_string(),
+            ..Config::default()
+        };
+        assert!(Checkpoint::load(&path).unwrap().is_none());
+
+        let mut checkpoint = Checkpoint::new(&config);
+        checkpoint.rows_read = 2_000;
+        checkpoint.rows_written = 1_998;
+        checkpoint.save(&path).unwrap();
+        assert!(!std::fs::read_to_string(&path).unwrap().contains("secret"));
+
+        let loaded = Checkpoint::load(&path).unwrap().unwrap();
+        assert_eq!(loaded.rows_read, 2_000);
+        loaded.check_run(&config).unwrap();
+        let other = Config {
+            target: "other.jsonl".to_string(),
+            ..config
+        };
+        assert!(loaded.check_run(&other).is_err());
+
+        Checkpoint::remove(&path).unwrap();
+        assert!(Checkpoint::load(&path).unwrap().is_none());
+        Checkpoint::remove(&path).unwrap();
+    }
+}
@@ -1,6 +1,14 @@
-use crate::config::{Config, LogLevel};
+use crate::config::{Config, IfExists, LogLevel, OversizePolicy, RowErrorPolicy};
+use crate::null_fallback::{parse_column_fallback, NullFallback};
+use crate::output_names::HeaderCase;
+use crate::router::RouteConfig;
+use crate::row_number::RowNumberConfig;
+use crate::schema::{BinaryEncoding, SchemaMode};
+use crate::schema_export::SchemaFormat;
+use crate::state::SchemaDriftPolicy;
 use crate::transformer::TransformConfig;
 use clap::{Parser, Subcommand};
+use std::collections::HashMap;
 
 #[derive(Parser)]
 #[command(name = "tinyetl")]
@@ -22,7 +30,7 @@ pub struct Cli {
     #[arg(long, default_value = "true")]
     pub infer_schema: bool,
 
-    /// Path to schema file (YAML) to override auto-detection
+    /// Path to schema file (YAML), or a dbt properties file as FILE#MODEL, to override auto-detection
     #[arg(long, value_name = "FILE")]
     pub schema_file: Option<String>,
 
@@ -46,7 +54,7 @@ pub struct Cli {
     #[arg(long)]
     pub skip_existing: bool,
 
-    /// Truncate target before writing (overrides append-first behavior)
+    /// Truncate target before writing, same as --if-exists truncate
     #[arg(long)]
     pub truncate: bool,
 
@@ -69,9 +77,307 @@ pub struct Cli {
     /// Secret ID for destination password (resolves to TINYETL_SECRET_{id})
     #[arg(long, value_name = "ID")]
     pub dest_secret_id: Option<String>,
+
+    /// Compression level for compressed outputs (Parquet, .gz and .zst files; Parquet uses zstd unless the target option 'compression' selects another codec)
+    #[arg(long, value_name = "LEVEL", allow_negative_numbers = true)]
+    pub compression_level: Option<i32>,
+
+    /// How long a SQLite target waits for other writers to release the database, in milliseconds
+    #[arg(long, value_name = "MS")]
+    pub sqlite_busy_timeout: Option<u64>,
+
+    /// Hold a lock file while loading a SQLite target so parallel loads to one file take turns
+    #[arg(long)]
+    pub sqlite_lock: bool,
+
+    /// Create a new PostgreSQL target table as UNLOGGED and switch it to LOGGED after the load
+    #[arg(long)]
+    pub pg_unlogged: bool,
+
+    /// Load a new or truncated PostgreSQL table with COPY FREEZE in a single transaction
+    #[arg(long)]
+    pub pg_copy_freeze: bool,
+
+    /// Write to PostgreSQL with COPY instead of multi-row INSERTs, falling back to INSERT if the server refuses COPY
+    #[arg(long)]
+    pub pg_copy: bool,
+
+    /// Turn off foreign key checks on SQLite, PostgreSQL and MySQL targets while loading
+    #[arg(long)]
+    pub disable_foreign_keys: bool,
+
+    /// Character set of tables created on a MySQL target (e.g. utf8mb4)
+    #[arg(long, value_name = "CHARSET")]
+    pub target_charset: Option<String>,
+
+    /// Collation of tables created on a MySQL target, or of text columns on SQL Server
+    #[arg(long, value_name = "COLLATION")]
+    pub target_collation: Option<String>,
+
+    /// Encoding of binary values written to CSV and JSON targets: base64 (default) or hex
+    #[arg(long, value_name = "ENCODING")]
+    pub binary_encoding: Option<BinaryEncoding>,
+
+    /// Skip N lines before the header row of a CSV source (e.g. report banners)
+    #[arg(long, value_name = "N")]
+    pub skip_rows: Option<usize>,
+
+    /// Drop the last N records of a CSV source (e.g. a totals line)
+    #[arg(long, value_name = "N")]
+    pub skip_footer: Option<usize>,
+
+    /// Field delimiter of a CSV source: one or more characters (e.g. '|', '||', '~|~', '\t') or regex:<PATTERN>
+    #[arg(long, value_name = "DELIMITER")]
+    pub csv_delimiter: Option<String>,
+
+    /// Compiled descriptor set (protoc --descriptor_set_out) of protobuf (.pb) sources and targets
+    #[arg(long, value_name = "FILE")]
+    pub proto_descriptor: Option<String>,
+
+    /// Read coded values of SPSS and Stata sources as their value labels
+    #[arg(long)]
+    pub value_labels: bool,
+
+    /// Table of an HTML or PDF source to read: 1 for the first on the page (or matching --table-selector), 2 for the second...
+    #[arg(long, value_name = "N")]
+    pub table_index: Option<usize>,
+
+    /// CSS selector of the table of an HTML source, or of an element holding it (e.g. '#results')
+    #[arg(long, value_name = "SELECTOR")]
+    pub table_selector: Option<String>,
+
+    /// Pages of a PDF source to look for tables on (e.g. '2-4,7' or '3-'); every page by default
+    #[arg(long, value_name = "RANGE")]
+    pub pages: Option<String>,
+
+    /// Write a JSON report with transfer statistics to this file
+    #[arg(long, value_name = "FILE")]
+    pub report_file: Option<String>,
+
+    /// Allow Lua transforms to use the io, os and module loading libraries (sandboxed by default)
+    #[arg(long)]
+    pub lua_allow_io: bool,
+
+    /// Maximum time in milliseconds a Lua transform may spend on a single row (0 disables the limit) [default: 1000]
+    #[arg(long, value_name = "MS")]
+    pub lua_timeout_ms: Option<u64>,
+
+    /// Directory searched by require() in Lua transforms (repeatable)
+    #[arg(long, value_name = "DIR")]
+    pub lua_path: Vec<String>,
+
+    /// Load the target as a Slowly Changing Dimension Type 2 table (requires --key)
+    #[arg(long)]
+    pub scd2: bool,
+
+    /// Business key columns identifying each dimension member in SCD2 mode, or each document a JSON target is merged on otherwise (comma-separated)
+    #[arg(long = "key", value_name = "COLUMNS", value_delimiter = ',')]
+    pub key_columns: Vec<String>,
+
+    /// Columns whose changes create a new version in SCD2 mode (comma-separated, default: all non-key columns)
+    #[arg(long = "track", value_name = "COLUMNS", value_delimiter = ',')]
+    pub track_columns: Vec<String>,
+
+    /// Add a row number column, e.g. "rn partition-by customer_id order-by ts" (input must be sorted by the order-by column within each partition)
+    #[arg(long, value_name = "SPEC")]
+    pub row_number: Option<RowNumberConfig>,
+
+    /// Route matching rows to another target: "<TARGET> if <LUA EXPRESSION>" or "<TARGET> otherwise" (repeatable; first match wins, unmatched rows go to the main target)
+    #[arg(long = "route", value_name = "RULE")]
+    pub routes: Vec<RouteConfig>,
+
+    /// Read the result of this SQL query from a database source instead of a whole table; use :name placeholders for --param values
+    #[arg(long, value_name = "SQL")]
+    pub query: Option<String>,
+
+    /// Bind a value to a :name placeholder in --query, e.g. since:timestamp=2024-01-01; a :int, :decimal, :bool or :timestamp suffix sets its type, otherwise it is text (repeatable)
+    #[arg(long = "param", value_name = "NAME=VALUE", value_parser = parse_key_value)]
+    pub query_params: Vec<(String, String)>,
+
+    /// Copy every table of a SQLite, PostgreSQL or MySQL source to the target database, parents before the tables referencing them
+    #[arg(long)]
+    pub all_tables: bool,
+
+    /// Guarantee no writes against the source: reject data-modifying --query SQL and targets in the source database
+    #[arg(long)]
+    pub read_only: bool,
+
+    /// Regex marking production-looking target URLs, which need --confirm-production in read-only mode [default: prod, production or prd as a separate word]
+    #[arg(long, value_name = "REGEX")]
+    pub production_pattern: Option<String>,
+
+    /// Confirm writing to a production-looking target in read-only mode
+    #[arg(long)]
+    pub confirm_production: bool,
+
+    /// Don't ask for confirmation before truncating database targets
+    #[arg(long, short = 'y')]
+    pub yes: bool,
+
+    /// Tables that must never be truncated (comma-separated); runs that would truncate them fail
+    #[arg(long, value_name = "TABLES", value_delimiter = ',')]
+    pub protect: Vec<String>,
+
+    /// Also mask what this regular expression matches in logs and errors, or only its first group if it has one (repeatable; passwords and secrets are always masked)
+    #[arg(long, value_name = "REGEX")]
+    pub redact: Vec<String>,
+
+    /// Stop after N rows have been written to the target (unlike --preview, rows are loaded)
+    #[arg(long, value_name = "N")]
+    pub limit: Option<usize>,
+
+    /// Keep state between runs in this JSON file (the source schema for drift detection, the files read by --new-files-only and the --incremental-column watermark)
+    #[arg(long, value_name = "FILE")]
+    pub state_file: Option<String>,
+
+    /// What to do when the source schema changed since the last run recorded in --state-file: warn, fail
+    #[arg(long, value_name = "POLICY", default_value = "warn")]
+    pub on_schema_drift: SchemaDriftPolicy,
+
+    /// Schema source: inferred (sample the source) or declared (use --schema-file or YAML columns verbatim, converting values strictly)
+    #[arg(long, value_name = "MODE", default_value = "inferred")]
+    pub schema_mode: SchemaMode,
+
+    /// What to do with rows that fail schema validation or conversion: fail, skip
+    #[arg(long, value_name = "POLICY", default_value = "fail")]
+    pub on_row_error: RowErrorPolicy,
+
+    /// Refresh the target table's planner statistics after loading (ANALYZE, OPTIMIZE TABLE or UPDATE STATISTICS)
+    #[arg(long)]
+    pub analyze: bool,
+
+    /// Columns to leave out of the target (comma-separated), e.g. identity columns the target generates
+    #[arg(long, value_name = "COLUMNS", value_delimiter = ',')]
+    pub exclude_columns: Vec<String>,
+
+    /// Largest allowed size of a single field value, in bytes (see --on-oversize)
+    #[arg(long, value_name = "BYTES")]
+    pub max_field_length: Option<usize>,
+
+    /// Largest allowed approximate size of a whole row, in bytes (see --on-oversize)
+    #[arg(long, value_name = "BYTES")]
+    pub max_row_bytes: Option<usize>,
+
+    /// What to do with rows over --max-field-length or --max-row-bytes: error, truncate, deadletter
+    #[arg(long, value_name = "POLICY", default_value = "error")]
+    pub on_oversize: OversizePolicy,
+
+    /// JSON Lines file receiving rows rejected by --on-oversize deadletter or --bisect-failed-batches
+    #[arg(long, value_name = "FILE")]
+    pub dead_letter_file: Option<String>,
+
+    /// When the target rejects a batch, retry it in halves to isolate the failing rows and skip only those (written to --dead-letter-file when set)
+    #[arg(long)]
+    pub bisect_failed_batches: bool,
+
+    /// Log every statement database targets execute, with parameter values summarized and cut short
+    #[arg(long)]
+    pub echo_sql: bool,
+
+    /// Fail a source read that takes longer than SECONDS; database sources also have the server stop the query
+    #[arg(long, value_name = "SECONDS")]
+    pub batch_timeout: Option<u64>,
+
+    /// Number of Lua transform workers; above 1 the source is also read ahead while batches are written, in order
+    #[arg(long, value_name = "N", default_value = "1")]
+    pub parallelism: usize,
+
+    /// Read all batches from one point-in-time snapshot of the source (PostgreSQL, MySQL and SQL Server)
+    #[arg(long)]
+    pub consistent_snapshot: bool,
+
+    /// Rows PostgreSQL and MySQL sources pull from the server at a time (defaults to --batch-size)
+    #[arg(long, value_name = "ROWS")]
+    pub fetch_size: Option<usize>,
+
+    /// Unique column without NULLs to page SQL Server sources on, for tables without a primary key or --query results
+    #[arg(long, value_name = "COLUMN")]
+    pub keyset_column: Option<String>,
+
+    /// Attach another SQLite database file under an alias for --query to join against, e.g. ref=./ref.db (repeatable)
+    #[arg(long, value_name = "ALIAS=PATH", value_parser = parse_key_value)]
+    pub attach: Vec<(String, String)>,
+
+    /// What to do when the target table or file exists: fail, append, truncate, replace
+    #[arg(
+        long,
+        value_name = "POLICY",
+        default_value = "append",
+        conflicts_with = "truncate"
+    )]
+    pub if_exists: IfExists,
+
+    /// Write file targets as a directory of partitions, one per value of these columns (comma-separated)
+    #[arg(long, value_name = "COLUMNS", value_delimiter = ',')]
+    pub partition_by: Vec<String>,
+
+    /// Replace the partitions present in the incoming data instead of adding files to them (requires --partition-by)
+    #[arg(long, requires = "partition_by")]
+    pub overwrite_partitions: bool,
+
+    /// Read only the files of a source folder that are new or changed since the last run recorded in --state-file
+    #[arg(long, requires = "state_file")]
+    pub new_files_only: bool,
+
+    /// Read only rows whose COLUMN is greater than the highest value loaded by the last run recorded in --state-file (SQL sources)
+    #[arg(long, value_name = "COLUMN", requires = "state_file")]
+    pub incremental_column: Option<String>,
+
+    /// Read a PostgreSQL, MySQL or MSSQL source as --split-count ranges of this numeric or date COLUMN, concurrently; rows arrive in no particular order
+    #[arg(long, value_name = "COLUMN")]
+    pub split_column: Option<String>,
+
+    /// Number of ranges --split-column reads at once
+    #[arg(long, value_name = "N", default_value = "4", requires = "split_column")]
+    pub split_count: usize,
+
+    /// Save the progress of the transfer to this JSON file after every batch, so an interrupted run can be resumed
+    #[arg(long, value_name = "FILE")]
+    pub checkpoint_file: Option<String>,
+
+    /// Continue the interrupted run recorded in --checkpoint-file instead of starting over
+    #[arg(long, requires = "checkpoint_file")]
+    pub resume: bool,
+
+    /// Directory for temporary files such as downloads (default: the system temp directory); removed after a successful run, kept after a failure
+    #[arg(long, value_name = "DIR")]
+    pub staging_dir: Option<String>,
+
+    /// Fail the run when its temporary files would take more than BYTES in the staging directory
+    #[arg(long, value_name = "BYTES")]
+    pub staging_max_bytes: Option<u64>,
+
+    /// Write this instead of NULL into a column that doesn't allow NULLs: empty, zero or value:<TEXT>, as COLUMN=POLICY or a bare POLICY for every column declared nullable: false (repeatable)
+    #[arg(long, value_name = "[COLUMN=]POLICY", value_parser = parse_column_fallback)]
+    pub null_fallback: Vec<(String, NullFallback)>,
+
+    /// Exact order of the output columns (comma-separated); unlisted columns follow in source order
+    #[arg(long, value_name = "COLUMNS", value_delimiter = ',')]
+    pub column_order: Vec<String>,
+
+    /// Casing of the column names written to the target: snake, camel, upper (UPPER_SNAKE), preserve
+    #[arg(long, value_name = "CASE", default_value = "preserve")]
+    pub output_header_case: HeaderCase,
+
+    /// Write a column to the target under another name, e.g. cust_id=CUSTOMER_ID (repeatable; overrides --output-header-case)
+    #[arg(long = "rename-column", value_name = "SOURCE=TARGET", value_parser = parse_key_value)]
+    pub rename_columns: Vec<(String, String)>,
+
+    /// Fail on any lossy conversion (rounded floats, unreadable values, values that don't fit their column) instead of coercing it silently
+    #[arg(long)]
+    pub strict: bool,
+
+    /// Decode a column on read and encode it again on write, e.g. payload=base64 or id=hex-uuid (repeatable; codecs: base64, hex, hex-uuid)
+    #[arg(long = "codec", value_name = "COLUMN=CODEC", value_parser = parse_key_value)]
+    pub codecs: Vec<(String, String)>,
+
+    /// Add a column holding each row's SHA-256 content hash and report the run's Merkle root hash, so the export can later be verified as unmodified
+    #[arg(long, value_name = "COLUMN")]
+    pub row_hash: Option<String>,
 }
 
 #[derive(Subcommand)]
+#[allow(clippy::large_enum_variant)]
 pub enum Commands {
     /// Run a job from a YAML configuration file
     Run {
@@ -80,6 +386,134 @@ pub enum Commands {
     },
     /// Generate a default YAML configuration example and output to STDOUT
     GenerateDefaultConfig,
+    /// Serve the YAML jobs of a directory over HTTP: POST /run/<job> starts a run, GET /runs/<id> reports it
+    Serve {
+        /// Directory of YAML job files; each job is named after its file
+        #[arg(long = "config", value_name = "DIR")]
+        config_dir: String,
+
+        /// Address to listen on
+        #[arg(long, value_name = "ADDR", default_value = "127.0.0.1:8080")]
+        listen: String,
+
+        /// Allow listening on an address other hosts can reach
+        #[arg(long)]
+        allow_remote: bool,
+    },
+    /// Infer the schema of a source and write it as an annotated schema file to edit and use with --schema-file
+    ScaffoldSchema {
+        /// Source connection string (file path or connection string)
+        source: String,
+
+        /// Write the schema file here instead of to STDOUT
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<String>,
+
+        /// Number of rows sampled to infer the types
+        #[arg(long, value_name = "N", default_value = "1000")]
+        sample_size: usize,
+
+        /// Force source file type (csv, json, parquet) - useful for HTTP URLs without clear extensions
+        #[arg(long, value_name = "TYPE")]
+        source_type: Option<String>,
+
+        /// Secret ID for source password (resolves to TINYETL_SECRET_{id})
+        #[arg(long, value_name = "ID")]
+        source_secret_id: Option<String>,
+
+        /// Skip N lines before the header row of a CSV source (e.g. report banners)
+        #[arg(long, value_name = "N")]
+        skip_rows: Option<usize>,
+
+        /// Drop the last N records of a CSV source (e.g. a totals line)
+        #[arg(long, value_name = "N")]
+        skip_footer: Option<usize>,
+
+        /// Field delimiter of a CSV source: one or more characters (e.g. '|', '||', '~|~', '\t') or regex:<PATTERN>
+        #[arg(long, value_name = "DELIMITER")]
+        csv_delimiter: Option<String>,
+
+        /// Compiled descriptor set (protoc --descriptor_set_out) of a protobuf (.pb) source
+        #[arg(long, value_name = "FILE")]
+        proto_descriptor: Option<String>,
+
+        /// Read coded values of an SPSS or Stata source as their value labels
+        #[arg(long)]
+        value_labels: bool,
+
+        /// Table of an HTML or PDF source to read: 1 for the first on the page (or matching --table-selector)
+        #[arg(long, value_name = "N")]
+        table_index: Option<usize>,
+
+        /// CSS selector of the table of an HTML source, or of an element holding it
+        #[arg(long, value_name = "SELECTOR")]
+        table_selector: Option<String>,
+
+        /// Pages of a PDF source to look for tables on (e.g. '2-4,7')
+        #[arg(long, value_name = "RANGE")]
+        pages: Option<String>,
+    },
+    /// Infer the schema of a source and write it as JSON Schema, an Avro or Arrow schema, or CREATE TABLE DDL
+    Schema {
+        /// Source connection string (file path or connection string)
+        source: String,
+
+        /// Schema format: jsonschema, avro, arrow, sql:<dialect> (postgres, mysql, sqlite, mssql, duckdb)
+        #[arg(short, long, value_name = "FORMAT", default_value = "jsonschema")]
+        format: SchemaFormat,
+
+        /// Table name of the DDL and title of the JSON Schema [default: the source's #table or file name]
+        #[arg(long, value_name = "NAME")]
+        name: Option<String>,
+
+        /// Write the schema here instead of to STDOUT
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<String>,
+
+        /// Number of rows sampled to infer the types
+        #[arg(long, value_name = "N", default_value = "1000")]
+        sample_size: usize,
+
+        /// Force source file type (csv, json, parquet) - useful for HTTP URLs without clear extensions
+        #[arg(long, value_name = "TYPE")]
+        source_type: Option<String>,
+
+        /// Secret ID for source password (resolves to TINYETL_SECRET_{id})
+        #[arg(long, value_name = "ID")]
+        source_secret_id: Option<String>,
+
+        /// Skip N lines before the header row of a CSV source (e.g. report banners)
+        #[arg(long, value_name = "N")]
+        skip_rows: Option<usize>,
+
+        /// Drop the last N records of a CSV source (e.g. a totals line)
+        #[arg(long, value_name = "N")]
+        skip_footer: Option<usize>,
+
+        /// Field delimiter of a CSV source: one or more characters (e.g. '|', '||', '~|~', '\t') or regex:<PATTERN>
+        #[arg(long, value_name = "DELIMITER")]
+        csv_delimiter: Option<String>,
+
+        /// Compiled descriptor set (protoc --descriptor_set_out) of a protobuf (.pb) source
+        #[arg(long, value_name = "FILE")]
+        proto_descriptor: Option<String>,
+
+        /// Read coded values of an SPSS or Stata source as their value labels
+        #[arg(long)]
+        value_labels: bool,
+
+        /// Table of an HTML or PDF source to read: 1 for the first on the page (or matching --table-selector)
+        #[arg(long, value_name = "N")]
+        table_index: Option<usize>,
+
+        /// CSS selector of the table of an HTML source, or of an element holding it
+        #[arg(long, value_name = "SELECTOR")]
+        table_selector: Option<String>,
+
+        /// Pages of a PDF source to look for tables on (e.g. '2-4,7')
+        #[arg(long, value_name = "RANGE")]
+        pages: Option<String>,
+    },
     /// Generate a YAML configuration file from CLI arguments and output to STDOUT
     #[command(override_usage = "tinyetl generate-config [OPTIONS] <SOURCE> <TARGET>")]
     GenerateConfig {
@@ -93,7 +527,7 @@ pub enum Commands {
         #[arg(long, default_value = "true")]
         infer_schema: bool,
 
-        /// Path to schema file (YAML) to override auto-detection
+        /// Path to schema file (YAML), or a dbt properties file as FILE#MODEL, to override auto-detection
         #[arg(long, value_name = "FILE")]
         schema_file: Option<String>,
 
@@ -117,7 +551,7 @@ pub enum Commands {
         #[arg(long)]
         skip_existing: bool,
 
-        /// Truncate target before writing (overrides append-first behavior)
+        /// Truncate target before writing, same as --if-exists truncate
         #[arg(long)]
         truncate: bool,
 
@@ -140,6 +574,303 @@ pub enum Commands {
         /// Secret ID for destination password (resolves to TINYETL_SECRET_{id})
         #[arg(long, value_name = "ID")]
         dest_secret_id: Option<String>,
+
+        /// Compression level for compressed outputs (Parquet, .gz and .zst files)
+        #[arg(long, value_name = "LEVEL", allow_negative_numbers = true)]
+        compression_level: Option<i32>,
+
+        /// How long a SQLite target waits for other writers, in milliseconds
+        #[arg(long, value_name = "MS")]
+        sqlite_busy_timeout: Option<u64>,
+
+        /// Hold a lock file while loading a SQLite target
+        #[arg(long)]
+        sqlite_lock: bool,
+
+        /// Create a new PostgreSQL target table as UNLOGGED until the load finishes
+        #[arg(long)]
+        pg_unlogged: bool,
+
+        /// Load a new or truncated PostgreSQL table with COPY FREEZE
+        #[arg(long)]
+        pg_copy_freeze: bool,
+
+        /// Write to PostgreSQL with COPY instead of multi-row INSERTs
+        #[arg(long)]
+        pg_copy: bool,
+
+        /// Turn off foreign key checks on database targets while loading
+        #[arg(long)]
+        disable_foreign_keys: bool,
+
+        /// Character set of tables created on a MySQL target
+        #[arg(long, value_name = "CHARSET")]
+        target_charset: Option<String>,
+
+        /// Collation of tables created on a MySQL target, or of text columns on SQL Server
+        #[arg(long, value_name = "COLLATION")]
+        target_collation: Option<String>,
+
+        /// Encoding of binary values written to CSV and JSON targets: base64 (default) or hex
+        #[arg(long, value_name = "ENCODING")]
+        binary_encoding: Option<BinaryEncoding>,
+
+        /// Skip N lines before the header row of a CSV source
+        #[arg(long, value_name = "N")]
+        skip_rows: Option<usize>,
+
+        /// Drop the last N records of a CSV source
+        #[arg(long, value_name = "N")]
+        skip_footer: Option<usize>,
+
+        /// Field delimiter of a CSV source (one or more characters, or regex:<PATTERN>)
+        #[arg(long, value_name = "DELIMITER")]
+        csv_delimiter: Option<String>,
+
+        /// Compiled descriptor set (protoc --descriptor_set_out) of protobuf (.pb) sources and targets
+        #[arg(long, value_name = "FILE")]
+        proto_descriptor: Option<String>,
+
+        /// Read coded values of SPSS and Stata sources as their value labels
+        #[arg(long)]
+        value_labels: bool,
+
+        /// Table of an HTML or PDF source to read: 1 for the first on the page (or matching --table-selector)
+        #[arg(long, value_name = "N")]
+        table_index: Option<usize>,
+
+        /// CSS selector of the table of an HTML source, or of an element holding it
+        #[arg(long, value_name = "SELECTOR")]
+        table_selector: Option<String>,
+
+        /// Pages of a PDF source to look for tables on (e.g. '2-4,7')
+        #[arg(long, value_name = "RANGE")]
+        pages: Option<String>,
+
+        /// Write a JSON report with transfer statistics to this file
+        #[arg(long, value_name = "FILE")]
+        report_file: Option<String>,
+
+        /// Allow Lua transforms to use the io, os and module loading libraries (sandboxed by default)
+        #[arg(long)]
+        lua_allow_io: bool,
+
+        /// Maximum time in milliseconds a Lua transform may spend on a single row
+        #[arg(long, value_name = "MS")]
+        lua_timeout_ms: Option<u64>,
+
+        /// Directory searched by require() in Lua transforms (repeatable)
+        #[arg(long, value_name = "DIR")]
+        lua_path: Vec<String>,
+
+        /// Load the target as a Slowly Changing Dimension Type 2 table (requires --key)
+        #[arg(long)]
+        scd2: bool,
+
+        /// Business key columns identifying each dimension member in SCD2 mode, or each document a JSON target is merged on otherwise (comma-separated)
+        #[arg(long = "key", value_name = "COLUMNS", value_delimiter = ',')]
+        key_columns: Vec<String>,
+
+        /// Columns whose changes create a new version in SCD2 mode (comma-separated, default: all non-key columns)
+        #[arg(long = "track", value_name = "COLUMNS", value_delimiter = ',')]
+        track_columns: Vec<String>,
+
+        /// Add a row number column, e.g. "rn partition-by customer_id order-by ts" (input must be sorted by the order-by column within each partition)
+        #[arg(long, value_name = "SPEC")]
+        row_number: Option<RowNumberConfig>,
+
+        /// Route matching rows to another target: "<TARGET> if <LUA EXPRESSION>" or "<TARGET> otherwise" (repeatable; first match wins, unmatched rows go to the main target)
+        #[arg(long = "route", value_name = "RULE")]
+        routes: Vec<RouteConfig>,
+
+        /// Read the result of this SQL query from a database source instead of a whole table; use :name placeholders for --param values
+        #[arg(long, value_name = "SQL")]
+        query: Option<String>,
+
+        /// Bind a value to a :name placeholder in --query, e.g. since:timestamp=2024-01-01; a :int, :decimal, :bool or :timestamp suffix sets its type, otherwise it is text (repeatable)
+        #[arg(long = "param", value_name = "NAME=VALUE", value_parser = parse_key_value)]
+        query_params: Vec<(String, String)>,
+
+        /// Copy every table of a database source, in foreign key order
+        #[arg(long)]
+        all_tables: bool,
+
+        /// Guarantee no writes against the source: reject data-modifying --query SQL and targets in the source database
+        #[arg(long)]
+        read_only: bool,
+
+        /// Regex marking production-looking target URLs, which need --confirm-production in read-only mode [default: prod, production or prd as a separate word]
+        #[arg(long, value_name = "REGEX")]
+        production_pattern: Option<String>,
+
+        /// Confirm writing to a production-looking target in read-only mode
+        #[arg(long)]
+        confirm_production: bool,
+
+        /// Don't ask for confirmation before truncating database targets
+        #[arg(long, short = 'y')]
+        yes: bool,
+
+        /// Tables that must never be truncated (comma-separated); runs that would truncate them fail
+        #[arg(long, value_name = "TABLES", value_delimiter = ',')]
+        protect: Vec<String>,
+
+        /// Also mask what this regular expression matches in logs and errors, or only its first group if it has one (repeatable; passwords and secrets are always masked)
+        #[arg(long, value_name = "REGEX")]
+        redact: Vec<String>,
+
+        /// Stop after N rows have been written to the target (unlike --preview, rows are loaded)
+        #[arg(long, value_name = "N")]
+        limit: Option<usize>,
+
+        /// Keep state between runs in this JSON file (the source schema for drift detection, the files read by --new-files-only and the --incremental-column watermark)
+        #[arg(long, value_name = "FILE")]
+        state_file: Option<String>,
+
+        /// What to do when the source schema changed since the last run recorded in --state-file: warn, fail
+        #[arg(long, value_name = "POLICY", default_value = "warn")]
+        on_schema_drift: SchemaDriftPolicy,
+
+        /// Schema source: inferred (sample the source) or declared (use --schema-file or YAML columns verbatim, converting values strictly)
+        #[arg(long, value_name = "MODE", default_value = "inferred")]
+        schema_mode: SchemaMode,
+
+        /// What to do with rows that fail schema validation or conversion: fail, skip
+        #[arg(long, value_name = "POLICY", default_value = "fail")]
+        on_row_error: RowErrorPolicy,
+
+        /// Refresh the target table's planner statistics after loading (ANALYZE, OPTIMIZE TABLE or UPDATE STATISTICS)
+        #[arg(long)]
+        analyze: bool,
+
+        /// Columns to leave out of the target (comma-separated), e.g. identity columns the target generates
+        #[arg(long, value_name = "COLUMNS", value_delimiter = ',')]
+        exclude_columns: Vec<String>,
+
+        /// Largest allowed size of a single field value, in bytes (see --on-oversize)
+        #[arg(long, value_name = "BYTES")]
+        max_field_length: Option<usize>,
+
+        /// Largest allowed approximate size of a whole row, in bytes (see --on-oversize)
+        #[arg(long, value_name = "BYTES")]
+        max_row_bytes: Option<usize>,
+
+        /// What to do with rows over --max-field-length or --max-row-bytes: error, truncate, deadletter
+        #[arg(long, value_name = "POLICY", default_value = "error")]
+        on_oversize: OversizePolicy,
+
+        /// JSON Lines file receiving rows rejected by --on-oversize deadletter or --bisect-failed-batches
+        #[arg(long, value_name = "FILE")]
+        dead_letter_file: Option<String>,
+
+        /// When the target rejects a batch, retry it in halves to isolate the failing rows and skip only those (written to --dead-letter-file when set)
+        #[arg(long)]
+        bisect_failed_batches: bool,
+
+        /// Log every statement database targets execute, with parameter values summarized and cut short
+        #[arg(long)]
+        echo_sql: bool,
+
+        /// Fail a source read that takes longer than SECONDS; database sources also have the server stop the query
+        #[arg(long, value_name = "SECONDS")]
+        batch_timeout: Option<u64>,
+
+        /// Number of Lua transform workers; above 1 the source is also read ahead while batches are written, in order
+        #[arg(long, value_name = "N", default_value = "1")]
+        parallelism: usize,
+
+        /// Read all batches from one point-in-time snapshot of the source (PostgreSQL, MySQL and SQL Server)
+        #[arg(long)]
+        consistent_snapshot: bool,
+
+        /// Rows PostgreSQL and MySQL sources pull from the server at a time (defaults to --batch-size)
+        #[arg(long, value_name = "ROWS")]
+        fetch_size: Option<usize>,
+
+        /// Unique column without NULLs to page SQL Server sources on, for tables without a primary key or --query results
+        #[arg(long, value_name = "COLUMN")]
+        keyset_column: Option<String>,
+
+        /// Attach another SQLite database file under an alias for --query to join against, e.g. ref=./ref.db (repeatable)
+        #[arg(long, value_name = "ALIAS=PATH", value_parser = parse_key_value)]
+        attach: Vec<(String, String)>,
+
+        /// What to do when the target table or file exists: fail, append, truncate, replace
+        #[arg(
+            long,
+            value_name = "POLICY",
+            default_value = "append",
+            conflicts_with = "truncate"
+        )]
+        if_exists: IfExists,
+
+        /// Write file targets as a directory of partitions, one per value of these columns (comma-separated)
+        #[arg(long, value_name = "COLUMNS", value_delimiter = ',')]
+        partition_by: Vec<String>,
+
+        /// Replace the partitions present in the incoming data instead of adding files to them (requires --partition-by)
+        #[arg(long, requires = "partition_by")]
+        overwrite_partitions: bool,
+
+        /// Read only the files of a source folder that are new or changed since the last run recorded in --state-file
+        #[arg(long, requires = "state_file")]
+        new_files_only: bool,
+
+        /// Read only rows whose COLUMN is greater than the highest value loaded by the last run recorded in --state-file (SQL sources)
+        #[arg(long, value_name = "COLUMN", requires = "state_file")]
+        incremental_column: Option<String>,
+
+        /// Read a PostgreSQL, MySQL or MSSQL source as --split-count ranges of this numeric or date COLUMN, concurrently; rows arrive in no particular order
+        #[arg(long, value_name = "COLUMN")]
+        split_column: Option<String>,
+
+        /// Number of ranges --split-column reads at once
+        #[arg(long, value_name = "N", default_value = "4", requires = "split_column")]
+        split_count: usize,
+
+        /// Save the progress of the transfer to this JSON file after every batch, so an interrupted run can be resumed
+        #[arg(long, value_name = "FILE")]
+        checkpoint_file: Option<String>,
+
+        /// Continue the interrupted run recorded in --checkpoint-file instead of starting over
+        #[arg(long, requires = "checkpoint_file")]
+        resume: bool,
+
+        /// Directory for temporary files such as downloads (default: the system temp directory); removed after a successful run, kept after a failure
+        #[arg(long, value_name = "DIR")]
+        staging_dir: Option<String>,
+
+        /// Fail the run when its temporary files would take more than BYTES in the staging directory
+        #[arg(long, value_name = "BYTES")]
+        staging_max_bytes: Option<u64>,
+
+        /// Write this instead of NULL into a column that doesn't allow NULLs: empty, zero or value:<TEXT>, as COLUMN=POLICY or a bare POLICY for every column declared nullable: false (repeatable)
+        #[arg(long, value_name = "[COLUMN=]POLICY", value_parser = parse_column_fallback)]
+        null_fallback: Vec<(String, NullFallback)>,
+
+        /// Exact order of the output columns (comma-separated); unlisted columns follow in source order
+        #[arg(long, value_name = "COLUMNS", value_delimiter = ',')]
+        column_order: Vec<String>,
+
+        /// Casing of the column names written to the target: snake, camel, upper (UPPER_SNAKE), preserve
+        #[arg(long, value_name = "CASE", default_value = "preserve")]
+        output_header_case: HeaderCase,
+
+        /// Write a column to the target under another name, e.g. cust_id=CUSTOMER_ID (repeatable; overrides --output-header-case)
+        #[arg(long = "rename-column", value_name = "SOURCE=TARGET", value_parser = parse_key_value)]
+        rename_columns: Vec<(String, String)>,
+
+        /// Fail on any lossy conversion (rounded floats, unreadable values, values that don't fit their column) instead of coercing it silently
+        #[arg(long)]
+        strict: bool,
+
+        /// Decode a column on read and encode it again on write, e.g. payload=base64 or id=hex-uuid (repeatable; codecs: base64, hex, hex-uuid)
+        #[arg(long = "codec", value_name = "COLUMN=CODEC", value_parser = parse_key_value)]
+        codecs: Vec<(String, String)>,
+
+        /// Add a column holding each row's SHA-256 content hash and report the run's Merkle root hash, so the export can later be verified as unmodified
+        #[arg(long, value_name = "COLUMN")]
+        row_hash: Option<String>,
     },
 }
 
@@ -173,6 +904,108 @@ impl Cli {
     }
 }
 
+/// Build target options from CLI flags that map onto connector-specific options
+#[allow(clippy::too_many_arguments)]
+pub fn target_options_from_flags(
+    compression_level: Option<i32>,
+    sqlite_busy_timeout: Option<u64>,
+    sqlite_lock: bool,
+    pg_unlogged: bool,
+    pg_copy_freeze: bool,
+    pg_copy: bool,
+    disable_foreign_keys: bool,
+    target_charset: Option<String>,
+    target_collation: Option<String>,
+    binary_encoding: Option<BinaryEncoding>,
+    proto_descriptor: Option<String>,
+) -> HashMap<String, String> {
+    let mut options = HashMap::new();
+    if let Some(level) = compression_level {
+        options.insert("compression.level".to_string(), level.to_string());
+    }
+    if let Some(ms) = sqlite_busy_timeout {
+        options.insert("busy_timeout".to_string(), ms.to_string());
+    }
+    if sqlite_lock {
+        options.insert("lock".to_string(), "true".to_string());
+    }
+    if pg_unlogged {
+        options.insert("unlogged".to_string(), "true".to_string());
+    }
+    if pg_copy_freeze {
+        options.insert("copy_freeze".to_string(), "true".to_string());
+    }
+    if pg_copy {
+        options.insert("copy".to_string(), "true".to_string());
+    }
+    if disable_foreign_keys {
+        options.insert("foreign_keys".to_string(), "false".to_string());
+    }
+    if let Some(charset) = target_charset {
+        options.insert("charset".to_string(), charset);
+    }
+    if let Some(collation) = target_collation {
+        options.insert("collation".to_string(), collation);
+    }
+    if let Some(encoding) = binary_encoding {
+        options.insert("binary_encoding".to_string(), encoding.to_string());
+    }
+    if let Some(descriptor) = proto_descriptor {
+        options.insert("descriptor".to_string(), descriptor);
+    }
+    options
+}
+
+/// Build source options from CLI flags that map onto connector-specific options
+#[allow(clippy::too_many_arguments)]
+pub fn source_options_from_flags(
+    skip_rows: Option<usize>,
+    skip_footer: Option<usize>,
+    csv_delimiter: Option<String>,
+    proto_descriptor: Option<String>,
+    value_labels: bool,
+    table_index: Option<usize>,
+    table_selector: Option<String>,
+    pages: Option<String>,
+) -> HashMap<String, String> {
+    let mut options = HashMap::new();
+    if let Some(delimiter) = csv_delimiter {
+        options.insert("delimiter".to_string(), delimiter);
+    }
+    if let Some(rows) = skip_rows {
+        options.insert("skip_rows".to_string(), rows.to_string());
+    }
+    if let Some(rows) = skip_footer {
+        options.insert("skip_footer".to_string(), rows.to_string());
+    }
+    if let Some(descriptor) = proto_descriptor {
+        options.insert("descriptor".to_string(), descriptor);
+    }
+    if value_labels {
+        options.insert("value_labels".to_string(), "true".to_string());
+    }
+    if let Some(index) = table_index {
+        options.insert("table_index".to_string(), index.to_string());
+    }
+    if let Some(selector) = table_selector {
+        options.insert("table_selector".to_string(), selector);
+    }
+    if let Some(pages) = pages {
+        options.insert("pages".to_string(), pages);
+    }
+    options
+}
+
+/// Parse a `NAME=VALUE` pair
+fn parse_key_value(s: &str) -> Result<(String, String), String> {
+    match s.split_once('=') {
+        Some((name, value)) if !name.trim().is_empty() => {
+            Ok((name.trim().to_string(), value.to_string()))
+        }
+        _ => Err(format!("expected NAME=VALUE, got '{}'", s)),
+    }
+}
+
 impl From<Cli> for Config {
     fn from(cli: Cli) -> Self {
         // Ensure we have both source and target for direct CLI usage
@@ -205,8 +1038,90 @@ impl From<Cli> for Config {
             source_type: cli.source_type,
             source_secret_id: cli.source_secret_id,
             dest_secret_id: cli.dest_secret_id,
-            source_options: std::collections::HashMap::new(), // CLI doesn't support options yet
-            target_options: std::collections::HashMap::new(), // CLI doesn't support options yet
+            report_file: cli.report_file,
+            lua_allow_io: cli.lua_allow_io,
+            lua_timeout_ms: cli.lua_timeout_ms,
+            lua_path: cli.lua_path,
+            scd2: cli.scd2,
+            key_columns: cli.key_columns,
+            track_columns: cli.track_columns,
+            pivot: None,
+            unpivot: None,
+            row_number: cli.row_number,
+            join: None,
+            routes: cli.routes,
+            query: cli.query,
+            query_params: cli.query_params.into_iter().collect(),
+            all_tables: cli.all_tables,
+            read_only: cli.read_only,
+            production_pattern: cli.production_pattern,
+            confirm_production: cli.confirm_production,
+            yes: cli.yes,
+            protect: cli.protect,
+            redact: cli.redact,
+            limit: cli.limit,
+            state_file: cli.state_file,
+            on_schema_drift: cli.on_schema_drift,
+            schema_mode: cli.schema_mode,
+            columns: Vec::new(),
+            on_row_error: cli.on_row_error,
+            analyze: cli.analyze,
+            exclude_columns: cli.exclude_columns,
+            max_field_length: cli.max_field_length,
+            max_row_bytes: cli.max_row_bytes,
+            on_oversize: cli.on_oversize,
+            dead_letter_file: cli.dead_letter_file,
+            bisect_failed_batches: cli.bisect_failed_batches,
+            echo_sql: cli.echo_sql,
+            batch_timeout: cli.batch_timeout,
+            parallelism: cli.parallelism,
+            consistent_snapshot: cli.consistent_snapshot,
+            fetch_size: cli.fetch_size,
+            keyset_column: cli.keyset_column,
+            attach: cli.attach.into_iter().collect(),
+            if_exists: cli.if_exists,
+            partition_by: cli.partition_by,
+            overwrite_partitions: cli.overwrite_partitions,
+            new_files_only: cli.new_files_only,
+            incremental_column: cli.incremental_column,
+            split_column: cli.split_column,
+            split_count: cli.split_count,
+            checkpoint_file: cli.checkpoint_file,
+            resume: cli.resume,
+            staging_dir: cli.staging_dir,
+            staging_max_bytes: cli.staging_max_bytes,
+            null_fallback: cli.null_fallback.into_iter().collect(),
+            column_order: cli.column_order,
+            output_header_case: cli.output_header_case,
+            rename_columns: cli.rename_columns.into_iter().collect(),
+            strict: cli.strict,
+            codecs: cli.codecs.into_iter().collect(),
+            row_hash: cli.row_hash,
+            job_name: None,
+            audit_table: None,
+            source_options: source_options_from_flags(
+                cli.skip_rows,
+                cli.skip_footer,
+                cli.csv_delimiter,
+                cli.proto_descriptor.clone(),
+                cli.value_labels,
+                cli.table_index,
+                cli.table_selector,
+                cli.pages,
+            ),
+            target_options: target_options_from_flags(
+                cli.compression_level,
+                cli.sqlite_busy_timeout,
+                cli.sqlite_lock,
+                cli.pg_unlogged,
+                cli.pg_copy_freeze,
+                cli.pg_copy,
+                cli.disable_foreign_keys,
+                cli.target_charset,
+                cli.target_collation,
+                cli.binary_encoding,
+                cli.proto_descriptor,
+            ),
         }
     }
 }
@@ -218,7 +1133,7 @@ mod tests {
 
     #[test]
     fn test_basic_cli_parsing() {
-        let cli = Cli::try_parse_from(&["tinyetl", "source.csv", "target.db#table"]).unwrap();
+        let cli = Cli::try_parse_from(["tinyetl", "source.csv", "target.db#table"]).unwrap();
 
         assert_eq!(cli.source, Some("source.csv".to_string()));
         assert_eq!(cli.target, Some("target.db#table".to_string()));
@@ -232,7 +1147,7 @@ mod tests {
 
     #[test]
     fn test_config_file_parsing() {
-        let cli = Cli::try_parse_from(&["tinyetl", "run", "my_job.yaml"]).unwrap();
+        let cli = Cli::try_parse_from(["tinyetl", "run", "my_job.yaml"]).unwrap();
 
         assert!(cli.is_config_mode());
         assert_eq!(cli.get_config_file(), Some("my_job.yaml"));
@@ -310,7 +1225,6 @@ mod tests {
         assert!(result.is_err());
     }
 
-
     #[test]
     fn test_http_source_with_type() {
         let cli = Cli::try_parse_from([
@@ -332,11 +1246,199 @@ mod tests {
 
     #[test]
     fn test_generate_default_config_parsing() {
-        let cli = Cli::try_parse_from(&["tinyetl", "generate-default-config"]).unwrap();
+        let cli = Cli::try_parse_from(["tinyetl", "generate-default-config"]).unwrap();
 
         assert!(cli.is_generate_default_config_mode());
         assert!(!cli.is_config_mode());
         assert!(!cli.is_generate_config_mode());
         assert!(!cli.has_direct_params());
     }
+
+    #[test]
+    fn test_scaffold_schema_parsing() {
+        let cli = Cli::try_parse_from([
+            "tinyetl",
+            "scaffold-schema",
+            "orders.csv",
+            "-o",
+            "schema.yaml",
+        ])
+        .unwrap();
+
+        assert!(!cli.has_direct_params());
+        match cli.command {
+            Some(Commands::ScaffoldSchema {
+                source,
+                output,
+                sample_size,
+                ..
+            }) => {
+                assert_eq!(source, "orders.csv");
+                assert_eq!(output, Some("schema.yaml".to_string()));
+                assert_eq!(sample_size, 1000);
+            }
+            _ => panic!("expected scaffold-schema"),
+        }
+    }
+
+    #[test]
+    fn test_schema_parsing() {
+        let cli = Cli::try_parse_from([
+            "tinyetl",
+            "schema",
+            "orders.csv",
+            "--format",
+            "sql:postgres",
+        ])
+        .unwrap();
+
+        match cli.command {
+            Some(Commands::Schema {
+                source,
+                format,
+                name,
+                ..
+            }) => {
+                assert_eq!(source, "orders.csv");
+                assert_eq!(
+                    format,
+                    SchemaFormat::Sql(crate::schema_export::SqlDialect::Postgres)
+                );
+                assert_eq!(name, None);
+            }
+            _ => panic!("expected schema"),
+        }
+        assert!(Cli::try_parse_from(["tinyetl", "schema", "orders.csv", "-f", "xml"]).is_err());
+    }
+
+    #[test]
+    fn test_compression_level_maps_to_target_options() {
+        let cli = Cli::try_parse_from([
+            "tinyetl",
+            "input.csv",
+            "output.parquet",
+            "--compression-level",
+            "19",
+        ])
+        .unwrap();
+
+        assert_eq!(cli.compression_level, Some(19));
+        let config: Config = cli.into();
+        assert_eq!(
+            config.target_options.get("compression.level"),
+            Some(&"19".to_string())
+        );
+    }
+
+    #[test]
+    fn test_sqlite_flags_map_to_target_options() {
+        let cli = Cli::try_parse_from([
+            "tinyetl",
+            "input.csv",
+            "shared.db#events",
+            "--sqlite-busy-timeout",
+            "30000",
+            "--sqlite-lock",
+        ])
+        .unwrap();
+
+        let config: Config = cli.into();
+        assert_eq!(
+            config.target_options.get("busy_timeout"),
+            Some(&"30000".to_string())
+        );
+        assert_eq!(config.target_options.get("lock"), Some(&"true".to_string()));
+    }
+
+    #[test]
+    fn test_skip_flags_map_to_source_options() {
+        let cli = Cli::try_parse_from([
+            "tinyetl",
+            "vendor.csv",
+            "output.parquet",
+            "--skip-rows",
+            "3",
+            "--skip-footer",
+            "1",
+        ])
+        .unwrap();
+
+        let config: Config = cli.into();
+        assert_eq!(
+            config.source_options.get("skip_rows"),
+            Some(&"3".to_string())
+        );
+        assert_eq!(
+            config.source_options.get("skip_footer"),
+            Some(&"1".to_string())
+        );
+    }
+
+    #[test]
+    fn test_html_table_flags_map_to_source_options() {
+        let cli = Cli::try_parse_from([
+            "tinyetl",
+            "https://stats.example.gov/population.html",
+            "population.csv",
+            "--table-index",
+            "2",
+            "--table-selector",
+            "#regions",
+        ])
+        .unwrap();
+
+        let config: Config = cli.into();
+        assert_eq!(
+            config.source_options.get("table_index"),
+            Some(&"2".to_string())
+        );
+        assert_eq!(
+            config.source_options.get("table_selector"),
+            Some(&"#regions".to_string())
+        );
+    }
+
+    #[test]
+    fn test_pdf_pages_flag_maps_to_source_options() {
+        let cli = Cli::try_parse_from([
+            "tinyetl",
+            "report.pdf",
+            "sales.csv",
+            "--pages",
+            "2-4",
+            "--table-index",
+            "1",
+        ])
+        .unwrap();
+
+        let config: Config = cli.into();
+        assert_eq!(config.source_options.get("pages"), Some(&"2-4".to_string()));
+        assert_eq!(
+            config.source_options.get("table_index"),
+            Some(&"1".to_string())
+        );
+    }
+
+    #[test]
+    fn test_query_with_params() {
+        let cli = Cli::try_parse_from([
+            "tinyetl",
+            "postgres://localhost/app",
+            "changes.csv",
+            "--query",
+            "SELECT * FROM t WHERE updated_at > :since AND region = :region",
+            "--param",
+            "since=2024-01-01",
+            "--param",
+            "region=EU=West",
+        ])
+        .unwrap();
+
+        let config: Config = cli.into();
+        assert!(config.query.is_some());
+        assert_eq!(config.query_params.get("since").unwrap(), "2024-01-01");
+        assert_eq!(config.query_params.get("region").unwrap(), "EU=West");
+
+        assert!(Cli::try_parse_from(["tinyetl", "a.db", "b.csv", "--param", "since"]).is_err());
+    }
 }
@@ -0,0 +1,275 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, OnceLock, RwLock};
+
+use crate::{
+    schema::{BinaryEncoding, Row, Schema, Value},
+    Result, TinyEtlError,
+};
+
+/// Codecs available to `codecs:`, set up with the built-in ones on first use
+static REGISTRY: OnceLock<RwLock<CodecRegistry>> = OnceLock::new();
+
+/// Converts the values of a column between the encoding they are stored in
+/// and the value the rest of the pipeline works with. NULLs are passed
+/// through without calling the codec; errors describe the offending value.
+pub trait ValueCodec: Send + Sync {
+    /// Value read from the source
+    fn decode(&self, value: Value) -> std::result::Result<Value, String>;
+
+    /// Value written to the target
+    fn encode(&self, value: Value) -> std::result::Result<Value, String>;
+}
+
+/// Text holding binary data: `base64` or `hex`. Decodes to bytes and
+/// encodes bytes, or text as its UTF-8 bytes, back to text.
+struct BinaryCodec(BinaryEncoding);
+
+impl ValueCodec for BinaryCodec {
+    fn decode(&self, value: Value) -> std::result::Result<Value, String> {
+        match value {
+            Value::String(s) => self
+                .0
+                .decode(s.trim())
+                .map(Value::Bytes)
+                .ok_or_else(|| format!("'{}' is not valid {}", s, self.0)),
+            Value::Bytes(_) => Ok(value),
+            other => Err(format!("{:?} is not {} text", other, self.0)),
+        }
+    }
+
+    fn encode(&self, value: Value) -> std::result::Result<Value, String> {
+        match value {
+            Value::Bytes(bytes) => Ok(Value::String(self.0.encode(&bytes))),
+            Value::String(s) => Ok(Value::String(self.0.encode(s.as_bytes()))),
+            other => Err(format!("{:?} can't be encoded as {}", other, self.0)),
+        }
+    }
+}
+
+/// UUIDs stored as 32 hex digits (or 16 bytes). Decodes to the hyphenated
+/// form and encodes back to bare hex digits.
+struct HexUuidCodec;
+
+impl ValueCodec for HexUuidCodec {
+    fn decode(&self, value: Value) -> std::result::Result<Value, String> {
+        let uuid = match &value {
+            Value::String(s) => uuid::Uuid::parse_str(s.trim()).ok(),
+            Value::Bytes(bytes) => uuid::Uuid::from_slice(bytes).ok(),
+            _ => None,
+        };
+        uuid.map(|uuid| Value::String(uuid.hyphenated().to_string()))
+            .ok_or_else(|| format!("{:?} is not a UUID", value))
+    }
+
+    fn encode(&self, value: Value) -> std::result::Result<Value, String> {
+        match &value {
+            Value::String(s) => uuid::Uuid::parse_str(s.trim())
+                .map(|uuid| Value::String(uuid.simple().to_string()))
+                .map_err(|_| format!("'{}' is not a UUID", s)),
+            _ => Err(format!("{:?} is not a UUID", value)),
+        }
+    }
+}
+
+/// Codecs by name. Starts with `base64`, `hex` and `hex-uuid`; programs
+/// embedding TinyETL add their own with `register_codec`.
+pub struct CodecRegistry {
+    codecs: BTreeMap<String, Arc<dyn ValueCodec>>,
+}
+
+impl Default for CodecRegistry {
+    fn default() -> Self {
+        let mut registry = Self {
+            codecs: BTreeMap::new(),
+        };
+        registry.register("base64", Arc::new(BinaryCodec(BinaryEncoding::Base64)));
+        registry.register("hex", Arc::new(BinaryCodec(BinaryEncoding::Hex)));
+        registry.register("hex-uuid", Arc::new(HexUuidCodec));
+        registry
+    }
+}
+
+impl CodecRegistry {
+    /// Add a codec, replacing any codec of the same name
+    pub fn register(&mut self, name: &str, codec: Arc<dyn ValueCodec>) {
+        self.codecs.insert(name.to_lowercase(), codec);
+    }
+
+    pub fn get(&self, name: &str) -> Option<Arc<dyn ValueCodec>> {
+        self.codecs.get(&name.to_lowercase()).cloned()
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.codecs.keys().map(String::as_str).collect()
+    }
+}
+
+fn registry() -> &'static RwLock<CodecRegistry> {
+    REGISTRY.get_or_init(|| RwLock::new(CodecRegistry::default()))
+}
+
+/// Make a codec available to `codecs:` under `name` for the rest of the process
+pub fn register_codec(name: &str, codec: Arc<dyn ValueCodec>) {
+    registry()
+        .write()
+        .unwrap_or_else(|e| e.into_inner())
+        .register(name, codec);
+}
+
+/// The codecs of the columns given `codecs:`, decoding the values read from
+/// the source and encoding the values written to the target
+pub struct ColumnCodecs {
+    columns: Vec<(String, String, Arc<dyn ValueCodec>)>,
+}
+
+impl ColumnCodecs {
+    /// Look up the codec of each column; `None` when there are none. The
+    /// columns must be in the source `schema`.
+    pub fn new(codecs: &BTreeMap<String, String>, schema: &Schema) -> Result<Option<Self>> {
+        let registry = registry().read().unwrap_or_else(|e| e.into_inner());
+        let mut columns = Vec::new();
+        for (column, name) in codecs {
+            if !schema.columns.iter().any(|c| &c.name == column) {
+                return Err(TinyEtlError::Configuration(format!(
+                    "Codec column '{}' is not in the source schema",
+                    column
+                )));
+            }
+            let codec = registry.get(name).ok_or_else(|| {
+                TinyEtlError::Configuration(format!(
+                    "Unknown codec '{}' for column '{}'. Valid values: {}",
+                    name,
+                    column,
+                    registry.names().join(", ")
+                ))
+            })?;
+            columns.push((column.clone(), name.clone(), codec));
+        }
+        Ok((!columns.is_empty()).then_some(Self { columns }))
+    }
+
+    pub fn decode_row(&self, row: &mut Row) -> Result<()> {
+        self.convert_row(row, "decoded", |codec, value| codec.decode(value))
+    }
+
+    pub fn encode_row(&self, row: &mut Row) -> Result<()> {
+        self.convert_row(row, "encoded", |codec, value| codec.encode(value))
+    }
+
+    fn convert_row(
+        &self,
+        row: &mut Row,
+        action: &str,
+        convert: impl Fn(&dyn ValueCodec, Value) -> std::result::Result<Value, String>,
+    ) -> Result<()> {
+        for (column, name, codec) in &self.columns {
+            let Some(value) = row.get_mut(column) else {
+                continue;
+            };
+            if matches!(value, Value::Null) {
+                continue;
+            }
+            let converted = convert(codec.as_ref(), std::mem::replace(value, Value::Null))
+                .map_err(|e| {
+                    TinyEtlError::DataValidation(format!(
+                        "Column '{}' can't be {} as {}: {}",
+                        column, action, name, e
+                    ))
+                })?;
+            *value = converted;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::{Column, DataType};
+
+    fn schema(names: &[&str]) -> Schema {
+        Schema {
+            columns: names
+                .iter()
+                .map(|name| Column {
+                    name: name.to_string(),
+                    data_type: DataType::String,
+                    nullable: true,
+                })
+                .collect(),
+            estimated_rows: None,
+            primary_key_candidate: None,
+        }
+    }
+
+    #[test]
+    fn test_builtin_codecs_round_trip() {
+        let codecs = BTreeMap::from([
+            ("payload".to_string(), "base64".to_string()),
+            ("id".to_string(), "hex-uuid".to_string()),
+        ]);
+        let codecs = ColumnCodecs::new(&codecs, &schema(&["id", "payload"]))
+            .unwrap()
+            .unwrap();
+
+        let mut row = Row::from([
+            (
+                "id".to_string(),
+                Value::String("550E8400E29B41D4A716446655440000".to_string()),
+            ),
+            ("payload".to_string(), Value::String("CgNhYmM=".to_string())),
+        ]);
+        codecs.decode_row(&mut row).unwrap();
+        assert_eq!(
+            row["id"],
+            Value::String("550e8400-e29b-41d4-a716-446655440000".to_string())
+        );
+        assert_eq!(row["payload"], Value::Bytes(b"\n\x03abc".to_vec()));
+
+        codecs.encode_row(&mut row).unwrap();
+        assert_eq!(
+            row["id"],
+            Value::String("550e8400e29b41d4a716446655440000".to_string())
+        );
+        assert_eq!(row["payload"], Value::String("CgNhYmM=".to_string()));
+
+        let mut bad = Row::from([("payload".to_string(), Value::String("%%".to_string()))]);
+        let err = codecs.decode_row(&mut bad).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("Column 'payload' can't be decoded as base64"));
+    }
+
+    #[test]
+    fn test_registered_codecs_and_unknown_names() {
+        struct Upper;
+        impl ValueCodec for Upper {
+            fn decode(&self, value: Value) -> std::result::Result<Value, String> {
+                Ok(value)
+            }
+            fn encode(&self, value: Value) -> std::result::Result<Value, String> {
+                match value {
+                    Value::String(s) => Ok(Value::String(s.to_uppercase())),
+                    other => Ok(other),
+                }
+            }
+        }
+        register_codec("test-upper", Arc::new(Upper));
+
+        let codecs = BTreeMap::from([("code".to_string(), "test-upper".to_string())]);
+        let codecs = ColumnCodecs::new(&codecs, &schema(&["code"]))
+            .unwrap()
+            .unwrap();
+        let mut row = Row::from([("code".to_string(), Value::String("ab".to_string()))]);
+        codecs.encode_row(&mut row).unwrap();
+        assert_eq!(row["code"], Value::String("AB".to_string()));
+
+        let unknown = BTreeMap::from([("code".to_string(), "rot13".to_string())]);
+        let err = ColumnCodecs::new(&unknown, &schema(&["code"]))
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("Unknown codec 'rot13'"));
+        let missing = BTreeMap::from([("other".to_string(), "hex".to_string())]);
+        assert!(ColumnCodecs::new(&missing, &schema(&["code"])).is_err());
+    }
+}
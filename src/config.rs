@@ -1,6 +1,14 @@
+use crate::join::JoinConfig;
+use crate::null_fallback::NullFallback;
+use crate::output_names::HeaderCase;
+use crate::reshape::{PivotConfig, UnpivotConfig};
+use crate::router::RouteConfig;
+use crate::row_number::RowNumberConfig;
+use crate::schema::{SchemaFileColumn, SchemaMode};
+use crate::state::SchemaDriftPolicy;
 use crate::transformer::TransformConfig;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
@@ -18,10 +26,91 @@ pub struct Config {
     pub source_type: Option<String>,
     pub source_secret_id: Option<String>,
     pub dest_secret_id: Option<String>,
+    pub report_file: Option<String>,
+    pub lua_allow_io: bool,
+    pub lua_timeout_ms: Option<u64>,
+    pub lua_path: Vec<String>,
+    pub scd2: bool,
+    pub key_columns: Vec<String>,
+    pub track_columns: Vec<String>,
+    pub pivot: Option<PivotConfig>,
+    pub unpivot: Option<UnpivotConfig>,
+    pub row_number: Option<RowNumberConfig>,
+    pub join: Option<JoinConfig>,
+    pub routes: Vec<RouteConfig>,
+    pub query: Option<String>,
+    pub query_params: HashMap<String, String>,
+    /// Copy every table of a database source, in foreign key order
+    pub all_tables: bool,
+    pub read_only: bool,
+    pub production_pattern: Option<String>,
+    pub confirm_production: bool,
+    pub yes: bool,
+    pub protect: Vec<String>,
+    /// Patterns masked in logs and errors, on top of passwords and secrets
+    pub redact: Vec<String>,
+    pub limit: Option<usize>,
+    pub state_file: Option<String>,
+    pub on_schema_drift: SchemaDriftPolicy,
+    pub schema_mode: SchemaMode,
+    pub columns: Vec<SchemaFileColumn>,
+    pub on_row_error: RowErrorPolicy,
+    pub analyze: bool,
+    pub exclude_columns: Vec<String>,
+    pub max_field_length: Option<usize>,
+    pub max_row_bytes: Option<usize>,
+    pub on_oversize: OversizePolicy,
+    pub dead_letter_file: Option<String>,
+    pub bisect_failed_batches: bool,
+    pub echo_sql: bool,
+    pub batch_timeout: Option<u64>,
+    /// Lua transform workers; above 1 the source is also read ahead of the target
+    pub parallelism: usize,
+    pub consistent_snapshot: bool,
+    pub fetch_size: Option<usize>,
+    pub keyset_column: Option<String>,
+    pub attach: HashMap<String, String>,
+    pub if_exists: IfExists,
+    pub partition_by: Vec<String>,
+    pub overwrite_partitions: bool,
+    pub new_files_only: bool,
+    pub incremental_column: Option<String>,
+    /// Numeric or date column to read a SQL source by in `split_count`
+    /// concurrent range partitions
+    pub split_column: Option<String>,
+    pub split_count: usize,
+    pub checkpoint_file: Option<String>,
+    pub resume: bool,
+    pub staging_dir: Option<String>,
+    pub staging_max_bytes: Option<u64>,
+    pub null_fallback: BTreeMap<String, NullFallback>,
+    pub column_order: Vec<String>,
+    pub output_header_case: HeaderCase,
+    pub rename_columns: BTreeMap<String, String>,
+    pub strict: bool,
+    pub codecs: BTreeMap<String, String>,
+    /// Column given each row's SHA-256 content hash; the run's Merkle root is
+    /// reported and recorded in the `audit_table`
+    pub row_hash: Option<String>,
+    /// Name of the job in the `audit_table` (default: the target table or file)
+    pub job_name: Option<String>,
+    /// Table URL each run appends a row to (job, start and end, rows, status, error)
+    pub audit_table: Option<String>,
     pub source_options: HashMap<String, String>,
     pub target_options: HashMap<String, String>,
 }
 
+impl Config {
+    /// The `if_exists` policy, with `truncate` standing for `--if-exists truncate`
+    pub fn if_exists_policy(&self) -> IfExists {
+        if self.truncate && self.if_exists == IfExists::Append {
+            IfExists::Truncate
+        } else {
+            self.if_exists
+        }
+    }
+}
+
 impl Default for Config {
     fn default() -> Self {
         Self {
@@ -39,6 +128,67 @@ impl Default for Config {
             source_type: None,
             source_secret_id: None,
             dest_secret_id: None,
+            report_file: None,
+            lua_allow_io: false,
+            lua_timeout_ms: None,
+            lua_path: Vec::new(),
+            scd2: false,
+            key_columns: Vec::new(),
+            track_columns: Vec::new(),
+            pivot: None,
+            unpivot: None,
+            row_number: None,
+            join: None,
+            routes: Vec::new(),
+            query: None,
+            query_params: HashMap::new(),
+            all_tables: false,
+            read_only: false,
+            production_pattern: None,
+            confirm_production: false,
+            yes: false,
+            protect: Vec::new(),
+            redact: Vec::new(),
+            limit: None,
+            state_file: None,
+            on_schema_drift: SchemaDriftPolicy::Warn,
+            schema_mode: SchemaMode::Inferred,
+            columns: Vec::new(),
+            on_row_error: RowErrorPolicy::Fail,
+            analyze: false,
+            exclude_columns: Vec::new(),
+            max_field_length: None,
+            max_row_bytes: None,
+            on_oversize: OversizePolicy::Error,
+            dead_letter_file: None,
+            bisect_failed_batches: false,
+            echo_sql: false,
+            batch_timeout: None,
+            parallelism: 1,
+            consistent_snapshot: false,
+            fetch_size: None,
+            keyset_column: None,
+            attach: HashMap::new(),
+            if_exists: IfExists::Append,
+            partition_by: Vec::new(),
+            overwrite_partitions: false,
+            new_files_only: false,
+            incremental_column: None,
+            split_column: None,
+            split_count: 4,
+            checkpoint_file: None,
+            resume: false,
+            staging_dir: None,
+            staging_max_bytes: None,
+            null_fallback: BTreeMap::new(),
+            column_order: Vec::new(),
+            output_header_case: HeaderCase::Preserve,
+            rename_columns: BTreeMap::new(),
+            strict: false,
+            codecs: BTreeMap::new(),
+            row_hash: None,
+            job_name: None,
+            audit_table: None,
             source_options: HashMap::new(),
             target_options: HashMap::new(),
         }
@@ -66,7 +216,6 @@ impl std::fmt::Display for LogLevel {
 impl std::str::FromStr for LogLevel {
     type Err = &'static str;
 
-
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s.to_lowercase().as_str() {
             "info" => Ok(LogLevel::Info),
@@ -77,11 +226,118 @@ impl std::str::FromStr for LogLevel {
     }
 }
 
+/// What to do with a row that fails schema validation
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RowErrorPolicy {
+    /// Abort the transfer
+    #[default]
+    Fail,
+    /// Log the row's error, drop it and continue
+    Skip,
+}
+
+impl std::fmt::Display for RowErrorPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RowErrorPolicy::Fail => write!(f, "fail"),
+            RowErrorPolicy::Skip => write!(f, "skip"),
+        }
+    }
+}
+
+impl std::str::FromStr for RowErrorPolicy {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "fail" => Ok(RowErrorPolicy::Fail),
+            "skip" => Ok(RowErrorPolicy::Skip),
+            _ => Err("Invalid row error policy. Valid values: fail, skip"),
+        }
+    }
+}
+
+/// What to do with a row holding a field or total size above the configured limits
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum OversizePolicy {
+    /// Abort the transfer
+    #[default]
+    Error,
+    /// Cut the oversized values down to the limits and keep the row
+    Truncate,
+    /// Write the row to the dead letter file instead of the target
+    DeadLetter,
+}
+
+impl std::fmt::Display for OversizePolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OversizePolicy::Error => write!(f, "error"),
+            OversizePolicy::Truncate => write!(f, "truncate"),
+            OversizePolicy::DeadLetter => write!(f, "deadletter"),
+        }
+    }
+}
+
+impl std::str::FromStr for OversizePolicy {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "error" => Ok(OversizePolicy::Error),
+            "truncate" => Ok(OversizePolicy::Truncate),
+            "deadletter" => Ok(OversizePolicy::DeadLetter),
+            _ => Err("Invalid oversize policy. Valid values: error, truncate, deadletter"),
+        }
+    }
+}
+
+/// What to do with a target table or file that already exists
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum IfExists {
+    /// Stop without touching it
+    Fail,
+    /// Add the rows to it; targets that can't append are rewritten
+    #[default]
+    Append,
+    /// Empty it, keeping its table definition
+    Truncate,
+    /// Drop it and create it again with the new schema
+    Replace,
+}
+
+impl std::fmt::Display for IfExists {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IfExists::Fail => write!(f, "fail"),
+            IfExists::Append => write!(f, "append"),
+            IfExists::Truncate => write!(f, "truncate"),
+            IfExists::Replace => write!(f, "replace"),
+        }
+    }
+}
+
+impl std::str::FromStr for IfExists {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "fail" => Ok(IfExists::Fail),
+            "append" => Ok(IfExists::Append),
+            "truncate" => Ok(IfExists::Truncate),
+            "replace" => Ok(IfExists::Replace),
+            _ => Err("Invalid if-exists policy. Valid values: fail, append, truncate, replace"),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
-
     #[test]
     fn test_default_config() {
         let config = Config::default();
@@ -93,7 +349,6 @@ mod tests {
         assert!(matches!(config.log_level, LogLevel::Info));
     }
 
-
     #[test]
     fn test_log_level_parsing() {
         assert!(matches!("info".parse::<LogLevel>(), Ok(LogLevel::Info)));
@@ -102,7 +357,6 @@ mod tests {
         assert!("invalid".parse::<LogLevel>().is_err());
     }
 
-
     #[test]
     fn test_log_level_display() {
         assert_eq!(LogLevel::Info.to_string(), "info");
@@ -0,0 +1,209 @@
+//! Microsoft Access (`.mdb`, `.accdb`) tables, read-only, through an ODBC
+//! driver: Microsoft's Access driver on Windows, MDBTools elsewhere.
+
+use async_trait::async_trait;
+use std::collections::{HashMap, VecDeque};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::{
+    connectors::{
+        odbc::{describe_columns, odbc_value, query_scalar, read_text_rows, OdbcConnection},
+        sql_ident::{quote_table, QuoteStyle},
+        Source,
+    },
+    schema::{Row, Schema},
+    Result, TinyEtlError,
+};
+
+/// ODBC driver used unless the `driver` option names another
+const DEFAULT_DRIVER: &str = if cfg!(windows) {
+    "Microsoft Access Driver (*.mdb, *.accdb)"
+} else {
+    "MDBTools"
+};
+
+/// Whether a connection string names an Access database
+/// (`legacy.mdb#Customers`)
+pub fn is_access_file(connection_string: &str) -> bool {
+    let path = split_table(connection_string).0.to_lowercase();
+    !connection_string.contains("://") && (path.ends_with(".mdb") || path.ends_with(".accdb"))
+}
+
+/// The database path and the table name after `#`, if any
+fn split_table(connection_string: &str) -> (&str, Option<&str>) {
+    match connection_string.rsplit_once('#') {
+        Some((path, table)) => (path, Some(table)),
+        None => (connection_string, None),
+    }
+}
+
+pub struct AccessSource {
+    file_path: PathBuf,
+    table: String,
+    driver: String,
+    connection: Option<Arc<OdbcConnection>>,
+    /// Rows read but not returned yet; Access SQL has no OFFSET to page
+    /// with, so the table is read in one query
+    rows: VecDeque<Row>,
+    loaded: bool,
+    total_rows: Option<usize>,
+}
+
+impl AccessSource {
+    pub fn new(connection_string: &str, options: &HashMap<String, String>) -> Result<Self> {
+        let (path, table) = split_table(connection_string);
+        let table = table.filter(|t| !t.is_empty()).ok_or_else(|| {
+            TinyEtlError::Configuration(format!(
+                "Access connection string must include table name: {}#table",
+                path
+            ))
+        })?;
+        Ok(Self {
+            file_path: PathBuf::from(path),
+            table: table.to_string(),
+            driver: options
+                .get("driver")
+                .cloned()
+                .unwrap_or_else(|| DEFAULT_DRIVER.to_string()),
+            connection: None,
+            rows: VecDeque::new(),
+            loaded: false,
+            total_rows: None,
+        })
+    }
+
+    /// ODBC connection string for the driver; the database path is made
+    /// absolute, since drivers resolve relative ones from their own directory
+    fn odbc_connection_string(&self) -> Result<String> {
+        if !self.file_path.exists() {
+            return Err(TinyEtlError::Connection(format!(
+                "Access database not found: {}",
+                self.file_path.display()
+            )));
+        }
+        let path = std::env::current_dir()?.join(&self.file_path);
+        Ok(format!(
+            "Driver={};DBQ={}",
+            odbc_value(&self.driver),
+            odbc_value(&path.to_string_lossy())
+        ))
+    }
+
+    fn relation(&self) -> String {
+        quote_table(&self.table, QuoteStyle::Bracket)
+    }
+
+    fn connection(&self) -> Result<&OdbcConnection> {
+        self.connection
+            .as_deref()
+            .ok_or_else(|| TinyEtlError::Connection("Not connected to Access".to_string()))
+    }
+}
+
+#[async_trait]
+impl Source for AccessSource {
+    async fn connect(&mut self) -> Result<()> {
+        let odbc = self.odbc_connection_string()?;
+        self.connection = Some(Arc::new(OdbcConnection::new(&odbc)?));
+        Ok(())
+    }
+
+    async fn infer_schema(&mut self, _sample_size: usize) -> Result<Schema> {
+        if self.connection.is_none() {
+            self.connect().await?;
+        }
+        let connection = self.connection()?;
+
+        let query = format!("SELECT * FROM {} WHERE 1 = 0", self.relation());
+        let mut cursor = connection
+            .connection()
+            .execute(&query, ())
+            .map_err(|e| TinyEtlError::SchemaInference(format!("Failed to describe table: {}", e)))?
+            .ok_or_else(|| {
+                TinyEtlError::SchemaInference(format!("Table '{}' not found", self.table))
+            })?;
+        let columns = describe_columns(&mut cursor)?;
+        drop(cursor);
+
+        let count_query = format!("SELECT COUNT(*) FROM {}", self.relation());
+        let total_rows = query_scalar(connection, &count_query)
+            .ok()
+            .flatten()
+            .and_then(|count| count.parse().ok());
+
+        self.total_rows = total_rows;
+        Ok(Schema {
+            columns,
+            estimated_rows: total_rows,
+            primary_key_candidate: None,
+        })
+    }
+
+    async fn read_batch(&mut self, batch_size: usize) -> Result<Vec<Row>> {
+        if !self.loaded {
+            if self.connection.is_none() {
+                self.connect().await?;
+            }
+            let query = format!("SELECT * FROM {}", self.relation());
+            // Cloned so the cursor doesn't borrow self while the rows are stored
+            let connection =
+                Arc::clone(self.connection.as_ref().ok_or_else(|| {
+                    TinyEtlError::Connection("Not connected to Access".to_string())
+                })?);
+            let cursor = connection.connection().execute(&query, ()).map_err(|e| {
+                TinyEtlError::DataTransfer(format!("Failed to execute query: {}", e))
+            })?;
+            if let Some(mut cursor) = cursor {
+                self.rows = read_text_rows(&mut cursor, usize::MAX)?.into();
+            }
+            self.loaded = true;
+        }
+        let count = batch_size.min(self.rows.len());
+        Ok(self.rows.drain(..count).collect())
+    }
+
+    async fn estimated_row_count(&self) -> Result<Option<usize>> {
+        Ok(self.total_rows)
+    }
+
+    async fn reset(&mut self) -> Result<()> {
+        self.rows.clear();
+        self.loaded = false;
+        Ok(())
+    }
+
+    fn has_more(&self) -> bool {
+        !self.loaded || !self.rows.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_access_connection_strings() {
+        assert!(is_access_file("legacy.mdb#Customers"));
+        assert!(is_access_file("C:/Data/Orders.ACCDB#Order Details"));
+        assert!(!is_access_file("legacy.db#customers"));
+        assert!(!is_access_file("odbc://DSN=legacy#Customers"));
+
+        let source = AccessSource::new("legacy.mdb#Order Details", &HashMap::new()).unwrap();
+        assert_eq!(source.relation(), "[Order Details]");
+        assert_eq!(source.driver, DEFAULT_DRIVER);
+        assert!(AccessSource::new("legacy.mdb", &HashMap::new()).is_err());
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("legacy.mdb");
+        std::fs::write(&path, b"").unwrap();
+        let options = HashMap::from([("driver".to_string(), "MDBTools".to_string())]);
+        let source = AccessSource::new(&format!("{}#Customers", path.display()), &options).unwrap();
+        let odbc = source.odbc_connection_string().unwrap();
+        assert!(odbc.starts_with("Driver=MDBTools;DBQ="));
+        assert!(odbc.ends_with("legacy.mdb"));
+
+        let missing = AccessSource::new("missing.mdb#Customers", &HashMap::new()).unwrap();
+        assert!(missing.odbc_connection_string().is_err());
+    }
+}
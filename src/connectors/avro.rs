@@ -4,17 +4,19 @@ use rust_decimal::Decimal;
 use serde_json::{json, Value as JsonValue};
 use std::collections::HashMap;
 use std::fs::File;
-use std::io::{BufReader, BufWriter};
+use std::io::{BufReader, Write};
 use std::path::PathBuf;
 
 use crate::{
     connectors::{Source, Target},
-    schema::{Column, DataType, Row, Schema, Value},
+    schema::{BinaryEncoding, Column, DataType, Row, Schema, Value},
     Result, TinyEtlError,
 };
 
 pub struct AvroSource {
     file_path: PathBuf,
+    /// Reader positioned after the records read so far, opened on connect
+    reader: Option<Reader<'static, BufReader<File>>>,
     current_position: usize,
     total_records: Option<usize>,
     has_more: bool,
@@ -24,12 +26,20 @@ impl AvroSource {
     pub fn new(file_path: &str) -> Result<Self> {
         Ok(Self {
             file_path: PathBuf::from(file_path),
+            reader: None,
             current_position: 0,
             total_records: None,
             has_more: true,
         })
     }
 
+    /// A reader at the first record of the file
+    fn open_reader(&self) -> Result<Reader<'static, BufReader<File>>> {
+        let file = File::open(&self.file_path)?;
+        Reader::new(BufReader::new(file))
+            .map_err(|e| TinyEtlError::DataTransfer(format!("Invalid Avro file: {}", e)))
+    }
+
     fn avro_type_to_schema_type(avro_type: &JsonValue) -> DataType {
         match avro_type {
             JsonValue::String(type_name) => match type_name.as_str() {
@@ -39,6 +49,7 @@ impl AvroSource {
                 "boolean" => DataType::Boolean,
                 "date" => DataType::Date,
                 "timestamp-millis" | "timestamp-micros" => DataType::DateTime,
+                "bytes" | "fixed" => DataType::Binary,
                 _ => DataType::String, // Default to string
             },
             JsonValue::Array(union_types) => {
@@ -82,7 +93,7 @@ impl AvroSource {
         }
     }
 
-    fn avro_value_to_value(avro_value: &AvroValue) -> Result<Value> {
+    pub(crate) fn avro_value_to_value(avro_value: &AvroValue) -> Result<Value> {
         match avro_value {
             AvroValue::Null => Ok(Value::Null),
             AvroValue::Boolean(b) => Ok(Value::Boolean(*b)),
@@ -102,9 +113,9 @@ impl AvroSource {
                     Err(_) => Ok(Value::String(d.to_string())),
                 }
             }
-            AvroValue::Bytes(b) => Ok(Value::String(format!("{:?}", b))),
+            AvroValue::Bytes(b) => Ok(Value::Bytes(b.clone())),
             AvroValue::String(s) => Ok(Value::String(s.clone())),
-            AvroValue::Fixed(_, bytes) => Ok(Value::String(format!("{:?}", bytes))),
+            AvroValue::Fixed(_, bytes) => Ok(Value::Bytes(bytes.clone())),
             AvroValue::Enum(_, symbol) => Ok(Value::String(symbol.clone())),
             AvroValue::Union(_, boxed_value) => Self::avro_value_to_value(boxed_value),
             AvroValue::Array(values) => {
@@ -240,13 +251,7 @@ impl Source for AvroSource {
             )));
         }
 
-        // Just verify the file can be opened and is a valid Avro file
-        let file = File::open(&self.file_path)?;
-        let buf_reader = BufReader::new(file);
-
-        let _reader = Reader::new(buf_reader)
-            .map_err(|e| TinyEtlError::DataTransfer(format!("Invalid Avro file: {}", e)))?;
-
+        self.reader = Some(self.open_reader()?);
         self.current_position = 0;
         self.has_more = true;
 
@@ -316,62 +321,40 @@ impl Source for AvroSource {
             return Ok(vec![]);
         }
 
-        // Create a new reader each time (not ideal for performance, but works around lifetime issues)
-        let file = File::open(&self.file_path)?;
-        let buf_reader = BufReader::new(file);
-
-        let reader = Reader::new(buf_reader).map_err(|e| {
-            TinyEtlError::DataTransfer(format!("Failed to create Avro reader: {}", e))
-        })?;
-
-        let mut rows = Vec::new();
-        let mut count = 0;
-        let mut current_index = 0;
-        let mut reached_end = false;
-
-        for value_result in reader {
-            // Skip records we've already read
-            if current_index < self.current_position {
-                current_index += 1;
-                continue;
-            }
-
-            if count >= batch_size {
-                break;
-            }
-
-            match value_result {
-                Ok(avro_value) => {
-                    if let AvroValue::Record(fields) = avro_value {
-                        let mut row = HashMap::new();
-                        for (field_name, field_value) in fields {
-                            let converted_value = Self::avro_value_to_value(&field_value)?;
-                            row.insert(field_name, converted_value);
-                        }
-                        rows.push(row);
-                        count += 1;
-                        self.current_position += 1;
-                        current_index += 1;
+        if self.reader.is_none() {
+            self.reader = Some(self.open_reader()?);
+        }
+        // Each batch continues from where the last one stopped, so the file
+        // is decoded once
+        let reader = self.reader.as_mut().unwrap();
+
+        let mut rows = Vec::with_capacity(batch_size);
+        while rows.len() < batch_size {
+            match reader.next() {
+                Some(Ok(AvroValue::Record(fields))) => {
+                    let mut row = HashMap::new();
+                    for (field_name, field_value) in fields {
+                        let converted_value = Self::avro_value_to_value(&field_value)?;
+                        row.insert(field_name, converted_value);
                     }
+                    rows.push(row);
                 }
-                Err(e) => {
+                // Values other than records have no columns to read
+                Some(Ok(_)) => {}
+                Some(Err(e)) => {
                     return Err(TinyEtlError::DataTransfer(format!(
                         "Failed to read Avro record: {}",
                         e
                     )));
                 }
+                None => {
+                    self.has_more = false;
+                    break;
+                }
             }
         }
 
-        // Check if we've reached the end by seeing if we can read more
-        if count < batch_size {
-            reached_end = true;
-        }
-
-        if rows.is_empty() || reached_end {
-            self.has_more = false;
-        }
-
+        self.current_position += rows.len();
         Ok(rows)
     }
 
@@ -390,10 +373,18 @@ impl Source for AvroSource {
     }
 }
 
+/// Target writing each batch to the file as it comes, as one data block
 pub struct AvroTarget {
     file_path: PathBuf,
-    schema: Option<AvroSchema>,
-    buffer: Vec<Row>,
+    /// Schema of the file, set by `create_table`. It's leaked, as the writer
+    /// borrows it for as long as the target lives.
+    schema: Option<&'static AvroSchema>,
+    /// Name, type and nullability of the fields of the schema, in order
+    fields: Vec<(String, DataType, bool)>,
+    /// Open from `create_table` until `finalize`. The writer buffers a batch
+    /// as a block itself, so the file isn't wrapped in a `BufWriter` that
+    /// would hold the block back from disk.
+    writer: Option<Writer<'static, File>>,
 }
 
 impl AvroTarget {
@@ -401,11 +392,46 @@ impl AvroTarget {
         Ok(Self {
             file_path: PathBuf::from(file_path),
             schema: None,
-            buffer: Vec::new(),
+            fields: Vec::new(),
+            writer: None,
+        })
+    }
+
+    /// Fields of the schema as the records are written
+    pub(crate) fn record_fields(schema: &AvroSchema) -> Result<Vec<(String, DataType, bool)>> {
+        let schema_json: JsonValue = serde_json::from_str(&schema.canonical_form())
+            .map_err(|e| TinyEtlError::DataTransfer(format!("Failed to parse schema: {}", e)))?;
+
+        let mut record_fields = Vec::new();
+        if let Some(JsonValue::Array(fields)) = schema_json.get("fields") {
+            for field in fields {
+                if let JsonValue::Object(field_obj) = field {
+                    let field_name = field_obj
+                        .get("name")
+                        .and_then(|v| v.as_str())
+                        .unwrap_or("unknown");
+
+                    let default_type = JsonValue::String("string".to_string());
+                    let field_type = field_obj.get("type").unwrap_or(&default_type);
+                    record_fields.push((
+                        field_name.to_string(),
+                        AvroSource::avro_type_to_schema_type(field_type),
+                        AvroSource::is_nullable(field_type),
+                    ));
+                }
+            }
+        }
+        Ok(record_fields)
+    }
+
+    pub(crate) fn schema_to_avro_schema(schema: &Schema) -> Result<AvroSchema> {
+        AvroSchema::parse(&Self::schema_to_avro_json(schema)).map_err(|e| {
+            TinyEtlError::Configuration(format!("Failed to create Avro schema: {}", e))
         })
     }
 
-    fn schema_to_avro_schema(schema: &Schema) -> Result<AvroSchema> {
+    /// The Avro record schema written for `schema`, as JSON
+    pub(crate) fn schema_to_avro_json(schema: &Schema) -> JsonValue {
         let mut fields = Vec::new();
 
         for column in &schema.columns {
@@ -460,6 +486,13 @@ impl AvroTarget {
                         json!("string")
                     }
                 }
+                DataType::Binary => {
+                    if column.nullable {
+                        json!(["null", "bytes"])
+                    } else {
+                        json!("bytes")
+                    }
+                }
                 DataType::Null => json!(["null", "string"]),
             };
 
@@ -469,18 +502,14 @@ impl AvroTarget {
             }));
         }
 
-        let avro_schema_json = json!({
+        json!({
             "type": "record",
             "name": "Record",
             "fields": fields
-        });
-
-        AvroSchema::parse(&avro_schema_json).map_err(|e| {
-            TinyEtlError::Configuration(format!("Failed to create Avro schema: {}", e))
         })
     }
 
-    fn value_to_avro_value(
+    pub(crate) fn value_to_avro_value(
         value: &Value,
         data_type: &DataType,
         nullable: bool,
@@ -506,6 +535,7 @@ impl AvroTarget {
                 AvroValue::Double(f)
             }
             (Value::Boolean(b), DataType::Boolean) => AvroValue::Boolean(*b),
+            (Value::Bytes(b), DataType::Binary) => AvroValue::Bytes(b.clone()),
             (Value::Date(dt), DataType::Date) => {
                 let days_since_epoch = (dt.timestamp() / 86400) as i32;
                 AvroValue::Date(days_since_epoch)
@@ -536,6 +566,9 @@ impl AvroTarget {
             (Value::Integer(i), DataType::String) => AvroValue::String(i.to_string()),
             (Value::Decimal(d), DataType::String) => AvroValue::String(d.to_string()),
             (Value::Boolean(b), DataType::String) => AvroValue::String(b.to_string()),
+            (Value::Bytes(b), DataType::String) => {
+                AvroValue::String(BinaryEncoding::default().encode(b))
+            }
             _ => {
                 return Err(TinyEtlError::DataTransfer(format!(
                     "Cannot convert value {:?} to Avro type {:?}",
@@ -561,80 +594,54 @@ impl Target for AvroTarget {
     }
 
     async fn create_table(&mut self, _table_name: &str, schema: &Schema) -> Result<()> {
-        let avro_schema = Self::schema_to_avro_schema(schema)?;
+        let avro_schema: &'static AvroSchema =
+            Box::leak(Box::new(Self::schema_to_avro_schema(schema)?));
+        self.fields = Self::record_fields(avro_schema)?;
+        let file = File::create(&self.file_path)?;
+        self.writer = Some(Writer::new(avro_schema, file));
         self.schema = Some(avro_schema);
         Ok(())
     }
 
     async fn write_batch(&mut self, rows: &[Row]) -> Result<usize> {
-        if self.schema.is_none() {
-            return Err(TinyEtlError::Configuration(
+        let writer = self.writer.as_mut().ok_or_else(|| {
+            TinyEtlError::Configuration(
                 "Schema not initialized. Call create_table() first.".to_string(),
-            ));
+            )
+        })?;
+
+        for row in rows {
+            let mut record_fields = Vec::with_capacity(self.fields.len());
+            for (field_name, data_type, nullable) in &self.fields {
+                let value = row.get(field_name).unwrap_or(&Value::Null);
+                let avro_value = Self::value_to_avro_value(value, data_type, *nullable)?;
+                record_fields.push((field_name.clone(), avro_value));
+            }
+
+            writer
+                .append(AvroValue::Record(record_fields))
+                .map_err(|e| {
+                    TinyEtlError::DataTransfer(format!("Failed to write Avro record: {}", e))
+                })?;
         }
 
-        // Buffer the rows for writing during finalization
-        self.buffer.extend_from_slice(rows);
+        // Write the batch out as a data block, so only one batch is ever
+        // held in memory
+        writer.flush().map_err(|e| {
+            TinyEtlError::DataTransfer(format!("Failed to flush Avro writer: {}", e))
+        })?;
         Ok(rows.len())
     }
 
     async fn finalize(&mut self) -> Result<()> {
-        if self.schema.is_none() {
+        let Some(writer) = self.writer.take() else {
             return Ok(());
-        }
-
-        let schema = self.schema.as_ref().unwrap();
-
-        // Create writer and write all buffered data
-        let file = File::create(&self.file_path)?;
-        let buf_writer = BufWriter::new(file);
-
-        let mut writer = Writer::new(schema, buf_writer);
-
-        for row in &self.buffer {
-            let mut record_fields = Vec::new();
-
-            // Extract fields from schema to maintain order
-            let schema_json: JsonValue =
-                serde_json::from_str(&schema.canonical_form()).map_err(|e| {
-                    TinyEtlError::DataTransfer(format!("Failed to parse schema: {}", e))
-                })?;
-
-            if let JsonValue::Object(obj) = &schema_json {
-                if let Some(JsonValue::Array(fields)) = obj.get("fields") {
-                    for field in fields {
-                        if let JsonValue::Object(field_obj) = field {
-                            let field_name = field_obj
-                                .get("name")
-                                .and_then(|v| v.as_str())
-                                .unwrap_or("unknown");
-
-                            let default_type = JsonValue::String("string".to_string());
-                            let field_type = field_obj.get("type").unwrap_or(&default_type);
-                            let data_type = AvroSource::avro_type_to_schema_type(field_type);
-                            let nullable = AvroSource::is_nullable(field_type);
-
-                            let value = row.get(field_name).unwrap_or(&Value::Null);
-                            let avro_value =
-                                Self::value_to_avro_value(value, &data_type, nullable)?;
-
-                            record_fields.push((field_name.to_string(), avro_value));
-                        }
-                    }
-                }
-            }
-
-            let record = AvroValue::Record(record_fields);
-            writer.append(record).map_err(|e| {
-                TinyEtlError::DataTransfer(format!("Failed to write Avro record: {}", e))
-            })?;
-        }
+        };
 
-        writer.flush().map_err(|e| {
+        let mut file = writer.into_inner().map_err(|e| {
             TinyEtlError::DataTransfer(format!("Failed to flush Avro writer: {}", e))
         })?;
-
-        self.buffer.clear();
+        file.flush()?;
         Ok(())
     }
 
@@ -643,8 +650,8 @@ impl Target for AvroTarget {
     }
 
     async fn truncate(&mut self, _table_name: &str) -> Result<()> {
-        // For Avro files, truncation means clearing buffer
-        self.buffer.clear();
+        // Drop what was written so far; create_table starts the file over
+        self.writer = None;
         Ok(())
     }
 
@@ -653,6 +660,10 @@ impl Target for AvroTarget {
         // For simplicity, we return false to force truncation
         false
     }
+
+    fn bytes_written(&self) -> Option<u64> {
+        std::fs::metadata(&self.file_path).ok().map(|m| m.len())
+    }
 }
 
 #[cfg(test)]
@@ -875,6 +886,43 @@ mod tests {
         assert_eq!(rows.len(), 2);
     }
 
+    #[tokio::test]
+    async fn test_avro_source_batches_continue_across_blocks() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let schema = AvroSchema::parse(&json!({
+            "type": "record",
+            "name": "Event",
+            "fields": [{"name": "id", "type": "long"}]
+        }))
+        .unwrap();
+        let mut writer = Writer::new(&schema, temp_file.reopen().unwrap());
+        for id in 0..250 {
+            writer
+                .append(AvroValue::Record(vec![(
+                    "id".to_string(),
+                    AvroValue::Long(id),
+                )]))
+                .unwrap();
+            // Several data blocks, so batches also start mid-block
+            if id % 40 == 39 {
+                writer.flush().unwrap();
+            }
+        }
+        writer.flush().unwrap();
+
+        let mut source = AvroSource::new(temp_file.path().to_str().unwrap()).unwrap();
+        source.connect().await.unwrap();
+        let mut ids = Vec::new();
+        while source.has_more() {
+            for row in source.read_batch(100).await.unwrap() {
+                ids.push(row["id"].clone());
+            }
+        }
+        let expected: Vec<Value> = (0..250).map(Value::Integer).collect();
+        assert_eq!(ids, expected);
+        assert_eq!(source.current_position, 250);
+    }
+
     #[tokio::test]
     async fn test_avro_type_to_schema_type() {
         assert_eq!(
@@ -1000,7 +1048,7 @@ mod tests {
         let target = target.unwrap();
         assert_eq!(target.file_path.to_string_lossy(), "output.avro");
         assert!(target.schema.is_none());
-        assert!(target.buffer.is_empty());
+        assert!(target.writer.is_none());
     }
 
     #[tokio::test]
@@ -1012,7 +1060,8 @@ mod tests {
 
     #[tokio::test]
     async fn test_avro_target_create_table() {
-        let mut target = AvroTarget::new("output.avro").unwrap();
+        let temp_file = NamedTempFile::new().unwrap();
+        let mut target = AvroTarget::new(temp_file.path().to_str().unwrap()).unwrap();
 
         let schema = Schema {
             columns: vec![
@@ -1049,6 +1098,8 @@ mod tests {
         let result = target.create_table("test_table", &schema).await;
         assert!(result.is_ok());
         assert!(target.schema.is_some());
+        assert!(target.writer.is_some());
+        assert_eq!(target.fields.len(), 5);
     }
 
     #[tokio::test]
@@ -1391,20 +1442,12 @@ mod tests {
         // Test Bytes
         let bytes_value = AvroValue::Bytes(vec![1, 2, 3]);
         let result = AvroSource::avro_value_to_value(&bytes_value).unwrap();
-        if let Value::String(s) = result {
-            assert!(s.contains("1"));
-        } else {
-            panic!("Expected String value");
-        }
+        assert_eq!(result, Value::Bytes(vec![1, 2, 3]));
 
         // Test Fixed
         let fixed_value = AvroValue::Fixed(4, vec![1, 2, 3, 4]);
         let result = AvroSource::avro_value_to_value(&fixed_value).unwrap();
-        if let Value::String(_) = result {
-            // Success
-        } else {
-            panic!("Expected String value");
-        }
+        assert_eq!(result, Value::Bytes(vec![1, 2, 3, 4]));
 
         // Test Enum
         let enum_value = AvroValue::Enum(0, "OPTION_A".to_string());
@@ -1599,7 +1642,7 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_avro_target_buffer_size() {
+    async fn test_avro_target_writes_each_batch() {
         let temp_file = NamedTempFile::new().unwrap();
         let mut target = AvroTarget::new(temp_file.path().to_str().unwrap()).unwrap();
 
@@ -1616,18 +1659,24 @@ mod tests {
         target.connect().await.unwrap();
         target.create_table("test", &schema).await.unwrap();
 
-        // Write multiple small batches
+        // Each batch is on disk before the next one is written
+        let mut size = 0;
         for i in 0..5 {
             let row = std::collections::HashMap::from([("id".to_string(), Value::Integer(i))]);
             target.write_batch(&[row]).await.unwrap();
+            let written = target.bytes_written().unwrap();
+            assert!(written > size);
+            size = written;
         }
 
-        // Check buffer
-        assert_eq!(target.buffer.len(), 5);
-
-        // Finalize to flush buffer
         target.finalize().await.unwrap();
-        assert_eq!(target.buffer.len(), 0);
+        assert!(target.writer.is_none());
+
+        let mut source = AvroSource::new(temp_file.path().to_str().unwrap()).unwrap();
+        source.connect().await.unwrap();
+        let rows = source.read_batch(10).await.unwrap();
+        let ids: Vec<Value> = rows.iter().map(|row| row["id"].clone()).collect();
+        assert_eq!(ids, (0..5).map(Value::Integer).collect::<Vec<_>>());
     }
 
     #[test]
@@ -0,0 +1,457 @@
+//! Compressed files (`data.csv.gz`, `events.jsonl.zst`): every file
+//! connector reads and writes them through the staging directory. A source
+//! is decompressed there and read by the connector of the inner extension;
+//! a target writes there, and the file is compressed into place when the
+//! transfer finishes.
+
+use async_trait::async_trait;
+use flate2::{read::MultiGzDecoder, write::GzEncoder};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+use crate::{
+    connectors::Target,
+    schema::{Row, Schema},
+    staging::staging,
+    Result, TinyEtlError,
+};
+
+/// Compression of a file, from its last extension
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Codec {
+    Gzip,
+    Zstd,
+}
+
+impl Codec {
+    fn from_extension(extension: &str) -> Option<Self> {
+        match extension.to_lowercase().as_str() {
+            "gz" => Some(Self::Gzip),
+            "zst" => Some(Self::Zstd),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Gzip => "gzip",
+            Self::Zstd => "zstd",
+        }
+    }
+
+    /// Check a `compression.level` against the levels the codec has
+    fn check_level(self, level: i32) -> Result<()> {
+        let (min, max) = match self {
+            Self::Gzip => (0, 9),
+            Self::Zstd => (1, 22),
+        };
+        if !(min..=max).contains(&level) {
+            return Err(TinyEtlError::Configuration(format!(
+                "Invalid compression level for {}: {} (must be {} to {})",
+                self.name(),
+                level,
+                min,
+                max
+            )));
+        }
+        Ok(())
+    }
+
+    /// Decompress `from` into `to`, returning the bytes written
+    fn decompress(self, from: &Path, to: &Path) -> Result<u64> {
+        let input = BufReader::new(File::open(from)?);
+        let mut reader: Box<dyn Read> = match self {
+            // Several gzip members, as `cat a.gz b.gz` makes, are one file
+            Self::Gzip => Box::new(MultiGzDecoder::new(input)),
+            Self::Zstd => Box::new(zstd::stream::read::Decoder::new(input)?),
+        };
+        let mut output = BufWriter::new(File::create(to)?);
+        let bytes = std::io::copy(&mut reader, &mut output).map_err(|e| {
+            TinyEtlError::DataValidation(format!("Can't decompress {}: {}", from.display(), e))
+        })?;
+        output.flush()?;
+        Ok(bytes)
+    }
+
+    /// Compress `from` into `to` at `level`, or the codec's default level
+    fn compress(self, from: &Path, to: &Path, level: Option<i32>) -> Result<()> {
+        let mut input = BufReader::new(File::open(from)?);
+        let output = BufWriter::new(File::create(to)?);
+        match self {
+            Self::Gzip => {
+                let level = level.map_or_else(flate2::Compression::default, |level| {
+                    flate2::Compression::new(level as u32)
+                });
+                let mut encoder = GzEncoder::new(output, level);
+                std::io::copy(&mut input, &mut encoder)?;
+                encoder.finish()?.flush()?;
+            }
+            Self::Zstd => {
+                // Level 0 is zstd's default
+                let mut encoder = zstd::stream::write::Encoder::new(output, level.unwrap_or(0))?;
+                std::io::copy(&mut input, &mut encoder)?;
+                encoder.finish()?.flush()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A compressed file named by a connection string, e.g. `sales.xlsx.gz#Q1`
+struct CompressedPath {
+    codec: Codec,
+    path: PathBuf,
+    /// Extension of the file once decompressed (`xlsx`)
+    inner_extension: String,
+    /// `#table` or `#Sheet` part of the connection string, if any
+    fragment: Option<String>,
+}
+
+impl CompressedPath {
+    fn parse(connection_string: &str) -> Result<Option<Self>> {
+        if connection_string.contains("://") {
+            return Ok(None);
+        }
+        let (path, fragment) = match connection_string.split_once('#') {
+            Some((path, fragment)) => (path, Some(fragment.to_string())),
+            None => (connection_string, None),
+        };
+        let path = PathBuf::from(path);
+        let Some(codec) = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .and_then(Codec::from_extension)
+        else {
+            return Ok(None);
+        };
+        let inner_extension = path
+            .file_stem()
+            .map(Path::new)
+            .and_then(|stem| stem.extension())
+            .and_then(|ext| ext.to_str())
+            .map(str::to_lowercase)
+            .ok_or_else(|| {
+                TinyEtlError::Configuration(format!(
+                    "Can't tell the format of {} once decompressed; name it after its format, like data.csv.gz",
+                    path.display()
+                ))
+            })?;
+        Ok(Some(Self {
+            codec,
+            path,
+            inner_extension,
+            fragment,
+        }))
+    }
+
+    /// Connection string of a staged file, with the fragment of this one
+    fn connection_string(&self, staged: &Path) -> String {
+        match &self.fragment {
+            Some(fragment) => format!("{}#{}", staged.display(), fragment),
+            None => staged.display().to_string(),
+        }
+    }
+}
+
+/// Whether a connection string names a compressed file
+pub fn is_compressed_file(connection_string: &str) -> bool {
+    !matches!(CompressedPath::parse(connection_string), Ok(None))
+}
+
+/// Extension to stage a downloaded file under: a compressed file keeps the
+/// extension of its format, so `data.csv.gz` is staged as `csv.gz`
+pub fn staged_extension(path: &str, extension: Option<String>) -> Option<String> {
+    let extension = extension?;
+    if Codec::from_extension(&extension).is_none() {
+        return Some(extension);
+    }
+    let stem = Path::new(path).file_stem()?;
+    match Path::new(stem).extension().and_then(|ext| ext.to_str()) {
+        Some(inner) => Some(format!("{}.{}", inner.to_lowercase(), extension)),
+        None => Some(extension),
+    }
+}
+
+/// Decompress a compressed source into the staging directory and return
+/// the connection string of the decompressed copy; any other connection
+/// string is returned as is
+pub fn decompressed_source(connection_string: &str) -> Result<String> {
+    let Some(compressed) = CompressedPath::parse(connection_string)? else {
+        return Ok(connection_string.to_string());
+    };
+    if !compressed.path.exists() {
+        return Err(TinyEtlError::Connection(format!(
+            "File not found: {}",
+            compressed.path.display()
+        )));
+    }
+
+    let staging = staging()?;
+    let staged = staging.temp_path(Some(&compressed.inner_extension));
+    let bytes = compressed
+        .codec
+        .decompress(&compressed.path, &staged)
+        .and_then(|bytes| staging.reserve(bytes).map(|_| bytes));
+    let bytes = match bytes {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            let _ = std::fs::remove_file(&staged);
+            return Err(e);
+        }
+    };
+    info!(
+        "Decompressed {} ({} bytes)",
+        compressed.path.display(),
+        bytes
+    );
+    Ok(compressed.connection_string(&staged))
+}
+
+/// Target writing a file of the inner format in the staging directory, then
+/// compressing it into place on finalize. Appending targets start from a
+/// decompressed copy of the existing file, so the whole file is rewritten.
+pub struct CompressedTarget {
+    inner: Box<dyn Target>,
+    codec: Codec,
+    /// `compression.level`, checked against the codec
+    level: Option<i32>,
+    /// File the inner target writes
+    staged: PathBuf,
+    /// The compressed file
+    path: PathBuf,
+}
+
+impl CompressedTarget {
+    /// A target for `connection_string` if it names a compressed file,
+    /// compressing at `level`, with `create` making the target of the
+    /// staged, uncompressed file
+    pub fn wrap(
+        connection_string: &str,
+        level: Option<i32>,
+        create: impl FnOnce(&str) -> Result<Box<dyn Target>>,
+    ) -> Result<Option<Box<dyn Target>>> {
+        let Some(compressed) = CompressedPath::parse(connection_string)? else {
+            return Ok(None);
+        };
+        if let Some(level) = level {
+            compressed.codec.check_level(level)?;
+        }
+        let staged = staging()?.temp_path(Some(&compressed.inner_extension));
+        let inner = create(&compressed.connection_string(&staged))?;
+        Ok(Some(Box::new(Self {
+            inner,
+            codec: compressed.codec,
+            level,
+            staged,
+            path: compressed.path,
+        })))
+    }
+}
+
+#[async_trait]
+impl Target for CompressedTarget {
+    async fn connect(&mut self) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        if self.path.exists() && self.inner.supports_append() {
+            self.codec.decompress(&self.path, &self.staged)?;
+        }
+        self.inner.connect().await
+    }
+
+    fn bytes_written(&self) -> Option<u64> {
+        std::fs::metadata(&self.path).ok().map(|m| m.len())
+    }
+
+    async fn create_table(&mut self, table_name: &str, schema: &Schema) -> Result<()> {
+        self.inner.create_table(table_name, schema).await
+    }
+
+    async fn write_batch(&mut self, rows: &[Row]) -> Result<usize> {
+        self.inner.write_batch(rows).await
+    }
+
+    async fn finalize(&mut self) -> Result<()> {
+        self.inner.finalize().await?;
+        if self.staged.exists() {
+            self.codec.compress(&self.staged, &self.path, self.level)?;
+            std::fs::remove_file(&self.staged)?;
+        }
+        Ok(())
+    }
+
+    async fn exists(&self, _table_name: &str) -> Result<bool> {
+        Ok(self.path.exists())
+    }
+
+    async fn truncate(&mut self, table_name: &str) -> Result<()> {
+        self.inner.truncate(table_name).await
+    }
+
+    async fn drop_table(&mut self, table_name: &str) -> Result<()> {
+        self.inner.drop_table(table_name).await
+    }
+
+    fn supports_append(&self) -> bool {
+        self.inner.supports_append()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connectors::{create_source, create_target, create_target_with_options};
+    use crate::schema::{Column, DataType, Value};
+    use std::collections::HashMap;
+
+    fn schema() -> Schema {
+        Schema {
+            columns: vec![
+                Column {
+                    name: "id".to_string(),
+                    data_type: DataType::Integer,
+                    nullable: false,
+                },
+                Column {
+                    name: "name".to_string(),
+                    data_type: DataType::String,
+                    nullable: true,
+                },
+            ],
+            estimated_rows: None,
+            primary_key_candidate: None,
+        }
+    }
+
+    fn row(id: i64, name: &str) -> Row {
+        Row::from([
+            ("id".to_string(), Value::Integer(id)),
+            ("name".to_string(), Value::String(name.to_string())),
+        ])
+    }
+
+    async fn write(path: &str, rows: &[Row]) {
+        let mut target = create_target(path).unwrap();
+        target.connect().await.unwrap();
+        if target.exists("data").await.unwrap() && !target.supports_append() {
+            target.truncate("data").await.unwrap();
+        }
+        target.create_table("data", &schema()).await.unwrap();
+        target.write_batch(rows).await.unwrap();
+        target.finalize().await.unwrap();
+    }
+
+    async fn read(path: &str) -> Vec<Row> {
+        let mut source = create_source(path).unwrap();
+        source.connect().await.unwrap();
+        let mut rows = Vec::new();
+        while source.has_more() {
+            rows.extend(source.read_batch(100).await.unwrap());
+        }
+        rows
+    }
+
+    #[tokio::test]
+    async fn test_compressed_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["people.csv.gz", "people.jsonl.zst", "people.parquet.gz"] {
+            let path = dir.path().join(name).to_string_lossy().to_string();
+            write(&path, &[row(1, "Ann"), row(2, "Bob")]).await;
+
+            // The file on disk is compressed
+            let magic = std::fs::read(&path).unwrap()[..2].to_vec();
+            if name.ends_with(".gz") {
+                assert_eq!(magic, [0x1f, 0x8b]);
+            } else {
+                assert_eq!(magic, [0x28, 0xb5]);
+            }
+
+            let rows = read(&path).await;
+            assert_eq!(rows.len(), 2, "{}", name);
+            assert_eq!(rows[1]["name"], Value::String("Bob".to_string()));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_appending_to_a_compressed_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir
+            .path()
+            .join("log.jsonl.gz")
+            .to_string_lossy()
+            .to_string();
+        write(&path, &[row(1, "Ann")]).await;
+        write(&path, &[row(2, "Bob")]).await;
+
+        let ids: Vec<Value> = read(&path).await.iter().map(|r| r["id"].clone()).collect();
+        assert_eq!(ids, [Value::Integer(1), Value::Integer(2)]);
+    }
+
+    #[tokio::test]
+    async fn test_compression_level() {
+        let dir = tempfile::tempdir().unwrap();
+        let rows: Vec<Row> = (0..2_000)
+            .map(|i| row(i, &format!("user {}", i * 7_919 % 1_000)))
+            .collect();
+        for (name, fast, small) in [("people.csv.gz", "1", "9"), ("people.csv.zst", "1", "19")] {
+            let mut sizes = Vec::new();
+            for level in [fast, small] {
+                let path = dir.path().join(format!("{}-{}", level, name));
+                let options = HashMap::from([("compression.level".to_string(), level.to_string())]);
+                let mut target =
+                    create_target_with_options(&path.to_string_lossy(), &options).unwrap();
+                target.connect().await.unwrap();
+                target.create_table("data", &schema()).await.unwrap();
+                target.write_batch(&rows).await.unwrap();
+                target.finalize().await.unwrap();
+                sizes.push(std::fs::metadata(&path).unwrap().len());
+            }
+            assert!(sizes[1] < sizes[0], "{}: {:?}", name, sizes);
+        }
+
+        for (name, level) in [
+            ("out.csv.gz", "10"),
+            ("out.csv.zst", "0"),
+            ("out.csv.zst", "23"),
+        ] {
+            let options = HashMap::from([("compression.level".to_string(), level.to_string())]);
+            let result = create_target_with_options(name, &options);
+            assert!(
+                matches!(result, Err(TinyEtlError::Configuration(_))),
+                "{} {}",
+                name,
+                level
+            );
+        }
+    }
+
+    #[test]
+    fn test_compressed_paths() {
+        assert!(is_compressed_file("data.csv.gz"));
+        assert!(is_compressed_file("C:\\exports\\data.JSONL.ZST"));
+        assert!(!is_compressed_file("data.csv"));
+        assert!(!is_compressed_file("https://example.com/data.csv.gz"));
+        assert!(is_compressed_file("data.gz"));
+        assert!(decompressed_source("data.gz").is_err());
+
+        let sheet = CompressedPath::parse("sales.xlsx.gz#Q1").unwrap().unwrap();
+        assert_eq!(sheet.inner_extension, "xlsx");
+        assert_eq!(
+            sheet.connection_string(Path::new("/tmp/tmp-1.xlsx")),
+            "/tmp/tmp-1.xlsx#Q1"
+        );
+
+        assert_eq!(
+            staged_extension("/exports/data.csv.gz", Some("gz".to_string())).as_deref(),
+            Some("csv.gz")
+        );
+        assert_eq!(
+            staged_extension("data.csv", Some("csv".to_string())).as_deref(),
+            Some("csv")
+        );
+        assert_eq!(staged_extension("export", None), None);
+    }
+}
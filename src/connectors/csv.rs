@@ -1,23 +1,331 @@
 use async_trait::async_trait;
 use csv::{ReaderBuilder, WriterBuilder};
+use regex::Regex;
 use rust_decimal::Decimal;
 use serde_json;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
 
 use crate::{
-    connectors::{Source, Target},
+    connectors::{estimate_line_count, Source, Target},
     date_parser::DateParser,
-    schema::{Row, Schema, Value},
+    limits::SizeLimits,
+    schema::{BinaryEncoding, Row, Schema, Value},
     Result, TinyEtlError,
 };
 
+/// Separator the csv crate splits on once a multi-character or regex
+/// delimiter has been rewritten (ASCII unit separator)
+const SPLIT_BYTE: u8 = 0x1f;
+
+type RecordReader = csv::Reader<Box<dyn Read + Send + Sync>>;
+
+/// Field delimiter of a CSV source
+#[derive(Debug, Clone)]
+pub enum Delimiter {
+    /// Single byte, handled by the csv crate itself
+    Byte(u8),
+    /// Multi-character separator such as `||` or `~|~`
+    Literal(String),
+    /// Separator matched by a regular expression
+    Pattern(Regex),
+}
+
+impl Delimiter {
+    /// Parse a delimiter spec: a literal separator, `\t` for tab, or
+    /// `regex:<PATTERN>`
+    pub fn parse(spec: &str) -> Result<Self> {
+        if let Some(pattern) = spec.strip_prefix("regex:") {
+            let regex = Regex::new(pattern).map_err(|e| {
+                TinyEtlError::Configuration(format!("Invalid delimiter regex '{}': {}", pattern, e))
+            })?;
+            if regex.is_match("") {
+                return Err(TinyEtlError::Configuration(format!(
+                    "Delimiter regex '{}' must not match an empty string",
+                    pattern
+                )));
+            }
+            return Ok(Self::Pattern(regex));
+        }
+
+        let spec = if spec == "\\t" { "\t" } else { spec };
+        match spec.as_bytes() {
+            [] => Err(TinyEtlError::Configuration(
+                "CSV delimiter must not be empty".to_string(),
+            )),
+            [byte] => Ok(Self::Byte(*byte)),
+            _ => Ok(Self::Literal(spec.to_string())),
+        }
+    }
+}
+
+impl Default for Delimiter {
+    fn default() -> Self {
+        Self::Byte(b',')
+    }
+}
+
+/// Rewrites a multi-character or regex delimiter to `SPLIT_BYTE` line by line,
+/// so the csv crate can do the rest of the parsing
+struct DelimiterSplitter<R> {
+    inner: R,
+    delimiter: Delimiter,
+    line: Vec<u8>,
+    offset: usize,
+    /// Bytes consumed from `inner`, before rewriting
+    consumed: Arc<AtomicU64>,
+    /// Longest line kept; the rest of a longer one is skipped
+    max_line: Option<usize>,
+}
+
+impl<R: BufRead> DelimiterSplitter<R> {
+    fn rewrite_line(&mut self) {
+        // Invalid UTF-8 is passed through for the csv crate to report
+        let Ok(text) = std::str::from_utf8(&self.line) else {
+            return;
+        };
+        // Keep the line ending out of reach of patterns like `\s+`
+        let body = text.trim_end_matches(['\r', '\n']);
+        let ending = &text[body.len()..];
+        let separator = char::from(SPLIT_BYTE).to_string();
+        let rewritten = match &self.delimiter {
+            Delimiter::Byte(_) => return,
+            Delimiter::Literal(delimiter) => body.replace(delimiter.as_str(), &separator),
+            Delimiter::Pattern(regex) => regex.replace_all(body, separator.as_str()).into_owned(),
+        };
+        self.line = format!("{}{}", rewritten, ending).into_bytes();
+    }
+}
+
+impl<R: BufRead> Read for DelimiterSplitter<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.offset >= self.line.len() {
+            self.line.clear();
+            self.offset = 0;
+            let read = read_line(&mut self.inner, &mut self.line, self.max_line)?;
+            if read == 0 {
+                return Ok(0);
+            }
+            self.consumed.fetch_add(read as u64, Ordering::Relaxed);
+            self.rewrite_line();
+        }
+
+        let len = buf.len().min(self.line.len() - self.offset);
+        buf[..len].copy_from_slice(&self.line[self.offset..self.offset + len]);
+        self.offset += len;
+        Ok(len)
+    }
+}
+
+/// Read a line into `line`, keeping at most `max` bytes of it (and its line
+/// break) without buffering the rest. Returns the bytes consumed.
+fn read_line<R: BufRead>(
+    reader: &mut R,
+    line: &mut Vec<u8>,
+    max: Option<usize>,
+) -> std::io::Result<usize> {
+    let Some(max) = max else {
+        return reader.read_until(b'\n', line);
+    };
+    let mut consumed = 0;
+    loop {
+        let available = reader.fill_buf()?;
+        if available.is_empty() {
+            break;
+        }
+        let newline = available.iter().position(|&byte| byte == b'\n');
+        let end = newline.map_or(available.len(), |i| i + 1);
+        let room = max.saturating_sub(line.len()).min(end);
+        line.extend_from_slice(&available[..room]);
+        reader.consume(end);
+        consumed += end;
+        if newline.is_some() {
+            if room < end {
+                // Don't leave half a character behind the cut
+                let valid = std::str::from_utf8(line).map_or_else(|e| e.valid_up_to(), str::len);
+                line.truncate(valid);
+                line.push(b'\n');
+            }
+            break;
+        }
+    }
+    Ok(consumed)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Quoting {
+    Unquoted,
+    Quoted,
+    /// A quote inside a quoted field: the end of the field, or the first
+    /// half of an escaped quote
+    QuoteInQuoted,
+}
+
+/// Passes records through to the csv crate until a field or record goes
+/// over the size limits, then drops the rest of its content, so a
+/// pathological line is never held in memory whole. The part passed through
+/// is still over the limit. Delimiters, quotes and line breaks are kept, so
+/// the records after it parse as before.
+struct SizeLimiter<R> {
+    inner: R,
+    delimiter: u8,
+    limits: SizeLimits,
+    quoting: Quoting,
+    field_start: bool,
+    /// The header is passed through whole
+    in_header: bool,
+    field_bytes: usize,
+    record_bytes: usize,
+    cutting_field: bool,
+    cutting_record: bool,
+    /// Whether the character being read is passed through
+    keeping: bool,
+    out: Vec<u8>,
+    offset: usize,
+    /// Bytes consumed from `inner`, before dropping any
+    consumed: Arc<AtomicU64>,
+}
+
+impl<R: BufRead> SizeLimiter<R> {
+    fn new(inner: R, delimiter: u8, limits: SizeLimits, consumed: Arc<AtomicU64>) -> Self {
+        Self {
+            inner,
+            delimiter,
+            limits,
+            quoting: Quoting::Unquoted,
+            field_start: true,
+            in_header: true,
+            field_bytes: 0,
+            record_bytes: 0,
+            cutting_field: false,
+            cutting_record: false,
+            keeping: true,
+            out: Vec::new(),
+            offset: 0,
+            consumed,
+        }
+    }
+
+    fn end_field(&mut self) {
+        self.field_start = true;
+        self.field_bytes = 0;
+        self.cutting_field = false;
+    }
+
+    fn end_record(&mut self) {
+        self.end_field();
+        self.in_header = false;
+        self.record_bytes = 0;
+        self.cutting_record = false;
+    }
+
+    /// Pass `bytes` of field content, which hold one byte of the value,
+    /// unless the field or record is already over a limit. Content is only
+    /// cut between characters.
+    fn content(&mut self, bytes: &[u8]) {
+        let continuation = bytes[0] & 0xC0 == 0x80;
+        if !continuation {
+            if !self.in_header {
+                let over = |max: Option<usize>, size: usize| max.is_some_and(|max| size > max);
+                self.cutting_field |= over(self.limits.max_field_length, self.field_bytes);
+                self.cutting_record |= over(self.limits.max_row_bytes, self.record_bytes);
+            }
+            self.keeping = !(self.cutting_field || self.cutting_record);
+        }
+        if self.keeping {
+            self.out.extend_from_slice(bytes);
+            self.field_bytes += 1;
+            self.record_bytes += 1;
+        }
+    }
+
+    fn push(&mut self, byte: u8) {
+        match self.quoting {
+            Quoting::Quoted if byte == b'"' => self.quoting = Quoting::QuoteInQuoted,
+            Quoting::Quoted => self.content(&[byte]),
+            Quoting::QuoteInQuoted if byte == b'"' => {
+                self.quoting = Quoting::Quoted;
+                self.content(b"\"\"");
+            }
+            Quoting::QuoteInQuoted => {
+                self.out.push(b'"');
+                self.quoting = Quoting::Unquoted;
+                self.push(byte);
+            }
+            Quoting::Unquoted => {
+                if byte == self.delimiter {
+                    self.out.push(byte);
+                    self.end_field();
+                } else if byte == b'\n' || byte == b'\r' {
+                    self.out.push(byte);
+                    self.end_record();
+                } else if byte == b'"' && self.field_start {
+                    self.out.push(byte);
+                    self.quoting = Quoting::Quoted;
+                    self.field_start = false;
+                } else {
+                    self.field_start = false;
+                    self.content(&[byte]);
+                }
+            }
+        }
+    }
+}
+
+impl<R: BufRead> Read for SizeLimiter<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        while self.offset >= self.out.len() {
+            self.out.clear();
+            self.offset = 0;
+            let available = self.inner.fill_buf()?.to_vec();
+            if available.is_empty() {
+                if self.quoting != Quoting::QuoteInQuoted {
+                    return Ok(0);
+                }
+                // The file ends right after a closing quote
+                self.out.push(b'"');
+                self.quoting = Quoting::Unquoted;
+                break;
+            }
+            self.inner.consume(available.len());
+            self.consumed
+                .fetch_add(available.len() as u64, Ordering::Relaxed);
+            for byte in available {
+                self.push(byte);
+            }
+        }
+
+        let len = buf.len().min(self.out.len() - self.offset);
+        buf[..len].copy_from_slice(&self.out[self.offset..self.offset + len]);
+        self.offset += len;
+        Ok(len)
+    }
+}
+
 pub struct CsvSource {
     file_path: PathBuf,
-    reader: Option<csv::Reader<std::fs::File>>,
+    reader: Option<RecordReader>,
     headers: Vec<String>,
     current_position: u64,
     has_more_data: bool,
+    file_size: u64,
+    /// Lines before the header row (e.g. report banners)
+    skip_rows: usize,
+    /// Records at the end of the file that are not data (e.g. a totals line)
+    skip_footer: usize,
+    /// Bytes of the skipped leading lines
+    skipped_bytes: u64,
+    /// Records read ahead so the last `skip_footer` can be held back
+    lookahead: VecDeque<csv::StringRecord>,
+    delimiter: Delimiter,
+    /// Raw bytes read through a `DelimiterSplitter` or `SizeLimiter`, whose
+    /// output is shorter than the file
+    raw_bytes: Option<Arc<AtomicU64>>,
+    size_limits: SizeLimits,
 }
 
 impl CsvSource {
@@ -28,9 +336,125 @@ impl CsvSource {
             headers: Vec::new(),
             current_position: 0,
             has_more_data: true,
+            file_size: 0,
+            skip_rows: 0,
+            skip_footer: 0,
+            skipped_bytes: 0,
+            lookahead: VecDeque::new(),
+            delimiter: Delimiter::default(),
+            raw_bytes: None,
+            size_limits: SizeLimits::default(),
         })
     }
 
+    pub fn with_delimiter(mut self, delimiter: Delimiter) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    /// Skip `skip_rows` lines before the header and drop the last
+    /// `skip_footer` records
+    pub fn with_skip(mut self, skip_rows: usize, skip_footer: usize) -> Self {
+        self.skip_rows = skip_rows;
+        self.skip_footer = skip_footer;
+        self
+    }
+
+    /// Open the file positioned at the header row
+    fn open_reader(&mut self) -> Result<RecordReader> {
+        let mut file = BufReader::new(File::open(&self.file_path)?);
+        let mut line = Vec::new();
+        self.skipped_bytes = 0;
+        for _ in 0..self.skip_rows {
+            line.clear();
+            self.skipped_bytes += file.read_until(b'\n', &mut line)? as u64;
+        }
+
+        let mut builder = ReaderBuilder::new();
+        builder
+            .has_headers(true)
+            // Footer lines such as totals rarely have every column
+            .flexible(self.skip_footer > 0);
+
+        let (input, split_byte): (Box<dyn BufRead + Send + Sync>, u8) = match &self.delimiter {
+            Delimiter::Byte(byte) => {
+                self.raw_bytes = None;
+                (Box::new(file), *byte)
+            }
+            delimiter => {
+                let consumed = Arc::new(AtomicU64::new(0));
+                self.raw_bytes = Some(consumed.clone());
+                let splitter = DelimiterSplitter {
+                    inner: file,
+                    delimiter: delimiter.clone(),
+                    line: Vec::new(),
+                    offset: 0,
+                    consumed,
+                    // Lines are rewritten whole; leave room for the delimiters
+                    max_line: self
+                        .size_limits
+                        .max_row_bytes
+                        .map(|max| max.saturating_mul(2)),
+                };
+                (Box::new(BufReader::new(splitter)), SPLIT_BYTE)
+            }
+        };
+        builder.delimiter(split_byte);
+
+        if !self.size_limits.is_set() {
+            return Ok(builder.from_reader(Box::new(input)));
+        }
+        let consumed = Arc::new(AtomicU64::new(0));
+        if self.raw_bytes.is_none() {
+            self.raw_bytes = Some(consumed.clone());
+        }
+        let limiter = SizeLimiter::new(input, split_byte, self.size_limits, consumed);
+        Ok(builder.from_reader(Box::new(limiter)))
+    }
+
+    /// Bytes of the file consumed past the skipped leading lines
+    fn data_bytes_read(&self) -> Option<u64> {
+        match &self.raw_bytes {
+            Some(consumed) => Some(consumed.load(Ordering::Relaxed)),
+            None => self.reader.as_ref().map(|reader| reader.position().byte()),
+        }
+    }
+
+    /// Next data record, holding back the last `skip_footer` records of the file
+    fn next_record(&mut self) -> Result<Option<csv::StringRecord>> {
+        let Some(reader) = self.reader.as_mut() else {
+            return Ok(None);
+        };
+        while self.lookahead.len() <= self.skip_footer {
+            let mut record = csv::StringRecord::new();
+            if !reader.read_record(&mut record)? {
+                // Whatever is still buffered is the footer
+                return Ok(None);
+            }
+            self.lookahead.push_back(record);
+        }
+        Ok(self.lookahead.pop_front())
+    }
+
+    fn record_to_row(&self, record: &csv::StringRecord) -> Row {
+        // A record cut short by the size limits stays text, so the limits
+        // still find it oversized
+        let cut =
+            self.size_limits.is_set() && self.size_limits.exceeded_by(record.iter().map(str::len));
+        let mut row = Row::new();
+        for (i, field) in record.iter().enumerate() {
+            if let Some(header) = self.headers.get(i) {
+                let value = if cut && !field.is_empty() {
+                    Value::String(field.to_string())
+                } else {
+                    Self::parse_value(field)
+                };
+                row.insert(header.clone(), value);
+            }
+        }
+        row
+    }
+
     fn infer_schema_with_order(&self, rows: &[Row]) -> Result<Schema> {
         if rows.is_empty() {
             return Ok(Schema {
@@ -79,7 +503,7 @@ impl CsvSource {
         })
     }
 
-    fn parse_value(value: &str) -> Value {
+    pub(crate) fn parse_value(value: &str) -> Value {
         // Try to parse as different types
 
         // Try integer first
@@ -121,15 +545,14 @@ impl Source for CsvSource {
             )));
         }
 
-        let file = std::fs::File::open(&self.file_path)?;
-        let mut reader = ReaderBuilder::new().has_headers(true).from_reader(file);
+        self.file_size = std::fs::metadata(&self.file_path)?.len();
+        let mut reader = self.open_reader()?;
 
         // Read and store headers
         self.headers = reader.headers()?.iter().map(|h| h.to_string()).collect();
 
-        // Reset file for actual reading
-        let file = std::fs::File::open(&self.file_path)?;
-        self.reader = Some(ReaderBuilder::new().has_headers(true).from_reader(file));
+        self.reader = Some(reader);
+        self.lookahead.clear();
 
         Ok(())
     }
@@ -140,26 +563,11 @@ impl Source for CsvSource {
         }
 
         let mut sample_rows = Vec::new();
-        let mut count = 0;
-
-        if let Some(ref mut reader) = self.reader {
-            for result in reader.records() {
-                if count >= sample_size {
-                    break;
-                }
-
-                let record = result?;
-                let mut row = Row::new();
 
-                for (i, field) in record.iter().enumerate() {
-                    if let Some(header) = self.headers.get(i) {
-                        let value = Self::parse_value(field);
-                        row.insert(header.clone(), value);
-                    }
-                }
-
-                sample_rows.push(row);
-                count += 1;
+        while sample_rows.len() < sample_size {
+            match self.next_record()? {
+                Some(record) => sample_rows.push(self.record_to_row(&record)),
+                None => break,
             }
         }
 
@@ -176,32 +584,17 @@ impl Source for CsvSource {
         }
 
         let mut rows = Vec::new();
-        let mut count = 0;
-
-        if let Some(ref mut reader) = self.reader {
-            let mut records_iter = reader.records();
-
-            while count < batch_size {
-                match records_iter.next() {
-                    Some(Ok(record)) => {
-                        let mut row = Row::new();
-
-                        for (i, field) in record.iter().enumerate() {
-                            if let Some(header) = self.headers.get(i) {
-                                let value = Self::parse_value(field);
-                                row.insert(header.clone(), value);
-                            }
-                        }
 
-                        rows.push(row);
-                        count += 1;
-                    }
-                    Some(Err(e)) => return Err(e.into()),
-                    None => {
-                        // No more records available
-                        self.has_more_data = false;
-                        break;
-                    }
+        while rows.len() < batch_size {
+            match self.next_record()? {
+                Some(record) => {
+                    rows.push(self.record_to_row(&record));
+                    self.current_position += 1;
+                }
+                None => {
+                    // No more records available
+                    self.has_more_data = false;
+                    break;
                 }
             }
         }
@@ -210,16 +603,31 @@ impl Source for CsvSource {
     }
 
     async fn estimated_row_count(&self) -> Result<Option<usize>> {
-        // Simple estimation by counting lines (not perfect but fast)
-        let content = std::fs::read_to_string(&self.file_path)?;
-        let line_count = content.lines().count();
-        // Subtract 1 for header if present
-        Ok(Some(line_count.saturating_sub(1)))
+        // Sample average row length against file size instead of scanning the whole file
+        let lines = estimate_line_count(&self.file_path, true)?;
+        let skipped = self.skip_rows + self.skip_footer;
+        Ok(Some(lines.saturating_sub(skipped)))
+    }
+
+    fn refined_row_count(&self) -> Option<usize> {
+        // Extrapolate from the bytes consumed by the rows read so far
+        let bytes_read = self.data_bytes_read()?;
+        if self.current_position == 0 || bytes_read == 0 {
+            return None;
+        }
+        let avg_row_bytes = bytes_read as f64 / self.current_position as f64;
+        let data_bytes = self.file_size.saturating_sub(self.skipped_bytes) as f64;
+        Some((data_bytes / avg_row_bytes).round() as usize)
+    }
+
+    fn bytes_read(&self) -> Option<u64> {
+        self.data_bytes_read()
+            .map(|bytes| self.skipped_bytes + bytes)
     }
 
     async fn reset(&mut self) -> Result<()> {
-        let file = std::fs::File::open(&self.file_path)?;
-        self.reader = Some(ReaderBuilder::new().has_headers(true).from_reader(file));
+        self.reader = Some(self.open_reader()?);
+        self.lookahead.clear();
         self.current_position = 0;
         self.has_more_data = true;
         Ok(())
@@ -228,6 +636,10 @@ impl Source for CsvSource {
     fn has_more(&self) -> bool {
         self.has_more_data && self.reader.is_some()
     }
+
+    fn set_size_limits(&mut self, limits: SizeLimits) {
+        self.size_limits = limits;
+    }
 }
 
 pub struct CsvTarget {
@@ -235,6 +647,7 @@ pub struct CsvTarget {
     writer: Option<csv::Writer<std::fs::File>>,
     headers_written: bool,
     column_order: Vec<String>,
+    binary_encoding: BinaryEncoding,
 }
 
 impl CsvTarget {
@@ -244,9 +657,23 @@ impl CsvTarget {
             writer: None,
             headers_written: false,
             column_order: Vec::new(),
+            binary_encoding: BinaryEncoding::default(),
         })
     }
 
+    /// Write binary values as hex instead of base64
+    pub fn with_binary_encoding(mut self, encoding: BinaryEncoding) -> Self {
+        self.binary_encoding = encoding;
+        self
+    }
+
+    fn field(value: &Value, encoding: BinaryEncoding) -> String {
+        match value {
+            Value::Bytes(b) => encoding.encode(b),
+            value => Self::value_to_string(value),
+        }
+    }
+
     fn value_to_string(value: &Value) -> String {
         match value {
             Value::String(s) => s.clone(),
@@ -255,6 +682,7 @@ impl CsvTarget {
             Value::Boolean(b) => b.to_string(),
             Value::Date(dt) => dt.to_rfc3339(),
             Value::Json(j) => serde_json::to_string(j).unwrap_or_else(|_| "{}".to_string()),
+            Value::Bytes(b) => BinaryEncoding::default().encode(b),
             Value::Null => String::new(),
         }
     }
@@ -263,19 +691,19 @@ impl CsvTarget {
 #[async_trait]
 impl Target for CsvTarget {
     async fn connect(&mut self) -> Result<()> {
-        // Create parent directory if it doesn't exist
+        // Create parent directory if it doesn't exist. The file itself is
+        // created with the table, so an existing one is only replaced once
+        // the engine has decided what to do with it.
         if let Some(parent) = self.file_path.parent() {
             std::fs::create_dir_all(parent)?;
         }
-
-        let file = std::fs::File::create(&self.file_path)?;
-        self.writer = Some(WriterBuilder::new().from_writer(file));
         Ok(())
     }
 
     async fn create_table(&mut self, _table_name: &str, schema: &Schema) -> Result<()> {
         if self.writer.is_none() {
-            self.connect().await?;
+            let file = std::fs::File::create(&self.file_path)?;
+            self.writer = Some(WriterBuilder::new().from_writer(file));
         }
 
         // Store column order from schema
@@ -296,6 +724,7 @@ impl Target for CsvTarget {
         }
 
         let mut written_count = 0;
+        let encoding = self.binary_encoding;
 
         if let Some(ref mut writer) = self.writer {
             for row in rows {
@@ -306,7 +735,7 @@ impl Target for CsvTarget {
                         .iter()
                         .map(|key| {
                             row.get(key)
-                                .map(CsvTarget::value_to_string)
+                                .map(|value| CsvTarget::field(value, encoding))
                                 .unwrap_or_default()
                         })
                         .collect()
@@ -316,7 +745,7 @@ impl Target for CsvTarget {
                     keys.iter()
                         .map(|key| {
                             row.get(key)
-                                .map(CsvTarget::value_to_string)
+                                .map(|value| CsvTarget::field(value, encoding))
                                 .unwrap_or_default()
                         })
                         .collect()
@@ -354,6 +783,10 @@ impl Target for CsvTarget {
         // So we return false to force truncation for existing files
         false
     }
+
+    fn bytes_written(&self) -> Option<u64> {
+        std::fs::metadata(&self.file_path).ok().map(|m| m.len())
+    }
 }
 
 #[cfg(test)]
@@ -474,6 +907,30 @@ mod tests {
         assert_eq!(count, Some(2)); // 2 data rows (excluding header)
     }
 
+    #[tokio::test]
+    async fn test_csv_source_estimated_row_count_by_sampling() {
+        let mut temp_file = NamedTempFile::new().unwrap();
+        writeln!(temp_file, "id,name").unwrap();
+        for i in 0..5000 {
+            writeln!(temp_file, "{},name_{:04}", i % 10, i).unwrap();
+        }
+
+        let mut source = CsvSource::new(temp_file.path().to_str().unwrap()).unwrap();
+        source.connect().await.unwrap();
+
+        let count = source.estimated_row_count().await.unwrap().unwrap();
+        assert!((4900..=5100).contains(&count), "estimate was {}", count);
+
+        assert_eq!(source.refined_row_count(), None);
+        source.read_batch(2000).await.unwrap();
+        let refined = source.refined_row_count().unwrap();
+        assert!(
+            (4900..=5100).contains(&refined),
+            "refined estimate was {}",
+            refined
+        );
+    }
+
     #[tokio::test]
     async fn test_csv_source_has_more() {
         let mut temp_file = NamedTempFile::new().unwrap();
@@ -486,6 +943,87 @@ mod tests {
         assert!(source.has_more());
     }
 
+    #[tokio::test]
+    async fn test_csv_source_skips_banner_and_footer() {
+        let mut temp_file = NamedTempFile::new().unwrap();
+        writeln!(temp_file, "Vendor export").unwrap();
+        writeln!(temp_file, "Generated 2024-01-31, page 1").unwrap();
+        writeln!(temp_file).unwrap();
+        writeln!(temp_file, "id,name,amount").unwrap();
+        writeln!(temp_file, "1,Alice,10").unwrap();
+        writeln!(temp_file, "2,Bob,20").unwrap();
+        writeln!(temp_file, "Total,30").unwrap();
+
+        let mut source = CsvSource::new(temp_file.path().to_str().unwrap())
+            .unwrap()
+            .with_skip(3, 1);
+        source.connect().await.unwrap();
+
+        let schema = source.infer_schema(100).await.unwrap();
+        assert_eq!(schema.columns.len(), 3);
+        assert_eq!(
+            schema.columns[0].data_type,
+            crate::schema::DataType::Integer
+        );
+
+        let rows = source.read_batch(10).await.unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[1].get("name"), Some(&Value::String("Bob".to_string())));
+        assert!(!source.has_more());
+    }
+
+    #[tokio::test]
+    async fn test_csv_source_multi_character_delimiter() {
+        let mut temp_file = NamedTempFile::new().unwrap();
+        writeln!(temp_file, "id~|~name~|~note").unwrap();
+        writeln!(temp_file, "1~|~Alice~|~a|b, c").unwrap();
+        writeln!(temp_file, "2~|~Bob~|~").unwrap();
+
+        let mut source = CsvSource::new(temp_file.path().to_str().unwrap())
+            .unwrap()
+            .with_delimiter(Delimiter::parse("~|~").unwrap());
+        source.connect().await.unwrap();
+
+        let rows = source.read_batch(10).await.unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(
+            rows[0].get("note"),
+            Some(&Value::String("a|b, c".to_string()))
+        );
+        assert_eq!(rows[1].get("note"), Some(&Value::Null));
+    }
+
+    #[tokio::test]
+    async fn test_csv_source_regex_delimiter() {
+        let mut temp_file = NamedTempFile::new().unwrap();
+        writeln!(temp_file, "id | name").unwrap();
+        writeln!(temp_file, "1|  Alice").unwrap();
+
+        let mut source = CsvSource::new(temp_file.path().to_str().unwrap())
+            .unwrap()
+            .with_delimiter(Delimiter::parse(r"regex:\s*\|\s*").unwrap());
+        source.connect().await.unwrap();
+
+        let rows = source.read_batch(10).await.unwrap();
+        assert_eq!(
+            rows[0].get("name"),
+            Some(&Value::String("Alice".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_parse_delimiter() {
+        assert!(matches!(Delimiter::parse("|"), Ok(Delimiter::Byte(b'|'))));
+        assert!(matches!(
+            Delimiter::parse("\\t"),
+            Ok(Delimiter::Byte(b'\t'))
+        ));
+        assert!(matches!(Delimiter::parse("||"), Ok(Delimiter::Literal(_))));
+        assert!(Delimiter::parse("").is_err());
+        assert!(Delimiter::parse("regex:(").is_err());
+        assert!(Delimiter::parse("regex:x*").is_err());
+    }
+
     #[tokio::test]
     async fn test_csv_target_write_batch_without_connection() {
         let temp_file = NamedTempFile::new().unwrap();
@@ -548,6 +1086,10 @@ mod tests {
 
         let dt = chrono::Utc::now();
         assert!(CsvTarget::value_to_string(&Value::Date(dt)).contains("T"));
+
+        let bytes = Value::Bytes(vec![0xca, 0xfe]);
+        assert_eq!(CsvTarget::value_to_string(&bytes), "yv4=");
+        assert_eq!(CsvTarget::field(&bytes, BinaryEncoding::Hex), "cafe");
     }
 
     #[tokio::test]
@@ -565,6 +1107,21 @@ mod tests {
         let mut target = CsvTarget::new(temp_file.path().to_str().unwrap()).unwrap();
         target.connect().await.unwrap();
 
+        // The file is created with the table
+        let schema = crate::schema::Schema {
+            columns: ["string", "integer", "decimal", "boolean", "date", "null"]
+                .iter()
+                .map(|name| crate::schema::Column {
+                    name: name.to_string(),
+                    data_type: crate::schema::DataType::String,
+                    nullable: true,
+                })
+                .collect(),
+            estimated_rows: None,
+            primary_key_candidate: None,
+        };
+        target.create_table("test", &schema).await.unwrap();
+
         let mut row = std::collections::HashMap::new();
         row.insert("string".to_string(), Value::String("test".to_string()));
         row.insert("integer".to_string(), Value::Integer(42));
@@ -683,4 +1240,68 @@ mod tests {
             "Second data row should maintain column order"
         );
     }
+
+    async fn read_limited(content: &str, delimiter: &str, limits: SizeLimits) -> Vec<Row> {
+        let mut temp_file = NamedTempFile::new().unwrap();
+        write!(temp_file, "{}", content).unwrap();
+        let mut source = CsvSource::new(temp_file.path().to_str().unwrap())
+            .unwrap()
+            .with_delimiter(Delimiter::parse(delimiter).unwrap());
+        source.set_size_limits(limits);
+        source.connect().await.unwrap();
+        source.read_batch(100).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_size_limits_cut_oversized_fields_while_reading() {
+        let limits = SizeLimits {
+            max_field_length: Some(5),
+            max_row_bytes: None,
+        };
+        let huge = "x".repeat(1_000_000);
+        let content = format!(
+            "id,description_of_item\n1,{}\n2,\"ab\"\"cd\nefgh\"\n3,héllo wörld\n4,ok\n",
+            huge
+        );
+        let rows = read_limited(&content, ",", limits).await;
+
+        assert_eq!(rows.len(), 4);
+        assert_eq!(
+            rows[0]["description_of_item"],
+            Value::String("xxxxxx".to_string())
+        );
+        // Escaped quotes count once, and the quoted line break is content
+        assert_eq!(
+            rows[1]["description_of_item"],
+            Value::String("ab\"cd\n".to_string())
+        );
+        // Cut between characters, still over the limit
+        assert_eq!(
+            rows[2]["description_of_item"],
+            Value::String("héllo".to_string())
+        );
+        assert_eq!(
+            rows[3]["description_of_item"],
+            Value::String("ok".to_string())
+        );
+        assert_eq!(rows[3]["id"], Value::Integer(4));
+    }
+
+    #[tokio::test]
+    async fn test_size_limits_cut_oversized_records_while_reading() {
+        let limits = SizeLimits {
+            max_field_length: None,
+            max_row_bytes: Some(10),
+        };
+        let content = "a||b||c\n1||abcdefghijklmno||xyz\n2||short||xyz\n";
+        let rows = read_limited(content, "||", limits).await;
+
+        assert_eq!(rows.len(), 2);
+        // The rest of the record is dropped, and what was read stays text
+        assert_eq!(rows[0]["a"], Value::String("1".to_string()));
+        assert_eq!(rows[0]["b"], Value::String("abcdefghij".to_string()));
+        assert_eq!(rows[0]["c"], Value::Null);
+        assert_eq!(rows[1]["a"], Value::Integer(2));
+        assert_eq!(rows[1]["c"], Value::String("xyz".to_string()));
+    }
 }
@@ -0,0 +1,707 @@
+//! dBase / FoxPro (`.dbf`) tables, read-only. Memo fields are read from the
+//! `.dbt` or `.fpt` file next to the table; rows marked deleted are skipped.
+
+use async_trait::async_trait;
+use chrono::{NaiveDate, TimeZone, Utc};
+use rust_decimal::Decimal;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use crate::{
+    connectors::{encoding::Encoding, Source},
+    schema::{Column, DataType, Row, Schema, Value},
+    strict::Coercion,
+    Result, TinyEtlError,
+};
+
+/// Marks the end of the field descriptors in the header
+const HEADER_TERMINATOR: u8 = 0x0D;
+/// Marks the end of the records
+const END_OF_FILE: u8 = 0x1A;
+/// Julian day number of 0001-01-01, the first day chrono counts from
+const JULIAN_DAY_OF_CE: i32 = 1_721_425;
+
+/// Whether a connection string names a dBase / FoxPro table
+pub fn is_dbf_file(connection_string: &str) -> bool {
+    !connection_string.contains("://") && connection_string.to_lowercase().ends_with(".dbf")
+}
+
+/// A field descriptor of the header
+#[derive(Debug, Clone)]
+struct Field {
+    name: String,
+    kind: u8,
+    /// Position in the record, after the deletion flag
+    offset: usize,
+    length: usize,
+    decimals: u8,
+    /// Bit of the Visual FoxPro `_NullFlags` field set when the value is NULL
+    null_bit: Option<usize>,
+    /// Bit set when a varchar is shorter than the field; its length is
+    /// then stored in the last byte
+    varlen_bit: Option<usize>,
+}
+
+#[derive(Debug)]
+struct Header {
+    version: u8,
+    records: usize,
+    header_length: u64,
+    record_length: usize,
+    language_driver: u8,
+    fields: Vec<Field>,
+    /// The hidden Visual FoxPro field holding the NULL bits
+    null_flags: Option<Field>,
+}
+
+impl Header {
+    fn read(reader: &mut impl Read) -> Result<Self> {
+        let mut prefix = [0u8; 32];
+        reader
+            .read_exact(&mut prefix)
+            .map_err(|e| invalid(&format!("header is too short ({})", e)))?;
+        let version = prefix[0];
+        if matches!(version & 0x07, 0x04) {
+            return Err(invalid("dBase 7 tables aren't supported"));
+        }
+        let records = u32::from_le_bytes([prefix[4], prefix[5], prefix[6], prefix[7]]) as usize;
+        let header_length = u16::from_le_bytes([prefix[8], prefix[9]]) as u64;
+        let record_length = u16::from_le_bytes([prefix[10], prefix[11]]) as usize;
+        if header_length < 33 || record_length == 0 {
+            return Err(invalid("header has no fields"));
+        }
+
+        let mut descriptors = vec![0u8; header_length as usize - 32];
+        reader
+            .read_exact(&mut descriptors)
+            .map_err(|e| invalid(&format!("header is too short ({})", e)))?;
+
+        let mut fields = Vec::new();
+        let mut null_flags = None;
+        let mut offset = 1;
+        let mut bits = 0;
+        for descriptor in descriptors.chunks(32) {
+            if descriptor[0] == HEADER_TERMINATOR || descriptor.len() < 32 {
+                break;
+            }
+            let name_end = descriptor[..11].iter().position(|&b| b == 0).unwrap_or(11);
+            let name = String::from_utf8_lossy(&descriptor[..name_end])
+                .trim()
+                .to_string();
+            let kind = descriptor[11].to_ascii_uppercase();
+            let length = descriptor[16] as usize;
+            let mut field = Field {
+                name,
+                kind,
+                offset,
+                length,
+                decimals: descriptor[17],
+                null_bit: None,
+                varlen_bit: None,
+            };
+            offset += length;
+
+            if kind == b'0' {
+                null_flags = Some(field);
+                continue;
+            }
+            // Visual FoxPro numbers the NULL bits of nullable fields, and
+            // the length bits of varchar fields, in field order
+            let flags = descriptor[18];
+            if matches!(kind, b'V' | b'Q') {
+                field.varlen_bit = Some(bits);
+                bits += 1;
+            }
+            if flags & 0x02 != 0 {
+                field.null_bit = Some(bits);
+                bits += 1;
+            }
+            fields.push(field);
+        }
+        if offset > record_length {
+            return Err(invalid("fields are longer than the record"));
+        }
+
+        Ok(Self {
+            version,
+            records,
+            header_length,
+            record_length,
+            language_driver: prefix[29],
+            fields,
+            null_flags,
+        })
+    }
+
+    fn is_visual_foxpro(&self) -> bool {
+        matches!(self.version, 0x30..=0x32)
+    }
+
+    fn has_memo(&self) -> bool {
+        self.fields.iter().any(|field| self.is_memo(field))
+    }
+
+    fn is_memo(&self, field: &Field) -> bool {
+        match field.kind {
+            b'M' | b'G' | b'P' | b'W' => true,
+            b'B' => !self.is_visual_foxpro(),
+            _ => false,
+        }
+    }
+
+    fn data_type(&self, field: &Field) -> DataType {
+        match field.kind {
+            b'C' | b'V' | b'M' => DataType::String,
+            b'N' | b'F' if field.decimals == 0 && field.length <= 18 => DataType::Integer,
+            b'N' | b'F' | b'Y' => DataType::Decimal,
+            b'B' if self.is_visual_foxpro() => DataType::Decimal,
+            b'L' => DataType::Boolean,
+            b'D' => DataType::Date,
+            b'T' => DataType::DateTime,
+            b'I' => DataType::Integer,
+            b'G' | b'P' | b'W' | b'B' | b'Q' => DataType::Binary,
+            _ => DataType::String,
+        }
+    }
+}
+
+/// Encoding named by the language driver byte of the header. Tables without
+/// one (0) are most often Windows-1252.
+fn language_driver_encoding(id: u8) -> Encoding {
+    match id {
+        0x01 => Encoding::Cp437,
+        0x02 => Encoding::Cp850,
+        _ => Encoding::Windows1252,
+    }
+}
+
+fn invalid(problem: &str) -> TinyEtlError {
+    TinyEtlError::DataTransfer(format!("Invalid DBF file: {}", problem))
+}
+
+/// The `.dbt` (dBase) or `.fpt` (FoxPro) file holding the memo values
+struct MemoFile {
+    file: BufReader<File>,
+    format: MemoFormat,
+    block_size: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MemoFormat {
+    /// Text up to an end-of-file marker
+    Dbase3,
+    /// Blocks starting with a signature and the length
+    Dbase4,
+    /// Blocks starting with a type and the big-endian length
+    FoxPro,
+}
+
+impl MemoFile {
+    /// Open the memo file of a table, with the extension in either case
+    fn open(table: &Path, header: &Header) -> Result<Self> {
+        let foxpro = matches!(header.version, 0x30 | 0x31 | 0x32 | 0xF5 | 0xFB);
+        let extension = if foxpro { "fpt" } else { "dbt" };
+        let path = [extension.to_string(), extension.to_uppercase()]
+            .iter()
+            .map(|extension| table.with_extension(extension))
+            .find(|path| path.exists())
+            .ok_or_else(|| {
+                TinyEtlError::Connection(format!(
+                    "Memo file {} of {} not found",
+                    table.with_extension(extension).display(),
+                    table.display()
+                ))
+            })?;
+
+        let mut file = BufReader::new(File::open(&path)?);
+        let mut memo_header = [0u8; 32];
+        file.read_exact(&mut memo_header)
+            .map_err(|e| invalid(&format!("memo file header is too short ({})", e)))?;
+        let (format, block_size) = if foxpro {
+            let size = u16::from_be_bytes([memo_header[6], memo_header[7]]) as u64;
+            (MemoFormat::FoxPro, if size == 0 { 64 } else { size })
+        } else if header.version == 0x83 {
+            (MemoFormat::Dbase3, 512)
+        } else {
+            let size = u16::from_le_bytes([memo_header[20], memo_header[21]]) as u64;
+            (MemoFormat::Dbase4, if size == 0 { 512 } else { size })
+        };
+        Ok(Self {
+            file,
+            format,
+            block_size,
+        })
+    }
+
+    fn read(&mut self, block: u64) -> Result<Vec<u8>> {
+        self.file.seek(SeekFrom::Start(block * self.block_size))?;
+        let mut prefix = [0u8; 8];
+        match self.format {
+            MemoFormat::FoxPro => {
+                self.file.read_exact(&mut prefix)?;
+                let length = u32::from_be_bytes([prefix[4], prefix[5], prefix[6], prefix[7]]);
+                self.read_exact(length as usize)
+            }
+            MemoFormat::Dbase4 => {
+                self.file.read_exact(&mut prefix)?;
+                if prefix[..4] == [0xFF, 0xFF, 0x08, 0x00] {
+                    let length = u32::from_le_bytes([prefix[4], prefix[5], prefix[6], prefix[7]]);
+                    self.read_exact((length as usize).saturating_sub(8))
+                } else {
+                    self.file.seek(SeekFrom::Start(block * self.block_size))?;
+                    self.read_to_terminator()
+                }
+            }
+            MemoFormat::Dbase3 => self.read_to_terminator(),
+        }
+    }
+
+    fn read_exact(&mut self, length: usize) -> Result<Vec<u8>> {
+        let mut value = vec![0u8; length];
+        self.file
+            .read_exact(&mut value)
+            .map_err(|e| invalid(&format!("memo is cut short ({})", e)))?;
+        Ok(value)
+    }
+
+    fn read_to_terminator(&mut self) -> Result<Vec<u8>> {
+        let mut value = Vec::new();
+        let mut block = vec![0u8; self.block_size as usize];
+        loop {
+            let read = self.file.read(&mut block)?;
+            if read == 0 {
+                return Ok(value);
+            }
+            if let Some(end) = block[..read].iter().position(|&b| b == END_OF_FILE) {
+                value.extend_from_slice(&block[..end]);
+                return Ok(value);
+            }
+            value.extend_from_slice(&block[..read]);
+        }
+    }
+}
+
+/// An open table, positioned at the next record
+struct DbfReader {
+    header: Header,
+    records: BufReader<File>,
+    memo: Option<MemoFile>,
+    encoding: Encoding,
+    /// Records read so far, deleted ones included
+    position: usize,
+}
+
+pub struct DbfSource {
+    file_path: PathBuf,
+    /// `encoding` option; the header's language driver otherwise
+    encoding: Option<Encoding>,
+    reader: Option<DbfReader>,
+    coercion: Coercion,
+}
+
+impl DbfSource {
+    pub fn new(file_path: &str, options: &HashMap<String, String>) -> Result<Self> {
+        Ok(Self {
+            file_path: PathBuf::from(file_path),
+            encoding: options
+                .get("encoding")
+                .map(|name| Encoding::parse(name))
+                .transpose()?,
+            reader: None,
+            coercion: Coercion::default(),
+        })
+    }
+
+    fn reader(&mut self) -> Result<&mut DbfReader> {
+        self.reader
+            .as_mut()
+            .ok_or_else(|| TinyEtlError::Connection("DBF file is not open".to_string()))
+    }
+}
+
+impl DbfReader {
+    fn open(path: &Path, encoding: Option<Encoding>) -> Result<Self> {
+        if !path.exists() {
+            return Err(TinyEtlError::Connection(format!(
+                "DBF file not found: {}",
+                path.display()
+            )));
+        }
+        let mut records = BufReader::new(File::open(path)?);
+        let header = Header::read(&mut records)?;
+        let memo = if header.has_memo() {
+            Some(MemoFile::open(path, &header)?)
+        } else {
+            None
+        };
+        let encoding = encoding.unwrap_or_else(|| language_driver_encoding(header.language_driver));
+        records.seek(SeekFrom::Start(header.header_length))?;
+        Ok(Self {
+            header,
+            records,
+            memo,
+            encoding,
+            position: 0,
+        })
+    }
+
+    fn has_more(&self) -> bool {
+        self.position < self.header.records
+    }
+
+    /// The next record that isn't deleted, or `None` at the end of the table
+    fn next_record(&mut self, coercion: Coercion) -> Result<Option<Row>> {
+        let mut record = vec![0u8; self.header.record_length];
+        while self.has_more() {
+            self.position += 1;
+            match self.records.read_exact(&mut record) {
+                Ok(()) => {}
+                // Some writers count records they never wrote
+                Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+                Err(e) => return Err(e.into()),
+            }
+            match record[0] {
+                END_OF_FILE => break,
+                b'*' => continue,
+                _ => return self.decode_record(&record, coercion).map(Some),
+            }
+        }
+        self.position = self.header.records;
+        Ok(None)
+    }
+
+    fn decode_record(&mut self, record: &[u8], coercion: Coercion) -> Result<Row> {
+        let null_flags = self
+            .header
+            .null_flags
+            .as_ref()
+            .map(|field| record[field.offset..field.offset + field.length].to_vec())
+            .unwrap_or_default();
+        let bit_set = |bit: Option<usize>| {
+            bit.is_some_and(|bit| {
+                null_flags
+                    .get(bit / 8)
+                    .is_some_and(|byte| byte & (1 << (bit % 8)) != 0)
+            })
+        };
+
+        let mut row = Row::new();
+        for index in 0..self.header.fields.len() {
+            let field = &self.header.fields[index];
+            let name = field.name.clone();
+            if bit_set(field.null_bit) {
+                row.insert(name, Value::Null);
+                continue;
+            }
+            let mut raw = &record[field.offset..field.offset + field.length];
+            if bit_set(field.varlen_bit) {
+                let length = raw.last().copied().unwrap_or_default() as usize;
+                raw = &raw[..length.min(raw.len())];
+            }
+            let value = self.decode_value(index, raw, coercion)?;
+            row.insert(name, value);
+        }
+        Ok(row)
+    }
+
+    fn decode_value(&mut self, index: usize, raw: &[u8], coercion: Coercion) -> Result<Value> {
+        let field = &self.header.fields[index];
+        let text = || String::from_utf8_lossy(raw).trim().to_string();
+        let column = field.name.as_str();
+
+        if self.header.is_memo(field) {
+            let block = if raw.len() == 4 {
+                u32::from_le_bytes([raw[0], raw[1], raw[2], raw[3]]) as u64
+            } else {
+                text().parse().unwrap_or(0)
+            };
+            if block == 0 {
+                return Ok(Value::Null);
+            }
+            let kind = field.kind;
+            let memo = self
+                .memo
+                .as_mut()
+                .ok_or_else(|| invalid("memo field without a memo file"))?;
+            let bytes = memo.read(block)?;
+            return Ok(match kind {
+                b'M' => Value::String(self.encoding.decode(&bytes)),
+                _ => Value::Bytes(bytes),
+            });
+        }
+
+        Ok(match field.kind {
+            b'C' | b'V' => {
+                let value = self.encoding.decode(raw);
+                Value::String(value.trim_end_matches([' ', '\0']).to_string())
+            }
+            b'N' | b'F' => {
+                let text = text();
+                if text.is_empty() {
+                    Value::Null
+                } else if let (0, Ok(i)) = (field.decimals, text.parse::<i64>()) {
+                    Value::Integer(i)
+                } else if let Ok(d) = text.parse::<Decimal>() {
+                    Value::Decimal(d)
+                } else {
+                    // Numbers too wide for the field are written as `*`s
+                    coercion.lossy(column, &text, "is not a number", Value::Null)?
+                }
+            }
+            b'L' => match raw.first() {
+                Some(b'T' | b't' | b'Y' | b'y') => Value::Boolean(true),
+                Some(b'F' | b'f' | b'N' | b'n') => Value::Boolean(false),
+                _ => Value::Null,
+            },
+            b'D' => {
+                let text = text();
+                if text.is_empty() || text.bytes().all(|b| b == b'0') {
+                    Value::Null
+                } else {
+                    match NaiveDate::parse_from_str(&text, "%Y%m%d") {
+                        Ok(date) => {
+                            Value::Date(Utc.from_utc_datetime(&date.and_hms_opt(0, 0, 0).unwrap()))
+                        }
+                        Err(_) => coercion.lossy(column, &text, "is not a date", Value::Null)?,
+                    }
+                }
+            }
+            b'T' if raw.len() == 8 => {
+                let day = i32::from_le_bytes([raw[0], raw[1], raw[2], raw[3]]);
+                let millis = i32::from_le_bytes([raw[4], raw[5], raw[6], raw[7]]);
+                if day == 0 {
+                    Value::Null
+                } else {
+                    let datetime = NaiveDate::from_num_days_from_ce_opt(day - JULIAN_DAY_OF_CE)
+                        .and_then(|date| date.and_hms_opt(0, 0, 0))
+                        .map(|midnight| {
+                            Utc.from_utc_datetime(&midnight)
+                                + chrono::Duration::milliseconds(millis as i64)
+                        });
+                    match datetime {
+                        Some(datetime) => Value::Date(datetime),
+                        None => coercion.lossy(
+                            column,
+                            &day.to_string(),
+                            "is not a valid day",
+                            Value::Null,
+                        )?,
+                    }
+                }
+            }
+            b'I' if raw.len() == 4 => {
+                Value::Integer(i32::from_le_bytes([raw[0], raw[1], raw[2], raw[3]]) as i64)
+            }
+            b'Y' if raw.len() == 8 => {
+                let units = i64::from_le_bytes(raw.try_into().unwrap_or_default());
+                Value::Decimal(Decimal::new(units, 4))
+            }
+            b'B' if raw.len() == 8 => coercion.decimal_from_f64(
+                f64::from_le_bytes(raw.try_into().unwrap_or_default()),
+                column,
+            )?,
+            b'Q' => Value::Bytes(raw.to_vec()),
+            _ => Value::String(self.encoding.decode(raw).trim().to_string()),
+        })
+    }
+}
+
+#[async_trait]
+impl Source for DbfSource {
+    async fn connect(&mut self) -> Result<()> {
+        self.reader = Some(DbfReader::open(&self.file_path, self.encoding)?);
+        Ok(())
+    }
+
+    async fn infer_schema(&mut self, _sample_size: usize) -> Result<Schema> {
+        if self.reader.is_none() {
+            self.connect().await?;
+        }
+        let header = &self.reader()?.header;
+        // dBase has no NULL marker, but blank numbers and dates read as NULL
+        let columns = header
+            .fields
+            .iter()
+            .map(|field| Column {
+                name: field.name.clone(),
+                data_type: header.data_type(field),
+                nullable: true,
+            })
+            .collect();
+        Ok(Schema {
+            columns,
+            estimated_rows: Some(header.records),
+            primary_key_candidate: None,
+        })
+    }
+
+    async fn read_batch(&mut self, batch_size: usize) -> Result<Vec<Row>> {
+        if self.reader.is_none() {
+            self.connect().await?;
+        }
+        let coercion = self.coercion;
+        let reader = self.reader()?;
+        let mut rows = Vec::new();
+        while rows.len() < batch_size {
+            match reader.next_record(coercion)? {
+                Some(row) => rows.push(row),
+                None => break,
+            }
+        }
+        Ok(rows)
+    }
+
+    async fn estimated_row_count(&self) -> Result<Option<usize>> {
+        Ok(self.reader.as_ref().map(|reader| reader.header.records))
+    }
+
+    fn bytes_read(&self) -> Option<u64> {
+        self.reader.as_ref().map(|reader| {
+            reader.header.header_length + (reader.position * reader.header.record_length) as u64
+        })
+    }
+
+    fn set_coercion(&mut self, coercion: Coercion) {
+        self.coercion = coercion;
+    }
+
+    async fn reset(&mut self) -> Result<()> {
+        if let Some(reader) = self.reader.as_mut() {
+            reader
+                .records
+                .seek(SeekFrom::Start(reader.header.header_length))?;
+            reader.position = 0;
+        }
+        Ok(())
+    }
+
+    fn has_more(&self) -> bool {
+        self.reader
+            .as_ref()
+            .map_or(true, |reader| reader.has_more())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A dBase III table with a memo file, as written by dBase and Clipper
+    fn write_table(dir: &Path) -> PathBuf {
+        let fields: [(&str, u8, u8, u8); 6] = [
+            ("NAME", b'C', 10, 0),
+            ("QTY", b'N', 5, 0),
+            ("PRICE", b'N', 8, 2),
+            ("ACTIVE", b'L', 1, 0),
+            ("SINCE", b'D', 8, 0),
+            ("NOTES", b'M', 10, 0),
+        ];
+        let record_length = 1 + fields.iter().map(|f| f.2 as usize).sum::<usize>();
+        let header_length = 32 + 32 * fields.len() + 1;
+
+        let mut bytes = vec![0x83, 124, 1, 15];
+        bytes.extend_from_slice(&3u32.to_le_bytes());
+        bytes.extend_from_slice(&(header_length as u16).to_le_bytes());
+        bytes.extend_from_slice(&(record_length as u16).to_le_bytes());
+        bytes.resize(29, 0);
+        bytes.push(0x01); // cp437
+        bytes.resize(32, 0);
+        for (name, kind, length, decimals) in fields {
+            let mut descriptor = [0u8; 32];
+            descriptor[..name.len()].copy_from_slice(name.as_bytes());
+            descriptor[11] = kind;
+            descriptor[16] = length;
+            descriptor[17] = decimals;
+            bytes.extend_from_slice(&descriptor);
+        }
+        bytes.push(HEADER_TERMINATOR);
+
+        // "Müller" in cp437
+        let records: [&[u8]; 3] = [
+            b" M\x81ller       42   12.50T20240131         1",
+            b"*Deleted       1    1.00F20240101          ",
+            b" Smith               abc?                  ",
+        ];
+        for record in records {
+            assert_eq!(record.len(), record_length);
+            bytes.extend_from_slice(record);
+        }
+        bytes.push(END_OF_FILE);
+
+        let path = dir.join("CUSTOMER.DBF");
+        std::fs::write(&path, bytes).unwrap();
+
+        let mut memo = vec![0u8; 512];
+        memo.extend_from_slice(b"Prefers email\x1a\x1a");
+        std::fs::write(dir.join("CUSTOMER.DBT"), memo).unwrap();
+        path
+    }
+
+    #[tokio::test]
+    async fn test_read_dbase_table() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_table(dir.path());
+        let mut source = DbfSource::new(&path.to_string_lossy(), &HashMap::new()).unwrap();
+        assert!(is_dbf_file(&path.to_string_lossy()));
+
+        let schema = source.infer_schema(100).await.unwrap();
+        let types: Vec<DataType> = schema.columns.iter().map(|c| c.data_type.clone()).collect();
+        assert_eq!(
+            types,
+            vec![
+                DataType::String,
+                DataType::Integer,
+                DataType::Decimal,
+                DataType::Boolean,
+                DataType::Date,
+                DataType::String,
+            ]
+        );
+
+        let rows = source.read_batch(10).await.unwrap();
+        assert_eq!(rows.len(), 2, "the deleted record is skipped");
+        assert_eq!(rows[0]["NAME"], Value::String("Müller".to_string()));
+        assert_eq!(rows[0]["QTY"], Value::Integer(42));
+        assert_eq!(rows[0]["PRICE"], Value::Decimal(Decimal::new(1250, 2)));
+        assert_eq!(rows[0]["ACTIVE"], Value::Boolean(true));
+        assert_eq!(
+            rows[0]["SINCE"],
+            Value::Date(Utc.with_ymd_and_hms(2024, 1, 31, 0, 0, 0).unwrap())
+        );
+        assert_eq!(rows[0]["NOTES"], Value::String("Prefers email".to_string()));
+
+        // Blank numbers, unknown logicals, blank dates and memos are NULL
+        assert_eq!(rows[1]["QTY"], Value::Null);
+        assert_eq!(rows[1]["ACTIVE"], Value::Null);
+        assert_eq!(rows[1]["SINCE"], Value::Null);
+        assert_eq!(rows[1]["NOTES"], Value::Null);
+        // A malformed number is NULL, or an error with --strict
+        assert_eq!(rows[1]["PRICE"], Value::Null);
+        assert!(!source.has_more());
+
+        source.reset().await.unwrap();
+        source.set_coercion(Coercion::strict());
+        assert_eq!(source.read_batch(1).await.unwrap().len(), 1);
+        assert!(source.read_batch(1).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_encoding_option_and_missing_memo_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_table(dir.path());
+        let options = HashMap::from([("encoding".to_string(), "latin1".to_string())]);
+        let mut source = DbfSource::new(&path.to_string_lossy(), &options).unwrap();
+        let rows = source.read_batch(1).await.unwrap();
+        assert_eq!(rows[0]["NAME"], Value::String("M\u{81}ller".to_string()));
+
+        let bad = HashMap::from([("encoding".to_string(), "ebcdic".to_string())]);
+        assert!(DbfSource::new(&path.to_string_lossy(), &bad).is_err());
+
+        std::fs::remove_file(dir.path().join("CUSTOMER.DBT")).unwrap();
+        let mut source = DbfSource::new(&path.to_string_lossy(), &HashMap::new()).unwrap();
+        let err = source.connect().await.unwrap_err();
+        assert!(err.to_string().contains("Memo file"));
+    }
+}
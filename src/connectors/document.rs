@@ -0,0 +1,430 @@
+//! YAML and TOML data files: server inventories, feature lists and other
+//! configuration kept as data. A list of maps, or a table of maps keyed by
+//! name, becomes one row per entry; nested maps are flattened into
+//! `parent.child` columns and lists are kept as JSON text.
+
+use serde::Deserialize;
+use serde_json::Value as JsonValue;
+use std::collections::HashMap;
+
+use crate::{
+    connectors::{
+        json,
+        record_format::{RecordFormat, Records},
+        vobject,
+    },
+    schema::Row,
+    strict::Coercion,
+    Result, TinyEtlError,
+};
+
+/// Column of the key of each entry of a keyed table, by default
+const DEFAULT_KEY_COLUMN: &str = "key";
+
+/// Column of the entries of a list that aren't maps
+const VALUE_COLUMN: &str = "value";
+
+/// A parsed document, with the keys of its maps in file order
+#[derive(Debug)]
+enum Node {
+    Scalar(JsonValue),
+    List(Vec<Node>),
+    Map(Vec<(String, Node)>),
+}
+
+impl Node {
+    fn from_yaml(value: serde_yaml::Value) -> Self {
+        use serde_yaml::Value as Yaml;
+        match value {
+            Yaml::Null => Node::Scalar(JsonValue::Null),
+            Yaml::Bool(b) => Node::Scalar(JsonValue::Bool(b)),
+            Yaml::Number(n) => Node::Scalar(if let Some(i) = n.as_i64() {
+                i.into()
+            } else if let Some(u) = n.as_u64() {
+                u.into()
+            } else {
+                float(n.as_f64().unwrap_or(f64::NAN))
+            }),
+            Yaml::String(s) => Node::Scalar(JsonValue::String(s)),
+            Yaml::Sequence(items) => Node::List(items.into_iter().map(Self::from_yaml).collect()),
+            Yaml::Mapping(map) => Node::Map(
+                map.into_iter()
+                    .map(|(key, value)| (yaml_key(&key), Self::from_yaml(value)))
+                    .collect(),
+            ),
+            // Custom tags (`!secret`, `!include`) are read as the tagged value
+            Yaml::Tagged(tagged) => Self::from_yaml(tagged.value),
+        }
+    }
+
+    fn from_toml(value: toml::Value) -> Self {
+        use toml::Value as Toml;
+        match value {
+            Toml::String(s) => Node::Scalar(JsonValue::String(s)),
+            Toml::Integer(i) => Node::Scalar(i.into()),
+            Toml::Float(f) => Node::Scalar(float(f)),
+            Toml::Boolean(b) => Node::Scalar(JsonValue::Bool(b)),
+            // Written as RFC 3339, so read as a date like any date text
+            Toml::Datetime(dt) => Node::Scalar(JsonValue::String(dt.to_string())),
+            Toml::Array(items) => Node::List(items.into_iter().map(Self::from_toml).collect()),
+            Toml::Table(table) => Node::Map(
+                table
+                    .into_iter()
+                    .map(|(key, value)| (key, Self::from_toml(value)))
+                    .collect(),
+            ),
+        }
+    }
+
+    fn to_json(&self) -> JsonValue {
+        match self {
+            Node::Scalar(value) => value.clone(),
+            Node::List(items) => JsonValue::Array(items.iter().map(Self::to_json).collect()),
+            Node::Map(entries) => JsonValue::Object(
+                entries
+                    .iter()
+                    .map(|(key, value)| (key.clone(), value.to_json()))
+                    .collect(),
+            ),
+        }
+    }
+
+    fn get(&self, key: &str) -> Option<&Node> {
+        match self {
+            Node::Map(entries) => entries.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    /// The node at a dotted path of keys, such as `inventory.servers`
+    fn find(&self, path: &str) -> Option<&Node> {
+        path.split('.').try_fold(self, |node, key| node.get(key))
+    }
+}
+
+/// A float as a JSON number, or as text when it has none (`.nan`, `inf`)
+fn float(f: f64) -> JsonValue {
+    serde_json::Number::from_f64(f)
+        .map(JsonValue::Number)
+        .unwrap_or_else(|| JsonValue::String(f.to_string()))
+}
+
+/// YAML map keys may be any value; those that aren't text are written out
+fn yaml_key(key: &serde_yaml::Value) -> String {
+    match key {
+        serde_yaml::Value::String(s) => s.clone(),
+        serde_yaml::Value::Number(n) => n.to_string(),
+        serde_yaml::Value::Bool(b) => b.to_string(),
+        serde_yaml::Value::Null => "null".to_string(),
+        other => serde_yaml::to_string(other)
+            .map(|text| text.trim_end().to_string())
+            .unwrap_or_default(),
+    }
+}
+
+/// Turns parsed documents into rows, the same way for YAML and TOML
+struct Flattener<'a> {
+    key_column: &'a str,
+    records: Records,
+}
+
+impl Flattener<'_> {
+    /// Add the rows of a collection: a list, a table of maps keyed by name,
+    /// a map holding nothing but one list, or else a single map
+    fn collection(&mut self, node: &Node) -> Result<()> {
+        match node {
+            Node::List(items) => {
+                for item in items {
+                    self.entry(None, item)?;
+                }
+            }
+            Node::Map(entries) if entries.len() == 1 && matches!(entries[0].1, Node::List(_)) => {
+                self.collection(&entries[0].1)?;
+            }
+            Node::Map(entries)
+                if !entries.is_empty()
+                    && entries
+                        .iter()
+                        .all(|(_, value)| matches!(value, Node::Map(_))) =>
+            {
+                for (key, value) in entries {
+                    self.entry(Some(key), value)?;
+                }
+            }
+            Node::Scalar(JsonValue::Null) => {}
+            _ => self.entry(None, node)?,
+        }
+        Ok(())
+    }
+
+    fn entry(&mut self, key: Option<&str>, node: &Node) -> Result<()> {
+        let mut row = Row::new();
+        if let Some(key) = key {
+            let key = Node::Scalar(JsonValue::String(key.to_string()));
+            self.leaf(&mut row, self.key_column.to_string(), &key)?;
+        }
+        match node {
+            Node::Map(entries) if !entries.is_empty() => {
+                self.flatten(&mut row, "", entries)?;
+            }
+            _ => self.leaf(&mut row, VALUE_COLUMN.to_string(), node)?,
+        }
+        self.records.rows.push(row);
+        Ok(())
+    }
+
+    fn flatten(&mut self, row: &mut Row, prefix: &str, entries: &[(String, Node)]) -> Result<()> {
+        for (key, value) in entries {
+            let column = format!("{}{}", prefix, key);
+            match value {
+                Node::Map(nested) if !nested.is_empty() => {
+                    self.flatten(row, &format!("{}.", column), nested)?;
+                }
+                _ => self.leaf(row, column, value)?,
+            }
+        }
+        Ok(())
+    }
+
+    fn leaf(&mut self, row: &mut Row, column: String, node: &Node) -> Result<()> {
+        let value = json::read_json_value(&column, &node.to_json(), Coercion::default())?;
+        self.records.insert(row, column, value);
+        Ok(())
+    }
+}
+
+/// Flatten the collection at the `root` option of each document, or the
+/// whole document without one
+fn read_documents(
+    documents: &[Node],
+    options: &HashMap<String, String>,
+    kind: &str,
+) -> Result<Records> {
+    let mut flattener = Flattener {
+        key_column: options
+            .get("key_column")
+            .map(String::as_str)
+            .unwrap_or(DEFAULT_KEY_COLUMN),
+        records: Records::default(),
+    };
+    match options.get("root") {
+        Some(root) => {
+            // In a YAML stream, documents without the root are skipped
+            let mut found = false;
+            for node in documents.iter().filter_map(|document| document.find(root)) {
+                found = true;
+                flattener.collection(node)?;
+            }
+            if !found {
+                return Err(TinyEtlError::Configuration(format!(
+                    "No '{}' key in the {} file",
+                    root, kind
+                )));
+            }
+        }
+        None => {
+            for document in documents {
+                flattener.collection(document)?;
+            }
+        }
+    }
+    let mut records = flattener.records;
+    vobject::settle_dates(&mut records);
+    Ok(records)
+}
+
+fn check_key_column(options: &HashMap<String, String>) -> Result<()> {
+    match options.get("key_column") {
+        Some(column) if column.trim().is_empty() => Err(TinyEtlError::Configuration(
+            "key_column can't be empty".to_string(),
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// The built-in `yaml` record format. A file may hold several documents,
+/// separated by `---`.
+pub struct YamlFormat;
+
+impl RecordFormat for YamlFormat {
+    fn extensions(&self) -> &[&str] {
+        &["yaml", "yml"]
+    }
+
+    fn check_options(&self, options: &HashMap<String, String>) -> Result<()> {
+        check_key_column(options)
+    }
+
+    fn read(&self, text: &str, options: &HashMap<String, String>) -> Result<Records> {
+        let mut documents = Vec::new();
+        for document in serde_yaml::Deserializer::from_str(text) {
+            let value = serde_yaml::Value::deserialize(document)
+                .map_err(|e| TinyEtlError::DataValidation(format!("Invalid YAML file: {}", e)))?;
+            documents.push(Node::from_yaml(value));
+        }
+        read_documents(&documents, options, "YAML")
+    }
+}
+
+/// The built-in `toml` record format
+pub struct TomlFormat;
+
+impl RecordFormat for TomlFormat {
+    fn extensions(&self) -> &[&str] {
+        &["toml"]
+    }
+
+    fn check_options(&self, options: &HashMap<String, String>) -> Result<()> {
+        check_key_column(options)
+    }
+
+    fn read(&self, text: &str, options: &HashMap<String, String>) -> Result<Records> {
+        let table: toml::Table = text
+            .parse()
+            .map_err(|e| TinyEtlError::DataValidation(format!("Invalid TOML file: {}", e)))?;
+        let document = Node::from_toml(toml::Value::Table(table));
+        read_documents(&[document], options, "TOML")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::Value;
+    use chrono::{TimeZone, Utc};
+
+    fn options(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    fn text(value: &str) -> Value {
+        Value::String(value.to_string())
+    }
+
+    #[test]
+    fn test_yaml_list_of_maps() {
+        let yaml = "\
+- host: web-1
+  port: 8080
+  tags: [frontend, eu]
+  owner:
+    team: platform
+    contact: ops@example.com
+- host: db-1
+  port: 5432
+  provisioned: 2024-03-01
+";
+        let records = YamlFormat.read(yaml, &HashMap::new()).unwrap();
+        assert_eq!(
+            records.columns,
+            [
+                "host",
+                "port",
+                "tags",
+                "owner.team",
+                "owner.contact",
+                "provisioned"
+            ]
+        );
+        assert_eq!(records.rows.len(), 2);
+        let web = &records.rows[0];
+        assert_eq!(web["port"], Value::Integer(8080));
+        assert_eq!(web["tags"], text(r#"["frontend","eu"]"#));
+        assert_eq!(web["owner.team"], text("platform"));
+        let db = &records.rows[1];
+        assert!(!db.contains_key("owner.team"));
+        let provisioned = Utc.with_ymd_and_hms(2024, 3, 1, 0, 0, 0).unwrap();
+        assert_eq!(db["provisioned"], Value::Date(provisioned));
+    }
+
+    #[test]
+    fn test_yaml_keyed_table_and_root() {
+        let yaml = "\
+version: 2
+services:
+  api:
+    image: api:1.4
+    replicas: 3
+  worker:
+    image: worker:1.4
+";
+        let records = YamlFormat
+            .read(
+                yaml,
+                &options(&[("root", "services"), ("key_column", "name")]),
+            )
+            .unwrap();
+        assert_eq!(records.columns, ["name", "image", "replicas"]);
+        assert_eq!(records.rows[0]["name"], text("api"));
+        assert_eq!(records.rows[1]["name"], text("worker"));
+        assert_eq!(records.rows[0]["replicas"], Value::Integer(3));
+
+        // Without a root, the whole file is one row
+        let records = YamlFormat.read(yaml, &HashMap::new()).unwrap();
+        assert_eq!(records.rows.len(), 1);
+        assert_eq!(records.rows[0]["services.api.image"], text("api:1.4"));
+
+        let err = YamlFormat
+            .read(yaml, &options(&[("root", "services.db")]))
+            .unwrap_err();
+        assert!(err.to_string().contains("No 'services.db' key"));
+    }
+
+    #[test]
+    fn test_yaml_documents_and_scalar_lists() {
+        let yaml = "---\n- name: a\n---\n- name: b\n- c\n";
+        let records = YamlFormat.read(yaml, &HashMap::new()).unwrap();
+        assert_eq!(records.columns, ["name", "value"]);
+        assert_eq!(records.rows.len(), 3);
+        assert_eq!(records.rows[2]["value"], text("c"));
+
+        let err = YamlFormat.read("a: [1, 2", &HashMap::new()).unwrap_err();
+        assert!(err.to_string().contains("Invalid YAML file"));
+    }
+
+    #[test]
+    fn test_toml_array_of_tables() {
+        let toml = r#"
+[[servers]]
+name = "alpha"
+ip = "10.0.0.1"
+weight = 0.5
+
+[servers.limits]
+cpu = 2
+
+[[servers]]
+name = "beta"
+ip = "10.0.0.2"
+since = 2023-11-05T08:00:00Z
+"#;
+        let records = TomlFormat.read(toml, &HashMap::new()).unwrap();
+        assert_eq!(
+            records.columns,
+            ["name", "ip", "weight", "limits.cpu", "since"]
+        );
+        assert_eq!(records.rows.len(), 2);
+        assert_eq!(records.rows[0]["limits.cpu"], Value::Integer(2));
+        let since = Utc.with_ymd_and_hms(2023, 11, 5, 8, 0, 0).unwrap();
+        assert_eq!(records.rows[1]["since"], Value::Date(since));
+    }
+
+    #[test]
+    fn test_toml_keyed_tables() {
+        let toml = "[owner]\nname = \"Ann\"\n\n[databases.main]\nport = 5432\n\n[databases.replica]\nport = 5433\n";
+        let records = TomlFormat
+            .read(toml, &options(&[("root", "databases")]))
+            .unwrap();
+        assert_eq!(records.columns, ["key", "port"]);
+        assert_eq!(records.rows[1]["key"], text("replica"));
+
+        let err = TomlFormat.read("name = ", &HashMap::new()).unwrap_err();
+        assert!(err.to_string().contains("Invalid TOML file"));
+        assert!(TomlFormat
+            .check_options(&options(&[("key_column", " ")]))
+            .is_err());
+    }
+}
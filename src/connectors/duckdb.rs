@@ -1,14 +1,17 @@
 use async_trait::async_trait;
-use base64::{engine::general_purpose, Engine as _};
 use chrono::{DateTime, Utc};
 use duckdb::{types::ValueRef, Connection};
-use rust_decimal::Decimal;
 use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 
 use crate::{
-    connectors::{Source, Target},
+    connectors::{
+        sql_echo,
+        sql_ident::{quote_table, table_schema, unqualified_table, QuoteStyle},
+        Source, Target,
+    },
     schema::{Column as SchemaColumn, DataType, Row, Schema, Value},
+    strict::Coercion,
     Result, TinyEtlError,
 };
 
@@ -19,42 +22,18 @@ fn insert_with_appender(
     rows: &[Row],
     schema: &Schema,
 ) -> Result<usize> {
-    let mut appender = conn
-        .appender(table_name)
+    let table = unqualified_table(table_name);
+    let appender = match table_schema(table_name) {
+        Some(schema) => conn.appender_to_db(&table, &schema),
+        None => conn.appender(&table),
+    };
+    let mut appender = appender
         .map_err(|e| TinyEtlError::DataTransfer(format!("Failed to create appender: {}", e)))?;
 
     // Insert each row using the appender
     for row in rows {
         // Collect values in schema order as owned values
-        let mut row_values: Vec<duckdb::types::Value> = Vec::new();
-
-        for col in &schema.columns {
-            let value = row.get(&col.name).unwrap_or(&Value::Null);
-
-            let duckdb_value = match value {
-                Value::String(s) => duckdb::types::Value::Text(s.clone()),
-                Value::Integer(i) => duckdb::types::Value::BigInt(*i),
-                Value::Decimal(d) => {
-                    // Convert decimal to f64 for DuckDB
-                    let f: f64 = (*d).try_into().unwrap_or(0.0);
-                    duckdb::types::Value::Double(f)
-                }
-                Value::Boolean(b) => duckdb::types::Value::Boolean(*b),
-                Value::Date(dt) => {
-                    // Convert datetime to string for DuckDB
-                    let timestamp_str = dt.to_rfc3339();
-                    duckdb::types::Value::Text(timestamp_str)
-                }
-                Value::Json(j) => {
-                    // DuckDB can store JSON as text or use JSON type
-                    let json_str = serde_json::to_string(j).unwrap_or_else(|_| "{}".to_string());
-                    duckdb::types::Value::Text(json_str)
-                }
-                Value::Null => duckdb::types::Value::Null,
-            };
-
-            row_values.push(duckdb_value);
-        }
+        let row_values = row_values(row, schema);
 
         // Convert to refs for appender
         let params_refs: Vec<&dyn duckdb::types::ToSql> = row_values
@@ -75,6 +54,85 @@ fn insert_with_appender(
     Ok(rows.len())
 }
 
+/// Insert naming the columns, for tables whose columns differ from the
+/// schema (the appender fills every table column in order)
+fn insert_with_columns(
+    conn: &Connection,
+    table_name: &str,
+    rows: &[Row],
+    schema: &Schema,
+) -> Result<usize> {
+    let columns: Vec<String> = schema
+        .columns
+        .iter()
+        .map(|col| format!("\"{}\"", col.name))
+        .collect();
+    let insert_sql = format!(
+        "INSERT INTO {} ({}) VALUES ({})",
+        quote_table(table_name, QuoteStyle::DoubleQuote),
+        columns.join(", "),
+        vec!["?"; columns.len()].join(", ")
+    );
+    let insert_error =
+        |e: duckdb::Error| TinyEtlError::DataTransfer(format!("Failed to insert row: {}", e));
+    sql_echo::echo_rows(
+        &insert_sql,
+        schema.columns.iter().map(|c| c.name.as_str()),
+        rows,
+    );
+
+    conn.execute_batch("BEGIN TRANSACTION")
+        .map_err(insert_error)?;
+    let inserted = (|| {
+        let mut statement = conn.prepare(&insert_sql)?;
+        for row in rows {
+            let row_values = row_values(row, schema);
+            let params_refs: Vec<&dyn duckdb::types::ToSql> = row_values
+                .iter()
+                .map(|v| v as &dyn duckdb::types::ToSql)
+                .collect();
+            statement.execute(params_refs.as_slice())?;
+        }
+        conn.execute_batch("COMMIT")
+    })();
+    if let Err(e) = inserted {
+        let _ = conn.execute_batch("ROLLBACK");
+        return Err(insert_error(e));
+    }
+
+    Ok(rows.len())
+}
+
+/// Values of a row in schema order
+fn row_values(row: &Row, schema: &Schema) -> Vec<duckdb::types::Value> {
+    schema
+        .columns
+        .iter()
+        .map(|col| match row.get(&col.name).unwrap_or(&Value::Null) {
+            Value::String(s) => duckdb::types::Value::Text(s.clone()),
+            Value::Integer(i) => duckdb::types::Value::BigInt(*i),
+            Value::Decimal(d) => {
+                // Convert decimal to f64 for DuckDB
+                let f: f64 = (*d).try_into().unwrap_or(0.0);
+                duckdb::types::Value::Double(f)
+            }
+            Value::Boolean(b) => duckdb::types::Value::Boolean(*b),
+            Value::Date(dt) => {
+                // Convert datetime to string for DuckDB
+                let timestamp_str = dt.to_rfc3339();
+                duckdb::types::Value::Text(timestamp_str)
+            }
+            Value::Json(j) => {
+                // DuckDB can store JSON as text or use JSON type
+                let json_str = serde_json::to_string(j).unwrap_or_else(|_| "{}".to_string());
+                duckdb::types::Value::Text(json_str)
+            }
+            Value::Bytes(b) => duckdb::types::Value::Blob(b.clone()),
+            Value::Null => duckdb::types::Value::Null,
+        })
+        .collect()
+}
+
 /// DuckDB source connector for reading data from DuckDB databases
 pub struct DuckdbSource {
     connection_string: String,
@@ -83,6 +141,7 @@ pub struct DuckdbSource {
     query: Option<String>,
     current_offset: usize,
     total_rows: Option<usize>,
+    coercion: Coercion,
 }
 
 impl DuckdbSource {
@@ -109,6 +168,7 @@ impl DuckdbSource {
             query: None,
             current_offset: 0,
             total_rows: None,
+            coercion: Coercion::default(),
         })
     }
 }
@@ -137,7 +197,10 @@ impl Source for DuckdbSource {
         let conn = conn.lock().unwrap();
 
         // Get table schema using PRAGMA or DESCRIBE
-        let query = format!("DESCRIBE \"{}\"", self.table_name);
+        let query = format!(
+            "DESCRIBE {}",
+            quote_table(&self.table_name, QuoteStyle::DoubleQuote)
+        );
         let mut stmt = conn.prepare(&query).map_err(|e| {
             TinyEtlError::DataTransfer(format!(
                 "Failed to describe table '{}': {}",
@@ -192,7 +255,10 @@ impl Source for DuckdbSource {
         }
 
         // Get estimated row count
-        let count_query = format!("SELECT COUNT(*) FROM \"{}\"", self.table_name);
+        let count_query = format!(
+            "SELECT COUNT(*) FROM {}",
+            quote_table(&self.table_name, QuoteStyle::DoubleQuote)
+        );
         let count: i64 = conn
             .query_row(&count_query, [], |row| row.get(0))
             .map_err(|e| TinyEtlError::DataTransfer(format!("Failed to count rows: {}", e)))?;
@@ -216,8 +282,10 @@ impl Source for DuckdbSource {
 
         // Use LIMIT and OFFSET for proper pagination
         let query = format!(
-            "SELECT * FROM \"{}\" LIMIT {} OFFSET {}",
-            self.table_name, batch_size, self.current_offset
+            "SELECT * FROM {} LIMIT {} OFFSET {}",
+            quote_table(&self.table_name, QuoteStyle::DoubleQuote),
+            batch_size,
+            self.current_offset
         );
 
         let mut stmt = conn
@@ -257,19 +325,13 @@ impl Source for DuckdbSource {
                         ValueRef::SmallInt(n) => Value::Integer(n as i64),
                         ValueRef::Int(n) => Value::Integer(n as i64),
                         ValueRef::BigInt(n) => Value::Integer(n),
-                        ValueRef::HugeInt(n) => Value::Integer(n as i64),
+                        ValueRef::HugeInt(n) => Value::from_wide_integer(n),
                         ValueRef::UTinyInt(n) => Value::Integer(n as i64),
                         ValueRef::USmallInt(n) => Value::Integer(n as i64),
                         ValueRef::UInt(n) => Value::Integer(n as i64),
-                        ValueRef::UBigInt(n) => Value::Integer(n as i64),
-                        ValueRef::Float(f) => match Decimal::try_from(f) {
-                            Ok(d) => Value::Decimal(d),
-                            Err(_) => Value::String(f.to_string()),
-                        },
-                        ValueRef::Double(f) => match Decimal::try_from(f) {
-                            Ok(d) => Value::Decimal(d),
-                            Err(_) => Value::String(f.to_string()),
-                        },
+                        ValueRef::UBigInt(n) => Value::from_wide_integer(n.into()),
+                        ValueRef::Float(f) => self.coercion.decimal_from_f32(f, &column_name)?,
+                        ValueRef::Double(f) => self.coercion.decimal_from_f64(f, &column_name)?,
                         ValueRef::Decimal(d) => {
                             // Convert DuckDB decimal to rust_decimal
                             Value::String(d.to_string())
@@ -283,17 +345,24 @@ impl Source for DuckdbSource {
 
                             match DateTime::<Utc>::from_timestamp(seconds, nanos) {
                                 Some(dt) => Value::String(dt.to_rfc3339()),
-                                None => Value::Null,
+                                None => self.coercion.lossy(
+                                    &column_name,
+                                    &format!("{} (microseconds)", micros),
+                                    "is not a valid timestamp and would be read as NULL",
+                                    Value::Null,
+                                )?,
                             }
                         }
                         ValueRef::Text(bytes) => match std::str::from_utf8(bytes) {
                             Ok(s) => Value::String(s.to_string()),
-                            Err(_) => Value::Null,
+                            Err(_) => self.coercion.lossy(
+                                &column_name,
+                                "of type TEXT",
+                                "is not valid UTF-8 and would be read as NULL",
+                                Value::Null,
+                            )?,
                         },
-                        ValueRef::Blob(bytes) => {
-                            // Convert blob to base64 string
-                            Value::String(general_purpose::STANDARD.encode(bytes))
-                        }
+                        ValueRef::Blob(bytes) => Value::Bytes(bytes.to_vec()),
                         ValueRef::Date32(_) => {
                             // Get as string
                             match row.get::<_, String>(i) {
@@ -301,15 +370,25 @@ impl Source for DuckdbSource {
                                 Err(_) => Value::Null,
                             }
                         }
-                        _ => {
+                        other => {
                             // Fallback: try to get as string
                             match row.get::<_, String>(i) {
                                 Ok(s) => Value::String(s),
-                                Err(_) => Value::Null,
+                                Err(_) => self.coercion.lossy(
+                                    &column_name,
+                                    &format!("of type {:?}", other.data_type()),
+                                    "is not supported and would be read as NULL",
+                                    Value::Null,
+                                )?,
                             }
                         }
                     },
-                    Err(_) => Value::Null,
+                    Err(e) => self.coercion.lossy(
+                        &column_name,
+                        "",
+                        &format!("can't be read ({}) and would be read as NULL", e),
+                        Value::Null,
+                    )?,
                 };
 
                 data_row.insert(column_name, value);
@@ -326,7 +405,10 @@ impl Source for DuckdbSource {
     async fn estimated_row_count(&self) -> Result<Option<usize>> {
         if let Some(conn) = &self.connection {
             let conn = conn.lock().unwrap();
-            let count_query = format!("SELECT COUNT(*) FROM \"{}\"", self.table_name);
+            let count_query = format!(
+                "SELECT COUNT(*) FROM {}",
+                quote_table(&self.table_name, QuoteStyle::DoubleQuote)
+            );
             let count: i64 = conn
                 .query_row(&count_query, [], |row| row.get(0))
                 .map_err(|e| TinyEtlError::DataTransfer(format!("Failed to count rows: {}", e)))?;
@@ -351,6 +433,10 @@ impl Source for DuckdbSource {
             false
         }
     }
+
+    fn set_coercion(&mut self, coercion: Coercion) {
+        self.coercion = coercion;
+    }
 }
 
 /// DuckDB target connector for writing data to DuckDB databases
@@ -359,6 +445,9 @@ pub struct DuckdbTarget {
     connection: Option<Arc<Mutex<Connection>>>,
     table_name: String,
     schema: Option<Schema>, // Cache the schema to avoid re-inference
+    /// Whether the table's columns are exactly the schema's, in order, so
+    /// the appender can be used
+    matches_schema: bool,
 }
 
 impl DuckdbTarget {
@@ -386,6 +475,7 @@ impl DuckdbTarget {
             connection: None,
             table_name: table.to_string(),
             schema: None,
+            matches_schema: true,
         })
     }
 
@@ -393,6 +483,31 @@ impl DuckdbTarget {
         Ok(PathBuf::from(&self.connection_string))
     }
 
+    fn table_columns(conn: &Connection, table_name: &str) -> Result<Vec<String>> {
+        let columns_error = |e: duckdb::Error| {
+            TinyEtlError::DataTransfer(format!(
+                "Failed to read columns of table '{}': {}",
+                table_name, e
+            ))
+        };
+        let mut statement = conn
+            .prepare(
+                "SELECT column_name FROM information_schema.columns \
+                 WHERE table_name = ? AND table_schema = COALESCE(?, current_schema()) \
+                 ORDER BY ordinal_position",
+            )
+            .map_err(columns_error)?;
+        let columns = statement
+            .query_map(
+                duckdb::params![unqualified_table(table_name), table_schema(table_name)],
+                |row| row.get::<_, String>(0),
+            )
+            .map_err(columns_error)?
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(columns_error)?;
+        Ok(columns)
+    }
+
     fn map_data_type_to_duckdb(&self, data_type: &DataType) -> &'static str {
         match data_type {
             DataType::Integer => "BIGINT",
@@ -401,6 +516,7 @@ impl DuckdbTarget {
             DataType::Boolean => "BOOLEAN",
             DataType::Date => "DATE",
             DataType::DateTime => "TIMESTAMP",
+            DataType::Binary => "BLOB",
             DataType::Json => "JSON", // DuckDB has native JSON type
             DataType::Null => "VARCHAR",
         }
@@ -463,14 +579,22 @@ impl Target for DuckdbTarget {
 
         // Use CREATE TABLE IF NOT EXISTS to support append-first philosophy
         let create_sql = format!(
-            "CREATE TABLE IF NOT EXISTS \"{}\" ({})",
-            actual_table_name,
+            "CREATE TABLE IF NOT EXISTS {} ({})",
+            quote_table(&actual_table_name, QuoteStyle::DoubleQuote),
             column_definitions.join(", ")
         );
 
+        sql_echo::echo(&create_sql);
         conn.execute(&create_sql, [])
             .map_err(|e| TinyEtlError::DataTransfer(format!("Failed to create table: {}", e)))?;
 
+        // An existing table may have extra columns, such as an id filled from
+        // a sequence, or the same columns in another order
+        let table_columns = Self::table_columns(&conn, &actual_table_name)?;
+        self.matches_schema = table_columns
+            .iter()
+            .eq(schema.columns.iter().map(|col| &col.name));
+
         Ok(())
     }
 
@@ -498,7 +622,11 @@ impl Target for DuckdbTarget {
         };
 
         // Use DuckDB's high-performance Appender API for batch insertion
-        let affected = insert_with_appender(&conn, &self.table_name, rows, &schema)?;
+        let affected = if self.matches_schema {
+            insert_with_appender(&conn, &self.table_name, rows, &schema)?
+        } else {
+            insert_with_columns(&conn, &self.table_name, rows, &schema)?
+        };
 
         Ok(affected)
     }
@@ -517,8 +645,16 @@ impl Target for DuckdbTarget {
                 table_name
             };
 
-            let query = "SELECT table_name FROM information_schema.tables WHERE table_name = ?";
-            let result = conn.query_row(query, [actual_table_name], |_| Ok(true));
+            let query = "SELECT table_name FROM information_schema.tables \
+                         WHERE table_name = ? AND table_schema = COALESCE(?, current_schema())";
+            let result = conn.query_row(
+                query,
+                duckdb::params![
+                    unqualified_table(actual_table_name),
+                    table_schema(actual_table_name)
+                ],
+                |_| Ok(true),
+            );
 
             Ok(result.unwrap_or(false))
         } else {
@@ -535,10 +671,34 @@ impl Target for DuckdbTarget {
                 table_name
             };
 
-            conn.execute(&format!("DELETE FROM \"{}\"", actual_table_name), [])
-                .map_err(|e| {
-                    TinyEtlError::DataTransfer(format!("Failed to truncate table: {}", e))
-                })?;
+            let delete_sql = format!(
+                "DELETE FROM {}",
+                quote_table(actual_table_name, QuoteStyle::DoubleQuote)
+            );
+            sql_echo::echo(&delete_sql);
+            conn.execute(&delete_sql, []).map_err(|e| {
+                TinyEtlError::DataTransfer(format!("Failed to truncate table: {}", e))
+            })?;
+        }
+        Ok(())
+    }
+
+    async fn drop_table(&mut self, table_name: &str) -> Result<()> {
+        if let Some(conn) = &self.connection {
+            let conn = conn.lock().unwrap();
+            let actual_table_name = if table_name.is_empty() {
+                &self.table_name
+            } else {
+                table_name
+            };
+
+            let drop_sql = format!(
+                "DROP TABLE IF EXISTS {}",
+                quote_table(actual_table_name, QuoteStyle::DoubleQuote)
+            );
+            sql_echo::echo(&drop_sql);
+            conn.execute(&drop_sql, [])
+                .map_err(|e| TinyEtlError::DataTransfer(format!("Failed to drop table: {}", e)))?;
         }
         Ok(())
     }
@@ -573,4 +733,42 @@ mod tests {
         let target2 = DuckdbTarget::new("test.duckdb");
         assert!(target2.is_ok());
     }
+
+    #[tokio::test]
+    async fn test_append_to_table_with_identity_column() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.duckdb");
+        {
+            let conn = Connection::open(&path).unwrap();
+            conn.execute_batch(
+                "CREATE SEQUENCE user_ids; \
+                 CREATE TABLE users (id BIGINT DEFAULT nextval('user_ids'), name VARCHAR);",
+            )
+            .unwrap();
+        }
+
+        let mut target = DuckdbTarget::new(&format!("{}#users", path.display())).unwrap();
+        let schema = Schema {
+            columns: vec![SchemaColumn {
+                name: "name".to_string(),
+                data_type: DataType::String,
+                nullable: true,
+            }],
+            estimated_rows: None,
+            primary_key_candidate: None,
+        };
+        target.connect().await.unwrap();
+        target.create_table("users", &schema).await.unwrap();
+        let rows: Vec<Row> = ["alice", "bob"]
+            .iter()
+            .map(|name| Row::from([("name".to_string(), Value::String(name.to_string()))]))
+            .collect();
+        assert_eq!(target.write_batch(&rows).await.unwrap(), 2);
+
+        let conn = target.connection.as_ref().unwrap().lock().unwrap();
+        let numbered: i64 = conn
+            .query_row("SELECT COUNT(id) FROM users", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(numbered, 2);
+    }
 }
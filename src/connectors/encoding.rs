@@ -0,0 +1,97 @@
+//! Single-byte code pages of legacy data files (dBase tables, SPSS and Stata
+//! files), decoded without an encoding library
+
+use crate::{Result, TinyEtlError};
+
+/// Characters 0x80-0xFF of the DOS code pages
+const CP437_HIGH: &str = "ÇüéâäàåçêëèïîìÄÅÉæÆôöòûùÿÖÜ¢£¥₧ƒáíóúñÑªº¿⌐¬½¼¡«»░▒▓│┤╡╢╖╕╣║╗╝╜╛┐└┴┬├─┼╞╟╚╔╩╦╠═╬╧╨╤╥╙╘╒╓╫╪┘┌█▄▌▐▀αßΓπΣσµτΦΘΩδ∞φε∩≡±≥≤⌠⌡÷≈°∙·√ⁿ²■\u{a0}";
+const CP850_HIGH: &str = "ÇüéâäàåçêëèïîìÄÅÉæÆôöòûùÿÖÜø£Ø×ƒáíóúñÑªº¿®¬½¼¡«»░▒▓│┤ÁÂÀ©╣║╗╝¢¥┐└┴┬├─┼ãÃ╚╔╩╦╠═╬¤ðÐÊËÈıÍÎÏ┘┌█▄¦Ì▀ÓßÔÒõÕµþÞÚÛÙýÝ¯´\u{ad}±‗¾¶§÷¸°¨·¹³²■\u{a0}";
+/// Characters 0x80-0x9F of Windows-1252; the rest match Latin-1
+const CP1252_C1: &str = "€\u{81}‚ƒ„…†‡ˆ‰Š‹Œ\u{8d}Ž\u{8f}\u{90}‘’“”•–—˜™š›œ\u{9d}žŸ";
+
+/// Character encoding of the text in a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Utf8,
+    Latin1,
+    Windows1252,
+    Cp437,
+    Cp850,
+}
+
+impl Encoding {
+    /// The `encoding` source option
+    pub fn parse(name: &str) -> Result<Self> {
+        match name.to_lowercase().replace('_', "-").as_str() {
+            "utf-8" | "utf8" => Ok(Encoding::Utf8),
+            "latin1" | "latin-1" | "iso-8859-1" => Ok(Encoding::Latin1),
+            "cp1252" | "windows-1252" => Ok(Encoding::Windows1252),
+            "cp437" | "ibm437" => Ok(Encoding::Cp437),
+            "cp850" | "ibm850" => Ok(Encoding::Cp850),
+            _ => Err(TinyEtlError::Configuration(format!(
+                "Unknown encoding '{}'. Valid encodings: utf-8, latin1, cp1252, cp437, cp850",
+                name
+            ))),
+        }
+    }
+
+    /// Encoding of a Windows code page number, if it is one of these
+    pub fn from_code_page(code_page: i32) -> Option<Self> {
+        match code_page {
+            65001 => Some(Encoding::Utf8),
+            28591 => Some(Encoding::Latin1),
+            1252 => Some(Encoding::Windows1252),
+            437 => Some(Encoding::Cp437),
+            850 => Some(Encoding::Cp850),
+            _ => None,
+        }
+    }
+
+    pub fn decode(&self, bytes: &[u8]) -> String {
+        let high = |table: &str, b: u8| table.chars().nth(b as usize - 0x80).unwrap_or('\u{fffd}');
+        match self {
+            Encoding::Utf8 => String::from_utf8_lossy(bytes).into_owned(),
+            Encoding::Latin1 => bytes.iter().map(|&b| char::from(b)).collect(),
+            Encoding::Windows1252 => bytes
+                .iter()
+                .map(|&b| match b {
+                    0x80..=0x9F => high(CP1252_C1, b),
+                    _ => char::from(b),
+                })
+                .collect(),
+            Encoding::Cp437 | Encoding::Cp850 => {
+                let table = if *self == Encoding::Cp437 {
+                    CP437_HIGH
+                } else {
+                    CP850_HIGH
+                };
+                bytes
+                    .iter()
+                    .map(|&b| {
+                        if b < 0x80 {
+                            char::from(b)
+                        } else {
+                            high(table, b)
+                        }
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_code_pages() {
+        assert_eq!(CP437_HIGH.chars().count(), 128);
+        assert_eq!(CP850_HIGH.chars().count(), 128);
+        assert_eq!(CP1252_C1.chars().count(), 32);
+        assert_eq!(Encoding::Windows1252.decode(b"\x80 caf\xe9"), "€ café");
+        assert_eq!(Encoding::Cp850.decode(b"\x9d"), "Ø");
+        assert_eq!(Encoding::from_code_page(65001), Some(Encoding::Utf8));
+        assert!(Encoding::parse("ebcdic").is_err());
+    }
+}
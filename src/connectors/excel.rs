@@ -0,0 +1,547 @@
+use async_trait::async_trait;
+use calamine::{open_workbook_auto, Data, Reader};
+use rust_xlsxwriter::{Format, Workbook};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+
+use crate::{
+    connectors::{Source, Target},
+    date_parser::DateParser,
+    schema::{BinaryEncoding, Column, DataType, Row, Schema, SchemaInferer, Value},
+    strict::Coercion,
+    Result, TinyEtlError,
+};
+
+/// Rows searched for the header row
+const HEADER_SCAN_ROWS: usize = 20;
+/// Largest integer Excel stores exactly; larger ones are written as text
+const MAX_EXACT_INTEGER: i64 = 1 << 53;
+/// Name of the sheet written when the target names none
+const DEFAULT_SHEET: &str = "Sheet1";
+
+/// Whether a connection string names a workbook Excel or LibreOffice can
+/// read (`report.xlsx`, or `report.xlsx#Sheet1` with the sheet name)
+pub fn is_excel_file(connection_string: &str) -> bool {
+    let path = split_sheet(connection_string).0;
+    !connection_string.contains("://")
+        && [".xlsx", ".xlsm", ".xlsb", ".xls", ".ods"]
+            .iter()
+            .any(|extension| path.ends_with(extension))
+}
+
+/// Whether a connection string names a workbook TinyETL can write
+pub fn is_xlsx_file(connection_string: &str) -> bool {
+    !connection_string.contains("://") && split_sheet(connection_string).0.ends_with(".xlsx")
+}
+
+/// The file path and the sheet name after `#`, if any
+fn split_sheet(connection_string: &str) -> (&str, Option<&str>) {
+    match connection_string.rsplit_once('#') {
+        Some((path, sheet)) => (path, Some(sheet)),
+        None => (connection_string, None),
+    }
+}
+
+/// Index of the header row among the first rows of a sheet: the first row
+/// whose filled cells all hold text and that fills at least half as many
+/// cells as the widest of the first rows. Titles and notes above a table
+/// rarely fill that many cells.
+fn detect_header_row(rows: &[Vec<Data>]) -> Option<usize> {
+    let filled = |row: &Vec<Data>| row.iter().filter(|cell| !is_blank(cell)).count();
+    let scanned = &rows[..rows.len().min(HEADER_SCAN_ROWS)];
+    let widest = scanned.iter().map(filled).max().unwrap_or(0);
+    scanned.iter().position(|row| {
+        filled(row) > 0
+            && filled(row) * 2 >= widest
+            && row
+                .iter()
+                .all(|cell| is_blank(cell) || matches!(cell, Data::String(_)))
+    })
+}
+
+fn is_blank(cell: &Data) -> bool {
+    match cell {
+        Data::Empty => true,
+        Data::String(s) => s.trim().is_empty(),
+        _ => false,
+    }
+}
+
+/// Column names from the header row: blank cells become `column_<n>` and
+/// repeated names get a `_2`, `_3`, ... suffix
+fn column_names(header: Option<&[Data]>, width: usize) -> Vec<String> {
+    let mut seen = HashSet::new();
+    (0..width)
+        .map(|index| {
+            let name = match header.and_then(|header| header.get(index)) {
+                Some(cell) if !is_blank(cell) => cell.to_string().trim().to_string(),
+                _ => format!("column_{}", index + 1),
+            };
+            let mut unique = name.clone();
+            let mut suffix = 2;
+            while !seen.insert(unique.clone()) {
+                unique = format!("{}_{}", name, suffix);
+                suffix += 1;
+            }
+            unique
+        })
+        .collect()
+}
+
+/// Source reading one sheet of a workbook (the first one unless named
+/// after `#` or with the `sheet` option). The header row is found by
+/// `detect_header_row` unless the `header_row` option gives its row number
+/// (0 for a sheet without one).
+pub struct ExcelSource {
+    file_path: PathBuf,
+    sheet: Option<String>,
+    header_row: Option<usize>,
+    columns: Vec<String>,
+    data: Vec<Vec<Data>>,
+    current_index: usize,
+    coercion: Coercion,
+}
+
+impl ExcelSource {
+    pub fn new(connection_string: &str, options: &HashMap<String, String>) -> Result<Self> {
+        let (path, sheet) = split_sheet(connection_string);
+        let header_row = options
+            .get("header_row")
+            .map(|row| {
+                row.parse::<usize>().map_err(|_| {
+                    TinyEtlError::Configuration(format!(
+                        "Invalid header_row '{}': must be a row number, or 0 for none",
+                        row
+                    ))
+                })
+            })
+            .transpose()?;
+
+        Ok(Self {
+            file_path: PathBuf::from(path),
+            sheet: sheet
+                .or(options.get("sheet").map(String::as_str))
+                .map(str::to_string),
+            header_row,
+            columns: Vec::new(),
+            data: Vec::new(),
+            current_index: 0,
+            coercion: Coercion::default(),
+        })
+    }
+
+    fn cell_to_value(&self, column: &str, cell: &Data) -> Result<Value> {
+        Ok(match cell {
+            Data::Empty => Value::Null,
+            Data::String(s) if s.trim().is_empty() => Value::Null,
+            Data::String(s) => Value::String(s.clone()),
+            Data::Bool(b) => Value::Boolean(*b),
+            Data::Int(i) => Value::Integer(*i),
+            // Excel keeps every number as a float; whole ones are read as integers
+            Data::Float(f) if f.fract() == 0.0 && f.abs() < MAX_EXACT_INTEGER as f64 => {
+                Value::Integer(*f as i64)
+            }
+            Data::Float(f) => self.coercion.decimal_from_f64(*f, column)?,
+            Data::DateTime(dt) if !dt.is_duration() => match dt.as_datetime() {
+                Some(dt) => Value::Date(dt.and_utc()),
+                None => self.coercion.lossy(
+                    column,
+                    &dt.as_f64().to_string(),
+                    "is not a valid date and would be read as NULL",
+                    Value::Null,
+                )?,
+            },
+            // Durations
+            Data::DateTime(dt) => Value::String(dt.as_f64().to_string()),
+            Data::DateTimeIso(s) => DateParser::try_parse(s).unwrap_or(Value::String(s.clone())),
+            Data::DurationIso(s) => Value::String(s.clone()),
+            Data::Error(e) => self.coercion.lossy(
+                column,
+                &e.to_string(),
+                "is an Excel error and would be read as NULL",
+                Value::Null,
+            )?,
+        })
+    }
+
+    fn row_at(&self, index: usize) -> Result<Row> {
+        let cells = &self.data[index];
+        let mut row = Row::new();
+        for (position, column) in self.columns.iter().enumerate() {
+            let value = match cells.get(position) {
+                Some(cell) => self.cell_to_value(column, cell)?,
+                None => Value::Null,
+            };
+            row.insert(column.clone(), value);
+        }
+        Ok(row)
+    }
+}
+
+#[async_trait]
+impl Source for ExcelSource {
+    async fn connect(&mut self) -> Result<()> {
+        if !self.file_path.exists() {
+            return Err(TinyEtlError::Connection(format!(
+                "Excel file not found: {}",
+                self.file_path.display()
+            )));
+        }
+
+        let mut workbook = open_workbook_auto(&self.file_path).map_err(|e| {
+            TinyEtlError::Connection(format!(
+                "Failed to open workbook {}: {}",
+                self.file_path.display(),
+                e
+            ))
+        })?;
+        let sheet_names = workbook.sheet_names();
+        let sheet = match &self.sheet {
+            Some(sheet) if sheet_names.contains(sheet) => sheet.clone(),
+            Some(sheet) => {
+                return Err(TinyEtlError::Configuration(format!(
+                    "Sheet '{}' is not in {}. Sheets: {}",
+                    sheet,
+                    self.file_path.display(),
+                    sheet_names.join(", ")
+                )))
+            }
+            None => sheet_names.first().cloned().ok_or_else(|| {
+                TinyEtlError::Configuration(format!("{} has no sheets", self.file_path.display()))
+            })?,
+        };
+        let range = workbook.worksheet_range(&sheet).map_err(|e| {
+            TinyEtlError::DataTransfer(format!("Failed to read sheet '{}': {}", sheet, e))
+        })?;
+
+        // The range starts at the first filled cell; header_row counts from
+        // the top of the sheet like Excel's row numbers
+        let first_row = range.start().map_or(0, |(row, _)| row as usize);
+        let rows: Vec<Vec<Data>> = range.rows().map(|row| row.to_vec()).collect();
+        let header = match self.header_row {
+            Some(0) => None,
+            Some(row) => Some(row.checked_sub(first_row + 1).ok_or_else(|| {
+                TinyEtlError::Configuration(format!(
+                    "header_row {} is above the first filled row of sheet '{}' ({})",
+                    row,
+                    sheet,
+                    first_row + 1
+                ))
+            })?),
+            None => detect_header_row(&rows),
+        };
+
+        let width = rows.iter().map(Vec::len).max().unwrap_or(0);
+        self.columns = column_names(header.and_then(|h| rows.get(h)).map(Vec::as_slice), width);
+        let data_start = header.map_or(0, |h| h + 1);
+        self.data = rows
+            .into_iter()
+            .skip(data_start)
+            .filter(|row| !row.iter().all(is_blank))
+            .collect();
+        self.current_index = 0;
+        Ok(())
+    }
+
+    async fn infer_schema(&mut self, sample_size: usize) -> Result<Schema> {
+        let sample = (0..self.data.len().min(sample_size))
+            .map(|index| self.row_at(index))
+            .collect::<Result<Vec<_>>>()?;
+
+        let columns = self
+            .columns
+            .iter()
+            .map(|name| {
+                let types: Vec<DataType> = sample
+                    .iter()
+                    .map(|row| SchemaInferer::infer_type(&row[name]))
+                    .collect();
+                // Whole numbers are read as integers, so a column mixing
+                // them with fractions is a decimal column
+                let numeric = types
+                    .iter()
+                    .all(|t| matches!(t, DataType::Integer | DataType::Decimal | DataType::Null));
+                let (data_type, nullable) = if numeric && types.contains(&DataType::Decimal) {
+                    (DataType::Decimal, true)
+                } else {
+                    SchemaInferer::resolve_column_type(&types)
+                };
+                Column {
+                    name: name.clone(),
+                    data_type,
+                    nullable,
+                }
+            })
+            .collect();
+
+        Ok(Schema {
+            columns,
+            estimated_rows: Some(self.data.len()),
+            primary_key_candidate: None,
+        })
+    }
+
+    async fn read_batch(&mut self, batch_size: usize) -> Result<Vec<Row>> {
+        let end_index = (self.current_index + batch_size).min(self.data.len());
+        let rows = (self.current_index..end_index)
+            .map(|index| self.row_at(index))
+            .collect::<Result<Vec<_>>>()?;
+        self.current_index = end_index;
+        Ok(rows)
+    }
+
+    async fn estimated_row_count(&self) -> Result<Option<usize>> {
+        Ok(Some(self.data.len()))
+    }
+
+    fn set_coercion(&mut self, coercion: Coercion) {
+        self.coercion = coercion;
+    }
+
+    async fn reset(&mut self) -> Result<()> {
+        self.current_index = 0;
+        Ok(())
+    }
+
+    fn has_more(&self) -> bool {
+        self.current_index < self.data.len()
+    }
+}
+
+/// Target writing a workbook with one sheet (`Sheet1` unless named after
+/// `#`): a bold header row, then the rows. The workbook is written in
+/// `finalize`, replacing any existing file.
+pub struct ExcelTarget {
+    file_path: PathBuf,
+    sheet: String,
+    schema: Option<Schema>,
+    buffered_rows: Vec<Row>,
+    binary_encoding: BinaryEncoding,
+}
+
+impl ExcelTarget {
+    pub fn new(connection_string: &str) -> Result<Self> {
+        let (path, sheet) = split_sheet(connection_string);
+        Ok(Self {
+            file_path: PathBuf::from(path),
+            sheet: sheet.unwrap_or(DEFAULT_SHEET).to_string(),
+            schema: None,
+            buffered_rows: Vec::new(),
+            binary_encoding: BinaryEncoding::default(),
+        })
+    }
+
+    /// Write binary values as hex instead of base64
+    pub fn with_binary_encoding(mut self, encoding: BinaryEncoding) -> Self {
+        self.binary_encoding = encoding;
+        self
+    }
+
+    fn write_workbook(
+        &self,
+        schema: &Schema,
+    ) -> std::result::Result<(), rust_xlsxwriter::XlsxError> {
+        let mut workbook = Workbook::new();
+        let worksheet = workbook.add_worksheet().set_name(&self.sheet)?;
+        let header_format = Format::new().set_bold();
+        let date_format = Format::new().set_num_format("yyyy-mm-dd hh:mm:ss");
+
+        for (col, column) in schema.columns.iter().enumerate() {
+            worksheet.write_string_with_format(0, col as u16, &column.name, &header_format)?;
+        }
+        for (index, row) in self.buffered_rows.iter().enumerate() {
+            let row_number = index as u32 + 1;
+            for (col, column) in schema.columns.iter().enumerate() {
+                let col = col as u16;
+                match row.get(&column.name).unwrap_or(&Value::Null) {
+                    Value::Null => {}
+                    Value::String(s) => {
+                        worksheet.write_string(row_number, col, s)?;
+                    }
+                    Value::Integer(i) if i.unsigned_abs() <= MAX_EXACT_INTEGER as u64 => {
+                        worksheet.write_number(row_number, col, *i as f64)?;
+                    }
+                    Value::Integer(i) => {
+                        worksheet.write_string(row_number, col, i.to_string())?;
+                    }
+                    Value::Decimal(d) => {
+                        match f64::try_from(*d) {
+                            Ok(f) => worksheet.write_number(row_number, col, f)?,
+                            Err(_) => worksheet.write_string(row_number, col, d.to_string())?,
+                        };
+                    }
+                    Value::Boolean(b) => {
+                        worksheet.write_boolean(row_number, col, *b)?;
+                    }
+                    Value::Date(dt) => {
+                        worksheet.write_datetime_with_format(
+                            row_number,
+                            col,
+                            dt.naive_utc(),
+                            &date_format,
+                        )?;
+                    }
+                    Value::Json(j) => {
+                        worksheet.write_string(row_number, col, j.to_string())?;
+                    }
+                    Value::Bytes(b) => {
+                        worksheet.write_string(row_number, col, self.binary_encoding.encode(b))?;
+                    }
+                };
+            }
+        }
+        worksheet.autofit();
+        workbook.save(&self.file_path)
+    }
+}
+
+#[async_trait]
+impl Target for ExcelTarget {
+    async fn connect(&mut self) -> Result<()> {
+        if let Some(parent) = self.file_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        Ok(())
+    }
+
+    async fn create_table(&mut self, _table_name: &str, schema: &Schema) -> Result<()> {
+        self.schema = Some(schema.clone());
+        Ok(())
+    }
+
+    async fn write_batch(&mut self, rows: &[Row]) -> Result<usize> {
+        // Excel sheets hold 1,048,576 rows, one of them the header
+        if self.buffered_rows.len() + rows.len() >= 1_048_576 {
+            return Err(TinyEtlError::DataTransfer(format!(
+                "{} would exceed the 1,048,575 data rows an Excel sheet holds",
+                self.file_path.display()
+            )));
+        }
+        self.buffered_rows.extend_from_slice(rows);
+        Ok(rows.len())
+    }
+
+    async fn finalize(&mut self) -> Result<()> {
+        let schema = self
+            .schema
+            .as_ref()
+            .ok_or_else(|| TinyEtlError::Configuration("Schema not set".to_string()))?;
+        self.write_workbook(schema).map_err(|e| {
+            TinyEtlError::DataTransfer(format!(
+                "Failed to write workbook {}: {}",
+                self.file_path.display(),
+                e
+            ))
+        })
+    }
+
+    async fn exists(&self, _table_name: &str) -> Result<bool> {
+        Ok(self.file_path.exists())
+    }
+
+    async fn truncate(&mut self, _table_name: &str) -> Result<()> {
+        self.buffered_rows.clear();
+        Ok(())
+    }
+
+    fn supports_append(&self) -> bool {
+        false
+    }
+
+    fn bytes_written(&self) -> Option<u64> {
+        std::fs::metadata(&self.file_path).ok().map(|m| m.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal::Decimal;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_header_row_below_a_title() {
+        let text = |s: &str| Data::String(s.to_string());
+        let rows = vec![
+            vec![text("Quarterly sales"), Data::Empty, Data::Empty],
+            vec![Data::Empty, Data::Empty, Data::Empty],
+            vec![text("region"), text("units"), text("region")],
+            vec![text("north"), Data::Float(3.0), text("n")],
+        ];
+        assert_eq!(detect_header_row(&rows), Some(2));
+        assert_eq!(
+            column_names(Some(rows[2].as_slice()), 4),
+            ["region", "units", "region_2", "column_4"]
+        );
+        assert_eq!(detect_header_row(&rows[3..]), None);
+    }
+
+    #[tokio::test]
+    async fn test_excel_write_read_cycle() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("orders.xlsx");
+        let connection_string = format!("{}#Orders", path.display());
+
+        let schema = Schema {
+            columns: ["id", "price", "paid", "note"]
+                .iter()
+                .map(|name| Column {
+                    name: name.to_string(),
+                    data_type: DataType::String,
+                    nullable: true,
+                })
+                .collect(),
+            estimated_rows: None,
+            primary_key_candidate: None,
+        };
+        let rows: Vec<Row> = [(1, "9.5", true), (2, "12", false)]
+            .iter()
+            .map(|(id, price, paid)| {
+                Row::from([
+                    ("id".to_string(), Value::Integer(*id)),
+                    (
+                        "price".to_string(),
+                        Value::Decimal(price.parse::<Decimal>().unwrap()),
+                    ),
+                    ("paid".to_string(), Value::Boolean(*paid)),
+                    ("note".to_string(), Value::Null),
+                ])
+            })
+            .collect();
+
+        let mut target = ExcelTarget::new(&connection_string).unwrap();
+        target.connect().await.unwrap();
+        target.create_table("orders", &schema).await.unwrap();
+        target.write_batch(&rows).await.unwrap();
+        target.finalize().await.unwrap();
+
+        let mut source = ExcelSource::new(&connection_string, &HashMap::new()).unwrap();
+        source.connect().await.unwrap();
+        let inferred = source.infer_schema(100).await.unwrap();
+        let types: Vec<_> = inferred
+            .columns
+            .iter()
+            .map(|c| (c.name.as_str(), c.data_type.clone()))
+            .collect();
+        assert_eq!(
+            types,
+            [
+                ("id", DataType::Integer),
+                ("price", DataType::Decimal),
+                ("paid", DataType::Boolean),
+                ("note", DataType::String)
+            ]
+        );
+
+        let read = source.read_batch(10).await.unwrap();
+        assert_eq!(read.len(), 2);
+        assert_eq!(read[0]["price"], Value::Decimal(Decimal::new(95, 1)));
+        assert_eq!(read[1]["price"], Value::Integer(12));
+        assert_eq!(read[1]["note"], Value::Null);
+
+        let missing = format!("{}#Missing", path.display());
+        let mut source = ExcelSource::new(&missing, &HashMap::new()).unwrap();
+        let err = source.connect().await.unwrap_err();
+        assert!(err.to_string().contains("Sheets: Orders"));
+    }
+}
@@ -0,0 +1,414 @@
+//! HL7 v2 batch files (`.hl7`): messages of segments on their own lines,
+//! each message starting with an MSH segment, optionally wrapped in
+//! FHS/BHS headers and BTS/FTS trailers. Every message becomes a row, or
+//! with the `row_segment` option every occurrence of that segment does,
+//! with a column per field or component named like `PID_5_1`.
+
+use std::collections::HashMap;
+
+use crate::{
+    connectors::record_format::{RecordFormat, Records},
+    schema::{Row, Value},
+    Result, TinyEtlError,
+};
+
+/// Segments of the batch envelope, which aren't part of any message
+const ENVELOPE: [&str; 4] = ["FHS", "BHS", "BTS", "FTS"];
+/// An explicit NULL, as opposed to an empty (unknown) field
+const HL7_NULL: &str = "\"\"";
+
+fn invalid(problem: String) -> TinyEtlError {
+    TinyEtlError::DataValidation(format!("Invalid HL7 file: {}", problem))
+}
+
+/// How far fields are split into columns
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Level {
+    /// `PID_5`: the field as written, separators and escapes included
+    Field,
+    /// `PID_5_1`, `PID_5_2`: one column per component, unescaped
+    Component,
+}
+
+/// How messages are flattened into rows, from the source options
+struct Flattening {
+    level: Level,
+    /// Segments made into columns; all when empty
+    segments: Vec<String>,
+    /// Segment of which every occurrence is a row
+    row_segment: Option<String>,
+}
+
+impl Flattening {
+    fn parse(options: &HashMap<String, String>) -> Result<Self> {
+        let level = match options.get("level").map(|level| level.to_lowercase()) {
+            None => Level::Component,
+            Some(level) if level == "component" => Level::Component,
+            Some(level) if level == "field" => Level::Field,
+            Some(level) => {
+                return Err(TinyEtlError::Configuration(format!(
+                    "Invalid HL7 level '{}'. Valid values: field, component",
+                    level
+                )))
+            }
+        };
+        let segments = match options.get("segments") {
+            Some(list) => list
+                .split(',')
+                .map(segment_id)
+                .collect::<Result<Vec<_>>>()?,
+            None => Vec::new(),
+        };
+        Ok(Self {
+            level,
+            segments,
+            row_segment: options
+                .get("row_segment")
+                .map(|id| segment_id(id))
+                .transpose()?,
+        })
+    }
+
+    fn keeps(&self, id: &str) -> bool {
+        self.segments.is_empty()
+            || self.segments.iter().any(|segment| segment == id)
+            || self.row_segment.as_deref() == Some(id)
+    }
+}
+
+/// A segment ID of the options: three letters or digits, e.g. `PID`, `ZPI`
+fn segment_id(id: &str) -> Result<String> {
+    let id = id.trim().to_uppercase();
+    if id.len() == 3 && id.chars().all(|c| c.is_ascii_alphanumeric()) {
+        Ok(id)
+    } else {
+        Err(TinyEtlError::Configuration(format!(
+            "Invalid HL7 segment '{}': segment IDs are three letters or digits",
+            id
+        )))
+    }
+}
+
+/// The delimiters a message declares at the start of its MSH segment,
+/// `MSH|^~\&` by convention
+#[derive(Debug, Clone, Copy)]
+struct Separators {
+    field: char,
+    component: char,
+    repetition: char,
+    escape: Option<char>,
+    subcomponent: char,
+}
+
+impl Separators {
+    fn of(msh: &str) -> Result<Self> {
+        let mut chars = msh.chars().skip(3);
+        let field = chars
+            .next()
+            .ok_or_else(|| invalid("an MSH segment has no field separator".to_string()))?;
+        let encoding: Vec<char> = chars.take_while(|&c| c != field).collect();
+        Ok(Self {
+            field,
+            component: encoding.first().copied().unwrap_or('^'),
+            repetition: encoding.get(1).copied().unwrap_or('~'),
+            escape: encoding.get(2).copied(),
+            subcomponent: encoding.get(3).copied().unwrap_or('&'),
+        })
+    }
+
+    /// Text with its escape sequences (`\F\`, `\S\`, `\X0D\`...) replaced
+    fn unescape(&self, text: &str) -> String {
+        let Some(escape) = self.escape.filter(|&escape| text.contains(escape)) else {
+            return text.to_string();
+        };
+        let mut unescaped = String::new();
+        let mut rest = text;
+        while let Some(start) = rest.find(escape) {
+            unescaped.push_str(&rest[..start]);
+            let sequence = &rest[start + escape.len_utf8()..];
+            let Some(end) = sequence.find(escape) else {
+                // A lone escape character is kept as it is
+                unescaped.push_str(&rest[start..]);
+                return unescaped;
+            };
+            match &sequence[..end] {
+                "F" => unescaped.push(self.field),
+                "S" => unescaped.push(self.component),
+                "T" => unescaped.push(self.subcomponent),
+                "R" => unescaped.push(self.repetition),
+                "E" => unescaped.push(escape),
+                ".br" => unescaped.push('\n'),
+                code if code.starts_with('X') => {
+                    if let Ok(bytes) = hex::decode(&code[1..]) {
+                        unescaped.push_str(&String::from_utf8_lossy(&bytes));
+                    }
+                }
+                // Highlighting, character set switches and formatting
+                // commands have no text of their own
+                _ => {}
+            }
+            rest = &sequence[end + escape.len_utf8()..];
+        }
+        unescaped.push_str(rest);
+        unescaped
+    }
+}
+
+/// A segment and its fields by number
+struct Segment<'a> {
+    id: &'a str,
+    fields: Vec<(usize, &'a str)>,
+}
+
+impl<'a> Segment<'a> {
+    fn parse(line: &'a str, separators: &Separators) -> Self {
+        let mut parts = line.split(separators.field);
+        let id = parts.next().unwrap_or_default();
+        let fields = if id == "MSH" {
+            // MSH-1 is the field separator and MSH-2 the other delimiters,
+            // which aren't data
+            parts.enumerate().skip(1).map(|(i, f)| (i + 2, f)).collect()
+        } else {
+            parts.enumerate().map(|(i, f)| (i + 1, f)).collect()
+        };
+        Self { id, fields }
+    }
+}
+
+/// The built-in `hl7` record format
+pub struct Hl7Format;
+
+impl Hl7Format {
+    /// Add the columns of `segment` to `row`, named after `prefix`
+    fn flatten(
+        segment: &Segment,
+        prefix: &str,
+        separators: &Separators,
+        level: Level,
+        records: &mut Records,
+        row: &mut Row,
+    ) {
+        for &(number, text) in &segment.fields {
+            if level == Level::Field {
+                if !text.is_empty() && text != HL7_NULL {
+                    let column = format!("{}_{}", prefix, number);
+                    records.insert(row, column, Value::String(text.to_string()));
+                }
+                continue;
+            }
+            let repetitions: Vec<Vec<&str>> = text
+                .split(separators.repetition)
+                .map(|repetition| repetition.split(separators.component).collect())
+                .collect();
+            let components = repetitions.iter().map(Vec::len).max().unwrap_or_default();
+            for component in 0..components {
+                // Repetitions of a field are joined, e.g. several patient IDs
+                let values: Vec<String> = repetitions
+                    .iter()
+                    .filter_map(|repetition| repetition.get(component))
+                    .filter(|value| !value.is_empty() && **value != HL7_NULL)
+                    .map(|value| separators.unescape(value))
+                    .collect();
+                if !values.is_empty() {
+                    let column = format!("{}_{}_{}", prefix, number, component + 1);
+                    let value = values.join(&separators.repetition.to_string());
+                    records.insert(row, column, Value::String(value));
+                }
+            }
+        }
+    }
+
+    /// Add the rows of one message, whose first line is its MSH segment
+    fn read_message(lines: &[&str], flattening: &Flattening, records: &mut Records) -> Result<()> {
+        let separators = Separators::of(lines[0])?;
+        let segments: Vec<Segment> = lines
+            .iter()
+            .map(|line| Segment::parse(line, &separators))
+            .filter(|segment| flattening.keeps(segment.id))
+            .collect();
+        let level = flattening.level;
+
+        let Some(row_segment) = &flattening.row_segment else {
+            // One row per message; a segment's later occurrences are
+            // numbered, e.g. `OBX2_5_1`
+            let mut row = Row::new();
+            let mut occurrences: HashMap<&str, usize> = HashMap::new();
+            for segment in &segments {
+                let occurrence = occurrences.entry(segment.id).or_default();
+                *occurrence += 1;
+                let prefix = match *occurrence {
+                    1 => segment.id.to_string(),
+                    n => format!("{}{}", segment.id, n),
+                };
+                Self::flatten(segment, &prefix, &separators, level, records, &mut row);
+            }
+            records.rows.push(row);
+            return Ok(());
+        };
+
+        // One row per occurrence of the row segment, with the latest
+        // occurrence of every other segment before it
+        let mut context: Vec<&Segment> = Vec::new();
+        let mut found = false;
+        for segment in &segments {
+            if segment.id == row_segment {
+                let mut row = Row::new();
+                for other in context.iter().chain([&segment]) {
+                    Self::flatten(other, other.id, &separators, level, records, &mut row);
+                }
+                records.rows.push(row);
+                found = true;
+            } else {
+                match context.iter().position(|other| other.id == segment.id) {
+                    Some(i) => context[i] = segment,
+                    None => context.push(segment),
+                }
+            }
+        }
+        // A message without the segment isn't dropped
+        if !found {
+            let mut row = Row::new();
+            for other in &context {
+                Self::flatten(other, other.id, &separators, level, records, &mut row);
+            }
+            records.rows.push(row);
+        }
+        Ok(())
+    }
+}
+
+impl RecordFormat for Hl7Format {
+    fn extensions(&self) -> &[&str] {
+        &["hl7"]
+    }
+
+    fn check_options(&self, options: &HashMap<String, String>) -> Result<()> {
+        Flattening::parse(options).map(|_| ())
+    }
+
+    fn read(&self, text: &str, options: &HashMap<String, String>) -> Result<Records> {
+        let flattening = Flattening::parse(options)?;
+        let mut records = Records::default();
+        let mut message: Vec<&str> = Vec::new();
+        // Segments end with a carriage return; files edited by hand or
+        // framed for MLLP (vertical tab, file separator) are accepted too
+        for line in text.split(['\r', '\n']) {
+            let line = line.trim_matches(['\u{0b}', '\u{1c}']);
+            if line.trim().is_empty() {
+                continue;
+            }
+            let id = line.get(..3).unwrap_or(line);
+            if ENVELOPE.contains(&id) || id == "MSH" {
+                if !message.is_empty() {
+                    Self::read_message(&message, &flattening, &mut records)?;
+                    message.clear();
+                }
+                if id != "MSH" {
+                    continue;
+                }
+            } else if message.is_empty() {
+                return Err(invalid(format!(
+                    "segment {} isn't part of a message starting with MSH",
+                    id
+                )));
+            }
+            message.push(line);
+        }
+        if !message.is_empty() {
+            Self::read_message(&message, &flattening, &mut records)?;
+        }
+        Ok(records)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BATCH: &str = "FHS|^~\\&|LAB\rBHS|^~\\&|LAB\r\
+        MSH|^~\\&|LAB|NORTH|EHR|MAIN|20240301083000||ORU^R01|MSG001|P|2.5\r\
+        PID|1||12345^^^MRN~A998^^^ENC||DOE^JOHN^Q||19800101|M|||12 MAIN ST\\T\\APT 4^^SPRINGFIELD\r\
+        OBR|1|||CBC^Blood count\r\
+        OBX|1|NM|WBC^White cells||7.2|10*3/uL\r\
+        OBX|2|NM|HGB^Hemoglobin||\"\"|g/dL\r\
+        OBR|2|||LIPID^Lipid panel\r\
+        OBX|1|NM|LDL^LDL cholesterol||130|mg/dL\n\
+        MSH|^~\\&|LAB|NORTH|EHR|MAIN|20240301090000||ADT^A01|MSG002|P|2.5\n\
+        PID|1||67890^^^MRN||ROE^JANE\\F\\ANN\n\
+        BTS|2\rFTS|1\r";
+
+    fn read(options: &[(&str, &str)]) -> Records {
+        let options = options
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        Hl7Format.read(BATCH, &options).unwrap()
+    }
+
+    fn text(row: &Row, column: &str) -> Option<String> {
+        match row.get(column) {
+            Some(Value::String(s)) => Some(s.clone()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_one_row_per_message() {
+        let records = read(&[]);
+        assert_eq!(records.rows.len(), 2);
+        assert_eq!(&records.columns[..3], ["MSH_3_1", "MSH_4_1", "MSH_5_1"]);
+        let first = &records.rows[0];
+        assert_eq!(text(first, "MSH_9_2").as_deref(), Some("R01"));
+        assert_eq!(text(first, "PID_3_1").as_deref(), Some("12345~A998"));
+        assert_eq!(text(first, "PID_3_4").as_deref(), Some("MRN~ENC"));
+        assert_eq!(text(first, "PID_5_2").as_deref(), Some("JOHN"));
+        assert_eq!(text(first, "PID_11_1").as_deref(), Some("12 MAIN ST&APT 4"));
+        assert_eq!(text(first, "OBX_5_1").as_deref(), Some("7.2"));
+        assert_eq!(text(first, "OBX3_3_1").as_deref(), Some("LDL"));
+        assert_eq!(text(first, "OBR2_4_2").as_deref(), Some("Lipid panel"));
+        // An explicit NULL
+        assert_eq!(text(first, "OBX2_5_1"), None);
+        let second = &records.rows[1];
+        assert_eq!(text(second, "PID_5_2").as_deref(), Some("JANE|ANN"));
+        assert_eq!(text(second, "OBX_5_1"), None);
+
+        let records = read(&[("level", "field"), ("segments", "msh,pid")]);
+        assert_eq!(
+            text(&records.rows[0], "PID_5").as_deref(),
+            Some("DOE^JOHN^Q")
+        );
+        assert!(records
+            .columns
+            .iter()
+            .all(|c| c.starts_with("MSH_") || c.starts_with("PID_")));
+    }
+
+    #[test]
+    fn test_one_row_per_segment() {
+        let records = read(&[("row_segment", "OBX"), ("segments", "MSH,PID,OBR")]);
+        // Three observations, and the message without any
+        assert_eq!(records.rows.len(), 4);
+        let rows = &records.rows;
+        assert_eq!(text(&rows[0], "OBX_3_1").as_deref(), Some("WBC"));
+        assert_eq!(text(&rows[0], "OBR_4_1").as_deref(), Some("CBC"));
+        assert_eq!(text(&rows[1], "OBX_3_1").as_deref(), Some("HGB"));
+        assert_eq!(text(&rows[2], "OBX_3_1").as_deref(), Some("LDL"));
+        assert_eq!(text(&rows[2], "OBR_4_1").as_deref(), Some("LIPID"));
+        assert_eq!(text(&rows[2], "PID_5_1").as_deref(), Some("DOE"));
+        assert_eq!(text(&rows[3], "PID_5_1").as_deref(), Some("ROE"));
+        assert_eq!(text(&rows[3], "OBX_3_1"), None);
+    }
+
+    #[test]
+    fn test_invalid_files_and_options() {
+        let err = Hl7Format
+            .read("PID|1||12345\r", &HashMap::new())
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("segment PID"));
+        let options = HashMap::from([("level".to_string(), "subcomponent".to_string())]);
+        assert!(Hl7Format.check_options(&options).is_err());
+        let options = HashMap::from([("row_segment".to_string(), "OBSERVATION".to_string())]);
+        assert!(Hl7Format.check_options(&options).is_err());
+    }
+}
@@ -0,0 +1,427 @@
+//! HTML tables (`.html`): statistics and registers published as a table on
+//! a web page. One table of the page is read, picked by its position or a
+//! CSS selector, with a row per table row and a column per table column.
+
+use scraper::{ElementRef, Html, Node, Selector};
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    connectors::{
+        csv::CsvSource,
+        record_format::{RecordFormat, Records},
+        vobject,
+    },
+    schema::{Row, Value},
+    Result, TinyEtlError,
+};
+
+/// Largest `colspan` and `rowspan` honoured, as in browsers
+const MAX_COLSPAN: usize = 1000;
+const MAX_ROWSPAN: usize = 65534;
+
+/// A row of the table, with the cells spanning it from rows above filled in
+struct Line {
+    cells: Vec<String>,
+    /// In the `thead`
+    in_head: bool,
+    /// Made only of `th` cells
+    all_headings: bool,
+}
+
+/// Which table of the page to read, from the `table_index` and
+/// `table_selector` options
+struct TableChoice {
+    /// 1 for the first table on the page (or matching the selector)
+    index: usize,
+    selector: Option<Selector>,
+}
+
+impl TableChoice {
+    fn parse(options: &HashMap<String, String>) -> Result<Self> {
+        let index = match options.get("table_index") {
+            Some(index) => index
+                .trim()
+                .parse::<usize>()
+                .ok()
+                .filter(|index| *index >= 1)
+                .ok_or_else(|| {
+                    TinyEtlError::Configuration(format!(
+                        "Invalid table_index '{}': expected 1 for the first table, 2 for the second...",
+                        index
+                    ))
+                })?,
+            None => 1,
+        };
+        let selector = match options.get("table_selector") {
+            Some(css) => Some(Selector::parse(css).map_err(|e| {
+                TinyEtlError::Configuration(format!("Invalid table_selector '{}': {}", css, e))
+            })?),
+            None => None,
+        };
+        Ok(Self { index, selector })
+    }
+
+    fn find<'a>(&self, page: &'a Html) -> Result<ElementRef<'a>> {
+        let tables: Vec<ElementRef> = match &self.selector {
+            // A selector matching the table's container (`#results`) picks
+            // the first table inside it
+            Some(selector) => page
+                .select(selector)
+                .filter_map(|element| {
+                    element
+                        .descendent_elements()
+                        .find(|e| e.value().name() == "table")
+                })
+                .collect(),
+            None => page
+                .root_element()
+                .descendent_elements()
+                .filter(|e| e.value().name() == "table")
+                .collect(),
+        };
+        if let Some(table) = tables.get(self.index - 1) {
+            return Ok(*table);
+        }
+        Err(match (&self.selector, tables.len()) {
+            (None, 0) => TinyEtlError::DataValidation("No table in the HTML file".to_string()),
+            (Some(_), 0) => TinyEtlError::Configuration(
+                "No table matches the table_selector option".to_string(),
+            ),
+            (_, found) => TinyEtlError::Configuration(format!(
+                "table_index {} is past the last of the {} tables found",
+                self.index, found
+            )),
+        })
+    }
+}
+
+/// Rows of a table, not those of tables nested in its cells
+fn table_rows(table: ElementRef) -> Vec<(ElementRef, bool)> {
+    let mut rows = Vec::new();
+    for child in table.child_elements() {
+        match child.value().name() {
+            "tr" => rows.push((child, false)),
+            section @ ("thead" | "tbody" | "tfoot") => rows.extend(
+                child
+                    .child_elements()
+                    .filter(|e| e.value().name() == "tr")
+                    .map(|tr| (tr, section == "thead")),
+            ),
+            _ => {}
+        }
+    }
+    rows
+}
+
+/// Text of a cell as displayed: line breaks and runs of whitespace become
+/// single spaces
+fn cell_text(cell: ElementRef) -> String {
+    let mut text = String::new();
+    for node in cell.descendants() {
+        match node.value() {
+            Node::Text(part) => text.push_str(part),
+            Node::Element(element) if element.name() == "br" => text.push(' '),
+            _ => {}
+        }
+    }
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn span(cell: ElementRef, name: &str, max: usize) -> usize {
+    cell.attr(name)
+        .and_then(|span| span.trim().parse::<usize>().ok())
+        .filter(|span| *span >= 1)
+        .map_or(1, |span| span.min(max))
+}
+
+/// Add the cells spanning down into `line` at its next columns
+fn fill_spanned(line: &mut Line, spans: &mut [(usize, String)]) {
+    while let Some((left, text)) = spans.get_mut(line.cells.len()).filter(|s| s.0 > 0) {
+        *left -= 1;
+        line.cells.push(text.clone());
+    }
+}
+
+/// Lay the cells out on a grid, repeating a cell spanning several columns
+/// or rows in each of them
+fn lines(rows: &[(ElementRef, bool)]) -> Vec<Line> {
+    // Text and rows left of the cells spanning down into the next rows
+    let mut spans: Vec<(usize, String)> = Vec::new();
+    let mut lines = Vec::new();
+    for (row, in_head) in rows {
+        let mut line = Line {
+            cells: Vec::new(),
+            in_head: *in_head,
+            all_headings: true,
+        };
+        let cells = row
+            .child_elements()
+            .filter(|e| matches!(e.value().name(), "td" | "th"));
+        for cell in cells {
+            fill_spanned(&mut line, &mut spans);
+            line.all_headings &= cell.value().name() == "th";
+            let text = cell_text(cell);
+            let rowspan = span(cell, "rowspan", MAX_ROWSPAN);
+            for _ in 0..span(cell, "colspan", MAX_COLSPAN) {
+                let column = line.cells.len();
+                if spans.len() <= column {
+                    spans.resize(column + 1, (0, String::new()));
+                }
+                spans[column] = (rowspan - 1, text.clone());
+                line.cells.push(text.clone());
+            }
+        }
+        // Cells spanning down past the end of this row's own cells
+        while line.cells.len() < spans.len() {
+            fill_spanned(&mut line, &mut spans);
+            if line.cells.len() < spans.len() {
+                line.cells.push(String::new());
+            }
+        }
+        line.all_headings &= !line.cells.is_empty();
+        lines.push(line);
+    }
+    lines
+}
+
+/// Column names from the heading rows, those of each column joined (e.g.
+/// `Population 2020` under a `Population` heading spanning two columns):
+/// blank ones become `column_<n>` and repeated ones get a `_2`, `_3`, ...
+/// suffix
+pub(crate) fn column_names(headings: &[&[String]], width: usize) -> Vec<String> {
+    let mut seen = HashSet::new();
+    (0..width)
+        .map(|index| {
+            let mut parts: Vec<&str> = Vec::new();
+            for line in headings {
+                match line.get(index).map(String::as_str) {
+                    Some(part) if !part.is_empty() && parts.last() != Some(&part) => {
+                        parts.push(part)
+                    }
+                    _ => {}
+                }
+            }
+            let name = if parts.is_empty() {
+                format!("column_{}", index + 1)
+            } else {
+                parts.join(" ")
+            };
+            let mut unique = name.clone();
+            let mut suffix = 2;
+            while !seen.insert(unique.clone()) {
+                unique = format!("{}_{}", name, suffix);
+                suffix += 1;
+            }
+            unique
+        })
+        .collect()
+}
+
+/// `1,234,567.8` without its thousands separators, which keep numbers in
+/// published tables from being read as numbers
+fn without_separators(text: &str) -> Option<String> {
+    let digits = text.strip_prefix('-').unwrap_or(text);
+    let (whole, fraction) = match digits.split_once('.') {
+        Some((whole, fraction)) => (whole, Some(fraction)),
+        None => (digits, None),
+    };
+    let groups: Vec<&str> = whole.split(',').collect();
+    let grouped = groups.len() > 1
+        && (1..=3).contains(&groups[0].len())
+        && groups[1..].iter().all(|group| group.len() == 3)
+        && groups
+            .iter()
+            .all(|group| group.bytes().all(|b| b.is_ascii_digit()));
+    let fraction_ok = fraction.map_or(true, |f| {
+        !f.is_empty() && f.bytes().all(|b| b.is_ascii_digit())
+    });
+    (grouped && fraction_ok).then(|| text.replace(',', ""))
+}
+
+/// Value of the text of a table cell, typed as in CSV files
+pub(crate) fn cell_value(text: &str) -> Value {
+    match without_separators(text) {
+        Some(number) => CsvSource::parse_value(&number),
+        None => CsvSource::parse_value(text),
+    }
+}
+
+/// The built-in `html` record format
+pub struct HtmlFormat;
+
+impl RecordFormat for HtmlFormat {
+    fn extensions(&self) -> &[&str] {
+        &["html", "htm"]
+    }
+
+    fn check_options(&self, options: &HashMap<String, String>) -> Result<()> {
+        TableChoice::parse(options).map(|_| ())
+    }
+
+    fn read(&self, text: &str, options: &HashMap<String, String>) -> Result<Records> {
+        let choice = TableChoice::parse(options)?;
+        let page = Html::parse_document(text);
+        let table = choice.find(&page)?;
+        let lines = lines(&table_rows(table));
+
+        // Heading rows are those of the `thead`, or else the leading rows of
+        // `th` cells, as long as a row is left for the data
+        let has_head = lines.iter().any(|line| line.in_head);
+        let heading_rows = lines
+            .iter()
+            .take_while(|line| {
+                if has_head {
+                    line.in_head
+                } else {
+                    line.all_headings
+                }
+            })
+            .count();
+        let heading_rows = if heading_rows == lines.len() && !has_head {
+            heading_rows.min(1)
+        } else {
+            heading_rows
+        };
+        let (headings, data) = lines.split_at(heading_rows);
+
+        let width = lines.iter().map(|line| line.cells.len()).max().unwrap_or(0);
+        let headings: Vec<&[String]> = headings.iter().map(|line| line.cells.as_slice()).collect();
+        let names = column_names(&headings, width);
+        let mut records =
+            Records::with_columns(&names.iter().map(String::as_str).collect::<Vec<_>>());
+        for line in data {
+            if line.cells.iter().all(String::is_empty) {
+                continue;
+            }
+            let mut row = Row::new();
+            for (name, cell) in names.iter().zip(&line.cells) {
+                if cell.is_empty() {
+                    continue;
+                }
+                records.insert(&mut row, name.clone(), cell_value(cell));
+            }
+            records.rows.push(row);
+        }
+        vobject::settle_dates(&mut records);
+        Ok(records)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal::Decimal;
+
+    const PAGE: &str = r#"<!DOCTYPE html>
+<html><body>
+<table id="nav"><tr><td>Home</td><td>About</td></tr></table>
+<div id="stats">
+  <table>
+    <caption>Population by region</caption>
+    <thead>
+      <tr><th rowspan="2">Region</th><th colspan="2">Population</th></tr>
+      <tr><th>2010</th><th>2020</th></tr>
+    </thead>
+    <tbody>
+      <tr><td>North<br>East</td><td>1,204,300</td><td>1,310,050</td></tr>
+      <tr><td>South</td><td>987,000</td><td>n/a</td></tr>
+      <tr><td colspan="3"></td></tr>
+      <tr><td>West &amp; Islands</td><td>45.5</td><td>-1,200.25</td></tr>
+    </tbody>
+  </table>
+</div>
+</body></html>"#;
+
+    fn options(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    fn text(value: &str) -> Value {
+        Value::String(value.to_string())
+    }
+
+    #[test]
+    fn test_table_by_index_with_spanning_headings() {
+        let records = HtmlFormat
+            .read(PAGE, &options(&[("table_index", "2")]))
+            .unwrap();
+        assert_eq!(
+            records.columns,
+            ["Region", "Population 2010", "Population 2020"]
+        );
+        assert_eq!(records.rows.len(), 3);
+        assert_eq!(records.rows[0]["Region"], text("North East"));
+        assert_eq!(
+            records.rows[0]["Population 2010"],
+            Value::Integer(1_204_300)
+        );
+        assert_eq!(records.rows[1]["Population 2020"], text("n/a"));
+        assert_eq!(records.rows[2]["Region"], text("West & Islands"));
+        assert_eq!(
+            records.rows[2]["Population 2020"],
+            Value::Decimal(Decimal::new(-120025, 2))
+        );
+    }
+
+    #[test]
+    fn test_table_by_selector() {
+        let records = HtmlFormat
+            .read(PAGE, &options(&[("table_selector", "#stats")]))
+            .unwrap();
+        assert_eq!(records.rows.len(), 3);
+
+        // Without options the first table is read; without headings the
+        // columns are numbered
+        let records = HtmlFormat.read(PAGE, &HashMap::new()).unwrap();
+        assert_eq!(records.columns, ["column_1", "column_2"]);
+        assert_eq!(records.rows[0]["column_2"], text("About"));
+    }
+
+    #[test]
+    fn test_rowspans_and_heading_rows() {
+        let page = "<table>\
+            <tr><th>Site</th><th>Year</th><th>Site</th></tr>\
+            <tr><td rowspan=2>A</td><td>2021</td><td>x</td></tr>\
+            <tr><td>2022</td><td>y</td></tr>\
+            </table>";
+        let records = HtmlFormat.read(page, &HashMap::new()).unwrap();
+        assert_eq!(records.columns, ["Site", "Year", "Site_2"]);
+        assert_eq!(records.rows[1]["Site"], text("A"));
+        assert_eq!(records.rows[1]["Year"], Value::Integer(2022));
+        assert_eq!(records.rows[1]["Site_2"], text("y"));
+    }
+
+    #[test]
+    fn test_missing_tables_and_invalid_options() {
+        let err = HtmlFormat
+            .read(PAGE, &options(&[("table_index", "3")]))
+            .unwrap_err();
+        assert!(err.to_string().contains("past the last of the 2 tables"));
+        let err = HtmlFormat
+            .read(PAGE, &options(&[("table_selector", "#missing")]))
+            .unwrap_err();
+        assert!(err.to_string().contains("No table matches"));
+        assert!(HtmlFormat
+            .read("<p>Nothing here</p>", &HashMap::new())
+            .is_err());
+
+        assert!(HtmlFormat
+            .check_options(&options(&[("table_index", "0")]))
+            .is_err());
+        assert!(HtmlFormat
+            .check_options(&options(&[("table_selector", "table[")]))
+            .is_err());
+    }
+
+    #[test]
+    fn test_without_separators() {
+        assert_eq!(without_separators("1,234,567").as_deref(), Some("1234567"));
+        assert_eq!(without_separators("-12,000.5").as_deref(), Some("-12000.5"));
+        assert_eq!(without_separators("1234"), None);
+        assert_eq!(without_separators("12,34"), None);
+        assert_eq!(without_separators("Smith, J."), None);
+    }
+}
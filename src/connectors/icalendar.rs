@@ -0,0 +1,305 @@
+//! iCalendar files (`.ics`): calendar exports of Google Calendar, Outlook
+//! and the like. Every event (VEVENT) becomes a row with the same standard
+//! columns, whichever properties it has.
+
+use chrono::Duration;
+use std::collections::HashMap;
+
+use crate::{
+    connectors::{
+        record_format::{RecordFormat, Records},
+        vobject::{self, Component, Property},
+    },
+    schema::{Row, Value},
+    Result,
+};
+
+/// Columns of every event, in order
+const COLUMNS: [&str; 18] = [
+    "uid",
+    "summary",
+    "description",
+    "location",
+    "start",
+    "end",
+    "all_day",
+    "timezone",
+    "status",
+    "organizer",
+    "attendees",
+    "categories",
+    "recurrence_rule",
+    "url",
+    "created",
+    "last_modified",
+    "sequence",
+    "calendar",
+];
+
+/// An ISO 8601 duration such as `PT1H30M`, `P1D` or `-P2W`
+fn parse_duration(text: &str) -> Option<Duration> {
+    let text = text.trim();
+    let (negative, text) = match text.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, text.strip_prefix('+').unwrap_or(text)),
+    };
+    let mut rest = text.strip_prefix('P')?;
+    let mut seconds: i64 = 0;
+    let mut in_time = false;
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix('T') {
+            in_time = true;
+            rest = after;
+            continue;
+        }
+        let digits = rest.find(|c: char| !c.is_ascii_digit())?;
+        let amount: i64 = rest[..digits].parse().ok()?;
+        let unit = match (rest.as_bytes()[digits], in_time) {
+            (b'W', false) => 7 * 86_400,
+            (b'D', false) => 86_400,
+            (b'H', true) => 3_600,
+            (b'M', true) => 60,
+            (b'S', true) => 1,
+            _ => return None,
+        };
+        seconds += amount * unit;
+        rest = &rest[digits + 1..];
+    }
+    let duration = Duration::seconds(seconds);
+    Some(if negative { -duration } else { duration })
+}
+
+/// Calendar user address, without its `mailto:` scheme
+fn user_address(property: &Property) -> Option<String> {
+    let text = property.text()?;
+    Some(match text.get(..7) {
+        Some(scheme) if scheme.eq_ignore_ascii_case("mailto:") => text[7..].to_string(),
+        _ => text,
+    })
+}
+
+/// The built-in `icalendar` record format
+pub struct IcalendarFormat;
+
+impl IcalendarFormat {
+    fn row(event: &Component, calendar: Option<String>, records: &mut Records) -> Row {
+        let mut row = Row::new();
+        let mut set = |column: &str, value: Option<Value>| {
+            if let Some(value) = value {
+                records.insert(&mut row, column.to_string(), value);
+            }
+        };
+        let text = |name: &str| event.text(name).map(Value::String);
+        let date = |name: &str| event.text(name).map(|text| vobject::date_value(&text));
+
+        set("uid", text("UID"));
+        set("summary", text("SUMMARY"));
+        set("description", text("DESCRIPTION"));
+        set("location", text("LOCATION"));
+
+        if let Some(dtstart) = event.property("DTSTART") {
+            let value = dtstart.text().unwrap_or_default();
+            match vobject::parse_date_time(&value) {
+                Some((start, date_only)) => {
+                    let all_day = date_only || dtstart.param("VALUE") == Some("DATE");
+                    // Without DTEND or DURATION, an all-day event lasts the
+                    // day and any other ends when it starts
+                    let end = match event.property("DTEND") {
+                        Some(dtend) => dtend.text().map(|text| vobject::date_value(&text)),
+                        None => {
+                            let duration = event
+                                .text("DURATION")
+                                .and_then(|text| parse_duration(&text))
+                                .unwrap_or_else(|| {
+                                    if all_day {
+                                        Duration::days(1)
+                                    } else {
+                                        Duration::zero()
+                                    }
+                                });
+                            Some(Value::Date(start + duration))
+                        }
+                    };
+                    set("start", Some(Value::Date(start)));
+                    set("end", end);
+                    set("all_day", Some(Value::Boolean(all_day)));
+                }
+                None => set("start", Some(Value::String(value.clone()))),
+            }
+            // Times with a TZID are local to that zone and kept as written
+            let timezone = match dtstart.param("TZID") {
+                Some(tzid) => Some(tzid.to_string()),
+                None if value.ends_with(['Z', 'z']) => Some("UTC".to_string()),
+                None => None,
+            };
+            set("timezone", timezone.map(Value::String));
+        }
+
+        set("status", text("STATUS"));
+        set(
+            "organizer",
+            event
+                .property("ORGANIZER")
+                .and_then(user_address)
+                .map(Value::String),
+        );
+        let attendees: Vec<String> = event.all("ATTENDEE").filter_map(user_address).collect();
+        set(
+            "attendees",
+            Some(attendees.join("; "))
+                .filter(|all| !all.is_empty())
+                .map(Value::String),
+        );
+        let categories: Vec<String> = event.all("CATEGORIES").filter_map(Property::text).collect();
+        set(
+            "categories",
+            Some(categories.join(","))
+                .filter(|all| !all.is_empty())
+                .map(Value::String),
+        );
+        set(
+            "recurrence_rule",
+            event
+                .property("RRULE")
+                .map(|p| Value::String(p.value.clone())),
+        );
+        set("url", text("URL"));
+        set("created", date("CREATED"));
+        set("last_modified", date("LAST-MODIFIED"));
+        set(
+            "sequence",
+            event
+                .text("SEQUENCE")
+                .and_then(|text| text.trim().parse().ok())
+                .map(Value::Integer),
+        );
+        set("calendar", calendar.map(Value::String));
+        row
+    }
+}
+
+impl RecordFormat for IcalendarFormat {
+    fn extensions(&self) -> &[&str] {
+        &["ics", "ical"]
+    }
+
+    fn read(&self, text: &str, _options: &HashMap<String, String>) -> Result<Records> {
+        let mut records = Records::with_columns(&COLUMNS);
+        for calendar in vobject::parse(text, "iCalendar")? {
+            let name = calendar.text("X-WR-CALNAME");
+            // Events sit in a VCALENDAR; alarms inside them aren't events
+            let events = std::iter::once(&calendar)
+                .chain(calendar.descendants())
+                .filter(|component| component.name == "VEVENT");
+            for event in events {
+                let row = Self::row(event, name.clone(), &mut records);
+                records.rows.push(row);
+            }
+        }
+        vobject::settle_dates(&mut records);
+        Ok(records)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    const CALENDAR: &str = "BEGIN:VCALENDAR\r\n\
+        VERSION:2.0\r\n\
+        PRODID:-//Example//Calendar//EN\r\n\
+        X-WR-CALNAME:Team\r\n\
+        BEGIN:VEVENT\r\n\
+        UID:standup-1@example.com\r\n\
+        DTSTART;TZID=Europe/Paris:20240304T093000\r\n\
+        DURATION:PT15M\r\n\
+        SUMMARY:Standup\\, daily\r\n\
+        RRULE:FREQ=WEEKLY;BYDAY=MO,TU,WE,TH,FR\r\n\
+        ORGANIZER;CN=Ann:mailto:ann@example.com\r\n\
+        ATTENDEE;CN=Bob:mailto:bob@example.com\r\n\
+        ATTENDEE;CN=Cy:MAILTO:cy@example.com\r\n\
+        SEQUENCE:2\r\n\
+        BEGIN:VALARM\r\n\
+        ACTION:DISPLAY\r\n\
+        DESCRIPTION:Reminder\r\n\
+        END:VALARM\r\n\
+        END:VEVENT\r\n\
+        BEGIN:VEVENT\r\n\
+        UID:offsite-2@example.com\r\n\
+        DTSTART;VALUE=DATE:20240610\r\n\
+        DTEND;VALUE=DATE:20240612\r\n\
+        SUMMARY:Offsite\r\n\
+        LOCATION:Lyon\r\n\
+        CATEGORIES:Travel,Team\r\n\
+        END:VEVENT\r\n\
+        BEGIN:VEVENT\r\n\
+        UID:call-3@example.com\r\n\
+        DTSTART:20240305T140000Z\r\n\
+        SUMMARY:Call\r\n\
+        END:VEVENT\r\n\
+        END:VCALENDAR\r\n";
+
+    fn date(y: i32, m: u32, d: u32, h: u32, min: u32) -> Value {
+        Value::Date(Utc.with_ymd_and_hms(y, m, d, h, min, 0).unwrap())
+    }
+
+    #[test]
+    fn test_one_row_per_event() {
+        let records = IcalendarFormat.read(CALENDAR, &HashMap::new()).unwrap();
+        assert_eq!(records.columns, COLUMNS);
+        assert_eq!(records.rows.len(), 3);
+
+        let standup = &records.rows[0];
+        assert_eq!(
+            standup["summary"],
+            Value::String("Standup, daily".to_string())
+        );
+        assert_eq!(standup["start"], date(2024, 3, 4, 9, 30));
+        assert_eq!(standup["end"], date(2024, 3, 4, 9, 45));
+        assert_eq!(standup["all_day"], Value::Boolean(false));
+        assert_eq!(
+            standup["timezone"],
+            Value::String("Europe/Paris".to_string())
+        );
+        assert_eq!(
+            standup["organizer"],
+            Value::String("ann@example.com".to_string())
+        );
+        assert_eq!(
+            standup["attendees"],
+            Value::String("bob@example.com; cy@example.com".to_string())
+        );
+        assert_eq!(
+            standup["recurrence_rule"],
+            Value::String("FREQ=WEEKLY;BYDAY=MO,TU,WE,TH,FR".to_string())
+        );
+        assert_eq!(standup["sequence"], Value::Integer(2));
+        assert_eq!(standup["calendar"], Value::String("Team".to_string()));
+        // The alarm's description isn't the event's
+        assert!(!standup.contains_key("description"));
+
+        let offsite = &records.rows[1];
+        assert_eq!(offsite["all_day"], Value::Boolean(true));
+        assert_eq!(offsite["start"], date(2024, 6, 10, 0, 0));
+        assert_eq!(offsite["end"], date(2024, 6, 12, 0, 0));
+        assert_eq!(
+            offsite["categories"],
+            Value::String("Travel,Team".to_string())
+        );
+        assert!(!offsite.contains_key("timezone"));
+
+        let call = &records.rows[2];
+        assert_eq!(call["end"], date(2024, 3, 5, 14, 0));
+        assert_eq!(call["timezone"], Value::String("UTC".to_string()));
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("PT1H30M"), Some(Duration::minutes(90)));
+        assert_eq!(parse_duration("P1W"), Some(Duration::days(7)));
+        assert_eq!(parse_duration("-P1DT12H"), Some(-Duration::hours(36)));
+        assert_eq!(parse_duration("PT15"), None);
+        assert_eq!(parse_duration("1H"), None);
+    }
+}
@@ -1,12 +1,13 @@
 use async_trait::async_trait;
-use rust_decimal::Decimal;
 use serde_json;
+use std::collections::HashMap;
 use std::path::PathBuf;
 
 use crate::{
     connectors::{Source, Target},
     date_parser::DateParser,
-    schema::{Row, Schema, SchemaInferer, Value},
+    schema::{BinaryEncoding, Row, Schema, SchemaInferer, Value},
+    strict::Coercion,
     Result, TinyEtlError,
 };
 
@@ -14,6 +15,7 @@ pub struct JsonSource {
     file_path: PathBuf,
     data: Vec<serde_json::Value>,
     current_index: usize,
+    coercion: Coercion,
 }
 
 impl JsonSource {
@@ -22,39 +24,48 @@ impl JsonSource {
             file_path: PathBuf::from(file_path),
             data: Vec::new(),
             current_index: 0,
+            coercion: Coercion::default(),
         })
     }
 
-    fn json_value_to_value(&self, json_val: &serde_json::Value) -> Value {
-        match json_val {
-            serde_json::Value::String(s) => {
-                // Try to parse string as date first, fall back to string
-                if let Some(date_value) = DateParser::try_parse(s) {
-                    date_value
-                } else {
-                    Value::String(s.clone())
-                }
-            }
-            serde_json::Value::Number(n) => {
-                if let Some(i) = n.as_i64() {
-                    Value::Integer(i)
-                } else if let Some(f) = n.as_f64() {
-                    // Convert f64 to Decimal
-                    match Decimal::try_from(f) {
-                        Ok(d) => Value::Decimal(d),
-                        Err(_) => Value::String(n.to_string()),
-                    }
-                } else {
-                    Value::String(n.to_string())
-                }
+    fn json_value_to_value(&self, column: &str, json_val: &serde_json::Value) -> Result<Value> {
+        read_json_value(column, json_val, self.coercion)
+    }
+}
+
+/// Value of a JSON field read from a source: strings that look like dates
+/// become dates, and nested arrays and objects are kept as JSON text
+pub(crate) fn read_json_value(
+    column: &str,
+    json_val: &serde_json::Value,
+    coercion: Coercion,
+) -> Result<Value> {
+    Ok(match json_val {
+        serde_json::Value::String(s) => {
+            // Try to parse string as date first, fall back to string
+            if let Some(date_value) = DateParser::try_parse(s) {
+                date_value
+            } else {
+                Value::String(s.clone())
             }
-            serde_json::Value::Bool(b) => Value::Boolean(*b),
-            serde_json::Value::Null => Value::Null,
-            serde_json::Value::Array(_) | serde_json::Value::Object(_) => {
-                Value::String(json_val.to_string())
+        }
+        serde_json::Value::Number(n) => {
+            if let Some(i) = n.as_i64() {
+                Value::Integer(i)
+            } else if let Some(u) = n.as_u64() {
+                Value::from_wide_integer(u.into())
+            } else if let Some(f) = n.as_f64() {
+                coercion.decimal_from_f64(f, column)?
+            } else {
+                Value::String(n.to_string())
             }
         }
-    }
+        serde_json::Value::Bool(b) => Value::Boolean(*b),
+        serde_json::Value::Null => Value::Null,
+        serde_json::Value::Array(_) | serde_json::Value::Object(_) => {
+            Value::String(json_val.to_string())
+        }
+    })
 }
 
 #[async_trait]
@@ -94,7 +105,7 @@ impl Source for JsonSource {
             if let serde_json::Value::Object(obj) = json_obj {
                 let mut row = Row::new();
                 for (key, value) in obj {
-                    row.insert(key.clone(), self.json_value_to_value(value));
+                    row.insert(key.clone(), self.json_value_to_value(key, value)?);
                 }
                 rows.push(row);
             }
@@ -111,7 +122,7 @@ impl Source for JsonSource {
             if let serde_json::Value::Object(obj) = &self.data[i] {
                 let mut row = Row::new();
                 for (key, value) in obj {
-                    row.insert(key.clone(), self.json_value_to_value(value));
+                    row.insert(key.clone(), self.json_value_to_value(key, value)?);
                 }
                 rows.push(row);
             }
@@ -133,12 +144,23 @@ impl Source for JsonSource {
     fn has_more(&self) -> bool {
         self.current_index < self.data.len()
     }
+
+    fn set_coercion(&mut self, coercion: Coercion) {
+        self.coercion = coercion;
+    }
 }
 
 pub struct JsonTarget {
     file_path: PathBuf,
     accumulated_rows: Vec<Row>,
     schema: Option<Schema>,
+    truncated: bool,
+    binary_encoding: BinaryEncoding,
+    /// Columns identifying a document; incoming rows update the document
+    /// with the same key instead of being appended
+    merge_key: Vec<String>,
+    /// Position of each key in `accumulated_rows`
+    key_index: HashMap<String, usize>,
 }
 
 impl JsonTarget {
@@ -147,31 +169,57 @@ impl JsonTarget {
             file_path: PathBuf::from(file_path),
             accumulated_rows: Vec::new(),
             schema: None,
+            truncated: false,
+            binary_encoding: BinaryEncoding::default(),
+            merge_key: Vec::new(),
+            key_index: HashMap::new(),
         })
     }
 
-    fn value_to_json(&self, value: &Value) -> serde_json::Value {
-        match value {
-            Value::String(s) => serde_json::Value::String(s.clone()),
-            Value::Integer(i) => serde_json::Value::Number(serde_json::Number::from(*i)),
-            Value::Decimal(d) => {
-                // Convert Decimal to f64 for JSON representation
-                match (*d).try_into() {
-                    Ok(f) => {
-                        if let Some(n) = serde_json::Number::from_f64(f) {
-                            serde_json::Value::Number(n)
-                        } else {
-                            serde_json::Value::String(d.to_string())
-                        }
-                    }
-                    Err(_) => serde_json::Value::String(d.to_string()),
-                }
+    /// Write binary values as hex instead of base64
+    pub fn with_binary_encoding(mut self, encoding: BinaryEncoding) -> Self {
+        self.binary_encoding = encoding;
+        self
+    }
+
+    /// Merge incoming rows into the documents already in the file by these
+    /// key columns: fields of a matching document are updated, rows with a
+    /// new key are appended
+    pub fn with_merge_key(mut self, columns: Vec<String>) -> Self {
+        self.merge_key = columns;
+        self
+    }
+
+    /// Key of a row, or `None` when one of the key columns is missing or null
+    fn row_key(&self, row: &Row) -> Option<String> {
+        let parts = self
+            .merge_key
+            .iter()
+            .map(|column| row.get(column).and_then(Value::to_string_for_arrow))
+            .collect::<Option<Vec<_>>>()?;
+        Some(parts.join("\u{1f}"))
+    }
+
+    fn merge_row(&mut self, row: &Row) -> Result<()> {
+        let Some(key) = self.row_key(row) else {
+            return Err(TinyEtlError::DataValidation(format!(
+                "row has no value for merge key ({}) in {}",
+                self.merge_key.join(", "),
+                self.file_path.display()
+            )));
+        };
+        match self.key_index.get(&key) {
+            Some(&index) => self.accumulated_rows[index].extend(row.clone()),
+            None => {
+                self.key_index.insert(key, self.accumulated_rows.len());
+                self.accumulated_rows.push(row.clone());
             }
-            Value::Boolean(b) => serde_json::Value::Bool(*b),
-            Value::Date(dt) => serde_json::Value::String(dt.to_rfc3339()),
-            Value::Json(j) => j.clone(), // Already a JSON value, just clone it
-            Value::Null => serde_json::Value::Null,
         }
+        Ok(())
+    }
+
+    fn value_to_json(&self, value: &Value) -> serde_json::Value {
+        value_to_json(value, self.binary_encoding)
     }
 
     fn json_to_value(json_val: &serde_json::Value) -> Value {
@@ -203,6 +251,32 @@ impl JsonTarget {
     }
 }
 
+/// JSON form of a value, with binary values as text in `binary_encoding`
+pub(crate) fn value_to_json(value: &Value, binary_encoding: BinaryEncoding) -> serde_json::Value {
+    match value {
+        Value::String(s) => serde_json::Value::String(s.clone()),
+        Value::Integer(i) => serde_json::Value::Number(serde_json::Number::from(*i)),
+        Value::Decimal(d) => {
+            // Convert Decimal to f64 for JSON representation
+            match (*d).try_into() {
+                Ok(f) => {
+                    if let Some(n) = serde_json::Number::from_f64(f) {
+                        serde_json::Value::Number(n)
+                    } else {
+                        serde_json::Value::String(d.to_string())
+                    }
+                }
+                Err(_) => serde_json::Value::String(d.to_string()),
+            }
+        }
+        Value::Boolean(b) => serde_json::Value::Bool(*b),
+        Value::Date(dt) => serde_json::Value::String(dt.to_rfc3339()),
+        Value::Json(j) => j.clone(), // Already a JSON value, just clone it
+        Value::Bytes(b) => serde_json::Value::String(binary_encoding.encode(b)),
+        Value::Null => serde_json::Value::Null,
+    }
+}
+
 #[async_trait]
 impl Target for JsonTarget {
     async fn connect(&mut self) -> Result<()> {
@@ -214,10 +288,20 @@ impl Target for JsonTarget {
     }
 
     async fn create_table(&mut self, _table_name: &str, schema: &Schema) -> Result<()> {
+        if let Some(missing) = self
+            .merge_key
+            .iter()
+            .find(|column| !schema.columns.iter().any(|c| c.name == **column))
+        {
+            return Err(TinyEtlError::Configuration(format!(
+                "Merge key column '{}' is not in the output schema",
+                missing
+            )));
+        }
         self.schema = Some(schema.clone());
 
-        // If file exists and we support append, load existing data
-        if self.file_path.exists() && self.supports_append() {
+        // If file exists and we support append, load existing data (unless truncated)
+        if !self.truncated && self.file_path.exists() && self.supports_append() {
             if let Ok(content) = std::fs::read_to_string(&self.file_path) {
                 if let Ok(existing_json) = serde_json::from_str::<serde_json::Value>(&content) {
                     if let Some(array) = existing_json.as_array() {
@@ -237,10 +321,27 @@ impl Target for JsonTarget {
             }
         }
 
+        // Existing documents without a key are kept but never updated
+        if !self.merge_key.is_empty() {
+            self.key_index = self
+                .accumulated_rows
+                .iter()
+                .enumerate()
+                .filter_map(|(index, row)| self.row_key(row).map(|key| (key, index)))
+                .collect();
+        }
+
         Ok(())
     }
 
     async fn write_batch(&mut self, rows: &[Row]) -> Result<usize> {
+        if !self.merge_key.is_empty() {
+            for row in rows {
+                self.merge_row(row)?;
+            }
+            return Ok(rows.len());
+        }
+
         // Accumulate rows - we'll write them all at once in finalize()
         self.accumulated_rows.extend_from_slice(rows);
         Ok(rows.len())
@@ -287,6 +388,8 @@ impl Target for JsonTarget {
     async fn truncate(&mut self, _table_name: &str) -> Result<()> {
         // For JSON files, truncation means clearing accumulated rows
         self.accumulated_rows.clear();
+        self.key_index.clear();
+        self.truncated = true;
         Ok(())
     }
 
@@ -294,11 +397,16 @@ impl Target for JsonTarget {
         // JSON arrays support append - we can merge existing data with new data
         true
     }
+
+    fn bytes_written(&self) -> Option<u64> {
+        std::fs::metadata(&self.file_path).ok().map(|m| m.len())
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use rust_decimal::Decimal;
     use std::io::Write;
     use tempfile::NamedTempFile;
 
@@ -509,4 +617,91 @@ mod tests {
         assert_eq!(array[0]["name"], "Alice");
         assert_eq!(array[1]["name"], "Bob");
     }
+
+    #[tokio::test]
+    async fn test_json_target_truncate_discards_existing_rows() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let path = temp_file.path().to_str().unwrap();
+        std::fs::write(path, r#"[{"id": 1}, {"id": 2}]"#).unwrap();
+
+        let schema = Schema {
+            columns: vec![crate::schema::Column {
+                name: "id".to_string(),
+                data_type: crate::schema::DataType::Integer,
+                nullable: false,
+            }],
+            estimated_rows: None,
+            primary_key_candidate: None,
+        };
+
+        let mut target = JsonTarget::new(path).unwrap();
+        target.connect().await.unwrap();
+        target.truncate("test").await.unwrap();
+        target.create_table("test", &schema).await.unwrap();
+
+        let mut row = std::collections::HashMap::new();
+        row.insert("id".to_string(), Value::Integer(3));
+        target.write_batch(&[row]).await.unwrap();
+        target.finalize().await.unwrap();
+
+        let content = std::fs::read_to_string(path).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&content).unwrap();
+        let array = json.as_array().unwrap();
+        assert_eq!(array.len(), 1);
+        assert_eq!(array[0]["id"], 3);
+    }
+
+    #[tokio::test]
+    async fn test_json_target_merges_rows_by_key() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let path = temp_file.path().to_str().unwrap();
+        std::fs::write(
+            path,
+            r#"[{"id": 1, "name": "Alice", "city": "Oslo"}, {"id": 2, "name": "Bob", "city": "Rome"}]"#,
+        )
+        .unwrap();
+
+        let column = |name: &str, data_type| crate::schema::Column {
+            name: name.to_string(),
+            data_type,
+            nullable: true,
+        };
+        let schema = Schema {
+            columns: vec![
+                column("id", crate::schema::DataType::Integer),
+                column("name", crate::schema::DataType::String),
+                column("city", crate::schema::DataType::String),
+            ],
+            estimated_rows: None,
+            primary_key_candidate: None,
+        };
+
+        let mut target = JsonTarget::new(path)
+            .unwrap()
+            .with_merge_key(vec!["id".to_string()]);
+        target.connect().await.unwrap();
+        target.create_table("test", &schema).await.unwrap();
+
+        let updated = Row::from([
+            ("id".to_string(), Value::Integer(2)),
+            ("name".to_string(), Value::String("Robert".to_string())),
+        ]);
+        let added = Row::from([
+            ("id".to_string(), Value::Integer(3)),
+            ("name".to_string(), Value::String("Carol".to_string())),
+        ]);
+        target.write_batch(&[updated, added]).await.unwrap();
+        target.finalize().await.unwrap();
+
+        let content = std::fs::read_to_string(path).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&content).unwrap();
+        let array = json.as_array().unwrap();
+        assert_eq!(array.len(), 3);
+        assert_eq!(array[1]["name"], "Robert");
+        assert_eq!(array[1]["city"], "Rome");
+        assert_eq!(array[2]["name"], "Carol");
+
+        let missing_key = Row::from([("name".to_string(), Value::String("Dan".to_string()))]);
+        assert!(target.write_batch(&[missing_key]).await.is_err());
+    }
 }
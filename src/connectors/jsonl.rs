@@ -0,0 +1,564 @@
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use crate::{
+    connectors::{
+        estimate_line_count,
+        json::{read_json_value, value_to_json},
+        Source, Target,
+    },
+    schema::{BinaryEncoding, Row, Schema, SchemaInferer},
+    strict::Coercion,
+    Result, TinyEtlError,
+};
+
+/// Whether a path names a newline-delimited JSON file (`.jsonl`, `.ndjson`)
+pub fn is_jsonl_file(path: &str) -> bool {
+    Path::new(path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| matches!(ext.to_lowercase().as_str(), "jsonl" | "ndjson"))
+}
+
+/// Source streaming a JSON Lines file, one object per line, so only the
+/// batch being read is held in memory. Blank lines are skipped.
+pub struct JsonlSource {
+    file_path: PathBuf,
+    reader: Option<BufReader<File>>,
+    /// Line number of the last line read, for error messages
+    line_number: usize,
+    bytes_read: u64,
+    rows_read: usize,
+    file_size: u64,
+    has_more_data: bool,
+    coercion: Coercion,
+}
+
+impl JsonlSource {
+    pub fn new(file_path: &str) -> Result<Self> {
+        Ok(Self {
+            file_path: PathBuf::from(file_path),
+            reader: None,
+            line_number: 0,
+            bytes_read: 0,
+            rows_read: 0,
+            file_size: 0,
+            has_more_data: true,
+            coercion: Coercion::default(),
+        })
+    }
+
+    /// Next non-blank line as a row, or `None` at the end of the file
+    fn next_row(&mut self) -> Result<Option<Row>> {
+        let reader = self
+            .reader
+            .as_mut()
+            .ok_or_else(|| TinyEtlError::Connection("JSONL source not connected".to_string()))?;
+        let mut line = String::new();
+        loop {
+            line.clear();
+            let read = reader.read_line(&mut line)?;
+            if read == 0 {
+                return Ok(None);
+            }
+            self.bytes_read += read as u64;
+            self.line_number += 1;
+            if !line.trim().is_empty() {
+                break;
+            }
+        }
+
+        let json: serde_json::Value = serde_json::from_str(&line).map_err(|e| {
+            TinyEtlError::DataValidation(format!(
+                "Line {} of {} is not valid JSON: {}",
+                self.line_number,
+                self.file_path.display(),
+                e
+            ))
+        })?;
+        let serde_json::Value::Object(object) = json else {
+            return Err(TinyEtlError::DataValidation(format!(
+                "Line {} of {} is not a JSON object",
+                self.line_number,
+                self.file_path.display()
+            )));
+        };
+        object
+            .iter()
+            .map(|(key, value)| Ok((key.clone(), read_json_value(key, value, self.coercion)?)))
+            .collect::<Result<Row>>()
+            .map(Some)
+    }
+}
+
+#[async_trait]
+impl Source for JsonlSource {
+    async fn connect(&mut self) -> Result<()> {
+        if !self.file_path.exists() {
+            return Err(TinyEtlError::Connection(format!(
+                "JSONL file not found: {}",
+                self.file_path.display()
+            )));
+        }
+
+        self.file_size = std::fs::metadata(&self.file_path)?.len();
+        self.reset().await
+    }
+
+    async fn infer_schema(&mut self, sample_size: usize) -> Result<Schema> {
+        if self.reader.is_none() {
+            self.connect().await?;
+        }
+
+        let mut sample_rows = Vec::new();
+        while sample_rows.len() < sample_size {
+            match self.next_row()? {
+                Some(row) => sample_rows.push(row),
+                None => break,
+            }
+        }
+
+        // Reset for future reading
+        self.reset().await?;
+
+        SchemaInferer::infer_from_rows(&sample_rows)
+    }
+
+    async fn read_batch(&mut self, batch_size: usize) -> Result<Vec<Row>> {
+        if self.reader.is_none() {
+            self.connect().await?;
+        }
+
+        let mut rows = Vec::new();
+        while rows.len() < batch_size {
+            match self.next_row()? {
+                Some(row) => {
+                    rows.push(row);
+                    self.rows_read += 1;
+                }
+                None => {
+                    self.has_more_data = false;
+                    break;
+                }
+            }
+        }
+
+        Ok(rows)
+    }
+
+    async fn estimated_row_count(&self) -> Result<Option<usize>> {
+        Ok(Some(estimate_line_count(&self.file_path, false)?))
+    }
+
+    fn refined_row_count(&self) -> Option<usize> {
+        // Extrapolate from the bytes consumed by the rows read so far
+        if self.rows_read == 0 || self.bytes_read == 0 {
+            return None;
+        }
+        let avg_row_bytes = self.bytes_read as f64 / self.rows_read as f64;
+        Some((self.file_size as f64 / avg_row_bytes).round() as usize)
+    }
+
+    fn bytes_read(&self) -> Option<u64> {
+        Some(self.bytes_read)
+    }
+
+    fn set_coercion(&mut self, coercion: Coercion) {
+        self.coercion = coercion;
+    }
+
+    async fn reset(&mut self) -> Result<()> {
+        self.reader = Some(BufReader::new(File::open(&self.file_path)?));
+        self.line_number = 0;
+        self.bytes_read = 0;
+        self.rows_read = 0;
+        self.has_more_data = true;
+        Ok(())
+    }
+
+    fn has_more(&self) -> bool {
+        self.has_more_data
+    }
+}
+
+type Document = serde_json::Map<String, serde_json::Value>;
+
+/// Target writing one JSON object per line as batches arrive. Unlike JSON
+/// array files, existing files are appended to without being read back,
+/// unless rows are merged by key.
+pub struct JsonlTarget {
+    file_path: PathBuf,
+    writer: Option<BufWriter<File>>,
+    schema: Option<Schema>,
+    binary_encoding: BinaryEncoding,
+    /// Columns identifying a document. With a key the file is read, merged
+    /// with the incoming rows and rewritten by `finalize`.
+    merge_key: Vec<String>,
+    /// Documents being merged, and the position of each key among them
+    documents: Vec<Document>,
+    key_index: HashMap<String, usize>,
+    truncated: bool,
+}
+
+impl JsonlTarget {
+    pub fn new(file_path: &str) -> Result<Self> {
+        Ok(Self {
+            file_path: PathBuf::from(file_path),
+            writer: None,
+            schema: None,
+            binary_encoding: BinaryEncoding::default(),
+            merge_key: Vec::new(),
+            documents: Vec::new(),
+            key_index: HashMap::new(),
+            truncated: false,
+        })
+    }
+
+    /// Write binary values as hex instead of base64
+    pub fn with_binary_encoding(mut self, encoding: BinaryEncoding) -> Self {
+        self.binary_encoding = encoding;
+        self
+    }
+
+    /// Merge incoming rows into the documents already in the file by these
+    /// key columns: fields of a matching document are updated, rows with a
+    /// new key are appended
+    pub fn with_merge_key(mut self, columns: Vec<String>) -> Self {
+        self.merge_key = columns;
+        self
+    }
+
+    /// Key of a document, or `None` when one of the key fields is missing
+    /// or null
+    fn document_key(&self, document: &Document) -> Option<String> {
+        let parts = self
+            .merge_key
+            .iter()
+            .map(|column| match document.get(column)? {
+                serde_json::Value::Null => None,
+                serde_json::Value::String(s) => Some(s.clone()),
+                other => Some(other.to_string()),
+            })
+            .collect::<Option<Vec<_>>>()?;
+        Some(parts.join("\u{1f}"))
+    }
+
+    /// Documents of the existing file. A line that isn't a JSON object is an
+    /// error, since rewriting the file would lose it.
+    fn read_documents(&self) -> Result<Vec<Document>> {
+        let reader = BufReader::new(File::open(&self.file_path)?);
+        let mut documents = Vec::new();
+        for (number, line) in reader.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str(&line) {
+                Ok(serde_json::Value::Object(document)) => documents.push(document),
+                _ => {
+                    return Err(TinyEtlError::DataValidation(format!(
+                        "Line {} of {} is not a JSON object, so the file can't be merged into",
+                        number + 1,
+                        self.file_path.display()
+                    )))
+                }
+            }
+        }
+        Ok(documents)
+    }
+
+    fn merge_row(&mut self, row: &Row) -> Result<()> {
+        let serde_json::Value::Object(document) = self.row_to_json(row) else {
+            unreachable!("rows are written as objects");
+        };
+        let Some(key) = self.document_key(&document) else {
+            return Err(TinyEtlError::DataValidation(format!(
+                "row has no value for merge key ({}) in {}",
+                self.merge_key.join(", "),
+                self.file_path.display()
+            )));
+        };
+        match self.key_index.get(&key) {
+            // Only the fields the row carries replace the stored ones
+            Some(&index) => self.documents[index].extend(
+                document
+                    .into_iter()
+                    .filter(|(field, _)| row.contains_key(field)),
+            ),
+            None => {
+                self.key_index.insert(key, self.documents.len());
+                self.documents.push(document);
+            }
+        }
+        Ok(())
+    }
+
+    /// Replace the file with the merged documents, through a temporary
+    /// file so a failed write leaves the old one
+    fn write_documents(&self) -> Result<()> {
+        let mut temp = self.file_path.as_os_str().to_owned();
+        temp.push(".tmp");
+        let mut writer = BufWriter::new(File::create(&temp)?);
+        for document in &self.documents {
+            serde_json::to_writer(&mut writer, document)?;
+            writer.write_all(b"\n")?;
+        }
+        writer.flush()?;
+        drop(writer);
+        std::fs::rename(&temp, &self.file_path)?;
+        Ok(())
+    }
+
+    fn row_to_json(&self, row: &Row) -> serde_json::Value {
+        let object = match &self.schema {
+            Some(schema) => schema
+                .columns
+                .iter()
+                .map(|column| {
+                    let value = row
+                        .get(&column.name)
+                        .map(|v| value_to_json(v, self.binary_encoding))
+                        .unwrap_or(serde_json::Value::Null);
+                    (column.name.clone(), value)
+                })
+                .collect(),
+            None => row
+                .iter()
+                .map(|(key, value)| (key.clone(), value_to_json(value, self.binary_encoding)))
+                .collect(),
+        };
+        serde_json::Value::Object(object)
+    }
+}
+
+#[async_trait]
+impl Target for JsonlTarget {
+    async fn connect(&mut self) -> Result<()> {
+        // Create parent directory if it doesn't exist
+        if let Some(parent) = self.file_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        Ok(())
+    }
+
+    async fn create_table(&mut self, _table_name: &str, schema: &Schema) -> Result<()> {
+        self.schema = Some(schema.clone());
+        if !self.merge_key.is_empty() {
+            if let Some(missing) = self
+                .merge_key
+                .iter()
+                .find(|column| !schema.columns.iter().any(|c| c.name == **column))
+            {
+                return Err(TinyEtlError::Configuration(format!(
+                    "Merge key column '{}' is not in the output schema",
+                    missing
+                )));
+            }
+            if !self.truncated && self.file_path.exists() {
+                self.documents = self.read_documents()?;
+            }
+            // Existing documents without a key are kept but never updated
+            self.key_index = self
+                .documents
+                .iter()
+                .enumerate()
+                .filter_map(|(index, document)| self.document_key(document).map(|key| (key, index)))
+                .collect();
+            return Ok(());
+        }
+        if self.writer.is_none() {
+            let file = std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.file_path)?;
+            self.writer = Some(BufWriter::new(file));
+        }
+        Ok(())
+    }
+
+    async fn write_batch(&mut self, rows: &[Row]) -> Result<usize> {
+        if !self.merge_key.is_empty() {
+            for row in rows {
+                self.merge_row(row)?;
+            }
+            return Ok(rows.len());
+        }
+
+        let lines = rows
+            .iter()
+            .map(|row| serde_json::to_string(&self.row_to_json(row)))
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        let writer = self
+            .writer
+            .as_mut()
+            .ok_or_else(|| TinyEtlError::Connection("Writer not connected".to_string()))?;
+        for line in lines {
+            writeln!(writer, "{}", line)?;
+        }
+        Ok(rows.len())
+    }
+
+    async fn finalize(&mut self) -> Result<()> {
+        if !self.merge_key.is_empty() {
+            return self.write_documents();
+        }
+        if let Some(writer) = self.writer.as_mut() {
+            writer.flush()?;
+        }
+        Ok(())
+    }
+
+    async fn exists(&self, _table_name: &str) -> Result<bool> {
+        Ok(self.file_path.exists())
+    }
+
+    async fn truncate(&mut self, _table_name: &str) -> Result<()> {
+        if !self.merge_key.is_empty() {
+            // The file is rewritten by finalize
+            self.documents.clear();
+            self.key_index.clear();
+            self.truncated = true;
+            return Ok(());
+        }
+        self.writer = Some(BufWriter::new(File::create(&self.file_path)?));
+        Ok(())
+    }
+
+    fn supports_append(&self) -> bool {
+        true
+    }
+
+    fn bytes_written(&self) -> Option<u64> {
+        std::fs::metadata(&self.file_path).ok().map(|m| m.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::{Column, DataType, Value};
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_jsonl_source_streams_lines() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("events.jsonl");
+        std::fs::write(
+            &path,
+            "{\"id\": 1, \"level\": \"info\"}\n\n{\"id\": 2, \"level\": \"warn\"}\n{\"id\": 3}\n",
+        )
+        .unwrap();
+
+        let mut source = JsonlSource::new(path.to_str().unwrap()).unwrap();
+        source.connect().await.unwrap();
+        let schema = source.infer_schema(10).await.unwrap();
+        assert_eq!(schema.columns.len(), 2);
+
+        let first = source.read_batch(2).await.unwrap();
+        assert_eq!(first.len(), 2);
+        assert_eq!(first[1]["level"], Value::String("warn".to_string()));
+        assert!(source.has_more());
+
+        let rest = source.read_batch(2).await.unwrap();
+        assert_eq!(rest.len(), 1);
+        assert_eq!(rest[0]["id"], Value::Integer(3));
+        assert!(!source.has_more());
+    }
+
+    #[tokio::test]
+    async fn test_jsonl_source_reports_bad_line() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("events.ndjson");
+        std::fs::write(&path, "{\"id\": 1}\n[1, 2]\n").unwrap();
+
+        let mut source = JsonlSource::new(path.to_str().unwrap()).unwrap();
+        source.connect().await.unwrap();
+        let err = source.read_batch(10).await.unwrap_err();
+        assert!(err.to_string().contains("Line 2"));
+    }
+
+    #[tokio::test]
+    async fn test_jsonl_target_appends_lines() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("out.jsonl");
+        std::fs::write(&path, "{\"id\":1}\n").unwrap();
+        let schema = Schema {
+            columns: vec![Column {
+                name: "id".to_string(),
+                data_type: DataType::Integer,
+                nullable: false,
+            }],
+            estimated_rows: None,
+            primary_key_candidate: None,
+        };
+
+        let mut target = JsonlTarget::new(path.to_str().unwrap()).unwrap();
+        target.connect().await.unwrap();
+        target.create_table("out", &schema).await.unwrap();
+        let row = Row::from([("id".to_string(), Value::Integer(2))]);
+        target.write_batch(&[row]).await.unwrap();
+        target.finalize().await.unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "{\"id\":1}\n{\"id\":2}\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_jsonl_target_merges_rows_by_key() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("people.ndjson");
+        std::fs::write(
+            &path,
+            "{\"id\":1,\"name\":\"Alice\",\"city\":\"Oslo\"}\n{\"id\":2,\"name\":\"Bob\",\"city\":\"Rome\"}\n",
+        )
+        .unwrap();
+        let column = |name: &str, data_type| Column {
+            name: name.to_string(),
+            data_type,
+            nullable: true,
+        };
+        let schema = Schema {
+            columns: vec![
+                column("id", DataType::Integer),
+                column("name", DataType::String),
+                column("city", DataType::String),
+            ],
+            estimated_rows: None,
+            primary_key_candidate: None,
+        };
+
+        let mut target = JsonlTarget::new(path.to_str().unwrap())
+            .unwrap()
+            .with_merge_key(vec!["id".to_string()]);
+        target.connect().await.unwrap();
+        target.create_table("people", &schema).await.unwrap();
+        let updated = Row::from([
+            ("id".to_string(), Value::Integer(2)),
+            ("name".to_string(), Value::String("Robert".to_string())),
+        ]);
+        let added = Row::from([
+            ("id".to_string(), Value::Integer(3)),
+            ("name".to_string(), Value::String("Carol".to_string())),
+        ]);
+        target.write_batch(&[updated, added]).await.unwrap();
+
+        let missing_key = Row::from([("name".to_string(), Value::String("Dan".to_string()))]);
+        assert!(target.write_batch(&[missing_key]).await.is_err());
+        target.finalize().await.unwrap();
+
+        let lines: Vec<serde_json::Value> = std::fs::read_to_string(&path)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[1]["name"], "Robert");
+        assert_eq!(lines[1]["city"], "Rome");
+        assert_eq!(lines[2]["name"], "Carol");
+    }
+}
@@ -0,0 +1,334 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+use crate::{
+    connectors::{create_source_with_options, Source},
+    limits::SizeLimits,
+    schema::{Row, Schema},
+    state::{FileFingerprint, RunState},
+    strict::Coercion,
+    Result, TinyEtlError,
+};
+
+/// File extensions read from a folder when no file name pattern is given
+const FOLDER_FORMATS: &[&str] = &["csv", "json", "jsonl", "ndjson", "parquet", "avro"];
+
+/// Reads the files under a folder that are new or changed since the last
+/// run, one after the other, like a bucket sync. The source is a folder
+/// (`landing/`) or a folder and file name pattern (`landing/*.csv`);
+/// subfolders are included. A file counts as changed when its size or
+/// modification time differs from the one recorded in the state file, and
+/// is then read again in full.
+pub struct NewFilesSource {
+    /// Files still to read, oldest first, with their path relative to the folder
+    files: Vec<(PathBuf, String, FileFingerprint)>,
+    options: HashMap<String, String>,
+    next: usize,
+    /// Files at the front of `files` read to their end
+    drained: usize,
+    current: Option<Box<dyn Source>>,
+    /// Bytes read from the files already finished
+    finished_bytes: u64,
+    coercion: Coercion,
+    size_limits: SizeLimits,
+}
+
+impl NewFilesSource {
+    pub fn new(
+        connection_string: &str,
+        options: &HashMap<String, String>,
+        state: &RunState,
+    ) -> Result<Self> {
+        let (folder, pattern) = split_pattern(connection_string);
+        if !folder.is_dir() {
+            return Err(TinyEtlError::Configuration(format!(
+                "--new-files-only reads a local folder, not '{}'; use e.g. landing/ or landing/*.csv",
+                connection_string
+            )));
+        }
+
+        let mut files = Vec::new();
+        for path in list_files(&folder)? {
+            let relative = path
+                .strip_prefix(&folder)
+                .unwrap_or(&path)
+                .to_string_lossy()
+                .replace('\\', "/");
+            let name = path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .unwrap_or_default();
+            let wanted = match pattern {
+                Some(pattern) => wildcard_match(pattern, name),
+                None => path
+                    .extension()
+                    .and_then(|ext| ext.to_str())
+                    .is_some_and(|ext| FOLDER_FORMATS.contains(&ext.to_lowercase().as_str())),
+            };
+            if !wanted {
+                continue;
+            }
+
+            let metadata = std::fs::metadata(&path)?;
+            let fingerprint = FileFingerprint {
+                size: metadata.len(),
+                modified: DateTime::<Utc>::from(metadata.modified()?),
+            };
+            if state.files.get(&relative) != Some(&fingerprint) {
+                files.push((path, relative, fingerprint));
+            }
+        }
+        files.sort_by(|a, b| (a.2.modified, &a.1).cmp(&(b.2.modified, &b.1)));
+        info!(
+            "→ {} new or changed files in {}",
+            files.len(),
+            folder.display()
+        );
+
+        Ok(Self {
+            files,
+            options: options.clone(),
+            next: 0,
+            drained: 0,
+            current: None,
+            finished_bytes: 0,
+            coercion: Coercion::default(),
+            size_limits: SizeLimits::default(),
+        })
+    }
+
+    /// Open the next file to read, returning false once every file was read
+    async fn open_next(&mut self) -> Result<bool> {
+        if let Some(done) = self.current.take() {
+            self.finished_bytes += done.bytes_read().unwrap_or_default();
+        }
+        let Some((path, relative, _)) = self.files.get(self.next) else {
+            return Ok(false);
+        };
+        info!("→ Reading {}", relative);
+        let mut source = create_source_with_options(&path.to_string_lossy(), &self.options)?;
+        source.set_coercion(self.coercion);
+        source.set_size_limits(self.size_limits);
+        source.connect().await?;
+        // Some sources only load their data while inferring
+        source.infer_schema(1).await?;
+        self.current = Some(source);
+        self.next += 1;
+        Ok(true)
+    }
+}
+
+/// Split `landing/*.csv` into the folder and the file name pattern
+fn split_pattern(connection_string: &str) -> (PathBuf, Option<&str>) {
+    let path = Path::new(connection_string);
+    match path.file_name().and_then(|name| name.to_str()) {
+        Some(name) if name.contains(['*', '?']) => (
+            path.parent().unwrap_or(Path::new(".")).to_path_buf(),
+            Some(name),
+        ),
+        _ => (path.to_path_buf(), None),
+    }
+}
+
+/// Every file under `folder`, subfolders included
+fn list_files(folder: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut folders = vec![folder.to_path_buf()];
+    while let Some(folder) = folders.pop() {
+        for entry in std::fs::read_dir(&folder)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                folders.push(path);
+            } else {
+                files.push(path);
+            }
+        }
+    }
+    Ok(files)
+}
+
+/// Match a file name against a pattern where `*` stands for any run of
+/// characters and `?` for a single one
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // Position after the last `*` and the name position it was tried at
+    let mut backtrack = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p + 1, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match backtrack {
+                Some((star_p, star_n)) => {
+                    p = star_p;
+                    n = star_n + 1;
+                    backtrack = Some((star_p, star_n + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[async_trait]
+impl Source for NewFilesSource {
+    async fn connect(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    async fn infer_schema(&mut self, sample_size: usize) -> Result<Schema> {
+        let Some((path, _, _)) = self.files.first() else {
+            return Err(TinyEtlError::Configuration(
+                "No new or changed files to infer a schema from".to_string(),
+            ));
+        };
+        let mut source = create_source_with_options(&path.to_string_lossy(), &self.options)?;
+        source.set_coercion(self.coercion);
+        source.set_size_limits(self.size_limits);
+        source.connect().await?;
+        source.infer_schema(sample_size).await
+    }
+
+    async fn read_batch(&mut self, batch_size: usize) -> Result<Vec<Row>> {
+        loop {
+            if let Some(source) = self.current.as_mut() {
+                let rows = source.read_batch(batch_size).await?;
+                if !rows.is_empty() {
+                    return Ok(rows);
+                }
+                self.drained = self.next;
+            }
+            if !self.open_next().await? {
+                return Ok(Vec::new());
+            }
+        }
+    }
+
+    async fn estimated_row_count(&self) -> Result<Option<usize>> {
+        Ok(None)
+    }
+
+    fn bytes_read(&self) -> Option<u64> {
+        let current = self.current.as_ref().and_then(|source| source.bytes_read());
+        Some(self.finished_bytes + current.unwrap_or_default())
+    }
+
+    fn is_up_to_date(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// Only files read to their end are recorded; one a `--limit` stopped in
+    /// is read again in full by the next run
+    fn record_state(&self, state: &mut RunState) {
+        let current_done = self.current.as_ref().is_some_and(|s| !s.has_more());
+        let read = if current_done {
+            self.next
+        } else {
+            self.drained
+        };
+        for (_, relative, fingerprint) in &self.files[..read] {
+            state.files.insert(relative.clone(), fingerprint.clone());
+        }
+    }
+
+    async fn reset(&mut self) -> Result<()> {
+        self.next = 0;
+        self.drained = 0;
+        self.current = None;
+        self.finished_bytes = 0;
+        Ok(())
+    }
+
+    fn has_more(&self) -> bool {
+        self.next < self.files.len() || self.current.as_ref().is_some_and(|s| s.has_more())
+    }
+
+    fn set_coercion(&mut self, coercion: Coercion) {
+        self.coercion = coercion;
+    }
+
+    fn set_size_limits(&mut self, limits: SizeLimits) {
+        self.size_limits = limits;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn read_all(source: &mut NewFilesSource) -> Vec<Row> {
+        let mut rows = Vec::new();
+        loop {
+            let batch = source.read_batch(2).await.unwrap();
+            if batch.is_empty() {
+                return rows;
+            }
+            rows.extend(batch);
+        }
+    }
+
+    #[test]
+    fn test_wildcard_match() {
+        assert!(wildcard_match("*.csv", "orders_2024.csv"));
+        assert!(wildcard_match("orders_????.csv", "orders_2024.csv"));
+        assert!(wildcard_match("*_*.csv", "a_b_c.csv"));
+        assert!(!wildcard_match("*.csv", "orders.json"));
+        assert!(!wildcard_match("orders_?.csv", "orders_10.csv"));
+    }
+
+    #[tokio::test]
+    async fn test_only_new_and_changed_files_are_read() {
+        let dir = tempfile::tempdir().unwrap();
+        let folder = dir.path().join("landing");
+        std::fs::create_dir_all(folder.join("2024")).unwrap();
+        std::fs::write(folder.join("a.csv"), "id,name\n1,ann\n2,bob\n").unwrap();
+        std::fs::write(folder.join("2024").join("b.csv"), "id,name\n3,cy\n").unwrap();
+        std::fs::write(folder.join("notes.txt"), "not data").unwrap();
+        let pattern = folder.join("*.csv");
+        let pattern = pattern.to_str().unwrap();
+
+        let mut state = RunState::default();
+        let mut source = NewFilesSource::new(pattern, &HashMap::new(), &state).unwrap();
+        assert_eq!(source.infer_schema(10).await.unwrap().columns.len(), 2);
+        assert_eq!(read_all(&mut source).await.len(), 3);
+        source.record_state(&mut state);
+        assert!(state.files.contains_key("2024/b.csv"));
+
+        let source = NewFilesSource::new(pattern, &HashMap::new(), &state).unwrap();
+        assert!(source.is_up_to_date());
+
+        std::fs::write(folder.join("c.csv"), "id,name\n4,dee\n").unwrap();
+        std::fs::write(folder.join("a.csv"), "id,name\n1,ann\n2,bob\n5,eve\n").unwrap();
+        let mut source = NewFilesSource::new(pattern, &HashMap::new(), &state).unwrap();
+        assert_eq!(read_all(&mut source).await.len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_partly_read_files_are_not_recorded() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.csv"), "id\n1\n2\n3\n").unwrap();
+        let folder = dir.path().to_str().unwrap();
+
+        let mut state = RunState::default();
+        let mut source = NewFilesSource::new(folder, &HashMap::new(), &state).unwrap();
+        // A --limit of 2 stops the run before the end of the file
+        assert_eq!(source.read_batch(2).await.unwrap().len(), 2);
+        source.record_state(&mut state);
+        assert!(state.files.is_empty());
+
+        assert_eq!(read_all(&mut source).await.len(), 1);
+        source.record_state(&mut state);
+        assert!(state.files.contains_key("a.csv"));
+    }
+}
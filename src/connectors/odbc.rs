@@ -6,13 +6,17 @@ use odbc_api::{
 use std::sync::Arc;
 
 use crate::{
-    connectors::{Source, Target},
-    schema::{Column, DataType, Row, Schema, Value},
+    connectors::{
+        sql_echo,
+        sql_ident::{quote_table, QuoteStyle},
+        Source, Target,
+    },
+    schema::{BinaryEncoding, Column, DataType, Row, Schema, Value},
     Result, TinyEtlError,
 };
 
 /// Wrapper that keeps Environment and Connection together with proper lifetimes
-struct OdbcConnection {
+pub(crate) struct OdbcConnection {
     _env: Arc<Environment>,
     // Connection is stored as a raw pointer to avoid lifetime issues
     // We manually manage the lifetime to ensure env outlives connection
@@ -20,7 +24,7 @@ struct OdbcConnection {
 }
 
 impl OdbcConnection {
-    fn new(connection_string: &str) -> Result<Self> {
+    pub(crate) fn new(connection_string: &str) -> Result<Self> {
         let env = Environment::new().map_err(|e| {
             TinyEtlError::Connection(format!("Failed to create ODBC environment: {}", e))
         })?;
@@ -47,7 +51,7 @@ impl OdbcConnection {
         })
     }
 
-    fn connection(&self) -> &Connection<'static> {
+    pub(crate) fn connection(&self) -> &Connection<'static> {
         unsafe { &*self.connection }
     }
 }
@@ -111,7 +115,7 @@ impl OdbcSource {
         }
     }
 
-    fn map_odbc_type_to_datatype(col_desc: &ColumnDescription) -> DataType {
+    pub(crate) fn map_odbc_type_to_datatype(col_desc: &ColumnDescription) -> DataType {
         use odbc_api::DataType as OdbcDataType;
 
         match &col_desc.data_type {
@@ -161,7 +165,10 @@ impl Source for OdbcSource {
 
         // Query to get column metadata - fetch a single row to infer schema
         // Use quoted identifier for table name
-        let query = format!("SELECT * FROM [{}] WHERE 1=0", self.table_name);
+        let query = format!(
+            "SELECT * FROM {} WHERE 1=0",
+            quote_table(&self.table_name, QuoteStyle::Bracket)
+        );
 
         let cursor = conn
             .execute(&query, ())
@@ -232,26 +239,27 @@ impl Source for OdbcSource {
 
         // PERFORMANCE OPTIMIZATION: Use cursor-based pagination with PK if available
         // This is MUCH faster than OFFSET for large tables (O(1) vs O(n))
+        let relation = quote_table(&self.table_name, QuoteStyle::Bracket);
         let query = if let Some(pk_col) = &self.pk_column {
             if let Some(last_val) = &self.last_pk_value {
                 // Cursor-based: WHERE pk > last_value ORDER BY pk
                 format!(
-                    "SELECT * FROM [{}] WHERE [{}] > {} ORDER BY [{}] ASC FETCH NEXT {} ROWS ONLY",
-                    self.table_name, pk_col, last_val, pk_col, batch_size
+                    "SELECT * FROM {} WHERE [{}] > {} ORDER BY [{}] ASC FETCH NEXT {} ROWS ONLY",
+                    relation, pk_col, last_val, pk_col, batch_size
                 )
             } else {
                 // First batch with PK ordering
                 format!(
-                    "SELECT * FROM [{}] ORDER BY [{}] ASC FETCH NEXT {} ROWS ONLY",
-                    self.table_name, pk_col, batch_size
+                    "SELECT * FROM {} ORDER BY [{}] ASC FETCH NEXT {} ROWS ONLY",
+                    relation, pk_col, batch_size
                 )
             }
         } else {
             // Fallback: OFFSET-based pagination (slower for large tables)
             // Note: This syntax works for SQL Server, PostgreSQL, and many modern databases
             format!(
-                "SELECT * FROM [{}] ORDER BY (SELECT NULL) OFFSET {} ROWS FETCH NEXT {} ROWS ONLY",
-                self.table_name, self.current_offset, batch_size
+                "SELECT * FROM {} ORDER BY (SELECT NULL) OFFSET {} ROWS FETCH NEXT {} ROWS ONLY",
+                relation, self.current_offset, batch_size
             )
         };
 
@@ -263,90 +271,13 @@ impl Source for OdbcSource {
             None => return Ok(Vec::new()),
         };
 
-        let num_cols = cursor.num_result_cols().map_err(|e| {
-            TinyEtlError::DataTransfer(format!("Failed to get column count: {}", e))
-        })?;
-
-        // Get column names and types
-        let mut col_info = Vec::new();
-        let mut pk_col_idx = None;
-
-        for i in 1..=num_cols {
-            let mut col_desc = ColumnDescription::default();
-            cursor.describe_col(i as u16, &mut col_desc).map_err(|e| {
-                TinyEtlError::DataTransfer(format!("Failed to describe column {}: {}", i, e))
-            })?;
-
-            let name = col_desc.name_to_string().map_err(|e| {
-                TinyEtlError::DataTransfer(format!("Failed to get column name: {}", e))
-            })?;
-
-            // Track PK column index for cursor updates
-            if let Some(pk_col) = &self.pk_column {
-                if &name == pk_col {
-                    pk_col_idx = Some(i as u16);
-                }
-            }
-
-            col_info.push((name, i as u16));
-        }
-
-        // PERFORMANCE OPTIMIZATION: Reuse a single buffer for all text reads
-        // This eliminates allocating 4KB per column per row
-        let mut text_buffer = vec![0u8; 8192]; // Larger buffer for better performance
+        let rows = read_text_rows(&mut cursor, batch_size)?;
 
-        // Fetch rows one by one
-        let mut rows = Vec::new();
-
-        while let Some(mut row_cursor) = cursor
-            .next_row()
-            .map_err(|e| TinyEtlError::DataTransfer(format!("Failed to fetch row: {}", e)))?
-        {
-            if rows.len() >= batch_size {
-                break;
-            }
-
-            let mut row = Row::new();
-            let mut pk_value = None;
-
-            for (col_name, col_idx) in &col_info {
-                // Reuse buffer for text reading
-                text_buffer.clear();
-                text_buffer.resize(8192, 0);
-
-                let is_non_null = row_cursor
-                    .get_text(*col_idx, &mut text_buffer)
-                    .map_err(|e| {
-                        TinyEtlError::DataTransfer(format!("Failed to get column value: {}", e))
-                    })?;
-
-                let value = if is_non_null {
-                    // Convert buffer to string - find the null terminator
-                    let end = text_buffer
-                        .iter()
-                        .position(|&b| b == 0)
-                        .unwrap_or(text_buffer.len());
-                    let text = String::from_utf8_lossy(&text_buffer[..end]).to_string();
-
-                    // Track PK value for next cursor iteration
-                    if Some(*col_idx) == pk_col_idx {
-                        pk_value = Some(text.clone());
-                    }
-
-                    Value::String(text)
-                } else {
-                    Value::Null
-                };
-
-                row.insert(col_name.clone(), value);
+        // Update last PK value for cursor-based pagination
+        if let Some(pk_col) = &self.pk_column {
+            if let Some(Value::String(last)) = rows.last().and_then(|row| row.get(pk_col)) {
+                self.last_pk_value = Some(last.clone());
             }
-
-            // Update last PK value for cursor-based pagination
-            if let Some(val) = pk_value {
-                self.last_pk_value = Some(val);
-            }
-
-            rows.push(row);
         }
 
         self.current_offset += rows.len();
@@ -358,6 +289,11 @@ impl Source for OdbcSource {
         Ok(self.total_rows)
     }
 
+    // Only a table with a primary key is read in key order
+    async fn has_stable_order(&mut self) -> Result<bool> {
+        Ok(self.pk_column.is_some())
+    }
+
     async fn reset(&mut self) -> Result<()> {
         self.current_offset = 0;
         self.last_pk_value = None;
@@ -370,6 +306,126 @@ impl Source for OdbcSource {
     }
 }
 
+/// Value for an ODBC connection string attribute, in braces when it holds
+/// characters that would end it early
+pub(crate) fn odbc_value(value: &str) -> String {
+    if value.contains([';', '{', '}', '=']) || value.trim() != value {
+        format!("{{{}}}", value.replace('}', "}}"))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Run a query returning a single value, as text
+pub(crate) fn query_scalar(connection: &OdbcConnection, query: &str) -> Result<Option<String>> {
+    let cursor = connection
+        .connection()
+        .execute(query, ())
+        .map_err(|e| TinyEtlError::DataTransfer(format!("Failed to execute query: {}", e)))?;
+    let Some(mut cursor) = cursor else {
+        return Ok(None);
+    };
+    let rows = read_text_rows(&mut cursor, 1)?;
+    Ok(rows
+        .into_iter()
+        .next()
+        .and_then(|row| row.into_values().next())
+        .and_then(|value| match value {
+            Value::String(text) => Some(text.trim().to_string()),
+            _ => None,
+        }))
+}
+
+/// Columns of a result set, with their types and nullability
+pub(crate) fn describe_columns(cursor: &mut impl ResultSetMetadata) -> Result<Vec<Column>> {
+    let num_cols = cursor
+        .num_result_cols()
+        .map_err(|e| TinyEtlError::SchemaInference(format!("Failed to describe table: {}", e)))?;
+    let mut columns = Vec::new();
+    for i in 1..=num_cols {
+        let mut description = ColumnDescription::default();
+        cursor
+            .describe_col(i as u16, &mut description)
+            .map_err(|e| {
+                TinyEtlError::SchemaInference(format!("Failed to describe column {}: {}", i, e))
+            })?;
+        let name = description.name_to_string().map_err(|e| {
+            TinyEtlError::SchemaInference(format!("Failed to get column name: {}", e))
+        })?;
+        columns.push(Column {
+            name,
+            data_type: OdbcSource::map_odbc_type_to_datatype(&description),
+            nullable: description.nullability != odbc_api::Nullability::NoNulls,
+        });
+    }
+    Ok(columns)
+}
+
+/// Read up to `max_rows` rows of a result set, every value as text
+pub(crate) fn read_text_rows(cursor: &mut impl Cursor, max_rows: usize) -> Result<Vec<Row>> {
+    let num_cols = cursor
+        .num_result_cols()
+        .map_err(|e| TinyEtlError::DataTransfer(format!("Failed to get column count: {}", e)))?;
+
+    let mut col_info = Vec::new();
+    for i in 1..=num_cols {
+        let mut col_desc = ColumnDescription::default();
+        cursor.describe_col(i as u16, &mut col_desc).map_err(|e| {
+            TinyEtlError::DataTransfer(format!("Failed to describe column {}: {}", i, e))
+        })?;
+
+        let name = col_desc
+            .name_to_string()
+            .map_err(|e| TinyEtlError::DataTransfer(format!("Failed to get column name: {}", e)))?;
+        col_info.push((name, i as u16));
+    }
+
+    // PERFORMANCE OPTIMIZATION: Reuse a single buffer for all text reads
+    // This eliminates allocating 4KB per column per row
+    let mut text_buffer = vec![0u8; 8192]; // Larger buffer for better performance
+
+    // Fetch rows one by one
+    let mut rows = Vec::new();
+
+    while let Some(mut row_cursor) = cursor
+        .next_row()
+        .map_err(|e| TinyEtlError::DataTransfer(format!("Failed to fetch row: {}", e)))?
+    {
+        if rows.len() >= max_rows {
+            break;
+        }
+
+        let mut row = Row::new();
+        for (col_name, col_idx) in &col_info {
+            // Reuse buffer for text reading
+            text_buffer.clear();
+            text_buffer.resize(8192, 0);
+
+            let is_non_null = row_cursor
+                .get_text(*col_idx, &mut text_buffer)
+                .map_err(|e| {
+                    TinyEtlError::DataTransfer(format!("Failed to get column value: {}", e))
+                })?;
+
+            let value = if is_non_null {
+                // Convert buffer to string - find the null terminator
+                let end = text_buffer
+                    .iter()
+                    .position(|&b| b == 0)
+                    .unwrap_or(text_buffer.len());
+                Value::String(String::from_utf8_lossy(&text_buffer[..end]).to_string())
+            } else {
+                Value::Null
+            };
+
+            row.insert(col_name.clone(), value);
+        }
+        rows.push(row);
+    }
+
+    Ok(rows)
+}
+
 /// ODBC target connector for writing data to ODBC-compatible databases
 pub struct OdbcTarget {
     connection_string: String,
@@ -412,6 +468,7 @@ impl OdbcTarget {
             DataType::Integer => "BIGINT",
             DataType::Decimal => "DECIMAL(18,4)",
             DataType::Boolean => "BIT",
+            DataType::Binary => "VARCHAR(MAX)", // Parameters are bound as text, so base64
             DataType::Date => "DATE",
             DataType::DateTime => "DATETIME2", // Use DATETIME2 instead of TIMESTAMP for SQL Server
             DataType::Json => "NVARCHAR(MAX)", // ODBC/SQL Server stores JSON as NVARCHAR
@@ -454,12 +511,18 @@ impl Target for OdbcTarget {
         let conn = conn.connection();
 
         // Check if table already exists
-        let check_query = format!("SELECT 1 FROM [{}] WHERE 1=0", table_name);
+        let check_query = format!(
+            "SELECT 1 FROM {} WHERE 1=0",
+            quote_table(table_name, QuoteStyle::Bracket)
+        );
         let table_exists = conn.execute(&check_query, ()).is_ok();
 
         if !table_exists {
             // Build CREATE TABLE statement with proper identifier quoting for SQL Server
-            let mut create_sql = format!("CREATE TABLE [{}] (", table_name);
+            let mut create_sql = format!(
+                "CREATE TABLE {} (",
+                quote_table(table_name, QuoteStyle::Bracket)
+            );
 
             for (i, col) in schema.columns.iter().enumerate() {
                 if i > 0 {
@@ -476,6 +539,7 @@ impl Target for OdbcTarget {
             create_sql.push(')');
 
             // Execute CREATE TABLE
+            sql_echo::echo(&create_sql);
             conn.execute(&create_sql, ()).map_err(|e| {
                 TinyEtlError::DataTransfer(format!("Failed to create table: {}", e))
             })?;
@@ -557,6 +621,9 @@ impl Target for OdbcTarget {
                                 serde_json::to_string(j).unwrap_or_else(|_| "{}".to_string());
                             param_strings.push(Some(json_str));
                         }
+                        Value::Bytes(b) => {
+                            param_strings.push(Some(BinaryEncoding::default().encode(b)));
+                        }
                     }
                 }
             }
@@ -584,8 +651,16 @@ impl Target for OdbcTarget {
                 .join(", ");
 
             let insert_sql = format!(
-                "INSERT INTO [{}] ({}) VALUES {}",
-                self.table_name, column_names, all_value_sets
+                "INSERT INTO {} ({}) VALUES {}",
+                quote_table(&self.table_name, QuoteStyle::Bracket),
+                column_names,
+                all_value_sets
+            );
+
+            sql_echo::echo_rows(
+                &insert_sql,
+                schema.columns.iter().map(|c| c.name.as_str()),
+                chunk,
             );
 
             // Prepare and execute
@@ -630,7 +705,10 @@ impl Target for OdbcTarget {
         let conn = conn.connection();
 
         // Try to query the table - if it fails, it doesn't exist
-        let query = format!("SELECT 1 FROM [{}] WHERE 1=0", table_name);
+        let query = format!(
+            "SELECT 1 FROM {} WHERE 1=0",
+            quote_table(table_name, QuoteStyle::Bracket)
+        );
         let result = conn.execute(&query, ());
 
         match result {
@@ -651,13 +729,39 @@ impl Target for OdbcTarget {
         let conn = conn.connection();
 
         // Use DELETE instead of TRUNCATE for better compatibility
-        let truncate_sql = format!("DELETE FROM [{}]", table_name);
+        let truncate_sql = format!(
+            "DELETE FROM {}",
+            quote_table(table_name, QuoteStyle::Bracket)
+        );
+        sql_echo::echo(&truncate_sql);
         conn.execute(&truncate_sql, ())
             .map_err(|e| TinyEtlError::DataTransfer(format!("Failed to truncate table: {}", e)))?;
 
         Ok(())
     }
 
+    async fn drop_table(&mut self, table_name: &str) -> Result<()> {
+        if self.connection.is_none() {
+            self.connect().await?;
+        }
+
+        let conn = self.connection.as_ref().ok_or_else(|| {
+            TinyEtlError::Connection("ODBC connection not established".to_string())
+        })?;
+
+        let conn = conn.connection();
+
+        let drop_sql = format!(
+            "DROP TABLE {}",
+            quote_table(table_name, QuoteStyle::Bracket)
+        );
+        sql_echo::echo(&drop_sql);
+        conn.execute(&drop_sql, ())
+            .map_err(|e| TinyEtlError::DataTransfer(format!("Failed to drop table: {}", e)))?;
+
+        Ok(())
+    }
+
     fn supports_append(&self) -> bool {
         true
     }
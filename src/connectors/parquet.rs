@@ -3,14 +3,16 @@ use arrow::datatypes::*;
 use arrow::record_batch::RecordBatch;
 use async_trait::async_trait;
 use parquet::arrow::{arrow_reader::ParquetRecordBatchReaderBuilder, ArrowWriter};
-use rust_decimal::Decimal;
+use parquet::basic::{BrotliLevel, Compression, GzipLevel, ZstdLevel};
+use parquet::file::properties::WriterProperties;
 use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::Arc;
 
 use crate::{
     connectors::{Source, Target},
-    schema::{Row, Schema, Value},
+    schema::{BinaryEncoding, Row, Schema, Value},
+    strict::Coercion,
     Result, TinyEtlError,
 };
 
@@ -19,6 +21,7 @@ pub struct ParquetSource {
     all_batches: Vec<RecordBatch>,
     current_batch_index: usize,
     total_row_count: Option<usize>,
+    coercion: Coercion,
 }
 
 impl ParquetSource {
@@ -28,6 +31,7 @@ impl ParquetSource {
             all_batches: Vec::new(),
             current_batch_index: 0,
             total_row_count: None,
+            coercion: Coercion::default(),
         })
     }
 
@@ -39,6 +43,7 @@ impl ParquetSource {
         array: &dyn Array,
         column_name: &str,
         field: &Field,
+        coercion: Coercion,
     ) -> Result<Vec<(String, Value)>> {
         let mut values = Vec::new();
 
@@ -116,11 +121,7 @@ impl ParquetSource {
                     let value = if float_array.is_null(i) {
                         Value::Null
                     } else {
-                        // Convert f64 to Decimal
-                        match Decimal::try_from(float_array.value(i)) {
-                            Ok(d) => Value::Decimal(d),
-                            Err(_) => Value::String(float_array.value(i).to_string()),
-                        }
+                        coercion.decimal_from_f64(float_array.value(i), column_name)?
                     };
                     values.push((column_name.to_string(), value));
                 }
@@ -162,12 +163,72 @@ impl ParquetSource {
                         let nanoseconds = (timestamp_ns % 1_000_000_000) as u32;
                         match chrono::DateTime::from_timestamp(timestamp_s, nanoseconds) {
                             Some(dt) => Value::Date(dt),
-                            None => Value::Null,
+                            None => coercion.lossy(
+                                column_name,
+                                &timestamp_ns.to_string(),
+                                "is not a valid timestamp and would be read as NULL",
+                                Value::Null,
+                            )?,
                         }
                     };
                     values.push((column_name.to_string(), value));
                 }
             }
+            DataType::Binary => {
+                let binary_array =
+                    array
+                        .as_any()
+                        .downcast_ref::<BinaryArray>()
+                        .ok_or_else(|| {
+                            TinyEtlError::DataTransfer(
+                                "Failed to downcast to BinaryArray".to_string(),
+                            )
+                        })?;
+                for i in 0..binary_array.len() {
+                    let value = if binary_array.is_null(i) {
+                        Value::Null
+                    } else {
+                        Value::Bytes(binary_array.value(i).to_vec())
+                    };
+                    values.push((column_name.to_string(), value));
+                }
+            }
+            DataType::LargeBinary => {
+                let binary_array = array
+                    .as_any()
+                    .downcast_ref::<LargeBinaryArray>()
+                    .ok_or_else(|| {
+                        TinyEtlError::DataTransfer(
+                            "Failed to downcast to LargeBinaryArray".to_string(),
+                        )
+                    })?;
+                for i in 0..binary_array.len() {
+                    let value = if binary_array.is_null(i) {
+                        Value::Null
+                    } else {
+                        Value::Bytes(binary_array.value(i).to_vec())
+                    };
+                    values.push((column_name.to_string(), value));
+                }
+            }
+            DataType::FixedSizeBinary(_) => {
+                let binary_array = array
+                    .as_any()
+                    .downcast_ref::<FixedSizeBinaryArray>()
+                    .ok_or_else(|| {
+                        TinyEtlError::DataTransfer(
+                            "Failed to downcast to FixedSizeBinaryArray".to_string(),
+                        )
+                    })?;
+                for i in 0..binary_array.len() {
+                    let value = if binary_array.is_null(i) {
+                        Value::Null
+                    } else {
+                        Value::Bytes(binary_array.value(i).to_vec())
+                    };
+                    values.push((column_name.to_string(), value));
+                }
+            }
             _ => {
                 // For unsupported types, convert to string representation
                 for i in 0..array.len() {
@@ -175,7 +236,12 @@ impl ParquetSource {
                         Value::Null
                     } else {
                         // This is a fallback - in production you might want more sophisticated conversion
-                        Value::String(format!("unsupported_type_row_{}", i))
+                        coercion.lossy(
+                            column_name,
+                            &format!("of type {}", field.data_type()),
+                            "is not supported and would be read as a placeholder",
+                            Value::String(format!("unsupported_type_row_{}", i)),
+                        )?
                     };
                     values.push((column_name.to_string(), value));
                 }
@@ -185,14 +251,18 @@ impl ParquetSource {
         Ok(values)
     }
 
-    fn record_batch_to_rows(batch: &RecordBatch) -> Result<Vec<Row>> {
+    pub(crate) fn record_batch_to_rows(
+        batch: &RecordBatch,
+        coercion: Coercion,
+    ) -> Result<Vec<Row>> {
         let num_rows = batch.num_rows();
         let mut rows = vec![HashMap::new(); num_rows];
 
         let schema = batch.schema();
         for (col_index, field) in schema.fields().iter().enumerate() {
             let column = batch.column(col_index);
-            let column_values = Self::arrow_array_to_values(column.as_ref(), field.name(), field)?;
+            let column_values =
+                Self::arrow_array_to_values(column.as_ref(), field.name(), field, coercion)?;
 
             for (row_index, (column_name, value)) in column_values.into_iter().enumerate() {
                 if row_index < num_rows {
@@ -283,7 +353,7 @@ impl Source for ParquetSource {
         let batch = &self.all_batches[self.current_batch_index];
         self.current_batch_index += 1;
 
-        let mut rows = Self::record_batch_to_rows(batch)?;
+        let mut rows = Self::record_batch_to_rows(batch, self.coercion)?;
 
         // If requested batch size is smaller than the record batch, we need to split it
         if rows.len() > batch_size {
@@ -300,6 +370,10 @@ impl Source for ParquetSource {
         Ok(self.total_row_count)
     }
 
+    fn set_coercion(&mut self, coercion: Coercion) {
+        self.coercion = coercion;
+    }
+
     async fn reset(&mut self) -> Result<()> {
         self.current_batch_index = 0;
         Ok(())
@@ -315,6 +389,7 @@ pub struct ParquetTarget {
     schema: Option<Arc<arrow::datatypes::Schema>>,
     buffered_rows: Vec<Row>,
     is_finalized: bool,
+    compression: Option<Compression>,
 }
 
 impl ParquetTarget {
@@ -324,14 +399,80 @@ impl ParquetTarget {
             schema: None,
             buffered_rows: Vec::new(),
             is_finalized: false,
+            compression: None,
         })
     }
 
+    /// Set the compression codec and level used when writing the file.
+    /// When only a level is given the codec defaults to zstd.
+    pub fn with_compression(mut self, codec: Option<&str>, level: Option<i32>) -> Result<Self> {
+        self.compression = Self::parse_compression(codec, level)?;
+        Ok(self)
+    }
+
+    fn parse_compression(codec: Option<&str>, level: Option<i32>) -> Result<Option<Compression>> {
+        let codec = match (codec, level) {
+            (None, None) => return Ok(None),
+            (None, Some(_)) => "zstd".to_string(),
+            (Some(codec), _) => codec.to_lowercase(),
+        };
+
+        let invalid_level = |e: parquet::errors::ParquetError| {
+            TinyEtlError::Configuration(format!("Invalid compression level for {}: {}", codec, e))
+        };
+        let unsigned_level = |level: i32| {
+            u32::try_from(level).map_err(|_| {
+                TinyEtlError::Configuration(format!(
+                    "Invalid compression level for {}: {} (must not be negative)",
+                    codec, level
+                ))
+            })
+        };
+
+        let compression = match codec.as_str() {
+            "zstd" => Compression::ZSTD(match level {
+                Some(level) => ZstdLevel::try_new(level).map_err(invalid_level)?,
+                None => ZstdLevel::default(),
+            }),
+            "gzip" => Compression::GZIP(match level {
+                Some(level) => GzipLevel::try_new(unsigned_level(level)?).map_err(invalid_level)?,
+                None => GzipLevel::default(),
+            }),
+            "brotli" => Compression::BROTLI(match level {
+                Some(level) => {
+                    BrotliLevel::try_new(unsigned_level(level)?).map_err(invalid_level)?
+                }
+                None => BrotliLevel::default(),
+            }),
+            "snappy" | "lz4" | "none" | "uncompressed" => {
+                if level.is_some() {
+                    return Err(TinyEtlError::Configuration(format!(
+                        "Compression codec '{}' does not support a compression level",
+                        codec
+                    )));
+                }
+                match codec.as_str() {
+                    "snappy" => Compression::SNAPPY,
+                    "lz4" => Compression::LZ4_RAW,
+                    _ => Compression::UNCOMPRESSED,
+                }
+            }
+            other => {
+                return Err(TinyEtlError::Configuration(format!(
+                    "Unsupported parquet compression codec '{}'. Valid values: zstd, gzip, brotli, snappy, lz4, none",
+                    other
+                )))
+            }
+        };
+
+        Ok(Some(compression))
+    }
+
     fn schema_to_arrow_schema(schema: &Schema) -> Arc<arrow::datatypes::Schema> {
         Arc::new(schema.to_arrow_schema())
     }
 
-    fn rows_to_record_batch(
+    pub(crate) fn rows_to_record_batch(
         rows: &[Row],
         schema: &Arc<arrow::datatypes::Schema>,
     ) -> Result<RecordBatch> {
@@ -351,6 +492,9 @@ impl ParquetTarget {
                         match row.get(column_name) {
                             Some(Value::String(s)) => builder.append_value(s),
                             Some(Value::Json(j)) => builder.append_value(j.to_string()),
+                            Some(Value::Bytes(b)) => {
+                                builder.append_value(BinaryEncoding::default().encode(b))
+                            }
                             Some(Value::Null) => builder.append_null(),
                             Some(other) => builder.append_value(format!("{:?}", other)),
                             None => builder.append_null(),
@@ -413,6 +557,18 @@ impl ParquetTarget {
                     }
                     arrays.push(Arc::new(builder.finish()));
                 }
+                DataType::Binary => {
+                    let mut builder = BinaryBuilder::new();
+                    for row in rows {
+                        match row.get(column_name) {
+                            Some(Value::Bytes(b)) => builder.append_value(b),
+                            Some(Value::Null) => builder.append_null(),
+                            None => builder.append_null(),
+                            _ => builder.append_null(),
+                        }
+                    }
+                    arrays.push(Arc::new(builder.finish()));
+                }
                 _ => {
                     // Default to string for unsupported types
                     let mut builder = StringBuilder::new();
@@ -476,7 +632,13 @@ impl Target for ParquetTarget {
         // Create the file and writer
         let file = std::fs::File::create(&self.file_path).map_err(TinyEtlError::Io)?;
 
-        let mut writer = ArrowWriter::try_new(file, schema.clone(), None).map_err(|e| {
+        let props = self.compression.map(|compression| {
+            WriterProperties::builder()
+                .set_compression(compression)
+                .build()
+        });
+
+        let mut writer = ArrowWriter::try_new(file, schema.clone(), props).map_err(|e| {
             TinyEtlError::Connection(format!("Failed to create parquet writer: {}", e))
         })?;
 
@@ -510,6 +672,10 @@ impl Target for ParquetTarget {
         // For simplicity, we return false to force truncation
         false
     }
+
+    fn bytes_written(&self) -> Option<u64> {
+        std::fs::metadata(&self.file_path).ok().map(|m| m.len())
+    }
 }
 
 #[cfg(test)]
@@ -580,4 +746,64 @@ mod tests {
         let read_rows = source.read_batch(100).await.unwrap();
         assert_eq!(read_rows.len(), 2);
     }
+
+    #[test]
+    fn test_parquet_compression_parsing() {
+        assert_eq!(ParquetTarget::parse_compression(None, None).unwrap(), None);
+        assert_eq!(
+            ParquetTarget::parse_compression(None, Some(9)).unwrap(),
+            Some(Compression::ZSTD(ZstdLevel::try_new(9).unwrap()))
+        );
+        assert_eq!(
+            ParquetTarget::parse_compression(Some("GZIP"), Some(6)).unwrap(),
+            Some(Compression::GZIP(GzipLevel::try_new(6).unwrap()))
+        );
+        assert_eq!(
+            ParquetTarget::parse_compression(Some("snappy"), None).unwrap(),
+            Some(Compression::SNAPPY)
+        );
+
+        assert!(ParquetTarget::parse_compression(Some("zstd"), Some(99)).is_err());
+        assert!(ParquetTarget::parse_compression(Some("gzip"), Some(-1)).is_err());
+        assert!(ParquetTarget::parse_compression(Some("snappy"), Some(3)).is_err());
+        assert!(ParquetTarget::parse_compression(Some("lzma"), None).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_parquet_write_with_compression_level() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("compressed.parquet");
+
+        let schema = Schema {
+            columns: vec![Column {
+                name: "id".to_string(),
+                data_type: DataType::Integer,
+                nullable: false,
+            }],
+            estimated_rows: None,
+            primary_key_candidate: None,
+        };
+
+        let rows: Vec<Row> = (0..100)
+            .map(|i| {
+                let mut row = HashMap::new();
+                row.insert("id".to_string(), Value::Integer(i));
+                row
+            })
+            .collect();
+
+        let mut target = ParquetTarget::new(file_path.to_str().unwrap())
+            .unwrap()
+            .with_compression(Some("zstd"), Some(19))
+            .unwrap();
+        target.connect().await.unwrap();
+        target.create_table("test_table", &schema).await.unwrap();
+        target.write_batch(&rows).await.unwrap();
+        target.finalize().await.unwrap();
+
+        let mut source = ParquetSource::new(file_path.to_str().unwrap()).unwrap();
+        source.connect().await.unwrap();
+        let read_rows = source.read_batch(1000).await.unwrap();
+        assert_eq!(read_rows.len(), 100);
+    }
 }
@@ -0,0 +1,406 @@
+use async_trait::async_trait;
+use chrono::{Timelike, Utc};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+use crate::{
+    connectors::{create_target_from_url_with_options, Target},
+    schema::{Row, Schema, Value},
+    Result, TinyEtlError,
+};
+
+/// Directory name Hive and Spark use for a null partition value
+const NULL_PARTITION: &str = "__HIVE_DEFAULT_PARTITION__";
+
+/// File extensions a partitioned target can write
+const PARTITION_FORMATS: &[&str] = &["csv", "json", "jsonl", "ndjson", "parquet", "avro"];
+
+/// Writes a file target as a directory of Hive-style partitions,
+/// `out/events.parquet` becoming `out/events/event_date=2024-01-01/part-<run>.parquet`.
+/// Each run adds its own file to the partitions it touches; with
+/// `overwrite` the partitions it touches are replaced, so a rerun for a day
+/// replaces that day and leaves the others alone.
+pub struct PartitionedTarget {
+    root: PathBuf,
+    extension: String,
+    columns: Vec<String>,
+    options: HashMap<String, String>,
+    /// Identifies this run's files, so runs in the same second don't collide
+    run_id: String,
+    /// File name shared by every partition this run writes
+    file_name: String,
+    /// With `overwrite`, where the replacement partitions are written; they
+    /// are swapped in by `finalize`, so a failed run leaves the old ones
+    staging: Option<PathBuf>,
+    schema: Option<Schema>,
+    /// Writers by partition directory
+    partitions: BTreeMap<PathBuf, Box<dyn Target>>,
+}
+
+impl PartitionedTarget {
+    pub fn new(
+        connection_string: &str,
+        columns: Vec<String>,
+        overwrite: bool,
+        options: &HashMap<String, String>,
+    ) -> Result<Self> {
+        if columns.is_empty() {
+            return Err(TinyEtlError::Configuration(
+                "--partition-by needs at least one column".to_string(),
+            ));
+        }
+        let path = Path::new(connection_string);
+        let extension = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(str::to_lowercase)
+            .filter(|ext| PARTITION_FORMATS.contains(&ext.as_str()))
+            .filter(|_| !connection_string.contains("://"));
+        let Some(extension) = extension else {
+            return Err(TinyEtlError::Configuration(format!(
+                "--partition-by writes local files, not '{}'; name the target like out/events.parquet (formats: {})",
+                connection_string,
+                PARTITION_FORMATS.join(", ")
+            )));
+        };
+
+        let root = path.with_extension("");
+        let run_id = format!(
+            "{}-{}",
+            Utc::now().format("%Y%m%dT%H%M%S"),
+            &uuid::Uuid::new_v4().simple().to_string()[..8]
+        );
+        // Hive and Spark skip directories starting with a dot
+        let staging = overwrite.then(|| root.join(format!(".staging-{}", run_id)));
+        Ok(Self {
+            root,
+            extension,
+            columns,
+            options: options.clone(),
+            file_name: format!("part-{}", run_id),
+            run_id,
+            staging,
+            schema: None,
+            partitions: BTreeMap::new(),
+        })
+    }
+
+    /// Directory of the partition a row belongs to
+    fn partition_dir(&self, row: &Row) -> PathBuf {
+        let mut dir = self.root.clone();
+        for column in &self.columns {
+            let value = row
+                .get(column)
+                .map_or(NULL_PARTITION.to_string(), partition_value);
+            dir.push(format!("{}={}", escape_path_part(column), value));
+        }
+        dir
+    }
+
+    /// Directory this run writes a partition's file to: the partition
+    /// itself, or its replacement in the staging directory
+    fn write_dir(&self, dir: &Path) -> PathBuf {
+        match (&self.staging, dir.strip_prefix(&self.root)) {
+            (Some(staging), Ok(relative)) => staging.join(relative),
+            _ => dir.to_path_buf(),
+        }
+    }
+
+    /// Create the writer of a partition seen for the first time
+    async fn open_partition(&mut self, dir: &Path) -> Result<()> {
+        let write_dir = self.write_dir(dir);
+        std::fs::create_dir_all(&write_dir)?;
+
+        let path = write_dir.join(format!("{}.{}", self.file_name, self.extension));
+        let path = path.to_string_lossy().into_owned();
+        let schema = self
+            .schema
+            .as_ref()
+            .ok_or_else(|| TinyEtlError::DataTransfer("Schema not set".to_string()))?;
+
+        let mut target = create_target_from_url_with_options(&path, &self.options).await?;
+        target.connect().await?;
+        target.create_table(&path, schema).await?;
+        self.partitions.insert(dir.to_path_buf(), target);
+        Ok(())
+    }
+
+    /// Put the staged replacement of each partition written in its place.
+    /// The old partition is moved aside first and removed last, so it is
+    /// only missing between two renames.
+    fn swap_in_staged(&self, staging: &Path) -> Result<()> {
+        for dir in self.partitions.keys() {
+            if let Some(parent) = dir.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            let old = dir.with_file_name(format!(
+                ".{}.old-{}",
+                dir.file_name().unwrap_or_default().to_string_lossy(),
+                self.run_id
+            ));
+            let replaced = dir.exists();
+            if replaced {
+                info!("→ Overwriting partition: {}", dir.display());
+                std::fs::rename(dir, &old)?;
+            }
+            std::fs::rename(self.write_dir(dir), dir)?;
+            if replaced {
+                std::fs::remove_dir_all(&old)?;
+            }
+        }
+        std::fs::remove_dir_all(staging)?;
+        Ok(())
+    }
+}
+
+/// Value as it appears in a partition directory name. Timestamps at
+/// midnight are written as plain dates.
+fn partition_value(value: &Value) -> String {
+    let text = match value {
+        Value::Null => return NULL_PARTITION.to_string(),
+        Value::Date(dt) if dt.num_seconds_from_midnight() == 0 && dt.nanosecond() == 0 => {
+            dt.format("%Y-%m-%d").to_string()
+        }
+        other => other.to_string_for_arrow().unwrap_or_default(),
+    };
+    escape_path_part(&text)
+}
+
+/// Percent-encode the characters that can't appear in a directory name on
+/// every platform, plus `=` and `%` so names can be decoded again
+fn escape_path_part(text: &str) -> String {
+    text.chars()
+        .map(|c| {
+            if c.is_control() || "/\\:*?\"<>|=%".contains(c) {
+                format!("%{:02X}", c as u32)
+            } else {
+                c.to_string()
+            }
+        })
+        .collect()
+}
+
+#[async_trait]
+impl Target for PartitionedTarget {
+    async fn connect(&mut self) -> Result<()> {
+        if let Some(parent) = self.root.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        Ok(())
+    }
+
+    fn bytes_written(&self) -> Option<u64> {
+        self.partitions
+            .values()
+            .map(|target| target.bytes_written())
+            .sum()
+    }
+
+    async fn create_table(&mut self, _table_name: &str, schema: &Schema) -> Result<()> {
+        if let Some(missing) = self
+            .columns
+            .iter()
+            .find(|column| !schema.columns.iter().any(|c| c.name == **column))
+        {
+            return Err(TinyEtlError::Configuration(format!(
+                "Partition column '{}' is not in the output schema",
+                missing
+            )));
+        }
+        self.schema = Some(schema.clone());
+        Ok(())
+    }
+
+    async fn write_batch(&mut self, rows: &[Row]) -> Result<usize> {
+        let mut grouped: BTreeMap<PathBuf, Vec<Row>> = BTreeMap::new();
+        for row in rows {
+            grouped
+                .entry(self.partition_dir(row))
+                .or_default()
+                .push(row.clone());
+        }
+
+        let mut written = 0;
+        for (dir, rows) in grouped {
+            if !self.partitions.contains_key(&dir) {
+                self.open_partition(&dir).await?;
+            }
+            if let Some(target) = self.partitions.get_mut(&dir) {
+                written += target.write_batch(&rows).await?;
+            }
+        }
+        Ok(written)
+    }
+
+    async fn finalize(&mut self) -> Result<()> {
+        for target in self.partitions.values_mut() {
+            target.finalize().await?;
+        }
+        if let Some(staging) = self.staging.as_deref().filter(|staging| staging.exists()) {
+            self.swap_in_staged(staging)?;
+        }
+        info!(
+            "→ Wrote {} partitions under {}",
+            self.partitions.len(),
+            self.root.display()
+        );
+        Ok(())
+    }
+
+    async fn exists(&self, _table_name: &str) -> Result<bool> {
+        Ok(std::fs::read_dir(&self.root).is_ok_and(|mut entries| entries.next().is_some()))
+    }
+
+    async fn truncate(&mut self, _table_name: &str) -> Result<()> {
+        if self.root.exists() {
+            std::fs::remove_dir_all(&self.root)?;
+        }
+        Ok(())
+    }
+
+    fn supports_append(&self) -> bool {
+        // Every run writes new files, so existing partitions are never rewritten
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::{Column, DataType};
+    use chrono::TimeZone;
+
+    fn schema() -> Schema {
+        Schema {
+            columns: vec![
+                Column {
+                    name: "day".to_string(),
+                    data_type: DataType::String,
+                    nullable: true,
+                },
+                Column {
+                    name: "amount".to_string(),
+                    data_type: DataType::Integer,
+                    nullable: false,
+                },
+            ],
+            estimated_rows: None,
+            primary_key_candidate: None,
+        }
+    }
+
+    fn row(day: &str, amount: i64) -> Row {
+        Row::from([
+            ("day".to_string(), Value::String(day.to_string())),
+            ("amount".to_string(), Value::Integer(amount)),
+        ])
+    }
+
+    async fn load(target: &str, overwrite: bool, run: &str, rows: &[Row]) {
+        let mut target =
+            PartitionedTarget::new(target, vec!["day".to_string()], overwrite, &HashMap::new())
+                .unwrap();
+        // A fixed name, so the test can find the file
+        target.file_name = format!("part-{}", run);
+        target.connect().await.unwrap();
+        target.create_table("sales", &schema()).await.unwrap();
+        target.write_batch(rows).await.unwrap();
+        target.finalize().await.unwrap();
+    }
+
+    fn files(dir: &Path) -> usize {
+        std::fs::read_dir(dir).map_or(0, |entries| entries.count())
+    }
+
+    #[tokio::test]
+    async fn test_overwrite_replaces_only_incoming_partitions() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("sales.csv");
+        let target = target.to_str().unwrap();
+        let root = dir.path().join("sales");
+
+        load(
+            target,
+            false,
+            "1",
+            &[row("2024-01-01", 1), row("2024-01-02", 2)],
+        )
+        .await;
+        load(target, false, "2", &[row("2024-01-02", 3)]).await;
+        assert_eq!(files(&root.join("day=2024-01-01")), 1);
+        assert_eq!(files(&root.join("day=2024-01-02")), 2);
+
+        load(
+            target,
+            true,
+            "3",
+            &[row("2024-01-02", 4), row("2024-01-03", 5)],
+        )
+        .await;
+        assert_eq!(files(&root.join("day=2024-01-01")), 1);
+        assert_eq!(files(&root.join("day=2024-01-02")), 1);
+        assert_eq!(files(&root.join("day=2024-01-03")), 1);
+
+        let day2 = std::fs::read_to_string(root.join("day=2024-01-02").join("part-3.csv"));
+        assert!(day2.unwrap().contains("2024-01-02,4"));
+        // Only the partitions are left under the root
+        assert_eq!(files(&root), 3);
+    }
+
+    #[tokio::test]
+    async fn test_unfinished_overwrite_keeps_old_partitions() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("sales.csv");
+        let target = target.to_str().unwrap();
+        let day = dir.path().join("sales").join("day=2024-01-01");
+        load(target, false, "1", &[row("2024-01-01", 1)]).await;
+
+        let mut failed =
+            PartitionedTarget::new(target, vec!["day".to_string()], true, &HashMap::new()).unwrap();
+        failed.connect().await.unwrap();
+        failed.create_table("sales", &schema()).await.unwrap();
+        failed.write_batch(&[row("2024-01-01", 2)]).await.unwrap();
+        drop(failed);
+
+        assert_eq!(files(&day), 1);
+        assert!(day.join("part-1.csv").exists());
+    }
+
+    #[test]
+    fn test_runs_get_distinct_file_names() {
+        let new = || {
+            PartitionedTarget::new(
+                "out/sales.csv",
+                vec!["day".to_string()],
+                false,
+                &HashMap::new(),
+            )
+            .unwrap()
+            .file_name
+        };
+        assert_ne!(new(), new());
+    }
+
+    #[test]
+    fn test_partition_directory_names() {
+        let midnight = Utc.with_ymd_and_hms(2024, 3, 1, 0, 0, 0).unwrap();
+        assert_eq!(partition_value(&Value::Date(midnight)), "2024-03-01");
+        assert_eq!(partition_value(&Value::Null), NULL_PARTITION);
+        assert_eq!(
+            partition_value(&Value::String("a/b=c".to_string())),
+            "a%2Fb%3Dc"
+        );
+
+        assert!(PartitionedTarget::new(
+            "postgres://db/app#t",
+            vec!["day".to_string()],
+            false,
+            &HashMap::new()
+        )
+        .is_err());
+        assert!(
+            PartitionedTarget::new("out/sales.csv", Vec::new(), false, &HashMap::new()).is_err()
+        );
+    }
+}
@@ -0,0 +1,525 @@
+//! PDF tables (`.pdf`, with the `pdf` feature): grid-style reports printed
+//! to PDF. Text is laid out in lines by its position on the page, and runs
+//! of lines split into several cells at the same places make up a table.
+//! One table is read, picked by its position among those of the pages
+//! named by the `pages` option.
+
+use pdf_extract::{MediaBox, OutputDev, OutputError, Transform};
+use std::collections::HashMap;
+
+use crate::{
+    connectors::{
+        encoding::Encoding,
+        html,
+        record_format::{RecordFormat, Records},
+        vobject,
+    },
+    schema::Row,
+    Result, TinyEtlError,
+};
+
+/// Largest difference in height of characters on the same line, in font
+/// sizes
+const LINE_TOLERANCE: f64 = 0.5;
+/// Smallest gap between words, in font sizes
+const WORD_GAP: f64 = 0.1;
+/// Smallest gap between the cells of a row, in font sizes
+const CELL_GAP: f64 = 1.0;
+/// Largest distance between the rows of a table, in font sizes
+const ROW_SPACING: f64 = 2.5;
+
+/// A character drawn on a page, `y` growing down the page
+#[derive(Debug, Clone)]
+struct Glyph {
+    x: f64,
+    y: f64,
+    end: f64,
+    size: f64,
+    text: String,
+}
+
+/// Text of a line standing apart from the rest, e.g. a table cell
+#[derive(Debug, Clone)]
+struct Segment {
+    start: f64,
+    end: f64,
+    text: String,
+}
+
+#[derive(Debug)]
+struct TextLine {
+    y: f64,
+    size: f64,
+    segments: Vec<Segment>,
+}
+
+/// Collects the characters of a page
+#[derive(Default)]
+struct PageText {
+    glyphs: Vec<Glyph>,
+}
+
+impl OutputDev for PageText {
+    fn begin_page(
+        &mut self,
+        _page_num: u32,
+        _media_box: &MediaBox,
+        _art_box: Option<(f64, f64, f64, f64)>,
+    ) -> std::result::Result<(), OutputError> {
+        Ok(())
+    }
+
+    fn end_page(&mut self) -> std::result::Result<(), OutputError> {
+        Ok(())
+    }
+
+    fn output_character(
+        &mut self,
+        trm: &Transform,
+        width: f64,
+        _spacing: f64,
+        font_size: f64,
+        char: &str,
+    ) -> std::result::Result<(), OutputError> {
+        // Size of the text as drawn, once scaled by the text matrix
+        let scaled_x = font_size * (trm.m11 + trm.m21);
+        let scaled_y = font_size * (trm.m12 + trm.m22);
+        let size = (scaled_x * scaled_y).abs().sqrt();
+        if size > 0.0 {
+            self.glyphs.push(Glyph {
+                x: trm.m31,
+                y: -trm.m32,
+                end: trm.m31 + width * size,
+                size,
+                text: char.to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    fn begin_word(&mut self) -> std::result::Result<(), OutputError> {
+        Ok(())
+    }
+
+    fn end_word(&mut self) -> std::result::Result<(), OutputError> {
+        Ok(())
+    }
+
+    fn end_line(&mut self) -> std::result::Result<(), OutputError> {
+        Ok(())
+    }
+}
+
+/// Pages to look for tables on, from the `pages` option (`2-4,7`, `3-`);
+/// every page by default
+struct PageRange(Vec<(u32, Option<u32>)>);
+
+impl PageRange {
+    fn parse(options: &HashMap<String, String>) -> Result<Self> {
+        let Some(pages) = options.get("pages") else {
+            return Ok(Self(vec![(1, None)]));
+        };
+        let invalid = || {
+            TinyEtlError::Configuration(format!(
+                "Invalid pages '{}': expected page numbers and ranges such as 2-4,7 or 3-",
+                pages
+            ))
+        };
+        let number = |text: &str| {
+            text.trim()
+                .parse::<u32>()
+                .ok()
+                .filter(|page| *page >= 1)
+                .ok_or_else(invalid)
+        };
+        let mut ranges = Vec::new();
+        for part in pages.split(',') {
+            let range = match part.split_once('-') {
+                Some((first, last)) if last.trim().is_empty() => (number(first)?, None),
+                Some((first, last)) => (number(first)?, Some(number(last)?)),
+                None => (number(part)?, Some(number(part)?)),
+            };
+            if range.1.is_some_and(|last| last < range.0) {
+                return Err(invalid());
+            }
+            ranges.push(range);
+        }
+        Ok(Self(ranges))
+    }
+
+    fn contains(&self, page: u32) -> bool {
+        self.0
+            .iter()
+            .any(|(first, last)| page >= *first && last.map_or(true, |last| page <= last))
+    }
+}
+
+/// The `table_index` option: 1 for the first table
+fn table_index(options: &HashMap<String, String>) -> Result<usize> {
+    match options.get("table_index") {
+        Some(index) => index
+            .trim()
+            .parse::<usize>()
+            .ok()
+            .filter(|index| *index >= 1)
+            .ok_or_else(|| {
+                TinyEtlError::Configuration(format!(
+                    "Invalid table_index '{}': expected 1 for the first table, 2 for the second...",
+                    index
+                ))
+            }),
+        None => Ok(1),
+    }
+}
+
+/// Lines of text of a page, top down, each split into segments where the
+/// gap between two characters is wide enough to part table cells
+fn text_lines(mut glyphs: Vec<Glyph>) -> Vec<TextLine> {
+    // Spaces are only used to tell words apart, which their gap does too
+    glyphs.retain(|glyph| !glyph.text.trim().is_empty());
+    glyphs.sort_by(|a, b| a.y.total_cmp(&b.y));
+
+    let mut rows: Vec<Vec<Glyph>> = Vec::new();
+    for glyph in glyphs {
+        match rows.last_mut() {
+            Some(row) if (glyph.y - row[0].y).abs() <= LINE_TOLERANCE * row[0].size => {
+                row.push(glyph)
+            }
+            _ => rows.push(vec![glyph]),
+        }
+    }
+
+    rows.into_iter()
+        .map(|mut row| {
+            row.sort_by(|a, b| a.x.total_cmp(&b.x));
+            let size = row.iter().map(|glyph| glyph.size).fold(0.0, f64::max);
+            let mut segments: Vec<Segment> = Vec::new();
+            for glyph in &row {
+                match segments.last_mut() {
+                    Some(segment) if glyph.x - segment.end < CELL_GAP * glyph.size => {
+                        if glyph.x - segment.end > WORD_GAP * glyph.size {
+                            segment.text.push(' ');
+                        }
+                        segment.text.push_str(&glyph.text);
+                        segment.end = segment.end.max(glyph.end);
+                    }
+                    _ => segments.push(Segment {
+                        start: glyph.x,
+                        end: glyph.end,
+                        text: glyph.text.clone(),
+                    }),
+                }
+            }
+            TextLine {
+                y: row[0].y,
+                size,
+                segments,
+            }
+        })
+        .collect()
+}
+
+/// Horizontal extents of the columns of some lines: those of their
+/// segments, merged where they overlap
+fn columns(lines: &[TextLine]) -> Vec<(f64, f64)> {
+    let mut extents: Vec<(f64, f64)> = lines
+        .iter()
+        .flat_map(|line| &line.segments)
+        .map(|segment| (segment.start, segment.end))
+        .collect();
+    extents.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let mut columns: Vec<(f64, f64)> = Vec::new();
+    for (start, end) in extents {
+        match columns.last_mut() {
+            Some(column) if start <= column.1 => column.1 = column.1.max(end),
+            _ => columns.push((start, end)),
+        }
+    }
+    columns
+}
+
+/// The column a segment falls in: the one it overlaps most, or else the
+/// nearest
+fn column_of(segment: &Segment, columns: &[(f64, f64)]) -> usize {
+    let overlap = |(start, end): (f64, f64)| segment.end.min(end) - segment.start.max(start);
+    let distance = |(start, end): (f64, f64)| {
+        ((segment.start + segment.end) / 2.0 - (start + end) / 2.0).abs()
+    };
+    (0..columns.len())
+        .max_by(|a, b| {
+            let (a, b) = (columns[*a], columns[*b]);
+            overlap(a)
+                .max(0.0)
+                .total_cmp(&overlap(b).max(0.0))
+                .then(distance(b).total_cmp(&distance(a)))
+        })
+        .unwrap_or(0)
+}
+
+/// Runs of lines laid out as tables: lines of several cells, close to each
+/// other. A line of a single cell stays in a table it falls in one column
+/// of, e.g. a row with one value filled in.
+fn tables(lines: Vec<TextLine>) -> Vec<Vec<TextLine>> {
+    let mut tables: Vec<Vec<TextLine>> = Vec::new();
+    let mut current: Vec<TextLine> = Vec::new();
+    for line in lines {
+        let continues = current
+            .last()
+            .is_some_and(|last| line.y - last.y <= ROW_SPACING * last.size.max(line.size));
+        let fits = match line.segments.len() {
+            0 => false,
+            1 if continues => {
+                let columns = columns(&current);
+                let segment = &line.segments[0];
+                columns
+                    .iter()
+                    .filter(|(start, end)| segment.start < *end && segment.end > *start)
+                    .count()
+                    == 1
+            }
+            1 => false,
+            _ => true,
+        };
+        if fits && continues {
+            current.push(line);
+            continue;
+        }
+        if current.len() >= 2 {
+            tables.push(std::mem::take(&mut current));
+        }
+        current.clear();
+        if fits {
+            current.push(line);
+        }
+    }
+    if current.len() >= 2 {
+        tables.push(current);
+    }
+    tables
+}
+
+/// Rows of a table, its first line holding the column names
+fn table_records(table: &[TextLine]) -> Records {
+    let (heading, data) = table.split_first().expect("tables have two lines or more");
+    // The data lay out the columns; a heading may span several of them
+    let columns = columns(data);
+    let cells = |line: &TextLine| {
+        let mut cells = vec![String::new(); columns.len()];
+        for segment in &line.segments {
+            let cell = &mut cells[column_of(segment, &columns)];
+            if !cell.is_empty() {
+                cell.push(' ');
+            }
+            cell.push_str(&segment.text);
+        }
+        cells
+    };
+
+    let names = html::column_names(&[cells(heading).as_slice()], columns.len());
+    let mut records = Records::with_columns(&names.iter().map(String::as_str).collect::<Vec<_>>());
+    for line in data {
+        let mut row = Row::new();
+        for (name, cell) in names.iter().zip(cells(line)) {
+            if !cell.is_empty() {
+                records.insert(&mut row, name.clone(), html::cell_value(&cell));
+            }
+        }
+        records.rows.push(row);
+    }
+    vobject::settle_dates(&mut records);
+    records
+}
+
+/// The built-in `pdf` record format
+pub struct PdfFormat;
+
+impl RecordFormat for PdfFormat {
+    fn extensions(&self) -> &[&str] {
+        &["pdf"]
+    }
+
+    fn check_options(&self, options: &HashMap<String, String>) -> Result<()> {
+        PageRange::parse(options)?;
+        table_index(options).map(|_| ())
+    }
+
+    fn read(&self, text: &str, options: &HashMap<String, String>) -> Result<Records> {
+        self.read_bytes(text.as_bytes(), Encoding::Utf8, options)
+    }
+
+    fn read_bytes(
+        &self,
+        bytes: &[u8],
+        _encoding: Encoding,
+        options: &HashMap<String, String>,
+    ) -> Result<Records> {
+        let pages = PageRange::parse(options)?;
+        let index = table_index(options)?;
+        let invalid = |e: &dyn std::fmt::Display| {
+            TinyEtlError::DataValidation(format!("Invalid PDF file: {}", e))
+        };
+        let document = pdf_extract::Document::load_mem(bytes).map_err(|e| invalid(&e))?;
+        if document.is_encrypted() {
+            return Err(TinyEtlError::DataValidation(
+                "Encrypted PDF files can't be read".to_string(),
+            ));
+        }
+
+        let mut found = 0;
+        for page in document.get_pages().into_keys() {
+            if !pages.contains(page) {
+                continue;
+            }
+            let mut text = PageText::default();
+            pdf_extract::output_doc_page(&document, &mut text, page).map_err(|e| invalid(&e))?;
+            for table in tables(text_lines(text.glyphs)) {
+                found += 1;
+                if found == index {
+                    return Ok(table_records(&table));
+                }
+            }
+        }
+        Err(match found {
+            0 => TinyEtlError::DataValidation("No table in the PDF file".to_string()),
+            found => TinyEtlError::Configuration(format!(
+                "table_index {} is past the last of the {} tables found",
+                index, found
+            )),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::Value;
+    use pdf_extract::content::{Content, Operation};
+    use pdf_extract::{Dictionary, Document, Object, Stream};
+    use rust_decimal::Decimal;
+
+    /// A one-page PDF with `text` drawn at each position, from the top left
+    fn pdf(text: &[(i64, i64, &str)]) -> Vec<u8> {
+        let mut document = Document::with_version("1.5");
+        let mut font = Dictionary::new();
+        font.set("Type", Object::Name(b"Font".to_vec()));
+        font.set("Subtype", Object::Name(b"Type1".to_vec()));
+        font.set("BaseFont", Object::Name(b"Helvetica".to_vec()));
+        let font_id = document.add_object(font);
+        let mut fonts = Dictionary::new();
+        fonts.set("F1", font_id);
+        let mut resources = Dictionary::new();
+        resources.set("Font", fonts);
+
+        let mut operations = vec![
+            Operation::new("BT", vec![]),
+            Operation::new("Tf", vec![Object::Name(b"F1".to_vec()), 10.into()]),
+        ];
+        for (x, y, text) in text {
+            let position = [1, 0, 0, 1, *x, 800 - *y];
+            operations.push(Operation::new(
+                "Tm",
+                position.into_iter().map(Object::Integer).collect(),
+            ));
+            operations.push(Operation::new("Tj", vec![Object::string_literal(*text)]));
+        }
+        operations.push(Operation::new("ET", vec![]));
+        let content = Content { operations }.encode().unwrap();
+        let content_id = document.add_object(Stream::new(Dictionary::new(), content));
+
+        let pages_id = document.new_object_id();
+        let mut page = Dictionary::new();
+        page.set("Type", Object::Name(b"Page".to_vec()));
+        page.set("Parent", pages_id);
+        page.set("Contents", content_id);
+        page.set("Resources", resources);
+        page.set("MediaBox", [0, 0, 600, 800].map(Object::Integer).to_vec());
+        let page_id = document.add_object(page);
+        let mut pages = Dictionary::new();
+        pages.set("Type", Object::Name(b"Pages".to_vec()));
+        pages.set("Kids", vec![Object::Reference(page_id)]);
+        pages.set("Count", 1);
+        document.objects.insert(pages_id, Object::Dictionary(pages));
+        let mut catalog = Dictionary::new();
+        catalog.set("Type", Object::Name(b"Catalog".to_vec()));
+        catalog.set("Pages", pages_id);
+        let catalog_id = document.add_object(catalog);
+        document.trailer.set("Root", catalog_id);
+
+        let mut bytes = Vec::new();
+        document.save_to(&mut bytes).unwrap();
+        bytes
+    }
+
+    fn report() -> Vec<u8> {
+        pdf(&[
+            (50, 40, "Quarterly sales report"),
+            (50, 80, "Region"),
+            (200, 80, "Units"),
+            (300, 80, "Revenue"),
+            (50, 95, "North East"),
+            (200, 95, "1,204"),
+            (300, 95, "15300.50"),
+            (50, 110, "South"),
+            (300, 110, "980.25"),
+            (50, 125, "West"),
+            (200, 125, "87"),
+            (300, 125, "1010.00"),
+            (50, 200, "Figures are unaudited."),
+            (50, 260, "Code"),
+            (200, 260, "Name"),
+            (50, 275, "A1"),
+            (200, 275, "Alpha"),
+        ])
+    }
+
+    #[test]
+    fn test_reads_a_grid_table() {
+        let records = PdfFormat
+            .read_bytes(&report(), Encoding::Utf8, &HashMap::new())
+            .unwrap();
+        assert_eq!(records.columns, ["Region", "Units", "Revenue"]);
+        assert_eq!(records.rows.len(), 3);
+        let north = &records.rows[0];
+        assert_eq!(north["Region"], Value::String("North East".to_string()));
+        assert_eq!(north["Units"], Value::Integer(1204));
+        assert_eq!(north["Revenue"], Value::Decimal(Decimal::new(1530050, 2)));
+        // A row with a blank cell stays in the table
+        assert!(!records.rows[1].contains_key("Units"));
+    }
+
+    #[test]
+    fn test_table_index() {
+        let options = HashMap::from([("table_index".to_string(), "2".to_string())]);
+        let records = PdfFormat
+            .read_bytes(&report(), Encoding::Utf8, &options)
+            .unwrap();
+        assert_eq!(records.columns, ["Code", "Name"]);
+        assert_eq!(records.rows[0]["Name"], Value::String("Alpha".to_string()));
+
+        let options = HashMap::from([("table_index".to_string(), "3".to_string())]);
+        let error = PdfFormat
+            .read_bytes(&report(), Encoding::Utf8, &options)
+            .unwrap_err();
+        assert!(error.to_string().contains("past the last of the 2 tables"));
+
+        let options = HashMap::from([("pages".to_string(), "2-".to_string())]);
+        let error = PdfFormat
+            .read_bytes(&report(), Encoding::Utf8, &options)
+            .unwrap_err();
+        assert!(error.to_string().contains("No table in the PDF file"));
+    }
+
+    #[test]
+    fn test_page_range() {
+        let parse = |pages: &str| {
+            PageRange::parse(&HashMap::from([("pages".to_string(), pages.to_string())]))
+        };
+        let range = parse("2-4, 7,9-").unwrap();
+        let included: Vec<u32> = (1..=10).filter(|page| range.contains(*page)).collect();
+        assert_eq!(included, [2, 3, 4, 7, 9, 10]);
+        assert!(PageRange::parse(&HashMap::new()).unwrap().contains(1));
+        for invalid in ["0", "4-2", "two", "1,,2", ""] {
+            assert!(parse(invalid).is_err(), "{}", invalid);
+        }
+    }
+}
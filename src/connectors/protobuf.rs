@@ -0,0 +1,780 @@
+use async_trait::async_trait;
+use prost::bytes::Bytes;
+use prost::Message;
+use prost_reflect::{
+    DescriptorPool, DynamicMessage, FieldDescriptor, Kind, MapKey, MessageDescriptor,
+    Value as ProtoValue,
+};
+use rust_decimal::prelude::ToPrimitive;
+use serde_json::Value as JsonValue;
+use std::collections::HashMap;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+
+use crate::{
+    connectors::{Source, Target},
+    schema::{BinaryEncoding, Column, DataType, Row, Schema, Value},
+    strict::Coercion,
+    Result, TinyEtlError,
+};
+
+/// Separator between the names of a nested message field and its fields
+const NESTED_SEPARATOR: &str = ".";
+
+/// Whether a connection string names a file of length-delimited protobuf
+/// records (`events.pb`, or `events.pb#acme.Event` with the message name)
+pub fn is_protobuf_file(connection_string: &str) -> bool {
+    let path = split_message(connection_string).0;
+    !connection_string.contains("://") && (path.ends_with(".pb") || path.ends_with(".binpb"))
+}
+
+/// The file path and the message name after `#`, if any
+fn split_message(connection_string: &str) -> (&str, Option<&str>) {
+    match connection_string.rsplit_once('#') {
+        Some((path, message)) => (path, Some(message)),
+        None => (connection_string, None),
+    }
+}
+
+/// The file path and the descriptor of its records: the message is named
+/// after `#` or with the `message` option, and looked up in the compiled
+/// descriptor set of the `descriptor` option
+/// (`protoc --include_imports --descriptor_set_out=events.desc events.proto`)
+fn resolve(
+    connection_string: &str,
+    options: &HashMap<String, String>,
+) -> Result<(PathBuf, MessageDescriptor)> {
+    let (path, message) = split_message(connection_string);
+    let message = message
+        .or(options.get("message").map(String::as_str))
+        .ok_or_else(|| {
+            TinyEtlError::Configuration(format!(
+                "Protobuf file {} needs a message name, e.g. {}#acme.Event or the message option",
+                path, path
+            ))
+        })?;
+    let descriptor = options.get("descriptor").ok_or_else(|| {
+        TinyEtlError::Configuration(format!(
+            "Protobuf file {} needs a descriptor set (--proto-descriptor or the descriptor option)",
+            path
+        ))
+    })?;
+
+    let bytes = std::fs::read(descriptor).map_err(|e| {
+        TinyEtlError::Configuration(format!("Can't read descriptor set {}: {}", descriptor, e))
+    })?;
+    let pool = DescriptorPool::decode(bytes.as_slice()).map_err(|e| {
+        TinyEtlError::Configuration(format!("Invalid descriptor set {}: {}", descriptor, e))
+    })?;
+    let descriptor = pool.get_message_by_name(message).ok_or_else(|| {
+        TinyEtlError::Configuration(format!(
+            "Message '{}' is not in descriptor set {}",
+            message, descriptor
+        ))
+    })?;
+    Ok((PathBuf::from(path), descriptor))
+}
+
+fn column_name(prefix: &str, name: &str) -> String {
+    if prefix.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}{}{}", prefix, NESTED_SEPARATOR, name)
+    }
+}
+
+/// The message type of a field whose fields become columns of their own:
+/// singular message fields, except ones nesting a message already on
+/// `path` (recursive types), which are kept whole as JSON
+fn flattened(field: &FieldDescriptor, path: &[String]) -> Option<MessageDescriptor> {
+    match field.kind() {
+        Kind::Message(nested)
+            if !field.is_list()
+                && !field.is_map()
+                && !path.contains(&nested.full_name().to_string()) =>
+        {
+            Some(nested)
+        }
+        _ => None,
+    }
+}
+
+fn schema_columns(
+    descriptor: &MessageDescriptor,
+    prefix: &str,
+    path: &mut Vec<String>,
+    columns: &mut Vec<Column>,
+) {
+    path.push(descriptor.full_name().to_string());
+    for field in descriptor.fields() {
+        let name = column_name(prefix, field.name());
+        if let Some(nested) = flattened(&field, path) {
+            schema_columns(&nested, &name, path, columns);
+            continue;
+        }
+        let data_type = match field.kind() {
+            _ if field.is_list() || field.is_map() => DataType::Json,
+            Kind::Bool => DataType::Boolean,
+            Kind::Double | Kind::Float => DataType::Decimal,
+            Kind::String | Kind::Enum(_) => DataType::String,
+            Kind::Bytes => DataType::Binary,
+            Kind::Message(_) => DataType::Json,
+            _ => DataType::Integer,
+        };
+        columns.push(Column {
+            name,
+            data_type,
+            // Fields of nested messages are NULL when the message isn't set
+            nullable: !prefix.is_empty() || field.supports_presence(),
+        });
+    }
+    path.pop();
+}
+
+/// Source reading a file of length-delimited protobuf records, with nested
+/// message fields flattened into `parent.child` columns and repeated and
+/// map fields read as JSON
+pub struct ProtobufSource {
+    file_path: PathBuf,
+    descriptor: MessageDescriptor,
+    data: Vec<u8>,
+    position: usize,
+    total_records: Option<usize>,
+    coercion: Coercion,
+}
+
+impl ProtobufSource {
+    pub fn new(connection_string: &str, options: &HashMap<String, String>) -> Result<Self> {
+        let (file_path, descriptor) = resolve(connection_string, options)?;
+        Ok(Self {
+            file_path,
+            descriptor,
+            data: Vec::new(),
+            position: 0,
+            total_records: None,
+            coercion: Coercion::default(),
+        })
+    }
+
+    /// Position and length of the record starting at `position`
+    fn record_at(&self, position: usize) -> Result<(usize, usize)> {
+        let mut buf = &self.data[position..];
+        let length = prost::decode_length_delimiter(&mut buf).map_err(|e| {
+            TinyEtlError::DataTransfer(format!(
+                "Invalid record length at byte {} of {}: {}",
+                position,
+                self.file_path.display(),
+                e
+            ))
+        })?;
+        let start = self.data.len() - buf.len();
+        if length > buf.len() {
+            return Err(TinyEtlError::DataTransfer(format!(
+                "Truncated record at byte {} of {}",
+                position,
+                self.file_path.display()
+            )));
+        }
+        Ok((start, length))
+    }
+
+    fn message_to_row(&self, message: &DynamicMessage) -> Result<Row> {
+        let mut row = Row::new();
+        self.flatten(message, &self.descriptor, "", &mut Vec::new(), &mut row)?;
+        Ok(row)
+    }
+
+    fn flatten(
+        &self,
+        message: &DynamicMessage,
+        descriptor: &MessageDescriptor,
+        prefix: &str,
+        path: &mut Vec<String>,
+        row: &mut Row,
+    ) -> Result<()> {
+        path.push(descriptor.full_name().to_string());
+        for field in descriptor.fields() {
+            let name = column_name(prefix, field.name());
+            if let Some(nested) = flattened(&field, path) {
+                let value = message.get_field(&field);
+                match value.as_ref() {
+                    ProtoValue::Message(inner) if message.has_field(&field) => {
+                        self.flatten(inner, &nested, &name, path, row)?
+                    }
+                    _ => {
+                        let mut columns = Vec::new();
+                        schema_columns(&nested, &name, path, &mut columns);
+                        for column in columns {
+                            row.insert(column.name, Value::Null);
+                        }
+                    }
+                }
+                continue;
+            }
+            let value = if field.supports_presence() && !message.has_field(&field) {
+                Value::Null
+            } else {
+                self.field_value(&field, &message.get_field(&field), &name)?
+            };
+            row.insert(name, value);
+        }
+        path.pop();
+        Ok(())
+    }
+
+    fn field_value(
+        &self,
+        field: &FieldDescriptor,
+        value: &ProtoValue,
+        column: &str,
+    ) -> Result<Value> {
+        Ok(match value {
+            ProtoValue::Bool(b) => Value::Boolean(*b),
+            ProtoValue::I32(i) => Value::Integer(*i as i64),
+            ProtoValue::I64(i) => Value::Integer(*i),
+            ProtoValue::U32(u) => Value::Integer(*u as i64),
+            ProtoValue::U64(u) => Value::from_wide_integer((*u).into()),
+            ProtoValue::F32(f) => self.coercion.decimal_from_f32(*f, column)?,
+            ProtoValue::F64(f) => self.coercion.decimal_from_f64(*f, column)?,
+            ProtoValue::String(s) => Value::String(s.clone()),
+            ProtoValue::Bytes(b) => Value::Bytes(b.to_vec()),
+            ProtoValue::EnumNumber(n) => match field.kind() {
+                Kind::Enum(e) => match e.get_value(*n) {
+                    Some(v) => Value::String(v.name().to_string()),
+                    None => self.coercion.lossy(
+                        column,
+                        &n.to_string(),
+                        &format!("is not a value of enum {}", e.full_name()),
+                        Value::String(n.to_string()),
+                    )?,
+                },
+                _ => Value::Integer(*n as i64),
+            },
+            ProtoValue::Message(_) | ProtoValue::List(_) | ProtoValue::Map(_) => {
+                Value::Json(to_json(&field.kind(), value))
+            }
+        })
+    }
+}
+
+/// JSON of a protobuf value of `kind`: bytes as base64, enums by name
+fn to_json(kind: &Kind, value: &ProtoValue) -> JsonValue {
+    match value {
+        ProtoValue::Bool(b) => JsonValue::Bool(*b),
+        ProtoValue::I32(i) => JsonValue::from(*i),
+        ProtoValue::I64(i) => JsonValue::from(*i),
+        ProtoValue::U32(u) => JsonValue::from(*u),
+        ProtoValue::U64(u) => JsonValue::from(*u),
+        ProtoValue::F32(f) => serde_json::Number::from_f64(*f as f64)
+            .map_or_else(|| JsonValue::String(f.to_string()), JsonValue::Number),
+        ProtoValue::F64(f) => serde_json::Number::from_f64(*f)
+            .map_or_else(|| JsonValue::String(f.to_string()), JsonValue::Number),
+        ProtoValue::String(s) => JsonValue::String(s.clone()),
+        ProtoValue::Bytes(b) => JsonValue::String(BinaryEncoding::Base64.encode(b)),
+        ProtoValue::EnumNumber(n) => match kind {
+            Kind::Enum(e) => e
+                .get_value(*n)
+                .map_or_else(|| JsonValue::from(*n), |v| JsonValue::from(v.name())),
+            _ => JsonValue::from(*n),
+        },
+        ProtoValue::Message(message) => {
+            let mut object = serde_json::Map::new();
+            for (field, value) in message.fields() {
+                object.insert(field.name().to_string(), to_json(&field.kind(), value));
+            }
+            JsonValue::Object(object)
+        }
+        ProtoValue::List(values) => {
+            JsonValue::Array(values.iter().map(|value| to_json(kind, value)).collect())
+        }
+        ProtoValue::Map(entries) => {
+            let value_kind = match kind {
+                Kind::Message(entry) => entry.map_entry_value_field().kind(),
+                _ => kind.clone(),
+            };
+            let mut object = serde_json::Map::new();
+            for (key, value) in entries {
+                let key = match key {
+                    MapKey::Bool(b) => b.to_string(),
+                    MapKey::I32(i) => i.to_string(),
+                    MapKey::I64(i) => i.to_string(),
+                    MapKey::U32(u) => u.to_string(),
+                    MapKey::U64(u) => u.to_string(),
+                    MapKey::String(s) => s.clone(),
+                };
+                object.insert(key, to_json(&value_kind, value));
+            }
+            JsonValue::Object(object)
+        }
+    }
+}
+
+#[async_trait]
+impl Source for ProtobufSource {
+    async fn connect(&mut self) -> Result<()> {
+        if !self.file_path.exists() {
+            return Err(TinyEtlError::Connection(format!(
+                "Protobuf file not found: {}",
+                self.file_path.display()
+            )));
+        }
+        self.data = std::fs::read(&self.file_path)?;
+
+        let mut records = 0;
+        let mut position = 0;
+        while position < self.data.len() {
+            let (start, length) = self.record_at(position)?;
+            position = start + length;
+            records += 1;
+        }
+        self.total_records = Some(records);
+        Ok(())
+    }
+
+    async fn infer_schema(&mut self, _sample_size: usize) -> Result<Schema> {
+        if self.total_records.is_none() {
+            self.connect().await?;
+        }
+        let mut columns = Vec::new();
+        schema_columns(&self.descriptor, "", &mut Vec::new(), &mut columns);
+        Ok(Schema {
+            columns,
+            estimated_rows: self.total_records,
+            primary_key_candidate: None,
+        })
+    }
+
+    async fn read_batch(&mut self, batch_size: usize) -> Result<Vec<Row>> {
+        let mut rows = Vec::new();
+        while rows.len() < batch_size && self.position < self.data.len() {
+            let (start, length) = self.record_at(self.position)?;
+            let message =
+                DynamicMessage::decode(self.descriptor.clone(), &self.data[start..start + length])
+                    .map_err(|e| {
+                        TinyEtlError::DataTransfer(format!(
+                            "Invalid {} record at byte {} of {}: {}",
+                            self.descriptor.full_name(),
+                            self.position,
+                            self.file_path.display(),
+                            e
+                        ))
+                    })?;
+            rows.push(self.message_to_row(&message)?);
+            self.position = start + length;
+        }
+        Ok(rows)
+    }
+
+    async fn estimated_row_count(&self) -> Result<Option<usize>> {
+        Ok(self.total_records)
+    }
+
+    fn bytes_read(&self) -> Option<u64> {
+        Some(self.position as u64)
+    }
+
+    fn set_coercion(&mut self, coercion: Coercion) {
+        self.coercion = coercion;
+    }
+
+    async fn reset(&mut self) -> Result<()> {
+        self.position = 0;
+        Ok(())
+    }
+
+    fn has_more(&self) -> bool {
+        self.position < self.data.len()
+    }
+}
+
+/// Target writing rows as length-delimited protobuf records. Columns map to
+/// fields by name, `parent.child` columns to the fields of nested messages,
+/// and JSON values to repeated, map and unflattened message fields;
+/// columns without a field are an error.
+pub struct ProtobufTarget {
+    file_path: PathBuf,
+    descriptor: MessageDescriptor,
+    writer: Option<BufWriter<std::fs::File>>,
+}
+
+impl ProtobufTarget {
+    pub fn new(connection_string: &str, options: &HashMap<String, String>) -> Result<Self> {
+        let (file_path, descriptor) = resolve(connection_string, options)?;
+        Ok(Self {
+            file_path,
+            descriptor,
+            writer: None,
+        })
+    }
+
+    fn row_to_message(&self, row: &Row) -> Result<DynamicMessage> {
+        let message = build_message(&self.descriptor, "", &mut Vec::new(), row)?;
+        Ok(message.unwrap_or_else(|| DynamicMessage::new(self.descriptor.clone())))
+    }
+}
+
+/// The message of the columns under `prefix`, `None` when they are all NULL
+fn build_message(
+    descriptor: &MessageDescriptor,
+    prefix: &str,
+    path: &mut Vec<String>,
+    row: &Row,
+) -> Result<Option<DynamicMessage>> {
+    let mut message = DynamicMessage::new(descriptor.clone());
+    let mut any_set = false;
+    path.push(descriptor.full_name().to_string());
+    for field in descriptor.fields() {
+        let name = column_name(prefix, field.name());
+        let value = match flattened(&field, path) {
+            Some(nested) => build_message(&nested, &name, path, row)?.map(ProtoValue::Message),
+            None => match row.get(&name) {
+                None | Some(Value::Null) => None,
+                Some(value) => Some(field_from_value(&field, value).ok_or_else(|| {
+                    TinyEtlError::DataTransfer(format!(
+                        "Column '{}' value '{}' doesn't fit protobuf field {} ({:?})",
+                        name,
+                        value.to_string_for_arrow().unwrap_or_default(),
+                        field.full_name(),
+                        field.kind()
+                    ))
+                })?),
+            },
+        };
+        if let Some(value) = value {
+            message.set_field(&field, value);
+            any_set = true;
+        }
+    }
+    path.pop();
+    Ok(any_set.then_some(message))
+}
+
+/// Protobuf value of a field from a row value; repeated, map and message
+/// fields take JSON
+fn field_from_value(field: &FieldDescriptor, value: &Value) -> Option<ProtoValue> {
+    if field.is_list() || field.is_map() || matches!(field.kind(), Kind::Message(_)) {
+        let json = match value {
+            Value::Json(json) => json.clone(),
+            Value::String(s) => serde_json::from_str(s).ok()?,
+            _ => return None,
+        };
+        return field_from_json(field, &json);
+    }
+    scalar(&field.kind(), value)
+}
+
+fn field_from_json(field: &FieldDescriptor, json: &JsonValue) -> Option<ProtoValue> {
+    if field.is_map() {
+        let Kind::Message(entry) = field.kind() else {
+            return None;
+        };
+        let key_kind = entry.map_entry_key_field().kind();
+        let value_kind = entry.map_entry_value_field().kind();
+        let mut map = HashMap::new();
+        for (key, value) in json.as_object()? {
+            let key = match scalar(&key_kind, &Value::String(key.clone()))? {
+                ProtoValue::Bool(b) => MapKey::Bool(b),
+                ProtoValue::I32(i) => MapKey::I32(i),
+                ProtoValue::I64(i) => MapKey::I64(i),
+                ProtoValue::U32(u) => MapKey::U32(u),
+                ProtoValue::U64(u) => MapKey::U64(u),
+                ProtoValue::String(s) => MapKey::String(s),
+                _ => return None,
+            };
+            map.insert(key, from_json(&value_kind, value)?);
+        }
+        Some(ProtoValue::Map(map))
+    } else if field.is_list() {
+        json.as_array()?
+            .iter()
+            .map(|item| from_json(&field.kind(), item))
+            .collect::<Option<Vec<_>>>()
+            .map(ProtoValue::List)
+    } else {
+        from_json(&field.kind(), json)
+    }
+}
+
+fn from_json(kind: &Kind, json: &JsonValue) -> Option<ProtoValue> {
+    match (kind, json) {
+        (Kind::Message(descriptor), JsonValue::Object(object)) => {
+            let mut message = DynamicMessage::new(descriptor.clone());
+            for field in descriptor.fields() {
+                if let Some(value) = object.get(field.name()).filter(|v| !v.is_null()) {
+                    message.set_field(&field, field_from_json(&field, value)?);
+                }
+            }
+            Some(ProtoValue::Message(message))
+        }
+        (Kind::Message(_), _) => None,
+        (_, JsonValue::String(s)) => scalar(kind, &Value::String(s.clone())),
+        (_, JsonValue::Bool(b)) => scalar(kind, &Value::Boolean(*b)),
+        (_, JsonValue::Number(n)) => match n.as_i64() {
+            Some(i) => scalar(kind, &Value::Integer(i)),
+            None => scalar(kind, &Value::String(n.to_string())),
+        },
+        _ => None,
+    }
+}
+
+/// Protobuf scalar of `kind` from a row value, `None` when it doesn't fit
+fn scalar(kind: &Kind, value: &Value) -> Option<ProtoValue> {
+    let integer = || -> Option<i128> {
+        match value {
+            Value::Integer(i) => Some(*i as i128),
+            Value::Decimal(d) if d.fract().is_zero() => d.to_i128(),
+            Value::String(s) => s.trim().parse().ok(),
+            _ => None,
+        }
+    };
+    let float = || -> Option<f64> {
+        match value {
+            Value::Integer(i) => Some(*i as f64),
+            Value::Decimal(d) => d.to_f64(),
+            Value::String(s) => s.trim().parse().ok(),
+            _ => None,
+        }
+    };
+    Some(match kind {
+        Kind::Int32 | Kind::Sint32 | Kind::Sfixed32 => ProtoValue::I32(integer()?.try_into().ok()?),
+        Kind::Int64 | Kind::Sint64 | Kind::Sfixed64 => ProtoValue::I64(integer()?.try_into().ok()?),
+        Kind::Uint32 | Kind::Fixed32 => ProtoValue::U32(integer()?.try_into().ok()?),
+        Kind::Uint64 | Kind::Fixed64 => ProtoValue::U64(integer()?.try_into().ok()?),
+        Kind::Float => ProtoValue::F32(float()? as f32),
+        Kind::Double => ProtoValue::F64(float()?),
+        Kind::Bool => match value {
+            Value::Boolean(b) => ProtoValue::Bool(*b),
+            Value::String(s) => ProtoValue::Bool(s.trim().parse().ok()?),
+            _ => return None,
+        },
+        Kind::String => ProtoValue::String(match value {
+            Value::Json(json) => json.to_string(),
+            other => other.to_string_for_arrow()?,
+        }),
+        Kind::Bytes => match value {
+            Value::Bytes(b) => ProtoValue::Bytes(Bytes::from(b.clone())),
+            Value::String(s) => ProtoValue::Bytes(Bytes::from(s.clone().into_bytes())),
+            _ => return None,
+        },
+        Kind::Enum(e) => match value {
+            Value::String(s) => match e.get_value_by_name(s.trim()) {
+                Some(v) => ProtoValue::EnumNumber(v.number()),
+                None => ProtoValue::EnumNumber(s.trim().parse().ok()?),
+            },
+            _ => ProtoValue::EnumNumber(integer()?.try_into().ok()?),
+        },
+        Kind::Message(_) => return None,
+    })
+}
+
+#[async_trait]
+impl Target for ProtobufTarget {
+    async fn connect(&mut self) -> Result<()> {
+        if let Some(parent) = self.file_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        Ok(())
+    }
+
+    async fn create_table(&mut self, _table_name: &str, schema: &Schema) -> Result<()> {
+        let mut fields = Vec::new();
+        schema_columns(&self.descriptor, "", &mut Vec::new(), &mut fields);
+        if let Some(column) = schema
+            .columns
+            .iter()
+            .find(|column| !fields.iter().any(|field| field.name == column.name))
+        {
+            return Err(TinyEtlError::Configuration(format!(
+                "Column '{}' has no field in protobuf message {}",
+                column.name,
+                self.descriptor.full_name()
+            )));
+        }
+
+        let file = std::fs::File::create(&self.file_path)?;
+        self.writer = Some(BufWriter::new(file));
+        Ok(())
+    }
+
+    async fn write_batch(&mut self, rows: &[Row]) -> Result<usize> {
+        let mut records = Vec::with_capacity(rows.len());
+        for row in rows {
+            records.push(self.row_to_message(row)?.encode_length_delimited_to_vec());
+        }
+        let writer = self
+            .writer
+            .as_mut()
+            .ok_or_else(|| TinyEtlError::Connection("Writer not connected".to_string()))?;
+        for record in &records {
+            writer.write_all(record)?;
+        }
+        Ok(rows.len())
+    }
+
+    async fn finalize(&mut self) -> Result<()> {
+        if let Some(writer) = self.writer.as_mut() {
+            writer.flush()?;
+        }
+        Ok(())
+    }
+
+    async fn exists(&self, _table_name: &str) -> Result<bool> {
+        Ok(self.file_path.exists())
+    }
+
+    async fn truncate(&mut self, _table_name: &str) -> Result<()> {
+        let file = std::fs::File::create(&self.file_path)?;
+        self.writer = Some(BufWriter::new(file));
+        Ok(())
+    }
+
+    fn supports_append(&self) -> bool {
+        false
+    }
+
+    fn bytes_written(&self) -> Option<u64> {
+        std::fs::metadata(&self.file_path).ok().map(|m| m.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use prost_reflect::prost_types::{
+        field_descriptor_proto::{Label, Type},
+        DescriptorProto, EnumDescriptorProto, EnumValueDescriptorProto, FieldDescriptorProto,
+        FileDescriptorProto, FileDescriptorSet,
+    };
+
+    fn field(name: &str, number: i32, kind: Type, type_name: Option<&str>) -> FieldDescriptorProto {
+        FieldDescriptorProto {
+            name: Some(name.to_string()),
+            number: Some(number),
+            label: Some(Label::Optional as i32),
+            r#type: Some(kind as i32),
+            type_name: type_name.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    /// Descriptor set of `acme.Event { id, kind, tags[], customer { name } }`
+    fn write_descriptor(dir: &std::path::Path) -> String {
+        let mut tags = field("tags", 3, Type::String, None);
+        tags.label = Some(Label::Repeated as i32);
+        let file = FileDescriptorProto {
+            name: Some("event.proto".to_string()),
+            package: Some("acme".to_string()),
+            syntax: Some("proto3".to_string()),
+            message_type: vec![
+                DescriptorProto {
+                    name: Some("Event".to_string()),
+                    field: vec![
+                        field("id", 1, Type::Int64, None),
+                        field("kind", 2, Type::Enum, Some(".acme.Kind")),
+                        tags,
+                        field("customer", 4, Type::Message, Some(".acme.Customer")),
+                    ],
+                    ..Default::default()
+                },
+                DescriptorProto {
+                    name: Some("Customer".to_string()),
+                    field: vec![field("name", 1, Type::String, None)],
+                    ..Default::default()
+                },
+            ],
+            enum_type: vec![EnumDescriptorProto {
+                name: Some("Kind".to_string()),
+                value: ["UNKNOWN", "CLICK", "VIEW"]
+                    .iter()
+                    .enumerate()
+                    .map(|(number, name)| EnumValueDescriptorProto {
+                        name: Some(name.to_string()),
+                        number: Some(number as i32),
+                        ..Default::default()
+                    })
+                    .collect(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let set = FileDescriptorSet { file: vec![file] };
+        let path = dir.join("event.desc");
+        std::fs::write(&path, set.encode_to_vec()).unwrap();
+        path.to_str().unwrap().to_string()
+    }
+
+    #[tokio::test]
+    async fn test_round_trip_flattens_nested_messages() {
+        let dir = tempfile::tempdir().unwrap();
+        let options = HashMap::from([("descriptor".to_string(), write_descriptor(dir.path()))]);
+        let file = dir.path().join("events.pb");
+        let connection = format!("{}#acme.Event", file.display());
+        assert!(is_protobuf_file(&connection));
+
+        let rows = vec![
+            Row::from([
+                ("id".to_string(), Value::Integer(1)),
+                ("kind".to_string(), Value::String("CLICK".to_string())),
+                (
+                    "tags".to_string(),
+                    Value::Json(serde_json::json!(["new", "mobile"])),
+                ),
+                (
+                    "customer.name".to_string(),
+                    Value::String("ann".to_string()),
+                ),
+            ]),
+            Row::from([("id".to_string(), Value::Integer(2))]),
+        ];
+        let mut target = ProtobufTarget::new(&connection, &options).unwrap();
+        target.connect().await.unwrap();
+        let mut source = ProtobufSource::new(&connection, &options).unwrap();
+        let schema = {
+            let mut columns = Vec::new();
+            schema_columns(&source.descriptor, "", &mut Vec::new(), &mut columns);
+            Schema {
+                columns,
+                estimated_rows: None,
+                primary_key_candidate: None,
+            }
+        };
+        target.create_table("events", &schema).await.unwrap();
+        assert_eq!(target.write_batch(&rows).await.unwrap(), 2);
+        target.finalize().await.unwrap();
+
+        let inferred = source.infer_schema(10).await.unwrap();
+        let columns: Vec<_> = inferred.columns.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(columns, ["id", "kind", "tags", "customer.name"]);
+        assert_eq!(inferred.estimated_rows, Some(2));
+
+        let read = source.read_batch(10).await.unwrap();
+        assert_eq!(read.len(), 2);
+        assert_eq!(read[0]["kind"], Value::String("CLICK".to_string()));
+        assert_eq!(
+            read[0]["tags"],
+            Value::Json(serde_json::json!(["new", "mobile"]))
+        );
+        assert_eq!(read[0]["customer.name"], Value::String("ann".to_string()));
+        assert_eq!(read[1]["id"], Value::Integer(2));
+        assert_eq!(read[1]["kind"], Value::String("UNKNOWN".to_string()));
+        assert_eq!(read[1]["customer.name"], Value::Null);
+        assert!(!source.has_more());
+    }
+
+    #[test]
+    fn test_message_and_descriptor_are_required() {
+        let dir = tempfile::tempdir().unwrap();
+        let options = HashMap::from([("descriptor".to_string(), write_descriptor(dir.path()))]);
+
+        let err = ProtobufSource::new("events.pb", &options).err().unwrap();
+        assert!(err.to_string().contains("needs a message name"));
+        let err = ProtobufSource::new("events.pb#acme.Event", &HashMap::new())
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("needs a descriptor set"));
+        let err = ProtobufSource::new("events.pb#acme.Missing", &options)
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("Message 'acme.Missing'"));
+    }
+}
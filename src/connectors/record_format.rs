@@ -0,0 +1,356 @@
+//! Record format adapters: text files of a domain format, such as HL7 v2
+//! batch files or vCard contacts, split into records and flattened into
+//! rows, so they feed the pipeline without a pre-processing step. The
+//! adapter of a file is found by its extension, or named by the
+//! `record_format` source option.
+
+use async_trait::async_trait;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock, RwLock};
+
+use crate::{
+    connectors::{
+        document::{TomlFormat, YamlFormat},
+        encoding::Encoding,
+        hl7::Hl7Format,
+        html::HtmlFormat,
+        icalendar::IcalendarFormat,
+        vcard::VcardFormat,
+        Source,
+    },
+    schema::{Column, Row, Schema, SchemaInferer, Value},
+    Result, TinyEtlError,
+};
+
+/// Record formats, set up with the built-in ones on first use
+static REGISTRY: OnceLock<RwLock<RecordFormatRegistry>> = OnceLock::new();
+
+/// Rows read from a file, with their columns in the order they first appear
+#[derive(Debug, Default)]
+pub struct Records {
+    pub columns: Vec<String>,
+    pub rows: Vec<Row>,
+    seen: HashSet<String>,
+}
+
+impl Records {
+    /// Records starting with `columns`, in this order, whether or not any
+    /// row has a value for them
+    pub fn with_columns(columns: &[&str]) -> Self {
+        let mut records = Self::default();
+        for column in columns {
+            records.seen.insert(column.to_string());
+            records.columns.push(column.to_string());
+        }
+        records
+    }
+
+    /// Set a value of `row`, adding its column the first time it appears
+    pub fn insert(&mut self, row: &mut Row, column: String, value: Value) {
+        if self.seen.insert(column.clone()) {
+            self.columns.push(column.clone());
+        }
+        row.insert(column, value);
+    }
+}
+
+/// Splits the text of a file into records and flattens them into rows. The
+/// options are those of the source, which may hold some for other layers
+/// (e.g. `encoding`).
+pub trait RecordFormat: Send + Sync {
+    /// Extensions of the files read in this format, lowercase, without the dot
+    fn extensions(&self) -> &[&str];
+
+    /// Reject invalid values of the options the format reads, before the
+    /// file is opened
+    fn check_options(&self, _options: &HashMap<String, String>) -> Result<()> {
+        Ok(())
+    }
+
+    fn read(&self, text: &str, options: &HashMap<String, String>) -> Result<Records>;
+
+    /// Read a file from its bytes: the text in `encoding` by default, while
+    /// binary formats such as PDF read the bytes themselves
+    fn read_bytes(
+        &self,
+        bytes: &[u8],
+        encoding: Encoding,
+        options: &HashMap<String, String>,
+    ) -> Result<Records> {
+        self.read(&encoding.decode(bytes), options)
+    }
+}
+
+/// Record formats by name. Starts with `hl7`, `html`, `icalendar`, `toml`,
+/// `vcard` and `yaml`, plus `pdf` with the `pdf` feature;
+/// programs embedding TinyETL add their own with `register_record_format`.
+pub struct RecordFormatRegistry {
+    formats: BTreeMap<String, Arc<dyn RecordFormat>>,
+}
+
+impl Default for RecordFormatRegistry {
+    fn default() -> Self {
+        let mut registry = Self {
+            formats: BTreeMap::new(),
+        };
+        registry.register("hl7", Arc::new(Hl7Format));
+        registry.register("html", Arc::new(HtmlFormat));
+        registry.register("icalendar", Arc::new(IcalendarFormat));
+        #[cfg(feature = "pdf")]
+        registry.register("pdf", Arc::new(crate::connectors::pdf::PdfFormat));
+        registry.register("toml", Arc::new(TomlFormat));
+        registry.register("vcard", Arc::new(VcardFormat));
+        registry.register("yaml", Arc::new(YamlFormat));
+        registry
+    }
+}
+
+impl RecordFormatRegistry {
+    /// Add a format, replacing any format of the same name
+    pub fn register(&mut self, name: &str, format: Arc<dyn RecordFormat>) {
+        self.formats.insert(name.to_lowercase(), format);
+    }
+
+    pub fn get(&self, name: &str) -> Option<Arc<dyn RecordFormat>> {
+        self.formats.get(&name.to_lowercase()).cloned()
+    }
+
+    /// The format reading files with the extension of `path`
+    pub fn for_path(&self, path: &str) -> Option<Arc<dyn RecordFormat>> {
+        let extension = Path::new(path)
+            .extension()
+            .and_then(|ext| ext.to_str())?
+            .to_lowercase();
+        self.formats
+            .values()
+            .find(|format| format.extensions().contains(&extension.as_str()))
+            .cloned()
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.formats.keys().map(String::as_str).collect()
+    }
+}
+
+fn registry() -> &'static RwLock<RecordFormatRegistry> {
+    REGISTRY.get_or_init(|| RwLock::new(RecordFormatRegistry::default()))
+}
+
+/// Make a record format available to sources under `name`, and for files
+/// with its extensions, for the rest of the process
+pub fn register_record_format(name: &str, format: Arc<dyn RecordFormat>) {
+    registry()
+        .write()
+        .unwrap_or_else(|e| e.into_inner())
+        .register(name, format);
+}
+
+/// Whether a connection string names a file read by a record format
+pub fn is_record_file(connection_string: &str) -> bool {
+    !connection_string.contains("://")
+        && registry()
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .for_path(connection_string)
+            .is_some()
+}
+
+/// Source reading a file with a record format. The file is read and
+/// flattened whole on connect, so every record's columns are in the schema.
+pub struct RecordFormatSource {
+    file_path: PathBuf,
+    format: Arc<dyn RecordFormat>,
+    options: HashMap<String, String>,
+    /// `encoding` option; UTF-8 by default
+    encoding: Encoding,
+    columns: Vec<String>,
+    rows: VecDeque<Row>,
+    total_rows: usize,
+    bytes_read: u64,
+    loaded: bool,
+}
+
+impl RecordFormatSource {
+    /// A source for the format named by the `record_format` option, or else
+    /// the one of the file's extension
+    pub fn new(file_path: &str, options: &HashMap<String, String>) -> Result<Self> {
+        let registry = registry().read().unwrap_or_else(|e| e.into_inner());
+        let format = match options.get("record_format") {
+            Some(name) => registry.get(name).ok_or_else(|| {
+                TinyEtlError::Configuration(format!(
+                    "Unknown record format '{}'. Valid values: {}",
+                    name,
+                    registry.names().join(", ")
+                ))
+            })?,
+            None => registry.for_path(file_path).ok_or_else(|| {
+                TinyEtlError::Configuration(format!(
+                    "No record format reads {}; set the record_format option",
+                    file_path
+                ))
+            })?,
+        };
+        format.check_options(options)?;
+        Ok(Self {
+            file_path: PathBuf::from(file_path),
+            format,
+            options: options.clone(),
+            encoding: options
+                .get("encoding")
+                .map(|name| Encoding::parse(name))
+                .transpose()?
+                .unwrap_or(Encoding::Utf8),
+            columns: Vec::new(),
+            rows: VecDeque::new(),
+            total_rows: 0,
+            bytes_read: 0,
+            loaded: false,
+        })
+    }
+
+    fn load(&mut self) -> Result<()> {
+        if !self.file_path.exists() {
+            return Err(TinyEtlError::Connection(format!(
+                "File not found: {}",
+                self.file_path.display()
+            )));
+        }
+        let bytes = std::fs::read(&self.file_path)?;
+        let records = self
+            .format
+            .read_bytes(&bytes, self.encoding, &self.options)?;
+        self.columns = records.columns;
+        self.total_rows = records.rows.len();
+        self.rows = records.rows.into();
+        self.bytes_read = bytes.len() as u64;
+        self.loaded = true;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Source for RecordFormatSource {
+    async fn connect(&mut self) -> Result<()> {
+        self.load()
+    }
+
+    async fn infer_schema(&mut self, _sample_size: usize) -> Result<Schema> {
+        if !self.loaded {
+            self.load()?;
+        }
+        // Every row is in memory, so no record's columns are missed
+        let columns = self
+            .columns
+            .iter()
+            .map(|name| {
+                let types: Vec<_> = self
+                    .rows
+                    .iter()
+                    .filter_map(|row| row.get(name))
+                    .map(SchemaInferer::infer_type)
+                    .collect();
+                let (data_type, nullable) = SchemaInferer::resolve_column_type(&types);
+                Column {
+                    name: name.clone(),
+                    data_type,
+                    nullable,
+                }
+            })
+            .collect();
+        Ok(Schema {
+            columns,
+            estimated_rows: Some(self.total_rows),
+            primary_key_candidate: None,
+        })
+    }
+
+    async fn read_batch(&mut self, batch_size: usize) -> Result<Vec<Row>> {
+        if !self.loaded {
+            self.load()?;
+        }
+        let count = batch_size.min(self.rows.len());
+        Ok(self.rows.drain(..count).collect())
+    }
+
+    async fn estimated_row_count(&self) -> Result<Option<usize>> {
+        Ok(self.loaded.then_some(self.total_rows))
+    }
+
+    fn bytes_read(&self) -> Option<u64> {
+        Some(self.bytes_read)
+    }
+
+    async fn reset(&mut self) -> Result<()> {
+        if self.loaded {
+            self.load()?;
+        }
+        Ok(())
+    }
+
+    fn has_more(&self) -> bool {
+        !self.loaded || !self.rows.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::DataType;
+
+    /// Lines of `name=value` pairs, one record per line
+    struct KeyValueFormat;
+
+    impl RecordFormat for KeyValueFormat {
+        fn extensions(&self) -> &[&str] {
+            &["kv"]
+        }
+
+        fn read(&self, text: &str, _options: &HashMap<String, String>) -> Result<Records> {
+            let mut records = Records::default();
+            for line in text.lines() {
+                let mut row = Row::new();
+                for (name, value) in line.split(' ').filter_map(|pair| pair.split_once('=')) {
+                    records.insert(&mut row, name.to_string(), Value::String(value.to_string()));
+                }
+                records.rows.push(row);
+            }
+            Ok(records)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_registered_format_by_extension_and_name() {
+        register_record_format("test-kv", Arc::new(KeyValueFormat));
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("events.kv");
+        std::fs::write(&path, "id=1 kind=login\nid=2 user=ann\n").unwrap();
+        let path = path.to_string_lossy().to_string();
+        assert!(is_record_file(&path));
+
+        let mut source = RecordFormatSource::new(&path, &HashMap::new()).unwrap();
+        let schema = source.infer_schema(1).await.unwrap();
+        let names: Vec<&str> = schema.columns.iter().map(|c| c.name.as_str()).collect();
+        // The second record's column is found beyond the sample
+        assert_eq!(names, vec!["id", "kind", "user"]);
+        assert!(schema
+            .columns
+            .iter()
+            .all(|c| c.data_type == DataType::String));
+        let rows = source.read_batch(10).await.unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[1]["user"], Value::String("ann".to_string()));
+        assert!(!source.has_more());
+
+        let txt = dir.path().join("events.txt");
+        std::fs::write(&txt, "id=3\n").unwrap();
+        let options = HashMap::from([("record_format".to_string(), "TEST-KV".to_string())]);
+        let mut source = RecordFormatSource::new(&txt.to_string_lossy(), &options).unwrap();
+        assert_eq!(source.read_batch(10).await.unwrap().len(), 1);
+
+        let options = HashMap::from([("record_format".to_string(), "edifact".to_string())]);
+        let err = RecordFormatSource::new(&path, &options).err().unwrap();
+        assert!(err.to_string().contains("Unknown record format 'edifact'"));
+        assert!(RecordFormatSource::new("notes.txt", &HashMap::new()).is_err());
+    }
+}
@@ -0,0 +1,874 @@
+//! SAS data sets (`.sas7bdat`), 32- or 64-bit, uncompressed or compressed
+//! with `COMPRESS=CHAR` (RLE) or `COMPRESS=BINARY` (RDC). After the header
+//! come fixed-size pages: the metadata is in subheaders at the start of the
+//! pages, the rows after them, in pages of their own or, when compressed, in
+//! subheaders. Value labels live in separate format catalogs, so SAS
+//! variables have none.
+
+use chrono::NaiveDate;
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufReader, Seek, SeekFrom};
+use std::path::Path;
+
+use crate::{
+    connectors::{
+        encoding::Encoding,
+        stat_file::{datetime_from_seconds, read_bytes, ByteOrder, StatReader, Variable},
+    },
+    schema::{DataType, Value},
+    strict::Coercion,
+    Result, TinyEtlError,
+};
+
+const FORMAT: &str = "SAS";
+
+const MAGIC: [u8; 32] = [
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xc2, 0xea, 0x81, 0x60,
+    0xb3, 0x14, 0x11, 0xcf, 0xbd, 0x92, 0x08, 0x00, 0x09, 0xc7, 0x31, 0x8c, 0x18, 0x1f, 0x10, 0x11,
+];
+
+/// Subheader signatures, read in the byte order of the file
+const ROW_SIZE: u32 = 0xf7f7_f7f7;
+const COLUMN_SIZE: u32 = 0xf6f6_f6f6;
+const SUBHEADER_COUNTS: u32 = 0xffff_fc00;
+const COLUMN_TEXT: u32 = 0xffff_fffd;
+const COLUMN_NAME: u32 = 0xffff_ffff;
+const COLUMN_ATTRIBUTES: u32 = 0xffff_fffc;
+const FORMAT_AND_LABEL: u32 = 0xffff_fbfe;
+const COLUMN_LIST: u32 = 0xffff_fffe;
+const SIGNATURES: [u32; 8] = [
+    ROW_SIZE,
+    COLUMN_SIZE,
+    SUBHEADER_COUNTS,
+    COLUMN_TEXT,
+    COLUMN_NAME,
+    COLUMN_ATTRIBUTES,
+    FORMAT_AND_LABEL,
+    COLUMN_LIST,
+];
+
+/// Subheader compression flags
+const TRUNCATED: u8 = 1;
+const COMPRESSED: u8 = 4;
+/// Page of compressed metadata, which holds nothing to read
+const COMPRESSED_PAGE: u16 = 0x9000;
+
+/// Formats of numbers that are timestamps (seconds since 1960) or dates
+/// (days since 1960), by prefix
+const DATETIME_FORMATS: [&str; 15] = [
+    "DATETIME", "DATEAMPM", "DTDATE", "DTMONYY", "DTWKDATX", "DTYEAR", "MDYAMPM", "E8601DT",
+    "E8601DX", "E8601DZ", "E8601LX", "B8601DT", "B8601DX", "B8601DZ", "B8601LX",
+];
+const DATE_FORMATS: [&str; 26] = [
+    "DATE", "DAY", "DDMMYY", "DOWNAME", "JULDAY", "JULIAN", "MMDDYY", "MMYY", "MONNAME", "MONTH",
+    "MONYY", "QTR", "WEEKDATE", "WEEKDATX", "WEEKDAY", "WORDDATE", "WORDDATX", "YEAR", "YYMM",
+    "YYMON", "YYQ", "E8601DA", "B8601DA", "MINGUO", "NENGO", "NLDATE",
+];
+
+fn invalid(problem: &str) -> TinyEtlError {
+    TinyEtlError::DataTransfer(format!("Invalid SAS file: {}", problem))
+}
+
+fn corrupt() -> TinyEtlError {
+    invalid("a compressed row is corrupt")
+}
+
+/// SAS counts dates and times from 1960-01-01
+fn epoch() -> NaiveDate {
+    NaiveDate::from_ymd_opt(1960, 1, 1).unwrap()
+}
+
+/// `length` bytes at `at`, or an error for a structure that runs past its page
+fn field(bytes: &[u8], at: usize, length: usize) -> Result<&[u8]> {
+    at.checked_add(length)
+        .and_then(|end| bytes.get(at..end))
+        .ok_or_else(|| invalid("a subheader runs past its page"))
+}
+
+/// The next `length` bytes of compressed input
+fn take<'a>(input: &'a [u8], position: &mut usize, length: usize) -> Result<&'a [u8]> {
+    let bytes = input
+        .get(*position..*position + length)
+        .ok_or_else(corrupt)?;
+    *position += length;
+    Ok(bytes)
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum Compression {
+    #[default]
+    None,
+    Rle,
+    Rdc,
+}
+
+impl Compression {
+    /// Named in the first column text subheader
+    fn of(text: &[u8]) -> Self {
+        if text.windows(8).any(|w| w == b"SASYZCRL") {
+            Compression::Rle
+        } else if text.windows(8).any(|w| w == b"SASYZCR2") {
+            Compression::Rdc
+        } else {
+            Compression::None
+        }
+    }
+
+    fn decompress(&self, input: &[u8], length: usize) -> Result<Vec<u8>> {
+        let mut row = match self {
+            Compression::None => input.to_vec(),
+            Compression::Rle => rle_decompress(input, length)?,
+            Compression::Rdc => rdc_decompress(input, length)?,
+        };
+        row.resize(length, 0);
+        Ok(row)
+    }
+}
+
+/// `COMPRESS=CHAR`: runs of a byte and literal bytes, by control nibble
+fn rle_decompress(input: &[u8], length: usize) -> Result<Vec<u8>> {
+    let mut output = Vec::with_capacity(length);
+    let mut position = 0;
+    while position < input.len() {
+        let control = input[position] >> 4;
+        let low = (input[position] & 0x0f) as usize;
+        position += 1;
+        let (count, byte) = match control {
+            0x0 | 0x8..=0xb => {
+                let count = match control {
+                    0x0 => take(input, &mut position, 1)?[0] as usize + 64 + low * 256,
+                    _ => low + 1 + 16 * (control as usize - 8),
+                };
+                output.extend_from_slice(take(input, &mut position, count)?);
+                (0, 0)
+            }
+            0x4 => {
+                let count = take(input, &mut position, 1)?[0] as usize + 18 + low * 256;
+                (count, take(input, &mut position, 1)?[0])
+            }
+            0x6 | 0x7 => {
+                let count = take(input, &mut position, 1)?[0] as usize + 17 + low * 256;
+                (count, if control == 0x6 { b' ' } else { 0 })
+            }
+            0xc => (low + 3, take(input, &mut position, 1)?[0]),
+            0xd => (low + 2, b'@'),
+            0xe => (low + 2, b' '),
+            0xf => (low + 2, 0),
+            _ => return Err(corrupt()),
+        };
+        output.resize(output.len() + count, byte);
+        if output.len() > length {
+            return Err(corrupt());
+        }
+    }
+    Ok(output)
+}
+
+/// `COMPRESS=BINARY`: literal bytes, runs and copies of earlier output, told
+/// apart by a 16-bit control word before every 16 items
+fn rdc_decompress(input: &[u8], length: usize) -> Result<Vec<u8>> {
+    let mut output: Vec<u8> = Vec::with_capacity(length);
+    let mut position = 0;
+    let mut control = 0u16;
+    let mut mask = 0u16;
+    while position < input.len() {
+        mask >>= 1;
+        if mask == 0 {
+            let bits = take(input, &mut position, 2)?;
+            control = u16::from_be_bytes([bits[0], bits[1]]);
+            mask = 0x8000;
+        }
+        let byte = take(input, &mut position, 1)?[0];
+        if control & mask == 0 {
+            output.push(byte);
+            continue;
+        }
+        let command = (byte >> 4) as usize;
+        let low = (byte & 0x0f) as usize;
+        match command {
+            0 | 1 => {
+                let count = match command {
+                    0 => low + 3,
+                    _ => low + ((take(input, &mut position, 1)?[0] as usize) << 4) + 19,
+                };
+                let byte = take(input, &mut position, 1)?[0];
+                output.resize(output.len() + count, byte);
+            }
+            _ => {
+                let offset = low + 3 + ((take(input, &mut position, 1)?[0] as usize) << 4);
+                let count = match command {
+                    2 => take(input, &mut position, 1)?[0] as usize + 16,
+                    _ => command,
+                };
+                if offset > output.len() {
+                    return Err(corrupt());
+                }
+                for _ in 0..count {
+                    output.push(output[output.len() - offset]);
+                }
+            }
+        }
+        if output.len() > length {
+            return Err(corrupt());
+        }
+    }
+    Ok(output)
+}
+
+/// Widths and byte order of the integers and offsets of a file
+struct Layout {
+    order: ByteOrder,
+    /// 64-bit file: integers and offsets take 8 bytes
+    wide: bool,
+    page_length: usize,
+}
+
+impl Layout {
+    fn int_length(&self) -> usize {
+        if self.wide {
+            8
+        } else {
+            4
+        }
+    }
+
+    /// Start of the page header fields
+    fn page_offset(&self) -> usize {
+        if self.wide {
+            32
+        } else {
+            16
+        }
+    }
+
+    fn pointer_length(&self) -> usize {
+        3 * self.int_length()
+    }
+
+    fn int(&self, bytes: &[u8], at: usize) -> Result<usize> {
+        let bytes = field(bytes, at, self.int_length())?;
+        Ok(if self.wide {
+            self.order.u64(bytes) as usize
+        } else {
+            self.order.u32(bytes) as usize
+        })
+    }
+
+    fn u16(&self, bytes: &[u8], at: usize) -> Result<usize> {
+        Ok(self.order.u16(field(bytes, at, 2)?) as usize)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PageKind {
+    /// Subheaders, which hold the rows of compressed files
+    Meta,
+    /// Rows only
+    Data,
+    /// Subheaders, then rows
+    Mix,
+    Other,
+}
+
+/// Where a subheader is on its page
+struct Pointer {
+    offset: usize,
+    length: usize,
+    compression: u8,
+    kind: u8,
+}
+
+struct Page {
+    bytes: Vec<u8>,
+    kind: PageKind,
+    block_count: usize,
+    pointers: Vec<Pointer>,
+}
+
+impl Page {
+    fn parse(bytes: Vec<u8>, layout: &Layout) -> Result<Self> {
+        let at = layout.page_offset();
+        let page_type = layout.u16(&bytes, at)? as u16;
+        let kind = match page_type & 0x0f00 {
+            _ if page_type == COMPRESSED_PAGE => PageKind::Other,
+            0x000 | 0x400 => PageKind::Meta,
+            0x100 => PageKind::Data,
+            0x200 => PageKind::Mix,
+            _ => PageKind::Other,
+        };
+        let block_count = layout.u16(&bytes, at + 2)?;
+        let mut pointers = Vec::new();
+        if matches!(kind, PageKind::Meta | PageKind::Mix) {
+            let int = layout.int_length();
+            for i in 0..layout.u16(&bytes, at + 4)? {
+                let start = at + 8 + i * layout.pointer_length();
+                let flags = field(&bytes, start + 2 * int, 2)?;
+                pointers.push(Pointer {
+                    offset: layout.int(&bytes, start)?,
+                    length: layout.int(&bytes, start + int)?,
+                    compression: flags[0],
+                    kind: flags[1],
+                });
+            }
+        }
+        Ok(Self {
+            bytes,
+            kind,
+            block_count,
+            pointers,
+        })
+    }
+
+    /// Pointers to subheaders that hold something
+    fn subheaders(&self) -> impl Iterator<Item = &Pointer> {
+        self.pointers
+            .iter()
+            .filter(|pointer| pointer.compression != TRUNCATED && pointer.length > 0)
+    }
+
+    /// Signature of a subheader, if it is long enough to have one
+    fn signature(&self, pointer: &Pointer, layout: &Layout) -> Option<u32> {
+        if pointer.length < layout.int_length() {
+            return None;
+        }
+        // The 4 bytes that matter of a 64-bit signature are its last ones
+        // in a big-endian file
+        let at = match layout.order {
+            ByteOrder::Big if layout.wide => pointer.offset + 4,
+            _ => pointer.offset,
+        };
+        field(&self.bytes, at, 4)
+            .ok()
+            .map(|bytes| layout.order.u32(bytes))
+    }
+
+    /// Whether a subheader holds a row of a compressed file
+    fn is_row(&self, pointer: &Pointer, layout: &Layout, compression: Compression) -> bool {
+        compression != Compression::None
+            && pointer.kind == 1
+            && (pointer.compression == COMPRESSED || pointer.compression == 0)
+            && !self
+                .signature(pointer, layout)
+                .is_some_and(|signature| SIGNATURES.contains(&signature))
+    }
+}
+
+/// A name or format in a column text subheader
+struct TextRef {
+    block: usize,
+    offset: usize,
+    length: usize,
+}
+
+impl TextRef {
+    fn read(subheader: &[u8], at: usize, layout: &Layout) -> Result<Self> {
+        Ok(Self {
+            block: layout.u16(subheader, at)?,
+            offset: layout.u16(subheader, at + 2)?,
+            length: layout.u16(subheader, at + 4)?,
+        })
+    }
+}
+
+struct Attributes {
+    /// Position in the row
+    offset: usize,
+    width: usize,
+    numeric: bool,
+}
+
+/// The metadata subheaders, as they are read page by page
+#[derive(Default)]
+struct Metadata {
+    row_length: usize,
+    row_count: usize,
+    mix_page_row_count: usize,
+    compression: Compression,
+    texts: Vec<Vec<u8>>,
+    names: Vec<TextRef>,
+    attributes: Vec<Attributes>,
+    formats: Vec<TextRef>,
+}
+
+impl Metadata {
+    fn read_page(&mut self, page: &Page, layout: &Layout) -> Result<()> {
+        for pointer in page.subheaders() {
+            if let Some(signature) = page.signature(pointer, layout) {
+                let subheader = field(&page.bytes, pointer.offset, pointer.length)?;
+                self.read_subheader(signature, subheader, layout)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn read_subheader(&mut self, signature: u32, subheader: &[u8], layout: &Layout) -> Result<()> {
+        let int = layout.int_length();
+        match signature {
+            ROW_SIZE => {
+                self.row_length = layout.int(subheader, 5 * int)?;
+                self.row_count = layout.int(subheader, 6 * int)?;
+                self.mix_page_row_count = layout.int(subheader, 15 * int)?;
+            }
+            COLUMN_TEXT => {
+                let size = layout.u16(subheader, int)?;
+                let text = field(subheader, int, size)?.to_vec();
+                if self.texts.is_empty() {
+                    self.compression = Compression::of(&text);
+                }
+                self.texts.push(text);
+            }
+            COLUMN_NAME => {
+                for i in 0..subheader.len().saturating_sub(2 * int + 12) / 8 {
+                    self.names
+                        .push(TextRef::read(subheader, int + 8 * (i + 1), layout)?);
+                }
+            }
+            COLUMN_ATTRIBUTES => {
+                let vector_length = int + 8;
+                for i in 0..subheader.len().saturating_sub(2 * int + 12) / vector_length {
+                    let at = i * vector_length;
+                    self.attributes.push(Attributes {
+                        offset: layout.int(subheader, int + 8 + at)?,
+                        width: layout.order.u32(field(subheader, 2 * int + 8 + at, 4)?) as usize,
+                        numeric: field(subheader, 2 * int + 14 + at, 1)?[0] == 1,
+                    });
+                }
+            }
+            FORMAT_AND_LABEL => {
+                self.formats
+                    .push(TextRef::read(subheader, 3 * int + 22, layout)?);
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn text(&self, text: &TextRef, encoding: Encoding) -> String {
+        self.texts
+            .get(text.block)
+            .and_then(|block| block.get(text.offset..text.offset + text.length))
+            .map(|bytes| encoding.decode(bytes).trim_end().to_string())
+            .unwrap_or_default()
+    }
+}
+
+/// Column type of a number from its format
+fn numeric_type(format: &str) -> DataType {
+    let format = format.to_uppercase();
+    if DATETIME_FORMATS.iter().any(|name| format.starts_with(name)) {
+        DataType::DateTime
+    } else if DATE_FORMATS.iter().any(|name| format.starts_with(name)) {
+        DataType::Date
+    } else {
+        DataType::Decimal
+    }
+}
+
+struct SasColumn {
+    data_type: DataType,
+    offset: usize,
+    width: usize,
+}
+
+pub(crate) struct SasReader {
+    variables: Vec<Variable>,
+    columns: Vec<SasColumn>,
+    file: BufReader<File>,
+    layout: Layout,
+    encoding: Encoding,
+    compression: Compression,
+    rows: usize,
+    row: usize,
+    row_length: usize,
+    mix_page_row_count: usize,
+    pages_left: usize,
+    /// Rows of the current page not read yet
+    page_rows: VecDeque<Vec<u8>>,
+    bytes_read: u64,
+}
+
+impl SasReader {
+    pub(crate) fn open(path: &Path, encoding: Option<Encoding>) -> Result<Self> {
+        let mut file = BufReader::new(File::open(path)?);
+        let header = read_bytes(&mut file, 288, FORMAT)?;
+        if header[..32] != MAGIC {
+            return Err(invalid("it doesn't start with the SAS magic number"));
+        }
+        let wide = header[32] == b'3';
+        let align = if header[35] == b'3' { 4 } else { 0 };
+        let order = if header[37] == 1 {
+            ByteOrder::Little
+        } else {
+            ByteOrder::Big
+        };
+        let encoding = encoding.unwrap_or(match header[70] {
+            20 => Encoding::Utf8,
+            29 => Encoding::Latin1,
+            _ => Encoding::Windows1252,
+        });
+        let header_length = order.u32(&header[196 + align..]) as u64;
+        let page_length = order.u32(&header[200 + align..]) as usize;
+        let page_count = if wide {
+            order.u64(&header[204 + align..]) as usize
+        } else {
+            order.u32(&header[204 + align..]) as usize
+        };
+        file.seek(SeekFrom::Start(header_length))?;
+
+        let mut reader = Self {
+            variables: Vec::new(),
+            columns: Vec::new(),
+            file,
+            layout: Layout {
+                order,
+                wide,
+                page_length,
+            },
+            encoding,
+            compression: Compression::None,
+            rows: 0,
+            row: 0,
+            row_length: 0,
+            mix_page_row_count: 0,
+            pages_left: page_count,
+            page_rows: VecDeque::new(),
+            bytes_read: header_length,
+        };
+
+        // The metadata ends at the first page with rows
+        let mut metadata = Metadata::default();
+        let mut first_page = None;
+        while let Some(page) = reader.read_page()? {
+            if page.kind != PageKind::Data {
+                metadata.read_page(&page, &reader.layout)?;
+            }
+            let has_rows = match page.kind {
+                PageKind::Data | PageKind::Mix => true,
+                PageKind::Meta => page
+                    .subheaders()
+                    .any(|pointer| page.is_row(pointer, &reader.layout, metadata.compression)),
+                PageKind::Other => false,
+            };
+            if has_rows {
+                first_page = Some(page);
+                break;
+            }
+        }
+        reader.describe(metadata)?;
+        if let Some(page) = first_page {
+            reader.load_rows(&page)?;
+        }
+        Ok(reader)
+    }
+
+    fn describe(&mut self, metadata: Metadata) -> Result<()> {
+        if metadata.names.len() != metadata.attributes.len() {
+            return Err(invalid("its column names and attributes don't match"));
+        }
+        for (index, (name, attributes)) in
+            metadata.names.iter().zip(&metadata.attributes).enumerate()
+        {
+            if attributes.offset + attributes.width > metadata.row_length
+                || (attributes.numeric && !(1..=8).contains(&attributes.width))
+            {
+                return Err(invalid("a column doesn't fit its row"));
+            }
+            let data_type = if attributes.numeric {
+                let format = metadata.formats.get(index);
+                numeric_type(
+                    &format.map_or_else(String::new, |format| metadata.text(format, self.encoding)),
+                )
+            } else {
+                DataType::String
+            };
+            self.variables.push(Variable {
+                name: metadata.text(name, self.encoding),
+                data_type: data_type.clone(),
+                labels: None,
+            });
+            self.columns.push(SasColumn {
+                data_type,
+                offset: attributes.offset,
+                width: attributes.width,
+            });
+        }
+        self.compression = metadata.compression;
+        self.rows = metadata.row_count;
+        self.row_length = metadata.row_length;
+        self.mix_page_row_count = metadata.mix_page_row_count;
+        Ok(())
+    }
+
+    fn read_page(&mut self) -> Result<Option<Page>> {
+        if self.pages_left == 0 {
+            return Ok(None);
+        }
+        self.pages_left -= 1;
+        let bytes = read_bytes(&mut self.file, self.layout.page_length, FORMAT)?;
+        self.bytes_read += bytes.len() as u64;
+        Page::parse(bytes, &self.layout).map(Some)
+    }
+
+    fn load_rows(&mut self, page: &Page) -> Result<()> {
+        let layout = &self.layout;
+        let start = layout.page_offset() + 8;
+        let (start, count) = match page.kind {
+            PageKind::Data => (start, page.block_count),
+            PageKind::Mix => {
+                // Rows start 8-byte aligned after the subheader pointers
+                let start = start + page.pointers.len() * layout.pointer_length();
+                let pending = self.row + self.page_rows.len();
+                (
+                    start + start % 8,
+                    self.mix_page_row_count
+                        .min(self.rows.saturating_sub(pending)),
+                )
+            }
+            PageKind::Meta => {
+                for pointer in page.subheaders() {
+                    if page.is_row(pointer, layout, self.compression) {
+                        let bytes = field(&page.bytes, pointer.offset, pointer.length)?;
+                        let row = if pointer.length < self.row_length {
+                            self.compression.decompress(bytes, self.row_length)?
+                        } else {
+                            bytes[..self.row_length].to_vec()
+                        };
+                        self.page_rows.push_back(row);
+                    }
+                }
+                return Ok(());
+            }
+            PageKind::Other => return Ok(()),
+        };
+        for i in 0..count {
+            let row = field(&page.bytes, start + i * self.row_length, self.row_length)?;
+            self.page_rows.push_back(row.to_vec());
+        }
+        Ok(())
+    }
+
+    fn decode(
+        &self,
+        column: &SasColumn,
+        name: &str,
+        row: &[u8],
+        coercion: Coercion,
+    ) -> Result<Value> {
+        let bytes = &row[column.offset..column.offset + column.width];
+        if column.data_type == DataType::String {
+            let text = self.encoding.decode(bytes);
+            return Ok(Value::String(
+                text.trim_end_matches([' ', '\0']).to_string(),
+            ));
+        }
+        // Short numbers are doubles without their low-order bytes
+        let mut bits = [0u8; 8];
+        match self.layout.order {
+            ByteOrder::Little => bits[8 - column.width..].copy_from_slice(bytes),
+            ByteOrder::Big => bits[..column.width].copy_from_slice(bytes),
+        }
+        let value = self.layout.order.f64(&bits);
+        // Missing values (`.`, `._` and `.A` to `.Z`) are NaNs
+        if value.is_nan() {
+            return Ok(Value::Null);
+        }
+        match column.data_type {
+            DataType::Date | DataType::DateTime => {
+                let seconds = if column.data_type == DataType::Date {
+                    value * 86_400.0
+                } else {
+                    value
+                };
+                match datetime_from_seconds(epoch(), seconds) {
+                    Some(datetime) => Ok(Value::Date(datetime)),
+                    None => coercion.lossy(name, &value.to_string(), "is not a date", Value::Null),
+                }
+            }
+            _ => coercion.decimal_from_f64(value, name),
+        }
+    }
+}
+
+impl StatReader for SasReader {
+    fn variables(&self) -> &[Variable] {
+        &self.variables
+    }
+
+    fn row_count(&self) -> Option<usize> {
+        Some(self.rows)
+    }
+
+    fn next_row(&mut self, coercion: Coercion) -> Result<Option<Vec<Value>>> {
+        if self.row >= self.rows {
+            return Ok(None);
+        }
+        let row = loop {
+            if let Some(row) = self.page_rows.pop_front() {
+                break row;
+            }
+            match self.read_page()? {
+                Some(page) => self.load_rows(&page)?,
+                None => return Err(invalid("it has fewer rows than its header says")),
+            }
+        };
+        self.row += 1;
+        self.columns
+            .iter()
+            .zip(&self.variables)
+            .map(|(column, variable)| self.decode(column, &variable.name, &row, coercion))
+            .collect::<Result<_>>()
+            .map(Some)
+    }
+
+    fn bytes_read(&self) -> u64 {
+        self.bytes_read
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connectors::{stat_file::StatFileSource, Source};
+    use chrono::{TimeZone, Utc};
+    use rust_decimal::Decimal;
+    use std::collections::HashMap;
+
+    const PAGE: usize = 1024;
+
+    fn put(bytes: &mut [u8], at: usize, value: &[u8]) {
+        bytes[at..at + value.len()].copy_from_slice(value);
+    }
+
+    fn subheader(signature: u32, length: usize) -> Vec<u8> {
+        let mut bytes = vec![0; length];
+        put(&mut bytes, 0, &signature.to_le_bytes());
+        bytes
+    }
+
+    /// A 32-bit little-endian file of a metadata page and a data page:
+    /// a string, a DATE and a number stored in 4 bytes
+    fn write_file(path: &Path) {
+        let mut header = vec![0; 1024];
+        put(&mut header, 0, &MAGIC);
+        header[37] = 1;
+        header[70] = 20;
+        put(&mut header, 196, &1024u32.to_le_bytes());
+        put(&mut header, 200, &(PAGE as u32).to_le_bytes());
+        put(&mut header, 204, &2u32.to_le_bytes());
+
+        let mut row_size = subheader(ROW_SIZE, 64);
+        put(&mut row_size, 20, &20u32.to_le_bytes());
+        put(&mut row_size, 24, &2u32.to_le_bytes());
+
+        let mut text = subheader(COLUMN_TEXT, 28);
+        put(&mut text, 4, &24u16.to_le_bytes());
+        put(&mut text, 8, b"citybornDATEscore");
+
+        let mut names = subheader(COLUMN_NAME, 44);
+        for (i, (offset, length)) in [(4u16, 4u16), (8, 4), (16, 5)].iter().enumerate() {
+            put(&mut names, 14 + 8 * i, &offset.to_le_bytes());
+            put(&mut names, 16 + 8 * i, &length.to_le_bytes());
+        }
+
+        let mut attributes = subheader(COLUMN_ATTRIBUTES, 56);
+        for (i, (offset, width, kind)) in [(0u32, 8u32, 2u8), (8, 8, 1), (16, 4, 1)]
+            .iter()
+            .enumerate()
+        {
+            put(&mut attributes, 12 + 12 * i, &offset.to_le_bytes());
+            put(&mut attributes, 16 + 12 * i, &width.to_le_bytes());
+            attributes[22 + 12 * i] = *kind;
+        }
+
+        let mut formats = Vec::new();
+        for (offset, length) in [(0u16, 0u16), (12, 4), (0, 0)] {
+            let mut format = subheader(FORMAT_AND_LABEL, 52);
+            put(&mut format, 36, &offset.to_le_bytes());
+            put(&mut format, 38, &length.to_le_bytes());
+            formats.push(format);
+        }
+
+        let mut meta = vec![0; PAGE];
+        let subheaders = [vec![row_size, text, names, attributes], formats].concat();
+        put(&mut meta, 20, &(subheaders.len() as u16).to_le_bytes());
+        let mut offset = 512;
+        for (i, subheader) in subheaders.iter().enumerate() {
+            put(&mut meta, 24 + 12 * i, &(offset as u32).to_le_bytes());
+            put(
+                &mut meta,
+                28 + 12 * i,
+                &(subheader.len() as u32).to_le_bytes(),
+            );
+            put(&mut meta, offset, subheader);
+            offset += subheader.len();
+        }
+
+        let mut data = vec![0; PAGE];
+        put(&mut data, 16, &0x100u16.to_le_bytes());
+        put(&mut data, 18, &2u16.to_le_bytes());
+        put(&mut data, 24, "Köln   ".as_bytes());
+        // 2000-01-01 is day 14,610
+        put(&mut data, 32, &14_610f64.to_le_bytes());
+        put(&mut data, 40, &2.5f64.to_le_bytes()[4..]);
+        put(&mut data, 44, b"Bern    ");
+        put(&mut data, 52, &f64::NAN.to_le_bytes());
+        put(&mut data, 60, &f64::NAN.to_le_bytes()[4..]);
+
+        std::fs::write(path, [header, meta, data].concat()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_read_uncompressed_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("claims.sas7bdat");
+        write_file(&path);
+
+        let mut source = StatFileSource::new(&path.to_string_lossy(), &HashMap::new()).unwrap();
+        let schema = source.infer_schema(100).await.unwrap();
+        let columns: Vec<(&str, DataType)> = schema
+            .columns
+            .iter()
+            .map(|c| (c.name.as_str(), c.data_type.clone()))
+            .collect();
+        assert_eq!(
+            columns,
+            vec![
+                ("city", DataType::String),
+                ("born", DataType::Date),
+                ("score", DataType::Decimal)
+            ]
+        );
+        assert_eq!(schema.estimated_rows, Some(2));
+
+        let rows = source.read_batch(10).await.unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0]["city"], Value::String("Köln".to_string()));
+        assert_eq!(
+            rows[0]["born"],
+            Value::Date(Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap())
+        );
+        assert_eq!(rows[0]["score"], Value::Decimal(Decimal::new(25, 1)));
+        assert_eq!(rows[1]["city"], Value::String("Bern".to_string()));
+        assert_eq!(rows[1]["born"], Value::Null);
+        assert_eq!(rows[1]["score"], Value::Null);
+        assert!(source.read_batch(10).await.unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_decompress_rows() {
+        // Three literal bytes, three spaces, three x's and two NULs
+        let rle = [0x82, b'a', b'b', b'c', 0xe1, 0xc0, b'x', 0xf0];
+        assert_eq!(
+            Compression::Rle.decompress(&rle, 12).unwrap(),
+            b"abc   xxx\0\0\0"
+        );
+        // Three literal bytes, then six copied from three back
+        let rdc = [0x10, 0x00, b'a', b'b', b'c', 0x60, 0x00];
+        assert_eq!(Compression::Rdc.decompress(&rdc, 9).unwrap(), b"abcabcabc");
+        assert!(Compression::Rle.decompress(&[0x30], 4).is_err());
+        assert_eq!(numeric_type("MMDDYY"), DataType::Date);
+        assert_eq!(numeric_type("datetime"), DataType::DateTime);
+        assert_eq!(numeric_type("BEST"), DataType::Decimal);
+    }
+}
@@ -0,0 +1,606 @@
+use async_trait::async_trait;
+use chrono::{SecondsFormat, TimeZone, Utc};
+use rust_decimal::Decimal;
+use std::collections::{HashMap, VecDeque};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tracing::info;
+
+use crate::{
+    connectors::{
+        create_query_source,
+        sql_ident::{quote_ident, quote_style_for, source_relation},
+        sql_params::{typed_value, ParamType},
+        Source,
+    },
+    schema::{Row, Schema, Value},
+    strict::Coercion,
+    Result, TinyEtlError,
+};
+
+/// Names of the `:name` placeholders a partition's range is bound to
+pub const SPLIT_LOW_PARAM: &str = "tinyetl_split_low";
+pub const SPLIT_HIGH_PARAM: &str = "tinyetl_split_high";
+
+/// Check that `--split-column` can read from this source
+fn check_split_source(connection_string: &str) -> Result<()> {
+    let supported = [
+        "postgres://",
+        "postgresql://",
+        "mysql://",
+        "mssql://",
+        "sqlserver://",
+    ];
+    if supported
+        .iter()
+        .any(|scheme| connection_string.starts_with(scheme))
+    {
+        Ok(())
+    } else {
+        Err(TinyEtlError::Configuration(format!(
+            "--split-column is only supported for PostgreSQL, MySQL and MSSQL sources, not {}",
+            connection_string
+        )))
+    }
+}
+
+/// Values splitting `low..=high` into up to `count` ranges of about the same
+/// width. Integers split exactly, decimals by value and dates by millisecond;
+/// any other type is an error.
+pub fn split_points(low: &Value, high: &Value, count: usize) -> Result<Vec<String>> {
+    let (low, high) = (bound_value(low), bound_value(high));
+    let count = count.max(1) as i128;
+    match (&low, &high) {
+        (Value::Integer(low), Value::Integer(high)) => {
+            Ok(integer_points(*low as i128, *high as i128, count)
+                .map(|point| point.to_string())
+                .collect())
+        }
+        (Value::Date(low), Value::Date(high)) => {
+            let points = integer_points(
+                low.timestamp_millis() as i128,
+                high.timestamp_millis() as i128,
+                count,
+            );
+            Ok(points
+                .filter_map(|millis| Utc.timestamp_millis_opt(millis as i64).single())
+                .map(|point| point.to_rfc3339_opts(SecondsFormat::Millis, true))
+                .collect())
+        }
+        (Value::Integer(_) | Value::Decimal(_), Value::Integer(_) | Value::Decimal(_)) => {
+            let (low, high) = (decimal(&low), decimal(&high));
+            let width = (high - low) / Decimal::from(count as i64);
+            if width.is_zero() {
+                return Ok(Vec::new());
+            }
+            Ok((1..count as i64)
+                .map(|i| (low + width * Decimal::from(i)).normalize().to_string())
+                .collect())
+        }
+        _ => Err(TinyEtlError::Configuration(format!(
+            "--split-column must be a numeric or date column, but its values look like {:?} and {:?}",
+            low, high
+        ))),
+    }
+}
+
+/// Bounds some drivers return as text, such as dates from MySQL
+fn bound_value(value: &Value) -> Value {
+    match value {
+        Value::String(s) => typed_value(s),
+        other => other.clone(),
+    }
+}
+
+/// Type the split points of `low..=high` are bound with
+fn point_type(low: &Value, high: &Value) -> ParamType {
+    match (bound_value(low), bound_value(high)) {
+        (Value::Integer(_), Value::Integer(_)) => ParamType::Int,
+        (Value::Date(_), Value::Date(_)) => ParamType::Timestamp,
+        _ => ParamType::Decimal,
+    }
+}
+
+fn decimal(value: &Value) -> Decimal {
+    match value {
+        Value::Integer(i) => Decimal::from(*i),
+        Value::Decimal(d) => *d,
+        _ => Decimal::ZERO,
+    }
+}
+
+/// Starts of ranges 2..=count of `low..=high`, fewer when it holds fewer values
+fn integer_points(low: i128, high: i128, count: i128) -> impl Iterator<Item = i128> {
+    let values = (high - low + 1).max(1);
+    let count = count.min(values);
+    (1..count).map(move |i| low + values * i / count)
+}
+
+/// Queries reading each partition of `relation`: one per range between the
+/// split points, and one for the rows where `column` is NULL. Range bounds
+/// are left as placeholders, with their values in the returned params.
+pub fn partition_queries(
+    relation: &str,
+    column: &str,
+    points: &[String],
+) -> Vec<(String, Vec<(&'static str, String)>)> {
+    let select = format!("SELECT * FROM {}", relation);
+    if points.is_empty() {
+        return vec![(select, Vec::new())];
+    }
+
+    let mut queries = Vec::new();
+    let mut low: Option<&String> = None;
+    for high in points.iter().map(Some).chain(std::iter::once(None)) {
+        let mut conditions = Vec::new();
+        let mut params = Vec::new();
+        // The first and last ranges are open, so they also cover rows
+        // outside the bounds read before
+        if let Some(low) = low {
+            conditions.push(format!("{} >= :{}", column, SPLIT_LOW_PARAM));
+            params.push((SPLIT_LOW_PARAM, low.clone()));
+        }
+        if let Some(high) = high {
+            conditions.push(format!("{} < :{}", column, SPLIT_HIGH_PARAM));
+            params.push((SPLIT_HIGH_PARAM, high.clone()));
+        }
+        queries.push((
+            format!("{} WHERE {}", select, conditions.join(" AND ")),
+            params,
+        ));
+        low = high;
+    }
+    queries.push((format!("{} WHERE {} IS NULL", select, column), Vec::new()));
+    queries
+}
+
+/// Source reading a SQL `#table` or custom `query` as `count` partitions on
+/// the ranges of `column`, each over its own connection. With `read_only`
+/// the bounds are read in a read-only session too.
+pub async fn split_source(
+    connection_string: &str,
+    query: Option<&str>,
+    params: &HashMap<String, String>,
+    attach: &HashMap<String, String>,
+    column: &str,
+    count: usize,
+    read_only: bool,
+) -> Result<Box<dyn Source>> {
+    check_split_source(connection_string)?;
+    let style = quote_style_for(connection_string).ok_or_else(|| {
+        TinyEtlError::Configuration(format!("Unsupported source {}", connection_string))
+    })?;
+    let relation = source_relation(connection_string, query, style, "split", "--split-column")?;
+    let quoted = quote_ident(column, style);
+
+    let bounds_query = format!(
+        "SELECT MIN({0}) AS split_low, MAX({0}) AS split_high FROM {1}",
+        quoted, relation
+    );
+    let mut bounds = create_query_source(connection_string, &bounds_query, params, attach)?;
+    if read_only {
+        bounds.use_read_only_session()?;
+    }
+    bounds.connect().await?;
+    let row = bounds.read_batch(1).await?.into_iter().next();
+    let low = row.as_ref().and_then(|row| row.get("split_low"));
+    let high = row.as_ref().and_then(|row| row.get("split_high"));
+    let (points, param_type) = match (low, high) {
+        (Some(low), Some(high)) if *low != Value::Null && *high != Value::Null => {
+            info!("Splitting {} from {:?} to {:?}", column, low, high);
+            (split_points(low, high, count)?, point_type(low, high))
+        }
+        _ => (Vec::new(), ParamType::Text),
+    };
+
+    let mut partitions = Vec::new();
+    for (sql, bound) in partition_queries(&relation, &quoted, &points) {
+        let mut params = params.clone();
+        params.extend(
+            bound
+                .into_iter()
+                .map(|(name, value)| (format!("{}:{}", name, param_type), value)),
+        );
+        partitions.push(create_query_source(
+            connection_string,
+            &sql,
+            &params,
+            attach,
+        )?);
+    }
+    info!("Reading {} partitions of {}", partitions.len(), column);
+    Ok(Box::new(SplitSource::new(partitions)))
+}
+
+/// Up to `count` rows from the front of `rows`
+fn take_rows(rows: &mut VecDeque<Row>, count: usize) -> Vec<Row> {
+    let count = count.min(rows.len());
+    rows.drain(..count).collect()
+}
+
+enum SplitState {
+    Idle(Vec<Box<dyn Source>>),
+    Reading {
+        batches: mpsc::Receiver<Result<Vec<Row>>>,
+        tasks: Vec<JoinHandle<Box<dyn Source>>>,
+        done: bool,
+    },
+}
+
+/// Reads several partition sources at once, returning their rows in the
+/// order they arrive. Each partition is read on its own task.
+pub struct SplitSource {
+    state: SplitState,
+    /// Rows received beyond the batch size asked for
+    pending: VecDeque<Row>,
+    estimated_rows: Option<usize>,
+}
+
+impl SplitSource {
+    pub fn new(partitions: Vec<Box<dyn Source>>) -> Self {
+        Self {
+            state: SplitState::Idle(partitions),
+            pending: VecDeque::new(),
+            estimated_rows: None,
+        }
+    }
+
+    fn partitions_mut(&mut self) -> Result<&mut Vec<Box<dyn Source>>> {
+        match &mut self.state {
+            SplitState::Idle(partitions) => Ok(partitions),
+            SplitState::Reading { .. } => Err(TinyEtlError::DataTransfer(
+                "Partitions are already being read".to_string(),
+            )),
+        }
+    }
+
+    fn start_reading(partitions: Vec<Box<dyn Source>>, batch_size: usize) -> SplitState {
+        let (sender, batches) = mpsc::channel(partitions.len().max(1));
+        let tasks = partitions
+            .into_iter()
+            .map(|mut source| {
+                let sender = sender.clone();
+                tokio::spawn(async move {
+                    while source.has_more() {
+                        match source.read_batch(batch_size).await {
+                            Ok(rows) if rows.is_empty() => break,
+                            Ok(rows) => {
+                                if sender.send(Ok(rows)).await.is_err() {
+                                    break;
+                                }
+                            }
+                            Err(e) => {
+                                let _ = sender.send(Err(e)).await;
+                                break;
+                            }
+                        }
+                    }
+                    source
+                })
+            })
+            .collect();
+        SplitState::Reading {
+            batches,
+            tasks,
+            done: false,
+        }
+    }
+}
+
+#[async_trait]
+impl Source for SplitSource {
+    async fn connect(&mut self) -> Result<()> {
+        for partition in self.partitions_mut()? {
+            partition.connect().await?;
+        }
+        Ok(())
+    }
+
+    async fn infer_schema(&mut self, sample_size: usize) -> Result<Schema> {
+        // Some partitions may be empty, so take the schema of the largest
+        let mut best: Option<Schema> = None;
+        let mut total = 0;
+        for partition in self.partitions_mut()? {
+            let schema = partition.infer_schema(sample_size).await?;
+            let rows = schema.estimated_rows.unwrap_or_default();
+            total += rows;
+            if best
+                .as_ref()
+                .map_or(true, |best| rows > best.estimated_rows.unwrap_or_default())
+            {
+                best = Some(schema);
+            }
+        }
+        let mut schema = best.ok_or_else(|| {
+            TinyEtlError::SchemaInference("No partitions to infer a schema from".to_string())
+        })?;
+        schema.estimated_rows = Some(total);
+        self.estimated_rows = Some(total);
+        Ok(schema)
+    }
+
+    async fn read_batch(&mut self, batch_size: usize) -> Result<Vec<Row>> {
+        if let SplitState::Idle(partitions) = &mut self.state {
+            let partitions = std::mem::take(partitions);
+            self.state = Self::start_reading(partitions, batch_size);
+        }
+        loop {
+            if self.pending.len() >= batch_size {
+                return Ok(take_rows(&mut self.pending, batch_size));
+            }
+            let SplitState::Reading { batches, done, .. } = &mut self.state else {
+                unreachable!("reading started above");
+            };
+            if *done {
+                return Ok(take_rows(&mut self.pending, batch_size));
+            }
+            match batches.recv().await {
+                Some(Ok(rows)) => self.pending.extend(rows),
+                Some(Err(e)) => return Err(e),
+                None => *done = true,
+            }
+        }
+    }
+
+    async fn estimated_row_count(&self) -> Result<Option<usize>> {
+        if self.estimated_rows.is_some() {
+            return Ok(self.estimated_rows);
+        }
+        let SplitState::Idle(partitions) = &self.state else {
+            return Ok(None);
+        };
+        let mut total = 0;
+        for partition in partitions {
+            match partition.estimated_row_count().await? {
+                Some(rows) => total += rows,
+                None => return Ok(None),
+            }
+        }
+        Ok(Some(total))
+    }
+
+    fn set_query_timeout(&mut self, timeout: Duration) {
+        if let SplitState::Idle(partitions) = &mut self.state {
+            partitions
+                .iter_mut()
+                .for_each(|p| p.set_query_timeout(timeout));
+        }
+    }
+
+    fn set_fetch_size(&mut self, rows: usize) {
+        if let SplitState::Idle(partitions) = &mut self.state {
+            partitions.iter_mut().for_each(|p| p.set_fetch_size(rows));
+        }
+    }
+
+    fn set_coercion(&mut self, coercion: Coercion) {
+        if let SplitState::Idle(partitions) = &mut self.state {
+            partitions.iter_mut().for_each(|p| p.set_coercion(coercion));
+        }
+    }
+
+    fn set_keyset_column(&mut self, column: &str) -> Result<()> {
+        if let SplitState::Idle(partitions) = &mut self.state {
+            for partition in partitions {
+                partition.set_keyset_column(column)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn use_read_only_session(&mut self) -> Result<()> {
+        if let SplitState::Idle(partitions) = &mut self.state {
+            for partition in partitions {
+                partition.use_read_only_session()?;
+            }
+        }
+        Ok(())
+    }
+
+    fn use_consistent_snapshot(&mut self) -> Result<()> {
+        Err(TinyEtlError::Configuration(
+            "--consistent-snapshot can't be combined with --split-column, since each partition is read over its own connection"
+                .to_string(),
+        ))
+    }
+
+    // Partitions are interleaved in the order their rows arrive
+    async fn has_stable_order(&mut self) -> Result<bool> {
+        Ok(false)
+    }
+
+    async fn reset(&mut self) -> Result<()> {
+        self.pending.clear();
+        let state = std::mem::replace(&mut self.state, SplitState::Idle(Vec::new()));
+        let mut partitions = match state {
+            SplitState::Idle(partitions) => partitions,
+            SplitState::Reading { batches, tasks, .. } => {
+                // A task waiting for room in the channel stops once it closes
+                drop(batches);
+                let mut partitions = Vec::new();
+                for task in tasks {
+                    partitions.push(task.await.map_err(|e| {
+                        TinyEtlError::DataTransfer(format!("Partition reader task failed: {}", e))
+                    })?);
+                }
+                partitions
+            }
+        };
+        for partition in &mut partitions {
+            partition.reset().await?;
+        }
+        self.state = SplitState::Idle(partitions);
+        Ok(())
+    }
+
+    fn has_more(&self) -> bool {
+        match &self.state {
+            SplitState::Idle(partitions) => partitions.iter().any(|p| p.has_more()),
+            SplitState::Reading { done, .. } => !done || !self.pending.is_empty(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::{Column, DataType};
+
+    struct RangeSource {
+        ids: Vec<i64>,
+        position: usize,
+    }
+
+    #[async_trait]
+    impl Source for RangeSource {
+        async fn connect(&mut self) -> Result<()> {
+            Ok(())
+        }
+
+        async fn infer_schema(&mut self, _sample_size: usize) -> Result<Schema> {
+            Ok(Schema {
+                columns: vec![Column {
+                    name: "id".to_string(),
+                    data_type: DataType::Integer,
+                    nullable: false,
+                }],
+                estimated_rows: Some(self.ids.len()),
+                primary_key_candidate: None,
+            })
+        }
+
+        async fn read_batch(&mut self, batch_size: usize) -> Result<Vec<Row>> {
+            let end = (self.position + batch_size).min(self.ids.len());
+            let rows = self.ids[self.position..end]
+                .iter()
+                .map(|id| Row::from([("id".to_string(), Value::Integer(*id))]))
+                .collect();
+            self.position = end;
+            Ok(rows)
+        }
+
+        async fn estimated_row_count(&self) -> Result<Option<usize>> {
+            Ok(Some(self.ids.len()))
+        }
+
+        async fn reset(&mut self) -> Result<()> {
+            self.position = 0;
+            Ok(())
+        }
+
+        fn has_more(&self) -> bool {
+            self.position < self.ids.len()
+        }
+    }
+
+    #[test]
+    fn test_split_points() {
+        let int = Value::Integer;
+        assert_eq!(
+            split_points(&int(1), &int(100), 4).unwrap(),
+            vec!["26", "51", "76"]
+        );
+        // Fewer values than partitions
+        assert_eq!(split_points(&int(5), &int(7), 8).unwrap(), vec!["6", "7"]);
+        assert!(split_points(&int(5), &int(5), 8).unwrap().is_empty());
+
+        let dec = |s: &str| Value::Decimal(s.parse().unwrap());
+        assert_eq!(
+            split_points(&dec("0.0"), &dec("1.0"), 4).unwrap(),
+            vec!["0.25", "0.5", "0.75"]
+        );
+
+        assert_eq!(
+            split_points(
+                &Value::String("2024-01-01 00:00:00".to_string()),
+                &Value::String("2024-01-03 00:00:00".to_string()),
+                2
+            )
+            .unwrap(),
+            vec!["2024-01-02T00:00:00.000Z"]
+        );
+
+        assert_eq!(point_type(&int(1), &dec("2.5")), ParamType::Decimal);
+        assert_eq!(
+            point_type(
+                &Value::String("2024-01-01".to_string()),
+                &Value::String("2024-02-01".to_string())
+            ),
+            ParamType::Timestamp
+        );
+
+        assert!(split_points(
+            &Value::String("a".to_string()),
+            &Value::String("z".to_string()),
+            2
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_partition_queries() {
+        let points = vec!["26".to_string(), "51".to_string()];
+        let queries = partition_queries("\"orders\"", "\"id\"", &points);
+        let sql: Vec<&str> = queries.iter().map(|(sql, _)| sql.as_str()).collect();
+        assert_eq!(
+            sql,
+            vec![
+                "SELECT * FROM \"orders\" WHERE \"id\" < :tinyetl_split_high",
+                "SELECT * FROM \"orders\" WHERE \"id\" >= :tinyetl_split_low AND \"id\" < :tinyetl_split_high",
+                "SELECT * FROM \"orders\" WHERE \"id\" >= :tinyetl_split_low",
+                "SELECT * FROM \"orders\" WHERE \"id\" IS NULL",
+            ]
+        );
+        assert_eq!(
+            queries[1].1,
+            vec![
+                (SPLIT_LOW_PARAM, "26".to_string()),
+                (SPLIT_HIGH_PARAM, "51".to_string())
+            ]
+        );
+
+        let queries = partition_queries("`orders`", "`id`", &[]);
+        assert_eq!(
+            queries,
+            vec![("SELECT * FROM `orders`".to_string(), vec![])]
+        );
+
+        assert!(
+            check_split_source("sqlite:orders.db#orders").is_err()
+                && check_split_source("orders.csv").is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_split_source_reads_every_partition() {
+        let partitions: Vec<Box<dyn Source>> = (0..4)
+            .map(|p| {
+                Box::new(RangeSource {
+                    ids: (p * 25..p * 25 + 25).collect(),
+                    position: 0,
+                }) as Box<dyn Source>
+            })
+            .collect();
+        let mut source = SplitSource::new(partitions);
+        source.connect().await.unwrap();
+        let schema = source.infer_schema(10).await.unwrap();
+        assert_eq!(schema.estimated_rows, Some(100));
+
+        // A sample read followed by a reset starts over
+        assert_eq!(source.read_batch(1).await.unwrap().len(), 1);
+        source.reset().await.unwrap();
+
+        let mut ids = Vec::new();
+        while source.has_more() {
+            let rows = source.read_batch(7).await.unwrap();
+            assert!(rows.len() <= 7);
+            ids.extend(rows.into_iter().map(|row| row["id"].clone()));
+        }
+        ids.sort_by_key(|id| match id {
+            Value::Integer(i) => *i,
+            _ => unreachable!(),
+        });
+        assert_eq!(ids, (0..100).map(Value::Integer).collect::<Vec<_>>());
+    }
+}
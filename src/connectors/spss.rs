@@ -0,0 +1,741 @@
+//! SPSS system files (`.sav`), including zlib-compressed ones (`.zsav`).
+//! Every value takes one or more 8-byte slots of a case: numbers are doubles,
+//! strings are padded with spaces to a multiple of 8 bytes.
+
+use chrono::NaiveDate;
+use flate2::read::ZlibDecoder;
+use std::collections::{HashMap, VecDeque};
+use std::fs::File;
+use std::io::{BufReader, Cursor, Read, Seek, SeekFrom};
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::{
+    connectors::{
+        encoding::Encoding,
+        stat_file::{
+            datetime_from_seconds, read_array, read_bytes, ByteOrder, LabelKey, StatReader,
+            ValueLabels, Variable,
+        },
+    },
+    schema::{DataType, Value},
+    strict::Coercion,
+    Result, TinyEtlError,
+};
+
+const FORMAT: &str = "SPSS";
+
+/// Case data compressed with a one-byte code per slot
+const BYTECODE: i32 = 1;
+/// Bytecode-compressed case data in zlib blocks (`.zsav`)
+const ZLIB: i32 = 2;
+
+/// Print format types of dates, stored as seconds since 1582-10-14
+const DATE_FORMATS: [u32; 8] = [20, 23, 24, 28, 29, 30, 38, 39];
+/// Print format types of timestamps
+const DATETIME_FORMATS: [u32; 2] = [22, 41];
+/// Print format types of durations (TIME, DTIME, MTIME) and scientific
+/// notation, which aren't whole numbers even without decimals
+const FRACTIONAL_FORMATS: [u32; 4] = [17, 21, 25, 40];
+
+fn invalid(problem: &str) -> TinyEtlError {
+    TinyEtlError::DataTransfer(format!("Invalid SPSS file: {}", problem))
+}
+
+/// Start of the Gregorian calendar, from which SPSS counts dates
+fn epoch() -> NaiveDate {
+    NaiveDate::from_ymd_opt(1582, 10, 14).unwrap()
+}
+
+/// A variable record of the dictionary. Each takes one slot of a case;
+/// strings longer than 8 bytes are followed by a continuation record for
+/// each further slot.
+struct RawVariable {
+    /// String width, 0 for numbers, -1 for the continuation of a string
+    width: i32,
+    name: Vec<u8>,
+    print_format: u32,
+    missing: Vec<[u8; 8]>,
+    /// The first two missing values are a range
+    missing_range: bool,
+}
+
+/// Value labels, with the (1-based) slots of the variables they label
+type RawLabels = (Vec<([u8; 8], Vec<u8>)>, Vec<usize>);
+
+/// Where a variable's value is in a case
+struct SpssColumn {
+    data_type: DataType,
+    /// Slot and bytes used of each segment; strings over 255 bytes are
+    /// split into segments of 252 bytes
+    segments: Vec<(usize, usize)>,
+    missing_numbers: Vec<f64>,
+    missing_range: Option<(f64, f64)>,
+    missing_text: Vec<String>,
+}
+
+impl SpssColumn {
+    fn is_missing(&self, value: f64) -> bool {
+        self.missing_numbers.contains(&value)
+            || self
+                .missing_range
+                .is_some_and(|(low, high)| value >= low && value <= high)
+    }
+}
+
+/// The case data: the file itself, or the zlib blocks of a `.zsav`
+enum CaseData {
+    Plain {
+        file: BufReader<File>,
+        position: u64,
+    },
+    Zlib(ZlibBlocks),
+}
+
+impl CaseData {
+    fn position(&self) -> u64 {
+        match self {
+            CaseData::Plain { position, .. } => *position,
+            CaseData::Zlib(blocks) => blocks.position,
+        }
+    }
+}
+
+impl Read for CaseData {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self {
+            CaseData::Plain { file, position } => {
+                let read = file.read(buf)?;
+                *position += read as u64;
+                Ok(read)
+            }
+            CaseData::Zlib(blocks) => blocks.read(buf),
+        }
+    }
+}
+
+/// The zlib blocks of a `.zsav`, read one after another
+struct ZlibBlocks {
+    file: BufReader<File>,
+    /// Offset and compressed size of the blocks not read yet
+    blocks: VecDeque<(u64, usize)>,
+    current: Option<ZlibDecoder<Cursor<Vec<u8>>>>,
+    /// End of the last block read
+    position: u64,
+}
+
+impl ZlibBlocks {
+    /// The blocks listed in the trailer the zlib header points to
+    fn open(mut file: BufReader<File>, order: ByteOrder) -> Result<Self> {
+        let header = read_array::<24>(&mut file, FORMAT)?;
+        let data_start = file.stream_position()?;
+        let trailer_offset = order.u64(&header[8..16]);
+        file.seek(SeekFrom::Start(trailer_offset))?;
+        let trailer = read_array::<24>(&mut file, FORMAT)?;
+        let block_count = order.u32(&trailer[20..24]) as usize;
+        let mut blocks = VecDeque::new();
+        for _ in 0..block_count {
+            let entry = read_array::<24>(&mut file, FORMAT)?;
+            let offset = order.u64(&entry[8..16]);
+            let size = order.u32(&entry[20..24]) as usize;
+            blocks.push_back((offset, size));
+        }
+        Ok(Self {
+            file,
+            blocks,
+            current: None,
+            position: data_start,
+        })
+    }
+}
+
+impl Read for ZlibBlocks {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        loop {
+            if let Some(decoder) = self.current.as_mut() {
+                let read = decoder.read(buf)?;
+                if read > 0 || buf.is_empty() {
+                    return Ok(read);
+                }
+            }
+            let Some((offset, size)) = self.blocks.pop_front() else {
+                return Ok(0);
+            };
+            self.file.seek(SeekFrom::Start(offset))?;
+            let mut block = vec![0u8; size];
+            self.file.read_exact(&mut block)?;
+            self.position = offset + size as u64;
+            self.current = Some(ZlibDecoder::new(Cursor::new(block)));
+        }
+    }
+}
+
+/// Bytecode decompression: each block of 8 codes is followed by the
+/// slots the codes don't hold themselves
+struct Bytecode {
+    codes: [u8; 8],
+    next: usize,
+    bias: f64,
+    finished: bool,
+}
+
+impl Bytecode {
+    fn slot(
+        &mut self,
+        input: &mut impl Read,
+        order: ByteOrder,
+        sysmis: f64,
+    ) -> Result<Option<[u8; 8]>> {
+        let number = |value: f64| match order {
+            ByteOrder::Little => value.to_le_bytes(),
+            ByteOrder::Big => value.to_be_bytes(),
+        };
+        loop {
+            if self.finished {
+                return Ok(None);
+            }
+            if self.next == self.codes.len() {
+                match read_slot(input)? {
+                    Some(codes) => {
+                        self.codes = codes;
+                        self.next = 0;
+                    }
+                    None => {
+                        self.finished = true;
+                        return Ok(None);
+                    }
+                }
+            }
+            let code = self.codes[self.next];
+            self.next += 1;
+            let slot = match code {
+                // Padding
+                0 => continue,
+                1..=251 => number(code as f64 - self.bias),
+                252 => {
+                    self.finished = true;
+                    return Ok(None);
+                }
+                253 => read_slot(input)?.ok_or_else(|| invalid("case data is cut short"))?,
+                254 => [b' '; 8],
+                _ => number(sysmis),
+            };
+            return Ok(Some(slot));
+        }
+    }
+}
+
+/// The next 8 bytes, or `None` at the end of the data
+fn read_slot(input: &mut impl Read) -> Result<Option<[u8; 8]>> {
+    let mut slot = [0u8; 8];
+    let mut filled = 0;
+    while filled < slot.len() {
+        let read = input.read(&mut slot[filled..])?;
+        if read == 0 {
+            break;
+        }
+        filled += read;
+    }
+    match filled {
+        0 => Ok(None),
+        8 => Ok(Some(slot)),
+        _ => Err(invalid("case data is cut short")),
+    }
+}
+
+fn read_i32(input: &mut impl Read, order: ByteOrder) -> Result<i32> {
+    Ok(order.i32(&read_array::<4>(input, FORMAT)?))
+}
+
+/// A count in the dictionary, which can't be negative
+fn read_count(input: &mut impl Read, order: ByteOrder) -> Result<usize> {
+    usize::try_from(read_i32(input, order)?).map_err(|_| invalid("negative count"))
+}
+
+fn read_variable(input: &mut impl Read, order: ByteOrder) -> Result<RawVariable> {
+    let record = read_array::<28>(input, FORMAT)?;
+    let width = order.i32(&record[0..4]);
+    let has_label = order.i32(&record[4..8]);
+    let missing_count = order.i32(&record[8..12]);
+    if has_label == 1 {
+        // Labels are padded to a multiple of 4 bytes
+        let length = read_count(input, order)?;
+        read_bytes(input, (length + 3) / 4 * 4, FORMAT)?;
+    }
+    let missing = (0..missing_count.unsigned_abs())
+        .map(|_| read_array::<8>(input, FORMAT))
+        .collect::<Result<_>>()?;
+    Ok(RawVariable {
+        width,
+        name: record[20..28].to_vec(),
+        print_format: order.u32(&record[12..16]),
+        missing,
+        missing_range: missing_count < 0,
+    })
+}
+
+/// A value label record and the variable index record following it
+fn read_value_labels(input: &mut impl Read, order: ByteOrder) -> Result<RawLabels> {
+    let count = read_count(input, order)?;
+    let mut labels = Vec::new();
+    for _ in 0..count {
+        let value = read_array::<8>(input, FORMAT)?;
+        let length = read_array::<1>(input, FORMAT)?[0] as usize;
+        // The length byte and the label are padded to a multiple of 8 bytes
+        let label = read_bytes(input, (length + 8) / 8 * 8 - 1, FORMAT)?;
+        labels.push((value, label[..length].to_vec()));
+    }
+    if read_i32(input, order)? != 4 {
+        return Err(invalid("value labels aren't followed by their variables"));
+    }
+    let count = read_count(input, order)?;
+    let slots = (0..count)
+        .map(|_| read_count(input, order))
+        .collect::<Result<_>>()?;
+    Ok((labels, slots))
+}
+
+/// `NAME=value` pairs of the long name and very long string records
+fn name_pairs(text: &str) -> HashMap<String, String> {
+    text.split('\t')
+        .filter_map(|pair| pair.trim_matches('\0').split_once('='))
+        .map(|(name, value)| (name.to_string(), value.trim_matches('\0').to_string()))
+        .collect()
+}
+
+pub(crate) struct SpssReader {
+    variables: Vec<Variable>,
+    columns: Vec<SpssColumn>,
+    data: CaseData,
+    bytecode: Option<Bytecode>,
+    order: ByteOrder,
+    encoding: Encoding,
+    sysmis: f64,
+    case_slots: usize,
+    cases: Option<usize>,
+}
+
+impl SpssReader {
+    pub(crate) fn open(path: &Path, encoding: Option<Encoding>) -> Result<Self> {
+        let mut data = CaseData::Plain {
+            file: BufReader::new(File::open(path)?),
+            position: 0,
+        };
+        let header = read_array::<176>(&mut data, FORMAT)?;
+        if !matches!(&header[..4], b"$FL2" | b"$FL3") {
+            return Err(invalid("it doesn't start with $FL2"));
+        }
+        // The layout code is 2 or 3 in the byte order of the file
+        let order = [ByteOrder::Little, ByteOrder::Big]
+            .into_iter()
+            .find(|order| matches!(order.i32(&header[64..68]), 2 | 3))
+            .ok_or_else(|| invalid("unknown byte order"))?;
+        let compression = order.i32(&header[72..76]);
+        let cases = usize::try_from(order.i32(&header[80..84])).ok();
+        let bias = order.f64(&header[84..92]);
+
+        let mut raw = Vec::new();
+        let mut label_sets = Vec::new();
+        let mut long_names = Vec::new();
+        let mut very_long_strings = HashMap::new();
+        let mut encoding_name = None;
+        let mut code_page = None;
+        let mut sysmis = -f64::MAX;
+        loop {
+            match read_i32(&mut data, order)? {
+                2 => raw.push(read_variable(&mut data, order)?),
+                3 => label_sets.push(read_value_labels(&mut data, order)?),
+                // Documents: lines of 80 bytes
+                6 => {
+                    let lines = read_count(&mut data, order)?;
+                    read_bytes(&mut data, lines * 80, FORMAT)?;
+                }
+                7 => {
+                    let subtype = read_i32(&mut data, order)?;
+                    let size = read_count(&mut data, order)?;
+                    let count = read_count(&mut data, order)?;
+                    let length = size
+                        .checked_mul(count)
+                        .ok_or_else(|| invalid("extension record is too long"))?;
+                    let body = read_bytes(&mut data, length, FORMAT)?;
+                    match subtype {
+                        3 if body.len() >= 32 => code_page = Some(order.i32(&body[28..32])),
+                        4 if body.len() >= 8 => sysmis = order.f64(&body[..8]),
+                        13 => long_names = body,
+                        14 => very_long_strings = name_pairs(&String::from_utf8_lossy(&body)),
+                        20 => encoding_name = Some(String::from_utf8_lossy(&body).into_owned()),
+                        _ => {}
+                    }
+                }
+                999 => {
+                    read_i32(&mut data, order)?;
+                    break;
+                }
+                other => return Err(invalid(&format!("unknown record type {}", other))),
+            }
+        }
+
+        // Files before SPSS 16 only name the code page, and older ones neither
+        let encoding = encoding
+            .or_else(|| encoding_name.and_then(|name| Encoding::parse(name.trim()).ok()))
+            .or_else(|| code_page.and_then(Encoding::from_code_page))
+            .unwrap_or(Encoding::Windows1252);
+        let long_names = name_pairs(&encoding.decode(&long_names));
+
+        let mut labels_by_slot = HashMap::new();
+        for (labels, slots) in label_sets {
+            let numeric = slots
+                .first()
+                .and_then(|slot| raw.get(slot.wrapping_sub(1)))
+                .map_or(true, |variable: &RawVariable| variable.width == 0);
+            let labels: ValueLabels = labels
+                .iter()
+                .map(|(value, label)| {
+                    let key = if numeric {
+                        LabelKey::number(order.f64(value))
+                    } else {
+                        LabelKey::Text(encoding.decode(value).trim_end().to_string())
+                    };
+                    (key, encoding.decode(label))
+                })
+                .collect();
+            let labels = Arc::new(labels);
+            for slot in slots {
+                labels_by_slot.insert(slot.wrapping_sub(1), Arc::clone(&labels));
+            }
+        }
+
+        let mut variables = Vec::new();
+        let mut columns = Vec::new();
+        let mut slot = 0;
+        while slot < raw.len() {
+            let variable = &raw[slot];
+            if variable.width < 0 {
+                return Err(invalid("string continuation without a string"));
+            }
+            let short_name = encoding.decode(&variable.name).trim_end().to_string();
+            let name = long_names
+                .get(&short_name)
+                .cloned()
+                .unwrap_or_else(|| short_name.clone());
+            let labels = labels_by_slot.get(&slot).cloned();
+
+            let column = if variable.width == 0 {
+                let missing: Vec<f64> = variable.missing.iter().map(|m| order.f64(m)).collect();
+                let (missing_range, missing_numbers) =
+                    if variable.missing_range && missing.len() >= 2 {
+                        (Some((missing[0], missing[1])), missing[2..].to_vec())
+                    } else {
+                        (None, missing)
+                    };
+                let column = SpssColumn {
+                    data_type: numeric_type(variable.print_format),
+                    segments: vec![(slot, 8)],
+                    missing_numbers,
+                    missing_range,
+                    missing_text: Vec::new(),
+                };
+                slot += 1;
+                column
+            } else {
+                let missing_text = variable
+                    .missing
+                    .iter()
+                    .map(|m| encoding.decode(m).trim_end().to_string())
+                    .collect();
+                let width = very_long_strings
+                    .get(&short_name)
+                    .and_then(|width| width.trim().parse::<usize>().ok())
+                    .unwrap_or(variable.width as usize);
+                let segment_count = if width < 256 { 1 } else { (width + 251) / 252 };
+                let mut segments = Vec::new();
+                for segment in 0..segment_count {
+                    if slot >= raw.len() {
+                        return Err(invalid("a very long string is missing segments"));
+                    }
+                    let used = if segment + 1 < segment_count {
+                        252
+                    } else {
+                        width - segment * 252
+                    };
+                    segments.push((slot, used));
+                    // The segment's record and its continuations
+                    slot += 1;
+                    while raw.get(slot).is_some_and(|v| v.width < 0) {
+                        slot += 1;
+                    }
+                }
+                SpssColumn {
+                    data_type: DataType::String,
+                    segments,
+                    missing_numbers: Vec::new(),
+                    missing_range: None,
+                    missing_text,
+                }
+            };
+            variables.push(Variable {
+                name,
+                data_type: column.data_type.clone(),
+                labels,
+            });
+            columns.push(column);
+        }
+
+        let bytecode = match compression {
+            0 => None,
+            BYTECODE | ZLIB => Some(Bytecode {
+                codes: [0; 8],
+                next: 8,
+                bias,
+                finished: false,
+            }),
+            other => return Err(invalid(&format!("unknown compression {}", other))),
+        };
+        if compression == ZLIB {
+            let CaseData::Plain { file, .. } = data else {
+                unreachable!("the dictionary is read from the file itself")
+            };
+            data = CaseData::Zlib(ZlibBlocks::open(file, order)?);
+        }
+
+        Ok(Self {
+            variables,
+            columns,
+            data,
+            bytecode,
+            order,
+            encoding,
+            sysmis,
+            case_slots: raw.len(),
+            cases,
+        })
+    }
+
+    /// The slots of the next case, or `None` after the last
+    fn read_case(&mut self) -> Result<Option<Vec<[u8; 8]>>> {
+        let mut case = Vec::with_capacity(self.case_slots);
+        for index in 0..self.case_slots {
+            let slot = match self.bytecode.as_mut() {
+                Some(bytecode) => bytecode.slot(&mut self.data, self.order, self.sysmis)?,
+                None => read_slot(&mut self.data)?,
+            };
+            match slot {
+                Some(slot) => case.push(slot),
+                None if index == 0 => return Ok(None),
+                None => return Err(invalid("the last case is cut short")),
+            }
+        }
+        Ok(Some(case))
+    }
+
+    fn decode(
+        &self,
+        column: &SpssColumn,
+        name: &str,
+        case: &[[u8; 8]],
+        coercion: Coercion,
+    ) -> Result<Value> {
+        if column.data_type == DataType::String {
+            let mut bytes = Vec::new();
+            for &(slot, used) in &column.segments {
+                let end = (slot + (used + 7) / 8).min(case.len());
+                let segment = case[slot.min(end)..end].concat();
+                bytes.extend_from_slice(&segment[..used.min(segment.len())]);
+            }
+            let text = self.encoding.decode(&bytes).trim_end().to_string();
+            return Ok(if column.missing_text.contains(&text) {
+                Value::Null
+            } else {
+                Value::String(text)
+            });
+        }
+
+        let value = self.order.f64(&case[column.segments[0].0]);
+        if value == self.sysmis || value.is_nan() || column.is_missing(value) {
+            return Ok(Value::Null);
+        }
+        match column.data_type {
+            DataType::Integer => {
+                if value.fract() == 0.0 && value.abs() < i64::MAX as f64 {
+                    Ok(Value::Integer(value as i64))
+                } else {
+                    coercion.lossy(
+                        name,
+                        &value.to_string(),
+                        "is not a whole number",
+                        Value::Integer(value.round() as i64),
+                    )
+                }
+            }
+            DataType::Date | DataType::DateTime => match datetime_from_seconds(epoch(), value) {
+                Some(datetime) => Ok(Value::Date(datetime)),
+                None => coercion.lossy(name, &value.to_string(), "is not a date", Value::Null),
+            },
+            _ => coercion.decimal_from_f64(value, name),
+        }
+    }
+}
+
+/// Column type of a numeric variable from its print format
+fn numeric_type(print_format: u32) -> DataType {
+    let format_type = (print_format >> 16) & 0xFF;
+    let decimals = print_format & 0xFF;
+    if DATE_FORMATS.contains(&format_type) {
+        DataType::Date
+    } else if DATETIME_FORMATS.contains(&format_type) {
+        DataType::DateTime
+    } else if decimals == 0 && !FRACTIONAL_FORMATS.contains(&format_type) {
+        DataType::Integer
+    } else {
+        DataType::Decimal
+    }
+}
+
+impl StatReader for SpssReader {
+    fn variables(&self) -> &[Variable] {
+        &self.variables
+    }
+
+    fn row_count(&self) -> Option<usize> {
+        self.cases
+    }
+
+    fn next_row(&mut self, coercion: Coercion) -> Result<Option<Vec<Value>>> {
+        let Some(case) = self.read_case()? else {
+            return Ok(None);
+        };
+        self.columns
+            .iter()
+            .zip(&self.variables)
+            .map(|(column, variable)| self.decode(column, &variable.name, &case, coercion))
+            .collect::<Result<_>>()
+            .map(Some)
+    }
+
+    fn bytes_read(&self) -> u64 {
+        self.data.position()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    /// A bytecode-compressed file with a number labelled 1 = "Agree",
+    /// 2 = "Disagree" and 9 missing, a date and a 10-byte string
+    fn write_file(path: &Path) {
+        let mut bytes = Vec::new();
+        let i32s = |bytes: &mut Vec<u8>, values: &[i32]| {
+            for value in values {
+                bytes.extend_from_slice(&value.to_le_bytes());
+            }
+        };
+        bytes.extend_from_slice(b"$FL2");
+        bytes.extend_from_slice(&[b' '; 60]);
+        // Layout code, slots per case, compression, weight, cases
+        i32s(&mut bytes, &[2, 4, 1, 0, 2]);
+        bytes.extend_from_slice(&100f64.to_le_bytes());
+        bytes.resize(176, b' ');
+
+        let variable = |bytes: &mut Vec<u8>, width: i32, format: u32, name: &[u8; 8]| {
+            let missing = if format == 0x050200 { 1 } else { 0 };
+            i32s(bytes, &[2, width, 0, missing, format as i32, format as i32]);
+            bytes.extend_from_slice(name);
+            if missing == 1 {
+                bytes.extend_from_slice(&9f64.to_le_bytes());
+            }
+        };
+        // F2.0 with a missing value, DATE11, A10 (two slots)
+        variable(&mut bytes, 0, 0x050200, b"Q1      ");
+        variable(&mut bytes, 0, 0x140b00, b"BORN    ");
+        variable(&mut bytes, 10, 0x010a00, b"CITY    ");
+        variable(&mut bytes, -1, 0, b"        ");
+
+        i32s(&mut bytes, &[3, 2]);
+        for (value, label) in [(1f64, "Agree"), (2f64, "Disagree")] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+            bytes.push(label.len() as u8);
+            bytes.extend_from_slice(label.as_bytes());
+            bytes.resize(bytes.len() + (8 - (label.len() + 1) % 8) % 8, b' ');
+        }
+        i32s(&mut bytes, &[4, 1, 1]);
+
+        // Long names and UTF-8
+        let long_names = b"Q1=Question1\tBORN=born_on\tCITY=city";
+        i32s(&mut bytes, &[7, 13, 1, long_names.len() as i32]);
+        bytes.extend_from_slice(long_names);
+        i32s(&mut bytes, &[7, 20, 1, 5]);
+        bytes.extend_from_slice(b"UTF-8");
+        i32s(&mut bytes, &[999, 0]);
+
+        // 2000-01-01 is 13,166,064,000 seconds after 1582-10-14
+        let born = 13_166_064_000f64;
+        // Case 1: 1, date, "Köln" over two slots; case 2: missing 9,
+        // system-missing, 8 spaces then "ab"
+        bytes.extend_from_slice(&[101, 253, 253, 253, 109, 255, 254, 253]);
+        bytes.extend_from_slice(&born.to_le_bytes());
+        bytes.extend_from_slice(&"Köln    ".as_bytes()[..8]);
+        bytes.extend_from_slice(b"        ");
+        bytes.extend_from_slice(b"ab      ");
+        bytes.extend_from_slice(&[252, 0, 0, 0, 0, 0, 0, 0]);
+        std::fs::write(path, bytes).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_read_compressed_file() {
+        use crate::connectors::{stat_file::StatFileSource, Source};
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("survey.sav");
+        write_file(&path);
+
+        let mut source = StatFileSource::new(&path.to_string_lossy(), &HashMap::new()).unwrap();
+        let schema = source.infer_schema(100).await.unwrap();
+        let columns: Vec<(&str, DataType)> = schema
+            .columns
+            .iter()
+            .map(|c| (c.name.as_str(), c.data_type.clone()))
+            .collect();
+        assert_eq!(
+            columns,
+            vec![
+                ("Question1", DataType::Integer),
+                ("born_on", DataType::Date),
+                ("city", DataType::String),
+            ]
+        );
+        assert_eq!(schema.estimated_rows, Some(2));
+
+        let rows = source.read_batch(10).await.unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0]["Question1"], Value::Integer(1));
+        assert_eq!(
+            rows[0]["born_on"],
+            Value::Date(Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap())
+        );
+        assert_eq!(rows[0]["city"], Value::String("Köln".to_string()));
+        // A user-missing value and the system-missing value are NULL
+        assert_eq!(rows[1]["Question1"], Value::Null);
+        assert_eq!(rows[1]["born_on"], Value::Null);
+        assert_eq!(rows[1]["city"], Value::String("        ab".to_string()));
+        assert!(!source.has_more());
+
+        let options = HashMap::from([("value_labels".to_string(), "true".to_string())]);
+        let mut source = StatFileSource::new(&path.to_string_lossy(), &options).unwrap();
+        let schema = source.infer_schema(100).await.unwrap();
+        assert_eq!(schema.columns[0].data_type, DataType::String);
+        let rows = source.read_batch(10).await.unwrap();
+        assert_eq!(rows[0]["Question1"], Value::String("Agree".to_string()));
+        assert_eq!(rows[1]["Question1"], Value::Null);
+    }
+
+    #[test]
+    fn test_numeric_types_from_print_formats() {
+        assert_eq!(numeric_type(0x050800), DataType::Integer);
+        assert_eq!(numeric_type(0x050802), DataType::Decimal);
+        assert_eq!(numeric_type(0x170a00), DataType::Date);
+        assert_eq!(numeric_type(0x161400), DataType::DateTime);
+        assert_eq!(numeric_type(0x150800), DataType::Decimal);
+    }
+}
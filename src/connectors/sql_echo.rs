@@ -0,0 +1,89 @@
+use tracing::debug;
+
+use crate::schema::{Row, Value};
+
+/// Log target of the statements echoed by `--echo-sql`
+pub const ECHO_TARGET: &str = "tinyetl::sql";
+
+/// Characters of a parameter value shown; the rest is redacted
+const VALUE_PREVIEW_CHARS: usize = 16;
+
+/// Characters of statement text shown (multi-row inserts get very long)
+const SQL_PREVIEW_CHARS: usize = 2000;
+
+/// Echo a statement run by a target
+pub fn echo(sql: &str) {
+    debug!(target: ECHO_TARGET, "{}", preview_sql(sql));
+}
+
+/// Echo a statement run with the values of `rows` as parameters. Only the
+/// first row's values are shown, in `columns` order; the others are counted.
+pub fn echo_rows<'a>(sql: &str, columns: impl IntoIterator<Item = &'a str>, rows: &[Row]) {
+    debug!(
+        target: ECHO_TARGET,
+        "{} -- {}",
+        preview_sql(sql),
+        summarize_params(columns, rows)
+    );
+}
+
+fn preview_sql(sql: &str) -> String {
+    match sql.char_indices().nth(SQL_PREVIEW_CHARS) {
+        Some((end, _)) => format!("{}... ({} chars)", &sql[..end], sql.chars().count()),
+        None => sql.to_string(),
+    }
+}
+
+fn summarize_params<'a>(columns: impl IntoIterator<Item = &'a str>, rows: &[Row]) -> String {
+    let Some(first) = rows.first() else {
+        return "no rows".to_string();
+    };
+    let values: Vec<String> = columns
+        .into_iter()
+        .map(|column| first.get(column).map_or("NULL".to_string(), preview_value))
+        .collect();
+    let mut summary = format!("params: ({})", values.join(", "));
+    if rows.len() > 1 {
+        summary.push_str(&format!(" and {} more rows", rows.len() - 1));
+    }
+    summary
+}
+
+/// Short display of a value, cut after a few characters so data doesn't end
+/// up in logs
+fn preview_value(value: &Value) -> String {
+    let text = match value {
+        Value::Null => return "NULL".to_string(),
+        Value::Bytes(b) => return format!("<{} bytes>", b.len()),
+        other => other.to_string_for_arrow().unwrap_or_default(),
+    };
+    match text.char_indices().nth(VALUE_PREVIEW_CHARS) {
+        Some((end, _)) => format!("'{}...' ({} chars)", &text[..end], text.chars().count()),
+        None => format!("'{}'", text),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_params_are_summarized_and_redacted() {
+        let columns = ["id", "note", "file"];
+        let row = Row::from([
+            ("id".to_string(), Value::Integer(7)),
+            (
+                "note".to_string(),
+                Value::String("a very long private comment".to_string()),
+            ),
+            ("file".to_string(), Value::Bytes(vec![0; 512])),
+        ]);
+
+        assert_eq!(
+            summarize_params(columns, &[row.clone(), row]),
+            "params: ('7', 'a very long priv...' (27 chars), <512 bytes>) and 1 more rows"
+        );
+        assert_eq!(summarize_params(columns, &[]), "no rows");
+        assert_eq!(preview_sql(&"x".repeat(2001)).len(), 2000 + 16);
+    }
+}
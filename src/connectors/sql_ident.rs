@@ -0,0 +1,170 @@
+use crate::{Result, TinyEtlError};
+
+/// How a dialect quotes identifiers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuoteStyle {
+    /// `"name"` (PostgreSQL, SQLite, DuckDB)
+    DoubleQuote,
+    /// `` `name` `` (MySQL)
+    Backtick,
+    /// `[name]` (SQL Server, ODBC)
+    Bracket,
+}
+
+/// Split the `#table` fragment of a connection string into its dotted parts,
+/// e.g. `sales."Order Items"` into `sales` and `Order Items`. Parts may be
+/// quoted with `"`, `` ` `` or `[]` to hold dots; unquoted parts are kept
+/// verbatim, including spaces and case.
+pub fn table_parts(table: &str) -> Vec<String> {
+    let chars: Vec<char> = table.chars().collect();
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let close = match chars[i] {
+            '"' => Some('"'),
+            '`' => Some('`'),
+            '[' => Some(']'),
+            _ => None,
+        };
+        match close {
+            Some(close) if current.is_empty() => {
+                // Quoted part; a doubled closing quote stands for itself
+                i += 1;
+                while i < chars.len() {
+                    if chars[i] == close {
+                        if chars.get(i + 1) == Some(&close) {
+                            current.push(close);
+                            i += 2;
+                            continue;
+                        }
+                        i += 1;
+                        break;
+                    }
+                    current.push(chars[i]);
+                    i += 1;
+                }
+            }
+            _ if chars[i] == '.' => {
+                parts.push(std::mem::take(&mut current));
+                i += 1;
+            }
+            _ => {
+                current.push(chars[i]);
+                i += 1;
+            }
+        }
+    }
+    parts.push(current);
+    parts
+}
+
+/// Quote a single identifier, escaping the closing quote inside it
+pub fn quote_ident(ident: &str, style: QuoteStyle) -> String {
+    match style {
+        QuoteStyle::DoubleQuote => format!("\"{}\"", ident.replace('"', "\"\"")),
+        QuoteStyle::Backtick => format!("`{}`", ident.replace('`', "``")),
+        QuoteStyle::Bracket => format!("[{}]", ident.replace(']', "]]")),
+    }
+}
+
+/// Quote a possibly schema-qualified table name part by part, so
+/// `sales.Order Items` becomes `"sales"."Order Items"`
+pub fn quote_table(table: &str, style: QuoteStyle) -> String {
+    table_parts(table)
+        .iter()
+        .map(|part| quote_ident(part, style))
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// The table name without its schema, for catalog lookups
+pub fn unqualified_table(table: &str) -> String {
+    table_parts(table).pop().unwrap_or_default()
+}
+
+/// The schema a table name is qualified with, if any
+pub fn table_schema(table: &str) -> Option<String> {
+    let mut parts = table_parts(table);
+    parts.pop();
+    parts.pop()
+}
+
+/// Identifier quoting of the SQL database a connection string points at;
+/// `None` for sources that aren't SQLite, PostgreSQL, Redshift, MySQL or SQL Server
+pub fn quote_style_for(connection_string: &str) -> Option<QuoteStyle> {
+    if connection_string.starts_with("postgres://")
+        || connection_string.starts_with("postgresql://")
+        || connection_string.starts_with("redshift://")
+        || connection_string.starts_with("sqlite:")
+        || connection_string.contains(".db#")
+        || connection_string.ends_with(".db")
+    {
+        Some(QuoteStyle::DoubleQuote)
+    } else if connection_string.starts_with("mysql://") {
+        Some(QuoteStyle::Backtick)
+    } else if connection_string.starts_with("mssql://")
+        || connection_string.starts_with("sqlserver://")
+    {
+        Some(QuoteStyle::Bracket)
+    } else {
+        None
+    }
+}
+
+/// What a generated query selects from: the `#table` of the connection
+/// string, or a custom `query` wrapped as a derived table named `alias`.
+/// `option` names the flag needing it, for the error without a table.
+pub fn source_relation(
+    connection_string: &str,
+    query: Option<&str>,
+    style: QuoteStyle,
+    alias: &str,
+    option: &str,
+) -> Result<String> {
+    match query {
+        Some(query) => Ok(format!(
+            "({}) AS {}",
+            query.trim().trim_end_matches(';'),
+            alias
+        )),
+        None => match connection_string.split_once('#') {
+            Some((_, table)) if !table.is_empty() => Ok(quote_table(table, style)),
+            _ => Err(TinyEtlError::Configuration(format!(
+                "{} needs a #table in the source connection string, or --query",
+                option
+            ))),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exotic_table_names_are_split_and_quoted() {
+        assert_eq!(table_parts("My Table"), vec!["My Table"]);
+        assert_eq!(table_parts("schema.Table$old"), vec!["schema", "Table$old"]);
+        assert_eq!(
+            table_parts("\"odd.name\".\"Say \"\"hi\"\"\""),
+            vec!["odd.name", "Say \"hi\""]
+        );
+        assert_eq!(table_parts("[dbo].[Order]]s]"), vec!["dbo", "Order]s"]);
+
+        assert_eq!(
+            quote_table("sales.Order Items", QuoteStyle::DoubleQuote),
+            "\"sales\".\"Order Items\""
+        );
+        assert_eq!(quote_table("My`Table", QuoteStyle::Backtick), "`My``Table`");
+        assert_eq!(
+            quote_table("dbo.Table$old", QuoteStyle::Bracket),
+            "[dbo].[Table$old]"
+        );
+
+        assert_eq!(unqualified_table("`app`.`Users`"), "Users");
+        assert_eq!(table_schema("app.Users"), Some("app".to_string()));
+        assert_eq!(table_schema("Users"), None);
+    }
+}
@@ -0,0 +1,413 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use tracing::warn;
+
+use crate::{date_parser::DateParser, schema::Value, Result, TinyEtlError};
+
+/// How a driver spells positional bind parameters
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlaceholderStyle {
+    /// `$1, $2, ...` (PostgreSQL)
+    Dollar,
+    /// `?` per occurrence (MySQL, SQLite)
+    Question,
+    /// `@P1, @P2, ...` (SQL Server)
+    AtP,
+}
+
+/// Type a parameter value is bound with, given as a `NAME:TYPE` suffix.
+/// Values without one are bound as text.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ParamType {
+    #[default]
+    Text,
+    Int,
+    Decimal,
+    Bool,
+    Timestamp,
+}
+
+impl ParamType {
+    fn parse(name: &str, suffix: &str) -> Result<Self> {
+        match suffix.to_ascii_lowercase().as_str() {
+            "text" => Ok(Self::Text),
+            "int" => Ok(Self::Int),
+            "decimal" => Ok(Self::Decimal),
+            "bool" => Ok(Self::Bool),
+            "timestamp" | "date" => Ok(Self::Timestamp),
+            _ => Err(TinyEtlError::Configuration(format!(
+                "parameter '{}' has unknown type '{}' (use text, int, decimal, bool or timestamp)",
+                name, suffix
+            ))),
+        }
+    }
+
+    /// The value `raw` stands for, or an error naming the parameter when it
+    /// isn't of this type
+    fn value(self, name: &str, raw: &str) -> Result<Value> {
+        let value = match self {
+            Self::Text => Some(Value::String(raw.to_string())),
+            Self::Int => raw.trim().parse().ok().map(Value::Integer),
+            Self::Decimal => raw.trim().parse().ok().map(Value::Decimal),
+            Self::Bool => match raw.trim().to_ascii_lowercase().as_str() {
+                "true" => Some(Value::Boolean(true)),
+                "false" => Some(Value::Boolean(false)),
+                _ => None,
+            },
+            Self::Timestamp => DateParser::try_parse(raw.trim()),
+        };
+        value.ok_or_else(|| {
+            TinyEtlError::Configuration(format!(
+                "parameter '{}' is not a valid {}: '{}'",
+                name, self, raw
+            ))
+        })
+    }
+}
+
+impl fmt::Display for ParamType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Text => "text",
+            Self::Int => "int",
+            Self::Decimal => "decimal",
+            Self::Bool => "bool",
+            Self::Timestamp => "timestamp",
+        })
+    }
+}
+
+/// A query with its `:name` placeholders rewritten to driver placeholders,
+/// plus the values to bind in placeholder order
+#[derive(Debug, Clone, PartialEq)]
+pub struct BoundQuery {
+    pub sql: String,
+    pub values: Vec<Value>,
+}
+
+/// Parameter values by placeholder name, typed by the `NAME:TYPE` suffix of
+/// their key
+fn typed_params(params: &HashMap<String, String>) -> Result<HashMap<String, Value>> {
+    let mut typed = HashMap::new();
+    for (key, raw) in params {
+        let (name, param_type) = match key.split_once(':') {
+            Some((name, suffix)) => (name, ParamType::parse(name, suffix)?),
+            None => (key.as_str(), ParamType::Text),
+        };
+        if typed
+            .insert(name.to_string(), param_type.value(name, raw)?)
+            .is_some()
+        {
+            return Err(TinyEtlError::Configuration(format!(
+                "parameter '{}' is given more than once",
+                name
+            )));
+        }
+    }
+    Ok(typed)
+}
+
+/// Rewrite `:name` placeholders for the given driver. Placeholders inside
+/// string literals, quoted identifiers and comments are left alone, as are
+/// PostgreSQL `::type` casts. Every placeholder must have a value.
+///
+/// A parameter named `name:type` is bound with that type; any other is
+/// bound as text, so the query decides how to compare it. PostgreSQL
+/// decimals are bound as text cast to `numeric`, since the driver has no
+/// decimal type here.
+pub fn bind_named_params(
+    sql: &str,
+    params: &HashMap<String, String>,
+    style: PlaceholderStyle,
+) -> Result<BoundQuery> {
+    let params = typed_params(params)?;
+    let chars: Vec<char> = sql.chars().collect();
+    let mut out = String::with_capacity(sql.len());
+    let mut values = Vec::new();
+    let mut positions: Vec<String> = Vec::new();
+    let mut used = HashSet::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '\'' | '"' | '`' => {
+                // Copy the quoted section verbatim; doubled quotes stay inside it
+                out.push(c);
+                i += 1;
+                while i < chars.len() {
+                    out.push(chars[i]);
+                    i += 1;
+                    if chars[i - 1] == c {
+                        if i < chars.len() && chars[i] == c {
+                            out.push(c);
+                            i += 1;
+                        } else {
+                            break;
+                        }
+                    }
+                }
+            }
+            '-' if chars.get(i + 1) == Some(&'-') => {
+                while i < chars.len() && chars[i] != '\n' {
+                    out.push(chars[i]);
+                    i += 1;
+                }
+            }
+            '/' if chars.get(i + 1) == Some(&'*') => {
+                let end = sql_index_of(&chars, i + 2, "*/").map_or(chars.len(), |end| end + 2);
+                out.extend(&chars[i..end]);
+                i = end;
+            }
+            ':' if chars.get(i + 1) == Some(&':') => {
+                out.push_str("::");
+                i += 2;
+            }
+            ':' if chars
+                .get(i + 1)
+                .is_some_and(|n| n.is_ascii_alphabetic() || *n == '_') =>
+            {
+                let start = i + 1;
+                let mut end = start;
+                while end < chars.len() && (chars[end].is_ascii_alphanumeric() || chars[end] == '_')
+                {
+                    end += 1;
+                }
+                let name: String = chars[start..end].iter().collect();
+                let value = params.get(&name).ok_or_else(|| {
+                    TinyEtlError::Configuration(format!(
+                        "query placeholder ':{}' has no value (use --param {}=VALUE)",
+                        name, name
+                    ))
+                })?;
+                used.insert(name.clone());
+
+                match style {
+                    PlaceholderStyle::Question => {
+                        values.push(value.clone());
+                        out.push('?');
+                    }
+                    PlaceholderStyle::Dollar | PlaceholderStyle::AtP => {
+                        // Numbered placeholders let a repeated name share one value
+                        let position = match positions.iter().position(|p| p == &name) {
+                            Some(position) => position + 1,
+                            None => {
+                                positions.push(name.clone());
+                                values.push(value.clone());
+                                positions.len()
+                            }
+                        };
+                        let prefix = if style == PlaceholderStyle::Dollar {
+                            "$"
+                        } else {
+                            "@P"
+                        };
+                        out.push_str(&format!("{}{}", prefix, position));
+                        if style == PlaceholderStyle::Dollar && matches!(value, Value::Decimal(_)) {
+                            out.push_str("::numeric");
+                        }
+                    }
+                }
+                i = end;
+            }
+            _ => {
+                out.push(c);
+                i += 1;
+            }
+        }
+    }
+
+    let mut unused: Vec<&str> = params
+        .keys()
+        .filter(|name| !used.contains(*name))
+        .map(|name| name.as_str())
+        .collect();
+    if !unused.is_empty() {
+        unused.sort();
+        warn!(
+            "query parameters not used by the query: {}",
+            unused.join(", ")
+        );
+    }
+
+    Ok(BoundQuery { sql: out, values })
+}
+
+/// Typed value guessed from text a driver returned, such as a MySQL date
+pub fn typed_value(raw: &str) -> Value {
+    if let Ok(i) = raw.parse::<i64>() {
+        Value::Integer(i)
+    } else if let Ok(d) = raw.parse::<rust_decimal::Decimal>() {
+        Value::Decimal(d)
+    } else if raw.eq_ignore_ascii_case("true") || raw.eq_ignore_ascii_case("false") {
+        Value::Boolean(raw.eq_ignore_ascii_case("true"))
+    } else if let Some(date) = DateParser::try_parse(raw) {
+        date
+    } else {
+        Value::String(raw.to_string())
+    }
+}
+
+/// Upper-cased words and `;` separators of a SQL text, skipping string
+/// literals, quoted identifiers and comments
+pub fn sql_tokens(sql: &str) -> Vec<String> {
+    let chars: Vec<char> = sql.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '\'' | '"' | '`' => {
+                i += 1;
+                while i < chars.len() {
+                    i += 1;
+                    if chars[i - 1] == c {
+                        if i < chars.len() && chars[i] == c {
+                            i += 1;
+                        } else {
+                            break;
+                        }
+                    }
+                }
+            }
+            '-' if chars.get(i + 1) == Some(&'-') => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+            }
+            '/' if chars.get(i + 1) == Some(&'*') => {
+                i = sql_index_of(&chars, i + 2, "*/").map_or(chars.len(), |end| end + 2);
+            }
+            ';' => {
+                tokens.push(";".to_string());
+                i += 1;
+            }
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                let start = i;
+                while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') {
+                    i += 1;
+                }
+                tokens.push(chars[start..i].iter().collect::<String>().to_uppercase());
+            }
+            _ => i += 1,
+        }
+    }
+
+    tokens
+}
+
+fn sql_index_of(chars: &[char], from: usize, needle: &str) -> Option<usize> {
+    let needle: Vec<char> = needle.chars().collect();
+    (from..chars.len().saturating_sub(needle.len() - 1)).find(|&i| chars[i..].starts_with(&needle))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_rewrites_placeholders_per_driver() {
+        let sql =
+            "SELECT * FROM t WHERE updated_at > :since AND kind = :kind OR created_at > :since";
+        let params = params(&[("since", "2024-01-01"), ("kind", "a")]);
+
+        let bound = bind_named_params(sql, &params, PlaceholderStyle::Dollar).unwrap();
+        assert_eq!(
+            bound.sql,
+            "SELECT * FROM t WHERE updated_at > $1 AND kind = $2 OR created_at > $1"
+        );
+        let text = |s: &str| Value::String(s.to_string());
+        assert_eq!(bound.values, vec![text("2024-01-01"), text("a")]);
+
+        let bound = bind_named_params(sql, &params, PlaceholderStyle::Question).unwrap();
+        assert_eq!(
+            bound.sql,
+            "SELECT * FROM t WHERE updated_at > ? AND kind = ? OR created_at > ?"
+        );
+        assert_eq!(
+            bound.values,
+            vec![text("2024-01-01"), text("a"), text("2024-01-01")]
+        );
+
+        let bound = bind_named_params(sql, &params, PlaceholderStyle::AtP).unwrap();
+        assert!(bound.sql.contains("> @P1 AND kind = @P2"));
+    }
+
+    #[test]
+    fn test_ignores_literals_comments_and_casts() {
+        let sql = "SELECT ':x', \"a:b\", ts::date -- :y\nFROM t /* :z */ WHERE id = :id";
+        let bound =
+            bind_named_params(sql, &params(&[("id", "7")]), PlaceholderStyle::Dollar).unwrap();
+        assert_eq!(
+            bound.sql,
+            "SELECT ':x', \"a:b\", ts::date -- :y\nFROM t /* :z */ WHERE id = $1"
+        );
+        assert_eq!(bound.values, vec![Value::String("7".to_string())]);
+    }
+
+    #[test]
+    fn test_type_suffixes() {
+        let sql = "SELECT * FROM t WHERE zip = :zip AND id > :id AND price < :price AND at > :at";
+        let typed = params(&[
+            ("zip", "02134"),
+            ("id:int", "42"),
+            ("price:decimal", "19.99"),
+            ("at:timestamp", "2024-01-01"),
+        ]);
+        let bound = bind_named_params(sql, &typed, PlaceholderStyle::Dollar).unwrap();
+        assert_eq!(
+            bound.sql,
+            "SELECT * FROM t WHERE zip = $1 AND id > $2 AND price < $3::numeric AND at > $4"
+        );
+        assert_eq!(bound.values[0], Value::String("02134".to_string()));
+        assert_eq!(bound.values[1], Value::Integer(42));
+        assert_eq!(bound.values[2], Value::Decimal("19.99".parse().unwrap()));
+        assert!(matches!(bound.values[3], Value::Date(_)));
+
+        let bound = bind_named_params(sql, &typed, PlaceholderStyle::Question).unwrap();
+        assert!(bound.sql.contains("price < ? AND"));
+
+        for bad in [
+            params(&[("id:int", "4.2")]),
+            params(&[("id:number", "4")]),
+            params(&[("id", "4"), ("id:int", "4")]),
+        ] {
+            let result = bind_named_params("SELECT :id", &bad, PlaceholderStyle::Question);
+            assert!(matches!(result, Err(TinyEtlError::Configuration(_))));
+        }
+    }
+
+    #[test]
+    fn test_missing_parameter_is_an_error() {
+        let result = bind_named_params(
+            "SELECT * FROM t WHERE id = :id",
+            &HashMap::new(),
+            PlaceholderStyle::Question,
+        );
+        assert!(matches!(result, Err(TinyEtlError::Configuration(_))));
+    }
+
+    #[test]
+    fn test_sql_tokens_skip_literals_and_comments() {
+        let tokens = sql_tokens("SELECT 'drop table' /* delete */ FROM t; -- insert\n");
+        assert_eq!(tokens, vec!["SELECT", "FROM", "T", ";"]);
+    }
+
+    #[test]
+    fn test_typed_values() {
+        assert_eq!(typed_value("42"), Value::Integer(42));
+        assert_eq!(typed_value("true"), Value::Boolean(true));
+        assert!(matches!(typed_value("2024-01-01"), Value::Date(_)));
+        assert_eq!(typed_value("EU"), Value::String("EU".to_string()));
+    }
+}
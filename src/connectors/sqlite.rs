@@ -1,11 +1,31 @@
 use async_trait::async_trait;
-use rust_decimal::Decimal;
-use sqlx::{sqlite::SqliteConnectOptions, Column, Row as SqlxRow, SqlitePool};
-use std::path::PathBuf;
+use fs2::FileExt;
+use rust_decimal::prelude::ToPrimitive;
+use sqlx::{
+    query::Query,
+    sqlite::{SqliteArguments, SqliteConnectOptions, SqlitePoolOptions, SqliteRow},
+    Column, Executor, Row as SqlxRow, Sqlite, SqlitePool, Statement, Transaction,
+};
+use std::collections::HashMap;
+use std::fs::File;
+use std::future::Future;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::warn;
 
 use crate::{
-    connectors::{Source, Target},
-    schema::{Column as SchemaColumn, DataType, Row, Schema, Value},
+    connectors::{
+        sql_echo,
+        sql_ident::{quote_ident, quote_table, table_schema, unqualified_table, QuoteStyle},
+        sql_params::{bind_named_params, PlaceholderStyle},
+        Source, Target,
+    },
+    scd2::Scd2Changes,
+    schema::{Column as SchemaColumn, DataType, Row, Schema, SchemaInferer, Value},
+    strict::Coercion,
     Result, TinyEtlError,
 };
 
@@ -14,8 +34,14 @@ pub struct SqliteSource {
     pool: Option<SqlitePool>,
     table_name: String,
     query: Option<String>,
+    params: Vec<Value>,
+    /// Other database files ATTACHed to every connection, as (alias, path)
+    attached: Arc<Vec<(String, String)>>,
     current_offset: usize,
     total_rows: Option<usize>,
+    coercion: Coercion,
+    /// `--read-only`: the database (and any attached one) is opened read-only
+    read_only: bool,
 }
 
 impl SqliteSource {
@@ -40,16 +66,215 @@ impl SqliteSource {
             pool: None,
             table_name: table.to_string(),
             query: None,
+            params: Vec::new(),
+            attached: Arc::default(),
             current_offset: 0,
             total_rows: None,
+            coercion: Coercion::default(),
+            read_only: false,
         })
     }
+
+    /// Read the result of a custom query; any `#table` suffix is ignored
+    pub fn with_query(connection_string: &str, query: &str) -> Result<Self> {
+        let db_path = connection_string
+            .split('#')
+            .next()
+            .unwrap_or_default()
+            .trim_start_matches("sqlite:");
+
+        Ok(Self {
+            connection_string: format!("sqlite:{}", db_path),
+            pool: None,
+            table_name: String::new(),
+            query: Some(query.to_string()),
+            params: Vec::new(),
+            attached: Arc::default(),
+            current_offset: 0,
+            total_rows: None,
+            coercion: Coercion::default(),
+            read_only: false,
+        })
+    }
+
+    /// Bind values to the `:name` placeholders of the custom query
+    pub fn with_params(mut self, params: &HashMap<String, String>) -> Result<Self> {
+        if let Some(query) = &self.query {
+            let bound = bind_named_params(query, params, PlaceholderStyle::Question)?;
+            self.query = Some(bound.sql);
+            self.params = bound.values;
+        }
+        Ok(self)
+    }
+
+    /// Attach other database files under the given aliases, so the custom
+    /// query can join across them as `alias.table`
+    pub fn with_attached(mut self, databases: &HashMap<String, String>) -> Result<Self> {
+        let mut attached = Vec::new();
+        for (alias, path) in databases {
+            // ATTACH silently creates a missing file, which would only show
+            // up later as a "no such table" error
+            if !Path::new(path).exists() {
+                return Err(TinyEtlError::Configuration(format!(
+                    "Attached SQLite database '{}' not found: {}",
+                    alias, path
+                )));
+            }
+            attached.push((alias.clone(), path.clone()));
+        }
+        attached.sort();
+        self.attached = Arc::new(attached);
+        Ok(self)
+    }
+
+    /// The relation rows are selected from: the table or the wrapped custom query
+    fn relation(&self) -> String {
+        match &self.query {
+            Some(query) => format!("({})", query),
+            None => quote_table(&self.table_name, QuoteStyle::DoubleQuote),
+        }
+    }
+
+    /// Numbers are bound as numbers and dates as the RFC 3339 text SQLite
+    /// stores them as; everything else is bound as text
+    fn bind_params<'q>(
+        &'q self,
+        mut query: Query<'q, Sqlite, SqliteArguments<'q>>,
+    ) -> Query<'q, Sqlite, SqliteArguments<'q>> {
+        for value in &self.params {
+            query = match value {
+                Value::Integer(i) => query.bind(*i),
+                Value::Decimal(d) => query.bind(d.to_f64()),
+                Value::Boolean(b) => query.bind(*b),
+                Value::Date(dt) => query.bind(dt.to_rfc3339()),
+                Value::String(s) => query.bind(s.as_str()),
+                Value::Json(j) => query.bind(j.to_string()),
+                Value::Bytes(b) => query.bind(b.as_slice()),
+                Value::Null => query.bind(None::<String>),
+            };
+        }
+        query
+    }
+
+    /// Schema of a custom query from a sample of its rows, or from its result
+    /// columns when it returns nothing
+    async fn infer_query_schema(&mut self, sample_size: usize) -> Result<Schema> {
+        let pool = self.pool.as_ref().unwrap();
+        let relation = self.relation();
+
+        let sample_query = format!("SELECT * FROM {} LIMIT {}", relation, sample_size);
+        let rows = self
+            .bind_params(sqlx::query(&sample_query))
+            .fetch_all(pool)
+            .await?;
+        let sample = rows
+            .iter()
+            .map(|row| Self::convert_row(row, self.coercion))
+            .collect::<Result<Vec<Row>>>()?;
+
+        let count_query = format!("SELECT COUNT(*) as count FROM {}", relation);
+        let count: i64 = self
+            .bind_params(sqlx::query(&count_query))
+            .fetch_one(pool)
+            .await?
+            .get("count");
+
+        let mut schema = if sample.is_empty() {
+            let statement = pool.prepare(&sample_query).await?;
+            Schema {
+                columns: statement
+                    .columns()
+                    .iter()
+                    .map(|column| SchemaColumn {
+                        name: column.name().to_string(),
+                        data_type: DataType::String,
+                        nullable: true,
+                    })
+                    .collect(),
+                estimated_rows: None,
+                primary_key_candidate: None,
+            }
+        } else {
+            let mut schema = SchemaInferer::infer_from_rows(&sample)?;
+            // Keep the column order of the query rather than name order
+            let column_names: Vec<String> = rows[0]
+                .columns()
+                .iter()
+                .map(|column| column.name().to_string())
+                .collect();
+            schema.reorder_columns(&column_names);
+            schema
+        };
+
+        self.total_rows = Some(count as usize);
+        schema.estimated_rows = Some(count as usize);
+        Ok(schema)
+    }
+
+    fn convert_row(row: &SqliteRow, coercion: Coercion) -> Result<Row> {
+        let mut data_row = Row::new();
+
+        // Get column info
+        for (i, column) in row.columns().iter().enumerate() {
+            let column_name = column.name();
+
+            // This is a simplified value extraction - in practice we'd need proper type handling
+            let value = if let Ok(val) = row.try_get::<Option<String>, _>(i) {
+                match val {
+                    Some(s) => Value::String(s),
+                    None => Value::Null,
+                }
+            } else if let Ok(val) = row.try_get::<Option<i64>, _>(i) {
+                match val {
+                    Some(i) => Value::Integer(i),
+                    None => Value::Null,
+                }
+            } else if let Ok(val) = row.try_get::<Option<f64>, _>(i) {
+                match val {
+                    Some(f) => coercion.decimal_from_f64(f, column_name)?,
+                    None => Value::Null,
+                }
+            } else if let Ok(val) = row.try_get::<Option<Vec<u8>>, _>(i) {
+                match val {
+                    Some(b) => Value::Bytes(b),
+                    None => Value::Null,
+                }
+            } else {
+                Value::Null
+            };
+
+            data_row.insert(column_name.to_string(), value);
+        }
+
+        Ok(data_row)
+    }
 }
 
 #[async_trait]
 impl Source for SqliteSource {
     async fn connect(&mut self) -> Result<()> {
-        match SqlitePool::connect(&self.connection_string).await {
+        // ATTACH only applies to the connection it runs on, so every pooled
+        // connection attaches the databases when it opens
+        let attached = self.attached.clone();
+        let options =
+            SqliteConnectOptions::from_str(&self.connection_string)?.read_only(self.read_only);
+        let pool = SqlitePoolOptions::new()
+            .after_connect(move |conn, _meta| {
+                let attached = attached.clone();
+                Box::pin(async move {
+                    for (alias, path) in attached.iter() {
+                        let attach = format!(
+                            "ATTACH DATABASE ? AS {}",
+                            quote_ident(alias, QuoteStyle::DoubleQuote)
+                        );
+                        sqlx::query(&attach).bind(path).execute(&mut *conn).await?;
+                    }
+                    Ok(())
+                })
+            })
+            .connect_with(options)
+            .await;
+        match pool {
             Ok(pool) => {
                 self.pool = Some(pool);
                 Ok(())
@@ -65,17 +290,32 @@ impl Source for SqliteSource {
         }
     }
 
-    async fn infer_schema(&mut self, _sample_size: usize) -> Result<Schema> {
+    async fn infer_schema(&mut self, sample_size: usize) -> Result<Schema> {
         if self.pool.is_none() {
             self.connect().await?;
         }
 
+        if self.query.is_some() {
+            return self.infer_query_schema(sample_size).await;
+        }
+
         let pool = self.pool.as_ref().unwrap();
 
         // Get table info for column definitions
-        let table_info = sqlx::query(&format!("PRAGMA table_info(\"{}\")", self.table_name))
-            .fetch_all(pool)
-            .await?;
+        // The schema goes before the pragma name, not inside its argument
+        let pragma_schema = table_schema(&self.table_name)
+            .map(|schema| format!("{}.", quote_ident(&schema, QuoteStyle::DoubleQuote)))
+            .unwrap_or_default();
+        let table_info = sqlx::query(&format!(
+            "PRAGMA {}table_info({})",
+            pragma_schema,
+            quote_ident(
+                &unqualified_table(&self.table_name),
+                QuoteStyle::DoubleQuote
+            )
+        ))
+        .fetch_all(pool)
+        .await?;
 
         let mut columns = Vec::new();
         for row in table_info {
@@ -102,8 +342,8 @@ impl Source for SqliteSource {
 
         // Get estimated row count
         let count_result = sqlx::query(&format!(
-            "SELECT COUNT(*) as count FROM \"{}\"",
-            self.table_name
+            "SELECT COUNT(*) as count FROM {}",
+            self.relation()
         ))
         .fetch_one(pool)
         .await?;
@@ -128,65 +368,31 @@ impl Source for SqliteSource {
 
         // Use LIMIT and OFFSET for proper pagination
         let query = format!(
-            "SELECT * FROM \"{}\" LIMIT {} OFFSET {}",
-            self.table_name, batch_size, self.current_offset
+            "SELECT * FROM {} LIMIT {} OFFSET {}",
+            self.relation(),
+            batch_size,
+            self.current_offset
         );
-        let rows = sqlx::query(&query).fetch_all(pool).await?;
+        let rows = self
+            .bind_params(sqlx::query(&query))
+            .fetch_all(pool)
+            .await?;
 
         // Update offset for next batch
         self.current_offset += rows.len();
 
-        let mut result_rows = Vec::new();
-        for row in rows {
-            let mut data_row = Row::new();
-
-            // Get column info
-            for (i, column) in row.columns().iter().enumerate() {
-                let column_name = column.name();
-
-                // This is a simplified value extraction - in practice we'd need proper type handling
-                let value = if let Ok(val) = row.try_get::<Option<String>, _>(i) {
-                    match val {
-                        Some(s) => Value::String(s),
-                        None => Value::Null,
-                    }
-                } else if let Ok(val) = row.try_get::<Option<i64>, _>(i) {
-                    match val {
-                        Some(i) => Value::Integer(i),
-                        None => Value::Null,
-                    }
-                } else if let Ok(val) = row.try_get::<Option<f64>, _>(i) {
-                    match val {
-                        Some(f) => {
-                            // Convert f64 to Decimal
-                            match Decimal::try_from(f) {
-                                Ok(d) => Value::Decimal(d),
-                                Err(_) => Value::String(f.to_string()),
-                            }
-                        }
-                        None => Value::Null,
-                    }
-                } else {
-                    Value::Null
-                };
-
-                data_row.insert(column_name.to_string(), value);
-            }
-
-            result_rows.push(data_row);
-        }
-
-        Ok(result_rows)
+        rows.iter()
+            .map(|row| Self::convert_row(row, self.coercion))
+            .collect()
     }
 
     async fn estimated_row_count(&self) -> Result<Option<usize>> {
         if let Some(pool) = &self.pool {
-            let count_result = sqlx::query(&format!(
-                "SELECT COUNT(*) as count FROM {}",
-                self.table_name
-            ))
-            .fetch_one(pool)
-            .await?;
+            let count_query = format!("SELECT COUNT(*) as count FROM {}", self.relation());
+            let count_result = self
+                .bind_params(sqlx::query(&count_query))
+                .fetch_one(pool)
+                .await?;
             let count: i64 = count_result.get("count");
             Ok(Some(count as usize))
         } else {
@@ -194,10 +400,18 @@ impl Source for SqliteSource {
         }
     }
 
+    fn set_coercion(&mut self, coercion: Coercion) {
+        self.coercion = coercion;
+    }
+
+    fn use_read_only_session(&mut self) -> Result<()> {
+        self.read_only = true;
+        Ok(())
+    }
+
     async fn reset(&mut self) -> Result<()> {
         // Reset pagination state
         self.current_offset = 0;
-        self.query = None;
         Ok(())
     }
 
@@ -211,10 +425,108 @@ impl Source for SqliteSource {
     }
 }
 
+/// How long to wait for another connection to release the database (SQLite's default in sqlx)
+const DEFAULT_BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Retries of a statement that still failed with SQLITE_BUSY/SQLITE_LOCKED
+const BUSY_RETRIES: u32 = 5;
+
 pub struct SqliteTarget {
     connection_string: String,
     pool: Option<SqlitePool>,
     table_name: String,
+    busy_timeout: Duration,
+    lock: bool,
+    lock_file: Option<LockFile>,
+    /// Insert each batch in its own transaction
+    atomic_batches: bool,
+    /// Enforce foreign keys (`PRAGMA foreign_keys`) on the load's connections
+    foreign_keys: bool,
+}
+
+/// Cross-process advisory lock on a database file, taken on `<file>.lock`.
+/// The operating system releases it when the file is closed, so a load that
+/// is killed or exits without unwinding leaves no stale lock behind.
+struct LockFile {
+    _file: File,
+}
+
+impl LockFile {
+    /// Lock the lock file, waiting up to `timeout` for another process to
+    /// release it
+    async fn acquire(path: PathBuf, timeout: Duration) -> Result<Self> {
+        // Not truncated here: until it is locked, it holds the PID of the
+        // load holding the lock
+        let mut file = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)?;
+        let started = Instant::now();
+        loop {
+            match file.try_lock_exclusive() {
+                Ok(()) => {
+                    // The holder's PID, to tell which load is writing
+                    file.set_len(0)?;
+                    writeln!(file, "{}", std::process::id())?;
+                    return Ok(Self { _file: file });
+                }
+                Err(e) if e.kind() == fs2::lock_contended_error().kind() => {
+                    if started.elapsed() >= timeout {
+                        let holder = std::fs::read_to_string(&path).unwrap_or_default();
+                        return Err(TinyEtlError::Connection(format!(
+                            "Timed out waiting for the lock on '{}'. Another load (process {}) is writing to this database.",
+                            path.display(),
+                            holder.trim()
+                        )));
+                    }
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+}
+
+/// Whether an error means another connection holds the database
+fn is_busy(error: &sqlx::Error) -> bool {
+    match error {
+        sqlx::Error::Database(e) => e
+            .code()
+            .and_then(|code| code.parse::<i32>().ok())
+            // Primary result code of extended codes such as SQLITE_BUSY_SNAPSHOT
+            .is_some_and(|code| matches!(code & 0xff, 5 | 6)),
+        _ => false,
+    }
+}
+
+/// Run a statement, retrying with backoff while the database is busy. The
+/// busy timeout makes SQLite wait on its own, but some conflicts (such as two
+/// readers upgrading to writers in WAL mode) fail immediately.
+async fn retry_busy<T, F, Fut>(mut statement: F) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = std::result::Result<T, sqlx::Error>>,
+{
+    let mut delay = Duration::from_millis(50);
+    let mut attempt = 0;
+    loop {
+        match statement().await {
+            Err(e) if is_busy(&e) && attempt < BUSY_RETRIES => {
+                attempt += 1;
+                warn!(
+                    "SQLite database is busy, retrying in {}ms ({}/{})",
+                    delay.as_millis(),
+                    attempt,
+                    BUSY_RETRIES
+                );
+                tokio::time::sleep(delay).await;
+                delay *= 2;
+            }
+            result => return Ok(result?),
+        }
+    }
 }
 
 impl SqliteTarget {
@@ -245,14 +557,151 @@ impl SqliteTarget {
             connection_string: format!("sqlite:{}", db_path),
             pool: None,
             table_name: table.to_string(),
+            busy_timeout: DEFAULT_BUSY_TIMEOUT,
+            lock: false,
+            lock_file: None,
+            atomic_batches: false,
+            foreign_keys: true,
         })
     }
 
+    /// How long to wait for other writers, and whether to hold a lock file
+    /// for the whole load so parallel loads to one file run one at a time
+    pub fn with_concurrency(mut self, busy_timeout: Option<Duration>, lock: bool) -> Self {
+        self.busy_timeout = busy_timeout.unwrap_or(DEFAULT_BUSY_TIMEOUT);
+        self.lock = lock;
+        self
+    }
+
+    /// Whether rows are checked against foreign keys. Turned off, rows can
+    /// be loaded before the rows they reference, as when copying tables
+    /// referencing each other.
+    pub fn with_foreign_keys(mut self, foreign_keys: bool) -> Self {
+        self.foreign_keys = foreign_keys;
+        self
+    }
+
     fn get_db_path(&self) -> Result<PathBuf> {
         let path_str = self.connection_string.trim_start_matches("sqlite:");
         Ok(PathBuf::from(path_str))
     }
 
+    /// Insert the chunks of a batch in one transaction
+    /// Column names of the rows (taken from the first) and the INSERT
+    /// statements writing them, each with the chunk of rows it binds
+    fn insert_statements<'r>(&self, rows: &'r [Row]) -> (Vec<String>, Vec<(String, &'r [Row])>) {
+        let Some(first) = rows.first() else {
+            return (Vec::new(), Vec::new());
+        };
+
+        // Get column names from first row
+        let columns: Vec<String> = first.keys().cloned().collect();
+
+        // Quote column names to handle reserved keywords
+        let quoted_columns: Vec<String> =
+            columns.iter().map(|col| format!("\"{}\"", col)).collect();
+
+        // SQLite has a limit of ~999 variables, so we need to chunk our inserts
+        // Calculate max rows per chunk based on column count
+        let max_variables = 900; // Use 900 to be safe
+        let max_rows_per_chunk = max_variables / columns.len();
+
+        // Process rows in chunks
+        let mut statements = Vec::new();
+        for chunk in rows.chunks(max_rows_per_chunk) {
+            // Create batch insert with multiple value groups for this chunk
+            let placeholders_per_row = vec!["?"; columns.len()].join(", ");
+            let value_groups: Vec<String> = (0..chunk.len())
+                .map(|_| format!("({})", placeholders_per_row))
+                .collect();
+
+            let insert_sql = format!(
+                "INSERT INTO {} ({}) VALUES {}",
+                quote_table(&self.table_name, QuoteStyle::DoubleQuote),
+                quoted_columns.join(", "),
+                value_groups.join(", ")
+            );
+
+            sql_echo::echo_rows(&insert_sql, columns.iter().map(String::as_str), chunk);
+            statements.push((insert_sql, chunk));
+        }
+        (columns, statements)
+    }
+
+    async fn insert_atomically(
+        pool: &SqlitePool,
+        statements: &[(String, &[Row])],
+        columns: &[String],
+    ) -> std::result::Result<usize, sqlx::Error> {
+        let mut tx = pool.begin().await?;
+        let written = Self::execute_all(&mut tx, statements, columns).await?;
+        tx.commit().await?;
+        Ok(written)
+    }
+
+    /// Expire the changed versions of an SCD2 history and insert the new
+    /// ones in a single transaction
+    async fn merge_atomically(
+        pool: &SqlitePool,
+        expire: &[(String, &[Row])],
+        expire_columns: &[String],
+        inserts: &[(String, &[Row])],
+        columns: &[String],
+    ) -> std::result::Result<(), sqlx::Error> {
+        let mut tx = pool.begin().await?;
+        Self::execute_all(&mut tx, expire, expire_columns).await?;
+        Self::execute_all(&mut tx, inserts, columns).await?;
+        tx.commit().await
+    }
+
+    /// Run statements in a transaction, each with the values of its rows
+    /// bound in `columns` order; returns the rows they affected
+    async fn execute_all(
+        tx: &mut Transaction<'_, Sqlite>,
+        statements: &[(String, &[Row])],
+        columns: &[String],
+    ) -> std::result::Result<usize, sqlx::Error> {
+        let mut affected = 0;
+        for (sql, chunk) in statements {
+            let result = Self::bind_chunk(sql, columns, chunk)
+                .execute(&mut *tx)
+                .await?;
+            affected += result.rows_affected() as usize;
+        }
+        Ok(affected)
+    }
+
+    /// INSERT statement with all values of a chunk bound in row order
+    fn bind_chunk<'q>(
+        insert_sql: &'q str,
+        columns: &[String],
+        chunk: &'q [Row],
+    ) -> Query<'q, Sqlite, SqliteArguments<'q>> {
+        let mut query = sqlx::query(insert_sql);
+        for row in chunk {
+            for column in columns {
+                let value = row.get(column).unwrap_or(&Value::Null);
+                query = match value {
+                    Value::String(s) => query.bind(s),
+                    Value::Integer(i) => query.bind(*i),
+                    Value::Decimal(d) => {
+                        // Convert Decimal to f64 for SQLite binding
+                        let f: f64 = (*d).try_into().unwrap_or(0.0);
+                        query.bind(f)
+                    }
+                    Value::Boolean(b) => query.bind(*b),
+                    Value::Date(dt) => query.bind(dt.to_rfc3339()),
+                    Value::Json(j) => {
+                        query.bind(serde_json::to_string(j).unwrap_or_else(|_| "{}".to_string()))
+                    }
+                    Value::Bytes(b) => query.bind(b),
+                    Value::Null => query.bind(None::<String>),
+                };
+            }
+        }
+        query
+    }
+
     fn map_data_type_to_sqlite(&self, data_type: &DataType) -> &'static str {
         match data_type {
             DataType::Integer => "INTEGER",
@@ -262,6 +711,7 @@ impl SqliteTarget {
             DataType::Date => "TEXT",
             DataType::DateTime => "TEXT",
             DataType::Json => "TEXT", // SQLite stores JSON as TEXT
+            DataType::Binary => "BLOB",
             DataType::Null => "TEXT",
         }
     }
@@ -276,11 +726,19 @@ impl Target for SqliteTarget {
             std::fs::create_dir_all(parent)?;
         }
 
+        if self.lock && self.lock_file.is_none() {
+            let mut lock_path = db_path.clone().into_os_string();
+            lock_path.push(".lock");
+            self.lock_file = Some(LockFile::acquire(lock_path.into(), self.busy_timeout).await?);
+        }
+
         // SQLite will automatically create the database file if it doesn't exist
         // when we connect to it, so we don't need to create it manually
         let connect_options = SqliteConnectOptions::new()
             .filename(&db_path)
-            .create_if_missing(true);
+            .create_if_missing(true)
+            .busy_timeout(self.busy_timeout)
+            .foreign_keys(self.foreign_keys);
 
         match SqlitePool::connect_with(connect_options).await {
             Ok(pool) => {
@@ -325,12 +783,13 @@ impl Target for SqliteTarget {
 
         // Use CREATE TABLE IF NOT EXISTS to support append-first philosophy
         let create_sql = format!(
-            "CREATE TABLE IF NOT EXISTS \"{}\" ({})",
-            actual_table_name,
+            "CREATE TABLE IF NOT EXISTS {} ({})",
+            quote_table(&actual_table_name, QuoteStyle::DoubleQuote),
             column_definitions.join(", ")
         );
 
-        sqlx::query(&create_sql).execute(pool).await?;
+        sql_echo::echo(&create_sql);
+        retry_busy(|| sqlx::query(&create_sql).execute(pool)).await?;
         Ok(())
     }
 
@@ -344,68 +803,61 @@ impl Target for SqliteTarget {
         }
 
         let pool = self.pool.as_ref().unwrap();
+        let (columns, statements) = self.insert_statements(rows);
 
-        // Get column names from first row
-        let columns: Vec<String> = rows[0].keys().cloned().collect();
-
-        // Quote column names to handle reserved keywords
-        let quoted_columns: Vec<String> =
-            columns.iter().map(|col| format!("\"{}\"", col)).collect();
-
-        // SQLite has a limit of ~999 variables, so we need to chunk our inserts
-        // Calculate max rows per chunk based on column count
-        let max_variables = 900; // Use 900 to be safe
-        let max_rows_per_chunk = max_variables / columns.len();
+        // A failed transaction leaves nothing behind, so the whole batch is
+        // retried while the database is busy
+        if self.atomic_batches {
+            return retry_busy(|| Self::insert_atomically(pool, &statements, &columns)).await;
+        }
 
         let mut total_written = 0;
+        for (insert_sql, chunk) in &statements {
+            let result =
+                retry_busy(|| Self::bind_chunk(insert_sql, &columns, chunk).execute(pool)).await?;
+            total_written += result.rows_affected() as usize;
+        }
 
-        // Process rows in chunks
-        for chunk in rows.chunks(max_rows_per_chunk) {
-            // Create batch insert with multiple value groups for this chunk
-            let placeholders_per_row = vec!["?"; columns.len()].join(", ");
-            let value_groups: Vec<String> = (0..chunk.len())
-                .map(|_| format!("({})", placeholders_per_row))
-                .collect();
+        Ok(total_written)
+    }
 
-            let insert_sql = format!(
-                "INSERT INTO \"{}\" ({}) VALUES {}",
-                self.table_name,
-                quoted_columns.join(", "),
-                value_groups.join(", ")
-            );
+    fn use_atomic_batches(&mut self) -> Result<()> {
+        self.atomic_batches = true;
+        Ok(())
+    }
 
-            let mut query = sqlx::query(&insert_sql);
-
-            // Bind all values in row order for this chunk
-            for row in chunk {
-                for column in &columns {
-                    let value = row.get(column).unwrap_or(&Value::Null);
-                    query = match value {
-                        Value::String(s) => query.bind(s),
-                        Value::Integer(i) => query.bind(*i),
-                        Value::Decimal(d) => {
-                            // Convert Decimal to f64 for SQLite binding
-                            let f: f64 = (*d).try_into().unwrap_or(0.0);
-                            query.bind(f)
-                        }
-                        Value::Boolean(b) => query.bind(*b),
-                        Value::Date(dt) => query.bind(dt.to_rfc3339()),
-                        Value::Json(j) => query
-                            .bind(serde_json::to_string(j).unwrap_or_else(|_| "{}".to_string())),
-                        Value::Null => query.bind(None::<String>),
-                    };
-                }
-            }
+    fn supports_scd2_merge(&self) -> bool {
+        true
+    }
 
-            let result = query.execute(pool).await?;
-            total_written += result.rows_affected() as usize;
-        }
+    async fn apply_scd2(&mut self, changes: &Scd2Changes) -> Result<()> {
+        let pool = self
+            .pool
+            .as_ref()
+            .ok_or_else(|| TinyEtlError::Connection("Pool not connected".to_string()))?;
+        let expire_sql = changes.expire_sql(&self.table_name, QuoteStyle::DoubleQuote, |_| {
+            "?".to_string()
+        });
+        let expire_columns = changes.expire_columns();
+        sql_echo::echo_rows(
+            &expire_sql,
+            expire_columns.iter().map(String::as_str),
+            &changes.expired,
+        );
+        let expire: Vec<(String, &[Row])> = changes
+            .expired
+            .iter()
+            .map(|row| (expire_sql.clone(), std::slice::from_ref(row)))
+            .collect();
+        let (columns, statements) = self.insert_statements(&changes.added);
 
-        Ok(total_written)
+        retry_busy(|| Self::merge_atomically(pool, &expire, &expire_columns, &statements, &columns))
+            .await
     }
 
     async fn finalize(&mut self) -> Result<()> {
-        // SQLite doesn't require explicit finalization
+        // The lock is held until the target is dropped, so it also covers
+        // the statistics refresh that follows
         Ok(())
     }
 
@@ -419,7 +871,7 @@ impl Target for SqliteTarget {
 
             let result =
                 sqlx::query("SELECT name FROM sqlite_master WHERE type='table' AND name=?")
-                    .bind(actual_table_name)
+                    .bind(unqualified_table(actual_table_name))
                     .fetch_optional(pool)
                     .await?;
 
@@ -437,9 +889,48 @@ impl Target for SqliteTarget {
                 table_name
             };
 
-            sqlx::query(&format!("DELETE FROM \"{}\"", actual_table_name))
-                .execute(pool)
-                .await?;
+            let delete_sql = format!(
+                "DELETE FROM {}",
+                quote_table(actual_table_name, QuoteStyle::DoubleQuote)
+            );
+            sql_echo::echo(&delete_sql);
+            retry_busy(|| sqlx::query(&delete_sql).execute(pool)).await?;
+        }
+        Ok(())
+    }
+
+    async fn drop_table(&mut self, table_name: &str) -> Result<()> {
+        if let Some(pool) = &self.pool {
+            let actual_table_name = if table_name.is_empty() {
+                &self.table_name
+            } else {
+                table_name
+            };
+
+            let drop_sql = format!(
+                "DROP TABLE IF EXISTS {}",
+                quote_table(actual_table_name, QuoteStyle::DoubleQuote)
+            );
+            sql_echo::echo(&drop_sql);
+            retry_busy(|| sqlx::query(&drop_sql).execute(pool)).await?;
+        }
+        Ok(())
+    }
+
+    async fn analyze(&mut self, table_name: &str) -> Result<()> {
+        if let Some(pool) = &self.pool {
+            let actual_table_name = if table_name.is_empty() {
+                &self.table_name
+            } else {
+                table_name
+            };
+
+            let analyze_sql = format!(
+                "ANALYZE {}",
+                quote_table(actual_table_name, QuoteStyle::DoubleQuote)
+            );
+            sql_echo::echo(&analyze_sql);
+            retry_busy(|| sqlx::query(&analyze_sql).execute(pool)).await?;
         }
         Ok(())
     }
@@ -474,4 +965,250 @@ mod tests {
         let target2 = SqliteTarget::new("test.db");
         assert!(target2.is_ok());
     }
+
+    #[tokio::test]
+    async fn test_lock_file_serializes_loads() {
+        let dir = tempfile::tempdir().unwrap();
+        let connection = format!("{}#orders", dir.path().join("shared.db").display());
+        let lock_path = dir.path().join("shared.db.lock");
+
+        let mut first = SqliteTarget::new(&connection)
+            .unwrap()
+            .with_concurrency(Some(Duration::from_millis(200)), true);
+        first.connect().await.unwrap();
+        assert!(lock_path.exists());
+
+        let mut second = SqliteTarget::new(&connection)
+            .unwrap()
+            .with_concurrency(Some(Duration::from_millis(200)), true);
+        assert!(second.connect().await.is_err());
+
+        // Held through finalize and analyze, until the target is dropped
+        first.finalize().await.unwrap();
+        assert!(second.connect().await.is_err());
+        drop(first);
+        second.connect().await.unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&lock_path).unwrap().trim(),
+            std::process::id().to_string()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_leftover_lock_file_is_not_a_lock() {
+        let dir = tempfile::tempdir().unwrap();
+        let connection = format!("{}#orders", dir.path().join("shared.db").display());
+        // Left behind by a killed load
+        std::fs::write(dir.path().join("shared.db.lock"), "4242\n").unwrap();
+
+        let mut target = SqliteTarget::new(&connection)
+            .unwrap()
+            .with_concurrency(Some(Duration::from_millis(200)), true);
+        target.connect().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_analyze_records_table_statistics() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("stats.db");
+        let mut target = SqliteTarget::new(&format!("{}#events", path.display())).unwrap();
+        let schema = Schema {
+            columns: vec![SchemaColumn {
+                name: "id".to_string(),
+                data_type: DataType::Integer,
+                nullable: false,
+            }],
+            estimated_rows: None,
+            primary_key_candidate: None,
+        };
+        target.connect().await.unwrap();
+        target.create_table("events", &schema).await.unwrap();
+        let rows: Vec<Row> = (0..10)
+            .map(|id| Row::from([("id".to_string(), Value::Integer(id))]))
+            .collect();
+        target.write_batch(&rows).await.unwrap();
+        target.analyze("events").await.unwrap();
+
+        let pool = target.pool.as_ref().unwrap();
+        let stat: String = sqlx::query_scalar("SELECT stat FROM sqlite_stat1 WHERE tbl = 'events'")
+            .fetch_one(pool)
+            .await
+            .unwrap();
+        assert_eq!(stat, "10");
+    }
+
+    #[tokio::test]
+    async fn test_rows_load_before_their_parents_without_foreign_keys() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("shop.db");
+        let orphan = [Row::from([("customer_id".to_string(), Value::Integer(7))])];
+        for foreign_keys in [true, false] {
+            let mut target = SqliteTarget::new(&format!("{}#orders", path.display()))
+                .unwrap()
+                .with_foreign_keys(foreign_keys);
+            target.connect().await.unwrap();
+            let pool = target.pool.as_ref().unwrap();
+            for statement in [
+                "CREATE TABLE IF NOT EXISTS customers (id INTEGER PRIMARY KEY)",
+                "CREATE TABLE IF NOT EXISTS orders (customer_id INTEGER REFERENCES customers(id))",
+            ] {
+                sqlx::query(statement).execute(pool).await.unwrap();
+            }
+            assert_eq!(target.write_batch(&orphan).await.is_ok(), !foreign_keys);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_binary_values_round_trip_as_blobs() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("files.db");
+        let mut target = SqliteTarget::new(&format!("{}#files", path.display())).unwrap();
+        let schema = Schema {
+            columns: vec![SchemaColumn {
+                name: "payload".to_string(),
+                data_type: DataType::Binary,
+                nullable: true,
+            }],
+            estimated_rows: None,
+            primary_key_candidate: None,
+        };
+        target.connect().await.unwrap();
+        target.create_table("files", &schema).await.unwrap();
+        let payload = vec![0u8, 159, 255];
+        let rows = vec![Row::from([(
+            "payload".to_string(),
+            Value::Bytes(payload.clone()),
+        )])];
+        target.write_batch(&rows).await.unwrap();
+
+        let pool = target.pool.as_ref().unwrap();
+        let kind: String = sqlx::query_scalar("SELECT typeof(payload) FROM files")
+            .fetch_one(pool)
+            .await
+            .unwrap();
+        assert_eq!(kind, "blob");
+        let row = sqlx::query("SELECT payload FROM files")
+            .fetch_one(pool)
+            .await
+            .unwrap();
+        assert_eq!(
+            SqliteSource::convert_row(&row, Coercion::default())
+                .unwrap()
+                .get("payload"),
+            Some(&Value::Bytes(payload))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_query_joins_attached_database() {
+        let dir = tempfile::tempdir().unwrap();
+        let orders = dir.path().join("orders.db");
+        let regions = dir.path().join("regions.db");
+        for (path, setup) in [
+            (
+                &orders,
+                "CREATE TABLE orders (id INTEGER, region_id INTEGER); \
+                 INSERT INTO orders VALUES (1, 10), (2, 20)",
+            ),
+            (
+                &regions,
+                "CREATE TABLE regions (id INTEGER, name TEXT); \
+                 INSERT INTO regions VALUES (10, 'EU'), (20, 'US')",
+            ),
+        ] {
+            let pool = SqlitePool::connect(&format!("sqlite:{}?mode=rwc", path.display()))
+                .await
+                .unwrap();
+            pool.execute(setup).await.unwrap();
+            pool.close().await;
+        }
+
+        let attach = HashMap::from([("lookup".to_string(), regions.to_str().unwrap().to_string())]);
+        let mut source = SqliteSource::with_query(
+            orders.to_str().unwrap(),
+            "SELECT o.id, r.name FROM orders o JOIN lookup.regions r ON r.id = o.region_id ORDER BY o.id",
+        )
+        .unwrap()
+        .with_attached(&attach)
+        .unwrap();
+        source.connect().await.unwrap();
+        let rows = source.read_batch(10).await.unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[1]["name"], Value::String("US".to_string()));
+
+        let missing = HashMap::from([("lookup".to_string(), "missing.db".to_string())]);
+        let source = SqliteSource::with_query(orders.to_str().unwrap(), "SELECT 1").unwrap();
+        assert!(source.with_attached(&missing).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_read_only_session_refuses_writes() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("orders.db");
+        let pool = SqlitePool::connect(&format!("sqlite:{}?mode=rwc", path.display()))
+            .await
+            .unwrap();
+        pool.execute("CREATE TABLE orders (id INTEGER)")
+            .await
+            .unwrap();
+        pool.close().await;
+
+        let mut source =
+            SqliteSource::with_query(path.to_str().unwrap(), "SELECT * FROM orders").unwrap();
+        source.use_read_only_session().unwrap();
+        source.connect().await.unwrap();
+        assert!(source.read_batch(10).await.unwrap().is_empty());
+        let pool = source.pool.as_ref().unwrap();
+        assert!(pool.execute("INSERT INTO orders VALUES (1)").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_parallel_appends_to_one_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let connection = format!("{}#events", dir.path().join("shared.db").display());
+        let schema = Schema {
+            columns: vec![SchemaColumn {
+                name: "id".to_string(),
+                data_type: DataType::Integer,
+                nullable: false,
+            }],
+            estimated_rows: None,
+            primary_key_candidate: None,
+        };
+
+        let loads = (0..4).map(|load| {
+            let connection = connection.clone();
+            let schema = schema.clone();
+            tokio::spawn(async move {
+                let mut target = SqliteTarget::new(&connection).unwrap();
+                target.connect().await.unwrap();
+                target.create_table("events", &schema).await.unwrap();
+                for batch in 0..10 {
+                    let rows: Vec<Row> = (0..50)
+                        .map(|i| {
+                            let id = load * 1000 + batch * 50 + i;
+                            Row::from([("id".to_string(), Value::Integer(id))])
+                        })
+                        .collect();
+                    target.write_batch(&rows).await.unwrap();
+                }
+                target.finalize().await.unwrap();
+            })
+        });
+        for load in loads.collect::<Vec<_>>() {
+            load.await.unwrap();
+        }
+
+        let pool = SqlitePool::connect(&format!(
+            "sqlite:{}",
+            dir.path().join("shared.db").display()
+        ))
+        .await
+        .unwrap();
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM events")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(count, 2000);
+    }
 }
@@ -0,0 +1,374 @@
+//! Data files of statistics packages, read-only: SPSS (`.sav`, `.zsav`),
+//! Stata (`.dta`) and SAS (`.sas7bdat`). With the `value_labels` option,
+//! coded values are read as the labels the file defines for them.
+
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, NaiveDate, TimeZone, Utc};
+use rust_decimal::prelude::ToPrimitive;
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::{
+    connectors::{
+        bool_option, encoding::Encoding, sas::SasReader, spss::SpssReader, stata::StataReader,
+        Source,
+    },
+    schema::{Column, DataType, Row, Schema, Value},
+    strict::Coercion,
+    Result, TinyEtlError,
+};
+
+/// Whether a connection string names an SPSS, Stata or SAS data file
+pub fn is_stat_file(connection_string: &str) -> bool {
+    StatFormat::of(connection_string).is_some()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum StatFormat {
+    Spss,
+    Stata,
+    Sas,
+}
+
+impl StatFormat {
+    fn of(connection_string: &str) -> Option<Self> {
+        if connection_string.contains("://") {
+            return None;
+        }
+        let path = connection_string.to_lowercase();
+        if path.ends_with(".sav") || path.ends_with(".zsav") {
+            Some(StatFormat::Spss)
+        } else if path.ends_with(".dta") {
+            Some(StatFormat::Stata)
+        } else if path.ends_with(".sas7bdat") {
+            Some(StatFormat::Sas)
+        } else {
+            None
+        }
+    }
+}
+
+/// Labels of the coded values of a variable
+pub(crate) type ValueLabels = HashMap<LabelKey, String>;
+
+/// A coded value: a number, by its bits, or text
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) enum LabelKey {
+    Number(u64),
+    Text(String),
+}
+
+impl LabelKey {
+    pub(crate) fn number(code: f64) -> Self {
+        // -0.0 and 0.0 are the same code
+        LabelKey::Number((code + 0.0).to_bits())
+    }
+
+    fn of(value: &Value) -> Option<Self> {
+        match value {
+            Value::Integer(i) => Some(Self::number(*i as f64)),
+            Value::Decimal(d) => d.to_f64().map(Self::number),
+            Value::String(s) => Some(LabelKey::Text(s.trim_end().to_string())),
+            _ => None,
+        }
+    }
+}
+
+/// A variable (column) of a data file
+pub(crate) struct Variable {
+    pub name: String,
+    pub data_type: DataType,
+    pub labels: Option<Arc<ValueLabels>>,
+}
+
+/// An open data file, positioned at the next row
+pub(crate) trait StatReader: Send + Sync {
+    fn variables(&self) -> &[Variable];
+
+    /// Rows in the file, when its header records them
+    fn row_count(&self) -> Option<usize>;
+
+    /// Values of the next row in variable order, or `None` after the last
+    fn next_row(&mut self, coercion: Coercion) -> Result<Option<Vec<Value>>>;
+
+    /// Bytes of the file read so far
+    fn bytes_read(&self) -> u64;
+}
+
+/// Byte order of the numbers in a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ByteOrder {
+    Little,
+    Big,
+}
+
+impl ByteOrder {
+    pub(crate) fn u16(self, bytes: &[u8]) -> u16 {
+        let bytes = [bytes[0], bytes[1]];
+        match self {
+            ByteOrder::Little => u16::from_le_bytes(bytes),
+            ByteOrder::Big => u16::from_be_bytes(bytes),
+        }
+    }
+
+    pub(crate) fn u32(self, bytes: &[u8]) -> u32 {
+        let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+        match self {
+            ByteOrder::Little => u32::from_le_bytes(bytes),
+            ByteOrder::Big => u32::from_be_bytes(bytes),
+        }
+    }
+
+    pub(crate) fn u64(self, bytes: &[u8]) -> u64 {
+        let mut array = [0u8; 8];
+        array.copy_from_slice(&bytes[..8]);
+        match self {
+            ByteOrder::Little => u64::from_le_bytes(array),
+            ByteOrder::Big => u64::from_be_bytes(array),
+        }
+    }
+
+    pub(crate) fn i32(self, bytes: &[u8]) -> i32 {
+        self.u32(bytes) as i32
+    }
+
+    pub(crate) fn f32(self, bytes: &[u8]) -> f32 {
+        f32::from_bits(self.u32(bytes))
+    }
+
+    pub(crate) fn f64(self, bytes: &[u8]) -> f64 {
+        f64::from_bits(self.u64(bytes))
+    }
+}
+
+/// Exactly `N` bytes, or an error naming the format of a file cut short
+pub(crate) fn read_array<const N: usize>(input: &mut impl Read, format: &str) -> Result<[u8; N]> {
+    let mut bytes = [0u8; N];
+    input
+        .read_exact(&mut bytes)
+        .map_err(|e| cut_short(format, e))?;
+    Ok(bytes)
+}
+
+/// Exactly `length` bytes, or an error naming the format of a file cut short
+pub(crate) fn read_bytes(input: &mut impl Read, length: usize, format: &str) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    input.take(length as u64).read_to_end(&mut bytes)?;
+    if bytes.len() < length {
+        return Err(cut_short(format, std::io::ErrorKind::UnexpectedEof.into()));
+    }
+    Ok(bytes)
+}
+
+fn cut_short(format: &str, e: std::io::Error) -> TinyEtlError {
+    TinyEtlError::DataTransfer(format!("Invalid {} file: it is cut short ({})", format, e))
+}
+
+/// The time `seconds` after midnight UTC of `epoch`, if chrono can hold it
+pub(crate) fn datetime_from_seconds(epoch: NaiveDate, seconds: f64) -> Option<DateTime<Utc>> {
+    let millis = (seconds * 1000.0).round();
+    // Beyond about 30,000 years either side
+    if !millis.is_finite() || millis.abs() > 1e15 {
+        return None;
+    }
+    let midnight = Utc.from_utc_datetime(&epoch.and_hms_opt(0, 0, 0)?);
+    midnight.checked_add_signed(Duration::milliseconds(millis as i64))
+}
+
+pub struct StatFileSource {
+    file_path: PathBuf,
+    format: StatFormat,
+    /// `encoding` option, for files that don't record theirs
+    encoding: Option<Encoding>,
+    /// Read coded values as their labels
+    value_labels: bool,
+    reader: Option<Box<dyn StatReader>>,
+    finished: bool,
+    coercion: Coercion,
+}
+
+impl StatFileSource {
+    pub fn new(file_path: &str, options: &HashMap<String, String>) -> Result<Self> {
+        let format = StatFormat::of(file_path).ok_or_else(|| {
+            TinyEtlError::Configuration(format!(
+                "Not an SPSS, Stata or SAS data file: {}",
+                file_path
+            ))
+        })?;
+        Ok(Self {
+            file_path: PathBuf::from(file_path),
+            format,
+            encoding: options
+                .get("encoding")
+                .map(|name| Encoding::parse(name))
+                .transpose()?,
+            value_labels: bool_option(options, "value_labels")?,
+            reader: None,
+            finished: false,
+            coercion: Coercion::default(),
+        })
+    }
+
+    fn open(&self) -> Result<Box<dyn StatReader>> {
+        let path: &Path = &self.file_path;
+        if !path.exists() {
+            return Err(TinyEtlError::Connection(format!(
+                "Data file not found: {}",
+                path.display()
+            )));
+        }
+        Ok(match self.format {
+            StatFormat::Spss => Box::new(SpssReader::open(path, self.encoding)?),
+            StatFormat::Stata => Box::new(StataReader::open(path, self.encoding)?),
+            StatFormat::Sas => Box::new(SasReader::open(path, self.encoding)?),
+        })
+    }
+
+    /// The labels a variable's values are read as, with `value_labels`
+    fn labels<'a>(&self, variable: &'a Variable) -> Option<&'a ValueLabels> {
+        variable.labels.as_deref().filter(|_| self.value_labels)
+    }
+
+    fn reader(&self) -> Result<&dyn StatReader> {
+        self.reader
+            .as_deref()
+            .ok_or_else(|| TinyEtlError::Connection("Data file is not open".to_string()))
+    }
+}
+
+/// A value read as its label; codes without one are read as text
+fn label_value(value: Value, labels: &ValueLabels) -> Value {
+    match LabelKey::of(&value).and_then(|key| labels.get(&key)) {
+        Some(label) => Value::String(label.clone()),
+        None => value
+            .to_string_for_arrow()
+            .map_or(Value::Null, Value::String),
+    }
+}
+
+#[async_trait]
+impl Source for StatFileSource {
+    async fn connect(&mut self) -> Result<()> {
+        self.reader = Some(self.open()?);
+        self.finished = false;
+        Ok(())
+    }
+
+    async fn infer_schema(&mut self, _sample_size: usize) -> Result<Schema> {
+        if self.reader.is_none() {
+            self.connect().await?;
+        }
+        let reader = self.reader()?;
+        let columns = reader
+            .variables()
+            .iter()
+            .map(|variable| Column {
+                name: variable.name.clone(),
+                data_type: match self.labels(variable) {
+                    Some(_) => DataType::String,
+                    None => variable.data_type.clone(),
+                },
+                nullable: true,
+            })
+            .collect();
+        Ok(Schema {
+            columns,
+            estimated_rows: reader.row_count(),
+            primary_key_candidate: None,
+        })
+    }
+
+    async fn read_batch(&mut self, batch_size: usize) -> Result<Vec<Row>> {
+        if self.reader.is_none() {
+            self.connect().await?;
+        }
+        let coercion = self.coercion;
+        let mut batch = Vec::new();
+        while batch.len() < batch_size && !self.finished {
+            let reader = self
+                .reader
+                .as_mut()
+                .ok_or_else(|| TinyEtlError::Connection("Data file is not open".to_string()))?;
+            let Some(values) = reader.next_row(coercion)? else {
+                self.finished = true;
+                break;
+            };
+            let reader = self.reader()?;
+            let row: Row = reader
+                .variables()
+                .iter()
+                .zip(values)
+                .map(|(variable, value)| {
+                    let value = match self.labels(variable) {
+                        Some(labels) => label_value(value, labels),
+                        None => value,
+                    };
+                    (variable.name.clone(), value)
+                })
+                .collect();
+            batch.push(row);
+        }
+        Ok(batch)
+    }
+
+    async fn estimated_row_count(&self) -> Result<Option<usize>> {
+        Ok(self.reader.as_ref().and_then(|reader| reader.row_count()))
+    }
+
+    fn bytes_read(&self) -> Option<u64> {
+        self.reader.as_ref().map(|reader| reader.bytes_read())
+    }
+
+    fn set_coercion(&mut self, coercion: Coercion) {
+        self.coercion = coercion;
+    }
+
+    async fn reset(&mut self) -> Result<()> {
+        if self.reader.is_some() {
+            self.connect().await?;
+        }
+        Ok(())
+    }
+
+    fn has_more(&self) -> bool {
+        !self.finished
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stat_file_names_and_labels() {
+        assert!(is_stat_file("survey.sav"));
+        assert!(is_stat_file("survey.ZSAV"));
+        assert!(is_stat_file("panel.dta"));
+        assert!(is_stat_file("/data/claims.sas7bdat"));
+        assert!(!is_stat_file("survey.csv"));
+        assert!(!is_stat_file("s3://bucket/panel.dta"));
+
+        let options = HashMap::from([("value_labels".to_string(), "maybe".to_string())]);
+        assert!(StatFileSource::new("survey.sav", &options).is_err());
+
+        let labels = ValueLabels::from([
+            (LabelKey::number(1.0), "Agree".to_string()),
+            (LabelKey::Text("M".to_string()), "Male".to_string()),
+        ]);
+        assert_eq!(
+            label_value(Value::Integer(1), &labels),
+            Value::String("Agree".to_string())
+        );
+        assert_eq!(
+            label_value(Value::String("M   ".to_string()), &labels),
+            Value::String("Male".to_string())
+        );
+        assert_eq!(
+            label_value(Value::Integer(9), &labels),
+            Value::String("9".to_string())
+        );
+        assert_eq!(label_value(Value::Null, &labels), Value::Null);
+    }
+}
@@ -0,0 +1,700 @@
+//! Stata data files (`.dta`) of Stata 8 and later: the binary formats 113
+//! to 115 and the tagged formats 117 to 119. Long strings (strLs) and value
+//! labels are stored after the data and read when the file is opened.
+
+use chrono::NaiveDate;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::{
+    connectors::{
+        encoding::Encoding,
+        stat_file::{
+            datetime_from_seconds, read_array, read_bytes, ByteOrder, LabelKey, StatReader,
+            ValueLabels, Variable,
+        },
+    },
+    schema::{DataType, Value},
+    strict::Coercion,
+    Result, TinyEtlError,
+};
+
+const FORMAT: &str = "Stata";
+
+/// Largest values that aren't missing; `.` and `.a` to `.z` are above them
+const MAX_BYTE: i8 = 100;
+const MAX_INT: i16 = 32_740;
+const MAX_LONG: i32 = 2_147_483_620;
+const MAX_FLOAT: u32 = 0x7eff_ffff;
+const MAX_DOUBLE: u64 = 0x7fdf_ffff_ffff_ffff;
+
+fn invalid(problem: &str) -> TinyEtlError {
+    TinyEtlError::DataTransfer(format!("Invalid Stata file: {}", problem))
+}
+
+/// Stata counts dates and times from 1960-01-01
+fn epoch() -> NaiveDate {
+    NaiveDate::from_ymd_opt(1960, 1, 1).unwrap()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum StataType {
+    Str(usize),
+    StrL,
+    Byte,
+    Int,
+    Long,
+    Float,
+    Double,
+}
+
+impl StataType {
+    /// Type codes of formats 113 to 115
+    fn from_old_code(code: u8) -> Result<Self> {
+        match code {
+            1..=244 => Ok(StataType::Str(code as usize)),
+            251 => Ok(StataType::Byte),
+            252 => Ok(StataType::Int),
+            253 => Ok(StataType::Long),
+            254 => Ok(StataType::Float),
+            255 => Ok(StataType::Double),
+            _ => Err(invalid(&format!("unknown variable type {}", code))),
+        }
+    }
+
+    /// Type codes of formats 117 and later
+    fn from_code(code: u16) -> Result<Self> {
+        match code {
+            1..=2045 => Ok(StataType::Str(code as usize)),
+            32768 => Ok(StataType::StrL),
+            65526 => Ok(StataType::Double),
+            65527 => Ok(StataType::Float),
+            65528 => Ok(StataType::Long),
+            65529 => Ok(StataType::Int),
+            65530 => Ok(StataType::Byte),
+            _ => Err(invalid(&format!("unknown variable type {}", code))),
+        }
+    }
+
+    fn width(&self) -> usize {
+        match self {
+            StataType::Str(width) => *width,
+            StataType::Byte => 1,
+            StataType::Int => 2,
+            StataType::Long | StataType::Float => 4,
+            StataType::StrL | StataType::Double => 8,
+        }
+    }
+
+    /// Column type, with dates and times told apart by the display format
+    fn data_type(&self, format: &str) -> DataType {
+        let format = format.trim_start_matches('%').trim_start_matches('-');
+        match self {
+            StataType::Str(_) | StataType::StrL => DataType::String,
+            _ if format.starts_with("td") || format.starts_with('d') => DataType::Date,
+            _ if format.starts_with("tc") || format.starts_with("tC") => DataType::DateTime,
+            StataType::Byte | StataType::Int | StataType::Long => DataType::Integer,
+            StataType::Float | StataType::Double => DataType::Decimal,
+        }
+    }
+}
+
+struct StataColumn {
+    stata_type: StataType,
+    data_type: DataType,
+    /// Position in the record
+    offset: usize,
+}
+
+/// Layout of the descriptors, which depends on the format
+struct Release {
+    number: u16,
+    name_length: usize,
+    format_length: usize,
+    label_name_length: usize,
+}
+
+impl Release {
+    fn new(number: u16) -> Result<Self> {
+        let (name_length, format_length) = match number {
+            113 => (33, 12),
+            114 | 115 | 117 => (33, 49),
+            118 | 119 => (129, 57),
+            _ => {
+                return Err(TinyEtlError::DataTransfer(format!(
+                    "Stata file format {} isn't supported; save the file in Stata 8 or later",
+                    number
+                )))
+            }
+        };
+        Ok(Self {
+            number,
+            name_length,
+            format_length,
+            label_name_length: name_length,
+        })
+    }
+}
+
+/// Reads the tagged sections of formats 117 and later
+struct Tagged<'a> {
+    file: &'a mut BufReader<File>,
+}
+
+impl Tagged<'_> {
+    fn expect(&mut self, tag: &str) -> Result<()> {
+        let bytes = read_bytes(self.file, tag.len(), FORMAT)?;
+        if bytes != tag.as_bytes() {
+            return Err(invalid(&format!("expected {}", tag)));
+        }
+        Ok(())
+    }
+
+    /// Seek to the section at `offset`, after its opening tag
+    fn section(&mut self, offset: u64, tag: &str) -> Result<()> {
+        self.file.seek(SeekFrom::Start(offset))?;
+        self.expect(tag)
+    }
+}
+
+/// Text up to the first NUL byte
+fn c_string(bytes: &[u8]) -> &[u8] {
+    let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    &bytes[..end]
+}
+
+/// A value label table: codes and the offsets of their labels in a text area
+fn parse_label_table(table: &[u8], order: ByteOrder, encoding: Encoding) -> Result<ValueLabels> {
+    if table.len() < 8 {
+        return Err(invalid("value label table is too short"));
+    }
+    let count = order.u32(&table[0..4]) as usize;
+    let text_length = order.u32(&table[4..8]) as usize;
+    let values_start = 8 + 4 * count;
+    let text_start = values_start + 4 * count;
+    let text = table
+        .get(text_start..text_start + text_length)
+        .ok_or_else(|| invalid("value label table is too short"))?;
+    Ok((0..count)
+        .map(|i| {
+            let offset = order.u32(&table[8 + 4 * i..]) as usize;
+            let value = order.i32(&table[values_start + 4 * i..]);
+            let label = c_string(text.get(offset..).unwrap_or_default());
+            (LabelKey::number(value as f64), encoding.decode(label))
+        })
+        .collect())
+}
+
+pub(crate) struct StataReader {
+    variables: Vec<Variable>,
+    columns: Vec<StataColumn>,
+    file: BufReader<File>,
+    release: Release,
+    order: ByteOrder,
+    encoding: Encoding,
+    rows: usize,
+    row: usize,
+    record_length: usize,
+    data_start: u64,
+    /// strL values by (variable, observation)
+    strls: HashMap<(u64, u64), Vec<u8>>,
+}
+
+impl StataReader {
+    pub(crate) fn open(path: &Path, encoding: Option<Encoding>) -> Result<Self> {
+        let mut file = BufReader::new(File::open(path)?);
+        let first = read_array::<1>(&mut file, FORMAT)?[0];
+        file.seek(SeekFrom::Start(0))?;
+        if first == b'<' {
+            Self::open_tagged(file, encoding)
+        } else {
+            Self::open_binary(file, encoding)
+        }
+    }
+
+    /// Formats 113 to 115: a fixed header, then the descriptors
+    fn open_binary(mut file: BufReader<File>, encoding: Option<Encoding>) -> Result<Self> {
+        let header = read_array::<109>(&mut file, FORMAT)?;
+        let release = Release::new(header[0] as u16)?;
+        let order = match header[1] {
+            1 => ByteOrder::Big,
+            2 => ByteOrder::Little,
+            _ => return Err(invalid("unknown byte order")),
+        };
+        let count = order.u16(&header[4..6]) as usize;
+        let rows = order.u32(&header[6..10]) as usize;
+
+        let types = read_bytes(&mut file, count, FORMAT)?
+            .into_iter()
+            .map(StataType::from_old_code)
+            .collect::<Result<Vec<_>>>()?;
+        let names = read_bytes(&mut file, count * release.name_length, FORMAT)?;
+        // Sort order
+        read_bytes(&mut file, (count + 1) * 2, FORMAT)?;
+        let formats = read_bytes(&mut file, count * release.format_length, FORMAT)?;
+        let label_names = read_bytes(&mut file, count * release.label_name_length, FORMAT)?;
+        // Variable labels
+        read_bytes(&mut file, count * 81, FORMAT)?;
+        // Expansion fields, up to an empty one
+        loop {
+            let field = read_array::<5>(&mut file, FORMAT)?;
+            let length = order.u32(&field[1..5]) as usize;
+            if field[0] == 0 && length == 0 {
+                break;
+            }
+            read_bytes(&mut file, length, FORMAT)?;
+        }
+        let data_start = file.stream_position()?;
+
+        let encoding = encoding.unwrap_or(Encoding::Windows1252);
+        let record_length = types.iter().map(StataType::width).sum::<usize>();
+        file.seek(SeekFrom::Start(data_start + (rows * record_length) as u64))?;
+        let mut tables = HashMap::new();
+        let mut prefix = [0u8; 4];
+        // Value labels run to the end of the file
+        while file.read(&mut prefix[..1])? == 1 {
+            file.read_exact(&mut prefix[1..])?;
+            let length = order.u32(&prefix) as usize;
+            let name = read_array::<36>(&mut file, FORMAT)?;
+            let table = read_bytes(&mut file, length, FORMAT)?;
+            let name = encoding.decode(c_string(&name[..33]));
+            tables.insert(name, Arc::new(parse_label_table(&table, order, encoding)?));
+        }
+
+        Self::build(
+            file,
+            release,
+            order,
+            encoding,
+            (types, names, formats, label_names),
+            tables,
+            rows,
+            data_start,
+            HashMap::new(),
+        )
+    }
+
+    /// Formats 117 to 119: a tagged header and a map of the sections
+    fn open_tagged(mut file: BufReader<File>, encoding: Option<Encoding>) -> Result<Self> {
+        let mut tagged = Tagged { file: &mut file };
+        tagged.expect("<stata_dta><header><release>")?;
+        let number = String::from_utf8_lossy(&read_array::<3>(tagged.file, FORMAT)?)
+            .parse::<u16>()
+            .map_err(|_| invalid("unknown release"))?;
+        let release = Release::new(number)?;
+        tagged.expect("</release><byteorder>")?;
+        let order = match &read_array::<3>(tagged.file, FORMAT)? {
+            b"MSF" => ByteOrder::Big,
+            b"LSF" => ByteOrder::Little,
+            _ => return Err(invalid("unknown byte order")),
+        };
+        tagged.expect("</byteorder><K>")?;
+        let count = if number == 119 {
+            order.u32(&read_array::<4>(tagged.file, FORMAT)?) as usize
+        } else {
+            order.u16(&read_array::<2>(tagged.file, FORMAT)?) as usize
+        };
+        tagged.expect("</K><N>")?;
+        let rows = if number == 117 {
+            order.u32(&read_array::<4>(tagged.file, FORMAT)?) as usize
+        } else {
+            order.u64(&read_array::<8>(tagged.file, FORMAT)?) as usize
+        };
+        tagged.expect("</N><label>")?;
+        let label_length = if number == 117 {
+            read_array::<1>(tagged.file, FORMAT)?[0] as usize
+        } else {
+            order.u16(&read_array::<2>(tagged.file, FORMAT)?) as usize
+        };
+        read_bytes(tagged.file, label_length, FORMAT)?;
+        tagged.expect("</label><timestamp>")?;
+        let timestamp_length = read_array::<1>(tagged.file, FORMAT)?[0] as usize;
+        read_bytes(tagged.file, timestamp_length, FORMAT)?;
+        tagged.expect("</timestamp></header><map>")?;
+        let map: Vec<u64> = (0..14)
+            .map(|_| read_array::<8>(tagged.file, FORMAT).map(|offset| order.u64(&offset)))
+            .collect::<Result<_>>()?;
+
+        tagged.section(map[2], "<variable_types>")?;
+        let types = (0..count)
+            .map(|_| {
+                let code = order.u16(&read_array::<2>(tagged.file, FORMAT)?);
+                StataType::from_code(code)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        tagged.section(map[3], "<varnames>")?;
+        let names = read_bytes(tagged.file, count * release.name_length, FORMAT)?;
+        tagged.section(map[5], "<formats>")?;
+        let formats = read_bytes(tagged.file, count * release.format_length, FORMAT)?;
+        tagged.section(map[6], "<value_label_names>")?;
+        let label_names = read_bytes(tagged.file, count * release.label_name_length, FORMAT)?;
+
+        // Stata 14 and later write UTF-8
+        let encoding = encoding.unwrap_or(if number >= 118 {
+            Encoding::Utf8
+        } else {
+            Encoding::Windows1252
+        });
+
+        tagged.section(map[10], "<strls>")?;
+        let mut strls = HashMap::new();
+        loop {
+            let tag = read_array::<3>(tagged.file, FORMAT)?;
+            if &tag != b"GSO" {
+                break;
+            }
+            let variable = order.u32(&read_array::<4>(tagged.file, FORMAT)?) as u64;
+            let observation = if number == 117 {
+                order.u32(&read_array::<4>(tagged.file, FORMAT)?) as u64
+            } else {
+                order.u64(&read_array::<8>(tagged.file, FORMAT)?)
+            };
+            let kind = read_array::<1>(tagged.file, FORMAT)?[0];
+            let length = order.u32(&read_array::<4>(tagged.file, FORMAT)?) as usize;
+            let mut value = read_bytes(tagged.file, length, FORMAT)?;
+            // Text strLs end with a NUL; binary ones (129) are kept whole
+            if kind == 130 && value.last() == Some(&0) {
+                value.pop();
+            }
+            strls.insert((variable, observation), value);
+        }
+
+        tagged.section(map[11], "<value_labels>")?;
+        let mut tables = HashMap::new();
+        loop {
+            let tag = read_array::<5>(tagged.file, FORMAT)?;
+            if &tag != b"<lbl>" {
+                break;
+            }
+            let length = order.u32(&read_array::<4>(tagged.file, FORMAT)?) as usize;
+            let name = read_bytes(tagged.file, release.label_name_length + 3, FORMAT)?;
+            let table = read_bytes(tagged.file, length, FORMAT)?;
+            tagged.expect("</lbl>")?;
+            let name = encoding.decode(c_string(&name));
+            tables.insert(name, Arc::new(parse_label_table(&table, order, encoding)?));
+        }
+
+        let data_start = map[9] + "<data>".len() as u64;
+        Self::build(
+            file,
+            release,
+            order,
+            encoding,
+            (types, names, formats, label_names),
+            tables,
+            rows,
+            data_start,
+            strls,
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn build(
+        mut file: BufReader<File>,
+        release: Release,
+        order: ByteOrder,
+        encoding: Encoding,
+        (types, names, formats, label_names): (Vec<StataType>, Vec<u8>, Vec<u8>, Vec<u8>),
+        tables: HashMap<String, Arc<ValueLabels>>,
+        rows: usize,
+        data_start: u64,
+        strls: HashMap<(u64, u64), Vec<u8>>,
+    ) -> Result<Self> {
+        let mut variables = Vec::new();
+        let mut columns = Vec::new();
+        let mut offset = 0;
+        for (index, stata_type) in types.into_iter().enumerate() {
+            let field = |bytes: &[u8], length: usize| {
+                encoding.decode(c_string(&bytes[index * length..(index + 1) * length]))
+            };
+            let format = field(&formats, release.format_length);
+            let data_type = stata_type.data_type(&format);
+            let label_name = field(&label_names, release.label_name_length);
+            variables.push(Variable {
+                name: field(&names, release.name_length),
+                data_type: data_type.clone(),
+                labels: tables.get(&label_name).cloned(),
+            });
+            columns.push(StataColumn {
+                stata_type,
+                data_type,
+                offset,
+            });
+            offset += stata_type.width();
+        }
+        file.seek(SeekFrom::Start(data_start))?;
+        Ok(Self {
+            variables,
+            columns,
+            file,
+            release,
+            order,
+            encoding,
+            rows,
+            row: 0,
+            record_length: offset,
+            data_start,
+            strls,
+        })
+    }
+
+    fn decode(
+        &self,
+        column: &StataColumn,
+        name: &str,
+        record: &[u8],
+        coercion: Coercion,
+    ) -> Result<Value> {
+        let order = self.order;
+        let bytes = &record[column.offset..column.offset + column.stata_type.width()];
+        let number = match column.stata_type {
+            StataType::Str(_) => return Ok(Value::String(self.encoding.decode(c_string(bytes)))),
+            StataType::StrL => return Ok(self.strl(bytes)),
+            StataType::Byte => {
+                let value = bytes[0] as i8;
+                (value <= MAX_BYTE).then_some(value as f64)
+            }
+            StataType::Int => {
+                let value = order.u16(bytes) as i16;
+                (value <= MAX_INT).then_some(value as f64)
+            }
+            StataType::Long => {
+                let value = order.i32(bytes);
+                (value <= MAX_LONG).then_some(value as f64)
+            }
+            StataType::Float => {
+                let value = order.f32(bytes);
+                (value <= f32::from_bits(MAX_FLOAT)).then_some(value as f64)
+            }
+            StataType::Double => {
+                let value = order.f64(bytes);
+                (value <= f64::from_bits(MAX_DOUBLE)).then_some(value)
+            }
+        };
+        // Missing values, and NaN, which Stata doesn't write
+        let Some(number) = number else {
+            return Ok(Value::Null);
+        };
+        match column.data_type {
+            DataType::Date | DataType::DateTime => {
+                let seconds = if column.data_type == DataType::Date {
+                    number * 86_400.0
+                } else {
+                    number / 1000.0
+                };
+                match datetime_from_seconds(epoch(), seconds) {
+                    Some(datetime) => Ok(Value::Date(datetime)),
+                    None => coercion.lossy(name, &number.to_string(), "is not a date", Value::Null),
+                }
+            }
+            DataType::Integer => Ok(Value::Integer(number as i64)),
+            _ if column.stata_type == StataType::Float => {
+                coercion.decimal_from_f32(number as f32, name)
+            }
+            _ => coercion.decimal_from_f64(number, name),
+        }
+    }
+
+    /// A strL value from its (variable, observation) reference
+    fn strl(&self, bytes: &[u8]) -> Value {
+        let reference = match self.release.number {
+            117 => (
+                self.order.u32(&bytes[..4]) as u64,
+                self.order.u32(&bytes[4..]) as u64,
+            ),
+            number => {
+                let packed = self.order.u64(bytes);
+                let bits = if number == 118 { 16 } else { 24 };
+                (packed & ((1 << bits) - 1), packed >> bits)
+            }
+        };
+        match self.strls.get(&reference) {
+            Some(value) => Value::String(self.encoding.decode(value)),
+            // (0, 0) is the empty string
+            None => Value::String(String::new()),
+        }
+    }
+}
+
+impl StatReader for StataReader {
+    fn variables(&self) -> &[Variable] {
+        &self.variables
+    }
+
+    fn row_count(&self) -> Option<usize> {
+        Some(self.rows)
+    }
+
+    fn next_row(&mut self, coercion: Coercion) -> Result<Option<Vec<Value>>> {
+        if self.row >= self.rows {
+            return Ok(None);
+        }
+        let record = read_bytes(&mut self.file, self.record_length, FORMAT)?;
+        self.row += 1;
+        self.columns
+            .iter()
+            .zip(&self.variables)
+            .map(|(column, variable)| self.decode(column, &variable.name, &record, coercion))
+            .collect::<Result<_>>()
+            .map(Some)
+    }
+
+    fn bytes_read(&self) -> u64 {
+        self.data_start + (self.row * self.record_length) as u64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connectors::{stat_file::StatFileSource, Source};
+    use chrono::{TimeZone, Utc};
+
+    fn padded(text: &str, length: usize) -> Vec<u8> {
+        let mut bytes = text.as_bytes().to_vec();
+        bytes.resize(length, 0);
+        bytes
+    }
+
+    /// A format 118 file: a labelled byte, a %td date, a str6 and a strL
+    fn write_file(path: &Path) {
+        let mut bytes = Vec::new();
+        let mut map = [0u64; 14];
+        bytes.extend_from_slice(
+            b"<stata_dta><header><release>118</release><byteorder>LSF</byteorder><K>",
+        );
+        bytes.extend_from_slice(&4u16.to_le_bytes());
+        bytes.extend_from_slice(b"</K><N>");
+        bytes.extend_from_slice(&2u64.to_le_bytes());
+        bytes.extend_from_slice(b"</N><label>");
+        bytes.extend_from_slice(&0u16.to_le_bytes());
+        bytes.extend_from_slice(b"</label><timestamp>");
+        bytes.push(0);
+        bytes.extend_from_slice(b"</timestamp></header><map>");
+        let map_at = bytes.len();
+        bytes.resize(map_at + 14 * 8, 0);
+        bytes.extend_from_slice(b"</map>");
+
+        map[2] = bytes.len() as u64;
+        bytes.extend_from_slice(b"<variable_types>");
+        for code in [65530u16, 65528, 6, 32768] {
+            bytes.extend_from_slice(&code.to_le_bytes());
+        }
+        bytes.extend_from_slice(b"</variable_types>");
+        map[3] = bytes.len() as u64;
+        bytes.extend_from_slice(b"<varnames>");
+        for name in ["agree", "born", "city", "notes"] {
+            bytes.extend_from_slice(&padded(name, 129));
+        }
+        bytes.extend_from_slice(b"</varnames>");
+        map[5] = bytes.len() as u64;
+        bytes.extend_from_slice(b"<formats>");
+        for format in ["%8.0g", "%td", "%9s", "%9s"] {
+            bytes.extend_from_slice(&padded(format, 57));
+        }
+        bytes.extend_from_slice(b"</formats>");
+        map[6] = bytes.len() as u64;
+        bytes.extend_from_slice(b"<value_label_names>");
+        for label in ["yesno", "", "", ""] {
+            bytes.extend_from_slice(&padded(label, 129));
+        }
+        bytes.extend_from_slice(b"</value_label_names>");
+
+        // Record: byte, long, str6, strL (variable 4, observation 1)
+        map[9] = bytes.len() as u64;
+        bytes.extend_from_slice(b"<data>");
+        bytes.push(1);
+        // 2000-01-01 is day 14,610
+        bytes.extend_from_slice(&14_610i32.to_le_bytes());
+        bytes.extend_from_slice(&padded("Zürich", 6)[..6]);
+        bytes.extend_from_slice(&(4u64 | 1 << 16).to_le_bytes());
+        // Missing byte and date, empty strL
+        bytes.push(101);
+        bytes.extend_from_slice(&2_147_483_621i32.to_le_bytes());
+        bytes.extend_from_slice(&padded("Bern", 6));
+        bytes.extend_from_slice(&0u64.to_le_bytes());
+        bytes.extend_from_slice(b"</data>");
+
+        map[10] = bytes.len() as u64;
+        bytes.extend_from_slice(b"<strls>GSO");
+        bytes.extend_from_slice(&4u32.to_le_bytes());
+        bytes.extend_from_slice(&1u64.to_le_bytes());
+        bytes.push(130);
+        bytes.extend_from_slice(&6u32.to_le_bytes());
+        bytes.extend_from_slice(b"long.\0");
+        bytes.extend_from_slice(b"</strls>");
+
+        map[11] = bytes.len() as u64;
+        bytes.extend_from_slice(b"<value_labels><lbl>");
+        let mut table = Vec::new();
+        for value in [2u32, 8, 0, 3, 0, 1] {
+            table.extend_from_slice(&value.to_le_bytes());
+        }
+        table.extend_from_slice(b"no\0yes\0");
+        table.resize(table.len() + 1, 0);
+        bytes.extend_from_slice(&(table.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&padded("yesno", 132));
+        bytes.extend_from_slice(&table);
+        bytes.extend_from_slice(b"</lbl></value_labels></stata_dta>");
+
+        for (i, offset) in map.iter().enumerate() {
+            bytes[map_at + i * 8..map_at + i * 8 + 8].copy_from_slice(&offset.to_le_bytes());
+        }
+        std::fs::write(path, bytes).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_read_tagged_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("panel.dta");
+        write_file(&path);
+
+        let mut source = StatFileSource::new(&path.to_string_lossy(), &HashMap::new()).unwrap();
+        let schema = source.infer_schema(100).await.unwrap();
+        let types: Vec<DataType> = schema.columns.iter().map(|c| c.data_type.clone()).collect();
+        assert_eq!(
+            types,
+            vec![
+                DataType::Integer,
+                DataType::Date,
+                DataType::String,
+                DataType::String
+            ]
+        );
+
+        let rows = source.read_batch(10).await.unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0]["agree"], Value::Integer(1));
+        assert_eq!(
+            rows[0]["born"],
+            Value::Date(Utc.with_ymd_and_hms(2000, 1, 1, 0, 0, 0).unwrap())
+        );
+        assert_eq!(rows[0]["city"], Value::String("Züric".to_string()));
+        assert_eq!(rows[0]["notes"], Value::String("long.".to_string()));
+        assert_eq!(rows[1]["agree"], Value::Null);
+        assert_eq!(rows[1]["born"], Value::Null);
+        assert_eq!(rows[1]["city"], Value::String("Bern".to_string()));
+        assert_eq!(rows[1]["notes"], Value::String(String::new()));
+
+        let options = HashMap::from([("value_labels".to_string(), "true".to_string())]);
+        let mut source = StatFileSource::new(&path.to_string_lossy(), &options).unwrap();
+        let rows = source.read_batch(10).await.unwrap();
+        assert_eq!(rows[0]["agree"], Value::String("yes".to_string()));
+    }
+
+    #[test]
+    fn test_dates_from_display_formats() {
+        assert_eq!(StataType::Long.data_type("%td"), DataType::Date);
+        assert_eq!(
+            StataType::Double.data_type("%tcDDmonCCYY"),
+            DataType::DateTime
+        );
+        assert_eq!(StataType::Int.data_type("%8.0g"), DataType::Integer);
+        assert_eq!(StataType::Float.data_type("%9.2f"), DataType::Decimal);
+        assert_eq!(StataType::Str(10).data_type("%10s"), DataType::String);
+        assert!(Release::new(105).is_err());
+    }
+}
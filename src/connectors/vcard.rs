@@ -0,0 +1,255 @@
+//! vCard files (`.vcf`): contact exports of address books and phones, in
+//! vCard 2.1, 3.0 or 4.0. Every card becomes a row with the same standard
+//! columns, whichever properties it has.
+
+use std::collections::HashMap;
+
+use crate::{
+    connectors::{
+        record_format::{RecordFormat, Records},
+        vobject::{self, Component, Property},
+    },
+    schema::{Row, Value},
+    Result,
+};
+
+/// Columns of every contact, in order
+const COLUMNS: [&str; 25] = [
+    "uid",
+    "full_name",
+    "family_name",
+    "given_name",
+    "middle_name",
+    "prefix",
+    "suffix",
+    "nickname",
+    "organization",
+    "department",
+    "title",
+    "email",
+    "emails",
+    "phone",
+    "mobile",
+    "phones",
+    "street",
+    "city",
+    "region",
+    "postal_code",
+    "country",
+    "birthday",
+    "url",
+    "note",
+    "categories",
+];
+
+/// Separator of the values of a property listed in one column
+const LIST_SEPARATOR: &str = "; ";
+
+/// The preferred property named `name`, or else the first
+fn preferred<'a>(card: &'a Component, name: &'a str) -> Option<&'a Property> {
+    card.all(name)
+        .find(|p| p.is_preferred())
+        .or_else(|| card.property(name))
+}
+
+/// Text of a phone number or address, without a `tel:` or `mailto:` URI
+/// scheme
+fn address(property: &Property) -> Option<String> {
+    let text = property.text()?;
+    let lower = text.to_lowercase();
+    Some(
+        match ["tel:", "mailto:"].iter().find(|s| lower.starts_with(*s)) {
+            Some(scheme) => text[scheme.len()..].to_string(),
+            None => text,
+        },
+    )
+}
+
+/// Every phone number or address of `name`, joined
+fn all_addresses(card: &Component, name: &str) -> Option<String> {
+    let all: Vec<String> = card.all(name).filter_map(address).collect();
+    Some(all.join(LIST_SEPARATOR)).filter(|all| !all.is_empty())
+}
+
+/// The built-in `vcard` record format
+pub struct VcardFormat;
+
+impl VcardFormat {
+    fn row(card: &Component, records: &mut Records) -> Row {
+        let mut row = Row::new();
+        let mut set = |column: &str, value: Option<String>| {
+            if let Some(value) = value.filter(|value| !value.is_empty()) {
+                records.insert(&mut row, column.to_string(), Value::String(value));
+            }
+        };
+
+        set("uid", card.text("UID"));
+        set("full_name", card.text("FN"));
+        // N: family; given; additional; prefixes; suffixes
+        let name = card
+            .property("N")
+            .map(Property::components)
+            .unwrap_or_default();
+        for (i, column) in [
+            "family_name",
+            "given_name",
+            "middle_name",
+            "prefix",
+            "suffix",
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            set(column, name.get(i).cloned());
+        }
+        set("nickname", card.text("NICKNAME"));
+        let org = card
+            .property("ORG")
+            .map(Property::components)
+            .unwrap_or_default();
+        set("organization", org.first().cloned());
+        set(
+            "department",
+            Some(
+                org.iter()
+                    .skip(1)
+                    .cloned()
+                    .collect::<Vec<_>>()
+                    .join(LIST_SEPARATOR),
+            ),
+        );
+        set("title", card.text("TITLE"));
+        set("email", preferred(card, "EMAIL").and_then(address));
+        set("emails", all_addresses(card, "EMAIL"));
+        set("phone", preferred(card, "TEL").and_then(address));
+        set(
+            "mobile",
+            card.all("TEL")
+                .find(|p| p.has_type("cell"))
+                .and_then(address),
+        );
+        set("phones", all_addresses(card, "TEL"));
+        // ADR: PO box; extended; street; locality; region; code; country
+        let adr = preferred(card, "ADR")
+            .map(Property::components)
+            .unwrap_or_default();
+        let street: Vec<String> = adr
+            .iter()
+            .take(3)
+            .filter(|part| !part.is_empty())
+            .cloned()
+            .collect();
+        set("street", Some(street.join(", ")));
+        for (i, column) in ["city", "region", "postal_code", "country"]
+            .into_iter()
+            .enumerate()
+        {
+            set(column, adr.get(i + 3).cloned());
+        }
+        set("url", card.text("URL"));
+        set("note", card.text("NOTE"));
+        set("categories", card.text("CATEGORIES"));
+
+        if let Some(birthday) = card.text("BDAY") {
+            records.insert(
+                &mut row,
+                "birthday".to_string(),
+                vobject::date_value(&birthday),
+            );
+        }
+        row
+    }
+}
+
+impl RecordFormat for VcardFormat {
+    fn extensions(&self) -> &[&str] {
+        &["vcf", "vcard"]
+    }
+
+    fn read(&self, text: &str, _options: &HashMap<String, String>) -> Result<Records> {
+        let mut records = Records::with_columns(&COLUMNS);
+        for card in vobject::parse(text, "vCard")? {
+            if card.name == "VCARD" {
+                let row = Self::row(&card, &mut records);
+                records.rows.push(row);
+            }
+        }
+        vobject::settle_dates(&mut records);
+        Ok(records)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    const CARDS: &str = "BEGIN:VCARD\r\n\
+        VERSION:3.0\r\n\
+        UID:urn:uuid:4fbe8971\r\n\
+        FN:Dr. Ann O'Neil\r\n\
+        N:O'Neil;Ann;Marie;Dr.;\r\n\
+        ORG:Acme\\, Inc.;Research;Lab 2\r\n\
+        EMAIL;TYPE=home:ann@home.example\r\n\
+        EMAIL;TYPE=work,pref:ann@acme.example\r\n\
+        TEL;TYPE=work:+1 555 0100\r\n\
+        TEL;TYPE=cell:+1 555 0199\r\n\
+        ADR;TYPE=work:;Suite 4;12 Main St;Springfield;IL;62701;USA\r\n\
+        BDAY:1980-04-15\r\n\
+        NOTE:Met at the\\nconference\r\n\
+        END:VCARD\r\n\
+        BEGIN:VCARD\r\n\
+        VERSION:2.1\r\n\
+        N:Roe;Jane\r\n\
+        TEL;CELL:555-0142\r\n\
+        BDAY:--0415\r\n\
+        END:VCARD\r\n";
+
+    fn text(row: &Row, column: &str) -> Option<String> {
+        match row.get(column) {
+            Some(Value::String(s)) => Some(s.clone()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_one_row_per_contact() {
+        let records = VcardFormat.read(CARDS, &HashMap::new()).unwrap();
+        assert_eq!(records.columns, COLUMNS);
+        assert_eq!(records.rows.len(), 2);
+
+        let ann = &records.rows[0];
+        assert_eq!(text(ann, "uid").as_deref(), Some("urn:uuid:4fbe8971"));
+        assert_eq!(text(ann, "family_name").as_deref(), Some("O'Neil"));
+        assert_eq!(text(ann, "middle_name").as_deref(), Some("Marie"));
+        assert_eq!(text(ann, "prefix").as_deref(), Some("Dr."));
+        assert_eq!(text(ann, "suffix"), None);
+        assert_eq!(text(ann, "organization").as_deref(), Some("Acme, Inc."));
+        assert_eq!(text(ann, "department").as_deref(), Some("Research; Lab 2"));
+        assert_eq!(text(ann, "email").as_deref(), Some("ann@acme.example"));
+        assert_eq!(
+            text(ann, "emails").as_deref(),
+            Some("ann@home.example; ann@acme.example")
+        );
+        assert_eq!(text(ann, "phone").as_deref(), Some("+1 555 0100"));
+        assert_eq!(text(ann, "mobile").as_deref(), Some("+1 555 0199"));
+        assert_eq!(text(ann, "street").as_deref(), Some("Suite 4, 12 Main St"));
+        assert_eq!(text(ann, "postal_code").as_deref(), Some("62701"));
+        assert_eq!(text(ann, "note").as_deref(), Some("Met at the\nconference"));
+
+        let jane = &records.rows[1];
+        assert_eq!(text(jane, "given_name").as_deref(), Some("Jane"));
+        assert_eq!(text(jane, "mobile").as_deref(), Some("555-0142"));
+        // A birthday without a year keeps the column text
+        assert_eq!(text(jane, "birthday").as_deref(), Some("--0415"));
+        assert_eq!(text(ann, "birthday").as_deref(), Some("1980-04-15"));
+    }
+
+    #[test]
+    fn test_birthdays_are_dates() {
+        let card = "BEGIN:VCARD\nVERSION:4.0\nFN:Ann\nBDAY:19800415\nEND:VCARD\n";
+        let records = VcardFormat.read(card, &HashMap::new()).unwrap();
+        let expected = Utc.with_ymd_and_hms(1980, 4, 15, 0, 0, 0).unwrap();
+        assert_eq!(records.rows[0]["birthday"], Value::Date(expected));
+    }
+}
@@ -0,0 +1,405 @@
+//! Content lines shared by vCard (RFC 6350, and the older 2.1 and 3.0) and
+//! iCalendar (RFC 5545) files: `NAME;PARAM=value:value` properties, folded
+//! onto continuation lines starting with a space or tab, and grouped into
+//! components between `BEGIN:` and `END:` lines.
+
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, TimeZone, Timelike, Utc};
+
+use crate::{connectors::record_format::Records, schema::Value, Result, TinyEtlError};
+
+/// A property, e.g. `TEL;TYPE=cell,voice:+1 555 0100`
+#[derive(Debug, Clone)]
+pub struct Property {
+    /// Uppercase name, without any `item1.` group
+    pub name: String,
+    /// Uppercase parameter names with their values, unquoted
+    pub params: Vec<(String, Vec<String>)>,
+    /// The value as written, escapes included
+    pub value: String,
+}
+
+impl Property {
+    fn parse(line: &str) -> Option<Self> {
+        let mut halves = split_unquoted(line, ':', 1).into_iter();
+        let head = halves.next()?;
+        let value = halves.next()?;
+        let mut parts = split_unquoted(head, ';', usize::MAX).into_iter();
+        let name = parts.next()?.trim();
+        let name = name.rsplit('.').next().unwrap_or(name).to_uppercase();
+        if name.is_empty() {
+            return None;
+        }
+        let params = parts
+            .map(|param| match param.split_once('=') {
+                Some((name, values)) => (
+                    name.trim().to_uppercase(),
+                    split_unquoted(values, ',', usize::MAX)
+                        .into_iter()
+                        .map(|value| value.trim_matches('"').to_string())
+                        .collect(),
+                ),
+                // vCard 2.1 lists types without a name, e.g. `TEL;CELL:`
+                None => ("TYPE".to_string(), vec![param.trim().to_string()]),
+            })
+            .collect();
+        Some(Self {
+            name,
+            params,
+            value: value.to_string(),
+        })
+    }
+
+    /// First value of a parameter
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(param, _)| param == name)
+            .and_then(|(_, values)| values.first())
+            .map(String::as_str)
+    }
+
+    /// Whether a `TYPE` parameter lists `kind`, e.g. `cell` or `work`
+    pub fn has_type(&self, kind: &str) -> bool {
+        self.params
+            .iter()
+            .filter(|(param, _)| param == "TYPE")
+            .flat_map(|(_, values)| values)
+            .flat_map(|value| value.split(','))
+            .any(|value| value.trim().eq_ignore_ascii_case(kind))
+    }
+
+    /// Marked preferred, with `TYPE=pref` (vCard 3.0) or `PREF=1` (4.0)
+    pub fn is_preferred(&self) -> bool {
+        self.has_type("pref") || self.param("PREF").is_some()
+    }
+
+    /// The value with any vCard 2.1 quoted-printable encoding undone
+    fn decoded(&self) -> String {
+        match self.param("ENCODING") {
+            Some(encoding) if encoding.eq_ignore_ascii_case("QUOTED-PRINTABLE") => {
+                decode_quoted_printable(&self.value)
+            }
+            _ => self.value.clone(),
+        }
+    }
+
+    /// The value as text, unescaped; empty values are `None`
+    pub fn text(&self) -> Option<String> {
+        Some(unescape(&self.decoded())).filter(|text| !text.is_empty())
+    }
+
+    /// The parts of a structured value such as a name or an address,
+    /// separated by `;` and each unescaped
+    pub fn components(&self) -> Vec<String> {
+        split_escaped(&self.decoded(), ';')
+            .iter()
+            .map(|part| unescape(part))
+            .collect()
+    }
+}
+
+/// A component, e.g. a VCARD or VEVENT, with the components inside it
+#[derive(Debug, Clone, Default)]
+pub struct Component {
+    /// Uppercase name
+    pub name: String,
+    pub properties: Vec<Property>,
+    pub children: Vec<Component>,
+}
+
+impl Component {
+    /// The first property named `name`
+    pub fn property(&self, name: &str) -> Option<&Property> {
+        self.properties.iter().find(|p| p.name == name)
+    }
+
+    /// Every property named `name`, in order
+    pub fn all<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Property> + 'a {
+        self.properties.iter().filter(move |p| p.name == name)
+    }
+
+    /// Text of the first property named `name`
+    pub fn text(&self, name: &str) -> Option<String> {
+        self.property(name).and_then(Property::text)
+    }
+
+    /// Components inside this one and theirs, depth first
+    pub fn descendants(&self) -> Vec<&Component> {
+        let mut found = Vec::new();
+        for child in &self.children {
+            found.push(child);
+            found.extend(child.descendants());
+        }
+        found
+    }
+}
+
+/// The top-level components of a file; `kind` names the format in errors
+pub fn parse(text: &str, kind: &str) -> Result<Vec<Component>> {
+    let invalid = |problem: String| {
+        TinyEtlError::DataValidation(format!("Invalid {} file: {}", kind, problem))
+    };
+    let mut top = Vec::new();
+    let mut open: Vec<Component> = Vec::new();
+    for line in unfold(text) {
+        let Some(property) = Property::parse(&line) else {
+            continue;
+        };
+        match property.name.as_str() {
+            "BEGIN" => open.push(Component {
+                name: property.value.trim().to_uppercase(),
+                ..Default::default()
+            }),
+            "END" => {
+                let name = property.value.trim().to_uppercase();
+                let component = match open.pop() {
+                    Some(component) if component.name == name => component,
+                    Some(component) => {
+                        return Err(invalid(format!(
+                            "END:{} where END:{} was expected",
+                            name, component.name
+                        )))
+                    }
+                    None => return Err(invalid(format!("END:{} without BEGIN:{}", name, name))),
+                };
+                match open.last_mut() {
+                    Some(parent) => parent.children.push(component),
+                    None => top.push(component),
+                }
+            }
+            // Properties outside any component are ignored
+            _ => {
+                if let Some(component) = open.last_mut() {
+                    component.properties.push(property);
+                }
+            }
+        }
+    }
+    match open.pop() {
+        Some(component) => Err(invalid(format!(
+            "BEGIN:{} without END:{}",
+            component.name, component.name
+        ))),
+        None => Ok(top),
+    }
+}
+
+/// Logical lines, with folded lines joined. vCard 2.1 quoted-printable
+/// values also continue on the next line after a trailing `=`.
+fn unfold(text: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    let mut soft_break = false;
+    for line in text.lines() {
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        if soft_break {
+            if let Some(last) = lines.last_mut() {
+                last.pop();
+                last.push_str(line);
+            }
+        } else if line.starts_with([' ', '\t']) && !lines.is_empty() {
+            lines.last_mut().unwrap().push_str(&line[1..]);
+        } else if line.trim().is_empty() {
+            continue;
+        } else {
+            lines.push(line.to_string());
+        }
+        soft_break = lines.last().is_some_and(|last| {
+            last.ends_with('=') && last.to_uppercase().contains("QUOTED-PRINTABLE")
+        });
+    }
+    lines
+}
+
+/// Split `text` on `separator` outside double quotes, at most `limit` times
+fn split_unquoted(text: &str, separator: char, limit: usize) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut quoted = false;
+    let mut start = 0;
+    for (i, c) in text.char_indices() {
+        if c == '"' {
+            quoted = !quoted;
+        } else if c == separator && !quoted && parts.len() < limit {
+            parts.push(&text[start..i]);
+            start = i + c.len_utf8();
+        }
+    }
+    parts.push(&text[start..]);
+    parts
+}
+
+/// Split `text` on `separator` where it isn't escaped with a backslash
+fn split_escaped(text: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut escaped = false;
+    let mut start = 0;
+    for (i, c) in text.char_indices() {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if c == separator {
+            parts.push(&text[start..i]);
+            start = i + 1;
+        }
+    }
+    parts.push(&text[start..]);
+    parts
+}
+
+/// Text with `\n`, `\,`, `\;` and `\\` replaced
+fn unescape(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n' | 'N') => unescaped.push('\n'),
+            Some(other) => unescaped.push(other),
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
+
+/// `=XX` byte escapes of quoted-printable text, read as UTF-8
+fn decode_quoted_printable(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let byte = bytes.get(i + 1..i + 3).and_then(|hex| {
+            std::str::from_utf8(hex)
+                .ok()
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        });
+        match byte {
+            Some(byte) if bytes[i] == b'=' => {
+                decoded.push(byte);
+                i += 3;
+            }
+            _ => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// A date (`19960415`, `1996-04-15`) or date-time (`19960415T231000Z`,
+/// `1996-04-15T23:10:00+02:00`) in UTC, and whether it was a date alone.
+/// Times without an offset are taken as written.
+pub fn parse_date_time(value: &str) -> Option<(DateTime<Utc>, bool)> {
+    let value = value.trim();
+    let (date, time) = match value.split_once(['T', 't']) {
+        Some((date, time)) => (date, Some(time)),
+        None => (value, None),
+    };
+    let date = NaiveDate::parse_from_str(&date.replace('-', ""), "%Y%m%d").ok()?;
+    let Some(time) = time else {
+        return Some((Utc.from_utc_datetime(&date.and_hms_opt(0, 0, 0)?), true));
+    };
+
+    let time = time.replace(':', "");
+    let (clock, offset) = if let Some(clock) = time.strip_suffix(|c| c == 'Z' || c == 'z') {
+        (clock, 0)
+    } else if let Some(at) = time.find(['+', '-']) {
+        let (clock, offset) = time.split_at(at);
+        let digits = &offset[1..];
+        let hours: i64 = digits.get(..2)?.parse().ok()?;
+        let minutes: i64 = digits.get(2..4).map_or(Some(0), |m| m.parse().ok())?;
+        let seconds = hours * 3600 + minutes * 60;
+        (
+            clock,
+            if offset.starts_with('-') {
+                -seconds
+            } else {
+                seconds
+            },
+        )
+    } else {
+        (time.as_str(), 0)
+    };
+    // Fractions of a second are dropped
+    let clock = clock.split('.').next().unwrap_or(clock);
+    let clock = match clock.len() {
+        4 => NaiveTime::parse_from_str(clock, "%H%M").ok()?,
+        _ => NaiveTime::parse_from_str(clock.get(..6)?, "%H%M%S").ok()?,
+    };
+    let local = Utc.from_utc_datetime(&date.and_time(clock));
+    Some((local - Duration::seconds(offset), false))
+}
+
+/// A date or date-time value, or its text when it isn't one, such as a
+/// birthday without a year (`--0415`)
+pub fn date_value(text: &str) -> Value {
+    match parse_date_time(text) {
+        Some((date, _)) => Value::Date(date),
+        None => Value::String(text.to_string()),
+    }
+}
+
+/// Columns holding both dates and text become text, so every value of a
+/// column has the type of the column
+pub fn settle_dates(records: &mut Records) {
+    for column in &records.columns {
+        let has_text = records
+            .rows
+            .iter()
+            .any(|row| matches!(row.get(column), Some(Value::String(_))));
+        if !has_text {
+            continue;
+        }
+        for row in &mut records.rows {
+            let Some(Value::Date(date)) = row.get(column) else {
+                continue;
+            };
+            let text = if date.num_seconds_from_midnight() == 0 {
+                date.format("%Y-%m-%d").to_string()
+            } else {
+                date.to_rfc3339()
+            };
+            row.insert(column.clone(), Value::String(text));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_folded_lines_and_parameters() {
+        let text = "BEGIN:VCARD\r\nitem1.EMAIL;TYPE=\"work,pref\":ann@exa\r\n mple.com\r\n\
+                    NOTE;ENCODING=QUOTED-PRINTABLE:Caf=C3=A9 =\r\nau lait\r\n\
+                    N:O\\;Brien;Ann;;;\r\nEND:VCARD\r\n";
+        let cards = parse(text, "vCard").unwrap();
+        assert_eq!(cards.len(), 1);
+        let email = cards[0].property("EMAIL").unwrap();
+        assert_eq!(email.text().as_deref(), Some("ann@example.com"));
+        assert!(email.has_type("WORK") && email.is_preferred());
+        assert_eq!(cards[0].text("NOTE").as_deref(), Some("Café au lait"));
+        let name = cards[0].property("N").unwrap().components();
+        assert_eq!(name[..2], ["O;Brien".to_string(), "Ann".to_string()]);
+
+        let err = parse("BEGIN:VCARD\nFN:Ann\n", "vCard").unwrap_err();
+        assert!(err.to_string().contains("BEGIN:VCARD without END:VCARD"));
+        assert!(parse("BEGIN:VEVENT\nEND:VCALENDAR\n", "iCalendar").is_err());
+    }
+
+    #[test]
+    fn test_parse_date_time() {
+        let (date, all_day) = parse_date_time("19960415").unwrap();
+        assert!(all_day);
+        assert_eq!(date.to_rfc3339(), "1996-04-15T00:00:00+00:00");
+        let (date, all_day) = parse_date_time("1996-04-15T23:10:00+02:00").unwrap();
+        assert!(!all_day);
+        assert_eq!(date.to_rfc3339(), "1996-04-15T21:10:00+00:00");
+        let (date, _) = parse_date_time("20240301T083000Z").unwrap();
+        assert_eq!(date.to_rfc3339(), "2024-03-01T08:30:00+00:00");
+        assert!(parse_date_time("--0415").is_none());
+    }
+}
@@ -0,0 +1,335 @@
+use serde::Deserialize;
+use serde_yaml::Value as Yaml;
+use tracing::info;
+
+use crate::{
+    schema::{SchemaFile, SchemaFileColumn},
+    Result, TinyEtlError,
+};
+
+/// A dbt properties file (`schema.yml`, `sources.yml`)
+#[derive(Debug, Deserialize)]
+struct Properties {
+    #[serde(default)]
+    models: Vec<Model>,
+    #[serde(default)]
+    sources: Vec<Source>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Source {
+    name: String,
+    #[serde(default)]
+    tables: Vec<Model>,
+}
+
+/// A model or source table
+#[derive(Debug, Deserialize)]
+struct Model {
+    name: String,
+    #[serde(default)]
+    columns: Vec<Column>,
+    #[serde(default)]
+    constraints: Vec<Constraint>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Column {
+    name: String,
+    data_type: Option<String>,
+    #[serde(default)]
+    constraints: Vec<Constraint>,
+    #[serde(default)]
+    tests: Vec<Yaml>,
+    /// `tests` was renamed `data_tests` in dbt 1.8; both are read
+    #[serde(default)]
+    data_tests: Vec<Yaml>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Constraint {
+    #[serde(rename = "type")]
+    kind: String,
+    /// Model-level constraints name their columns
+    #[serde(default)]
+    columns: Vec<String>,
+}
+
+/// Whether a YAML document is a dbt properties file rather than a TinyETL
+/// schema file
+pub fn is_properties_file(content: &str) -> bool {
+    match serde_yaml::from_str::<Yaml>(content) {
+        Ok(Yaml::Mapping(map)) => {
+            !map.contains_key("columns")
+                && (map.contains_key("models") || map.contains_key("sources"))
+        }
+        _ => false,
+    }
+}
+
+/// Build a schema file from the contract of one model (`name`) or source
+/// table (`source.table`) in a dbt properties file. `name` may be omitted
+/// when the file describes a single model or table.
+pub fn schema_file(content: &str, name: Option<&str>) -> Result<SchemaFile> {
+    let properties: Properties = serde_yaml::from_str(content)
+        .map_err(|e| TinyEtlError::Configuration(format!("Invalid dbt properties file: {}", e)))?;
+
+    let mut candidates: Vec<(String, &Model)> = properties
+        .models
+        .iter()
+        .map(|model| (model.name.clone(), model))
+        .collect();
+    for source in &properties.sources {
+        for table in &source.tables {
+            candidates.push((format!("{}.{}", source.name, table.name), table));
+        }
+    }
+
+    let model = match (name, candidates.len()) {
+        (Some(name), _) => candidates
+            .iter()
+            .find(|(candidate, _)| candidate == name)
+            .map(|(_, model)| *model),
+        (None, 1) => Some(candidates[0].1),
+        (None, _) => None,
+    };
+    let model = model.ok_or_else(|| {
+        let names: Vec<&str> = candidates.iter().map(|(name, _)| name.as_str()).collect();
+        TinyEtlError::Configuration(format!(
+            "{} in dbt properties file; select one with FILE#NAME from: {}",
+            match name {
+                Some(name) => format!("No model or source table '{}'", name),
+                None => "Several models or source tables".to_string(),
+            },
+            names.join(", ")
+        ))
+    })?;
+
+    let mut columns = model
+        .columns
+        .iter()
+        .map(schema_column)
+        .collect::<Result<Vec<_>>>()?;
+    for constraint in &model.constraints {
+        for column in columns
+            .iter_mut()
+            .filter(|c| constraint.columns.contains(&c.name))
+        {
+            match constraint.kind.as_str() {
+                "not_null" => column.nullable = false,
+                "primary_key" => {
+                    column.nullable = false;
+                    column.unique |= constraint.columns.len() == 1;
+                }
+                "unique" => column.unique |= constraint.columns.len() == 1,
+                _ => {}
+            }
+        }
+    }
+
+    let schema_file = SchemaFile { columns };
+    schema_file.validate()?;
+    Ok(schema_file)
+}
+
+fn schema_column(column: &Column) -> Result<SchemaFileColumn> {
+    let data_type = column.data_type.as_deref().ok_or_else(|| {
+        TinyEtlError::Configuration(format!(
+            "Column '{}' in dbt properties file has no data_type",
+            column.name
+        ))
+    })?;
+    let mut schema_column = SchemaFileColumn {
+        name: column.name.clone(),
+        data_type: declared_type(data_type)
+            .ok_or_else(|| {
+                TinyEtlError::Configuration(format!(
+                    "Unsupported data_type '{}' for column '{}' in dbt properties file",
+                    data_type, column.name
+                ))
+            })?
+            .to_string(),
+        nullable: true,
+        pattern: None,
+        default: None,
+        unique: false,
+        accepted_values: None,
+    };
+
+    for constraint in &column.constraints {
+        match constraint.kind.as_str() {
+            "not_null" => schema_column.nullable = false,
+            "unique" => schema_column.unique = true,
+            "primary_key" => {
+                schema_column.nullable = false;
+                schema_column.unique = true;
+            }
+            _ => {}
+        }
+    }
+
+    for test in column.tests.iter().chain(&column.data_tests) {
+        let (name, args) = match test {
+            Yaml::String(name) => (name.as_str(), None),
+            Yaml::Mapping(map) if map.len() == 1 => match map.iter().next() {
+                Some((Yaml::String(name), args)) => (name.as_str(), Some(args)),
+                _ => continue,
+            },
+            _ => continue,
+        };
+        // Tests limited to some rows can't be checked row by row
+        if args.is_some_and(|args| args["config"].get("where").is_some()) {
+            info!(
+                "→ Skipping dbt test '{}' on column '{}' (has a where config)",
+                name, column.name
+            );
+            continue;
+        }
+        match name {
+            "not_null" => schema_column.nullable = false,
+            "unique" => schema_column.unique = true,
+            "accepted_values" => {
+                let args = args.unwrap_or(&Yaml::Null);
+                // dbt 1.10 nests test arguments under `arguments`
+                let values = match args.get("values") {
+                    Some(values) => values,
+                    None => &args["arguments"]["values"],
+                };
+                let values = values
+                    .as_sequence()
+                    .map(|values| values.iter().filter_map(scalar).collect());
+                schema_column.accepted_values = values;
+            }
+            _ => info!(
+                "→ Skipping dbt test '{}' on column '{}' (not checked by TinyETL)",
+                name, column.name
+            ),
+        }
+    }
+
+    Ok(schema_column)
+}
+
+fn scalar(value: &Yaml) -> Option<String> {
+    match value {
+        Yaml::String(s) => Some(s.clone()),
+        Yaml::Number(n) => Some(n.to_string()),
+        Yaml::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+/// TinyETL type of a warehouse type as written in a dbt contract
+fn declared_type(data_type: &str) -> Option<&'static str> {
+    let data_type = data_type.trim().to_lowercase();
+    let (base, args) = match data_type.split_once(['(', '<']) {
+        Some((base, args)) => (base.trim(), args.trim_end_matches(')')),
+        None => (data_type.as_str(), ""),
+    };
+    // NUMBER(38,0) and friends hold whole numbers
+    let whole = args
+        .split(',')
+        .nth(1)
+        .is_some_and(|scale| scale.trim() == "0");
+
+    let declared = match base {
+        "smallint" | "integer" | "int" | "bigint" | "tinyint" | "byteint" | "int2" | "int4"
+        | "int8" | "int64" | "hugeint" => "integer",
+        "numeric" | "decimal" | "number" | "bignumeric" if whole => "integer",
+        "numeric" | "decimal" | "number" | "bignumeric" | "float" | "float4" | "float8"
+        | "float64" | "double" | "double precision" | "real" => "decimal",
+        "boolean" | "bool" => "boolean",
+        "date" => "date",
+        "datetime"
+        | "timestamp"
+        | "timestamptz"
+        | "timestamp_ntz"
+        | "timestamp_ltz"
+        | "timestamp_tz"
+        | "timestamp with time zone"
+        | "timestamp without time zone" => "datetime",
+        "json" | "jsonb" | "variant" | "object" | "array" | "struct" | "super" => "json",
+        "bytea" | "binary" | "varbinary" | "bytes" | "blob" | "varbyte" => "binary",
+        "text" | "string" | "varchar" | "char" | "character" | "character varying" | "nvarchar"
+        | "nchar" | "uuid" | "time" => "string",
+        _ => return None,
+    };
+    Some(declared)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROPERTIES: &str = r#"
+version: 2
+models:
+  - name: orders
+    config:
+      contract:
+        enforced: true
+    columns:
+      - name: order_id
+        data_type: bigint
+        constraints:
+          - type: primary_key
+      - name: status
+        data_type: varchar(20)
+        data_tests:
+          - not_null
+          - accepted_values:
+              values: ['placed', 'shipped']
+      - name: amount
+        data_type: number(12,2)
+        tests:
+          - not_null:
+              config:
+                where: "status = 'shipped'"
+      - name: customer_id
+        data_type: number(38,0)
+        tests:
+          - relationships:
+              to: ref('customers')
+              field: id
+sources:
+  - name: raw
+    tables:
+      - name: payments
+        columns:
+          - name: payment_id
+            data_type: integer
+            tests: [unique, not_null]
+"#;
+
+    #[test]
+    fn test_model_contract() {
+        assert!(is_properties_file(PROPERTIES));
+        assert!(!is_properties_file("columns: []"));
+
+        let schema_file = schema_file(PROPERTIES, Some("orders")).unwrap();
+        let columns = &schema_file.columns;
+        assert_eq!(columns.len(), 4);
+        assert_eq!(columns[0].data_type, "integer");
+        assert!(!columns[0].nullable && columns[0].unique);
+        assert_eq!(columns[1].data_type, "string");
+        assert!(!columns[1].nullable && !columns[1].unique);
+        assert_eq!(
+            columns[1].accepted_values,
+            Some(vec!["placed".to_string(), "shipped".to_string()])
+        );
+        // The conditional not_null test is skipped
+        assert_eq!(columns[2].data_type, "decimal");
+        assert!(columns[2].nullable);
+        assert_eq!(columns[3].data_type, "integer");
+    }
+
+    #[test]
+    fn test_source_table_selection() {
+        let schema_file = schema_file(PROPERTIES, Some("raw.payments")).unwrap();
+        assert!(!schema_file.columns[0].nullable && schema_file.columns[0].unique);
+
+        let err = super::schema_file(PROPERTIES, None).unwrap_err();
+        assert!(err.to_string().contains("orders, raw.payments"));
+        assert!(super::schema_file(PROPERTIES, Some("customers")).is_err());
+    }
+}
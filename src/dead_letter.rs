@@ -0,0 +1,39 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+use crate::{schema::Row, Result};
+
+/// JSON Lines file receiving rows left out of the target, one object per
+/// row with the reason it was rejected
+pub struct DeadLetterFile {
+    writer: BufWriter<File>,
+    /// Rows written so far
+    pub rows: usize,
+}
+
+impl DeadLetterFile {
+    pub fn create(path: &str) -> Result<Self> {
+        Ok(Self {
+            writer: BufWriter::new(File::create(path)?),
+            rows: 0,
+        })
+    }
+
+    /// Append a rejected row. `row_index` is its position among the rows
+    /// sent to the target, when known.
+    pub fn write(&mut self, reason: &str, row_index: Option<usize>, row: &Row) -> Result<()> {
+        let mut entry = serde_json::json!({ "reason": reason, "row": row });
+        if let Some(index) = row_index {
+            entry["row_index"] = index.into();
+        }
+        serde_json::to_writer(&mut self.writer, &entry)?;
+        self.writer.write_all(b"\n")?;
+        self.rows += 1;
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> Result<()> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,201 @@
+use futures_util::stream::{self, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::future::Future;
+
+use crate::{config::Config, redact::redact, transfer::TransferStats};
+
+/// YAML `foreach:` block expanding one job template over parameter sets
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ForeachConfig {
+    /// One parameter set per run; `${name}` placeholders in the job are replaced by its values
+    pub params: Vec<HashMap<String, String>>,
+    /// Maximum number of runs in flight at once (1 = sequential)
+    #[serde(default = "default_parallelism")]
+    pub parallelism: usize,
+    /// Keep starting new runs after one has failed
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub continue_on_error: bool,
+    /// Write one consolidated JSON report covering every run
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub report_file: Option<String>,
+}
+
+fn default_parallelism() -> usize {
+    1
+}
+
+/// One expanded run of a `foreach` job
+#[derive(Debug, Clone)]
+pub struct FanoutJob {
+    pub params: HashMap<String, String>,
+    pub config: Config,
+}
+
+#[derive(Debug, Clone)]
+pub enum JobOutcome {
+    Completed(Box<TransferStats>),
+    Failed(String),
+    /// Not started because an earlier run failed
+    Skipped,
+}
+
+#[derive(Debug, Clone)]
+pub struct JobResult {
+    pub params: HashMap<String, String>,
+    pub target: String,
+    pub outcome: JobOutcome,
+}
+
+impl JobResult {
+    pub fn is_success(&self) -> bool {
+        matches!(self.outcome, JobOutcome::Completed(_))
+    }
+}
+
+/// Run the jobs with at most `parallelism` in flight, returning results in job
+/// order. Unless `continue_on_error` is set, runs not yet started when one
+/// fails are skipped.
+pub async fn run_jobs<F, Fut, E>(
+    jobs: Vec<FanoutJob>,
+    parallelism: usize,
+    continue_on_error: bool,
+    run: F,
+) -> Vec<JobResult>
+where
+    F: Fn(Config) -> Fut,
+    Fut: Future<Output = std::result::Result<TransferStats, E>>,
+    E: Display,
+{
+    let failed = Cell::new(false);
+    let failed = &failed;
+    let run = &run;
+
+    let mut results: Vec<(usize, JobResult)> = stream::iter(jobs.into_iter().enumerate())
+        .map(|(index, job)| async move {
+            let target = job.config.target.clone();
+            let outcome = if failed.get() && !continue_on_error {
+                JobOutcome::Skipped
+            } else {
+                match run(job.config).await {
+                    Ok(stats) => JobOutcome::Completed(Box::new(stats)),
+                    Err(e) => {
+                        failed.set(true);
+                        JobOutcome::Failed(redact(&e.to_string()))
+                    }
+                }
+            };
+            (
+                index,
+                JobResult {
+                    params: job.params,
+                    target,
+                    outcome,
+                },
+            )
+        })
+        .buffer_unordered(parallelism.max(1))
+        .collect()
+        .await;
+
+    results.sort_by_key(|(index, _)| *index);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+/// Consolidated JSON report for a fan-out run
+pub fn report(results: &[JobResult]) -> serde_json::Value {
+    let runs: Vec<serde_json::Value> = results
+        .iter()
+        .map(|result| {
+            let (status, error, stats) = match &result.outcome {
+                JobOutcome::Completed(stats) => ("completed", None, Some(stats.to_json())),
+                JobOutcome::Failed(error) => ("failed", Some(error.clone()), None),
+                JobOutcome::Skipped => ("skipped", None, None),
+            };
+            serde_json::json!({
+                "params": result.params,
+                "target": result.target,
+                "status": status,
+                "error": error,
+                "stats": stats,
+            })
+        })
+        .collect();
+
+    let total_rows: usize = results
+        .iter()
+        .filter_map(|result| match &result.outcome {
+            JobOutcome::Completed(stats) => Some(stats.total_rows),
+            _ => None,
+        })
+        .sum();
+
+    serde_json::json!({
+        "runs": runs,
+        "total_rows": total_rows,
+        "succeeded": results.iter().filter(|r| r.is_success()).count(),
+        "failed": results
+            .iter()
+            .filter(|r| matches!(r.outcome, JobOutcome::Failed(_)))
+            .count(),
+        "skipped": results
+            .iter()
+            .filter(|r| matches!(r.outcome, JobOutcome::Skipped))
+            .count(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn jobs(tenants: &[&str]) -> Vec<FanoutJob> {
+        tenants
+            .iter()
+            .map(|tenant| FanoutJob {
+                params: HashMap::from([("tenant".to_string(), tenant.to_string())]),
+                config: Config {
+                    target: format!("{}.csv", tenant),
+                    ..Default::default()
+                },
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_results_keep_job_order() {
+        let results = run_jobs(jobs(&["a", "b", "c"]), 3, false, |config| async move {
+            Ok::<_, String>(TransferStats {
+                total_rows: config.target.len(),
+                ..Default::default()
+            })
+        })
+        .await;
+
+        let targets: Vec<&str> = results.iter().map(|r| r.target.as_str()).collect();
+        assert_eq!(targets, vec!["a.csv", "b.csv", "c.csv"]);
+        assert!(results.iter().all(|r| r.is_success()));
+        assert_eq!(report(&results)["total_rows"], 15);
+    }
+
+    #[tokio::test]
+    async fn test_failure_skips_remaining_sequential_runs() {
+        let run = |config: Config| async move {
+            if config.target == "b.csv" {
+                Err("boom".to_string())
+            } else {
+                Ok(TransferStats::default())
+            }
+        };
+
+        let results = run_jobs(jobs(&["a", "b", "c"]), 1, false, run).await;
+        assert!(results[0].is_success());
+        assert!(matches!(results[1].outcome, JobOutcome::Failed(ref e) if e == "boom"));
+        assert!(matches!(results[2].outcome, JobOutcome::Skipped));
+
+        let results = run_jobs(jobs(&["a", "b", "c"]), 1, true, run).await;
+        assert!(results[2].is_success());
+    }
+}
@@ -0,0 +1,67 @@
+use hmac::{Hmac, Mac};
+use md5::Md5;
+use sha2::{Digest, Sha256};
+
+/// Deterministic hashing helpers used for surrogate keys and pseudonymization
+pub struct Hasher;
+
+/// Separator placed between values when hashing composite keys, so that
+/// ("ab", "c") and ("a", "bc") produce different hashes
+const KEY_SEPARATOR: &str = "\u{1f}";
+
+impl Hasher {
+    /// Hex-encoded MD5 digest
+    pub fn md5_hex(data: &[u8]) -> String {
+        hex::encode(Md5::digest(data))
+    }
+
+    /// Hex-encoded SHA-256 digest
+    pub fn sha256_hex(data: &[u8]) -> String {
+        hex::encode(Sha256::digest(data))
+    }
+
+    /// Hex-encoded HMAC-SHA256 of `data` keyed with `key`
+    pub fn hmac_sha256_hex(key: &[u8], data: &[u8]) -> String {
+        // HMAC accepts keys of any length, so this cannot fail
+        let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+        mac.update(data);
+        hex::encode(mac.finalize().into_bytes())
+    }
+
+    /// SHA-256 surrogate key over several values (missing values hash as empty strings)
+    pub fn composite_key<S: AsRef<str>>(parts: &[Option<S>]) -> String {
+        let joined = parts
+            .iter()
+            .map(|part| part.as_ref().map(|p| p.as_ref()).unwrap_or(""))
+            .collect::<Vec<_>>()
+            .join(KEY_SEPARATOR);
+        Self::sha256_hex(joined.as_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_known_digests() {
+        assert_eq!(Hasher::md5_hex(b"abc"), "900150983cd24fb0d6963f7d28e17f72");
+        assert_eq!(
+            Hasher::sha256_hex(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(
+            Hasher::hmac_sha256_hex(b"key", b"The quick brown fox jumps over the lazy dog"),
+            "f7bc83f430538424b13298e6aa6fb143ef4d59a14946175997479dbc2d1a3cd8"
+        );
+    }
+
+    #[test]
+    fn test_composite_key_is_unambiguous() {
+        let a = Hasher::composite_key(&[Some("ab"), Some("c")]);
+        let b = Hasher::composite_key(&[Some("a"), Some("bc")]);
+        assert_ne!(a, b);
+        assert_eq!(a, Hasher::composite_key(&[Some("ab"), Some("c")]));
+        assert_eq!(a.len(), 64);
+    }
+}
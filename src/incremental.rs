@@ -0,0 +1,181 @@
+use crate::{
+    connectors::{
+        sql_ident::{quote_ident, quote_style_for, source_relation, QuoteStyle},
+        sql_params::ParamType,
+    },
+    row_number::compare_values,
+    schema::{Row, Value},
+    state::{RunState, Watermark},
+    Result, TinyEtlError,
+};
+use std::cmp::Ordering;
+
+/// Name of the `:name` placeholder the watermark is bound to
+pub const WATERMARK_PARAM: &str = "tinyetl_watermark";
+
+/// Identifier quoting of the SQL sources incremental reads support
+fn quote_style(connection_string: &str) -> Result<QuoteStyle> {
+    quote_style_for(connection_string).ok_or_else(|| {
+        TinyEtlError::Configuration(format!(
+            "--incremental-column is only supported for SQLite, PostgreSQL, MySQL and MSSQL sources, not {}",
+            connection_string
+        ))
+    })
+}
+
+/// Query reading the rows of a SQL source's `#table`, or of a custom
+/// `query`, whose `column` is greater than the watermark. Without a
+/// watermark (the first run) every row is read. The watermark is left as
+/// the `:tinyetl_watermark` placeholder, so the driver binds it with the
+/// column's type.
+pub fn incremental_query(
+    connection_string: &str,
+    query: Option<&str>,
+    column: &str,
+    has_watermark: bool,
+) -> Result<String> {
+    let style = quote_style(connection_string)?;
+    let relation = source_relation(
+        connection_string,
+        query,
+        style,
+        "incremental",
+        "--incremental-column",
+    )?;
+
+    let mut sql = format!("SELECT * FROM {}", relation);
+    if has_watermark {
+        sql.push_str(&format!(
+            " WHERE {} > :{}",
+            quote_ident(column, style),
+            WATERMARK_PARAM
+        ));
+    }
+    Ok(sql)
+}
+
+/// Highest value of the incremental column among the rows read by a run
+pub struct WatermarkTracker {
+    column: String,
+    highest: Option<Value>,
+}
+
+impl WatermarkTracker {
+    pub fn new(column: &str) -> Self {
+        Self {
+            column: column.to_string(),
+            highest: None,
+        }
+    }
+
+    pub fn observe(&mut self, rows: &[Row]) {
+        for value in rows.iter().filter_map(|row| row.get(&self.column)) {
+            if watermark_text(value).is_none() {
+                continue;
+            }
+            let higher = match &self.highest {
+                None => true,
+                Some(highest) => compare_values(value, highest) == Some(Ordering::Greater),
+            };
+            if higher {
+                self.highest = Some(value.clone());
+            }
+        }
+    }
+
+    /// Record the highest value read; a run that read no new rows keeps the
+    /// previous watermark
+    pub fn record(&self, state: &mut RunState) {
+        if let Some((value, param_type)) = self.highest.as_ref().and_then(watermark_text) {
+            state.watermark = Some(Watermark {
+                column: self.column.clone(),
+                value,
+                param_type,
+            });
+        }
+    }
+}
+
+/// Text form and type of a value that can serve as a watermark
+fn watermark_text(value: &Value) -> Option<(String, ParamType)> {
+    match value {
+        Value::Integer(i) => Some((i.to_string(), ParamType::Int)),
+        Value::Decimal(d) => Some((d.to_string(), ParamType::Decimal)),
+        Value::Date(dt) => Some((dt.to_rfc3339(), ParamType::Timestamp)),
+        Value::String(s) => Some((s.clone(), ParamType::Text)),
+        Value::Boolean(_) | Value::Json(_) | Value::Bytes(_) | Value::Null => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    #[test]
+    fn test_incremental_query() {
+        assert_eq!(
+            incremental_query(
+                "postgres://u@db/shop#sales.orders",
+                None,
+                "updated_at",
+                false
+            )
+            .unwrap(),
+            "SELECT * FROM \"sales\".\"orders\""
+        );
+        assert_eq!(
+            incremental_query("mysql://u@db/shop#orders", None, "updated_at", true).unwrap(),
+            "SELECT * FROM `orders` WHERE `updated_at` > :tinyetl_watermark"
+        );
+        assert_eq!(
+            incremental_query(
+                "mssql://u@db/shop",
+                Some("SELECT id, updated_at FROM orders;"),
+                "updated_at",
+                true
+            )
+            .unwrap(),
+            "SELECT * FROM (SELECT id, updated_at FROM orders) AS incremental WHERE [updated_at] > :tinyetl_watermark"
+        );
+        assert!(incremental_query("orders.csv", None, "updated_at", false).is_err());
+        assert!(incremental_query("postgres://u@db/shop", None, "updated_at", false).is_err());
+    }
+
+    #[test]
+    fn test_watermark_tracks_highest_value() {
+        let row = |ts: i64| {
+            Row::from([(
+                "updated_at".to_string(),
+                Value::Date(Utc.timestamp_opt(ts, 0).unwrap()),
+            )])
+        };
+        let mut state = RunState {
+            watermark: Some(Watermark {
+                column: "updated_at".to_string(),
+                value: "2020-01-01T00:00:00+00:00".to_string(),
+                param_type: ParamType::Timestamp,
+            }),
+            ..RunState::default()
+        };
+
+        let mut tracker = WatermarkTracker::new("updated_at");
+        let value = |state: &RunState| {
+            state
+                .watermark_for("updated_at")
+                .map(|watermark| watermark.value.clone())
+        };
+        tracker.record(&mut state);
+        assert_eq!(value(&state).unwrap(), "2020-01-01T00:00:00+00:00");
+
+        tracker.observe(&[row(1_700_000_000), row(1_700_000_500)]);
+        tracker.observe(&[row(1_700_000_100), Row::new()]);
+        tracker.record(&mut state);
+        assert_eq!(value(&state).unwrap(), "2023-11-14T22:21:40+00:00");
+        assert_eq!(
+            state.watermark_for("updated_at").unwrap().param_type,
+            ParamType::Timestamp
+        );
+        assert_eq!(state.watermark_for("id"), None);
+    }
+}
@@ -0,0 +1,333 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tracing::info;
+
+use crate::{
+    connectors::create_source_from_url_with_type_and_options,
+    schema::{Column, Row, Schema, Value},
+    secrets::process_connection_string,
+    Result, TinyEtlError,
+};
+
+/// Join semantics for rows of the primary source without a match
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JoinKind {
+    /// Drop unmatched rows
+    #[default]
+    Inner,
+    /// Keep unmatched rows with null lookup columns
+    Left,
+}
+
+/// Enrich the primary stream with rows from a secondary source
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JoinConfig {
+    /// Connection string of the secondary (lookup) source
+    pub source: String,
+    /// Force the secondary source type (csv, json, parquet, ...)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_type: Option<String>,
+    /// Secret ID for the secondary source password
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secret_id: Option<String>,
+    /// Connector-specific options for the secondary source
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub options: HashMap<String, String>,
+    /// Key columns of the primary source
+    pub on: Vec<String>,
+    /// Key columns of the secondary source when named differently (default: `on`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub right_on: Vec<String>,
+    #[serde(default, rename = "type")]
+    pub kind: JoinKind,
+    /// Secondary columns to add (default: all non-key columns)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub columns: Vec<String>,
+    /// Prefix for secondary columns whose names clash with primary columns.
+    /// Without a prefix the primary value is kept.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prefix: Option<String>,
+}
+
+/// In-memory hash join: the secondary source is loaded once and indexed by key
+pub struct HashJoiner {
+    config: JoinConfig,
+    lookup_columns: Vec<Column>,
+    index: HashMap<String, Vec<Row>>,
+}
+
+impl HashJoiner {
+    /// Read the whole secondary source and build the hash index
+    pub async fn load(config: &JoinConfig, batch_size: usize) -> Result<Self> {
+        let connection =
+            process_connection_string(&config.source, config.secret_id.as_ref(), "join")
+                .map_err(|e| TinyEtlError::Configuration(e.to_string()))?;
+        let mut source = create_source_from_url_with_type_and_options(
+            &connection,
+            config.source_type.as_deref(),
+            &config.options,
+        )
+        .await?;
+
+        source.connect().await?;
+        let schema = source.infer_schema(1000).await?;
+        source.reset().await?;
+
+        let mut rows = Vec::new();
+        while source.has_more() {
+            let batch = source.read_batch(batch_size).await?;
+            if batch.is_empty() {
+                break;
+            }
+            rows.extend(batch);
+        }
+
+        let joiner = Self::new(config, &schema, rows)?;
+        info!(
+            "→ Join source loaded: {} keys from {}",
+            joiner.index.len(),
+            config.source
+        );
+        Ok(joiner)
+    }
+
+    /// Build a joiner over already loaded secondary rows
+    pub fn new(config: &JoinConfig, right_schema: &Schema, rows: Vec<Row>) -> Result<Self> {
+        if config.on.is_empty() {
+            return Err(TinyEtlError::Configuration(
+                "join requires at least one key column in 'on'".to_string(),
+            ));
+        }
+        let right_on = if config.right_on.is_empty() {
+            config.on.clone()
+        } else {
+            config.right_on.clone()
+        };
+        if right_on.len() != config.on.len() {
+            return Err(TinyEtlError::Configuration(
+                "join 'on' and 'right_on' must list the same number of columns".to_string(),
+            ));
+        }
+
+        let lookup_columns: Vec<Column> = if config.columns.is_empty() {
+            right_schema
+                .columns
+                .iter()
+                .filter(|c| !right_on.contains(&c.name))
+                .cloned()
+                .collect()
+        } else {
+            config
+                .columns
+                .iter()
+                .map(|name| {
+                    right_schema
+                        .columns
+                        .iter()
+                        .find(|c| &c.name == name)
+                        .cloned()
+                        .ok_or_else(|| {
+                            TinyEtlError::Configuration(format!(
+                                "join column '{}' not found in {}",
+                                name, config.source
+                            ))
+                        })
+                })
+                .collect::<Result<_>>()?
+        };
+
+        let mut index: HashMap<String, Vec<Row>> = HashMap::new();
+        for row in rows {
+            // Null keys never match, as in SQL
+            if let Some(key) = Self::key_of(&row, &right_on) {
+                index.entry(key).or_default().push(row);
+            }
+        }
+
+        Ok(Self {
+            config: config.clone(),
+            lookup_columns,
+            index,
+        })
+    }
+
+    /// Schema of joined rows
+    pub fn transform_schema(&self, schema: &Schema) -> Schema {
+        let mut joined = schema.clone();
+        for column in &self.lookup_columns {
+            if let Some(name) = self.output_name(&column.name, schema) {
+                joined.columns.push(Column {
+                    name,
+                    data_type: column.data_type.clone(),
+                    // Left joins leave lookup columns empty for unmatched rows
+                    nullable: column.nullable || self.config.kind == JoinKind::Left,
+                });
+            }
+        }
+        joined.estimated_rows = None;
+        joined
+    }
+
+    /// Join a batch of primary rows
+    pub fn join_batch(&self, rows: Vec<Row>) -> Vec<Row> {
+        let mut joined = Vec::with_capacity(rows.len());
+        for row in rows {
+            let matches = Self::key_of(&row, &self.config.on).and_then(|key| self.index.get(&key));
+            match matches {
+                Some(matches) => {
+                    for right in matches {
+                        joined.push(self.merge(&row, Some(right)));
+                    }
+                }
+                None if self.config.kind == JoinKind::Left => joined.push(self.merge(&row, None)),
+                None => {}
+            }
+        }
+        joined
+    }
+
+    fn merge(&self, left: &Row, right: Option<&Row>) -> Row {
+        let mut row = left.clone();
+        for column in &self.lookup_columns {
+            let name = if left.contains_key(&column.name) {
+                match &self.config.prefix {
+                    Some(prefix) => format!("{}{}", prefix, column.name),
+                    None => continue,
+                }
+            } else {
+                column.name.clone()
+            };
+            let value = right
+                .and_then(|r| r.get(&column.name).cloned())
+                .unwrap_or(Value::Null);
+            row.insert(name, value);
+        }
+        row
+    }
+
+    fn output_name(&self, name: &str, schema: &Schema) -> Option<String> {
+        if schema.columns.iter().any(|c| c.name == name) {
+            self.config
+                .prefix
+                .as_ref()
+                .map(|prefix| format!("{}{}", prefix, name))
+        } else {
+            Some(name.to_string())
+        }
+    }
+
+    fn key_of(row: &Row, columns: &[String]) -> Option<String> {
+        let mut parts = Vec::with_capacity(columns.len());
+        for column in columns {
+            match row.get(column) {
+                None | Some(Value::Null) => return None,
+                Some(Value::Decimal(d)) => parts.push(d.normalize().to_string()),
+                Some(value) => parts.push(value.to_string_for_arrow().unwrap_or_default()),
+            }
+        }
+        Some(parts.join("\u{1f}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::DataType;
+
+    fn customers() -> (Schema, Vec<Row>) {
+        let schema = Schema {
+            columns: vec![
+                Column {
+                    name: "id".to_string(),
+                    data_type: DataType::Integer,
+                    nullable: false,
+                },
+                Column {
+                    name: "name".to_string(),
+                    data_type: DataType::String,
+                    nullable: false,
+                },
+            ],
+            estimated_rows: Some(1),
+            primary_key_candidate: None,
+        };
+        let mut row = Row::new();
+        row.insert("id".to_string(), Value::Integer(1));
+        row.insert("name".to_string(), Value::String("Ada".to_string()));
+        (schema, vec![row])
+    }
+
+    fn order(order_id: i64, customer_id: i64) -> Row {
+        let mut row = Row::new();
+        row.insert("order_id".to_string(), Value::Integer(order_id));
+        row.insert("customer_id".to_string(), Value::Integer(customer_id));
+        row
+    }
+
+    fn config(kind: JoinKind) -> JoinConfig {
+        JoinConfig {
+            source: "customers.csv".to_string(),
+            source_type: None,
+            secret_id: None,
+            options: HashMap::new(),
+            on: vec!["customer_id".to_string()],
+            right_on: vec!["id".to_string()],
+            kind,
+            columns: Vec::new(),
+            prefix: None,
+        }
+    }
+
+    #[test]
+    fn test_inner_join_drops_unmatched_rows() {
+        let (schema, rows) = customers();
+        let joiner = HashJoiner::new(&config(JoinKind::Inner), &schema, rows).unwrap();
+
+        let joined = joiner.join_batch(vec![order(10, 1), order(11, 2)]);
+        assert_eq!(joined.len(), 1);
+        assert_eq!(
+            joined[0].get("name"),
+            Some(&Value::String("Ada".to_string()))
+        );
+        assert!(!joined[0].contains_key("id"));
+    }
+
+    #[test]
+    fn test_left_join_keeps_unmatched_rows() {
+        let (schema, rows) = customers();
+        let joiner = HashJoiner::new(&config(JoinKind::Left), &schema, rows).unwrap();
+
+        let joined = joiner.join_batch(vec![order(10, 1), order(11, 2)]);
+        assert_eq!(joined.len(), 2);
+        assert_eq!(joined[1].get("name"), Some(&Value::Null));
+    }
+
+    #[test]
+    fn test_clashing_columns_use_prefix() {
+        let (schema, rows) = customers();
+        let mut config = config(JoinKind::Inner);
+        config.prefix = Some("customer_".to_string());
+        let joiner = HashJoiner::new(&config, &schema, rows).unwrap();
+
+        let mut left = order(10, 1);
+        left.insert("name".to_string(), Value::String("Widget".to_string()));
+        let joined = joiner.join_batch(vec![left]);
+        assert_eq!(
+            joined[0].get("name"),
+            Some(&Value::String("Widget".to_string()))
+        );
+        assert_eq!(
+            joined[0].get("customer_name"),
+            Some(&Value::String("Ada".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_parse_yaml() {
+        let yaml = "source: customers.csv\non: [customer_id]\nright_on: [id]\ntype: left\n";
+        let config: JoinConfig = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(config.kind, JoinKind::Left);
+        assert_eq!(config.right_on, vec!["id"]);
+    }
+}
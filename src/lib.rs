@@ -1,11 +1,39 @@
+pub mod all_tables;
+pub mod audit;
+pub mod checkpoint;
 pub mod cli;
+pub mod codecs;
 pub mod config;
 pub mod connectors;
 pub mod date_parser;
+pub mod dbt;
+pub mod dead_letter;
 pub mod error;
+pub mod fanout;
+pub mod hashing;
+pub mod incremental;
+pub mod join;
+pub mod limits;
+pub mod null_fallback;
+pub mod output_names;
+pub mod overflow;
+pub mod pipeline;
 pub mod protocols;
+pub mod provenance;
+pub mod redact;
+pub mod reshape;
+pub mod router;
+pub mod row_number;
+pub mod safety;
+pub mod scaffold;
+pub mod scd2;
 pub mod schema;
+pub mod schema_export;
 pub mod secrets;
+pub mod serve;
+pub mod staging;
+pub mod state;
+pub mod strict;
 pub mod transfer;
 pub mod transformer;
 pub mod yaml_config;
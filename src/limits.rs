@@ -0,0 +1,288 @@
+use tracing::warn;
+
+use crate::{
+    config::{Config, OversizePolicy},
+    dead_letter::DeadLetterFile,
+    schema::{Row, Value},
+    Result, TinyEtlError,
+};
+
+/// How many rows a batch lost or changed to the size limits
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LimitOutcome {
+    pub values_truncated: usize,
+    pub rows_dead_lettered: usize,
+}
+
+/// `--max-field-length` and `--max-row-bytes`, for sources that stop
+/// reading an oversized field or record instead of holding it whole
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SizeLimits {
+    pub max_field_length: Option<usize>,
+    pub max_row_bytes: Option<usize>,
+}
+
+impl SizeLimits {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            max_field_length: config.max_field_length,
+            max_row_bytes: config.max_row_bytes,
+        }
+    }
+
+    pub fn is_set(&self) -> bool {
+        self.max_field_length.is_some() || self.max_row_bytes.is_some()
+    }
+
+    /// Whether fields of these lengths go over a limit
+    pub fn exceeded_by(&self, field_lengths: impl Iterator<Item = usize>) -> bool {
+        let mut total = 0;
+        for length in field_lengths {
+            if self.max_field_length.is_some_and(|max| length > max) {
+                return true;
+            }
+            total += length;
+        }
+        self.max_row_bytes.is_some_and(|max| total > max)
+    }
+}
+
+/// `--max-field-length` and `--max-row-bytes`, checked on every row read
+pub struct RecordLimits {
+    max_field_length: Option<usize>,
+    max_row_bytes: Option<usize>,
+    policy: OversizePolicy,
+}
+
+impl RecordLimits {
+    /// Limits from the config; `None` when no limit is set
+    pub fn from_config(config: &Config) -> Result<Option<Self>> {
+        if config.max_field_length.is_none() && config.max_row_bytes.is_none() {
+            return Ok(None);
+        }
+        if config.on_oversize == OversizePolicy::DeadLetter && config.dead_letter_file.is_none() {
+            return Err(TinyEtlError::Configuration(
+                "--on-oversize deadletter requires --dead-letter-file".to_string(),
+            ));
+        }
+        Ok(Some(Self {
+            max_field_length: config.max_field_length,
+            max_row_bytes: config.max_row_bytes,
+            policy: config.on_oversize,
+        }))
+    }
+
+    /// Apply the limits to a batch: truncate, dead-letter or reject its
+    /// oversized rows depending on the policy
+    pub fn check_batch(
+        &self,
+        batch: &mut Vec<Row>,
+        mut dead_letter: Option<&mut DeadLetterFile>,
+    ) -> Result<LimitOutcome> {
+        let mut outcome = LimitOutcome::default();
+        let mut kept = Vec::with_capacity(batch.len());
+
+        for mut row in batch.drain(..) {
+            let Some(reason) = self.violation(&row) else {
+                kept.push(row);
+                continue;
+            };
+            match self.policy {
+                OversizePolicy::Error => return Err(TinyEtlError::DataValidation(reason)),
+                OversizePolicy::Truncate => {
+                    outcome.values_truncated += self.truncate(&mut row)?;
+                    kept.push(row);
+                }
+                OversizePolicy::DeadLetter => {
+                    warn!("→ Dead-lettering row: {}", reason);
+                    if let Some(file) = dead_letter.as_deref_mut() {
+                        file.write(&reason, None, &row)?;
+                    }
+                    outcome.rows_dead_lettered += 1;
+                }
+            }
+        }
+
+        *batch = kept;
+        Ok(outcome)
+    }
+
+    fn violation(&self, row: &Row) -> Option<String> {
+        if let Some(max) = self.max_field_length {
+            let oversized = row
+                .iter()
+                .map(|(column, value)| (column, value_bytes(value)))
+                .find(|(_, size)| *size > max);
+            if let Some((column, size)) = oversized {
+                return Some(format!(
+                    "field '{}' is {} bytes, over --max-field-length of {}",
+                    column, size, max
+                ));
+            }
+        }
+        if let Some(max) = self.max_row_bytes {
+            let size = row_bytes(row);
+            if size > max {
+                return Some(format!(
+                    "row is {} bytes, over --max-row-bytes of {}",
+                    size, max
+                ));
+            }
+        }
+        None
+    }
+
+    /// Cut text and binary values to the field limit, then the largest ones
+    /// until the row fits. Returns how many values were cut.
+    fn truncate(&self, row: &mut Row) -> Result<usize> {
+        let mut truncated = 0;
+        if let Some(max) = self.max_field_length {
+            for value in row.values_mut() {
+                if value_bytes(value) > max {
+                    truncate_value(value, max)?;
+                    truncated += 1;
+                }
+            }
+        }
+        if let Some(max) = self.max_row_bytes {
+            let mut size = row_bytes(row);
+            while size > max {
+                let largest = row
+                    .values_mut()
+                    .filter(|value| matches!(value, Value::String(_) | Value::Bytes(_)))
+                    .max_by_key(|value| value_bytes(value))
+                    .filter(|value| value_bytes(value) > 0);
+                let Some(value) = largest else {
+                    return Err(TinyEtlError::DataValidation(format!(
+                        "row is {} bytes, over --max-row-bytes of {}, and has no text left to truncate",
+                        size, max
+                    )));
+                };
+                let excess = size - max;
+                truncate_value(value, value_bytes(value).saturating_sub(excess))?;
+                truncated += 1;
+                size = row_bytes(row);
+            }
+        }
+        Ok(truncated)
+    }
+}
+
+/// Approximate in-memory size of a value
+fn value_bytes(value: &Value) -> usize {
+    match value {
+        Value::String(s) => s.len(),
+        Value::Bytes(b) => b.len(),
+        Value::Json(json) => json.to_string().len(),
+        Value::Null => 0,
+        _ => 8,
+    }
+}
+
+/// Approximate size of a row: its column names and values
+fn row_bytes(row: &Row) -> usize {
+    row.iter()
+        .map(|(column, value)| column.len() + value_bytes(value))
+        .sum()
+}
+
+fn truncate_value(value: &mut Value, max: usize) -> Result<()> {
+    match value {
+        Value::String(s) => {
+            let mut end = max.min(s.len());
+            while !s.is_char_boundary(end) {
+                end -= 1;
+            }
+            s.truncate(end);
+        }
+        Value::Bytes(b) => b.truncate(max),
+        _ => {
+            return Err(TinyEtlError::DataValidation(format!(
+                "only text and binary values can be truncated, not a {} byte {:?}",
+                value_bytes(value),
+                value.arrow_type()
+            )))
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limits(policy: OversizePolicy) -> RecordLimits {
+        let config = Config {
+            max_field_length: Some(10),
+            max_row_bytes: Some(40),
+            on_oversize: policy,
+            dead_letter_file: Some("rejected.jsonl".to_string()),
+            ..Default::default()
+        };
+        RecordLimits::from_config(&config).unwrap().unwrap()
+    }
+
+    fn rows() -> Vec<Row> {
+        vec![
+            Row::from([("note".to_string(), Value::String("short".to_string()))]),
+            Row::from([(
+                "note".to_string(),
+                Value::String("héllo wörld!".to_string()),
+            )]),
+            Row::from([
+                ("a".to_string(), Value::String("x".repeat(10))),
+                ("b".to_string(), Value::String("y".repeat(10))),
+                ("c".to_string(), Value::String("z".repeat(10))),
+                ("d".to_string(), Value::String("w".repeat(10))),
+            ]),
+        ]
+    }
+
+    #[test]
+    fn test_oversized_rows_fail_by_default() {
+        let mut batch = rows();
+        let err = limits(OversizePolicy::Error)
+            .check_batch(&mut batch, None)
+            .unwrap_err();
+        assert!(err.to_string().contains("field 'note'"));
+        assert!(RecordLimits::from_config(&Config::default())
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_truncate_cuts_fields_and_rows_to_the_limits() {
+        let mut batch = rows();
+        let outcome = limits(OversizePolicy::Truncate)
+            .check_batch(&mut batch, None)
+            .unwrap();
+        assert_eq!(batch.len(), 3);
+        assert_eq!(batch[1]["note"], Value::String("héllo wö".to_string()));
+        assert!(row_bytes(&batch[2]) <= 40);
+        assert_eq!(outcome.values_truncated, 2);
+    }
+
+    #[test]
+    fn test_dead_letter_rows_go_to_the_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("rejected.jsonl");
+        let mut file = DeadLetterFile::create(path.to_str().unwrap()).unwrap();
+
+        let mut batch = rows();
+        let outcome = limits(OversizePolicy::DeadLetter)
+            .check_batch(&mut batch, Some(&mut file))
+            .unwrap();
+        file.flush().unwrap();
+        assert_eq!(batch.len(), 1);
+        assert_eq!(outcome.rows_dead_lettered, 2);
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        let first: serde_json::Value =
+            serde_json::from_str(content.lines().next().unwrap()).unwrap();
+        assert!(first["reason"]
+            .as_str()
+            .unwrap()
+            .contains("--max-field-length"));
+        assert_eq!(first["row"]["note"], "héllo wörld!");
+    }
+}
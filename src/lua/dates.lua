@@ -0,0 +1,72 @@
+-- tinyetl.dates: helpers for ISO-8601 date strings (as produced for date columns)
+--
+--   local dates = require("tinyetl.dates")
+--   hire_year = dates.year(row.hire_date)
+
+local M = {}
+
+-- Parse "YYYY-MM-DD" with an optional "THH:MM:SS" / " HH:MM:SS" time part.
+-- Returns a table with year, month, day, hour, min, sec or nil if unparseable.
+function M.parse(s)
+  if s == nil then return nil end
+  local y, m, d, rest = tostring(s):match("^(%d%d%d%d)-(%d%d)-(%d%d)(.*)$")
+  if not y then return nil end
+  local hh, mi, ss = rest:match("^[T ](%d%d):(%d%d):?(%d?%d?)")
+  return {
+    year = tonumber(y),
+    month = tonumber(m),
+    day = tonumber(d),
+    hour = tonumber(hh) or 0,
+    min = tonumber(mi) or 0,
+    sec = tonumber(ss) or 0,
+  }
+end
+
+function M.year(s)
+  local t = M.parse(s)
+  return t and t.year
+end
+
+function M.month(s)
+  local t = M.parse(s)
+  return t and t.month
+end
+
+function M.day(s)
+  local t = M.parse(s)
+  return t and t.day
+end
+
+-- Date part ("YYYY-MM-DD") of a date or datetime string
+function M.to_date(s)
+  local t = M.parse(s)
+  if not t then return nil end
+  return string.format("%04d-%02d-%02d", t.year, t.month, t.day)
+end
+
+local function to_time(t)
+  -- Noon avoids DST edge cases when only the date part matters
+  return os.time({ year = t.year, month = t.month, day = t.day, hour = 12 })
+end
+
+function M.add_days(s, n)
+  local t = M.parse(s)
+  if not t then return nil end
+  return os.date("%Y-%m-%d", to_time(t) + n * 86400)
+end
+
+-- Whole days from a to b (negative if b is before a)
+function M.days_between(a, b)
+  local ta, tb = M.parse(a), M.parse(b)
+  if not ta or not tb then return nil end
+  return math.floor((to_time(tb) - to_time(ta)) / 86400 + 0.5)
+end
+
+-- Format a date string with os.date-style specifiers, e.g. "%d/%m/%Y"
+function M.format(s, fmt)
+  local t = M.parse(s)
+  if not t then return nil end
+  return os.date(fmt, os.time(t))
+end
+
+return M
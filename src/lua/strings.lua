@@ -0,0 +1,87 @@
+-- tinyetl.strings: common string cleansing helpers for transforms
+--
+--   local strings = require("tinyetl.strings")
+--   name = strings.title_case(strings.trim(row.name))
+
+local M = {}
+
+function M.trim(s)
+  if s == nil then return nil end
+  return (tostring(s):gsub("^%s+", ""):gsub("%s+$", ""))
+end
+
+function M.ltrim(s)
+  if s == nil then return nil end
+  return (tostring(s):gsub("^%s+", ""))
+end
+
+function M.rtrim(s)
+  if s == nil then return nil end
+  return (tostring(s):gsub("%s+$", ""))
+end
+
+function M.is_blank(s)
+  return s == nil or tostring(s):match("^%s*$") ~= nil
+end
+
+-- Collapse runs of whitespace into a single space and trim the ends
+function M.normalize_whitespace(s)
+  if s == nil then return nil end
+  return M.trim(tostring(s):gsub("%s+", " "))
+end
+
+-- Split on a plain (non-pattern) separator, keeping empty fields
+function M.split(s, sep)
+  local parts = {}
+  if s == nil then return parts end
+  s = tostring(s)
+  sep = sep or ","
+  local start = 1
+  while true do
+    local i, j = s:find(sep, start, true)
+    if not i then
+      table.insert(parts, s:sub(start))
+      break
+    end
+    table.insert(parts, s:sub(start, i - 1))
+    start = j + 1
+  end
+  return parts
+end
+
+function M.starts_with(s, prefix)
+  return s ~= nil and tostring(s):sub(1, #prefix) == prefix
+end
+
+function M.ends_with(s, suffix)
+  return s ~= nil and (suffix == "" or tostring(s):sub(-#suffix) == suffix)
+end
+
+function M.pad_left(s, width, char)
+  s = tostring(s or "")
+  char = char or " "
+  if #s >= width then return s end
+  return string.rep(char, width - #s) .. s
+end
+
+function M.pad_right(s, width, char)
+  s = tostring(s or "")
+  char = char or " "
+  if #s >= width then return s end
+  return s .. string.rep(char, width - #s)
+end
+
+function M.title_case(s)
+  if s == nil then return nil end
+  return (tostring(s):lower():gsub("(%a)([%w']*)", function(first, rest)
+    return first:upper() .. rest
+  end))
+end
+
+-- Keep only the digits of a value, e.g. for phone numbers
+function M.digits_only(s)
+  if s == nil then return nil end
+  return (tostring(s):gsub("%D", ""))
+end
+
+return M
@@ -0,0 +1,269 @@
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::{
+    schema::{DataType, Row, Schema, Value},
+    Result, TinyEtlError,
+};
+
+/// Column key standing for every non-nullable column without its own policy
+pub const ALL_COLUMNS: &str = "*";
+
+/// Value written instead of NULL into a column the target doesn't allow
+/// NULLs in, for legacy tables with NOT NULL text columns
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum NullFallback {
+    /// `''` for text columns
+    Empty,
+    /// `0` for numeric columns, `'0'` for text columns
+    Zero,
+    /// A sentinel value, converted to the column type (`value:N/A`)
+    Value(String),
+}
+
+impl FromStr for NullFallback {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "empty" => Ok(NullFallback::Empty),
+            "zero" => Ok(NullFallback::Zero),
+            _ => match s.strip_prefix("value:") {
+                Some(value) => Ok(NullFallback::Value(value.to_string())),
+                None => Err(format!(
+                    "Invalid null fallback '{}'. Valid values: empty, zero, value:<TEXT>",
+                    s
+                )),
+            },
+        }
+    }
+}
+
+impl fmt::Display for NullFallback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NullFallback::Empty => write!(f, "empty"),
+            NullFallback::Zero => write!(f, "zero"),
+            NullFallback::Value(value) => write!(f, "value:{}", value),
+        }
+    }
+}
+
+impl TryFrom<String> for NullFallback {
+    type Error = String;
+
+    fn try_from(s: String) -> std::result::Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<NullFallback> for String {
+    fn from(fallback: NullFallback) -> Self {
+        fallback.to_string()
+    }
+}
+
+/// `null_fallback:` in YAML: one policy for every non-nullable column, or
+/// policies by column (`"*"` covering the others)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum NullFallbackConfig {
+    All(NullFallback),
+    Columns(BTreeMap<String, NullFallback>),
+}
+
+impl NullFallbackConfig {
+    /// YAML form of the policies by column, `None` when there are none
+    pub fn from_columns(columns: BTreeMap<String, NullFallback>) -> Option<Self> {
+        if columns.is_empty() {
+            return None;
+        }
+        match columns.get(ALL_COLUMNS) {
+            Some(fallback) if columns.len() == 1 => Some(NullFallbackConfig::All(fallback.clone())),
+            _ => Some(NullFallbackConfig::Columns(columns)),
+        }
+    }
+
+    pub fn into_columns(self) -> BTreeMap<String, NullFallback> {
+        match self {
+            NullFallbackConfig::All(fallback) => {
+                BTreeMap::from([(ALL_COLUMNS.to_string(), fallback)])
+            }
+            NullFallbackConfig::Columns(columns) => columns,
+        }
+    }
+}
+
+/// Parse `--null-fallback`: `status=empty` for one column, or a bare
+/// policy for every non-nullable column
+pub fn parse_column_fallback(s: &str) -> std::result::Result<(String, NullFallback), String> {
+    match s.split_once('=') {
+        Some((column, fallback)) if !s.starts_with("value:") => {
+            Ok((column.trim().to_string(), fallback.parse()?))
+        }
+        _ => Ok((ALL_COLUMNS.to_string(), s.parse()?)),
+    }
+}
+
+/// Replaces NULLs with the configured fallback values, resolved for the
+/// column types of the output schema
+pub struct NullFiller {
+    values: Vec<(String, Value)>,
+}
+
+impl NullFiller {
+    /// Resolve the policies against `schema`; `None` when none apply.
+    /// Named columns must exist and fit their policy, and `"*"` covers the
+    /// columns declared `nullable: false`.
+    pub fn new(columns: &BTreeMap<String, NullFallback>, schema: &Schema) -> Result<Option<Self>> {
+        let mut values = Vec::new();
+        for column in &schema.columns {
+            let value = match (columns.get(&column.name), columns.get(ALL_COLUMNS)) {
+                (Some(fallback), _) => {
+                    Self::fallback_value(fallback, &column.data_type, &column.name)?
+                }
+                // `"*"` skips the columns its value doesn't fit, e.g. `empty`
+                // for a NOT NULL id column
+                (None, Some(fallback)) if !column.nullable => {
+                    match Self::fallback_value(fallback, &column.data_type, &column.name) {
+                        Ok(value) => value,
+                        Err(_) => continue,
+                    }
+                }
+                _ => continue,
+            };
+            values.push((column.name.clone(), value));
+        }
+
+        if let Some(missing) = columns
+            .keys()
+            .find(|name| *name != ALL_COLUMNS && !schema.columns.iter().any(|c| &c.name == *name))
+        {
+            return Err(TinyEtlError::Configuration(format!(
+                "Null fallback column '{}' is not in the output schema",
+                missing
+            )));
+        }
+        Ok((!values.is_empty()).then_some(Self { values }))
+    }
+
+    fn fallback_value(
+        fallback: &NullFallback,
+        data_type: &DataType,
+        column: &str,
+    ) -> Result<Value> {
+        let invalid = || {
+            TinyEtlError::Configuration(format!(
+                "Null fallback '{}' doesn't fit column '{}' of type {:?}",
+                fallback, column, data_type
+            ))
+        };
+        Ok(match (fallback, data_type) {
+            (NullFallback::Empty, DataType::String) => Value::String(String::new()),
+            (NullFallback::Zero, DataType::String) => Value::String("0".to_string()),
+            (NullFallback::Zero, DataType::Integer) => Value::Integer(0),
+            (NullFallback::Zero, DataType::Decimal) => Value::Decimal(Decimal::ZERO),
+            (NullFallback::Value(value), DataType::String) => Value::String(value.clone()),
+            (NullFallback::Value(value), DataType::Integer) => {
+                Value::Integer(value.parse().map_err(|_| invalid())?)
+            }
+            (NullFallback::Value(value), DataType::Decimal) => {
+                Value::Decimal(value.parse().map_err(|_| invalid())?)
+            }
+            (NullFallback::Value(value), DataType::Boolean) => {
+                Value::Boolean(value.parse().map_err(|_| invalid())?)
+            }
+            _ => return Err(invalid()),
+        })
+    }
+
+    /// Replace the NULL and missing values of the covered columns, returning
+    /// how many were replaced
+    pub fn fill(&self, rows: &mut [Row]) -> usize {
+        let mut replaced = 0;
+        for row in rows {
+            for (column, fallback) in &self.values {
+                let value = row.entry(column.clone()).or_insert(Value::Null);
+                if matches!(value, Value::Null) {
+                    *value = fallback.clone();
+                    replaced += 1;
+                }
+            }
+        }
+        replaced
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::Column;
+
+    fn column(name: &str, data_type: DataType, nullable: bool) -> Column {
+        Column {
+            name: name.to_string(),
+            data_type,
+            nullable,
+        }
+    }
+
+    #[test]
+    fn test_parse_null_fallbacks() {
+        assert_eq!(
+            parse_column_fallback("empty").unwrap(),
+            ("*".to_string(), NullFallback::Empty)
+        );
+        assert_eq!(
+            parse_column_fallback("code=value:N/A").unwrap(),
+            ("code".to_string(), NullFallback::Value("N/A".to_string()))
+        );
+        assert_eq!(
+            parse_column_fallback("value:a=b").unwrap(),
+            ("*".to_string(), NullFallback::Value("a=b".to_string()))
+        );
+        assert!(parse_column_fallback("qty=nothing").is_err());
+
+        let yaml: NullFallbackConfig = serde_yaml::from_str("{qty: zero, '*': empty}").unwrap();
+        assert_eq!(yaml.into_columns().len(), 2);
+        let yaml: NullFallbackConfig = serde_yaml::from_str("empty").unwrap();
+        assert_eq!(yaml, NullFallbackConfig::All(NullFallback::Empty));
+    }
+
+    #[test]
+    fn test_nulls_are_replaced_in_covered_columns() {
+        let schema = Schema {
+            columns: vec![
+                column("name", DataType::String, false),
+                column("note", DataType::String, true),
+                column("qty", DataType::Integer, true),
+            ],
+            estimated_rows: None,
+            primary_key_candidate: None,
+        };
+        let columns = BTreeMap::from([
+            ("*".to_string(), NullFallback::Empty),
+            ("qty".to_string(), NullFallback::Zero),
+        ]);
+        let filler = NullFiller::new(&columns, &schema).unwrap().unwrap();
+
+        let mut rows = vec![Row::from([
+            ("name".to_string(), Value::Null),
+            ("note".to_string(), Value::Null),
+        ])];
+        assert_eq!(filler.fill(&mut rows), 2);
+        assert_eq!(rows[0]["name"], Value::String(String::new()));
+        assert_eq!(rows[0]["note"], Value::Null);
+        assert_eq!(rows[0]["qty"], Value::Integer(0));
+
+        let zero_text = BTreeMap::from([("name".to_string(), NullFallback::Zero)]);
+        assert!(NullFiller::new(&zero_text, &schema).is_ok());
+        let wrong_type = BTreeMap::from([("qty".to_string(), NullFallback::Empty)]);
+        assert!(NullFiller::new(&wrong_type, &schema).is_err());
+        let unknown = BTreeMap::from([("missing".to_string(), NullFallback::Empty)]);
+        assert!(NullFiller::new(&unknown, &schema).is_err());
+    }
+}
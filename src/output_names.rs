@@ -0,0 +1,310 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+
+use crate::{
+    connectors::Target,
+    schema::{Row, Schema},
+    Result, TinyEtlError,
+};
+
+/// Casing applied to the column names written to the target
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum HeaderCase {
+    /// Keep the names as they are
+    #[default]
+    Preserve,
+    /// `order_id`
+    Snake,
+    /// `orderId`
+    Camel,
+    /// `ORDER_ID`
+    Upper,
+}
+
+impl std::fmt::Display for HeaderCase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HeaderCase::Preserve => write!(f, "preserve"),
+            HeaderCase::Snake => write!(f, "snake"),
+            HeaderCase::Camel => write!(f, "camel"),
+            HeaderCase::Upper => write!(f, "upper"),
+        }
+    }
+}
+
+impl std::str::FromStr for HeaderCase {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "preserve" => Ok(HeaderCase::Preserve),
+            "snake" => Ok(HeaderCase::Snake),
+            "camel" => Ok(HeaderCase::Camel),
+            "upper" => Ok(HeaderCase::Upper),
+            _ => Err("Invalid header case. Valid values: snake, camel, upper, preserve"),
+        }
+    }
+}
+
+impl HeaderCase {
+    pub fn apply(&self, name: &str) -> String {
+        let words = split_words(name);
+        match self {
+            HeaderCase::Preserve => name.to_string(),
+            HeaderCase::Snake => words
+                .iter()
+                .map(|word| word.to_lowercase())
+                .collect::<Vec<_>>()
+                .join("_"),
+            HeaderCase::Upper => words
+                .iter()
+                .map(|word| word.to_uppercase())
+                .collect::<Vec<_>>()
+                .join("_"),
+            HeaderCase::Camel => words
+                .iter()
+                .enumerate()
+                .map(|(index, word)| {
+                    let word = word.to_lowercase();
+                    let mut chars = word.chars();
+                    match chars.next() {
+                        Some(first) if index > 0 => first.to_uppercase().chain(chars).collect(),
+                        _ => word,
+                    }
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Split a column name into words at separators and case changes, so
+/// `Order ID`, `orderId`, `order-id` and `HTTPStatus` all have their words
+fn split_words(name: &str) -> Vec<String> {
+    let chars: Vec<char> = name.chars().collect();
+    let mut words = Vec::new();
+    let mut word = String::new();
+    for (index, &c) in chars.iter().enumerate() {
+        if !c.is_alphanumeric() {
+            if !word.is_empty() {
+                words.push(std::mem::take(&mut word));
+            }
+            continue;
+        }
+        if let Some(previous) = word.chars().last() {
+            let next_is_lower = chars.get(index + 1).is_some_and(|n| n.is_lowercase());
+            // `orderId` and the `S` of `HTTPStatus` start a new word
+            if c.is_uppercase()
+                && (previous.is_lowercase()
+                    || previous.is_ascii_digit()
+                    || (previous.is_uppercase() && next_is_lower))
+            {
+                words.push(std::mem::take(&mut word));
+            }
+        }
+        word.push(c);
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+/// Names the output columns get in the target. Everything before the
+/// target (transforms, filters, validation) keeps using the source names.
+#[derive(Debug, Clone)]
+pub struct OutputNames {
+    /// Source name to target name, for the columns whose name changes
+    renames: Vec<(String, String)>,
+}
+
+impl OutputNames {
+    /// Resolve the names of the `schema` columns: a `renames` entry is used
+    /// verbatim, the other columns get `case`. `None` when no name changes.
+    pub fn new(
+        case: HeaderCase,
+        renames: &BTreeMap<String, String>,
+        schema: &Schema,
+    ) -> Result<Option<Self>> {
+        if let Some(missing) = renames
+            .keys()
+            .find(|name| !schema.columns.iter().any(|c| &c.name == *name))
+        {
+            return Err(TinyEtlError::Configuration(format!(
+                "Renamed column '{}' is not in the output schema",
+                missing
+            )));
+        }
+
+        let mut seen = HashSet::new();
+        let mut changed = Vec::new();
+        for column in &schema.columns {
+            let name = match renames.get(&column.name) {
+                Some(name) => name.clone(),
+                None => case.apply(&column.name),
+            };
+            if !seen.insert(name.clone()) {
+                return Err(TinyEtlError::Configuration(format!(
+                    "More than one output column would be named '{}'",
+                    name
+                )));
+            }
+            if name != column.name {
+                changed.push((column.name.clone(), name));
+            }
+        }
+        Ok((!changed.is_empty()).then_some(Self { renames: changed }))
+    }
+
+    pub fn rename_schema(&self, schema: &Schema) -> Schema {
+        let mut schema = schema.clone();
+        for column in &mut schema.columns {
+            if let Some((_, name)) = self.renames.iter().find(|(from, _)| from == &column.name) {
+                column.name = name.clone();
+            }
+        }
+        schema
+    }
+
+    pub fn rename_row(&self, mut row: Row) -> Row {
+        let values: Vec<_> = self
+            .renames
+            .iter()
+            .map(|(from, to)| (to, row.remove(from)))
+            .collect();
+        for (to, value) in values {
+            if let Some(value) = value {
+                row.insert(to.clone(), value);
+            }
+        }
+        row
+    }
+}
+
+/// Target writing the columns under their output names
+pub struct RenamedTarget {
+    inner: Box<dyn Target>,
+    names: OutputNames,
+}
+
+impl RenamedTarget {
+    pub fn new(inner: Box<dyn Target>, names: OutputNames) -> Self {
+        Self { inner, names }
+    }
+}
+
+#[async_trait]
+impl Target for RenamedTarget {
+    async fn connect(&mut self) -> Result<()> {
+        self.inner.connect().await
+    }
+
+    fn bytes_written(&self) -> Option<u64> {
+        self.inner.bytes_written()
+    }
+
+    async fn create_table(&mut self, table_name: &str, schema: &Schema) -> Result<()> {
+        let schema = self.names.rename_schema(schema);
+        self.inner.create_table(table_name, &schema).await
+    }
+
+    async fn write_batch(&mut self, rows: &[Row]) -> Result<usize> {
+        let rows: Vec<Row> = rows
+            .iter()
+            .map(|row| self.names.rename_row(row.clone()))
+            .collect();
+        self.inner.write_batch(&rows).await
+    }
+
+    fn use_atomic_batches(&mut self) -> Result<()> {
+        self.inner.use_atomic_batches()
+    }
+
+    async fn finalize(&mut self) -> Result<()> {
+        self.inner.finalize().await
+    }
+
+    async fn exists(&self, table_name: &str) -> Result<bool> {
+        self.inner.exists(table_name).await
+    }
+
+    async fn truncate(&mut self, table_name: &str) -> Result<()> {
+        self.inner.truncate(table_name).await
+    }
+
+    async fn drop_table(&mut self, table_name: &str) -> Result<()> {
+        self.inner.drop_table(table_name).await
+    }
+
+    async fn analyze(&mut self, table_name: &str) -> Result<()> {
+        self.inner.analyze(table_name).await
+    }
+
+    fn supports_append(&self) -> bool {
+        self.inner.supports_append()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::{Column, DataType, Value};
+
+    fn schema(names: &[&str]) -> Schema {
+        Schema {
+            columns: names
+                .iter()
+                .map(|name| Column {
+                    name: name.to_string(),
+                    data_type: DataType::String,
+                    nullable: true,
+                })
+                .collect(),
+            estimated_rows: None,
+            primary_key_candidate: None,
+        }
+    }
+
+    #[test]
+    fn test_header_cases() {
+        for name in ["orderId", "Order ID", "order-id", "ORDER_ID", "order_id"] {
+            assert_eq!(HeaderCase::Snake.apply(name), "order_id");
+            assert_eq!(HeaderCase::Upper.apply(name), "ORDER_ID");
+            assert_eq!(HeaderCase::Camel.apply(name), "orderId");
+        }
+        assert_eq!(HeaderCase::Upper.apply("HTTPStatus2xx"), "HTTP_STATUS2XX");
+        assert_eq!(HeaderCase::Preserve.apply("Order ID"), "Order ID");
+        assert!("kebab".parse::<HeaderCase>().is_err());
+    }
+
+    #[test]
+    fn test_renames_take_precedence_over_case() {
+        let orders = schema(&["id", "custName", "total"]);
+        let renames = BTreeMap::from([("id".to_string(), "ORDER_NO".to_string())]);
+        let names = OutputNames::new(HeaderCase::Upper, &renames, &orders)
+            .unwrap()
+            .unwrap();
+
+        let renamed = names.rename_schema(&orders);
+        let columns: Vec<_> = renamed.columns.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(columns, ["ORDER_NO", "CUST_NAME", "TOTAL"]);
+
+        let row = names.rename_row(Row::from([
+            ("id".to_string(), Value::Integer(1)),
+            ("custName".to_string(), Value::String("ann".to_string())),
+        ]));
+        assert_eq!(row["ORDER_NO"], Value::Integer(1));
+        assert!(!row.contains_key("id"));
+
+        assert!(
+            OutputNames::new(HeaderCase::Preserve, &BTreeMap::new(), &orders)
+                .unwrap()
+                .is_none()
+        );
+        let unknown = BTreeMap::from([("missing".to_string(), "X".to_string())]);
+        assert!(OutputNames::new(HeaderCase::Upper, &unknown, &orders).is_err());
+        let clash = schema(&["orderId", "order_id"]);
+        assert!(OutputNames::new(HeaderCase::Snake, &BTreeMap::new(), &clash).is_err());
+    }
+}
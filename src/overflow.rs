@@ -0,0 +1,111 @@
+use rust_decimal::prelude::ToPrimitive;
+
+use crate::{
+    schema::{DataType, Row, Schema, Value},
+    Result, TinyEtlError,
+};
+
+/// Checks that the values of the integer columns of the output schema fit
+/// the 64-bit integers every target writes them as. Sources hand over wider
+/// numbers (unsigned BIGINT, HUGEINT, big decimals) unchanged rather than
+/// wrapped, so they are caught here instead of turning into wrong values or
+/// NULLs in the target.
+pub struct IntegerRangeCheck {
+    columns: Vec<String>,
+}
+
+impl IntegerRangeCheck {
+    /// `None` when the schema has no integer columns
+    pub fn new(schema: &Schema) -> Option<Self> {
+        let columns: Vec<String> = schema
+            .columns
+            .iter()
+            .filter(|column| column.data_type == DataType::Integer)
+            .map(|column| column.name.clone())
+            .collect();
+        (!columns.is_empty()).then_some(Self { columns })
+    }
+
+    /// Turn whole decimals in integer columns into integers, failing for
+    /// numbers outside the i64 range
+    pub fn check_row(&self, row: &mut Row) -> Result<()> {
+        for column in &self.columns {
+            let Some(value) = row.get_mut(column) else {
+                continue;
+            };
+            match value {
+                Value::Decimal(d) if d.fract().is_zero() => match d.to_i64() {
+                    Some(i) => *value = Value::Integer(i),
+                    None => return Err(overflow(column, &d.to_string())),
+                },
+                Value::String(s) if is_integer_text(s) && s.trim().parse::<i64>().is_err() => {
+                    return Err(overflow(column, s.trim()));
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+fn is_integer_text(s: &str) -> bool {
+    let s = s.trim();
+    let digits = s.strip_prefix(['-', '+']).unwrap_or(s);
+    !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit())
+}
+
+fn overflow(column: &str, value: &str) -> TinyEtlError {
+    TinyEtlError::DataValidation(format!(
+        "Column '{}' value {} overflows a 64-bit integer ({} to {})",
+        column,
+        value,
+        i64::MIN,
+        i64::MAX
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::Column;
+    use rust_decimal::Decimal;
+
+    #[test]
+    fn test_wide_integers_are_caught() {
+        let schema = Schema {
+            columns: vec![
+                Column {
+                    name: "id".to_string(),
+                    data_type: DataType::Integer,
+                    nullable: false,
+                },
+                Column {
+                    name: "amount".to_string(),
+                    data_type: DataType::Decimal,
+                    nullable: true,
+                },
+            ],
+            estimated_rows: None,
+            primary_key_candidate: None,
+        };
+        let check = IntegerRangeCheck::new(&schema).unwrap();
+
+        let mut row = Row::from([
+            ("id".to_string(), Value::Decimal(Decimal::from(42))),
+            (
+                "amount".to_string(),
+                Value::from_wide_integer(u64::MAX.into()),
+            ),
+        ]);
+        check.check_row(&mut row).unwrap();
+        assert_eq!(row["id"], Value::Integer(42));
+
+        let mut row = Row::from([("id".to_string(), Value::from_wide_integer(u64::MAX.into()))]);
+        let err = check.check_row(&mut row).unwrap_err().to_string();
+        assert!(err.contains("'id' value 18446744073709551615 overflows"));
+
+        let mut row = Row::from([("id".to_string(), Value::from_wide_integer(i128::MAX))]);
+        assert!(matches!(row["id"], Value::String(_)));
+        assert!(check.check_row(&mut row).is_err());
+    }
+}
@@ -0,0 +1,401 @@
+//! Stages of a `--parallelism` transfer: a reader that reads ahead of the
+//! writer, and a pool of Lua workers transforming several batches at once.
+//! Batches leave both stages in the order they were read.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::{mpsc as std_mpsc, Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    connectors::Source,
+    schema::Row,
+    transformer::{TransformConfig, TransformOptions, Transformer},
+    Result, TinyEtlError,
+};
+
+/// Rows read from the source, with the source's progress after reading them
+pub struct SourceBatch {
+    pub rows: Vec<Row>,
+    pub refined_row_count: Option<usize>,
+    pub bytes_read: Option<u64>,
+}
+
+/// Read the next batch, giving up once it takes longer than `timeout` seconds.
+/// Dropping the read cancels it for sources without a server-side timeout.
+pub async fn read_with_timeout(
+    source: &mut dyn Source,
+    batch_size: usize,
+    timeout: Option<u64>,
+) -> Result<Vec<Row>> {
+    let Some(seconds) = timeout else {
+        return source.read_batch(batch_size).await;
+    };
+    tokio::time::timeout(Duration::from_secs(seconds), source.read_batch(batch_size))
+        .await
+        .map_err(|_| {
+            TinyEtlError::DataTransfer(format!(
+                "Source read did not finish within --batch-timeout of {}s",
+                seconds
+            ))
+        })?
+}
+
+/// Where the engine gets its batches from: the source itself, or a task
+/// reading up to `depth` batches ahead of it
+pub enum BatchReader {
+    Inline {
+        source: Box<dyn Source>,
+        timeout: Option<u64>,
+    },
+    Prefetch {
+        batches: mpsc::Receiver<Result<SourceBatch>>,
+        task: JoinHandle<Box<dyn Source>>,
+        done: bool,
+    },
+}
+
+impl BatchReader {
+    pub fn inline(source: Box<dyn Source>, timeout: Option<u64>) -> Self {
+        BatchReader::Inline { source, timeout }
+    }
+
+    /// Read `batch_size` rows at a time on a separate task, and no more than
+    /// `limit` rows in all, so a source that acknowledges what it read (e.g.
+    /// Kafka offsets) never runs past `--limit`. It stops reading once
+    /// `cancel` fires, but batches it already read are still returned.
+    pub fn prefetch(
+        mut source: Box<dyn Source>,
+        batch_size: usize,
+        limit: Option<usize>,
+        timeout: Option<u64>,
+        depth: usize,
+        cancel: CancellationToken,
+    ) -> Self {
+        let (sender, batches) = mpsc::channel(depth.max(1));
+        let task = tokio::spawn(async move {
+            let mut remaining = limit.unwrap_or(usize::MAX);
+            while remaining > 0 && source.has_more() && !cancel.is_cancelled() {
+                let size = batch_size.min(remaining);
+                let read = read_with_timeout(source.as_mut(), size, timeout).await;
+                if let Ok(rows) = &read {
+                    remaining = remaining.saturating_sub(rows.len());
+                }
+                let batch = read.map(|rows| SourceBatch {
+                    rows,
+                    refined_row_count: source.refined_row_count(),
+                    bytes_read: source.bytes_read(),
+                });
+                let last = !matches!(&batch, Ok(batch) if !batch.rows.is_empty());
+                if sender.send(batch).await.is_err() || last {
+                    break;
+                }
+            }
+            source
+        });
+        BatchReader::Prefetch {
+            batches,
+            task,
+            done: false,
+        }
+    }
+
+    /// Whether reading stops as soon as the transfer is cancelled; a
+    /// prefetching reader hands over what it read ahead first
+    pub fn stops_on_cancel(&self) -> bool {
+        matches!(self, BatchReader::Inline { .. })
+    }
+
+    pub fn has_more(&self) -> bool {
+        match self {
+            BatchReader::Inline { source, .. } => source.has_more(),
+            BatchReader::Prefetch { done, .. } => !done,
+        }
+    }
+
+    /// The next batch; `None` or an empty batch once the source is exhausted.
+    /// `batch_size` only applies to inline reads.
+    pub async fn next(&mut self, batch_size: usize) -> Result<Option<SourceBatch>> {
+        match self {
+            BatchReader::Inline { source, timeout } => {
+                let rows = read_with_timeout(source.as_mut(), batch_size, *timeout).await?;
+                Ok(Some(SourceBatch {
+                    rows,
+                    refined_row_count: source.refined_row_count(),
+                    bytes_read: source.bytes_read(),
+                }))
+            }
+            BatchReader::Prefetch { batches, done, .. } => {
+                let batch = batches.recv().await.transpose()?;
+                *done = batch.as_ref().map_or(true, |batch| batch.rows.is_empty());
+                Ok(batch)
+            }
+        }
+    }
+
+    /// Stop reading and get the source back, e.g. to commit it
+    pub async fn into_source(self) -> Result<Box<dyn Source>> {
+        match self {
+            BatchReader::Inline { source, .. } => Ok(source),
+            BatchReader::Prefetch { batches, task, .. } => {
+                // A reader waiting for room in the channel stops once it closes
+                drop(batches);
+                task.await.map_err(|e| {
+                    TinyEtlError::DataTransfer(format!("Source reader task failed: {}", e))
+                })
+            }
+        }
+    }
+}
+
+/// Result of transforming a batch; a failure hands back the input rows so
+/// they can be reported
+pub type Transformed = std::result::Result<Vec<Row>, (TinyEtlError, Vec<Row>)>;
+
+/// Batches between reading and writing, along with what the engine keeps
+/// about each (`T`). Without workers a batch is transformed as it is
+/// submitted; with them, up to `depth` batches are transformed at once.
+pub struct TransformStage<T> {
+    pool: Option<TransformPool>,
+    queue: VecDeque<(Option<Transformed>, T)>,
+    depth: usize,
+}
+
+impl<T> TransformStage<T> {
+    /// Transform batches on the engine's own transformer
+    pub fn inline() -> Self {
+        Self {
+            pool: None,
+            queue: VecDeque::new(),
+            depth: 1,
+        }
+    }
+
+    /// Transform batches on `workers` threads, each running its own copy of
+    /// the Lua script
+    pub fn workers(
+        config: &TransformConfig,
+        options: &TransformOptions,
+        workers: usize,
+    ) -> Result<Self> {
+        Ok(Self {
+            pool: Some(TransformPool::start(config, options, workers)?),
+            queue: VecDeque::new(),
+            // Workers keep busy while the engine writes the oldest batch
+            depth: workers * 2,
+        })
+    }
+
+    /// Whether another batch can be submitted before the oldest is taken
+    pub fn has_room(&self) -> bool {
+        self.queue.len() < self.depth
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    pub fn submit(&mut self, transformer: &mut Transformer, rows: Vec<Row>, info: T) -> Result<()> {
+        if !transformer.is_enabled() {
+            self.queue.push_back((Some(Ok(rows)), info));
+            return Ok(());
+        }
+        match self.pool.as_mut() {
+            Some(pool) => {
+                pool.submit(rows)?;
+                self.queue.push_back((None, info));
+            }
+            None => {
+                let transformed = match transformer.transform_batch(&rows) {
+                    Ok(transformed) => Ok(transformed),
+                    Err(e) => Err((e, rows)),
+                };
+                self.queue.push_back((Some(transformed), info));
+            }
+        }
+        Ok(())
+    }
+
+    /// The oldest batch submitted, once transformed
+    pub async fn next(&mut self) -> Result<Option<(Transformed, T)>> {
+        let Some((transformed, info)) = self.queue.pop_front() else {
+            return Ok(None);
+        };
+        let transformed = match (transformed, self.pool.as_mut()) {
+            (Some(transformed), _) => transformed,
+            (None, Some(pool)) => pool.next().await?,
+            (None, None) => unreachable!("batches are only pending with workers"),
+        };
+        Ok(Some((transformed, info)))
+    }
+}
+
+type Job = (u64, Vec<Row>);
+
+/// Worker threads with a Lua state each; `Transformer` can't leave the
+/// thread it was created on
+struct TransformPool {
+    jobs: std_mpsc::Sender<Job>,
+    results: mpsc::UnboundedReceiver<(u64, Transformed)>,
+    /// Results that finished before those of earlier batches
+    early: BTreeMap<u64, Transformed>,
+    submitted: u64,
+    taken: u64,
+}
+
+impl TransformPool {
+    fn start(config: &TransformConfig, options: &TransformOptions, workers: usize) -> Result<Self> {
+        // The stage's depth bounds the jobs waiting here
+        let (jobs, job_receiver) = std_mpsc::channel::<Job>();
+        let job_receiver = Arc::new(Mutex::new(job_receiver));
+        let (result_sender, results) = mpsc::unbounded_channel();
+        let (ready_sender, ready) = std_mpsc::channel();
+
+        for _ in 0..workers {
+            let config = config.clone();
+            let options = options.clone();
+            let job_receiver = Arc::clone(&job_receiver);
+            let result_sender = result_sender.clone();
+            let ready_sender = ready_sender.clone();
+            std::thread::spawn(move || {
+                let mut transformer = match Transformer::with_options(&config, &options) {
+                    Ok(transformer) => {
+                        let _ = ready_sender.send(Ok(()));
+                        transformer
+                    }
+                    Err(e) => {
+                        let _ = ready_sender.send(Err(e));
+                        return;
+                    }
+                };
+                loop {
+                    let job = job_receiver.lock().map(|receiver| receiver.recv());
+                    let Ok(Ok((seq, rows))) = job else {
+                        break;
+                    };
+                    let transformed =
+                        catch_unwind(AssertUnwindSafe(|| transformer.transform_batch(&rows)))
+                            .unwrap_or_else(|_| {
+                                Err(TinyEtlError::Transform(
+                                    "Transform worker panicked".to_string(),
+                                ))
+                            });
+                    let transformed = transformed.map_err(|e| (e, rows));
+                    if result_sender.send((seq, transformed)).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(ready_sender);
+        for started in ready.iter().take(workers) {
+            started?;
+        }
+
+        Ok(Self {
+            jobs,
+            results,
+            early: BTreeMap::new(),
+            submitted: 0,
+            taken: 0,
+        })
+    }
+
+    fn submit(&mut self, rows: Vec<Row>) -> Result<()> {
+        self.jobs
+            .send((self.submitted, rows))
+            .map_err(|_| TinyEtlError::Transform("Transform workers have stopped".to_string()))?;
+        self.submitted += 1;
+        Ok(())
+    }
+
+    /// Result of the oldest batch not taken yet
+    async fn next(&mut self) -> Result<Transformed> {
+        loop {
+            if let Some(transformed) = self.early.remove(&self.taken) {
+                self.taken += 1;
+                return Ok(transformed);
+            }
+            match self.results.recv().await {
+                Some((seq, transformed)) => {
+                    self.early.insert(seq, transformed);
+                }
+                None => {
+                    return Err(TinyEtlError::Transform(
+                        "Transform workers have stopped".to_string(),
+                    ))
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connectors::csv::CsvSource;
+    use crate::schema::Value;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    #[tokio::test]
+    async fn test_prefetch_stops_at_limit() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "id").unwrap();
+        for id in 0..100 {
+            writeln!(file, "{}", id).unwrap();
+        }
+        let mut source = CsvSource::new(file.path().to_str().unwrap()).unwrap();
+        source.connect().await.unwrap();
+
+        let cancel = CancellationToken::new();
+        let mut reader = BatchReader::prefetch(Box::new(source), 15, Some(40), None, 4, cancel);
+        let mut read = 0;
+        while let Some(batch) = reader.next(15).await.unwrap() {
+            if batch.rows.is_empty() {
+                break;
+            }
+            read += batch.rows.len();
+        }
+        assert_eq!(read, 40);
+        // The source itself was never asked for more than the limit
+        let source = reader.into_source().await.unwrap();
+        assert!(source.has_more());
+    }
+
+    #[tokio::test]
+    async fn test_workers_keep_batch_order() {
+        let config = TransformConfig::Script("doubled = row.id * 2".to_string());
+        let mut transformer = Transformer::new(&config).unwrap();
+        let mut stage = TransformStage::workers(&config, &TransformOptions::default(), 3).unwrap();
+
+        let batch = |first: i64| -> Vec<Row> {
+            (first..first + 50)
+                .map(|id| Row::from([("id".to_string(), Value::Integer(id))]))
+                .collect()
+        };
+        let mut submitted = 0;
+        let mut first_ids = Vec::new();
+        for n in 0..20 {
+            while !stage.has_room() {
+                let (rows, _) = stage.next().await.unwrap().unwrap();
+                first_ids.push(rows.unwrap()[0]["id"].clone());
+            }
+            stage.submit(&mut transformer, batch(n * 50), n).unwrap();
+            submitted += 1;
+        }
+        while let Some((rows, n)) = stage.next().await.unwrap() {
+            let rows = rows.unwrap();
+            assert_eq!(rows[49]["doubled"], Value::Integer((n * 50 + 49) * 2));
+            first_ids.push(rows[0]["id"].clone());
+        }
+
+        assert_eq!(first_ids.len(), submitted);
+        let expected: Vec<Value> = (0..20).map(|n| Value::Integer(n * 50)).collect();
+        assert_eq!(first_ids, expected);
+        assert!(stage.is_empty());
+    }
+}
@@ -0,0 +1,904 @@
+use async_trait::async_trait;
+use serde_json::{json, Value as Json};
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncWrite, AsyncWriteExt, BufReader, Lines};
+use tokio::process::{Child, ChildStdout, Command};
+use tokio::task::JoinHandle;
+use tracing::{error, info, warn};
+use url::Url;
+
+use crate::{
+    connectors::{
+        connection_url::percent_decode,
+        json::{read_json_value, value_to_json},
+        Source, Target,
+    },
+    protocols::{
+        singer::{record_row, schema_columns, settings},
+        Protocol,
+    },
+    schema::{BinaryEncoding, Row, Schema, SchemaInferer},
+    schema_export::json_schema,
+    staging::staging,
+    strict::Coercion,
+    Result, TinyEtlError,
+};
+
+/// Airbyte protocol: sources run an Airbyte source connector
+/// (`airbyte://source-faker?config=faker.json&catalog=catalog.json#users`)
+/// and read the RECORD and STATE messages of its `read` command, targets
+/// pipe RECORD messages into an Airbyte destination's `write` command
+/// (`airbyte://destination-duckdb?config=duckdb.json#orders`).
+/// `airbyte://-` reads the messages from STDIN or writes them to STDOUT.
+pub struct AirbyteProtocol;
+
+impl Default for AirbyteProtocol {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AirbyteProtocol {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+#[async_trait]
+impl Protocol for AirbyteProtocol {
+    async fn create_source(
+        &self,
+        url: &Url,
+        options: &HashMap<String, String>,
+    ) -> Result<Box<dyn Source>> {
+        Ok(Box::new(AirbyteSource::new(url, options)?))
+    }
+
+    async fn create_target(
+        &self,
+        url: &Url,
+        options: &HashMap<String, String>,
+    ) -> Result<Box<dyn Target>> {
+        Ok(Box::new(AirbyteTarget::new(url, options)?))
+    }
+
+    fn validate_url(&self, url: &Url) -> Result<()> {
+        if url.scheme() != "airbyte" {
+            return Err(TinyEtlError::Configuration(format!(
+                "Airbyte protocol requires airbyte:// scheme, got: {}",
+                url.scheme()
+            )));
+        }
+
+        if AirbyteCommand::program(url).is_empty() {
+            return Err(TinyEtlError::Configuration(
+                "Airbyte URL must name a connector to run (airbyte://source-name), or - for STDIN/STDOUT"
+                    .to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "airbyte"
+    }
+}
+
+/// A connector to run, with the files the Airbyte protocol passes on its
+/// command line
+#[derive(Debug, Clone, PartialEq)]
+struct AirbyteCommand {
+    program: String,
+    config: Option<String>,
+    catalog: Option<String>,
+    state: Option<String>,
+}
+
+impl AirbyteCommand {
+    /// The executable, `airbyte://source-name` from the PATH or
+    /// `airbyte:///opt/airbyte/bin/source-name`; `-` stands for STDIN/STDOUT
+    fn program(url: &Url) -> String {
+        percent_decode(&format!("{}{}", url.host_str().unwrap_or(""), url.path()))
+    }
+
+    /// None for `airbyte://-`
+    fn parse(url: &Url, settings: &HashMap<String, String>) -> Option<Self> {
+        let program = Self::program(url);
+        (program != "-").then(|| Self {
+            program,
+            config: settings.get("config").cloned(),
+            catalog: settings.get("catalog").cloned(),
+            state: settings.get("state").cloned(),
+        })
+    }
+
+    /// Arguments of `read` (sources) or `write` (destinations)
+    fn args(&self, command: &str, catalog: &str) -> Vec<String> {
+        let mut args = vec![command.to_string()];
+        if let Some(config) = &self.config {
+            args.extend(["--config".to_string(), config.clone()]);
+        }
+        args.extend(["--catalog".to_string(), catalog.to_string()]);
+        // The first run of an incremental sync has no state yet
+        if let Some(state) = self
+            .state
+            .as_ref()
+            .filter(|state| Path::new(state).exists())
+        {
+            args.extend(["--state".to_string(), state.clone()]);
+        }
+        args
+    }
+
+    fn spawn(&self, args: Vec<String>, stdin: Stdio) -> Result<Child> {
+        Command::new(&self.program)
+            .args(args)
+            .stdin(stdin)
+            .stdout(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| TinyEtlError::Connection(format!("Failed to run {}: {}", self.program, e)))
+    }
+
+    /// Wait for the connector to exit, failing with the error it reported
+    async fn wait(&self, child: &mut Child, reported: Option<String>) -> Result<()> {
+        let status = child.wait().await?;
+        if !status.success() {
+            return Err(TinyEtlError::DataTransfer(match reported {
+                Some(message) => format!("{} failed: {}", self.program, message),
+                None => format!("{} exited with {}", self.program, status),
+            }));
+        }
+        Ok(())
+    }
+}
+
+/// Log a LOG or TRACE message of a connector; returns the message of an
+/// error TRACE
+fn log_message(program: &str, message: &Json) -> Option<String> {
+    match message["type"].as_str() {
+        Some("LOG") => {
+            let text = message["log"]["message"].as_str().unwrap_or_default();
+            match message["log"]["level"].as_str() {
+                Some("FATAL" | "ERROR") => error!("{}: {}", program, text),
+                Some("WARN") => warn!("{}: {}", program, text),
+                _ => info!("{}: {}", program, text),
+            }
+            None
+        }
+        Some("TRACE") if message["trace"]["type"] == "ERROR" => {
+            let error = &message["trace"]["error"];
+            let text = error["message"]
+                .as_str()
+                .or_else(|| error["internal_message"].as_str())
+                .unwrap_or("unknown error")
+                .to_string();
+            error!("{}: {}", program, text);
+            Some(text)
+        }
+        _ => None,
+    }
+}
+
+/// Name of a stream as the URL fragment selects it: `name`, or
+/// `namespace.name` for streams with a namespace
+fn stream_name(name: &Json, namespace: &Json) -> String {
+    let name = name.as_str().unwrap_or_default();
+    match namespace.as_str() {
+        Some(namespace) => format!("{}.{}", namespace, name),
+        None => name.to_string(),
+    }
+}
+
+/// Schema of a stream of a configured catalog (or of a catalog from
+/// `discover`)
+fn catalog_schema(catalog: &Json, stream: Option<&str>) -> Result<(String, Schema)> {
+    let entries = catalog["streams"]
+        .as_array()
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    // Configured catalog entries wrap the stream; discovered ones are it
+    let streams: Vec<(&Json, &Json)> = entries
+        .iter()
+        .map(|entry| match entry.get("stream") {
+            Some(stream) => (stream, &entry["primary_key"]),
+            None => (entry, &entry["source_defined_primary_key"]),
+        })
+        .collect();
+
+    let found = streams.iter().find(|(definition, _)| {
+        let Some(stream) = stream else {
+            return true;
+        };
+        stream_name(&definition["name"], &definition["namespace"]) == stream
+            || definition["name"] == stream
+    });
+    let (definition, primary_key) = found.ok_or_else(|| {
+        let names: Vec<String> = streams
+            .iter()
+            .map(|(s, _)| stream_name(&s["name"], &s["namespace"]))
+            .collect();
+        TinyEtlError::Configuration(format!(
+            "No stream '{}' in the Airbyte catalog; it has: {}",
+            stream.unwrap_or_default(),
+            names.join(", ")
+        ))
+    })?;
+
+    // [["id"]]: one key column, given as a path of one field
+    let primary_key_candidate = match primary_key.as_array().map(Vec::as_slice) {
+        Some([path]) => match path.as_array().map(Vec::as_slice) {
+            Some([Json::String(column)]) => Some(column.clone()),
+            _ => None,
+        },
+        _ => None,
+    };
+    let schema = Schema {
+        columns: schema_columns(&definition["json_schema"]),
+        estimated_rows: None,
+        primary_key_candidate,
+    };
+    let name = stream_name(&definition["name"], &definition["namespace"]);
+    Ok((name, schema))
+}
+
+/// Latest state of each stream (or the global or legacy state), as Airbyte
+/// sources take them back with `--state`
+#[derive(Debug, Default)]
+struct StreamStates {
+    states: Vec<Json>,
+}
+
+impl StreamStates {
+    fn update(&mut self, state: Json) {
+        let key = |state: &Json| {
+            (
+                state["type"].clone(),
+                state["stream"]["stream_descriptor"].clone(),
+            )
+        };
+        self.states.retain(|kept| key(kept) != key(&state));
+        self.states.push(state);
+    }
+
+    /// Content of a state file; a legacy state is its bare `data`
+    fn to_json(&self) -> Option<Json> {
+        match self.states.as_slice() {
+            [] => None,
+            [legacy] if legacy.get("type").is_none() => Some(legacy["data"].clone()),
+            states => Some(Json::Array(states.to_vec())),
+        }
+    }
+}
+
+type MessageLines = Lines<Box<dyn AsyncBufRead + Send + Sync + Unpin>>;
+
+/// Source reading the records of one stream of an Airbyte source
+/// connector: the stream named by the URL fragment, or the first of the
+/// catalog. Column types come from the catalog, or are inferred from the
+/// records when messages are read from STDIN without one. The latest
+/// states are written to the `state` file once the target is finalized,
+/// and passed to the connector on the next run.
+pub struct AirbyteSource {
+    command: Option<AirbyteCommand>,
+    catalog: Option<String>,
+    stream: Option<String>,
+    state_file: Option<String>,
+    child: Option<Child>,
+    lines: Option<MessageLines>,
+    schema: Option<Schema>,
+    /// Rows read and not yet handed out, plus those handed out since the
+    /// last time all were; `reset` rewinds to the first of them
+    rows: Vec<Row>,
+    position: usize,
+    done: bool,
+    states: StreamStates,
+    reported_error: Option<String>,
+    skipped_streams: BTreeSet<String>,
+    coercion: Coercion,
+}
+
+impl AirbyteSource {
+    fn new(url: &Url, options: &HashMap<String, String>) -> Result<Self> {
+        let settings = settings(url, options);
+        let command = AirbyteCommand::parse(url, &settings);
+        let catalog = settings.get("catalog").cloned();
+        if command.is_some() && catalog.is_none() {
+            return Err(TinyEtlError::Configuration(
+                "Airbyte sources need a configured catalog: airbyte://source-name?config=...&catalog=catalog.json"
+                    .to_string(),
+            ));
+        }
+        Ok(Self {
+            command,
+            catalog,
+            stream: url.fragment().map(percent_decode),
+            state_file: settings.get("state").cloned(),
+            child: None,
+            lines: None,
+            schema: None,
+            rows: Vec::new(),
+            position: 0,
+            done: false,
+            states: StreamStates::default(),
+            reported_error: None,
+            skipped_streams: BTreeSet::new(),
+            coercion: Coercion::default(),
+        })
+    }
+
+    fn source_name(&self) -> &str {
+        self.command
+            .as_ref()
+            .map_or("STDIN", |c| c.program.as_str())
+    }
+
+    /// Read messages until `rows` holds `wanted` rows not handed out yet or
+    /// the connector is done
+    async fn fetch(&mut self, wanted: usize) -> Result<()> {
+        while !self.done && self.rows.len() - self.position < wanted {
+            let lines = self.lines.as_mut().ok_or_else(|| {
+                TinyEtlError::Connection("Airbyte source is not connected".to_string())
+            })?;
+            let Some(line) = lines.next_line().await? else {
+                self.finish().await?;
+                break;
+            };
+            // Connectors may print other output, which Airbyte ignores too
+            let Ok(message) = serde_json::from_str::<Json>(&line) else {
+                continue;
+            };
+            self.handle(message)?;
+        }
+        Ok(())
+    }
+
+    fn handle(&mut self, message: Json) -> Result<()> {
+        match message["type"].as_str() {
+            Some("RECORD") => {
+                let record = &message["record"];
+                let stream = stream_name(&record["stream"], &record["namespace"]);
+                let selected = self.stream.get_or_insert_with(|| stream.clone());
+                if *selected != stream && record["stream"] != selected.as_str() {
+                    if self.skipped_streams.insert(stream.clone()) {
+                        warn!(
+                            "Skipping records of Airbyte stream '{}'; select it with #{}",
+                            stream, stream
+                        );
+                    }
+                    return Ok(());
+                }
+                let row = match &self.schema {
+                    Some(schema) => record_row(schema, &record["data"], self.coercion)?,
+                    None => self.untyped_row(&record["data"])?,
+                };
+                self.rows.push(row);
+            }
+            Some("STATE") => self.states.update(message["state"].clone()),
+            _ => {
+                let program = self.source_name().to_string();
+                if let Some(error) = log_message(&program, &message) {
+                    self.reported_error = Some(error);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Row of a record read without a catalog, typed by its JSON values
+    fn untyped_row(&self, data: &Json) -> Result<Row> {
+        let data = data.as_object().ok_or_else(|| {
+            TinyEtlError::DataValidation("RECORD message holds no data object".to_string())
+        })?;
+        data.iter()
+            .map(|(key, value)| Ok((key.clone(), read_json_value(key, value, self.coercion)?)))
+            .collect()
+    }
+
+    /// The connector printed everything: check that it succeeded
+    async fn finish(&mut self) -> Result<()> {
+        self.done = true;
+        self.lines = None;
+        if let (Some(command), Some(mut child)) = (&self.command, self.child.take()) {
+            command.wait(&mut child, self.reported_error.take()).await?;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Source for AirbyteSource {
+    async fn connect(&mut self) -> Result<()> {
+        if let Some(path) = &self.catalog {
+            let catalog: Json =
+                serde_json::from_str(&std::fs::read_to_string(path)?).map_err(|e| {
+                    TinyEtlError::Configuration(format!("Invalid Airbyte catalog {}: {}", path, e))
+                })?;
+            let (stream, schema) = catalog_schema(&catalog, self.stream.as_deref())?;
+            self.stream = Some(stream);
+            self.schema = Some(schema);
+        }
+
+        let input: Box<dyn AsyncBufRead + Send + Sync + Unpin> = match &self.command {
+            Some(command) => {
+                info!("Running Airbyte source {}", command.program);
+                let catalog = self.catalog.as_deref().unwrap_or_default();
+                let mut child = command.spawn(command.args("read", catalog), Stdio::null())?;
+                let stdout = child.stdout.take().ok_or_else(|| {
+                    TinyEtlError::Connection(format!("No output from {}", command.program))
+                })?;
+                self.child = Some(child);
+                Box::new(BufReader::new(stdout))
+            }
+            None => Box::new(BufReader::new(tokio::io::stdin())),
+        };
+        self.lines = Some(input.lines());
+        Ok(())
+    }
+
+    async fn infer_schema(&mut self, sample_size: usize) -> Result<Schema> {
+        if let Some(schema) = &self.schema {
+            return Ok(schema.clone());
+        }
+        // Without a catalog the records are all there is to go by
+        self.fetch(sample_size.max(1)).await?;
+        let sample = &self.rows[..self.rows.len().min(sample_size)];
+        SchemaInferer::infer_from_rows(sample)
+    }
+
+    async fn read_batch(&mut self, batch_size: usize) -> Result<Vec<Row>> {
+        if self.position > 0 && self.position == self.rows.len() {
+            self.rows.clear();
+            self.position = 0;
+        }
+        self.fetch(batch_size).await?;
+        let end = (self.position + batch_size).min(self.rows.len());
+        let batch = self.rows[self.position..end].to_vec();
+        self.position = end;
+        Ok(batch)
+    }
+
+    async fn estimated_row_count(&self) -> Result<Option<usize>> {
+        Ok(None)
+    }
+
+    fn set_coercion(&mut self, coercion: Coercion) {
+        self.coercion = coercion;
+    }
+
+    async fn commit(&mut self) -> Result<()> {
+        if let (Some(path), Some(state)) = (&self.state_file, self.states.to_json()) {
+            std::fs::write(path, serde_json::to_string_pretty(&state)?)?;
+            info!("Airbyte state written to {}", path);
+        }
+        Ok(())
+    }
+
+    async fn reset(&mut self) -> Result<()> {
+        self.position = 0;
+        Ok(())
+    }
+
+    fn has_more(&self) -> bool {
+        self.position < self.rows.len() || !self.done
+    }
+}
+
+/// Log the LOG and TRACE messages a destination prints while it runs;
+/// resolves to the error it reported, if any
+fn forward_messages(program: String, stdout: ChildStdout) -> JoinHandle<Option<String>> {
+    tokio::spawn(async move {
+        let mut lines = BufReader::new(stdout).lines();
+        let mut reported = None;
+        while let Ok(Some(line)) = lines.next_line().await {
+            if let Ok(message) = serde_json::from_str::<Json>(&line) {
+                reported = log_message(&program, &message).or(reported);
+            }
+        }
+        reported
+    })
+}
+
+/// Target sending a RECORD message per row for the stream named by the URL
+/// fragment into an Airbyte destination it runs, or to STDOUT, followed by
+/// a STATE message so the destination commits them. The destination is
+/// given a configured catalog made from the output schema unless `catalog`
+/// names one.
+pub struct AirbyteTarget {
+    command: Option<AirbyteCommand>,
+    stream: String,
+    namespace: Option<String>,
+    sync_mode: String,
+    primary_key: Vec<String>,
+    binary_encoding: BinaryEncoding,
+    schema: Option<Schema>,
+    child: Option<Child>,
+    messages: Option<JoinHandle<Option<String>>>,
+    output: Option<Box<dyn AsyncWrite + Send + Sync + Unpin>>,
+    /// Catalog written for the destination, removed once it's done
+    written_catalog: Option<PathBuf>,
+    rows_written: usize,
+}
+
+impl AirbyteTarget {
+    fn new(url: &Url, options: &HashMap<String, String>) -> Result<Self> {
+        let settings = settings(url, options);
+        let stream = url.fragment().map(percent_decode).ok_or_else(|| {
+            TinyEtlError::Configuration(
+                "Airbyte target URL must name the stream: airbyte://destination-name#stream"
+                    .to_string(),
+            )
+        })?;
+        let sync_mode = settings
+            .get("sync_mode")
+            .cloned()
+            .unwrap_or_else(|| "append".to_string());
+        if !matches!(sync_mode.as_str(), "append" | "overwrite" | "append_dedup") {
+            return Err(TinyEtlError::Configuration(format!(
+                "Invalid Airbyte sync_mode '{}': expected append, overwrite or append_dedup",
+                sync_mode
+            )));
+        }
+        let primary_key: Vec<String> = settings
+            .get("primary_key")
+            .map(|columns| {
+                columns
+                    .split(',')
+                    .map(|column| column.trim().to_string())
+                    .filter(|column| !column.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default();
+        if sync_mode == "append_dedup" && primary_key.is_empty() {
+            return Err(TinyEtlError::Configuration(
+                "Airbyte sync_mode append_dedup needs a primary_key".to_string(),
+            ));
+        }
+
+        Ok(Self {
+            command: AirbyteCommand::parse(url, &settings),
+            stream,
+            namespace: settings.get("namespace").cloned(),
+            sync_mode,
+            primary_key,
+            binary_encoding: settings
+                .get("binary_encoding")
+                .map(|encoding| {
+                    encoding
+                        .parse::<BinaryEncoding>()
+                        .map_err(|e| TinyEtlError::Configuration(e.to_string()))
+                })
+                .transpose()?
+                .unwrap_or_default(),
+            schema: None,
+            child: None,
+            messages: None,
+            output: None,
+            written_catalog: None,
+            rows_written: 0,
+        })
+    }
+
+    /// The stream's name under `key`, and its namespace if it has one;
+    /// Airbyte rejects a null namespace
+    fn descriptor(&self, key: &str) -> serde_json::Map<String, Json> {
+        let mut descriptor = serde_json::Map::new();
+        descriptor.insert(key.to_string(), json!(self.stream));
+        if let Some(namespace) = &self.namespace {
+            descriptor.insert("namespace".to_string(), json!(namespace));
+        }
+        descriptor
+    }
+
+    fn configured_catalog(&self, schema: &Schema) -> Json {
+        let sync_mode = match self.sync_mode.as_str() {
+            "append" => "incremental",
+            _ => "full_refresh",
+        };
+        let primary_key: Vec<Vec<&str>> = self
+            .primary_key
+            .iter()
+            .map(|key| vec![key.as_str()])
+            .collect();
+        let mut stream = self.descriptor("name");
+        stream.insert("json_schema".to_string(), json_schema(schema, &self.stream));
+        stream.insert(
+            "supported_sync_modes".to_string(),
+            json!(["full_refresh", "incremental"]),
+        );
+        json!({
+            "streams": [{
+                "stream": stream,
+                "sync_mode": sync_mode,
+                "destination_sync_mode": self.sync_mode,
+                "primary_key": primary_key,
+            }]
+        })
+    }
+
+    fn record_message(&self, row: &Row, emitted_at: i64) -> Json {
+        let columns = self.schema.iter().flat_map(|schema| &schema.columns);
+        let data: serde_json::Map<String, Json> = columns
+            .map(|column| {
+                let value = row
+                    .get(&column.name)
+                    .map(|value| value_to_json(value, self.binary_encoding))
+                    .unwrap_or(Json::Null);
+                (column.name.clone(), value)
+            })
+            .collect();
+        let mut record = self.descriptor("stream");
+        record.insert("data".to_string(), Json::Object(data));
+        record.insert("emitted_at".to_string(), json!(emitted_at));
+        json!({ "type": "RECORD", "record": record })
+    }
+
+    fn state_message(&self) -> Json {
+        json!({
+            "type": "STATE",
+            "state": {
+                "type": "STREAM",
+                "stream": {
+                    "stream_descriptor": self.descriptor("name"),
+                    "stream_state": { "rows_written": self.rows_written },
+                }
+            }
+        })
+    }
+
+    async fn send(&mut self, messages: &[Json]) -> Result<()> {
+        let mut text = String::new();
+        for message in messages {
+            text.push_str(&serde_json::to_string(message)?);
+            text.push('\n');
+        }
+        let output = self.output.as_mut().ok_or_else(|| {
+            TinyEtlError::Connection("Airbyte target is not connected".to_string())
+        })?;
+        output.write_all(text.as_bytes()).await.map_err(|e| {
+            TinyEtlError::DataTransfer(format!("Failed to send Airbyte messages: {}", e))
+        })
+    }
+}
+
+#[async_trait]
+impl Target for AirbyteTarget {
+    async fn connect(&mut self) -> Result<()> {
+        if self.command.is_none() {
+            self.output = Some(Box::new(tokio::io::stdout()));
+        }
+        Ok(())
+    }
+
+    async fn create_table(&mut self, _table_name: &str, schema: &Schema) -> Result<()> {
+        for key in &self.primary_key {
+            if !schema.columns.iter().any(|c| &c.name == key) {
+                return Err(TinyEtlError::Configuration(format!(
+                    "Airbyte primary key column '{}' is not in the output schema",
+                    key
+                )));
+            }
+        }
+        self.schema = Some(schema.clone());
+
+        // The destination needs the catalog on its command line, so it is
+        // started once the schema is known
+        if let Some(command) = self.command.clone() {
+            let catalog = match &command.catalog {
+                Some(catalog) => catalog.clone(),
+                None => {
+                    let path = staging()?.temp_path(Some("json"));
+                    let catalog = self.configured_catalog(schema);
+                    std::fs::write(&path, serde_json::to_string_pretty(&catalog)?)?;
+                    self.written_catalog = Some(path.clone());
+                    path.to_string_lossy().into_owned()
+                }
+            };
+            info!("Running Airbyte destination {}", command.program);
+            let mut child = command.spawn(command.args("write", &catalog), Stdio::piped())?;
+            let stdin = child.stdin.take().ok_or_else(|| {
+                TinyEtlError::Connection(format!("Can't write to {}", command.program))
+            })?;
+            if let Some(stdout) = child.stdout.take() {
+                self.messages = Some(forward_messages(command.program.clone(), stdout));
+            }
+            self.child = Some(child);
+            self.output = Some(Box::new(stdin));
+        }
+        Ok(())
+    }
+
+    async fn write_batch(&mut self, rows: &[Row]) -> Result<usize> {
+        let emitted_at = chrono::Utc::now().timestamp_millis();
+        let messages: Vec<Json> = rows
+            .iter()
+            .map(|row| self.record_message(row, emitted_at))
+            .collect();
+        self.send(&messages).await?;
+        self.rows_written += rows.len();
+        Ok(rows.len())
+    }
+
+    async fn finalize(&mut self) -> Result<()> {
+        if self.output.is_some() {
+            let state = self.state_message();
+            self.send(&[state]).await?;
+        }
+        if let Some(mut output) = self.output.take() {
+            output.flush().await?;
+            output.shutdown().await?;
+        }
+        // With its input closed the destination commits what it received and exits
+        let reported = match self.messages.take() {
+            Some(messages) => messages.await.ok().flatten(),
+            None => None,
+        };
+        if let (Some(command), Some(mut child)) = (&self.command, self.child.take()) {
+            command.wait(&mut child, reported).await?;
+        }
+        if let Some(path) = self.written_catalog.take() {
+            let _ = std::fs::remove_file(path);
+        }
+        Ok(())
+    }
+
+    async fn exists(&self, _table_name: &str) -> Result<bool> {
+        Ok(false)
+    }
+
+    async fn truncate(&mut self, _table_name: &str) -> Result<()> {
+        Err(TinyEtlError::Configuration(
+            "Airbyte targets can't be truncated; set sync_mode: overwrite instead".to_string(),
+        ))
+    }
+
+    fn supports_append(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::{DataType, Value};
+
+    #[test]
+    fn test_commands_from_urls() {
+        let url = Url::parse(
+            "airbyte://source-faker?config=faker.json&catalog=catalog.json&state=/nonexistent/state.json#users",
+        )
+        .unwrap();
+        let command = AirbyteCommand::parse(&url, &settings(&url, &HashMap::new())).unwrap();
+        assert_eq!(command.program, "source-faker");
+        assert_eq!(
+            command.args("read", "catalog.json"),
+            vec![
+                "read",
+                "--config",
+                "faker.json",
+                "--catalog",
+                "catalog.json"
+            ]
+        );
+
+        let url = Url::parse("airbyte://source-faker#users").unwrap();
+        assert!(AirbyteSource::new(&url, &HashMap::new()).is_err());
+        let url = Url::parse("airbyte://-").unwrap();
+        assert!(AirbyteSource::new(&url, &HashMap::new()).is_ok());
+        assert!(AirbyteTarget::new(&url, &HashMap::new()).is_err());
+        let url = Url::parse("airbyte://-?sync_mode=append_dedup#orders").unwrap();
+        assert!(AirbyteTarget::new(&url, &HashMap::new()).is_err());
+    }
+
+    #[test]
+    fn test_catalog_streams() {
+        let catalog = json!({"streams": [
+            {
+                "stream": {"name": "products", "json_schema": {"properties": {"sku": {"type": "string"}}}},
+                "sync_mode": "full_refresh",
+                "destination_sync_mode": "overwrite"
+            },
+            {
+                "stream": {
+                    "name": "users",
+                    "namespace": "crm",
+                    "json_schema": {"properties": {
+                        "id": {"type": "integer"},
+                        "updated_at": {"type": ["null", "string"], "format": "date-time"}
+                    }}
+                },
+                "primary_key": [["id"]],
+                "sync_mode": "incremental",
+                "destination_sync_mode": "append"
+            }
+        ]});
+
+        let (stream, schema) = catalog_schema(&catalog, None).unwrap();
+        assert_eq!(stream, "products");
+        assert_eq!(schema.columns[0].data_type, DataType::String);
+
+        let (stream, schema) = catalog_schema(&catalog, Some("users")).unwrap();
+        assert_eq!(stream, "crm.users");
+        assert_eq!(schema.primary_key_candidate.as_deref(), Some("id"));
+        assert_eq!(schema.columns[1].data_type, DataType::DateTime);
+        assert!(catalog_schema(&catalog, Some("crm.users")).is_ok());
+
+        let err = catalog_schema(&catalog, Some("orders")).unwrap_err();
+        assert!(err.to_string().contains("it has: products, crm.users"));
+
+        // Catalogs from `discover` list the streams themselves
+        let discovered = json!({"streams": [{
+            "name": "users",
+            "json_schema": {"properties": {"id": {"type": "integer"}}},
+            "source_defined_primary_key": [["id"]]
+        }]});
+        let (_, schema) = catalog_schema(&discovered, Some("users")).unwrap();
+        assert_eq!(schema.primary_key_candidate.as_deref(), Some("id"));
+    }
+
+    #[test]
+    fn test_source_messages() {
+        let url = Url::parse("airbyte://-").unwrap();
+        let mut source = AirbyteSource::new(&url, &HashMap::new()).unwrap();
+        let messages = [
+            json!({"type": "LOG", "log": {"level": "INFO", "message": "Starting sync"}}),
+            json!({"type": "RECORD", "record": {"stream": "users", "data": {"id": 1, "name": "Ada"}, "emitted_at": 1}}),
+            json!({"type": "RECORD", "record": {"stream": "orders", "data": {"id": 7}, "emitted_at": 1}}),
+            json!({"type": "STATE", "state": {"type": "STREAM", "stream": {"stream_descriptor": {"name": "users"}, "stream_state": {"cursor": 1}}}}),
+            json!({"type": "STATE", "state": {"type": "STREAM", "stream": {"stream_descriptor": {"name": "users"}, "stream_state": {"cursor": 2}}}}),
+            json!({"type": "TRACE", "trace": {"type": "ERROR", "error": {"message": "Rate limited"}}}),
+        ];
+        for message in messages {
+            source.handle(message).unwrap();
+        }
+        assert_eq!(source.rows.len(), 1);
+        assert_eq!(source.rows[0]["name"], Value::String("Ada".to_string()));
+        assert_eq!(
+            source.skipped_streams,
+            BTreeSet::from(["orders".to_string()])
+        );
+        assert_eq!(source.reported_error.as_deref(), Some("Rate limited"));
+        let state = source.states.to_json().unwrap();
+        assert_eq!(state.as_array().unwrap().len(), 1);
+        assert_eq!(state[0]["stream"]["stream_state"]["cursor"], 2);
+
+        let mut legacy = StreamStates::default();
+        legacy.update(json!({"data": {"cursor": 3}}));
+        assert_eq!(legacy.to_json(), Some(json!({"cursor": 3})));
+    }
+
+    #[test]
+    fn test_target_messages() {
+        let url =
+            Url::parse("airbyte://destination-duckdb?primary_key=id&sync_mode=append_dedup#orders")
+                .unwrap();
+        let mut target = AirbyteTarget::new(&url, &HashMap::new()).unwrap();
+        let schema = Schema {
+            columns: vec![crate::schema::Column {
+                name: "id".to_string(),
+                data_type: DataType::Integer,
+                nullable: false,
+            }],
+            estimated_rows: None,
+            primary_key_candidate: None,
+        };
+        let catalog = target.configured_catalog(&schema);
+        let stream = &catalog["streams"][0];
+        assert_eq!(stream["destination_sync_mode"], "append_dedup");
+        assert_eq!(stream["primary_key"], json!([["id"]]));
+        assert_eq!(
+            stream["stream"]["json_schema"]["properties"]["id"]["type"],
+            "integer"
+        );
+
+        target.schema = Some(schema);
+        let row = Row::from([("id".to_string(), Value::Integer(3))]);
+        let record = target.record_message(&row, 1_700_000_000_000);
+        assert_eq!(record["record"]["data"], json!({"id": 3}));
+        assert_eq!(record["record"]["stream"], "orders");
+        assert!(record["record"].get("namespace").is_none());
+    }
+}
@@ -0,0 +1,562 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::time::Duration;
+use tracing::info;
+use url::Url;
+
+use crate::{
+    connectors::{
+        connection_url::percent_decode,
+        sql_ident::{quote_table, QuoteStyle},
+        Source, Target,
+    },
+    protocols::{
+        trino::{data_type, text_value},
+        Protocol,
+    },
+    schema::{Column, DataType, Row, Schema, Value},
+    strict::Coercion,
+    Result, TinyEtlError,
+};
+
+const SERVICE: &str = "athena";
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+/// Rows per GetQueryResults call, the most Athena returns
+const PAGE_SIZE: usize = 1000;
+
+/// Athena protocol: sources run a query in a region
+/// (`athena://us-east-1/database#table`) and page through its result with
+/// the Athena API. There is no Athena target.
+pub struct AthenaProtocol;
+
+impl Default for AthenaProtocol {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AthenaProtocol {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+#[async_trait]
+impl Protocol for AthenaProtocol {
+    async fn create_source(
+        &self,
+        url: &Url,
+        options: &HashMap<String, String>,
+    ) -> Result<Box<dyn Source>> {
+        Ok(Box::new(AthenaSource::new(url, options)?))
+    }
+
+    async fn create_target(
+        &self,
+        _url: &Url,
+        _options: &HashMap<String, String>,
+    ) -> Result<Box<dyn Target>> {
+        Err(TinyEtlError::Configuration(
+            "Athena can only be used as a source: write to the tables it queries instead"
+                .to_string(),
+        ))
+    }
+
+    fn validate_url(&self, url: &Url) -> Result<()> {
+        if url.scheme() != "athena" {
+            return Err(TinyEtlError::Configuration(format!(
+                "Athena protocol requires athena:// scheme, got: {}",
+                url.scheme()
+            )));
+        }
+
+        if url.host().is_none() {
+            return Err(TinyEtlError::Configuration(
+                "Athena protocol requires an AWS region as host".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "athena"
+    }
+}
+
+/// AWS credentials from the source options, or the standard environment
+/// variables
+#[derive(Clone)]
+struct Credentials {
+    access_key_id: String,
+    secret_access_key: String,
+    session_token: Option<String>,
+}
+
+impl Credentials {
+    fn from_options(options: &HashMap<String, String>) -> Result<Self> {
+        let get = |option: &str, variable: &str| {
+            options
+                .get(option)
+                .cloned()
+                .or_else(|| std::env::var(variable).ok())
+        };
+        match (
+            get("access_key_id", "AWS_ACCESS_KEY_ID"),
+            get("secret_access_key", "AWS_SECRET_ACCESS_KEY"),
+        ) {
+            (Some(access_key_id), Some(secret_access_key)) => Ok(Self {
+                access_key_id,
+                secret_access_key,
+                session_token: get("session_token", "AWS_SESSION_TOKEN"),
+            }),
+            _ => Err(TinyEtlError::Configuration(
+                "Athena needs AWS credentials: set AWS_ACCESS_KEY_ID and AWS_SECRET_ACCESS_KEY, or the access_key_id and secret_access_key options"
+                    .to_string(),
+            )),
+        }
+    }
+}
+
+pub(crate) fn hmac_sha256(key: &[u8], data: &str) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// Headers of an Athena API call signed with AWS Signature Version 4
+fn signed_headers(
+    credentials: &Credentials,
+    region: &str,
+    target: &str,
+    body: &str,
+    now: DateTime<Utc>,
+) -> Vec<(String, String)> {
+    let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+    let date = now.format("%Y%m%d").to_string();
+    let mut headers = vec![
+        (
+            "content-type".to_string(),
+            "application/x-amz-json-1.1".to_string(),
+        ),
+        (
+            "host".to_string(),
+            format!("{}.{}.amazonaws.com", SERVICE, region),
+        ),
+        ("x-amz-date".to_string(), amz_date.clone()),
+    ];
+    if let Some(token) = &credentials.session_token {
+        headers.push(("x-amz-security-token".to_string(), token.clone()));
+    }
+    headers.push(("x-amz-target".to_string(), target.to_string()));
+
+    let signed_names = headers
+        .iter()
+        .map(|(name, _)| name.as_str())
+        .collect::<Vec<_>>()
+        .join(";");
+    let canonical_headers: String = headers
+        .iter()
+        .map(|(name, value)| format!("{}:{}\n", name, value.trim()))
+        .collect();
+    let canonical_request = format!(
+        "POST\n/\n\n{}\n{}\n{}",
+        canonical_headers,
+        signed_names,
+        hex::encode(Sha256::digest(body.as_bytes()))
+    );
+    let scope = format!("{}/{}/{}/aws4_request", date, region, SERVICE);
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        amz_date,
+        scope,
+        hex::encode(Sha256::digest(canonical_request.as_bytes()))
+    );
+
+    let key = [date.as_str(), region, SERVICE, "aws4_request"]
+        .iter()
+        .fold(
+            format!("AWS4{}", credentials.secret_access_key).into_bytes(),
+            |key, part| hmac_sha256(&key, part),
+        );
+    let signature = hex::encode(hmac_sha256(&key, &string_to_sign));
+    headers.push((
+        "authorization".to_string(),
+        format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            credentials.access_key_id, scope, signed_names, signature
+        ),
+    ));
+    headers
+}
+
+/// Source running a query with Athena: the `#table` of the URL, the `query`
+/// option or `--query`. Results are read a page at a time from the query
+/// execution, which can be read again from the start.
+pub struct AthenaSource {
+    region: String,
+    database: Option<String>,
+    catalog: String,
+    workgroup: String,
+    output_location: Option<String>,
+    query: String,
+    credentials: Credentials,
+    http: reqwest::Client,
+    execution_id: Option<String>,
+    /// Names and Athena types of the result columns
+    columns: Vec<(String, String)>,
+    next_token: Option<String>,
+    first_page: bool,
+    pending: Vec<Row>,
+    exhausted: bool,
+    coercion: Coercion,
+}
+
+impl AthenaSource {
+    fn new(url: &Url, options: &HashMap<String, String>) -> Result<Self> {
+        let region = url.host_str().map(str::to_string).ok_or_else(|| {
+            TinyEtlError::Configuration("Athena URL must name an AWS region".to_string())
+        })?;
+        let database = url
+            .path_segments()
+            .into_iter()
+            .flatten()
+            .find(|segment| !segment.is_empty())
+            .map(percent_decode);
+        let table = url.fragment().map(percent_decode);
+        let query = match (options.get("query"), table) {
+            (Some(query), None) => query.clone(),
+            (None, Some(table)) => {
+                format!(
+                    "SELECT * FROM {}",
+                    quote_table(&table, QuoteStyle::DoubleQuote)
+                )
+            }
+            (Some(_), Some(_)) => {
+                return Err(TinyEtlError::Configuration(
+                    "An Athena source reads either a #table or a query, not both".to_string(),
+                ))
+            }
+            (None, None) => {
+                return Err(TinyEtlError::Configuration(
+                    "Athena source needs a table (athena://region/database#table) or a query"
+                        .to_string(),
+                ))
+            }
+        };
+
+        Ok(Self {
+            region,
+            database,
+            catalog: options
+                .get("catalog")
+                .cloned()
+                .unwrap_or_else(|| "AwsDataCatalog".to_string()),
+            workgroup: options
+                .get("workgroup")
+                .cloned()
+                .unwrap_or_else(|| "primary".to_string()),
+            output_location: options.get("output_location").cloned(),
+            query,
+            credentials: Credentials::from_options(options)?,
+            http: reqwest::Client::new(),
+            execution_id: None,
+            columns: Vec::new(),
+            next_token: None,
+            first_page: true,
+            pending: Vec::new(),
+            exhausted: false,
+            coercion: Coercion::default(),
+        })
+    }
+
+    /// Source running a custom query, for `--query`
+    pub fn from_query(connection_string: &str, query: &str) -> Result<Self> {
+        let mut url = Url::parse(connection_string)
+            .map_err(|e| TinyEtlError::Configuration(format!("Invalid Athena URL: {}", e)))?;
+        url.set_fragment(None);
+        let options = HashMap::from([("query".to_string(), query.to_string())]);
+        Self::new(&url, &options)
+    }
+
+    /// Call an Athena API action
+    async fn call(&self, action: &str, body: serde_json::Value) -> Result<serde_json::Value> {
+        let body = body.to_string();
+        let target = format!("AmazonAthena.{}", action);
+        let mut request = self
+            .http
+            .post(format!(
+                "https://{}.{}.amazonaws.com/",
+                SERVICE, self.region
+            ))
+            .body(body.clone());
+        for (name, value) in
+            signed_headers(&self.credentials, &self.region, &target, &body, Utc::now())
+        {
+            // reqwest sets the host header itself
+            if name != "host" {
+                request = request.header(name, value);
+            }
+        }
+
+        let response = request.send().await.map_err(|e| {
+            TinyEtlError::Connection(format!("Athena {} request failed: {}", action, e))
+        })?;
+        let status = response.status();
+        let reply: serde_json::Value = response.json().await.map_err(|e| {
+            TinyEtlError::Connection(format!("Invalid Athena {} response: {}", action, e))
+        })?;
+        if !status.is_success() {
+            return Err(TinyEtlError::DataTransfer(format!(
+                "Athena {} failed: {}",
+                action,
+                reply["message"]
+                    .as_str()
+                    .or_else(|| reply["Message"].as_str())
+                    .unwrap_or(status.as_str())
+            )));
+        }
+        Ok(reply)
+    }
+
+    fn execution_id(&self) -> Result<&str> {
+        self.execution_id
+            .as_deref()
+            .ok_or_else(|| TinyEtlError::Connection("Athena source is not connected".to_string()))
+    }
+
+    /// Read the next page of results into `pending`
+    async fn fetch_page(&mut self) -> Result<()> {
+        let mut body = serde_json::json!({
+            "QueryExecutionId": self.execution_id()?,
+            "MaxResults": PAGE_SIZE,
+        });
+        if let Some(token) = &self.next_token {
+            body["NextToken"] = serde_json::json!(token);
+        }
+        let reply = self.call("GetQueryResults", body).await?;
+        let result = &reply["ResultSet"];
+        if self.columns.is_empty() {
+            self.columns = result["ResultSetMetadata"]["ColumnInfo"]
+                .as_array()
+                .map(Vec::as_slice)
+                .unwrap_or_default()
+                .iter()
+                .map(|column| {
+                    (
+                        column["Name"].as_str().unwrap_or_default().to_string(),
+                        column["Type"].as_str().unwrap_or("varchar").to_string(),
+                    )
+                })
+                .collect();
+        }
+
+        let mut rows = result["Rows"]
+            .as_array()
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        if std::mem::take(&mut self.first_page) {
+            // SELECT results start with a row of column names
+            let is_header = rows.first().is_some_and(|row| {
+                self.columns
+                    .iter()
+                    .zip(
+                        row["Data"]
+                            .as_array()
+                            .map(Vec::as_slice)
+                            .unwrap_or_default(),
+                    )
+                    .all(|((name, _), cell)| cell["VarCharValue"].as_str() == Some(name))
+            });
+            if is_header {
+                rows = &rows[1..];
+            }
+        }
+        for row in rows {
+            let cells = row["Data"]
+                .as_array()
+                .map(Vec::as_slice)
+                .unwrap_or_default();
+            let row = self
+                .columns
+                .iter()
+                .zip(cells)
+                .map(|((name, type_name), cell)| -> Result<(String, Value)> {
+                    let value = match cell["VarCharValue"].as_str() {
+                        None => Value::Null,
+                        // Athena shows varbinary as space-separated hex
+                        Some(text) if data_type(type_name) == DataType::Binary => {
+                            match hex::decode(text.replace(' ', "")) {
+                                Ok(bytes) => Value::Bytes(bytes),
+                                Err(_) => Value::String(text.to_string()),
+                            }
+                        }
+                        Some(text) => text_value(name, type_name, text, self.coercion)?,
+                    };
+                    Ok((name.clone(), value))
+                })
+                .collect::<Result<Row>>()?;
+            self.pending.push(row);
+        }
+
+        self.next_token = reply["NextToken"].as_str().map(str::to_string);
+        self.exhausted = self.next_token.is_none();
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Source for AthenaSource {
+    async fn connect(&mut self) -> Result<()> {
+        let mut context = serde_json::json!({ "Catalog": self.catalog });
+        if let Some(database) = &self.database {
+            context["Database"] = serde_json::json!(database);
+        }
+        let mut body = serde_json::json!({
+            "QueryString": self.query,
+            "QueryExecutionContext": context,
+            "WorkGroup": self.workgroup,
+        });
+        if let Some(location) = &self.output_location {
+            body["ResultConfiguration"] = serde_json::json!({ "OutputLocation": location });
+        }
+        let reply = self.call("StartQueryExecution", body).await?;
+        let execution_id = reply["QueryExecutionId"]
+            .as_str()
+            .ok_or_else(|| {
+                TinyEtlError::DataTransfer("Athena returned no query execution id".to_string())
+            })?
+            .to_string();
+        info!("Running Athena query {}", execution_id);
+
+        loop {
+            let reply = self
+                .call(
+                    "GetQueryExecution",
+                    serde_json::json!({ "QueryExecutionId": execution_id }),
+                )
+                .await?;
+            let status = &reply["QueryExecution"]["Status"];
+            match status["State"].as_str().unwrap_or_default() {
+                "SUCCEEDED" => break,
+                "FAILED" | "CANCELLED" => {
+                    return Err(TinyEtlError::DataTransfer(format!(
+                        "Athena query {}: {}",
+                        status["State"].as_str().unwrap_or_default().to_lowercase(),
+                        status["StateChangeReason"]
+                            .as_str()
+                            .unwrap_or("no reason given")
+                    )))
+                }
+                _ => tokio::time::sleep(POLL_INTERVAL).await,
+            }
+        }
+
+        self.execution_id = Some(execution_id);
+        self.reset().await?;
+        // The first page carries the column types
+        self.fetch_page().await
+    }
+
+    async fn infer_schema(&mut self, _sample_size: usize) -> Result<Schema> {
+        if self.execution_id.is_none() {
+            self.connect().await?;
+        }
+        Ok(Schema {
+            columns: self
+                .columns
+                .iter()
+                .map(|(name, type_name)| Column {
+                    name: name.clone(),
+                    data_type: data_type(type_name),
+                    nullable: true,
+                })
+                .collect(),
+            estimated_rows: None,
+            primary_key_candidate: None,
+        })
+    }
+
+    async fn read_batch(&mut self, batch_size: usize) -> Result<Vec<Row>> {
+        if self.execution_id.is_none() {
+            self.connect().await?;
+        }
+        while self.pending.len() < batch_size && !self.exhausted {
+            self.fetch_page().await?;
+        }
+        let count = batch_size.min(self.pending.len());
+        Ok(self.pending.drain(..count).collect())
+    }
+
+    async fn estimated_row_count(&self) -> Result<Option<usize>> {
+        Ok(None)
+    }
+
+    fn set_coercion(&mut self, coercion: Coercion) {
+        self.coercion = coercion;
+    }
+
+    async fn has_stable_order(&mut self) -> Result<bool> {
+        Ok(false)
+    }
+
+    async fn reset(&mut self) -> Result<()> {
+        // The results of a finished query can be paged again from the start
+        self.next_token = None;
+        self.first_page = true;
+        self.pending.clear();
+        self.exhausted = false;
+        Ok(())
+    }
+
+    fn has_more(&self) -> bool {
+        !self.exhausted || !self.pending.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_signature_v4() {
+        let credentials = Credentials {
+            access_key_id: "AKIDEXAMPLE".to_string(),
+            secret_access_key: "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY".to_string(),
+            session_token: None,
+        };
+        let now = Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap();
+        let headers = signed_headers(
+            &credentials,
+            "us-east-1",
+            "AmazonAthena.GetQueryExecution",
+            r#"{"QueryExecutionId":"abc"}"#,
+            now,
+        );
+        let authorization = &headers.last().unwrap().1;
+        assert_eq!(
+            authorization,
+            "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20240501/us-east-1/athena/aws4_request, \
+             SignedHeaders=content-type;host;x-amz-date;x-amz-target, \
+             Signature=2fb71c6f65e83aec727623cef557397af3fbde24401451df9255f26f19ef4482"
+        );
+    }
+
+    #[test]
+    fn test_table_url() {
+        let url = Url::parse("athena://eu-west-1/analytics#orders").unwrap();
+        let options = HashMap::from([
+            ("access_key_id".to_string(), "AKID".to_string()),
+            ("secret_access_key".to_string(), "secret".to_string()),
+        ]);
+        let source = AthenaSource::new(&url, &options).unwrap();
+        assert_eq!(source.region, "eu-west-1");
+        assert_eq!(source.database.as_deref(), Some("analytics"));
+        assert_eq!(source.query, "SELECT * FROM \"orders\"");
+        assert_eq!(source.workgroup, "primary");
+    }
+}
@@ -0,0 +1,1305 @@
+use arrow::datatypes::SchemaRef;
+use arrow::ipc::reader::StreamReader;
+use arrow::record_batch::RecordBatch;
+use async_trait::async_trait;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use google_cloud_auth::credentials::CredentialsFile;
+use google_cloud_auth::project::{create_token_source_from_project, project, Config, Project};
+use google_cloud_auth::token_source::TokenSource;
+use std::collections::HashMap;
+use std::io::Cursor;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tonic::codegen::http::uri::PathAndQuery;
+use tonic::metadata::MetadataValue;
+use tonic::transport::{Channel, ClientTlsConfig};
+use tracing::info;
+use url::Url;
+
+use crate::{
+    connectors::{connection_url::percent_decode, parquet::ParquetSource, Source, Target},
+    protocols::{
+        flight::{normalize, typed_values},
+        Protocol,
+    },
+    schema::{Column, DataType, Row, Schema, Value},
+    strict::Coercion,
+    Result, TinyEtlError,
+};
+
+const STORAGE_ENDPOINT: &str = "https://bigquerystorage.googleapis.com";
+const API_ENDPOINT: &str = "https://bigquery.googleapis.com/bigquery/v2";
+const UPLOAD_ENDPOINT: &str = "https://bigquery.googleapis.com/upload/bigquery/v2";
+const SCOPES: [&str; 1] = ["https://www.googleapis.com/auth/bigquery"];
+/// Streams requested for a read session unless `max_streams` is set; the
+/// service may create fewer for small tables
+const DEFAULT_STREAMS: usize = 4;
+/// Record batches decoded ahead of `read_batch`, across all streams
+const BATCH_QUEUE: usize = 8;
+const QUERY_POLL_INTERVAL: Duration = Duration::from_secs(1);
+/// Bytes of JSON lines sent per request of a load job's upload; resumable
+/// uploads take chunks in multiples of 256 KiB
+const UPLOAD_CHUNK: usize = 32 * 256 * 1024;
+
+/// Messages of the BigQuery Storage Read API
+/// (`google.cloud.bigquery.storage.v1`) the source uses. Only the fields read
+/// or set are declared; oneof members are plain optional fields on the wire.
+mod storage {
+    use tonic_prost::prost;
+
+    pub const DATA_FORMAT_ARROW: i32 = 2;
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    #[prost(prost_path = "::tonic_prost::prost")]
+    pub struct CreateReadSessionRequest {
+        #[prost(string, tag = "1")]
+        pub parent: String,
+        #[prost(message, optional, tag = "2")]
+        pub read_session: Option<ReadSession>,
+        #[prost(int32, tag = "3")]
+        pub max_stream_count: i32,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    #[prost(prost_path = "::tonic_prost::prost")]
+    pub struct ReadSession {
+        #[prost(string, tag = "1")]
+        pub name: String,
+        #[prost(int32, tag = "3")]
+        pub data_format: i32,
+        #[prost(message, optional, tag = "5")]
+        pub arrow_schema: Option<ArrowSchema>,
+        #[prost(string, tag = "6")]
+        pub table: String,
+        #[prost(message, optional, tag = "8")]
+        pub read_options: Option<TableReadOptions>,
+        #[prost(message, repeated, tag = "10")]
+        pub streams: Vec<ReadStream>,
+        #[prost(int64, tag = "14")]
+        pub estimated_row_count: i64,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    #[prost(prost_path = "::tonic_prost::prost")]
+    pub struct TableReadOptions {
+        #[prost(string, repeated, tag = "1")]
+        pub selected_fields: Vec<String>,
+        #[prost(string, tag = "2")]
+        pub row_restriction: String,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    #[prost(prost_path = "::tonic_prost::prost")]
+    pub struct ReadStream {
+        #[prost(string, tag = "1")]
+        pub name: String,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    #[prost(prost_path = "::tonic_prost::prost")]
+    pub struct ArrowSchema {
+        #[prost(bytes = "vec", tag = "1")]
+        pub serialized_schema: Vec<u8>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    #[prost(prost_path = "::tonic_prost::prost")]
+    pub struct ArrowRecordBatch {
+        #[prost(bytes = "vec", tag = "1")]
+        pub serialized_record_batch: Vec<u8>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    #[prost(prost_path = "::tonic_prost::prost")]
+    pub struct ReadRowsRequest {
+        #[prost(string, tag = "1")]
+        pub read_stream: String,
+        #[prost(int64, tag = "2")]
+        pub offset: i64,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    #[prost(prost_path = "::tonic_prost::prost")]
+    pub struct ReadRowsResponse {
+        #[prost(message, optional, tag = "4")]
+        pub arrow_record_batch: Option<ArrowRecordBatch>,
+        #[prost(int64, tag = "6")]
+        pub row_count: i64,
+    }
+}
+
+/// BigQuery protocol: sources read a table (`bigquery://project/dataset#table`
+/// or `bigquery://project/dataset/table`) or the result of the `query` option
+/// (`bigquery://project`) through the Storage Read API, as Arrow record
+/// batches from parallel streams. Targets load a table with a load job.
+pub struct BigQueryProtocol;
+
+impl Default for BigQueryProtocol {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BigQueryProtocol {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+#[async_trait]
+impl Protocol for BigQueryProtocol {
+    async fn create_source(
+        &self,
+        url: &Url,
+        options: &HashMap<String, String>,
+    ) -> Result<Box<dyn Source>> {
+        Ok(Box::new(BigQuerySource::new(url, options)?))
+    }
+
+    async fn create_target(
+        &self,
+        url: &Url,
+        options: &HashMap<String, String>,
+    ) -> Result<Box<dyn Target>> {
+        Ok(Box::new(BigQueryTarget::new(url, options)?))
+    }
+
+    fn validate_url(&self, url: &Url) -> Result<()> {
+        if url.scheme() != "bigquery" {
+            return Err(TinyEtlError::Configuration(format!(
+                "BigQuery protocol requires bigquery:// scheme, got: {}",
+                url.scheme()
+            )));
+        }
+
+        if url.host().is_none() {
+            return Err(TinyEtlError::Configuration(
+                "BigQuery protocol requires a project id as host".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "bigquery"
+    }
+}
+
+/// A BigQuery table
+#[derive(Debug, Clone, PartialEq)]
+struct TableRef {
+    project: String,
+    dataset: String,
+    table: String,
+}
+
+impl TableRef {
+    /// The table of a URL, `None` for `bigquery://project`
+    fn from_url(url: &Url, project: &str) -> Result<Option<Self>> {
+        let mut segments: Vec<String> = url
+            .path_segments()
+            .into_iter()
+            .flatten()
+            .filter(|segment| !segment.is_empty())
+            .map(percent_decode)
+            .collect();
+        if let Some(table) = url.fragment().filter(|table| !table.is_empty()) {
+            segments.push(percent_decode(table));
+        }
+        match segments.as_slice() {
+            [] => Ok(None),
+            [dataset, table] => Ok(Some(Self {
+                project: project.to_string(),
+                dataset: dataset.clone(),
+                table: table.clone(),
+            })),
+            _ => Err(TinyEtlError::Configuration(format!(
+                "BigQuery table must be bigquery://project/dataset#table or bigquery://project/dataset/table, not {}",
+                url
+            ))),
+        }
+    }
+
+    /// Resource name used by the Storage Read API
+    fn path(&self) -> String {
+        format!(
+            "projects/{}/datasets/{}/tables/{}",
+            self.project, self.dataset, self.table
+        )
+    }
+
+    /// URL of the table in the BigQuery API
+    fn api_url(&self) -> String {
+        format!("{}/{}", API_ENDPOINT, self.path())
+    }
+
+    fn reference(&self) -> serde_json::Value {
+        serde_json::json!({
+            "projectId": self.project,
+            "datasetId": self.dataset,
+            "tableId": self.table,
+        })
+    }
+}
+
+/// What a source reads, and with which credentials
+#[derive(Debug, Clone)]
+struct ReadSettings {
+    /// Project running the read session and query jobs, and billed for them
+    billing_project: String,
+    table: Option<TableRef>,
+    query: Option<String>,
+    location: Option<String>,
+    credentials_file: Option<String>,
+    selected_fields: Vec<String>,
+    row_restriction: String,
+    max_streams: usize,
+}
+
+impl ReadSettings {
+    fn parse(url: &Url, options: &HashMap<String, String>) -> Result<Self> {
+        let project = url_project(url)?;
+        let table = TableRef::from_url(url, &project)?;
+        let query = options.get("query").cloned();
+        match (&table, &query) {
+            (Some(_), Some(_)) => {
+                return Err(TinyEtlError::Configuration(
+                    "A BigQuery source reads either a table or a query, not both".to_string(),
+                ))
+            }
+            (None, None) => {
+                return Err(TinyEtlError::Configuration(format!(
+                    "BigQuery source must be bigquery://project/dataset#table, or bigquery://project with a query, not {}",
+                    url
+                )))
+            }
+            _ => {}
+        }
+
+        let max_streams = match options.get("max_streams") {
+            Some(value) => value
+                .parse::<usize>()
+                .ok()
+                .filter(|streams| *streams > 0)
+                .ok_or_else(|| {
+                    TinyEtlError::Configuration(format!(
+                        "Invalid max_streams '{}': must be a positive integer",
+                        value
+                    ))
+                })?,
+            None => DEFAULT_STREAMS,
+        };
+
+        Ok(Self {
+            billing_project: options.get("billing_project").cloned().unwrap_or(project),
+            table,
+            query,
+            location: options.get("location").cloned(),
+            credentials_file: options.get("credentials_file").cloned(),
+            selected_fields: options
+                .get("selected_fields")
+                .map(|fields| {
+                    fields
+                        .split(',')
+                        .map(|field| field.trim().to_string())
+                        .filter(|field| !field.is_empty())
+                        .collect()
+                })
+                .unwrap_or_default(),
+            row_restriction: options.get("row_restriction").cloned().unwrap_or_default(),
+            max_streams,
+        })
+    }
+}
+
+fn url_project(url: &Url) -> Result<String> {
+    url.host_str()
+        .map(percent_decode)
+        .ok_or_else(|| TinyEtlError::Configuration("BigQuery URL must name a project".to_string()))
+}
+
+fn auth_error(e: google_cloud_auth::error::Error) -> TinyEtlError {
+    TinyEtlError::Connection(format!("BigQuery authentication failed: {}", e))
+}
+
+fn storage_error(e: tonic::Status) -> TinyEtlError {
+    TinyEtlError::DataTransfer(format!("BigQuery Storage Read API failed: {}", e.message()))
+}
+
+/// Access tokens from the `credentials_file` option, or from the
+/// application default credentials (`GOOGLE_APPLICATION_CREDENTIALS`, the
+/// gcloud login or the metadata server)
+async fn token_source(credentials_file: Option<&str>) -> Result<Arc<dyn TokenSource>> {
+    let project = match credentials_file {
+        Some(path) => Project::FromFile(Box::new(
+            CredentialsFile::new_from_file(path.to_string())
+                .await
+                .map_err(auth_error)?,
+        )),
+        None => project().await.map_err(auth_error)?,
+    };
+    let source = create_token_source_from_project(&project, Config::default().with_scopes(&SCOPES))
+        .await
+        .map_err(auth_error)?;
+    Ok(Arc::from(source))
+}
+
+/// Poll a job until it is done and return it; a job that failed is an
+/// error. `what` names the job in errors.
+async fn finished_job(
+    http: &reqwest::Client,
+    token: &str,
+    jobs_url: &str,
+    mut job: serde_json::Value,
+    what: &str,
+) -> Result<serde_json::Value> {
+    let job_error =
+        |e: reqwest::Error| TinyEtlError::DataTransfer(format!("BigQuery {} failed: {}", what, e));
+    while job["status"]["state"] != "DONE" {
+        tokio::time::sleep(QUERY_POLL_INTERVAL).await;
+        let job_id = job["jobReference"]["jobId"].as_str().unwrap_or_default();
+        let mut request = http
+            .get(format!("{}/{}", jobs_url, job_id))
+            .header("Authorization", token);
+        if let Some(location) = job["jobReference"]["location"].as_str() {
+            request = request.query(&[("location", location)]);
+        }
+        job = request
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(job_error)?
+            .json()
+            .await
+            .map_err(job_error)?;
+    }
+
+    if let Some(message) = job["status"]["errorResult"]["message"].as_str() {
+        return Err(TinyEtlError::DataTransfer(format!(
+            "BigQuery {} failed: {}",
+            what, message
+        )));
+    }
+    Ok(job)
+}
+
+/// Client of the Storage Read API. Cloning it shares the connection.
+#[derive(Clone)]
+struct StorageClient {
+    grpc: tonic::client::Grpc<Channel>,
+    tokens: Arc<dyn TokenSource>,
+}
+
+impl StorageClient {
+    /// The connection is made by the first request
+    fn new(tokens: Arc<dyn TokenSource>) -> Result<Self> {
+        let channel = Channel::from_static(STORAGE_ENDPOINT)
+            .tls_config(ClientTlsConfig::new().with_native_roots())
+            .map(|endpoint| endpoint.connect_lazy())
+            .map_err(|e| {
+                TinyEtlError::Connection(format!("Failed to connect to BigQuery Storage: {}", e))
+            })?;
+        Ok(Self {
+            grpc: tonic::client::Grpc::new(channel),
+            tokens,
+        })
+    }
+
+    /// A request with credentials, routed by the resource it names
+    async fn request<T>(&self, message: T, routing: (&str, &str)) -> Result<tonic::Request<T>> {
+        let token = self.tokens.token().await.map_err(auth_error)?.value();
+        let routing: String = url::form_urlencoded::Serializer::new(String::new())
+            .append_pair(routing.0, routing.1)
+            .finish();
+        let mut request = tonic::Request::new(message);
+        let metadata = request.metadata_mut();
+        for (key, value) in [("authorization", token), ("x-goog-request-params", routing)] {
+            let value = MetadataValue::try_from(value).map_err(|e| {
+                TinyEtlError::Connection(format!("Invalid BigQuery request metadata: {}", e))
+            })?;
+            metadata.insert(key, value);
+        }
+        Ok(request)
+    }
+
+    async fn ready(&mut self) -> Result<()> {
+        self.grpc.ready().await.map_err(|e| {
+            TinyEtlError::Connection(format!("Failed to connect to BigQuery Storage: {}", e))
+        })
+    }
+
+    async fn create_read_session(
+        &mut self,
+        request: storage::CreateReadSessionRequest,
+    ) -> Result<storage::ReadSession> {
+        let table = request
+            .read_session
+            .as_ref()
+            .map(|session| session.table.clone())
+            .unwrap_or_default();
+        let request = self
+            .request(request, ("read_session.table", &table))
+            .await?;
+        self.ready().await?;
+        let response = self
+            .grpc
+            .unary(
+                request,
+                PathAndQuery::from_static(
+                    "/google.cloud.bigquery.storage.v1.BigQueryRead/CreateReadSession",
+                ),
+                tonic_prost::ProstCodec::default(),
+            )
+            .await
+            .map_err(storage_error)?;
+        Ok(response.into_inner())
+    }
+
+    async fn read_rows(
+        &mut self,
+        stream: &str,
+    ) -> Result<tonic::Streaming<storage::ReadRowsResponse>> {
+        let message = storage::ReadRowsRequest {
+            read_stream: stream.to_string(),
+            offset: 0,
+        };
+        let request = self.request(message, ("read_stream", stream)).await?;
+        self.ready().await?;
+        let response = self
+            .grpc
+            .server_streaming(
+                request,
+                PathAndQuery::from_static(
+                    "/google.cloud.bigquery.storage.v1.BigQueryRead/ReadRows",
+                ),
+                tonic_prost::ProstCodec::default(),
+            )
+            .await
+            .map_err(storage_error)?;
+        Ok(response.into_inner())
+    }
+}
+
+/// Decode the record batches of a ReadRows response. BigQuery sends the
+/// schema and each batch as separate Arrow IPC messages, which together
+/// make a stream.
+fn decode_batches(serialized_schema: &[u8], serialized_batch: &[u8]) -> Result<Vec<RecordBatch>> {
+    let mut message = Vec::with_capacity(serialized_schema.len() + serialized_batch.len());
+    message.extend_from_slice(serialized_schema);
+    message.extend_from_slice(serialized_batch);
+    let reader = StreamReader::try_new(Cursor::new(message), None)
+        .map_err(|e| TinyEtlError::DataTransfer(format!("Invalid BigQuery Arrow data: {}", e)))?;
+    reader
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .map_err(|e| TinyEtlError::DataTransfer(format!("Invalid BigQuery Arrow data: {}", e)))
+}
+
+/// Read one stream of a session to its end, sending its record batches
+async fn read_stream(
+    mut client: StorageClient,
+    stream: String,
+    serialized_schema: Arc<Vec<u8>>,
+    sender: mpsc::Sender<Result<RecordBatch>>,
+) -> Result<()> {
+    let mut responses = client.read_rows(&stream).await?;
+    while let Some(response) = responses.message().await.map_err(storage_error)? {
+        let Some(batch) = response.arrow_record_batch else {
+            continue;
+        };
+        for batch in decode_batches(&serialized_schema, &batch.serialized_record_batch)? {
+            if sender.send(Ok(batch)).await.is_err() {
+                // The source was reset or dropped
+                return Ok(());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Source reading a BigQuery table, or a query's result table, through a
+/// Storage Read API session. The session's streams are read in parallel,
+/// so rows don't come in table order.
+pub struct BigQuerySource {
+    settings: ReadSettings,
+    client: Option<StorageClient>,
+    session: Option<storage::ReadSession>,
+    arrow_schema: Option<SchemaRef>,
+    receiver: Option<mpsc::Receiver<Result<RecordBatch>>>,
+    readers: Vec<JoinHandle<()>>,
+    /// Rows of the last record batch not handed out yet
+    pending: Vec<Row>,
+    exhausted: bool,
+    coercion: Coercion,
+}
+
+impl BigQuerySource {
+    fn new(url: &Url, options: &HashMap<String, String>) -> Result<Self> {
+        Ok(Self {
+            settings: ReadSettings::parse(url, options)?,
+            client: None,
+            session: None,
+            arrow_schema: None,
+            receiver: None,
+            readers: Vec::new(),
+            pending: Vec::new(),
+            exhausted: false,
+            coercion: Coercion::default(),
+        })
+    }
+
+    /// Source reading the result of a query run in the URL's project, for
+    /// `--query`
+    pub fn from_query(connection_string: &str, query: &str) -> Result<Self> {
+        let mut url = Url::parse(connection_string)
+            .map_err(|e| TinyEtlError::Configuration(format!("Invalid BigQuery URL: {}", e)))?;
+        url.set_fragment(None);
+        let options = HashMap::from([("query".to_string(), query.to_string())]);
+        Self::new(&url, &options)
+    }
+
+    /// Run the query as a job and return the table holding its result
+    async fn run_query(&self, query: &str, tokens: &dyn TokenSource) -> Result<TableRef> {
+        let http = reqwest::Client::new();
+        let token = tokens.token().await.map_err(auth_error)?.value();
+        let jobs_url = format!(
+            "{}/projects/{}/jobs",
+            API_ENDPOINT, self.settings.billing_project
+        );
+        let mut body = serde_json::json!({
+            "configuration": {
+                "query": { "query": query, "useLegacySql": false }
+            }
+        });
+        if let Some(location) = &self.settings.location {
+            body["jobReference"] = serde_json::json!({ "location": location });
+        }
+
+        let query_error =
+            |e: reqwest::Error| TinyEtlError::DataTransfer(format!("BigQuery query failed: {}", e));
+        let job: serde_json::Value = http
+            .post(&jobs_url)
+            .header("Authorization", &token)
+            .json(&body)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(query_error)?
+            .json()
+            .await
+            .map_err(query_error)?;
+        info!(
+            "Running BigQuery query job {}",
+            job["id"].as_str().unwrap_or("?")
+        );
+
+        let job = finished_job(&http, &token, &jobs_url, job, "query").await?;
+        let destination = &job["configuration"]["query"]["destinationTable"];
+        match (
+            destination["projectId"].as_str(),
+            destination["datasetId"].as_str(),
+            destination["tableId"].as_str(),
+        ) {
+            (Some(project), Some(dataset), Some(table)) => Ok(TableRef {
+                project: project.to_string(),
+                dataset: dataset.to_string(),
+                table: table.to_string(),
+            }),
+            // Scripts and DDL statements have no result table
+            _ => Err(TinyEtlError::DataTransfer(
+                "BigQuery query has no result table to read; use a SELECT statement".to_string(),
+            )),
+        }
+    }
+
+    fn session(&self) -> Result<&storage::ReadSession> {
+        self.session
+            .as_ref()
+            .ok_or_else(|| TinyEtlError::Connection("BigQuery source is not connected".to_string()))
+    }
+
+    /// Start reading every stream of the session
+    fn start_readers(&mut self) -> Result<()> {
+        let session = self.session()?;
+        let client = self.client.clone().ok_or_else(|| {
+            TinyEtlError::Connection("BigQuery source is not connected".to_string())
+        })?;
+        let serialized_schema = Arc::new(
+            session
+                .arrow_schema
+                .as_ref()
+                .map(|schema| schema.serialized_schema.clone())
+                .unwrap_or_default(),
+        );
+
+        let (sender, receiver) = mpsc::channel(BATCH_QUEUE);
+        let readers = session
+            .streams
+            .iter()
+            .map(|stream| {
+                let sender = sender.clone();
+                let read = read_stream(
+                    client.clone(),
+                    stream.name.clone(),
+                    serialized_schema.clone(),
+                    sender.clone(),
+                );
+                tokio::spawn(async move {
+                    if let Err(e) = read.await {
+                        let _ = sender.send(Err(e)).await;
+                    }
+                })
+            })
+            .collect();
+        self.readers = readers;
+        // The channel closes once every stream has been read
+        self.receiver = Some(receiver);
+        Ok(())
+    }
+
+    fn stop_readers(&mut self) {
+        for reader in self.readers.drain(..) {
+            reader.abort();
+        }
+        self.receiver = None;
+    }
+}
+
+impl Drop for BigQuerySource {
+    fn drop(&mut self) {
+        self.stop_readers();
+    }
+}
+
+#[async_trait]
+impl Source for BigQuerySource {
+    async fn connect(&mut self) -> Result<()> {
+        let tokens = token_source(self.settings.credentials_file.as_deref()).await?;
+        let table = match (&self.settings.table, &self.settings.query) {
+            (Some(table), _) => table.clone(),
+            (None, Some(query)) => self.run_query(query, tokens.as_ref()).await?,
+            (None, None) => {
+                return Err(TinyEtlError::Configuration(
+                    "BigQuery source needs a table or a query".to_string(),
+                ))
+            }
+        };
+
+        let mut client = StorageClient::new(tokens)?;
+        let request = storage::CreateReadSessionRequest {
+            parent: format!("projects/{}", self.settings.billing_project),
+            read_session: Some(storage::ReadSession {
+                table: table.path(),
+                data_format: storage::DATA_FORMAT_ARROW,
+                read_options: Some(storage::TableReadOptions {
+                    selected_fields: self.settings.selected_fields.clone(),
+                    row_restriction: self.settings.row_restriction.clone(),
+                }),
+                ..Default::default()
+            }),
+            max_stream_count: i32::try_from(self.settings.max_streams).unwrap_or(i32::MAX),
+        };
+        let session = client.create_read_session(request).await?;
+        info!(
+            "Reading BigQuery table {} with {} stream(s)",
+            table.path(),
+            session.streams.len()
+        );
+
+        // Empty tables have no streams, and sessions over them no schema
+        self.arrow_schema = match &session.arrow_schema {
+            Some(schema) => Some(
+                StreamReader::try_new(Cursor::new(&schema.serialized_schema), None)
+                    .map_err(|e| {
+                        TinyEtlError::DataTransfer(format!("Invalid BigQuery Arrow schema: {}", e))
+                    })?
+                    .schema(),
+            ),
+            None => None,
+        };
+        self.client = Some(client);
+        self.session = Some(session);
+        Ok(())
+    }
+
+    async fn infer_schema(&mut self, _sample_size: usize) -> Result<Schema> {
+        let mut schema = match &self.arrow_schema {
+            Some(arrow_schema) => Schema::from_arrow_schema(arrow_schema),
+            None => Schema {
+                columns: Vec::new(),
+                estimated_rows: None,
+                primary_key_candidate: None,
+            },
+        };
+        schema.estimated_rows = self.estimated_row_count().await?;
+        Ok(schema)
+    }
+
+    async fn read_batch(&mut self, batch_size: usize) -> Result<Vec<Row>> {
+        if self.receiver.is_none() && !self.exhausted {
+            self.start_readers()?;
+        }
+        while self.pending.len() < batch_size && !self.exhausted {
+            let next = match self.receiver.as_mut() {
+                Some(receiver) => receiver.recv().await,
+                None => None,
+            };
+            match next {
+                Some(Ok(batch)) => {
+                    let mut rows =
+                        ParquetSource::record_batch_to_rows(&normalize(&batch)?, self.coercion)?;
+                    typed_values(&batch.schema(), &mut rows);
+                    self.pending.extend(rows);
+                }
+                Some(Err(e)) => {
+                    self.stop_readers();
+                    return Err(e);
+                }
+                None => self.exhausted = true,
+            }
+        }
+        let count = batch_size.min(self.pending.len());
+        Ok(self.pending.drain(..count).collect())
+    }
+
+    async fn estimated_row_count(&self) -> Result<Option<usize>> {
+        Ok(self
+            .session
+            .as_ref()
+            .and_then(|session| usize::try_from(session.estimated_row_count).ok()))
+    }
+
+    fn set_coercion(&mut self, coercion: Coercion) {
+        self.coercion = coercion;
+    }
+
+    // Each read stream is consumed on its own task
+    async fn has_stable_order(&mut self) -> Result<bool> {
+        Ok(false)
+    }
+
+    async fn reset(&mut self) -> Result<()> {
+        // Streams can be read again from the start while the session lasts
+        self.stop_readers();
+        self.pending.clear();
+        self.exhausted = false;
+        Ok(())
+    }
+
+    fn has_more(&self) -> bool {
+        !self.exhausted || !self.pending.is_empty()
+    }
+}
+
+/// BigQuery column type of a TinyETL type, as the table API names it
+fn bigquery_type(data_type: &DataType) -> &'static str {
+    match data_type {
+        DataType::Integer => "INTEGER",
+        // NUMERIC keeps only 9 decimal places
+        DataType::Decimal => "BIGNUMERIC",
+        DataType::Boolean => "BOOLEAN",
+        DataType::Date => "DATE",
+        DataType::DateTime => "TIMESTAMP",
+        DataType::Json => "JSON",
+        DataType::Binary => "BYTES",
+        DataType::String | DataType::Null => "STRING",
+    }
+}
+
+/// Schema of a new table
+fn table_schema(columns: &[Column]) -> serde_json::Value {
+    let fields: Vec<serde_json::Value> = columns
+        .iter()
+        .map(|column| {
+            serde_json::json!({
+                "name": column.name,
+                "type": bigquery_type(&column.data_type),
+                "mode": if column.nullable { "NULLABLE" } else { "REQUIRED" },
+            })
+        })
+        .collect();
+    serde_json::json!({ "fields": fields })
+}
+
+/// A value as a load job reads it from a JSON line
+fn json_value(value: &Value, data_type: &DataType) -> serde_json::Value {
+    match (value, data_type) {
+        (Value::Null, _) => serde_json::Value::Null,
+        (Value::Date(date), DataType::Date) => date.format("%Y-%m-%d").to_string().into(),
+        (Value::Date(date), _) => date.format("%Y-%m-%d %H:%M:%S%.6f UTC").to_string().into(),
+        (Value::Json(json), DataType::Json) => json.clone(),
+        (Value::Json(json), _) => json.to_string().into(),
+        (Value::Bytes(bytes), _) => STANDARD.encode(bytes).into(),
+        (Value::Integer(i), _) => (*i).into(),
+        (Value::Boolean(b), _) => (*b).into(),
+        // As text, so no digits are lost to a float
+        (Value::Decimal(d), _) => d.to_string().into(),
+        (Value::String(text), _) => text.as_str().into(),
+    }
+}
+
+/// `Content-Range` of a chunk of a resumable upload; the last one gives
+/// the total size
+fn content_range(start: usize, length: usize, last: bool) -> String {
+    match (length, last) {
+        (0, _) => format!("bytes */{}", start),
+        (_, true) => format!("bytes {}-{}/{}", start, start + length - 1, start + length),
+        (_, false) => format!("bytes {}-{}/*", start, start + length - 1),
+    }
+}
+
+/// Target loading a BigQuery table (`bigquery://project/dataset#table`) with
+/// one load job per run. Rows are sent as newline-delimited JSON over the
+/// job's resumable upload, a chunk at a time as batches arrive, and the job
+/// loads them once the upload is complete, so a failed run loads nothing.
+pub struct BigQueryTarget {
+    table: TableRef,
+    /// Project running the load job
+    billing_project: String,
+    location: Option<String>,
+    credentials_file: Option<String>,
+    http: reqwest::Client,
+    tokens: Option<Arc<dyn TokenSource>>,
+    columns: Vec<Column>,
+    /// JSON lines not uploaded yet
+    buffer: Vec<u8>,
+    /// Resumable upload of the load job, started by the first chunk
+    upload_url: Option<String>,
+    uploaded: usize,
+}
+
+impl BigQueryTarget {
+    fn new(url: &Url, options: &HashMap<String, String>) -> Result<Self> {
+        let project = url_project(url)?;
+        let table = TableRef::from_url(url, &project)?.ok_or_else(|| {
+            TinyEtlError::Configuration(format!(
+                "BigQuery target must be bigquery://project/dataset#table, not {}",
+                url
+            ))
+        })?;
+        Ok(Self {
+            table,
+            billing_project: options.get("billing_project").cloned().unwrap_or(project),
+            location: options.get("location").cloned(),
+            credentials_file: options.get("credentials_file").cloned(),
+            http: reqwest::Client::new(),
+            tokens: None,
+            columns: Vec::new(),
+            buffer: Vec::new(),
+            upload_url: None,
+            uploaded: 0,
+        })
+    }
+
+    async fn token(&self) -> Result<String> {
+        let tokens = self.tokens.as_ref().ok_or_else(|| {
+            TinyEtlError::Connection("BigQuery target is not connected".to_string())
+        })?;
+        Ok(tokens.token().await.map_err(auth_error)?.value())
+    }
+
+    fn jobs_url(&self) -> String {
+        format!("{}/projects/{}/jobs", API_ENDPOINT, self.billing_project)
+    }
+
+    fn job_reference(&self) -> serde_json::Value {
+        let mut reference = serde_json::json!({ "projectId": self.billing_project });
+        if let Some(location) = &self.location {
+            reference["location"] = location.as_str().into();
+        }
+        reference
+    }
+
+    /// Status of a table API request: `Ok(false)` for a table that isn't
+    /// there
+    async fn table_request(&self, method: reqwest::Method, what: &str) -> Result<bool> {
+        let response = self
+            .http
+            .request(method, self.table.api_url())
+            .header("Authorization", self.token().await?)
+            .send()
+            .await
+            .map_err(|e| {
+                TinyEtlError::Connection(format!("BigQuery {} request failed: {}", what, e))
+            })?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(false);
+        }
+        response.error_for_status().map_err(|e| {
+            TinyEtlError::DataTransfer(format!("BigQuery {} request failed: {}", what, e))
+        })?;
+        Ok(true)
+    }
+
+    /// Start the load job's resumable upload and return its URL
+    async fn start_upload(&self) -> Result<String> {
+        let body = serde_json::json!({
+            "jobReference": self.job_reference(),
+            "configuration": {
+                "load": {
+                    "destinationTable": self.table.reference(),
+                    "sourceFormat": "NEWLINE_DELIMITED_JSON",
+                    "writeDisposition": "WRITE_APPEND",
+                    "createDisposition": "CREATE_NEVER",
+                }
+            }
+        });
+        let upload_error =
+            |e: reqwest::Error| TinyEtlError::DataTransfer(format!("BigQuery load failed: {}", e));
+        let response = self
+            .http
+            .post(format!(
+                "{}/projects/{}/jobs",
+                UPLOAD_ENDPOINT, self.billing_project
+            ))
+            .query(&[("uploadType", "resumable")])
+            .header("Authorization", self.token().await?)
+            .json(&body)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(upload_error)?;
+        response
+            .headers()
+            .get(reqwest::header::LOCATION)
+            .and_then(|location| location.to_str().ok())
+            .map(str::to_string)
+            .ok_or_else(|| {
+                TinyEtlError::DataTransfer(
+                    "BigQuery load failed: no upload URL in the response".to_string(),
+                )
+            })
+    }
+
+    /// Upload the next chunk of the buffer, or all of it as the last one,
+    /// whose response is the load job
+    async fn upload_chunk(&mut self, last: bool) -> Result<reqwest::Response> {
+        let upload_url = match &self.upload_url {
+            Some(upload_url) => upload_url.clone(),
+            None => {
+                let upload_url = self.start_upload().await?;
+                self.upload_url = Some(upload_url.clone());
+                upload_url
+            }
+        };
+        let length = if last {
+            self.buffer.len()
+        } else {
+            UPLOAD_CHUNK
+        };
+        let chunk: Vec<u8> = self.buffer.drain(..length).collect();
+        let range = content_range(self.uploaded, length, last);
+
+        let response = self
+            .http
+            .put(&upload_url)
+            .header("Content-Range", range)
+            .body(chunk)
+            .send()
+            .await
+            .map_err(|e| TinyEtlError::DataTransfer(format!("BigQuery load failed: {}", e)))?;
+        self.uploaded += length;
+        // 308 Resume Incomplete acknowledges a chunk before the last
+        let accepted = if last {
+            response.status().is_success()
+        } else {
+            response.status().as_u16() == 308
+        };
+        if !accepted {
+            let status = response.status();
+            let detail = response.text().await.unwrap_or_default();
+            return Err(TinyEtlError::DataTransfer(format!(
+                "BigQuery load failed: upload answered {}: {}",
+                status,
+                detail.trim()
+            )));
+        }
+        Ok(response)
+    }
+}
+
+#[async_trait]
+impl Target for BigQueryTarget {
+    async fn connect(&mut self) -> Result<()> {
+        self.tokens = Some(token_source(self.credentials_file.as_deref()).await?);
+        Ok(())
+    }
+
+    fn bytes_written(&self) -> Option<u64> {
+        Some(self.uploaded as u64)
+    }
+
+    async fn create_table(&mut self, _table_name: &str, schema: &Schema) -> Result<()> {
+        if !self.table_request(reqwest::Method::GET, "table").await? {
+            info!("Creating BigQuery table {}", self.table.path());
+            let body = serde_json::json!({
+                "tableReference": self.table.reference(),
+                "schema": table_schema(&schema.columns),
+            });
+            let create_error = |e: reqwest::Error| {
+                TinyEtlError::DataTransfer(format!("BigQuery table creation failed: {}", e))
+            };
+            self.http
+                .post(format!(
+                    "{}/projects/{}/datasets/{}/tables",
+                    API_ENDPOINT, self.table.project, self.table.dataset
+                ))
+                .header("Authorization", self.token().await?)
+                .json(&body)
+                .send()
+                .await
+                .and_then(|response| response.error_for_status())
+                .map_err(create_error)?;
+        }
+        self.columns = schema.columns.clone();
+        Ok(())
+    }
+
+    async fn write_batch(&mut self, rows: &[Row]) -> Result<usize> {
+        for row in rows {
+            let line: serde_json::Map<String, serde_json::Value> = self
+                .columns
+                .iter()
+                .map(|column| {
+                    let value = row.get(&column.name).unwrap_or(&Value::Null);
+                    (column.name.clone(), json_value(value, &column.data_type))
+                })
+                .collect();
+            serde_json::to_writer(&mut self.buffer, &line)?;
+            self.buffer.push(b'\n');
+        }
+        while self.buffer.len() >= UPLOAD_CHUNK {
+            self.upload_chunk(false).await?;
+        }
+        Ok(rows.len())
+    }
+
+    async fn finalize(&mut self) -> Result<()> {
+        if self.upload_url.is_none() && self.buffer.is_empty() {
+            return Ok(());
+        }
+        let job: serde_json::Value = self
+            .upload_chunk(true)
+            .await?
+            .json()
+            .await
+            .map_err(|e| TinyEtlError::DataTransfer(format!("BigQuery load failed: {}", e)))?;
+        info!(
+            "Running BigQuery load job {}",
+            job["id"].as_str().unwrap_or("?")
+        );
+        let token = self.token().await?;
+        let job = finished_job(&self.http, &token, &self.jobs_url(), job, "load").await?;
+        info!(
+            "Loaded {} rows into BigQuery table {}",
+            job["statistics"]["load"]["outputRows"]
+                .as_str()
+                .unwrap_or("?"),
+            self.table.path()
+        );
+        Ok(())
+    }
+
+    async fn exists(&self, _table_name: &str) -> Result<bool> {
+        self.table_request(reqwest::Method::GET, "table").await
+    }
+
+    async fn truncate(&mut self, _table_name: &str) -> Result<()> {
+        let token = self.token().await?;
+        let body = serde_json::json!({
+            "jobReference": self.job_reference(),
+            "configuration": {
+                "query": {
+                    "query": format!(
+                        "TRUNCATE TABLE `{}.{}.{}`",
+                        self.table.project, self.table.dataset, self.table.table
+                    ),
+                    "useLegacySql": false,
+                }
+            }
+        });
+        let truncate_error = |e: reqwest::Error| {
+            TinyEtlError::DataTransfer(format!("BigQuery truncate failed: {}", e))
+        };
+        let job: serde_json::Value = self
+            .http
+            .post(self.jobs_url())
+            .header("Authorization", &token)
+            .json(&body)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(truncate_error)?
+            .json()
+            .await
+            .map_err(truncate_error)?;
+        finished_job(&self.http, &token, &self.jobs_url(), job, "truncate").await?;
+        Ok(())
+    }
+
+    async fn drop_table(&mut self, _table_name: &str) -> Result<()> {
+        self.table_request(reqwest::Method::DELETE, "table deletion")
+            .await?;
+        Ok(())
+    }
+
+    fn supports_append(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::{Int64Array, StringArray};
+    use arrow::datatypes::{DataType as ArrowDataType, Field};
+    use arrow::ipc::writer::StreamWriter;
+
+    fn options(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_table_and_query_urls() {
+        let url = Url::parse("bigquery://bigquery-public-data/samples/shakespeare").unwrap();
+        let settings = ReadSettings::parse(
+            &url,
+            &options(&[
+                ("billing_project", "acme-etl"),
+                ("selected_fields", "word, corpus"),
+            ]),
+        )
+        .unwrap();
+        assert_eq!(
+            settings.table.unwrap().path(),
+            "projects/bigquery-public-data/datasets/samples/tables/shakespeare"
+        );
+        assert_eq!(settings.billing_project, "acme-etl");
+        assert_eq!(settings.selected_fields, vec!["word", "corpus"]);
+        assert_eq!(settings.max_streams, DEFAULT_STREAMS);
+
+        let url = Url::parse("bigquery://acme-etl").unwrap();
+        let settings =
+            ReadSettings::parse(&url, &options(&[("query", "SELECT 1 AS one")])).unwrap();
+        assert!(settings.table.is_none());
+        assert_eq!(settings.billing_project, "acme-etl");
+
+        assert!(ReadSettings::parse(&url, &HashMap::new()).is_err());
+        let url = Url::parse("bigquery://acme-etl/sales").unwrap();
+        assert!(ReadSettings::parse(&url, &HashMap::new()).is_err());
+        let url = Url::parse("bigquery://acme-etl/sales/orders").unwrap();
+        assert!(ReadSettings::parse(&url, &options(&[("max_streams", "0")])).is_err());
+    }
+
+    #[test]
+    fn test_target_urls_and_load_rows() {
+        let url = Url::parse("bigquery://acme-etl/sales#orders").unwrap();
+        let target = BigQueryTarget::new(&url, &options(&[("location", "EU")])).unwrap();
+        assert_eq!(
+            target.table.api_url(),
+            "https://bigquery.googleapis.com/bigquery/v2/projects/acme-etl/datasets/sales/tables/orders"
+        );
+        assert_eq!(
+            target.job_reference(),
+            serde_json::json!({"projectId": "acme-etl", "location": "EU"})
+        );
+        let settings = ReadSettings::parse(&url, &HashMap::new()).unwrap();
+        assert_eq!(settings.table, Some(target.table.clone()));
+        assert!(
+            BigQueryTarget::new(&Url::parse("bigquery://acme-etl").unwrap(), &HashMap::new())
+                .is_err()
+        );
+
+        let columns = vec![
+            Column {
+                name: "id".to_string(),
+                data_type: DataType::Integer,
+                nullable: false,
+            },
+            Column {
+                name: "total".to_string(),
+                data_type: DataType::Decimal,
+                nullable: true,
+            },
+        ];
+        assert_eq!(
+            table_schema(&columns),
+            serde_json::json!({"fields": [
+                {"name": "id", "type": "INTEGER", "mode": "REQUIRED"},
+                {"name": "total", "type": "BIGNUMERIC", "mode": "NULLABLE"},
+            ]})
+        );
+
+        let date = chrono::DateTime::parse_from_rfc3339("2024-03-01T09:30:00Z")
+            .unwrap()
+            .with_timezone(&chrono::Utc);
+        assert_eq!(
+            json_value(&Value::Date(date), &DataType::DateTime),
+            "2024-03-01 09:30:00.000000 UTC"
+        );
+        assert_eq!(
+            json_value(&Value::Date(date), &DataType::Date),
+            "2024-03-01"
+        );
+        assert_eq!(
+            json_value(
+                &Value::Decimal("12.50".parse().unwrap()),
+                &DataType::Decimal
+            ),
+            "12.50"
+        );
+        assert_eq!(
+            json_value(&Value::Bytes(vec![1, 2]), &DataType::Binary),
+            "AQI="
+        );
+        assert_eq!(
+            json_value(&Value::Json(serde_json::json!({"a": 1})), &DataType::Json),
+            serde_json::json!({"a": 1})
+        );
+
+        assert_eq!(content_range(0, 262144, false), "bytes 0-262143/*");
+        assert_eq!(
+            content_range(262144, 10, true),
+            "bytes 262144-262153/262154"
+        );
+        assert_eq!(content_range(262144, 0, true), "bytes */262144");
+    }
+
+    #[test]
+    fn test_decode_schema_and_batch_messages() {
+        let schema = Arc::new(arrow::datatypes::Schema::new(vec![
+            Field::new("id", ArrowDataType::Int64, false),
+            Field::new("name", ArrowDataType::Utf8, true),
+        ]));
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            vec![
+                Arc::new(Int64Array::from(vec![1, 2])),
+                Arc::new(StringArray::from(vec![Some("a"), None])),
+            ],
+        )
+        .unwrap();
+
+        // Split an IPC stream into its schema message and the rest, the way
+        // the Storage Read API sends them; a stream without batches is the
+        // schema message and an 8 byte end marker
+        let stream = |batches: &[RecordBatch]| {
+            let mut writer = StreamWriter::try_new(Vec::new(), &schema).unwrap();
+            for batch in batches {
+                writer.write(batch).unwrap();
+            }
+            writer.into_inner().unwrap()
+        };
+        let schema_len = stream(&[]).len() - 8;
+        let bytes = stream(std::slice::from_ref(&batch));
+        let (serialized_schema, serialized_batch) = bytes.split_at(schema_len);
+
+        let batches = decode_batches(serialized_schema, serialized_batch).unwrap();
+        assert_eq!(batches, vec![batch]);
+    }
+}
@@ -0,0 +1,724 @@
+use async_trait::async_trait;
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Utc};
+use reqwest::header::{CONTENT_TYPE, RETRY_AFTER};
+use reqwest::{Method, RequestBuilder, StatusCode};
+use std::collections::HashMap;
+use std::time::Duration;
+use tracing::{info, warn};
+use url::Url;
+
+use crate::{
+    connectors::{connection_url::percent_decode, json::value_to_json, Source, Target},
+    protocols::Protocol,
+    safety::display_target,
+    schema::{BinaryEncoding, Row, Schema, Value},
+    Result, TinyEtlError,
+};
+
+const DEFAULT_PORT: u16 = 9200;
+/// Times a bulk request, or the rows of it the cluster rejected with 429
+/// Too Many Requests, are sent again unless `retries` is set
+const DEFAULT_RETRIES: u32 = 5;
+/// Wait before the first retry, doubled for each one after it, unless the
+/// response asks for another with Retry-After
+const RETRY_DELAY: Duration = Duration::from_millis(500);
+
+/// Elasticsearch and OpenSearch protocol: targets index rows as documents
+/// through the `_bulk` API (`elasticsearch://host:9200/index`). The index
+/// name can hold placeholders filled from each row, such as
+/// `logs-{timestamp:%Y.%m.%d}` for daily indices. There is no Elasticsearch
+/// source.
+pub struct ElasticsearchProtocol;
+
+impl Default for ElasticsearchProtocol {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ElasticsearchProtocol {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+#[async_trait]
+impl Protocol for ElasticsearchProtocol {
+    async fn create_source(
+        &self,
+        _url: &Url,
+        _options: &HashMap<String, String>,
+    ) -> Result<Box<dyn Source>> {
+        Err(TinyEtlError::Configuration(
+            "Elasticsearch and OpenSearch can only be used as targets".to_string(),
+        ))
+    }
+
+    async fn create_target(
+        &self,
+        url: &Url,
+        options: &HashMap<String, String>,
+    ) -> Result<Box<dyn Target>> {
+        Ok(Box::new(ElasticsearchTarget::new(url, options)?))
+    }
+
+    fn validate_url(&self, url: &Url) -> Result<()> {
+        if !matches!(url.scheme(), "elasticsearch" | "es" | "opensearch") {
+            return Err(TinyEtlError::Configuration(format!(
+                "Elasticsearch protocol requires elasticsearch:// or opensearch:// scheme, got: {}",
+                url.scheme()
+            )));
+        }
+
+        if url.host().is_none() {
+            return Err(TinyEtlError::Configuration(
+                "Elasticsearch protocol requires a host".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "elasticsearch"
+    }
+}
+
+/// Part of an index name template
+#[derive(Debug, Clone, PartialEq)]
+enum IndexPart {
+    Literal(String),
+    /// `{column}`, or `{column:%Y.%m.%d}` formatting a datetime column
+    Column {
+        name: String,
+        format: Option<String>,
+    },
+}
+
+/// Index name with `{column}` and `{column:<strftime format>}` placeholders
+/// filled from each row. `{now:...}` is the time the run started, unless
+/// the output has a `now` column.
+#[derive(Debug, Clone, PartialEq)]
+struct IndexTemplate {
+    parts: Vec<IndexPart>,
+}
+
+impl IndexTemplate {
+    fn parse(text: &str) -> Result<Self> {
+        let invalid = |reason: &str| {
+            TinyEtlError::Configuration(format!("Invalid index name '{}': {}", text, reason))
+        };
+        let mut parts = Vec::new();
+        let mut rest = text;
+        while let Some(start) = rest.find('{') {
+            if start > 0 {
+                parts.push(IndexPart::Literal(rest[..start].to_string()));
+            }
+            let end = rest[start..]
+                .find('}')
+                .ok_or_else(|| invalid("'{' without a closing '}'"))?;
+            let placeholder = &rest[start + 1..start + end];
+            let (name, format) = match placeholder.split_once(':') {
+                Some((name, format)) => (name, Some(format)),
+                None => (placeholder, None),
+            };
+            if name.trim().is_empty() {
+                return Err(invalid("placeholder without a column name"));
+            }
+            if let Some(format) = format {
+                if StrftimeItems::new(format).any(|item| matches!(item, Item::Error)) {
+                    return Err(invalid(&format!("invalid date format '{}'", format)));
+                }
+            }
+            parts.push(IndexPart::Column {
+                name: name.trim().to_string(),
+                format: format.map(str::to_string),
+            });
+            rest = &rest[start + end + 1..];
+        }
+        if !rest.is_empty() {
+            parts.push(IndexPart::Literal(rest.to_string()));
+        }
+        if parts.is_empty() {
+            return Err(TinyEtlError::Configuration(
+                "Elasticsearch target needs an index, e.g. elasticsearch://host:9200/orders"
+                    .to_string(),
+            ));
+        }
+        Ok(Self { parts })
+    }
+
+    fn is_fixed(&self) -> bool {
+        self.columns().next().is_none()
+    }
+
+    fn columns(&self) -> impl Iterator<Item = &str> {
+        self.parts.iter().filter_map(|part| match part {
+            IndexPart::Column { name, .. } => Some(name.as_str()),
+            IndexPart::Literal(_) => None,
+        })
+    }
+
+    /// Pattern matching every index the template can name, e.g. `logs-*`
+    fn pattern(&self) -> String {
+        self.parts
+            .iter()
+            .map(|part| match part {
+                IndexPart::Literal(text) => text.to_lowercase(),
+                IndexPart::Column { .. } => "*".to_string(),
+            })
+            .collect()
+    }
+
+    /// Index of a row; index names are lowercase
+    fn render(&self, row: &Row, now: DateTime<Utc>) -> Result<String> {
+        let mut index = String::new();
+        for part in &self.parts {
+            match part {
+                IndexPart::Literal(text) => index.push_str(text),
+                IndexPart::Column { name, format } => {
+                    let value = match row.get(name) {
+                        Some(value) => value.clone(),
+                        None if name == "now" => Value::Date(now),
+                        None => Value::Null,
+                    };
+                    let text = match (&value, format) {
+                        (Value::Null, _) => {
+                            return Err(TinyEtlError::DataValidation(format!(
+                                "Row has no value in column '{}' for its index name",
+                                name
+                            )))
+                        }
+                        (Value::Date(dt), Some(format)) => dt.format(format).to_string(),
+                        (_, Some(_)) => {
+                            return Err(TinyEtlError::DataValidation(format!(
+                                "Index name column '{}' needs a datetime to format, got {:?}",
+                                name, value
+                            )))
+                        }
+                        (value, None) => value.to_string_for_arrow().unwrap_or_default(),
+                    };
+                    index.push_str(&text);
+                }
+            }
+        }
+        Ok(index.to_lowercase())
+    }
+}
+
+/// Bulk actions to send again: those the cluster rejected with 429 Too
+/// Many Requests. Any other rejected row fails the batch.
+fn retried_actions(response: &serde_json::Value, actions: Vec<String>) -> Result<Vec<String>> {
+    if response["errors"] != true {
+        return Ok(Vec::new());
+    }
+    let items = response["items"]
+        .as_array()
+        .filter(|items| items.len() == actions.len())
+        .ok_or_else(|| {
+            TinyEtlError::DataTransfer(
+                "Elasticsearch bulk response doesn't have a result for every row".to_string(),
+            )
+        })?;
+
+    let mut retried = Vec::new();
+    let mut failed = 0;
+    let mut first_error = None;
+    for (item, action) in items.iter().zip(actions) {
+        // Each result is keyed by its action, e.g. {"index": {"status": 201}}
+        let result = item
+            .as_object()
+            .and_then(|item| item.values().next())
+            .unwrap_or(&serde_json::Value::Null);
+        match result["status"].as_u64() {
+            Some(429) => retried.push(action),
+            Some(status) if status >= 300 => {
+                failed += 1;
+                first_error.get_or_insert_with(|| result["error"].to_string());
+            }
+            _ => {}
+        }
+    }
+    if let Some(error) = first_error {
+        return Err(TinyEtlError::DataTransfer(format!(
+            "Elasticsearch rejected {} of {} rows: {}",
+            failed,
+            items.len(),
+            error
+        )));
+    }
+    Ok(retried)
+}
+
+/// Target indexing each row as a JSON document through the `_bulk` API, one
+/// request per batch. With `id_column` the documents get that column's value
+/// as their `_id`, so loading a row again replaces its document.
+pub struct ElasticsearchTarget {
+    url: String,
+    endpoint: String,
+    index: IndexTemplate,
+    id_column: Option<String>,
+    user: Option<(String, Option<String>)>,
+    api_key: Option<String>,
+    retries: u32,
+    binary_encoding: BinaryEncoding,
+    started_at: DateTime<Utc>,
+    schema: Option<Schema>,
+    http: reqwest::Client,
+}
+
+impl ElasticsearchTarget {
+    fn new(url: &Url, options: &HashMap<String, String>) -> Result<Self> {
+        let host = url.host_str().ok_or_else(|| {
+            TinyEtlError::Configuration("Elasticsearch URL must include a host".to_string())
+        })?;
+        let https = match options.get("https").map(String::as_str) {
+            None | Some("false") => false,
+            Some("true") => true,
+            Some(other) => {
+                return Err(TinyEtlError::Configuration(format!(
+                    "Invalid https '{}': expected true or false",
+                    other
+                )))
+            }
+        };
+        let endpoint = format!(
+            "{}://{}:{}",
+            if https { "https" } else { "http" },
+            host,
+            url.port().unwrap_or(DEFAULT_PORT)
+        );
+
+        // Braces in the URL path arrive percent-encoded
+        let index = match options.get("index") {
+            Some(index) => index.clone(),
+            None => percent_decode(url.path().trim_matches('/')),
+        };
+        if index.contains('/') {
+            return Err(TinyEtlError::Configuration(format!(
+                "Invalid index name '{}': index names can't contain '/'",
+                index
+            )));
+        }
+        let retries = match options.get("retries") {
+            Some(retries) => retries.parse().map_err(|_| {
+                TinyEtlError::Configuration(format!(
+                    "Invalid retries '{}': expected a number",
+                    retries
+                ))
+            })?,
+            None => DEFAULT_RETRIES,
+        };
+
+        let user = match url.username() {
+            "" => None,
+            user => Some((percent_decode(user), url.password().map(percent_decode))),
+        };
+
+        Ok(Self {
+            url: url.to_string(),
+            endpoint,
+            index: IndexTemplate::parse(&index)?,
+            id_column: options.get("id_column").cloned(),
+            user,
+            api_key: options.get("api_key").cloned(),
+            retries,
+            binary_encoding: options
+                .get("binary_encoding")
+                .map(|encoding| {
+                    encoding
+                        .parse::<BinaryEncoding>()
+                        .map_err(|e| TinyEtlError::Configuration(e.to_string()))
+                })
+                .transpose()?
+                .unwrap_or_default(),
+            started_at: Utc::now(),
+            schema: None,
+            http: reqwest::Client::new(),
+        })
+    }
+
+    fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        let mut request = self
+            .http
+            .request(method, format!("{}{}", self.endpoint, path));
+        if let Some((user, password)) = &self.user {
+            request = request.basic_auth(user, password.as_ref());
+        }
+        if let Some(api_key) = &self.api_key {
+            request = request.header("Authorization", format!("ApiKey {}", api_key));
+        }
+        request
+    }
+
+    fn request_error(&self, e: impl std::fmt::Display) -> TinyEtlError {
+        TinyEtlError::Connection(format!(
+            "Elasticsearch request to {} failed: {}",
+            display_target(&self.url),
+            e
+        ))
+    }
+
+    /// Send a request and fail on an error status, with the error the
+    /// cluster gave
+    async fn send(&self, request: RequestBuilder) -> Result<serde_json::Value> {
+        let response = request.send().await.map_err(|e| self.request_error(e))?;
+        let status = response.status();
+        let text = response.text().await.map_err(|e| self.request_error(e))?;
+        if !status.is_success() {
+            return Err(self.request_error(format!("{} {}", status, text)));
+        }
+        Ok(serde_json::from_str(&text).unwrap_or_default())
+    }
+
+    /// Action and source lines of each row's bulk request entry
+    fn actions(&self, rows: &[Row]) -> Result<Vec<String>> {
+        let columns: Vec<_> = self
+            .schema
+            .iter()
+            .flat_map(|schema| &schema.columns)
+            .collect();
+        rows.iter()
+            .map(|row| {
+                let mut action = serde_json::json!({
+                    "_index": self.index.render(row, self.started_at)?,
+                });
+                if let Some(column) = &self.id_column {
+                    let id = row
+                        .get(column)
+                        .and_then(Value::to_string_for_arrow)
+                        .ok_or_else(|| {
+                            TinyEtlError::DataValidation(format!(
+                                "Row has no value in column '{}' for its document id",
+                                column
+                            ))
+                        })?;
+                    action["_id"] = serde_json::Value::String(id);
+                }
+                let document: serde_json::Map<String, serde_json::Value> = columns
+                    .iter()
+                    .map(|column| {
+                        let value = row
+                            .get(&column.name)
+                            .map(|value| value_to_json(value, self.binary_encoding))
+                            .unwrap_or(serde_json::Value::Null);
+                        (column.name.clone(), value)
+                    })
+                    .collect();
+                Ok(format!(
+                    "{}\n{}\n",
+                    serde_json::json!({ "index": action }),
+                    serde_json::Value::Object(document)
+                ))
+            })
+            .collect()
+    }
+
+    /// Send bulk actions, sending what the cluster rejects with 429 Too
+    /// Many Requests again after a pause, up to `retries` times
+    async fn bulk(&self, mut actions: Vec<String>) -> Result<()> {
+        let mut attempt = 0;
+        loop {
+            let response = self
+                .request(Method::POST, "/_bulk")
+                .header(CONTENT_TYPE, "application/x-ndjson")
+                .body(actions.concat())
+                .send()
+                .await
+                .map_err(|e| self.request_error(e))?;
+            let status = response.status();
+            let retry_after = response
+                .headers()
+                .get(RETRY_AFTER)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.parse().ok())
+                .map(Duration::from_secs);
+            actions = if status == StatusCode::TOO_MANY_REQUESTS {
+                actions
+            } else {
+                let text = response.text().await.map_err(|e| self.request_error(e))?;
+                if !status.is_success() {
+                    return Err(self.request_error(format!("{} {}", status, text)));
+                }
+                retried_actions(&serde_json::from_str(&text)?, actions)?
+            };
+            if actions.is_empty() {
+                return Ok(());
+            }
+            if attempt >= self.retries {
+                return Err(TinyEtlError::DataTransfer(format!(
+                    "Elasticsearch still rejected {} rows with 429 Too Many Requests after {} retries",
+                    actions.len(),
+                    self.retries
+                )));
+            }
+            let delay = retry_after.unwrap_or(RETRY_DELAY * 2u32.pow(attempt.min(10)));
+            attempt += 1;
+            warn!(
+                "Elasticsearch rejected {} rows with 429 Too Many Requests, retrying in {:.1}s",
+                actions.len(),
+                delay.as_secs_f64()
+            );
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
+
+#[async_trait]
+impl Target for ElasticsearchTarget {
+    async fn connect(&mut self) -> Result<()> {
+        let cluster = self.send(self.request(Method::GET, "/")).await?;
+        info!(
+            "Connected to {} {}",
+            cluster["version"]["distribution"]
+                .as_str()
+                .unwrap_or("elasticsearch"),
+            cluster["version"]["number"].as_str().unwrap_or_default()
+        );
+        Ok(())
+    }
+
+    async fn create_table(&mut self, _table_name: &str, schema: &Schema) -> Result<()> {
+        let has_column = |name: &str| schema.columns.iter().any(|c| c.name == name);
+        if let Some(column) = &self.id_column {
+            if !has_column(column) {
+                return Err(TinyEtlError::Configuration(format!(
+                    "Elasticsearch id column '{}' is not in the output schema",
+                    column
+                )));
+            }
+        }
+        if let Some(column) = self
+            .index
+            .columns()
+            .find(|&column| !has_column(column) && column != "now")
+        {
+            return Err(TinyEtlError::Configuration(format!(
+                "Index name column '{}' is not in the output schema",
+                column
+            )));
+        }
+        // Indices are created with dynamic mappings by the first document
+        self.schema = Some(schema.clone());
+        Ok(())
+    }
+
+    async fn write_batch(&mut self, rows: &[Row]) -> Result<usize> {
+        if rows.is_empty() {
+            return Ok(0);
+        }
+        self.bulk(self.actions(rows)?).await?;
+        Ok(rows.len())
+    }
+
+    async fn finalize(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    async fn exists(&self, _table_name: &str) -> Result<bool> {
+        let path = format!(
+            "/{}?allow_no_indices=false&ignore_unavailable=false",
+            self.index.pattern()
+        );
+        let response = self
+            .request(Method::HEAD, &path)
+            .send()
+            .await
+            .map_err(|e| self.request_error(e))?;
+        match response.status() {
+            StatusCode::NOT_FOUND => Ok(false),
+            status if status.is_success() => Ok(true),
+            status => Err(self.request_error(status)),
+        }
+    }
+
+    async fn truncate(&mut self, _table_name: &str) -> Result<()> {
+        let path = format!(
+            "/{}/_delete_by_query?conflicts=proceed&refresh=true",
+            self.index.pattern()
+        );
+        let request = self
+            .request(Method::POST, &path)
+            .json(&serde_json::json!({ "query": { "match_all": {} } }));
+        let deleted = self.send(request).await?;
+        info!(
+            "Deleted {} documents from {}",
+            deleted["deleted"].as_u64().unwrap_or_default(),
+            self.index.pattern()
+        );
+        Ok(())
+    }
+
+    async fn drop_table(&mut self, table_name: &str) -> Result<()> {
+        // Clusters refuse to delete indices by wildcard, so the daily indices
+        // of a template are only emptied
+        if !self.index.is_fixed() {
+            return self.truncate(table_name).await;
+        }
+        let path = format!("/{}", self.index.pattern());
+        self.send(self.request(Method::DELETE, &path)).await?;
+        Ok(())
+    }
+
+    fn supports_append(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::{Column, DataType};
+    use chrono::TimeZone;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// Read one HTTP request, headers and body
+    async fn read_request(socket: &mut tokio::net::TcpStream) -> String {
+        let mut request = Vec::new();
+        let mut buffer = [0; 1024];
+        loop {
+            let read = socket.read(&mut buffer).await.unwrap();
+            request.extend_from_slice(&buffer[..read]);
+            let text = String::from_utf8_lossy(&request).into_owned();
+            let Some((head, body)) = text.split_once("\r\n\r\n") else {
+                if read == 0 {
+                    return text;
+                }
+                continue;
+            };
+            let length = head
+                .lines()
+                .find_map(|line| {
+                    line.to_lowercase()
+                        .strip_prefix("content-length:")
+                        .map(|n| n.trim().parse().unwrap())
+                })
+                .unwrap_or(0);
+            if read == 0 || body.len() >= length {
+                return text;
+            }
+        }
+    }
+
+    fn row(id: i64, day: u32) -> Row {
+        Row::from([
+            ("id".to_string(), Value::Integer(id)),
+            (
+                "timestamp".to_string(),
+                Value::Date(Utc.with_ymd_and_hms(2024, 5, day, 12, 0, 0).unwrap()),
+            ),
+        ])
+    }
+
+    #[test]
+    fn test_index_templates() {
+        let template = IndexTemplate::parse("Logs-{timestamp:%Y.%m.%d}").unwrap();
+        assert!(!template.is_fixed());
+        assert_eq!(template.pattern(), "logs-*");
+        let now = Utc.with_ymd_and_hms(2024, 6, 1, 0, 0, 0).unwrap();
+        assert_eq!(template.render(&row(1, 2), now).unwrap(), "logs-2024.05.02");
+
+        let template = IndexTemplate::parse("orders-{id}-{now:%Y%m}").unwrap();
+        assert_eq!(template.render(&row(7, 2), now).unwrap(), "orders-7-202406");
+        assert!(template.render(&Row::new(), now).is_err());
+
+        assert!(IndexTemplate::parse("orders").unwrap().is_fixed());
+        assert!(IndexTemplate::parse("logs-{timestamp").is_err());
+        assert!(IndexTemplate::parse("logs-{timestamp:%Q}").is_err());
+        assert!(IndexTemplate::parse("").is_err());
+
+        let url = Url::parse("elasticsearch://es:9200/logs-{timestamp:%Y.%m.%d}").unwrap();
+        let target = ElasticsearchTarget::new(&url, &HashMap::new()).unwrap();
+        assert_eq!(target.endpoint, "http://es:9200");
+        assert_eq!(target.index.pattern(), "logs-*");
+    }
+
+    #[tokio::test]
+    async fn test_rows_rejected_with_429_are_retried() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!(
+            "elasticsearch://{}/logs-{{timestamp:%Y.%m.%d}}",
+            listener.local_addr().unwrap()
+        );
+        let responses = [
+            ("429 Too Many Requests", "{}".to_string()),
+            (
+                "200 OK",
+                serde_json::json!({"errors": true, "items": [
+                    {"index": {"status": 201}},
+                    {"index": {"status": 429, "error": {"type": "es_rejected_execution_exception"}}},
+                ]})
+                .to_string(),
+            ),
+            (
+                "200 OK",
+                serde_json::json!({"errors": false, "items": [{"index": {"status": 201}}]})
+                    .to_string(),
+            ),
+        ];
+        let server = tokio::spawn(async move {
+            let mut requests = Vec::new();
+            for (status, body) in responses {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                requests.push(read_request(&mut socket).await);
+                let response = format!(
+                    "HTTP/1.1 {}\r\nContent-Type: application/json\r\nRetry-After: 0\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+                socket.write_all(response.as_bytes()).await.unwrap();
+            }
+            requests
+        });
+
+        let options = HashMap::from([("id_column".to_string(), "id".to_string())]);
+        let mut target = ElasticsearchTarget::new(&Url::parse(&url).unwrap(), &options).unwrap();
+        let schema = Schema {
+            columns: vec![
+                Column {
+                    name: "id".to_string(),
+                    data_type: DataType::Integer,
+                    nullable: false,
+                },
+                Column {
+                    name: "timestamp".to_string(),
+                    data_type: DataType::DateTime,
+                    nullable: false,
+                },
+            ],
+            estimated_rows: None,
+            primary_key_candidate: None,
+        };
+        target.create_table("logs", &schema).await.unwrap();
+        assert_eq!(
+            target.write_batch(&[row(1, 1), row(2, 2)]).await.unwrap(),
+            2
+        );
+
+        let requests = server.await.unwrap();
+        assert!(requests[0].starts_with("POST /_bulk"));
+        assert!(requests[0].contains(r#""_id":"1""#));
+        assert!(requests[0].contains(r#""_index":"logs-2024.05.01""#));
+        assert!(requests[0].contains(r#"{"id":2,"timestamp":"2024-05-02T12:00:00+00:00"}"#));
+        assert_eq!(requests[1], requests[0]);
+        // Only the rejected row is sent again
+        assert!(!requests[2].contains(r#""_id":"1""#));
+        assert!(requests[2].contains(r#""_id":"2""#));
+        assert!(requests[2].contains(r#""_index":"logs-2024.05.02""#));
+    }
+
+    #[test]
+    fn test_other_bulk_errors_fail_the_batch() {
+        let response = serde_json::json!({"errors": true, "items": [
+            {"index": {"status": 400, "error": {"type": "mapper_parsing_exception"}}},
+            {"index": {"status": 429}},
+        ]});
+        let actions = vec!["a".to_string(), "b".to_string()];
+        let error = retried_actions(&response, actions).unwrap_err();
+        assert!(error.to_string().contains("rejected 1 of 2 rows"));
+        assert!(error.to_string().contains("mapper_parsing_exception"));
+    }
+}
@@ -1,5 +1,8 @@
 use crate::{
-    connectors::{create_source, create_target, Source, Target},
+    connectors::{
+        connection_url::percent_decode, create_source_with_options, create_target_with_options,
+        Source, Target,
+    },
     protocols::Protocol,
     Result, TinyEtlError,
 };
@@ -33,12 +36,20 @@ impl FileProtocol {
             )));
         }
 
-        // For file URLs, the path is what matters
-        let path = if let Ok(path) = url.to_file_path() {
-            path.to_string_lossy().to_string()
-        } else {
-            // Fallback for URLs that don't convert to file paths cleanly
-            url.path().to_string()
+        // For file URLs, the path is what matters. Drive letters and UNC
+        // shares are turned back into Windows paths whatever the platform,
+        // so the conversion doesn't depend on where it runs.
+        let path = percent_decode(url.path());
+        let path = match url.host_str() {
+            // file://server/share/file.csv
+            Some(host) if !host.is_empty() && host != "localhost" => {
+                format!(r"\\{}{}", host, path.replace('/', "\\"))
+            }
+            // file:////server/share/file.csv, from a UNC path with backslashes
+            _ if path.starts_with("//") => path.replace('/', "\\"),
+            // file:///C:/data/file.csv
+            _ if has_drive_letter(&path[1..]) => path[1..].replacen('|', ":", 1).replace('/', "\\"),
+            _ => path,
         };
 
         // Handle fragment for database tables (e.g., file:///path/to/db.sqlite#table)
@@ -52,28 +63,81 @@ impl FileProtocol {
     }
 }
 
+/// Whether `path` is a Windows path: one starting with a drive letter
+/// (`C:\data`, `C:/data`) or a UNC share (`\\server\share`)
+pub fn is_windows_path(path: &str) -> bool {
+    has_drive_letter(path) || path.starts_with(r"\\")
+}
+
+/// `C:` (or `C|` in old file URLs) followed by a separator or nothing
+fn has_drive_letter(path: &str) -> bool {
+    let bytes = path.as_bytes();
+    bytes.len() >= 2
+        && bytes[0].is_ascii_alphabetic()
+        && matches!(bytes[1], b':' | b'|')
+        && matches!(bytes.get(2), None | Some(b'/') | Some(b'\\'))
+}
+
+/// `file://` URL of a plain path, keeping its `#table` fragment. Windows
+/// drive and UNC paths are accepted with either separator; relative paths
+/// are taken from the working directory.
+pub fn path_to_file_url(path: &str) -> Result<Url> {
+    let (path, table) = match path.split_once('#') {
+        Some((path, table)) => (path, Some(table)),
+        None => (path, None),
+    };
+    let invalid = |reason: String| {
+        TinyEtlError::Configuration(format!("Invalid file path '{}': {}", path, reason))
+    };
+
+    let normalized = if cfg!(windows) || is_windows_path(path) {
+        path.replace('\\', "/")
+    } else {
+        path.to_string()
+    };
+
+    let mut url = Url::parse("file:///").map_err(|e| invalid(e.to_string()))?;
+    if let Some(unc) = normalized.strip_prefix("//") {
+        let (host, share_path) = unc.split_once('/').unwrap_or((unc, ""));
+        url.set_host(Some(host))
+            .map_err(|e| invalid(e.to_string()))?;
+        url.set_path(share_path);
+    } else if has_drive_letter(&normalized) {
+        url.set_path(&format!("/{}", normalized));
+    } else if normalized.starts_with('/') {
+        url.set_path(&normalized);
+    } else {
+        let absolute = std::env::current_dir()?.join(path);
+        return path_to_file_url(&format!(
+            "{}{}",
+            absolute.to_string_lossy(),
+            table.map(|table| format!("#{}", table)).unwrap_or_default()
+        ));
+    }
+    url.set_fragment(table);
+    Ok(url)
+}
+
 #[async_trait]
 impl Protocol for FileProtocol {
     async fn create_source(
-        &self, 
+        &self,
         url: &Url,
-        _options: &HashMap<String, String>,
+        options: &HashMap<String, String>,
     ) -> Result<Box<dyn Source>> {
-        // File protocol doesn't use options currently, but could be extended
-        // for future features like compression, encoding, etc.
+        // Options are passed through to the file connector (e.g. CSV skip_rows)
         let path = self.url_to_path(url)?;
-        create_source(&path)
+        create_source_with_options(&path, options)
     }
 
     async fn create_target(
-        &self, 
+        &self,
         url: &Url,
-        _options: &HashMap<String, String>,
+        options: &HashMap<String, String>,
     ) -> Result<Box<dyn Target>> {
-        // File protocol doesn't use options currently, but could be extended
-        // for future features like compression, encoding, etc.
+        // Options are passed through to the file connector (e.g. compression settings)
         let path = self.url_to_path(url)?;
-        create_target(&path)
+        create_target_with_options(&path, options)
     }
 
     fn validate_url(&self, url: &Url) -> Result<()> {
@@ -134,6 +198,52 @@ mod tests {
         assert!(path.contains("db.sqlite#table"));
     }
 
+    #[test]
+    fn test_windows_paths() {
+        let protocol = FileProtocol::new();
+
+        assert!(is_windows_path(r"C:\data\file.csv"));
+        assert!(is_windows_path("c:/data/file.csv"));
+        assert!(is_windows_path(r"\\fileserver\exports\file.csv"));
+        assert!(!is_windows_path("/data/file.csv"));
+        assert!(!is_windows_path("postgres://user@host/db"));
+
+        // Drive letter with backslashes, as a plain path and as a file:// URL
+        let url = path_to_file_url(r"C:\data\file.csv").unwrap();
+        assert_eq!(url.as_str(), "file:///C:/data/file.csv");
+        assert_eq!(protocol.url_to_path(&url).unwrap(), r"C:\data\file.csv");
+        let url = Url::parse(r"file://C:\data\file.csv").unwrap();
+        assert_eq!(protocol.url_to_path(&url).unwrap(), r"C:\data\file.csv");
+
+        // Spaces and the table fragment survive the round trip
+        let url = path_to_file_url(r"D:\My Data\sales.db#orders").unwrap();
+        assert_eq!(
+            protocol.url_to_path(&url).unwrap(),
+            r"D:\My Data\sales.db#orders"
+        );
+
+        // UNC share
+        let url = path_to_file_url(r"\\fileserver\exports\file.csv").unwrap();
+        assert_eq!(url.as_str(), "file://fileserver/exports/file.csv");
+        assert_eq!(
+            protocol.url_to_path(&url).unwrap(),
+            r"\\fileserver\exports\file.csv"
+        );
+    }
+
+    #[test]
+    fn test_relative_paths_resolve_from_working_directory() {
+        let url = path_to_file_url("data/file.csv").unwrap();
+        let expected = std::env::current_dir().unwrap().join("data/file.csv");
+        assert_eq!(
+            url.to_file_path()
+                .unwrap()
+                .to_string_lossy()
+                .replace('\\', "/"),
+            expected.to_string_lossy().replace('\\', "/")
+        );
+    }
+
     #[tokio::test]
     async fn test_create_csv_source() {
         let protocol = FileProtocol::new();
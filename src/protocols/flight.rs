@@ -0,0 +1,639 @@
+use arrow::compute::{can_cast_types, cast};
+use arrow::datatypes::{DataType as ArrowDataType, Field, SchemaRef, TimeUnit};
+use arrow::record_batch::RecordBatch;
+use arrow_flight::decode::FlightRecordBatchStream;
+use arrow_flight::encode::FlightDataEncoderBuilder;
+use arrow_flight::{FlightClient, FlightDescriptor, FlightEndpoint, FlightInfo};
+use async_trait::async_trait;
+use futures_util::StreamExt;
+use rust_decimal::Decimal;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tracing::info;
+use url::Url;
+
+use crate::{
+    connectors::{
+        connection_url::percent_decode,
+        parquet::{ParquetSource, ParquetTarget},
+        Source, Target,
+    },
+    protocols::Protocol,
+    schema::{Row, Schema, Value},
+    strict::Coercion,
+    Result, TinyEtlError,
+};
+
+/// Record batches queued for a DoPut upload before `write_batch` waits
+const UPLOAD_QUEUE: usize = 4;
+
+/// Arrow Flight protocol: reads the dataset a Flight service exposes at
+/// `flight://host:port/path` with GetFlightInfo and DoGet, and writes to it
+/// with DoPut. Record batches are converted to rows with the Parquet
+/// connector's Arrow conversions.
+pub struct FlightProtocol;
+
+impl Default for FlightProtocol {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FlightProtocol {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+/// Where to reach a Flight service and what to ask it for
+#[derive(Debug, Clone)]
+struct FlightLocation {
+    /// gRPC address, e.g. `http://localhost:8815`
+    address: String,
+    descriptor: FlightDescriptor,
+    /// gRPC metadata sent with every call
+    headers: Vec<(String, String)>,
+}
+
+impl FlightLocation {
+    /// The URL path is the descriptor path (`flight://host:port/sales/2024`
+    /// is `["sales", "2024"]`), unless the `command` option gives a command
+    /// descriptor instead. `header.<name>` options and `auth.bearer` become
+    /// gRPC metadata.
+    fn parse(url: &Url, options: &HashMap<String, String>) -> Result<Self> {
+        let host = url.host_str().ok_or_else(|| {
+            TinyEtlError::Configuration("Flight URL must specify a host".to_string())
+        })?;
+        let port = url.port().ok_or_else(|| {
+            TinyEtlError::Configuration(
+                "Flight URL must specify a port (flight://host:port/path)".to_string(),
+            )
+        })?;
+
+        let descriptor = match options.get("command") {
+            Some(command) => FlightDescriptor::new_cmd(command.clone()),
+            None => {
+                let path: Vec<String> = url
+                    .path_segments()
+                    .into_iter()
+                    .flatten()
+                    .filter(|segment| !segment.is_empty())
+                    .map(percent_decode)
+                    .collect();
+                if path.is_empty() {
+                    return Err(TinyEtlError::Configuration(
+                        "Flight URL must specify a dataset path (flight://host:port/path) or the command option"
+                            .to_string(),
+                    ));
+                }
+                FlightDescriptor::new_path(path)
+            }
+        };
+
+        let mut headers: Vec<(String, String)> = options
+            .iter()
+            .filter_map(|(key, value)| {
+                key.strip_prefix("header.")
+                    .map(|name| (name.to_lowercase(), value.clone()))
+            })
+            .collect();
+        if let Some(token) = options.get("auth.bearer") {
+            headers.push(("authorization".to_string(), format!("Bearer {}", token)));
+        }
+        headers.sort();
+
+        Ok(Self {
+            address: format!("http://{}:{}", host, port),
+            descriptor,
+            headers,
+        })
+    }
+
+    async fn connect(&self, address: &str) -> Result<FlightClient> {
+        let channel = tonic::transport::Endpoint::from_shared(address.to_string())
+            .map_err(|e| {
+                TinyEtlError::Configuration(format!("Invalid Flight address {}: {}", address, e))
+            })?
+            .connect()
+            .await
+            .map_err(|e| {
+                TinyEtlError::Connection(format!(
+                    "Failed to connect to Flight service at {}: {}",
+                    address, e
+                ))
+            })?;
+
+        let mut client = FlightClient::new(channel);
+        for (name, value) in &self.headers {
+            client.add_header(name, value).map_err(|e| {
+                TinyEtlError::Configuration(format!("Invalid Flight header '{}': {}", name, e))
+            })?;
+        }
+        Ok(client)
+    }
+}
+
+/// gRPC address of an endpoint location the data must be fetched from, or
+/// `None` when it is served by the connection that returned the FlightInfo
+fn location_address(uri: &str) -> Result<Option<String>> {
+    if uri.starts_with("arrow-flight-reuse-connection:") {
+        return Ok(None);
+    }
+    match uri
+        .strip_prefix("grpc+tcp://")
+        .or_else(|| uri.strip_prefix("grpc://"))
+    {
+        Some(host_port) => Ok(Some(format!("http://{}", host_port.trim_end_matches('/')))),
+        None => Err(TinyEtlError::Configuration(format!(
+            "Unsupported Flight endpoint location '{}'. Supported locations: grpc://, grpc+tcp://",
+            uri
+        ))),
+    }
+}
+
+/// Cast the columns of a record batch to the Arrow types the row conversion
+/// reads: wider integers and floats, UTF-8 text and nanosecond timestamps.
+/// Unsigned 64-bit integers and decimals are cast to text and read back as
+/// numbers by `typed_values`, so that no digits are lost on the way.
+pub(crate) fn normalize(batch: &RecordBatch) -> Result<RecordBatch> {
+    let mut fields = Vec::with_capacity(batch.num_columns());
+    let mut columns = Vec::with_capacity(batch.num_columns());
+    for (field, column) in batch.schema().fields().iter().zip(batch.columns()) {
+        let target = match field.data_type() {
+            ArrowDataType::Int8
+            | ArrowDataType::Int16
+            | ArrowDataType::UInt8
+            | ArrowDataType::UInt16
+            | ArrowDataType::UInt32 => ArrowDataType::Int64,
+            ArrowDataType::Float16 | ArrowDataType::Float32 => ArrowDataType::Float64,
+            ArrowDataType::Date32 | ArrowDataType::Date64 => {
+                ArrowDataType::Timestamp(TimeUnit::Nanosecond, None)
+            }
+            ArrowDataType::Timestamp(_, tz) => {
+                ArrowDataType::Timestamp(TimeUnit::Nanosecond, tz.clone())
+            }
+            ArrowDataType::BinaryView => ArrowDataType::Binary,
+            ArrowDataType::LargeUtf8
+            | ArrowDataType::Utf8View
+            | ArrowDataType::UInt64
+            | ArrowDataType::Decimal128(_, _)
+            | ArrowDataType::Decimal256(_, _)
+            | ArrowDataType::Dictionary(_, _) => ArrowDataType::Utf8,
+            other => other.clone(),
+        };
+        if &target == field.data_type() || !can_cast_types(field.data_type(), &target) {
+            fields.push(field.clone());
+            columns.push(column.clone());
+            continue;
+        }
+        let cast_column = cast(column, &target).map_err(|e| {
+            TinyEtlError::DataTransfer(format!(
+                "Failed to convert Arrow column '{}' from {}: {}",
+                field.name(),
+                field.data_type(),
+                e
+            ))
+        })?;
+        fields.push(Arc::new(
+            Field::new(field.name(), target, field.is_nullable())
+                .with_metadata(field.metadata().clone()),
+        ));
+        columns.push(cast_column);
+    }
+
+    RecordBatch::try_new(Arc::new(arrow::datatypes::Schema::new(fields)), columns).map_err(|e| {
+        TinyEtlError::DataTransfer(format!("Failed to convert Arrow record batch: {}", e))
+    })
+}
+
+/// Read the numbers `normalize` cast to text back as numbers
+pub(crate) fn typed_values(schema: &SchemaRef, rows: &mut [Row]) {
+    for field in schema.fields() {
+        let parse: fn(&str) -> Option<Value> = match field.data_type() {
+            ArrowDataType::UInt64 => |s| s.parse::<i128>().ok().map(Value::from_wide_integer),
+            ArrowDataType::Decimal128(_, _) | ArrowDataType::Decimal256(_, _) => {
+                |s| Decimal::from_str(s).ok().map(Value::Decimal)
+            }
+            _ => continue,
+        };
+        for row in rows.iter_mut() {
+            if let Some(value) = row.get_mut(field.name()) {
+                if let Value::String(s) = value {
+                    if let Some(parsed) = parse(s) {
+                        *value = parsed;
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Protocol for FlightProtocol {
+    async fn create_source(
+        &self,
+        url: &Url,
+        options: &HashMap<String, String>,
+    ) -> Result<Box<dyn Source>> {
+        Ok(Box::new(FlightSource::new(FlightLocation::parse(
+            url, options,
+        )?)))
+    }
+
+    async fn create_target(
+        &self,
+        url: &Url,
+        options: &HashMap<String, String>,
+    ) -> Result<Box<dyn Target>> {
+        Ok(Box::new(FlightTarget::new(FlightLocation::parse(
+            url, options,
+        )?)))
+    }
+
+    fn validate_url(&self, url: &Url) -> Result<()> {
+        if url.scheme() != "flight" {
+            return Err(TinyEtlError::Configuration(format!(
+                "Flight protocol requires flight:// scheme, got: {}",
+                url.scheme()
+            )));
+        }
+
+        if url.host().is_none() {
+            return Err(TinyEtlError::Configuration(
+                "Flight protocol requires a valid host".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "flight"
+    }
+}
+
+/// Source streaming the endpoints of a Flight dataset one after the other
+pub struct FlightSource {
+    location: FlightLocation,
+    client: Option<FlightClient>,
+    info: Option<FlightInfo>,
+    /// Index of the next endpoint to read
+    next_endpoint: usize,
+    /// The stream isn't `Sync`; it is only used through `&mut self`
+    stream: Option<Mutex<FlightRecordBatchStream>>,
+    /// Rows of the last record batch not handed out yet
+    pending: Vec<Row>,
+    exhausted: bool,
+    coercion: Coercion,
+}
+
+impl FlightSource {
+    fn new(location: FlightLocation) -> Self {
+        Self {
+            location,
+            client: None,
+            info: None,
+            next_endpoint: 0,
+            stream: None,
+            pending: Vec::new(),
+            exhausted: false,
+            coercion: Coercion::default(),
+        }
+    }
+
+    fn info(&self) -> Result<&FlightInfo> {
+        self.info
+            .as_ref()
+            .ok_or_else(|| TinyEtlError::Connection("Flight source is not connected".to_string()))
+    }
+
+    /// DoGet the ticket of an endpoint, on its own connection when it names
+    /// another location
+    async fn open_endpoint(&mut self, endpoint: FlightEndpoint) -> Result<FlightRecordBatchStream> {
+        let ticket = endpoint.ticket.ok_or_else(|| {
+            TinyEtlError::DataTransfer("Flight endpoint has no ticket".to_string())
+        })?;
+        let address = match endpoint.location.first() {
+            Some(location) => location_address(&location.uri)?,
+            None => None,
+        };
+
+        let mut other_client;
+        let client = match address {
+            Some(address) if address != self.location.address => {
+                info!("Reading Flight endpoint at {}", address);
+                other_client = self.location.connect(&address).await?;
+                &mut other_client
+            }
+            _ => self.client.as_mut().ok_or_else(|| {
+                TinyEtlError::Connection("Flight source is not connected".to_string())
+            })?,
+        };
+        client
+            .do_get(ticket)
+            .await
+            .map_err(|e| TinyEtlError::DataTransfer(format!("Flight DoGet failed: {}", e)))
+    }
+
+    /// The next record batch of the dataset, moving on to the next endpoint
+    /// when one is exhausted; `None` after the last one
+    async fn next_batch(&mut self) -> Result<Option<RecordBatch>> {
+        loop {
+            if let Some(stream) = self.stream.as_mut() {
+                let stream = stream.get_mut().unwrap_or_else(|e| e.into_inner());
+                match stream.next().await {
+                    Some(batch) => {
+                        return batch.map(Some).map_err(|e| {
+                            TinyEtlError::DataTransfer(format!("Failed to read Flight data: {}", e))
+                        })
+                    }
+                    None => self.stream = None,
+                }
+            }
+
+            let Some(endpoint) = self.info()?.endpoint.get(self.next_endpoint).cloned() else {
+                return Ok(None);
+            };
+            self.next_endpoint += 1;
+            let stream = self.open_endpoint(endpoint).await?;
+            self.stream = Some(Mutex::new(stream));
+        }
+    }
+}
+
+#[async_trait]
+impl Source for FlightSource {
+    async fn connect(&mut self) -> Result<()> {
+        let mut client = self.location.connect(&self.location.address).await?;
+        let info = client
+            .get_flight_info(self.location.descriptor.clone())
+            .await
+            .map_err(|e| TinyEtlError::Connection(format!("Flight GetFlightInfo failed: {}", e)))?;
+        info!(
+            "Flight dataset has {} endpoint(s) at {}",
+            info.endpoint.len(),
+            self.location.address
+        );
+        self.client = Some(client);
+        self.info = Some(info);
+        Ok(())
+    }
+
+    async fn infer_schema(&mut self, _sample_size: usize) -> Result<Schema> {
+        let arrow_schema = self.info()?.clone().try_decode_schema().map_err(|e| {
+            TinyEtlError::DataTransfer(format!("Invalid schema in Flight info: {}", e))
+        })?;
+        let mut schema = Schema::from_arrow_schema(&arrow_schema);
+        schema.estimated_rows = self.estimated_row_count().await?;
+        Ok(schema)
+    }
+
+    async fn read_batch(&mut self, batch_size: usize) -> Result<Vec<Row>> {
+        while self.pending.len() < batch_size && !self.exhausted {
+            match self.next_batch().await? {
+                Some(batch) => {
+                    let mut rows =
+                        ParquetSource::record_batch_to_rows(&normalize(&batch)?, self.coercion)?;
+                    typed_values(&batch.schema(), &mut rows);
+                    self.pending.extend(rows);
+                }
+                None => self.exhausted = true,
+            }
+        }
+        let count = batch_size.min(self.pending.len());
+        Ok(self.pending.drain(..count).collect())
+    }
+
+    async fn estimated_row_count(&self) -> Result<Option<usize>> {
+        // -1 when the service doesn't know
+        Ok(self
+            .info
+            .as_ref()
+            .and_then(|info| usize::try_from(info.total_records).ok()))
+    }
+
+    fn set_coercion(&mut self, coercion: Coercion) {
+        self.coercion = coercion;
+    }
+
+    async fn reset(&mut self) -> Result<()> {
+        self.next_endpoint = 0;
+        self.stream = None;
+        self.pending.clear();
+        self.exhausted = false;
+        Ok(())
+    }
+
+    fn has_more(&self) -> bool {
+        !self.exhausted || !self.pending.is_empty()
+    }
+}
+
+/// Target uploading record batches to a Flight service with a single DoPut
+/// stream, which the service is sent as the batches are written
+pub struct FlightTarget {
+    location: FlightLocation,
+    client: Option<FlightClient>,
+    schema: Option<SchemaRef>,
+    sender: Option<mpsc::Sender<RecordBatch>>,
+    upload: Option<JoinHandle<Result<()>>>,
+}
+
+impl FlightTarget {
+    fn new(location: FlightLocation) -> Self {
+        Self {
+            location,
+            client: None,
+            schema: None,
+            sender: None,
+            upload: None,
+        }
+    }
+
+    /// Close the DoPut stream and wait for the service to acknowledge it
+    async fn finish_upload(&mut self) -> Result<()> {
+        self.sender = None;
+        match self.upload.take() {
+            Some(upload) => upload
+                .await
+                .map_err(|e| TinyEtlError::DataTransfer(format!("Flight upload failed: {}", e)))?,
+            None => Ok(()),
+        }
+    }
+}
+
+#[async_trait]
+impl Target for FlightTarget {
+    async fn connect(&mut self) -> Result<()> {
+        self.client = Some(self.location.connect(&self.location.address).await?);
+        Ok(())
+    }
+
+    async fn create_table(&mut self, _table_name: &str, schema: &Schema) -> Result<()> {
+        let arrow_schema = Arc::new(schema.to_arrow_schema());
+        let mut client = self.client.take().ok_or_else(|| {
+            TinyEtlError::Connection("Flight target is not connected".to_string())
+        })?;
+
+        let (sender, receiver) = mpsc::channel::<RecordBatch>(UPLOAD_QUEUE);
+        let batches = futures_util::stream::unfold(receiver, |mut receiver| async move {
+            receiver.recv().await.map(|batch| (Ok(batch), receiver))
+        });
+        let data = FlightDataEncoderBuilder::new()
+            .with_schema(arrow_schema.clone())
+            .with_flight_descriptor(Some(self.location.descriptor.clone()))
+            .build(batches);
+
+        self.upload = Some(tokio::spawn(async move {
+            let put_error = |e| TinyEtlError::DataTransfer(format!("Flight DoPut failed: {}", e));
+            let mut results = client.do_put(data).await.map_err(put_error)?;
+            while let Some(result) = results.next().await {
+                result.map_err(put_error)?;
+            }
+            Ok(())
+        }));
+        self.sender = Some(sender);
+        self.schema = Some(arrow_schema);
+        Ok(())
+    }
+
+    async fn write_batch(&mut self, rows: &[Row]) -> Result<usize> {
+        if rows.is_empty() {
+            return Ok(0);
+        }
+        let (Some(schema), Some(sender)) = (&self.schema, &self.sender) else {
+            return Err(TinyEtlError::Configuration("Schema not set".to_string()));
+        };
+
+        let batch = ParquetTarget::rows_to_record_batch(rows, schema)?;
+        if sender.send(batch).await.is_err() {
+            // The upload ended early; its result says why
+            self.finish_upload().await?;
+            return Err(TinyEtlError::DataTransfer(
+                "Flight service closed the DoPut stream".to_string(),
+            ));
+        }
+        Ok(rows.len())
+    }
+
+    async fn finalize(&mut self) -> Result<()> {
+        self.finish_upload().await
+    }
+
+    async fn exists(&self, _table_name: &str) -> Result<bool> {
+        // What a DoPut to an existing path does is up to the service
+        Ok(false)
+    }
+
+    async fn truncate(&mut self, _table_name: &str) -> Result<()> {
+        Ok(())
+    }
+
+    fn supports_append(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::{Date32Array, Decimal128Array, Int16Array, StringArray, UInt64Array};
+
+    fn options(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_flight_url_is_descriptor_path() {
+        let url = Url::parse("flight://localhost:8815/sales/eu%20west").unwrap();
+        let location = FlightLocation::parse(
+            &url,
+            &options(&[("header.X-Tenant", "acme"), ("auth.bearer", "secret")]),
+        )
+        .unwrap();
+
+        assert_eq!(location.address, "http://localhost:8815");
+        assert_eq!(
+            location.descriptor,
+            FlightDescriptor::new_path(vec!["sales".to_string(), "eu west".to_string()])
+        );
+        assert_eq!(
+            location.headers,
+            [
+                ("authorization".to_string(), "Bearer secret".to_string()),
+                ("x-tenant".to_string(), "acme".to_string())
+            ]
+        );
+
+        let command = FlightLocation::parse(&url, &options(&[("command", "SELECT 1")])).unwrap();
+        assert_eq!(command.descriptor, FlightDescriptor::new_cmd("SELECT 1"));
+
+        let no_port = Url::parse("flight://localhost/sales").unwrap();
+        assert!(FlightLocation::parse(&no_port, &HashMap::new()).is_err());
+        let no_path = Url::parse("flight://localhost:8815").unwrap();
+        assert!(FlightLocation::parse(&no_path, &HashMap::new()).is_err());
+    }
+
+    #[test]
+    fn test_endpoint_locations() {
+        assert_eq!(
+            location_address("grpc+tcp://worker-1:8815").unwrap(),
+            Some("http://worker-1:8815".to_string())
+        );
+        assert_eq!(
+            location_address("arrow-flight-reuse-connection://?").unwrap(),
+            None
+        );
+        assert!(location_address("grpc+tls://worker-1:8815").is_err());
+    }
+
+    #[test]
+    fn test_record_batches_are_read_without_losing_digits() {
+        let batch = RecordBatch::try_from_iter([
+            (
+                "id",
+                Arc::new(UInt64Array::from(vec![u64::MAX, 7])) as Arc<dyn arrow::array::Array>,
+            ),
+            ("qty", Arc::new(Int16Array::from(vec![Some(3), None])) as _),
+            (
+                "price",
+                Arc::new(
+                    Decimal128Array::from(vec![12345, -5])
+                        .with_precision_and_scale(10, 2)
+                        .unwrap(),
+                ) as _,
+            ),
+            ("day", Arc::new(Date32Array::from(vec![0, 19723])) as _),
+            ("name", Arc::new(StringArray::from(vec!["a", "b"])) as _),
+        ])
+        .unwrap();
+
+        let mut rows =
+            ParquetSource::record_batch_to_rows(&normalize(&batch).unwrap(), Coercion::default())
+                .unwrap();
+        typed_values(&batch.schema(), &mut rows);
+
+        assert_eq!(rows[0]["id"], Value::from_wide_integer(u64::MAX.into()));
+        assert_eq!(rows[1]["id"], Value::Integer(7));
+        assert_eq!(rows[0]["qty"], Value::Integer(3));
+        assert_eq!(rows[1]["qty"], Value::Null);
+        assert_eq!(rows[0]["price"], Value::Decimal(Decimal::new(12345, 2)));
+        assert_eq!(
+            rows[1]["day"],
+            Value::Date(
+                chrono::NaiveDate::from_ymd_opt(2024, 1, 1)
+                    .unwrap()
+                    .and_hms_opt(0, 0, 0)
+                    .unwrap()
+                    .and_utc()
+            )
+        );
+        assert_eq!(rows[1]["name"], Value::String("b".to_string()));
+    }
+}
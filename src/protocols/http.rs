@@ -1,17 +1,17 @@
 use crate::{
-    connectors::{create_source, Source, Target},
-    protocols::Protocol,
+    connectors::{compressed, create_source_with_options, Source, Target},
+    protocols::{oauth2::ClientCredentials, Protocol},
+    staging::staging,
     Result, TinyEtlError,
 };
 use async_trait::async_trait;
 use std::collections::HashMap;
-use std::io::Write;
-use tempfile::NamedTempFile;
+use std::path::PathBuf;
 use tracing::info;
 use url::Url;
 
 /// HTTP/HTTPS protocol for downloading files from web servers.
-/// Downloads files to temporary locations and then uses existing connectors.
+/// Downloads files to the staging directory and then uses existing connectors.
 pub struct HttpProtocol;
 
 impl Default for HttpProtocol {
@@ -25,18 +25,146 @@ impl HttpProtocol {
         Self
     }
 
-
-    /// Download a file from an HTTP/HTTPS URL to a temporary file with optional type hint and options
-    async fn download_to_temp_with_type_and_options(
+    /// Download a file from an HTTP/HTTPS URL to the staging directory with optional type hint and options
+    async fn download_to_staging(
         &self,
         url: &Url,
         source_type: Option<&str>,
         options: &HashMap<String, String>,
-    ) -> Result<NamedTempFile> {
-        let client = reqwest::Client::new();
+    ) -> Result<PathBuf> {
+        let client = Self::client_builder(options)?
+            .build()
+            .map_err(|e| TinyEtlError::Configuration(format!("Invalid HTTP options: {}", e)))?;
+        let oauth2 = ClientCredentials::from_options(options)?;
+        let cookies = Self::login(options).await?;
 
         info!("Downloading from HTTP URL: {}", url);
 
+        let token = match &oauth2 {
+            Some(credentials) => Some(credentials.token(&client).await?),
+            None => None,
+        };
+        let mut response =
+            Self::send(&client, url, options, token.as_deref(), cookies.as_deref()).await?;
+
+        // The cached token may have expired or been revoked; get a new one and retry once
+        if let (Some(credentials), reqwest::StatusCode::UNAUTHORIZED) = (&oauth2, response.status())
+        {
+            info!("Access token was rejected, requesting a new one");
+            let token = credentials.refresh(&client).await?;
+            response = Self::send(&client, url, options, Some(&token), cookies.as_deref()).await?;
+        }
+
+        if !response.status().is_success() {
+            return Err(TinyEtlError::Connection(format!(
+                "HTTP request failed with status {}: {}",
+                response.status(),
+                url
+            )));
+        }
+
+        // Log the content size if available
+        if let Some(size) = response.content_length() {
+            info!("Downloading {} bytes", size);
+        } else {
+            info!("Downloading file (size unknown)");
+        }
+
+        let content = response.bytes().await.map_err(|e| {
+            TinyEtlError::Connection(format!("Failed to read response body: {}", e))
+        })?;
+
+        info!("Download completed, {} bytes received", content.len());
+
+        // Stage the file with an appropriate extension based on the URL or source type
+        let extension = self.get_file_extension(url, source_type);
+        staging()?.store(&content, Some(extension.as_deref().unwrap_or("tmp")))
+    }
+
+    /// Client with the `user_agent` and `proxy` options applied. Without a
+    /// `proxy` option the HTTP_PROXY, HTTPS_PROXY and NO_PROXY environment
+    /// variables are used; `proxy: none` ignores them.
+    fn client_builder(options: &HashMap<String, String>) -> Result<reqwest::ClientBuilder> {
+        let mut builder = reqwest::Client::builder();
+        if let Some(user_agent) = options.get("user_agent") {
+            builder = builder.user_agent(user_agent);
+        }
+        match options.get("proxy").map(|proxy| proxy.as_str()) {
+            None => {}
+            Some("none") => builder = builder.no_proxy(),
+            Some(proxy) => {
+                let proxy = reqwest::Proxy::all(proxy).map_err(|e| {
+                    TinyEtlError::Configuration(format!("Invalid proxy '{}': {}", proxy, e))
+                })?;
+                builder = builder.proxy(proxy);
+            }
+        }
+        Ok(builder)
+    }
+
+    /// POST to `login.url` before the download and return the session
+    /// cookies it sets, as a `Cookie` header value. The form fields come from
+    /// the `login.form.*` options, or the raw body from `login.body` (with
+    /// `login.header.*` headers). Redirects after the login aren't followed,
+    /// since the cookies are usually set on the redirect itself.
+    async fn login(options: &HashMap<String, String>) -> Result<Option<String>> {
+        let Some(login_url) = options.get("login.url") else {
+            return Ok(None);
+        };
+        let client = Self::client_builder(options)?
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .map_err(|e| TinyEtlError::Configuration(format!("Invalid HTTP options: {}", e)))?;
+
+        info!("Logging in at {}", login_url);
+        let mut request = client.post(login_url);
+        for (key, value) in options {
+            if let Some(header_name) = key.strip_prefix("login.header.") {
+                request = request.header(header_name, value);
+            }
+        }
+        let form: Vec<(&str, &str)> = options
+            .iter()
+            .filter_map(|(key, value)| Some((key.strip_prefix("login.form.")?, value.as_str())))
+            .collect();
+        request = match options.get("login.body") {
+            Some(_) if !form.is_empty() => {
+                return Err(TinyEtlError::Configuration(
+                    "Use either login.body or login.form.* options, not both".to_string(),
+                ))
+            }
+            Some(body) => request.body(body.clone()),
+            None => request.form(&form),
+        };
+
+        let response = request.send().await.map_err(|e| {
+            TinyEtlError::Connection(format!("Failed to log in at {}: {}", login_url, e))
+        })?;
+        if !(response.status().is_success() || response.status().is_redirection()) {
+            return Err(TinyEtlError::Connection(format!(
+                "Login failed with status {}: {}",
+                response.status(),
+                login_url
+            )));
+        }
+
+        let cookies = session_cookies(response.headers());
+        if cookies.is_empty() {
+            info!("Login response set no cookies");
+            return Ok(None);
+        }
+        Ok(Some(cookies))
+    }
+
+    /// Send the GET request with the headers and authentication from the
+    /// options, the OAuth2 access token and session cookies when given
+    async fn send(
+        client: &reqwest::Client,
+        url: &Url,
+        options: &HashMap<String, String>,
+        oauth2_token: Option<&str>,
+        cookies: Option<&str>,
+    ) -> Result<reqwest::Response> {
         // Build the request with optional headers and authentication
         let mut request = client.get(url.as_str());
 
@@ -64,47 +192,20 @@ impl HttpProtocol {
             request = request.bearer_auth(token);
         }
 
-        let response = request.send().await.map_err(|e| {
-            TinyEtlError::Connection(format!("Failed to fetch URL {}: {}", url, e))
-        })?;
-
-        if !response.status().is_success() {
-            return Err(TinyEtlError::Connection(format!(
-                "HTTP request failed with status {}: {}",
-                response.status(),
-                url
-            )));
+        // Apply the OAuth2 access token if one was fetched
+        if let Some(token) = oauth2_token {
+            request = request.bearer_auth(token);
         }
 
-        // Log the content size if available
-        if let Some(size) = response.content_length() {
-            info!("Downloading {} bytes", size);
-        } else {
-            info!("Downloading file (size unknown)");
+        // Send the session cookies of the login step
+        if let Some(cookies) = cookies {
+            request = request.header(reqwest::header::COOKIE, cookies);
         }
 
-        let content = response.bytes().await.map_err(|e| {
-            TinyEtlError::Connection(format!("Failed to read response body: {}", e))
-        })?;
-
-        info!("Download completed, {} bytes received", content.len());
-
-        // Create a temporary file with an appropriate extension based on the URL or source type
-        let extension = self.get_file_extension(url, source_type);
-        let mut temp_file = if let Some(ext) = extension {
-            tempfile::Builder::new()
-                .suffix(&format!(".{}", ext))
-                .tempfile()
-                .map_err(TinyEtlError::Io)?
-        } else {
-            tempfile::NamedTempFile::new().map_err(TinyEtlError::Io)?
-        };
-
-        temp_file.write_all(&content).map_err(TinyEtlError::Io)?;
-
-        temp_file.flush().map_err(TinyEtlError::Io)?;
-
-        Ok(temp_file)
+        request
+            .send()
+            .await
+            .map_err(|e| TinyEtlError::Connection(format!("Failed to fetch URL {}: {}", url, e)))
     }
 
     /// Extract file extension from URL path for proper temporary file naming
@@ -125,15 +226,30 @@ impl HttpProtocol {
         if let Some(forced_type) = source_type {
             Some(forced_type.to_lowercase())
         } else {
-            self.extract_extension_from_url(url)
+            compressed::staged_extension(url.path(), self.extract_extension_from_url(url))
         }
     }
 }
 
+/// `name=value` pairs of the `Set-Cookie` headers of a response, joined
+/// into a `Cookie` header value. Attributes like `Path` and `Expires` are
+/// dropped; the cookies only live for this run.
+fn session_cookies(headers: &reqwest::header::HeaderMap) -> String {
+    headers
+        .get_all(reqwest::header::SET_COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .filter_map(|cookie| cookie.split(';').next())
+        .map(str::trim)
+        .filter(|pair| pair.contains('='))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
 #[async_trait]
 impl Protocol for HttpProtocol {
     async fn create_source(
-        &self, 
+        &self,
         url: &Url,
         options: &HashMap<String, String>,
     ) -> Result<Box<dyn Source>> {
@@ -146,32 +262,14 @@ impl Protocol for HttpProtocol {
         source_type: Option<&str>,
         options: &HashMap<String, String>,
     ) -> Result<Box<dyn Source>> {
-        // Download the file to a temporary location
-        let temp_file = self.download_to_temp_with_type_and_options(url, source_type, options).await?;
-
-        // Create a persistent temporary file in the system temp directory
-        let extension = self.get_file_extension(url, source_type);
-        let temp_dir = std::env::temp_dir();
-        let file_name = format!(
-            "tinyetl_download_{}.{}",
-            std::process::id(),
-            extension.unwrap_or_else(|| "tmp".to_string())
-        );
-        let persistent_path = temp_dir.join(file_name);
-
-        // Copy the content to the persistent path
-        std::fs::copy(temp_file.path(), &persistent_path).map_err(TinyEtlError::Io)?;
-
-        let final_path = persistent_path.to_string_lossy().to_string();
-
-        // Create source using the persistent temporary file path
-        // Note: This file will not be automatically cleaned up
-        // In a production implementation, we'd want better lifecycle management
-        create_source(&final_path)
+        // Download the file to the staging directory, which is cleaned up
+        // once the run succeeds
+        let path = self.download_to_staging(url, source_type, options).await?;
+        create_source_with_options(&path.to_string_lossy(), options)
     }
 
     async fn create_target(
-        &self, 
+        &self,
         _url: &Url,
         _options: &HashMap<String, String>,
     ) -> Result<Box<dyn Target>> {
@@ -277,4 +375,69 @@ mod tests {
             assert!(result.is_err());
         });
     }
+
+    #[test]
+    fn test_session_cookies_keep_name_and_value() {
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.append(
+            reqwest::header::SET_COOKIE,
+            "session=abc123; Path=/; HttpOnly".parse().unwrap(),
+        );
+        headers.append(
+            reqwest::header::SET_COOKIE,
+            "csrf=xyz; Secure".parse().unwrap(),
+        );
+        assert_eq!(session_cookies(&headers), "session=abc123; csrf=xyz");
+        assert_eq!(session_cookies(&reqwest::header::HeaderMap::new()), "");
+
+        let mut options = HashMap::new();
+        options.insert("proxy".to_string(), "not a proxy url".to_string());
+        assert!(HttpProtocol::client_builder(&options).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_login_cookies_and_user_agent_are_sent() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            let responses = [
+                "HTTP/1.1 302 Found\r\nLocation: /home\r\nSet-Cookie: session=abc123; Path=/\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                "HTTP/1.1 200 OK\r\nContent-Length: 8\r\nConnection: close\r\n\r\nid\n1\n2\n\n",
+            ];
+            let mut requests = Vec::new();
+            for response in responses {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut buffer = vec![0; 4096];
+                let read = socket.read(&mut buffer).await.unwrap();
+                requests.push(String::from_utf8_lossy(&buffer[..read]).to_lowercase());
+                socket.write_all(response.as_bytes()).await.unwrap();
+            }
+            requests
+        });
+
+        let options: HashMap<String, String> = [
+            ("login.url", format!("{}/login", base)),
+            ("login.form.user", "etl".to_string()),
+            ("user_agent", "TinyETL-test".to_string()),
+            ("proxy", "none".to_string()),
+        ]
+        .into_iter()
+        .map(|(key, value)| (key.to_string(), value))
+        .collect();
+        let url = Url::parse(&format!("{}/export.csv", base)).unwrap();
+        let file = HttpProtocol::new()
+            .download_to_staging(&url, None, &options)
+            .await
+            .unwrap();
+        let content = std::fs::read_to_string(file).unwrap();
+        assert!(content.starts_with("id\n1"));
+
+        let requests = server.await.unwrap();
+        assert!(requests[0].starts_with("post /login"));
+        assert!(requests[1].starts_with("get /export.csv"));
+        assert!(requests[1].contains("cookie: session=abc123"));
+        assert!(requests[1].contains("user-agent: tinyetl-test"));
+    }
 }
@@ -0,0 +1,765 @@
+use async_nats::jetstream::{
+    self,
+    consumer::{pull, AckPolicy, Consumer, DeliverPolicy},
+    AckKind,
+};
+use async_nats::{Client, ConnectOptions, HeaderMap};
+use async_trait::async_trait;
+use futures_util::StreamExt;
+use std::collections::HashMap;
+use std::time::Duration;
+use tracing::info;
+use url::Url;
+
+use crate::{
+    connectors::{
+        connection_url::percent_decode,
+        json::{read_json_value, value_to_json},
+        Source, Target,
+    },
+    protocols::Protocol,
+    safety::display_target,
+    schema::{BinaryEncoding, Row, Schema, SchemaInferer, Value},
+    strict::Coercion,
+    Result, TinyEtlError,
+};
+
+const DEFAULT_PORT: u16 = 4222;
+/// How long a fetch waits for messages before the stream counts as read
+const FETCH_WAIT: Duration = Duration::from_secs(2);
+/// Default time the server waits for the run to acknowledge what it read
+/// before delivering it again
+const DEFAULT_ACK_WAIT: Duration = Duration::from_secs(3600);
+
+/// NATS protocol: targets publish rows as JSON messages to a subject
+/// (`nats://host:4222/orders.created`), sources read a JetStream stream
+/// (`nats://host:4222/ORDERS`) up to its last message when the run starts.
+pub struct NatsProtocol;
+
+impl Default for NatsProtocol {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NatsProtocol {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+/// Server address and credentials of a `nats://` URL
+#[derive(Debug, Clone)]
+struct NatsServer {
+    url: String,
+    address: String,
+    user: Option<(String, String)>,
+    token: Option<String>,
+    credentials_file: Option<String>,
+    /// Subject of a target, stream of a source
+    name: String,
+}
+
+impl NatsServer {
+    fn parse(url: &Url, options: &HashMap<String, String>) -> Result<Self> {
+        let host = url.host_str().ok_or_else(|| {
+            TinyEtlError::Configuration("NATS URL must specify a host".to_string())
+        })?;
+        let name = percent_decode(url.path().trim_matches('/'));
+        if name.is_empty() {
+            return Err(TinyEtlError::Configuration(
+                "NATS URL must name a subject (targets) or stream (sources): nats://host:4222/name"
+                    .to_string(),
+            ));
+        }
+        let user = (!url.username().is_empty()).then(|| {
+            (
+                percent_decode(url.username()),
+                percent_decode(url.password().unwrap_or("")),
+            )
+        });
+
+        Ok(Self {
+            url: url.to_string(),
+            address: format!("nats://{}:{}", host, url.port().unwrap_or(DEFAULT_PORT)),
+            user,
+            token: options.get("token").cloned(),
+            credentials_file: options.get("credentials_file").cloned(),
+            name,
+        })
+    }
+
+    async fn connect(&self) -> Result<Client> {
+        let mut options = ConnectOptions::new();
+        if let Some((user, password)) = &self.user {
+            options = options.user_and_password(user.clone(), password.clone());
+        }
+        if let Some(token) = &self.token {
+            options = options.token(token.clone());
+        }
+        if let Some(path) = &self.credentials_file {
+            options = options.credentials_file(path).await.map_err(|e| {
+                TinyEtlError::Configuration(format!(
+                    "Can't read NATS credentials file {}: {}",
+                    path, e
+                ))
+            })?;
+        }
+        options.connect(&self.address).await.map_err(|e| {
+            TinyEtlError::Connection(format!(
+                "Failed to connect to NATS server {}: {}",
+                display_target(&self.url),
+                e
+            ))
+        })
+    }
+}
+
+#[async_trait]
+impl Protocol for NatsProtocol {
+    async fn create_source(
+        &self,
+        url: &Url,
+        options: &HashMap<String, String>,
+    ) -> Result<Box<dyn Source>> {
+        Ok(Box::new(NatsSource::new(url, options)?))
+    }
+
+    async fn create_target(
+        &self,
+        url: &Url,
+        options: &HashMap<String, String>,
+    ) -> Result<Box<dyn Target>> {
+        Ok(Box::new(NatsTarget::new(url, options)?))
+    }
+
+    fn validate_url(&self, url: &Url) -> Result<()> {
+        if url.scheme() != "nats" {
+            return Err(TinyEtlError::Configuration(format!(
+                "NATS protocol requires nats:// scheme, got: {}",
+                url.scheme()
+            )));
+        }
+
+        if url.host().is_none() {
+            return Err(TinyEtlError::Configuration(
+                "NATS protocol requires a valid host".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "nats"
+    }
+}
+
+fn invalid_option(name: &str, value: &str, expected: &str) -> TinyEtlError {
+    TinyEtlError::Configuration(format!(
+        "Invalid NATS {} '{}': expected {}",
+        name, value, expected
+    ))
+}
+
+fn time_option(options: &HashMap<String, String>, name: &str) -> Result<Option<i128>> {
+    options
+        .get(name)
+        .map(|value| {
+            chrono::DateTime::parse_from_rfc3339(value)
+                .ok()
+                .and_then(|time| time.timestamp_nanos_opt())
+                .map(i128::from)
+                .ok_or_else(|| invalid_option(name, value, "an RFC 3339 time"))
+        })
+        .transpose()
+}
+
+fn number_option(options: &HashMap<String, String>, name: &str) -> Result<Option<u64>> {
+    options
+        .get(name)
+        .map(|value| {
+            value
+                .parse::<u64>()
+                .map_err(|_| invalid_option(name, value, "a non-negative integer"))
+        })
+        .transpose()
+}
+
+/// Which messages of the stream a source reads
+#[derive(Debug, Clone, PartialEq)]
+struct ReadBounds {
+    deliver_policy: DeliverPolicy,
+    /// Last stream sequence read
+    end_sequence: Option<u64>,
+    /// Messages published after this time (Unix nanoseconds) aren't read
+    end_time: Option<i128>,
+}
+
+impl ReadBounds {
+    fn from_options(options: &HashMap<String, String>) -> Result<Self> {
+        let deliver_policy = match (
+            number_option(options, "start_sequence")?,
+            time_option(options, "start_time")?,
+        ) {
+            (Some(_), Some(_)) => {
+                return Err(TinyEtlError::Configuration(
+                    "Set either start_sequence or start_time for a NATS source, not both"
+                        .to_string(),
+                ))
+            }
+            (Some(start_sequence), None) => DeliverPolicy::ByStartSequence { start_sequence },
+            (None, Some(nanos)) => DeliverPolicy::ByStartTime {
+                start_time: time::OffsetDateTime::from_unix_timestamp_nanos(nanos).map_err(
+                    |e| invalid_option("start_time", &nanos.to_string(), &e.to_string()),
+                )?,
+            },
+            (None, None) => DeliverPolicy::All,
+        };
+        Ok(Self {
+            deliver_policy,
+            end_sequence: number_option(options, "end_sequence")?,
+            end_time: time_option(options, "end_time")?,
+        })
+    }
+
+    fn contains(&self, sequence: u64, published: i128) -> bool {
+        self.end_sequence.map_or(true, |end| sequence <= end)
+            && self.end_time.map_or(true, |end| published <= end)
+    }
+}
+
+/// Rows of a message payload: a JSON object, or an array of them
+fn payload_rows(payload: &[u8], sequence: u64, coercion: Coercion) -> Result<Vec<Row>> {
+    let json: serde_json::Value = serde_json::from_slice(payload).map_err(|e| {
+        TinyEtlError::DataValidation(format!(
+            "NATS message {} is not valid JSON: {}",
+            sequence, e
+        ))
+    })?;
+    let objects = match json {
+        serde_json::Value::Array(items) => items,
+        object => vec![object],
+    };
+    objects
+        .iter()
+        .map(|object| {
+            let object = object.as_object().ok_or_else(|| {
+                TinyEtlError::DataValidation(format!(
+                    "NATS message {} is not a JSON object or array of objects",
+                    sequence
+                ))
+            })?;
+            object
+                .iter()
+                .map(|(key, value)| Ok((key.clone(), read_json_value(key, value, coercion)?)))
+                .collect()
+        })
+        .collect()
+}
+
+/// Source reading the JSON messages of a JetStream stream through a pull
+/// consumer: an ephemeral one, or the durable `consumer` option, which
+/// carries on where the last run stopped. The messages read are
+/// acknowledged once the target is finalized (`ack: false` turns that off).
+pub struct NatsSource {
+    server: NatsServer,
+    filter_subject: Option<String>,
+    durable_name: Option<String>,
+    bounds: ReadBounds,
+    ack: bool,
+    ack_wait: Duration,
+    consumer: Option<Consumer<pull::Config>>,
+    /// Messages in the stream for the consumer when it was created
+    remaining: u64,
+    /// Rows fetched and not yet handed out, plus those handed out since the
+    /// last time all were; `reset` rewinds to the first of them
+    rows: Vec<Row>,
+    position: usize,
+    done: bool,
+    /// Last message read; acknowledging it acknowledges all before it
+    last_message: Option<jetstream::Message>,
+    coercion: Coercion,
+}
+
+impl NatsSource {
+    fn new(url: &Url, options: &HashMap<String, String>) -> Result<Self> {
+        let ack = match options.get("ack").map(String::as_str) {
+            None | Some("true") => true,
+            Some("false") => false,
+            Some(other) => return Err(invalid_option("ack", other, "true or false")),
+        };
+        Ok(Self {
+            server: NatsServer::parse(url, options)?,
+            filter_subject: options.get("filter_subject").cloned(),
+            durable_name: options.get("consumer").cloned(),
+            bounds: ReadBounds::from_options(options)?,
+            ack,
+            ack_wait: number_option(options, "ack_wait")?
+                .map(Duration::from_secs)
+                .unwrap_or(DEFAULT_ACK_WAIT),
+            consumer: None,
+            remaining: 0,
+            rows: Vec::new(),
+            position: 0,
+            done: false,
+            last_message: None,
+            coercion: Coercion::default(),
+        })
+    }
+
+    fn fetch_error(e: impl std::fmt::Display) -> TinyEtlError {
+        TinyEtlError::DataTransfer(format!("Failed to fetch NATS messages: {}", e))
+    }
+
+    /// Fetch up to `max` more messages into `rows`
+    async fn fetch(&mut self, max: usize) -> Result<()> {
+        let consumer = self
+            .consumer
+            .as_ref()
+            .ok_or_else(|| TinyEtlError::Connection("NATS source is not connected".to_string()))?;
+        let max = max.min(usize::try_from(self.remaining).unwrap_or(usize::MAX));
+        if max == 0 {
+            self.done = true;
+            return Ok(());
+        }
+
+        let mut messages = consumer
+            .fetch()
+            .max_messages(max)
+            .expires(FETCH_WAIT)
+            .messages()
+            .await
+            .map_err(Self::fetch_error)?;
+        let mut received = 0;
+        while let Some(message) = messages.next().await {
+            let message = message.map_err(Self::fetch_error)?;
+            received += 1;
+            let (sequence, published) = {
+                let info = message.info().map_err(Self::fetch_error)?;
+                (info.stream_sequence, info.published.unix_timestamp_nanos())
+            };
+            if !self.bounds.contains(sequence, published) {
+                // Hand it back so a durable consumer delivers it next run
+                if self.ack {
+                    message
+                        .ack_with(AckKind::Nak(None))
+                        .await
+                        .map_err(Self::fetch_error)?;
+                }
+                self.done = true;
+                continue;
+            }
+            self.remaining -= 1;
+            self.rows
+                .extend(payload_rows(&message.payload, sequence, self.coercion)?);
+            self.last_message = Some(message);
+        }
+        if received == 0 || self.remaining == 0 {
+            self.done = true;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Source for NatsSource {
+    async fn connect(&mut self) -> Result<()> {
+        let context = jetstream::new(self.server.connect().await?);
+        let stream = context.get_stream(&self.server.name).await.map_err(|e| {
+            TinyEtlError::Connection(format!(
+                "Failed to open JetStream stream '{}': {}",
+                self.server.name, e
+            ))
+        })?;
+
+        let config = pull::Config {
+            durable_name: self.durable_name.clone(),
+            filter_subject: self.filter_subject.clone().unwrap_or_default(),
+            deliver_policy: self.bounds.deliver_policy,
+            ack_policy: if self.ack {
+                AckPolicy::All
+            } else {
+                AckPolicy::None
+            },
+            ack_wait: self.ack_wait,
+            // Nothing is acknowledged before the run ends
+            max_ack_pending: -1,
+            ..Default::default()
+        };
+        let consumer_error = |e| {
+            TinyEtlError::Connection(format!(
+                "Failed to create consumer on stream '{}': {}",
+                self.server.name, e
+            ))
+        };
+        let mut consumer = match &self.durable_name {
+            Some(name) => stream.get_or_create_consumer(name, config).await,
+            None => stream.create_consumer(config).await,
+        }
+        .map_err(consumer_error)?;
+
+        // Messages published during the run are left for the next one
+        self.remaining = consumer
+            .info()
+            .await
+            .map_err(|e| TinyEtlError::Connection(format!("Failed to read consumer info: {}", e)))?
+            .num_pending;
+        info!(
+            "Reading {} message(s) from JetStream stream '{}'",
+            self.remaining, self.server.name
+        );
+        self.consumer = Some(consumer);
+        self.done = self.remaining == 0;
+        Ok(())
+    }
+
+    async fn infer_schema(&mut self, sample_size: usize) -> Result<Schema> {
+        while self.rows.len() < sample_size && !self.done {
+            self.fetch(sample_size - self.rows.len()).await?;
+        }
+        let sample = &self.rows[..self.rows.len().min(sample_size)];
+        let mut schema = SchemaInferer::infer_from_rows(sample)?;
+        schema.estimated_rows = self.estimated_row_count().await?;
+        Ok(schema)
+    }
+
+    async fn read_batch(&mut self, batch_size: usize) -> Result<Vec<Row>> {
+        // Rows handed out can't be fetched again; keep them only until the
+        // transfer's sampling is over and everything fetched was read
+        if self.position > 0 && self.position == self.rows.len() {
+            self.rows.clear();
+            self.position = 0;
+        }
+        while self.rows.len() - self.position < batch_size && !self.done {
+            let wanted = batch_size - (self.rows.len() - self.position);
+            self.fetch(wanted).await?;
+        }
+        let end = (self.position + batch_size).min(self.rows.len());
+        let batch = self.rows[self.position..end].to_vec();
+        self.position = end;
+        Ok(batch)
+    }
+
+    async fn estimated_row_count(&self) -> Result<Option<usize>> {
+        // One row per message, unless messages hold arrays
+        Ok(usize::try_from(self.remaining)
+            .ok()
+            .map(|remaining| remaining + self.rows.len()))
+    }
+
+    fn set_coercion(&mut self, coercion: Coercion) {
+        self.coercion = coercion;
+    }
+
+    async fn commit(&mut self) -> Result<()> {
+        if let Some(message) = self.last_message.take() {
+            message.ack().await.map_err(|e| {
+                TinyEtlError::DataTransfer(format!("Failed to acknowledge NATS messages: {}", e))
+            })?;
+        }
+        Ok(())
+    }
+
+    async fn reset(&mut self) -> Result<()> {
+        self.position = 0;
+        Ok(())
+    }
+
+    fn has_more(&self) -> bool {
+        self.position < self.rows.len() || !self.done
+    }
+}
+
+/// Target publishing each row (or each batch, with `publish: batch`) as a
+/// JSON message. With `jetstream: true` every message must be stored by a
+/// stream, which acknowledges it before `write_batch` returns.
+pub struct NatsTarget {
+    server: NatsServer,
+    subject_column: Option<String>,
+    message_id_column: Option<String>,
+    per_batch: bool,
+    use_jetstream: bool,
+    headers: HeaderMap,
+    binary_encoding: BinaryEncoding,
+    schema: Option<Schema>,
+    client: Option<Client>,
+}
+
+impl NatsTarget {
+    fn new(url: &Url, options: &HashMap<String, String>) -> Result<Self> {
+        let per_batch = match options.get("publish").map(String::as_str) {
+            None | Some("row") => false,
+            Some("batch") => true,
+            Some(other) => return Err(invalid_option("publish", other, "row or batch")),
+        };
+        let use_jetstream = match options.get("jetstream").map(String::as_str) {
+            None | Some("false") => false,
+            Some("true") => true,
+            Some(other) => return Err(invalid_option("jetstream", other, "true or false")),
+        };
+        let subject_column = options.get("subject_column").cloned();
+        let message_id_column = options.get("message_id_column").cloned();
+        if per_batch && (subject_column.is_some() || message_id_column.is_some()) {
+            return Err(TinyEtlError::Configuration(
+                "subject_column and message_id_column need one message per row (publish: row)"
+                    .to_string(),
+            ));
+        }
+
+        let mut headers = HeaderMap::new();
+        for (key, value) in options {
+            if let Some(name) = key.strip_prefix("header.") {
+                headers.insert(name, value.as_str());
+            }
+        }
+
+        Ok(Self {
+            server: NatsServer::parse(url, options)?,
+            subject_column,
+            message_id_column,
+            per_batch,
+            use_jetstream,
+            headers,
+            binary_encoding: options
+                .get("binary_encoding")
+                .map(|encoding| {
+                    encoding
+                        .parse::<BinaryEncoding>()
+                        .map_err(|e| TinyEtlError::Configuration(e.to_string()))
+                })
+                .transpose()?
+                .unwrap_or_default(),
+            schema: None,
+            client: None,
+        })
+    }
+
+    fn row_to_json(&self, row: &Row) -> serde_json::Value {
+        let columns = self.schema.iter().flat_map(|schema| &schema.columns);
+        serde_json::Value::Object(
+            columns
+                .map(|column| {
+                    let value = row
+                        .get(&column.name)
+                        .map(|value| value_to_json(value, self.binary_encoding))
+                        .unwrap_or(serde_json::Value::Null);
+                    (column.name.clone(), value)
+                })
+                .collect(),
+        )
+    }
+
+    fn column_text(row: &Row, column: &str) -> Result<String> {
+        row.get(column)
+            .and_then(Value::to_string_for_arrow)
+            .ok_or_else(|| {
+                TinyEtlError::DataValidation(format!(
+                    "Row has no value in column '{}' for its NATS message",
+                    column
+                ))
+            })
+    }
+
+    /// Subject, headers and payload of each message of a batch
+    fn messages(&self, rows: &[Row]) -> Result<Vec<(String, HeaderMap, Vec<u8>)>> {
+        if self.per_batch {
+            let batch: Vec<serde_json::Value> =
+                rows.iter().map(|row| self.row_to_json(row)).collect();
+            return Ok(vec![(
+                self.server.name.clone(),
+                self.headers.clone(),
+                serde_json::to_vec(&batch)?,
+            )]);
+        }
+
+        rows.iter()
+            .map(|row| {
+                let subject = match &self.subject_column {
+                    Some(column) => Self::column_text(row, column)?,
+                    None => self.server.name.clone(),
+                };
+                let mut headers = self.headers.clone();
+                if let Some(column) = &self.message_id_column {
+                    // JetStream drops messages whose id it has seen recently
+                    headers.insert("Nats-Msg-Id", Self::column_text(row, column)?.as_str());
+                }
+                Ok((
+                    subject,
+                    headers,
+                    serde_json::to_vec(&self.row_to_json(row))?,
+                ))
+            })
+            .collect()
+    }
+}
+
+#[async_trait]
+impl Target for NatsTarget {
+    async fn connect(&mut self) -> Result<()> {
+        self.client = Some(self.server.connect().await?);
+        Ok(())
+    }
+
+    async fn create_table(&mut self, _table_name: &str, schema: &Schema) -> Result<()> {
+        for column in [&self.subject_column, &self.message_id_column]
+            .into_iter()
+            .flatten()
+        {
+            if !schema.columns.iter().any(|c| &c.name == column) {
+                return Err(TinyEtlError::Configuration(format!(
+                    "NATS message column '{}' is not in the output schema",
+                    column
+                )));
+            }
+        }
+        self.schema = Some(schema.clone());
+        Ok(())
+    }
+
+    async fn write_batch(&mut self, rows: &[Row]) -> Result<usize> {
+        if rows.is_empty() {
+            return Ok(0);
+        }
+        let messages = self.messages(rows)?;
+        let client = self
+            .client
+            .clone()
+            .ok_or_else(|| TinyEtlError::Connection("NATS target is not connected".to_string()))?;
+        let publish_error = |e: &dyn std::fmt::Display| {
+            TinyEtlError::DataTransfer(format!("NATS publish failed: {}", e))
+        };
+
+        if self.use_jetstream {
+            let context = jetstream::new(client);
+            // Publish the whole batch before waiting for the acknowledgements
+            let mut acks = Vec::with_capacity(messages.len());
+            for (subject, headers, payload) in messages {
+                let ack = context
+                    .publish_with_headers(subject, headers, payload.into())
+                    .await
+                    .map_err(|e| publish_error(&e))?;
+                acks.push(ack);
+            }
+            for ack in acks {
+                ack.await.map_err(|e| publish_error(&e))?;
+            }
+        } else {
+            for (subject, headers, payload) in messages {
+                client
+                    .publish_with_headers(subject, headers, payload.into())
+                    .await
+                    .map_err(|e| publish_error(&e))?;
+            }
+            client.flush().await.map_err(|e| publish_error(&e))?;
+        }
+        Ok(rows.len())
+    }
+
+    async fn finalize(&mut self) -> Result<()> {
+        if let Some(client) = self.client.take() {
+            client
+                .flush()
+                .await
+                .map_err(|e| TinyEtlError::DataTransfer(format!("NATS publish failed: {}", e)))?;
+        }
+        Ok(())
+    }
+
+    async fn exists(&self, _table_name: &str) -> Result<bool> {
+        Ok(false)
+    }
+
+    async fn truncate(&mut self, _table_name: &str) -> Result<()> {
+        // Published messages can't be taken back
+        Ok(())
+    }
+
+    fn supports_append(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_read_bounds() {
+        let bounds = ReadBounds::from_options(&options(&[
+            ("start_sequence", "100"),
+            ("end_time", "2024-06-01T00:00:00Z"),
+        ]))
+        .unwrap();
+        assert_eq!(
+            bounds.deliver_policy,
+            DeliverPolicy::ByStartSequence {
+                start_sequence: 100
+            }
+        );
+        let june = 1_717_200_000_000_000_000;
+        assert!(bounds.contains(150, june));
+        assert!(!bounds.contains(151, june + 1));
+
+        assert!(ReadBounds::from_options(&options(&[
+            ("start_sequence", "1"),
+            ("start_time", "2024-06-01T00:00:00Z")
+        ]))
+        .is_err());
+        assert!(ReadBounds::from_options(&options(&[("end_sequence", "last")])).is_err());
+    }
+
+    #[test]
+    fn test_payloads_are_json_objects() {
+        let rows = payload_rows(
+            br#"[{"id": 1}, {"id": 2, "ok": true}]"#,
+            7,
+            Coercion::default(),
+        )
+        .unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[1]["ok"], Value::Boolean(true));
+
+        let err = payload_rows(b"42", 7, Coercion::default()).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("NATS message 7 is not a JSON object"));
+    }
+
+    #[test]
+    fn test_subject_and_message_id_from_columns() {
+        let url = Url::parse("nats://localhost/orders").unwrap();
+        let mut target = NatsTarget::new(
+            &url,
+            &options(&[("subject_column", "topic"), ("message_id_column", "id")]),
+        )
+        .unwrap();
+        target.schema = Some(Schema {
+            columns: vec![crate::schema::Column {
+                name: "id".to_string(),
+                data_type: crate::schema::DataType::Integer,
+                nullable: false,
+            }],
+            estimated_rows: None,
+            primary_key_candidate: None,
+        });
+        let row = Row::from([
+            ("id".to_string(), Value::Integer(9)),
+            ("topic".to_string(), Value::String("orders.eu".to_string())),
+        ]);
+
+        let messages = target.messages(&[row]).unwrap();
+        let (subject, headers, payload) = &messages[0];
+        assert_eq!(subject, "orders.eu");
+        assert_eq!(headers.get("Nats-Msg-Id").unwrap().as_str(), "9");
+        assert_eq!(payload, br#"{"id":9}"#);
+        assert_eq!(target.server.address, "nats://localhost:4222");
+    }
+}
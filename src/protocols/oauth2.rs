@@ -0,0 +1,262 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tracing::info;
+
+use crate::{secrets::resolve_secret, Result, TinyEtlError};
+
+/// Tokens are renewed this long before they expire, so a download started
+/// just before expiry doesn't fail halfway
+const EXPIRY_MARGIN: Duration = Duration::from_secs(30);
+
+/// Access tokens fetched so far, by token URL and client id, shared by every
+/// download of the process (e.g. the runs of a `foreach` job)
+static TOKENS: OnceLock<Mutex<HashMap<String, CachedToken>>> = OnceLock::new();
+
+#[derive(Clone)]
+struct CachedToken {
+    access_token: String,
+    expires_at: Option<Instant>,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    #[serde(default)]
+    expires_in: Option<u64>,
+}
+
+/// OAuth2 client credentials grant configured by the `auth.oauth2.*` options
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClientCredentials {
+    pub token_url: String,
+    pub client_id: String,
+    pub client_secret: String,
+    pub scope: Option<String>,
+}
+
+impl ClientCredentials {
+    /// Read the grant from source options; `None` when `auth.oauth2.token_url`
+    /// isn't set. The secret comes from `auth.oauth2.client_secret` or, via
+    /// the secrets module, `auth.oauth2.client_secret_id`.
+    pub fn from_options(options: &HashMap<String, String>) -> Result<Option<Self>> {
+        let Some(token_url) = options.get("auth.oauth2.token_url") else {
+            if options.keys().any(|key| key.starts_with("auth.oauth2.")) {
+                return Err(TinyEtlError::Configuration(
+                    "OAuth2 options need auth.oauth2.token_url".to_string(),
+                ));
+            }
+            return Ok(None);
+        };
+        let client_id = options.get("auth.oauth2.client_id").ok_or_else(|| {
+            TinyEtlError::Configuration("OAuth2 options need auth.oauth2.client_id".to_string())
+        })?;
+        let client_secret = match (
+            options.get("auth.oauth2.client_secret"),
+            options.get("auth.oauth2.client_secret_id"),
+        ) {
+            (Some(secret), None) => secret.clone(),
+            (None, Some(secret_id)) => resolve_secret(secret_id).map_err(|e| {
+                TinyEtlError::Configuration(format!("Failed to resolve OAuth2 client secret: {}", e))
+            })?,
+            _ => {
+                return Err(TinyEtlError::Configuration(
+                    "OAuth2 options need one of auth.oauth2.client_secret or auth.oauth2.client_secret_id"
+                        .to_string(),
+                ))
+            }
+        };
+        if options.contains_key("auth.bearer") || options.contains_key("auth.basic.username") {
+            return Err(TinyEtlError::Configuration(
+                "OAuth2 can't be combined with auth.bearer or auth.basic options".to_string(),
+            ));
+        }
+
+        Ok(Some(Self {
+            token_url: token_url.clone(),
+            client_id: client_id.clone(),
+            client_secret,
+            scope: options.get("auth.oauth2.scope").cloned(),
+        }))
+    }
+
+    /// Access token for a request, reusing one fetched earlier until it
+    /// is about to expire
+    pub async fn token(&self, client: &reqwest::Client) -> Result<String> {
+        let cached = tokens().get(&self.cache_key()).cloned();
+        match cached {
+            Some(token)
+                if token.expires_at.map_or(true, |expires_at| {
+                    Instant::now() + EXPIRY_MARGIN < expires_at
+                }) =>
+            {
+                Ok(token.access_token)
+            }
+            _ => self.refresh(client).await,
+        }
+    }
+
+    /// Fetch a new access token, e.g. after the server rejected the cached one
+    pub async fn refresh(&self, client: &reqwest::Client) -> Result<String> {
+        info!("Requesting OAuth2 access token from {}", self.token_url);
+        let mut form = vec![
+            ("grant_type", "client_credentials"),
+            ("client_id", self.client_id.as_str()),
+            ("client_secret", self.client_secret.as_str()),
+        ];
+        if let Some(scope) = &self.scope {
+            form.push(("scope", scope.as_str()));
+        }
+
+        let response = client
+            .post(&self.token_url)
+            .form(&form)
+            .send()
+            .await
+            .map_err(|e| {
+                TinyEtlError::Connection(format!(
+                    "Failed to request OAuth2 token from {}: {}",
+                    self.token_url, e
+                ))
+            })?;
+        if !response.status().is_success() {
+            return Err(TinyEtlError::Connection(format!(
+                "OAuth2 token request failed with status {}: {}",
+                response.status(),
+                self.token_url
+            )));
+        }
+        let body: TokenResponse = response.json().await.map_err(|e| {
+            TinyEtlError::Connection(format!("Invalid OAuth2 token response: {}", e))
+        })?;
+
+        let token = CachedToken {
+            access_token: body.access_token,
+            expires_at: body
+                .expires_in
+                .map(|seconds| Instant::now() + Duration::from_secs(seconds)),
+        };
+        tokens().insert(self.cache_key(), token.clone());
+        Ok(token.access_token)
+    }
+
+    fn cache_key(&self) -> String {
+        format!("{} {}", self.token_url, self.client_id)
+    }
+}
+
+fn tokens() -> std::sync::MutexGuard<'static, HashMap<String, CachedToken>> {
+    TOKENS
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    fn options(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    /// Read one HTTP request, headers and form body
+    async fn read_request(socket: &mut tokio::net::TcpStream) -> String {
+        let mut request = Vec::new();
+        let mut buffer = [0; 1024];
+        loop {
+            let read = socket.read(&mut buffer).await.unwrap();
+            request.extend_from_slice(&buffer[..read]);
+            let text = String::from_utf8_lossy(&request).into_owned();
+            let Some((head, body)) = text.split_once("\r\n\r\n") else {
+                if read == 0 {
+                    return text;
+                }
+                continue;
+            };
+            let length = head
+                .lines()
+                .find_map(|line| {
+                    line.to_lowercase()
+                        .strip_prefix("content-length:")
+                        .map(|n| n.trim().parse().unwrap())
+                })
+                .unwrap_or(0);
+            if read == 0 || body.len() >= length {
+                return text;
+            }
+        }
+    }
+
+    #[test]
+    fn test_client_credentials_from_options() {
+        assert_eq!(
+            ClientCredentials::from_options(&HashMap::new()).unwrap(),
+            None
+        );
+
+        std::env::set_var("TINYETL_SECRET_oauth_test", "s3cret");
+        let credentials = ClientCredentials::from_options(&options(&[
+            ("auth.oauth2.token_url", "https://login.example.com/token"),
+            ("auth.oauth2.client_id", "etl"),
+            ("auth.oauth2.client_secret_id", "oauth_test"),
+            ("auth.oauth2.scope", "reports.read"),
+        ]))
+        .unwrap()
+        .unwrap();
+        assert_eq!(credentials.client_secret, "s3cret");
+        assert_eq!(credentials.scope.as_deref(), Some("reports.read"));
+
+        assert!(
+            ClientCredentials::from_options(&options(&[("auth.oauth2.client_id", "etl")])).is_err()
+        );
+        assert!(ClientCredentials::from_options(&options(&[
+            ("auth.oauth2.token_url", "https://login.example.com/token"),
+            ("auth.oauth2.client_id", "etl"),
+        ]))
+        .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_tokens_are_cached_until_refreshed() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let token_url = format!("http://{}/token", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            let mut requests = Vec::new();
+            for n in 1..=2 {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                requests.push(read_request(&mut socket).await);
+                let body = format!(r#"{{"access_token":"token-{}","expires_in":3600}}"#, n);
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                socket.write_all(response.as_bytes()).await.unwrap();
+            }
+            requests
+        });
+
+        let credentials = ClientCredentials {
+            token_url,
+            client_id: "etl".to_string(),
+            client_secret: "s3cret".to_string(),
+            scope: None,
+        };
+        let client = reqwest::Client::new();
+        assert_eq!(credentials.token(&client).await.unwrap(), "token-1");
+        assert_eq!(credentials.token(&client).await.unwrap(), "token-1");
+        assert_eq!(credentials.refresh(&client).await.unwrap(), "token-2");
+        assert_eq!(credentials.token(&client).await.unwrap(), "token-2");
+
+        let requests = server.await.unwrap();
+        assert!(requests[0].contains("grant_type=client_credentials"));
+        assert!(requests[0].contains("client_secret=s3cret"));
+    }
+}
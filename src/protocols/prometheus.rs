@@ -0,0 +1,537 @@
+use async_trait::async_trait;
+use chrono::{DateTime, TimeZone, Utc};
+use std::collections::{BTreeSet, HashMap};
+use tracing::info;
+use url::Url;
+
+use crate::{
+    connectors::{connection_url::percent_decode, Source, Target},
+    protocols::Protocol,
+    safety::display_target,
+    schema::{Column, DataType, Row, Schema, Value},
+    strict::Coercion,
+    Result, TinyEtlError,
+};
+
+const DEFAULT_PORT: u16 = 9090;
+const RANGE_PATH: &str = "/api/v1/query_range";
+/// Points per series a range request asks for; Prometheus refuses more
+/// than 11,000
+const POINTS_PER_REQUEST: f64 = 10_000.0;
+const DEFAULT_STEP: f64 = 60.0;
+
+/// Prometheus protocol: sources evaluate a PromQL query over a time range
+/// (`prom://host:9090/api/v1/query_range?query=...&start=...`) and read one
+/// row per sample. Servers with the same HTTP API, such as VictoriaMetrics
+/// and Thanos, work too. There is no Prometheus target.
+pub struct PrometheusProtocol;
+
+impl Default for PrometheusProtocol {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PrometheusProtocol {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+#[async_trait]
+impl Protocol for PrometheusProtocol {
+    async fn create_source(
+        &self,
+        url: &Url,
+        options: &HashMap<String, String>,
+    ) -> Result<Box<dyn Source>> {
+        Ok(Box::new(PrometheusSource::new(url, options)?))
+    }
+
+    async fn create_target(
+        &self,
+        _url: &Url,
+        _options: &HashMap<String, String>,
+    ) -> Result<Box<dyn Target>> {
+        Err(TinyEtlError::Configuration(
+            "Prometheus can only be used as a source: use remote write to store metrics"
+                .to_string(),
+        ))
+    }
+
+    fn validate_url(&self, url: &Url) -> Result<()> {
+        if !matches!(url.scheme(), "prom" | "prometheus") {
+            return Err(TinyEtlError::Configuration(format!(
+                "Prometheus protocol requires prom:// scheme, got: {}",
+                url.scheme()
+            )));
+        }
+
+        if url.host().is_none() {
+            return Err(TinyEtlError::Configuration(
+                "Prometheus protocol requires a host".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "prometheus"
+    }
+}
+
+/// Seconds of a Prometheus duration such as `30s`, `5m` or `1h30m`; a bare
+/// number is seconds
+fn parse_duration(text: &str) -> Option<f64> {
+    if let Ok(seconds) = text.parse::<f64>() {
+        return Some(seconds);
+    }
+    let mut total = 0.0;
+    let mut rest = text;
+    while !rest.is_empty() {
+        let digits = rest.find(|c: char| !c.is_ascii_digit())?;
+        let amount: f64 = rest[..digits].parse().ok()?;
+        rest = &rest[digits..];
+        let unit_end = rest
+            .find(|c: char| c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let unit = match &rest[..unit_end] {
+            "ms" => 0.001,
+            "s" => 1.0,
+            "m" => 60.0,
+            "h" => 3_600.0,
+            "d" => 86_400.0,
+            "w" => 604_800.0,
+            "y" => 31_536_000.0,
+            _ => return None,
+        };
+        total += amount * unit;
+        rest = &rest[unit_end..];
+    }
+    Some(total)
+}
+
+/// Unix seconds of a time given as RFC 3339, Unix seconds, `now` or
+/// `now-<duration>`
+fn parse_time(text: &str, now: DateTime<Utc>) -> Option<f64> {
+    let now_seconds = now.timestamp_millis() as f64 / 1000.0;
+    if text == "now" {
+        Some(now_seconds)
+    } else if let Some(ago) = text.strip_prefix("now-") {
+        Some(now_seconds - parse_duration(ago)?)
+    } else if let Ok(seconds) = text.parse::<f64>() {
+        Some(seconds)
+    } else {
+        DateTime::parse_from_rfc3339(text)
+            .ok()
+            .map(|dt| dt.timestamp_millis() as f64 / 1000.0)
+    }
+}
+
+/// Column a label is read into; the metric name label becomes `metric`
+fn label_column(label: &str) -> &str {
+    if label == "__name__" {
+        "metric"
+    } else {
+        label
+    }
+}
+
+/// Rows of a query result, one per sample: `timestamp`, the series labels
+/// and `value`. Values no decimal can hold (NaN, ±Inf) are read as text.
+fn result_rows(data: &serde_json::Value, coercion: Coercion) -> Result<Vec<Row>> {
+    let sample_row = |labels: &Row, sample: &serde_json::Value| -> Result<Row> {
+        let invalid =
+            || TinyEtlError::DataValidation(format!("Invalid Prometheus sample: {}", sample));
+        let seconds = sample[0].as_f64().ok_or_else(invalid)?;
+        let text = sample[1].as_str().ok_or_else(invalid)?;
+        let timestamp = Utc
+            .timestamp_millis_opt((seconds * 1000.0).round() as i64)
+            .single()
+            .ok_or_else(invalid)?;
+        let value = match text.parse::<f64>() {
+            Ok(f) if f.is_finite() => coercion.decimal_from_f64(f, "value")?,
+            _ => coercion.lossy(
+                "value",
+                text,
+                "has no decimal equivalent",
+                Value::String(text.to_string()),
+            )?,
+        };
+        let mut row = labels.clone();
+        row.insert("timestamp".to_string(), Value::Date(timestamp));
+        row.insert("value".to_string(), value);
+        Ok(row)
+    };
+
+    let result = &data["result"];
+    match data["resultType"].as_str() {
+        Some("matrix") | Some("vector") => {
+            let mut rows = Vec::new();
+            for series in result.as_array().map(Vec::as_slice).unwrap_or_default() {
+                let labels: Row = series["metric"]
+                    .as_object()
+                    .into_iter()
+                    .flatten()
+                    .map(|(label, value)| {
+                        (
+                            label_column(label).to_string(),
+                            Value::String(value.as_str().unwrap_or_default().to_string()),
+                        )
+                    })
+                    .collect();
+                match series.get("values") {
+                    Some(values) => {
+                        for sample in values.as_array().map(Vec::as_slice).unwrap_or_default() {
+                            rows.push(sample_row(&labels, sample)?);
+                        }
+                    }
+                    None => rows.push(sample_row(&labels, &series["value"])?),
+                }
+            }
+            Ok(rows)
+        }
+        Some("scalar") => Ok(vec![sample_row(&Row::new(), result)?]),
+        other => Err(TinyEtlError::DataValidation(format!(
+            "Unsupported Prometheus result type: {}",
+            other.unwrap_or("none")
+        ))),
+    }
+}
+
+/// Source evaluating a PromQL query. Range queries are split into requests
+/// of at most 10,000 steps, fetched as rows are read; `reset` starts again
+/// from the first one.
+pub struct PrometheusSource {
+    url: String,
+    endpoint: String,
+    /// Query string parameters other than the range, sent with every request
+    params: Vec<(String, String)>,
+    /// Start and end (Unix seconds) of each range request; empty for
+    /// instant queries
+    windows: Vec<(f64, f64)>,
+    step: f64,
+    user: Option<(String, Option<String>)>,
+    token: Option<String>,
+    http: reqwest::Client,
+    next_request: usize,
+    requests: usize,
+    pending: Vec<Row>,
+    /// Label columns of the series seen when connecting
+    labels: BTreeSet<String>,
+    connected: bool,
+    coercion: Coercion,
+}
+
+impl PrometheusSource {
+    fn new(url: &Url, options: &HashMap<String, String>) -> Result<Self> {
+        let host = url.host_str().ok_or_else(|| {
+            TinyEtlError::Configuration("Prometheus URL must include a host".to_string())
+        })?;
+        let https = match options.get("https").map(String::as_str) {
+            None | Some("false") => false,
+            Some("true") => true,
+            Some(other) => {
+                return Err(TinyEtlError::Configuration(format!(
+                    "Invalid https '{}': expected true or false",
+                    other
+                )))
+            }
+        };
+        let path = match url.path() {
+            "" | "/" => RANGE_PATH,
+            path => path,
+        };
+        let endpoint = format!(
+            "{}://{}:{}{}",
+            if https { "https" } else { "http" },
+            host,
+            url.port().unwrap_or(DEFAULT_PORT),
+            path
+        );
+
+        let mut query = None;
+        let mut start = None;
+        let mut end = None;
+        let mut step = None;
+        let mut params = Vec::new();
+        for (key, value) in url.query_pairs() {
+            match key.as_ref() {
+                "query" => query = Some(value.to_string()),
+                "start" => start = Some(value.to_string()),
+                "end" => end = Some(value.to_string()),
+                "step" => step = Some(value.to_string()),
+                _ => params.push((key.to_string(), value.to_string())),
+            }
+        }
+        let query = query.ok_or_else(|| {
+            TinyEtlError::Configuration(
+                "Prometheus URL needs a query parameter, e.g. ?query=up&start=now-1h".to_string(),
+            )
+        })?;
+        params.push(("query".to_string(), query));
+
+        let now = Utc::now();
+        let time = |name: &str, text: &str| {
+            parse_time(text, now).ok_or_else(|| {
+                TinyEtlError::Configuration(format!(
+                    "Invalid Prometheus {} '{}': expected RFC 3339, Unix seconds or now-<duration>",
+                    name, text
+                ))
+            })
+        };
+        let step = match &step {
+            Some(text) => parse_duration(text)
+                .filter(|step| *step > 0.0)
+                .ok_or_else(|| {
+                    TinyEtlError::Configuration(format!("Invalid Prometheus step '{}'", text))
+                })?,
+            None => DEFAULT_STEP,
+        };
+        let windows = if path.ends_with("/query_range") {
+            let start = start.ok_or_else(|| {
+                TinyEtlError::Configuration(
+                    "Prometheus range queries need a start parameter".to_string(),
+                )
+            })?;
+            let start = time("start", &start)?;
+            let end = time("end", end.as_deref().unwrap_or("now"))?;
+            if end < start {
+                return Err(TinyEtlError::Configuration(
+                    "Prometheus query ends before it starts".to_string(),
+                ));
+            }
+            let span = step * (POINTS_PER_REQUEST - 1.0);
+            let mut windows = Vec::new();
+            let mut from = start;
+            while from <= end {
+                let to = (from + span).min(end);
+                windows.push((from, to));
+                from = to + step;
+            }
+            windows
+        } else {
+            // Instant queries evaluate at `time`, or now
+            if let Some(start) = start {
+                params.push(("time".to_string(), time("start", &start)?.to_string()));
+            }
+            Vec::new()
+        };
+
+        let user = match url.username() {
+            "" => None,
+            user => Some((percent_decode(user), url.password().map(percent_decode))),
+        };
+        let requests = windows.len().max(1);
+
+        Ok(Self {
+            url: url.to_string(),
+            endpoint,
+            params,
+            windows,
+            step,
+            user,
+            token: options.get("token").cloned(),
+            http: reqwest::Client::new(),
+            next_request: 0,
+            requests,
+            pending: Vec::new(),
+            labels: BTreeSet::new(),
+            connected: false,
+            coercion: Coercion::default(),
+        })
+    }
+
+    /// Run the next request and add its samples to `pending`
+    async fn fetch_next(&mut self) -> Result<()> {
+        let mut params = self.params.clone();
+        if let Some((start, end)) = self.windows.get(self.next_request) {
+            params.push(("start".to_string(), start.to_string()));
+            params.push(("end".to_string(), end.to_string()));
+            params.push(("step".to_string(), self.step.to_string()));
+        }
+        self.next_request += 1;
+
+        let mut request = self.http.post(&self.endpoint).form(&params);
+        if let Some((user, password)) = &self.user {
+            request = request.basic_auth(user, password.as_ref());
+        }
+        if let Some(token) = &self.token {
+            request = request.bearer_auth(token);
+        }
+        let request_error = |e: reqwest::Error| {
+            TinyEtlError::Connection(format!(
+                "Prometheus request to {} failed: {}",
+                display_target(&self.url),
+                e
+            ))
+        };
+        let response = request.send().await.map_err(request_error)?;
+        let status = response.status();
+        // Query errors come back as JSON with a 4xx or 5xx status
+        let body: serde_json::Value = match response.json().await {
+            Ok(body) => body,
+            Err(_) if !status.is_success() => {
+                return Err(TinyEtlError::Connection(format!(
+                    "Prometheus request to {} failed: {}",
+                    display_target(&self.url),
+                    status
+                )))
+            }
+            Err(e) => return Err(request_error(e)),
+        };
+        if body["status"] != "success" {
+            return Err(TinyEtlError::DataTransfer(format!(
+                "Prometheus query failed: {}",
+                body["error"].as_str().unwrap_or(status.as_str())
+            )));
+        }
+
+        let rows = result_rows(&body["data"], self.coercion)?;
+        self.pending.extend(rows);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Source for PrometheusSource {
+    async fn connect(&mut self) -> Result<()> {
+        self.reset().await?;
+        info!(
+            "Querying Prometheus in {} request(s) of up to {} steps",
+            self.requests, POINTS_PER_REQUEST
+        );
+        // The label columns come from the first series returned
+        while self.pending.is_empty() && self.next_request < self.requests {
+            self.fetch_next().await?;
+        }
+        self.labels = self
+            .pending
+            .iter()
+            .flat_map(|row| row.keys())
+            .filter(|key| !matches!(key.as_str(), "timestamp" | "value"))
+            .cloned()
+            .collect();
+        self.connected = true;
+        Ok(())
+    }
+
+    async fn infer_schema(&mut self, _sample_size: usize) -> Result<Schema> {
+        if !self.connected {
+            self.connect().await?;
+        }
+        let value_type = match self.pending.first().map(|row| &row["value"]) {
+            Some(Value::String(_)) => DataType::String,
+            _ => DataType::Decimal,
+        };
+        let mut columns = vec![Column {
+            name: "timestamp".to_string(),
+            data_type: DataType::DateTime,
+            nullable: false,
+        }];
+        columns.extend(self.labels.iter().map(|label| Column {
+            name: label.clone(),
+            data_type: DataType::String,
+            nullable: true,
+        }));
+        columns.push(Column {
+            name: "value".to_string(),
+            data_type: value_type,
+            nullable: false,
+        });
+        Ok(Schema {
+            columns,
+            estimated_rows: None,
+            primary_key_candidate: None,
+        })
+    }
+
+    async fn read_batch(&mut self, batch_size: usize) -> Result<Vec<Row>> {
+        if !self.connected {
+            self.connect().await?;
+        }
+        while self.pending.len() < batch_size && self.next_request < self.requests {
+            self.fetch_next().await?;
+        }
+        let count = batch_size.min(self.pending.len());
+        Ok(self.pending.drain(..count).collect())
+    }
+
+    async fn estimated_row_count(&self) -> Result<Option<usize>> {
+        Ok(None)
+    }
+
+    fn set_coercion(&mut self, coercion: Coercion) {
+        self.coercion = coercion;
+    }
+
+    async fn reset(&mut self) -> Result<()> {
+        // Queries can be evaluated again from the first request
+        self.next_request = 0;
+        self.pending.clear();
+        Ok(())
+    }
+
+    fn has_more(&self) -> bool {
+        !self.pending.is_empty() || self.next_request < self.requests
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_range_is_split_into_requests() {
+        assert_eq!(parse_duration("1h30m"), Some(5_400.0));
+        assert_eq!(parse_duration("15"), Some(15.0));
+        assert_eq!(parse_duration("5 minutes"), None);
+        let now = Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap();
+        assert_eq!(parse_time("now-1d", now), Some(1_714_478_400.0));
+        assert_eq!(
+            parse_time("2024-05-01T12:00:00Z", now),
+            Some(1_714_564_800.0)
+        );
+
+        let url = Url::parse(
+            "prom://metrics:9090/api/v1/query_range?query=rate(http_requests_total%5B5m%5D)&start=1714478400&end=1714564800&step=1s&dedup=1",
+        )
+        .unwrap();
+        let source = PrometheusSource::new(&url, &HashMap::new()).unwrap();
+        assert_eq!(source.endpoint, "http://metrics:9090/api/v1/query_range");
+        assert_eq!(source.windows.len(), 9);
+        assert_eq!(source.windows[0], (1_714_478_400.0, 1_714_488_399.0));
+        assert_eq!(source.windows[1].0, 1_714_488_400.0);
+        assert_eq!(source.windows[8].1, 1_714_564_800.0);
+        assert!(source.params.contains(&(
+            "query".to_string(),
+            "rate(http_requests_total[5m])".to_string()
+        )));
+        assert!(source
+            .params
+            .contains(&("dedup".to_string(), "1".to_string())));
+    }
+
+    #[test]
+    fn test_samples_become_rows() {
+        let data = serde_json::json!({
+            "resultType": "matrix",
+            "result": [{
+                "metric": {"__name__": "up", "job": "api"},
+                "values": [[1714478400, "1"], [1714478460.5, "NaN"]]
+            }]
+        });
+        let rows = result_rows(&data, Coercion::default()).unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0]["metric"], Value::String("up".to_string()));
+        assert_eq!(rows[0]["job"], Value::String("api".to_string()));
+        assert_eq!(rows[0]["value"], Value::Decimal(1.into()));
+        assert_eq!(
+            rows[1]["timestamp"],
+            Value::Date(Utc.timestamp_millis_opt(1_714_478_460_500).unwrap())
+        );
+        assert_eq!(rows[1]["value"], Value::String("NaN".to_string()));
+        assert!(result_rows(&data, Coercion::strict()).is_err());
+    }
+}
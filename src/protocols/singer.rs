@@ -0,0 +1,727 @@
+use async_trait::async_trait;
+use serde_json::{json, Value as Json};
+use std::collections::{BTreeSet, HashMap};
+use std::path::Path;
+use std::process::Stdio;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncWrite, AsyncWriteExt, BufReader, Lines};
+use tokio::process::{Child, Command};
+use tracing::{info, warn};
+use url::Url;
+
+use crate::{
+    connectors::{
+        connection_url::percent_decode,
+        json::{read_json_value, value_to_json},
+        Source, Target,
+    },
+    protocols::Protocol,
+    schema::{BinaryEncoding, Column, DataType, Row, Schema, Value},
+    schema_export::json_schema,
+    strict::Coercion,
+    Result, TinyEtlError,
+};
+
+/// Singer protocol: sources run a tap (`singer://tap-github?config=tap.json`)
+/// and read the SCHEMA, RECORD and STATE messages it prints, targets pipe
+/// SCHEMA and RECORD messages into a Singer target
+/// (`singer://target-postgres?config=target.json#orders`). `singer://-`
+/// reads the messages from STDIN or writes them to STDOUT instead.
+pub struct SingerProtocol;
+
+impl Default for SingerProtocol {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SingerProtocol {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+#[async_trait]
+impl Protocol for SingerProtocol {
+    async fn create_source(
+        &self,
+        url: &Url,
+        options: &HashMap<String, String>,
+    ) -> Result<Box<dyn Source>> {
+        Ok(Box::new(SingerSource::new(url, options)?))
+    }
+
+    async fn create_target(
+        &self,
+        url: &Url,
+        options: &HashMap<String, String>,
+    ) -> Result<Box<dyn Target>> {
+        Ok(Box::new(SingerTarget::new(url, options)?))
+    }
+
+    fn validate_url(&self, url: &Url) -> Result<()> {
+        if url.scheme() != "singer" {
+            return Err(TinyEtlError::Configuration(format!(
+                "Singer protocol requires singer:// scheme, got: {}",
+                url.scheme()
+            )));
+        }
+
+        if SingerCommand::program(url).is_empty() {
+            return Err(TinyEtlError::Configuration(
+                "Singer URL must name a tap or target to run (singer://tap-name), or - for STDIN/STDOUT"
+                    .to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "singer"
+    }
+}
+
+/// Settings of a `singer://` or `airbyte://` URL: its query string,
+/// overridden by options
+pub(crate) fn settings(url: &Url, options: &HashMap<String, String>) -> HashMap<String, String> {
+    let mut settings: HashMap<String, String> = url.query_pairs().into_owned().collect();
+    settings.extend(options.clone());
+    settings
+}
+
+/// A tap or target to run, with the files Singer passes on its command line
+#[derive(Debug, Clone, PartialEq)]
+struct SingerCommand {
+    program: String,
+    config: Option<String>,
+    catalog: Option<String>,
+    state: Option<String>,
+}
+
+impl SingerCommand {
+    /// The executable, `singer://tap-name` from the PATH or
+    /// `singer:///opt/taps/bin/tap-name`; `-` stands for STDIN/STDOUT
+    fn program(url: &Url) -> String {
+        percent_decode(&format!("{}{}", url.host_str().unwrap_or(""), url.path()))
+    }
+
+    /// None for `singer://-`
+    fn parse(url: &Url, settings: &HashMap<String, String>) -> Option<Self> {
+        let program = Self::program(url);
+        (program != "-").then(|| Self {
+            program,
+            config: settings.get("config").cloned(),
+            catalog: settings.get("catalog").cloned(),
+            state: settings.get("state").cloned(),
+        })
+    }
+
+    fn args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if let Some(config) = &self.config {
+            args.extend(["--config".to_string(), config.clone()]);
+        }
+        if let Some(catalog) = &self.catalog {
+            args.extend(["--catalog".to_string(), catalog.clone()]);
+        }
+        // The first run of an incremental sync has no state yet
+        if let Some(state) = self
+            .state
+            .as_ref()
+            .filter(|state| Path::new(state).exists())
+        {
+            args.extend(["--state".to_string(), state.clone()]);
+        }
+        args
+    }
+
+    fn spawn(&self, stdin: Stdio, stdout: Stdio) -> Result<Child> {
+        Command::new(&self.program)
+            .args(self.args())
+            .stdin(stdin)
+            .stdout(stdout)
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| TinyEtlError::Connection(format!("Failed to run {}: {}", self.program, e)))
+    }
+
+    async fn wait(&self, child: &mut Child) -> Result<()> {
+        let status = child.wait().await?;
+        if !status.success() {
+            return Err(TinyEtlError::DataTransfer(format!(
+                "{} exited with {}",
+                self.program, status
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// TinyETL type and nullability of a JSON Schema property
+fn column_type(property: &Json) -> (DataType, bool) {
+    // {"anyOf": [{"type": "string", "format": "date-time"}, {"type": "null"}]}
+    if let Some(variants) = property.get("anyOf").and_then(Json::as_array) {
+        let nullable = variants.iter().any(|variant| variant["type"] == "null");
+        let data_type = variants
+            .iter()
+            .find(|variant| variant["type"] != "null")
+            .map_or(DataType::Json, |variant| column_type(variant).0);
+        return (data_type, nullable);
+    }
+
+    let types: Vec<&str> = match &property["type"] {
+        Json::String(data_type) => vec![data_type.as_str()],
+        Json::Array(types) => types.iter().filter_map(Json::as_str).collect(),
+        _ => Vec::new(),
+    };
+    let nullable = types.is_empty() || types.contains(&"null");
+    let types: Vec<&str> = types.into_iter().filter(|t| *t != "null").collect();
+    let data_type = match (types.as_slice(), property["format"].as_str()) {
+        (["integer"], _) => DataType::Integer,
+        (["number"] | ["integer", "number"] | ["number", "integer"], _) => DataType::Decimal,
+        (["boolean"], _) => DataType::Boolean,
+        (["string"], Some("date-time")) => DataType::DateTime,
+        (["string"], Some("date")) => DataType::Date,
+        ([] | ["object"] | ["array"], _) => DataType::Json,
+        _ => DataType::String,
+    };
+    (data_type, nullable)
+}
+
+/// Columns of the JSON Schema of a stream
+pub(crate) fn schema_columns(json_schema: &Json) -> Vec<Column> {
+    json_schema["properties"]
+        .as_object()
+        .into_iter()
+        .flatten()
+        .map(|(name, property)| {
+            let (data_type, nullable) = column_type(property);
+            Column {
+                name: name.clone(),
+                data_type,
+                nullable,
+            }
+        })
+        .collect()
+}
+
+/// Schema of a SCHEMA message
+fn message_schema(message: &Json) -> Schema {
+    let key_properties: Vec<&str> = message["key_properties"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(Json::as_str)
+        .collect();
+
+    Schema {
+        columns: schema_columns(&message["schema"]),
+        estimated_rows: None,
+        primary_key_candidate: match key_properties.as_slice() {
+            [key] => Some(key.to_string()),
+            _ => None,
+        },
+    }
+}
+
+/// Row of the record of a RECORD message, typed by the stream's schema
+pub(crate) fn record_row(schema: &Schema, record: &Json, coercion: Coercion) -> Result<Row> {
+    let record = record.as_object().ok_or_else(|| {
+        TinyEtlError::DataValidation("RECORD message holds no record object".to_string())
+    })?;
+    schema
+        .columns
+        .iter()
+        .map(|column| {
+            let value = match (&column.data_type, record.get(&column.name)) {
+                (_, None | Some(Json::Null)) => Value::Null,
+                (DataType::Json, Some(json)) => Value::Json(json.clone()),
+                (DataType::String, Some(Json::String(s))) => Value::String(s.clone()),
+                (DataType::String, Some(json)) => Value::String(json.to_string()),
+                // Exactly as written rather than through f64
+                (DataType::Decimal, Some(Json::Number(n))) => match n.to_string().parse() {
+                    Ok(decimal) => Value::Decimal(decimal),
+                    Err(_) => read_json_value(&column.name, &Json::Number(n.clone()), coercion)?,
+                },
+                (_, Some(json)) => read_json_value(&column.name, json, coercion)?,
+            };
+            Ok((column.name.clone(), value))
+        })
+        .collect()
+}
+
+type MessageLines = Lines<Box<dyn AsyncBufRead + Send + Sync + Unpin>>;
+
+/// Source reading the messages of one stream of a Singer tap: the stream
+/// named by the URL fragment, or the first one the tap describes. The last
+/// STATE message is written to the `state` file once the target is
+/// finalized, and passed to the tap on the next run.
+pub struct SingerSource {
+    command: Option<SingerCommand>,
+    stream: Option<String>,
+    state_file: Option<String>,
+    child: Option<Child>,
+    lines: Option<MessageLines>,
+    schema: Option<Schema>,
+    /// Rows read and not yet handed out, plus those handed out since the
+    /// last time all were; `reset` rewinds to the first of them
+    rows: Vec<Row>,
+    position: usize,
+    done: bool,
+    state: Option<Json>,
+    skipped_streams: BTreeSet<String>,
+    coercion: Coercion,
+}
+
+impl SingerSource {
+    fn new(url: &Url, options: &HashMap<String, String>) -> Result<Self> {
+        let settings = settings(url, options);
+        Ok(Self {
+            command: SingerCommand::parse(url, &settings),
+            stream: url.fragment().map(percent_decode),
+            state_file: settings.get("state").cloned(),
+            child: None,
+            lines: None,
+            schema: None,
+            rows: Vec::new(),
+            position: 0,
+            done: false,
+            state: None,
+            skipped_streams: BTreeSet::new(),
+            coercion: Coercion::default(),
+        })
+    }
+
+    fn source_name(&self) -> &str {
+        self.command
+            .as_ref()
+            .map_or("STDIN", |c| c.program.as_str())
+    }
+
+    /// Read messages until `rows` holds `wanted` rows not handed out yet,
+    /// the stream's schema is known (`wanted` of 0) or the tap is done
+    async fn fetch(&mut self, wanted: usize) -> Result<()> {
+        while !self.done && (self.schema.is_none() || self.rows.len() - self.position < wanted) {
+            let lines = self.lines.as_mut().ok_or_else(|| {
+                TinyEtlError::Connection("Singer source is not connected".to_string())
+            })?;
+            let Some(line) = lines.next_line().await? else {
+                self.finish().await?;
+                break;
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+            let message: Json = serde_json::from_str(&line).map_err(|e| {
+                TinyEtlError::DataValidation(format!(
+                    "{} printed a line that is not a Singer message: {}",
+                    self.source_name(),
+                    e
+                ))
+            })?;
+            self.handle(message)?;
+        }
+        Ok(())
+    }
+
+    fn handle(&mut self, message: Json) -> Result<()> {
+        let stream = message["stream"].as_str().unwrap_or_default();
+        match message["type"].as_str() {
+            Some("SCHEMA") => {
+                let selected = self.stream.get_or_insert_with(|| stream.to_string());
+                if selected.as_str() != stream {
+                    return Ok(());
+                }
+                let schema = message_schema(&message);
+                if let Some(previous) = &self.schema {
+                    let names = |schema: &Schema| -> Vec<String> {
+                        schema.columns.iter().map(|c| c.name.clone()).collect()
+                    };
+                    if names(previous) != names(&schema) {
+                        return Err(TinyEtlError::SchemaInference(format!(
+                            "The schema of Singer stream '{}' changed during the run",
+                            stream
+                        )));
+                    }
+                }
+                self.schema = Some(schema);
+            }
+            Some("RECORD") => {
+                if self.stream.as_deref() != Some(stream) {
+                    if self.skipped_streams.insert(stream.to_string()) {
+                        warn!(
+                            "Skipping records of Singer stream '{}'; select it with #{}",
+                            stream, stream
+                        );
+                    }
+                    return Ok(());
+                }
+                let schema = self.schema.as_ref().ok_or_else(|| {
+                    TinyEtlError::DataValidation(format!(
+                        "Singer stream '{}' sent a RECORD before its SCHEMA",
+                        stream
+                    ))
+                })?;
+                let row = record_row(schema, &message["record"], self.coercion)?;
+                self.rows.push(row);
+            }
+            Some("STATE") => self.state = Some(message["value"].clone()),
+            Some("BATCH") => {
+                return Err(TinyEtlError::DataTransfer(
+                    "Singer BATCH messages are not supported; configure the tap to send RECORD messages"
+                        .to_string(),
+                ))
+            }
+            // ACTIVATE_VERSION and messages of later spec versions
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// The tap printed everything: check that it succeeded
+    async fn finish(&mut self) -> Result<()> {
+        self.done = true;
+        self.lines = None;
+        if let (Some(command), Some(mut child)) = (&self.command, self.child.take()) {
+            command.wait(&mut child).await?;
+        }
+        if self.schema.is_none() {
+            return Err(TinyEtlError::SchemaInference(match &self.stream {
+                Some(stream) => format!(
+                    "{} sent no SCHEMA message for stream '{}'",
+                    self.source_name(),
+                    stream
+                ),
+                None => format!("{} sent no SCHEMA message", self.source_name()),
+            }));
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Source for SingerSource {
+    async fn connect(&mut self) -> Result<()> {
+        let input: Box<dyn AsyncBufRead + Send + Sync + Unpin> = match &self.command {
+            Some(command) => {
+                info!("Running Singer tap {}", command.program);
+                let mut child = command.spawn(Stdio::null(), Stdio::piped())?;
+                let stdout = child.stdout.take().ok_or_else(|| {
+                    TinyEtlError::Connection(format!("No output from {}", command.program))
+                })?;
+                self.child = Some(child);
+                Box::new(BufReader::new(stdout))
+            }
+            None => Box::new(BufReader::new(tokio::io::stdin())),
+        };
+        self.lines = Some(input.lines());
+        Ok(())
+    }
+
+    async fn infer_schema(&mut self, _sample_size: usize) -> Result<Schema> {
+        // The tap declares the schema; no records need sampling
+        self.fetch(0).await?;
+        self.schema.clone().ok_or_else(|| {
+            TinyEtlError::SchemaInference(format!("{} sent no SCHEMA message", self.source_name()))
+        })
+    }
+
+    async fn read_batch(&mut self, batch_size: usize) -> Result<Vec<Row>> {
+        if self.position > 0 && self.position == self.rows.len() {
+            self.rows.clear();
+            self.position = 0;
+        }
+        self.fetch(batch_size).await?;
+        let end = (self.position + batch_size).min(self.rows.len());
+        let batch = self.rows[self.position..end].to_vec();
+        self.position = end;
+        Ok(batch)
+    }
+
+    async fn estimated_row_count(&self) -> Result<Option<usize>> {
+        Ok(None)
+    }
+
+    fn set_coercion(&mut self, coercion: Coercion) {
+        self.coercion = coercion;
+    }
+
+    async fn commit(&mut self) -> Result<()> {
+        if let (Some(path), Some(state)) = (&self.state_file, &self.state) {
+            std::fs::write(path, serde_json::to_string_pretty(state)?)?;
+            info!("Singer state written to {}", path);
+        }
+        Ok(())
+    }
+
+    async fn reset(&mut self) -> Result<()> {
+        self.position = 0;
+        Ok(())
+    }
+
+    fn has_more(&self) -> bool {
+        self.position < self.rows.len() || !self.done
+    }
+}
+
+/// Target printing a SCHEMA message for the stream named by the URL
+/// fragment and a RECORD message per row, into a Singer target it runs or
+/// to STDOUT. `key_properties` lists the stream's key columns.
+pub struct SingerTarget {
+    command: Option<SingerCommand>,
+    stream: String,
+    key_properties: Vec<String>,
+    binary_encoding: BinaryEncoding,
+    schema: Option<Schema>,
+    child: Option<Child>,
+    output: Option<Box<dyn AsyncWrite + Send + Sync + Unpin>>,
+}
+
+impl SingerTarget {
+    fn new(url: &Url, options: &HashMap<String, String>) -> Result<Self> {
+        let settings = settings(url, options);
+        let stream = url.fragment().map(percent_decode).ok_or_else(|| {
+            TinyEtlError::Configuration(
+                "Singer target URL must name the stream: singer://target-name#stream".to_string(),
+            )
+        })?;
+        Ok(Self {
+            command: SingerCommand::parse(url, &settings),
+            stream,
+            key_properties: settings
+                .get("key_properties")
+                .map(|columns| {
+                    columns
+                        .split(',')
+                        .map(|column| column.trim().to_string())
+                        .filter(|column| !column.is_empty())
+                        .collect()
+                })
+                .unwrap_or_default(),
+            binary_encoding: settings
+                .get("binary_encoding")
+                .map(|encoding| {
+                    encoding
+                        .parse::<BinaryEncoding>()
+                        .map_err(|e| TinyEtlError::Configuration(e.to_string()))
+                })
+                .transpose()?
+                .unwrap_or_default(),
+            schema: None,
+            child: None,
+            output: None,
+        })
+    }
+
+    fn schema_message(&self, schema: &Schema) -> Json {
+        json!({
+            "type": "SCHEMA",
+            "stream": self.stream,
+            "schema": json_schema(schema, &self.stream),
+            "key_properties": self.key_properties,
+        })
+    }
+
+    fn record_message(&self, row: &Row) -> Json {
+        let columns = self.schema.iter().flat_map(|schema| &schema.columns);
+        let record: serde_json::Map<String, Json> = columns
+            .map(|column| {
+                let value = row
+                    .get(&column.name)
+                    .map(|value| value_to_json(value, self.binary_encoding))
+                    .unwrap_or(Json::Null);
+                (column.name.clone(), value)
+            })
+            .collect();
+        json!({ "type": "RECORD", "stream": self.stream, "record": record })
+    }
+
+    async fn send(&mut self, messages: &[Json]) -> Result<()> {
+        let mut text = String::new();
+        for message in messages {
+            text.push_str(&serde_json::to_string(message)?);
+            text.push('\n');
+        }
+        let output = self.output.as_mut().ok_or_else(|| {
+            TinyEtlError::Connection("Singer target is not connected".to_string())
+        })?;
+        output.write_all(text.as_bytes()).await.map_err(|e| {
+            TinyEtlError::DataTransfer(format!("Failed to send Singer messages: {}", e))
+        })
+    }
+}
+
+#[async_trait]
+impl Target for SingerTarget {
+    async fn connect(&mut self) -> Result<()> {
+        self.output = Some(match &self.command {
+            Some(command) => {
+                info!("Running Singer target {}", command.program);
+                // The target's STATE messages go to our STDOUT
+                let mut child = command.spawn(Stdio::piped(), Stdio::inherit())?;
+                let stdin = child.stdin.take().ok_or_else(|| {
+                    TinyEtlError::Connection(format!("Can't write to {}", command.program))
+                })?;
+                self.child = Some(child);
+                Box::new(stdin)
+            }
+            None => Box::new(tokio::io::stdout()),
+        });
+        Ok(())
+    }
+
+    async fn create_table(&mut self, _table_name: &str, schema: &Schema) -> Result<()> {
+        for key in &self.key_properties {
+            if !schema.columns.iter().any(|c| &c.name == key) {
+                return Err(TinyEtlError::Configuration(format!(
+                    "Singer key property '{}' is not in the output schema",
+                    key
+                )));
+            }
+        }
+        let message = self.schema_message(schema);
+        self.schema = Some(schema.clone());
+        self.send(&[message]).await
+    }
+
+    async fn write_batch(&mut self, rows: &[Row]) -> Result<usize> {
+        let messages: Vec<Json> = rows.iter().map(|row| self.record_message(row)).collect();
+        self.send(&messages).await?;
+        Ok(rows.len())
+    }
+
+    async fn finalize(&mut self) -> Result<()> {
+        if let Some(mut output) = self.output.take() {
+            output.flush().await?;
+            output.shutdown().await?;
+        }
+        // With its input closed the target loads what it buffered and exits
+        if let (Some(command), Some(mut child)) = (&self.command, self.child.take()) {
+            command.wait(&mut child).await?;
+        }
+        Ok(())
+    }
+
+    async fn exists(&self, _table_name: &str) -> Result<bool> {
+        Ok(false)
+    }
+
+    async fn truncate(&mut self, _table_name: &str) -> Result<()> {
+        Err(TinyEtlError::Configuration(
+            "Singer targets can't be truncated; configure the Singer target to replace its table instead"
+                .to_string(),
+        ))
+    }
+
+    fn supports_append(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal::Decimal;
+
+    #[test]
+    fn test_commands_from_urls() {
+        let url =
+            Url::parse("singer://tap-github?config=tap.json&state=/nonexistent/state.json#issues")
+                .unwrap();
+        let command = SingerCommand::parse(&url, &settings(&url, &HashMap::new())).unwrap();
+        assert_eq!(command.program, "tap-github");
+        // A state file that doesn't exist yet is not passed
+        assert_eq!(command.args(), vec!["--config", "tap.json"]);
+
+        let url = Url::parse("singer:///opt/taps/bin/tap-csv").unwrap();
+        let options = HashMap::from([("catalog".to_string(), "catalog.json".to_string())]);
+        let command = SingerCommand::parse(&url, &settings(&url, &options)).unwrap();
+        assert_eq!(command.program, "/opt/taps/bin/tap-csv");
+        assert_eq!(command.args(), vec!["--catalog", "catalog.json"]);
+
+        let url = Url::parse("singer://-#issues").unwrap();
+        assert_eq!(SingerCommand::parse(&url, &HashMap::new()), None);
+        assert!(SingerTarget::new(&Url::parse("singer://-").unwrap(), &HashMap::new()).is_err());
+    }
+
+    #[test]
+    fn test_schema_and_record_messages() {
+        let schema = message_schema(&json!({
+            "type": "SCHEMA",
+            "stream": "users",
+            "key_properties": ["id"],
+            "schema": {"properties": {
+                "id": {"type": "integer"},
+                "balance": {"type": ["null", "number"]},
+                "joined": {"anyOf": [{"type": "string", "format": "date-time"}, {"type": "null"}]},
+                "name": {"type": ["string", "null"]},
+                "tags": {"type": "array", "items": {"type": "string"}}
+            }}
+        }));
+        let column = |name: &str| schema.columns.iter().find(|c| c.name == name).unwrap();
+        assert_eq!(column("id").data_type, DataType::Integer);
+        assert!(!column("id").nullable);
+        assert_eq!(column("balance").data_type, DataType::Decimal);
+        assert_eq!(column("joined").data_type, DataType::DateTime);
+        assert!(column("joined").nullable);
+        assert_eq!(column("tags").data_type, DataType::Json);
+        assert_eq!(schema.primary_key_candidate.as_deref(), Some("id"));
+
+        let row = record_row(
+            &schema,
+            &json!({"id": 7, "balance": 0.1, "joined": "2024-05-01T12:00:00Z", "name": 42, "tags": ["a"]}),
+            Coercion::default(),
+        )
+        .unwrap();
+        assert_eq!(row["id"], Value::Integer(7));
+        assert_eq!(row["balance"], Value::Decimal("0.1".parse().unwrap()));
+        assert!(matches!(row["joined"], Value::Date(_)));
+        assert_eq!(row["name"], Value::String("42".to_string()));
+        assert_eq!(row["tags"], Value::Json(json!(["a"])));
+
+        let url = Url::parse("singer://-#users").unwrap();
+        let mut target = SingerTarget::new(
+            &url,
+            &HashMap::from([("key_properties".to_string(), "id".to_string())]),
+        )
+        .unwrap();
+        let message = target.schema_message(&schema);
+        assert_eq!(message["key_properties"], json!(["id"]));
+        assert_eq!(message["schema"]["properties"]["id"]["type"], "integer");
+        target.schema = Some(schema);
+        let record = target.record_message(&row);
+        assert_eq!(record["stream"], "users");
+        assert_eq!(record["record"]["id"], 7);
+    }
+
+    #[test]
+    fn test_source_selects_one_stream() {
+        let url = Url::parse("singer://-#orders").unwrap();
+        let mut source = SingerSource::new(&url, &HashMap::new()).unwrap();
+        let messages = [
+            json!({"type": "SCHEMA", "stream": "users", "schema": {"properties": {"id": {"type": "integer"}}}, "key_properties": []}),
+            json!({"type": "RECORD", "stream": "users", "record": {"id": 1}}),
+            json!({"type": "SCHEMA", "stream": "orders", "schema": {"properties": {"total": {"type": "number"}}}, "key_properties": []}),
+            json!({"type": "RECORD", "stream": "orders", "record": {"total": 9.5}}),
+            json!({"type": "STATE", "value": {"bookmarks": {"orders": "2024-05-01"}}}),
+        ];
+        for message in messages {
+            source.handle(message).unwrap();
+        }
+        assert_eq!(source.rows.len(), 1);
+        assert_eq!(source.rows[0]["total"], Value::Decimal(Decimal::new(95, 1)));
+        assert_eq!(
+            source.skipped_streams,
+            BTreeSet::from(["users".to_string()])
+        );
+        assert_eq!(
+            source.state,
+            Some(json!({"bookmarks": {"orders": "2024-05-01"}}))
+        );
+
+        let err = source
+            .handle(json!({"type": "RECORD", "stream": "orders"}))
+            .unwrap_err();
+        assert!(err.to_string().contains("no record object"));
+    }
+}
@@ -1,17 +1,18 @@
 use crate::{
-    connectors::{create_source, Source, Target},
+    connectors::{compressed, create_source, Source, Target},
     protocols::Protocol,
+    staging::staging,
     Result, TinyEtlError,
 };
 use async_trait::async_trait;
 use std::collections::HashMap;
+use std::path::PathBuf;
 use std::process::Command;
-use tempfile::NamedTempFile;
 use tracing::info;
 use url::Url;
 
 /// SSH protocol for downloading files via SCP/SFTP.
-/// Uses system SSH client for file transfers to temporary locations.
+/// Uses system SSH client for file transfers to the staging directory.
 pub struct SshProtocol;
 
 impl Default for SshProtocol {
@@ -25,8 +26,8 @@ impl SshProtocol {
         Self
     }
 
-    /// Download a file via SCP to a temporary file with progress
-    async fn download_via_scp(&self, url: &Url) -> Result<NamedTempFile> {
+    /// Download a file via SCP to the staging directory
+    async fn download_via_scp(&self, url: &Url) -> Result<PathBuf> {
         // Parse SSH URL: ssh://user@host:port/path/to/file
         let host = url.host_str().ok_or_else(|| {
             TinyEtlError::Configuration("SSH URL must specify a host".to_string())
@@ -49,18 +50,13 @@ impl SshProtocol {
             ));
         }
 
-        // Create temporary file with appropriate extension
-        let extension = self.extract_extension_from_path(remote_path);
-        let temp_file = if let Some(ext) = extension {
-            tempfile::Builder::new()
-                .suffix(&format!(".{}", ext))
-                .tempfile()
-                .map_err(TinyEtlError::Io)?
-        } else {
-            tempfile::NamedTempFile::new().map_err(TinyEtlError::Io)?
-        };
-
-        let temp_path = temp_file.path().to_string_lossy().to_string();
+        // Download to a staging file with the appropriate extension
+        let extension = compressed::staged_extension(
+            remote_path,
+            self.extract_extension_from_path(remote_path),
+        );
+        let staging = staging()?;
+        let temp_path = staging.temp_path(extension.as_deref());
 
         // Build SCP command: scp -P port user@host:remote_path local_path
         let scp_source = format!("{}@{}:{}", username, host, remote_path);
@@ -92,7 +88,7 @@ impl SshProtocol {
 
         info!("SSH download completed");
 
-        Ok(temp_file)
+        staging.adopt(&temp_path, extension.as_deref())
     }
 
     /// Upload a file via SCP (for target operations)
@@ -168,7 +164,7 @@ impl SshProtocol {
 #[async_trait]
 impl Protocol for SshProtocol {
     async fn create_source(
-        &self, 
+        &self,
         url: &Url,
         _options: &HashMap<String, String>,
     ) -> Result<Box<dyn Source>> {
@@ -176,18 +172,14 @@ impl Protocol for SshProtocol {
         // - identity_file: path to SSH key
         // - known_hosts_file: path to known_hosts
         // - ssh_options: additional SSH options
-        // Download the file via SCP to a temporary location
-        let temp_file = self.download_via_scp(url).await?;
-        let temp_path = temp_file.path().to_string_lossy().to_string();
-
-        // Create source using the temporary file path
-        // Note: Similar limitation as HTTP - the temp file lifetime management
-        // could be improved
-        create_source(&temp_path)
+        // Download the file via SCP to the staging directory, which is
+        // cleaned up once the run succeeds
+        let path = self.download_via_scp(url).await?;
+        create_source(&path.to_string_lossy())
     }
 
     async fn create_target(
-        &self, 
+        &self,
         _url: &Url,
         _options: &HashMap<String, String>,
     ) -> Result<Box<dyn Target>> {
@@ -0,0 +1,630 @@
+use async_trait::async_trait;
+use base64::Engine;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use rust_decimal::Decimal;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::time::Duration;
+use tracing::info;
+use url::Url;
+
+use crate::{
+    connectors::{
+        connection_url::percent_decode,
+        sql_ident::{quote_table, QuoteStyle},
+        Source, Target,
+    },
+    protocols::Protocol,
+    safety::display_target,
+    schema::{Column, DataType, Row, Schema, Value},
+    strict::Coercion,
+    Result, TinyEtlError,
+};
+
+const DEFAULT_PORT: u16 = 8080;
+/// Attempts at a request the coordinator answers with 502, 503 or 504,
+/// which the client protocol says to retry
+const REQUEST_ATTEMPTS: u32 = 5;
+const RETRY_DELAY: Duration = Duration::from_millis(250);
+
+/// Trino and Presto protocol: sources run a query on the coordinator
+/// (`trino://user@host:8080/catalog/schema#table`) and page through its
+/// result over the HTTP client protocol. There is no Trino target.
+pub struct TrinoProtocol;
+
+impl Default for TrinoProtocol {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TrinoProtocol {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+#[async_trait]
+impl Protocol for TrinoProtocol {
+    async fn create_source(
+        &self,
+        url: &Url,
+        options: &HashMap<String, String>,
+    ) -> Result<Box<dyn Source>> {
+        Ok(Box::new(TrinoSource::new(url, options)?))
+    }
+
+    async fn create_target(
+        &self,
+        _url: &Url,
+        _options: &HashMap<String, String>,
+    ) -> Result<Box<dyn Target>> {
+        Err(TinyEtlError::Configuration(
+            "Trino and Presto can only be used as sources: write to the tables they query instead"
+                .to_string(),
+        ))
+    }
+
+    fn validate_url(&self, url: &Url) -> Result<()> {
+        if url.scheme() != "trino" && url.scheme() != "presto" {
+            return Err(TinyEtlError::Configuration(format!(
+                "Trino protocol requires trino:// or presto:// scheme, got: {}",
+                url.scheme()
+            )));
+        }
+
+        if url.host().is_none() {
+            return Err(TinyEtlError::Configuration(
+                "Trino protocol requires a valid host".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn name(&self) -> &'static str {
+        "trino"
+    }
+}
+
+/// TinyETL type of a Trino column type, e.g. `decimal(12,2)` or
+/// `timestamp(3) with time zone`. Athena, which runs Trino, uses the same
+/// names.
+pub(crate) fn data_type(type_name: &str) -> DataType {
+    let base = type_name
+        .split('(')
+        .next()
+        .unwrap_or(type_name)
+        .trim()
+        .to_lowercase();
+    match base.as_str() {
+        "boolean" => DataType::Boolean,
+        "tinyint" | "smallint" | "integer" | "int" | "bigint" => DataType::Integer,
+        "real" | "float" | "double" | "decimal" => DataType::Decimal,
+        "date" => DataType::Date,
+        timestamp if timestamp.starts_with("timestamp") => DataType::DateTime,
+        "json" | "array" | "map" | "row" | "struct" => DataType::Json,
+        "varbinary" => DataType::Binary,
+        // varchar, char, time, uuid, interval, ipaddress...
+        _ => DataType::String,
+    }
+}
+
+/// Timestamp in Trino's text form, `2024-05-01 12:30:00.123` with an
+/// optional `UTC` or `+02:00` zone; other named zones aren't read
+fn parse_timestamp(text: &str) -> Option<DateTime<Utc>> {
+    let text = text.trim();
+    let local = text
+        .strip_suffix(" UTC")
+        .or_else(|| text.strip_suffix(" Z"))
+        .unwrap_or(text);
+    if let Ok(local) = NaiveDateTime::parse_from_str(local, "%Y-%m-%d %H:%M:%S%.f") {
+        return Some(Utc.from_utc_datetime(&local));
+    }
+    DateTime::parse_from_str(text, "%Y-%m-%d %H:%M:%S%.f %:z")
+        .ok()
+        .map(|dt| dt.with_timezone(&Utc))
+}
+
+/// Value of a Trino column from its text form. Athena returns every value
+/// as text; the Trino client protocol does for decimals, dates and
+/// timestamps.
+pub(crate) fn text_value(
+    column: &str,
+    type_name: &str,
+    text: &str,
+    coercion: Coercion,
+) -> Result<Value> {
+    let unreadable =
+        |problem: &str| coercion.lossy(column, text, problem, Value::String(text.to_string()));
+    match data_type(type_name) {
+        DataType::Boolean => match text {
+            "true" => Ok(Value::Boolean(true)),
+            "false" => Ok(Value::Boolean(false)),
+            _ => unreadable("is not a boolean"),
+        },
+        DataType::Integer => match text.parse::<i64>() {
+            Ok(i) => Ok(Value::Integer(i)),
+            Err(_) => unreadable("is not an integer"),
+        },
+        DataType::Decimal => {
+            match Decimal::from_str(text).or_else(|_| Decimal::from_scientific(text)) {
+                Ok(d) => Ok(Value::Decimal(d)),
+                Err(_) => match text.parse::<f64>() {
+                    Ok(f) => coercion.decimal_from_f64(f, column),
+                    Err(_) => unreadable("is not a number"),
+                },
+            }
+        }
+        DataType::Date => match NaiveDate::parse_from_str(text, "%Y-%m-%d") {
+            Ok(date) => Ok(Value::Date(
+                Utc.from_utc_datetime(&date.and_hms_opt(0, 0, 0).unwrap_or_default()),
+            )),
+            Err(_) => unreadable("is not a date"),
+        },
+        DataType::DateTime => match parse_timestamp(text) {
+            Some(timestamp) => Ok(Value::Date(timestamp)),
+            None => unreadable("is not a timestamp in UTC or with an offset"),
+        },
+        DataType::Json => match serde_json::from_str(text) {
+            Ok(json) => Ok(Value::Json(json)),
+            Err(_) => unreadable("is not valid JSON"),
+        },
+        DataType::Binary => match base64::engine::general_purpose::STANDARD.decode(text) {
+            Ok(bytes) => Ok(Value::Bytes(bytes)),
+            Err(_) => unreadable("is not base64"),
+        },
+        _ => Ok(Value::String(text.to_string())),
+    }
+}
+
+/// Value of a Trino column from the JSON of a result page
+fn json_value(
+    column: &str,
+    type_name: &str,
+    json: &serde_json::Value,
+    coercion: Coercion,
+) -> Result<Value> {
+    match json {
+        serde_json::Value::Null => Ok(Value::Null),
+        serde_json::Value::String(text) => text_value(column, type_name, text, coercion),
+        serde_json::Value::Bool(b) => Ok(Value::Boolean(*b)),
+        serde_json::Value::Number(n) => match n.as_i64() {
+            Some(i) if data_type(type_name) == DataType::Integer => Ok(Value::Integer(i)),
+            _ => match n.as_f64() {
+                Some(f) => coercion.decimal_from_f64(f, column),
+                None => Ok(Value::String(n.to_string())),
+            },
+        },
+        // arrays, maps and rows
+        json => Ok(Value::Json(json.clone())),
+    }
+}
+
+/// Which server a `trino://` or `presto://` URL names, and what it runs
+#[derive(Debug, Clone)]
+struct TrinoServer {
+    url: String,
+    base_url: String,
+    /// `Trino` or `Presto`, the prefix of the protocol headers
+    header_prefix: &'static str,
+    user: String,
+    password: Option<String>,
+    catalog: Option<String>,
+    schema: Option<String>,
+    session: Vec<String>,
+}
+
+impl TrinoServer {
+    fn parse(url: &Url, options: &HashMap<String, String>) -> Result<Self> {
+        let host = url.host_str().ok_or_else(|| {
+            TinyEtlError::Configuration("Trino URL must specify a host".to_string())
+        })?;
+        let https = match options.get("https").map(String::as_str) {
+            None | Some("false") => false,
+            Some("true") => true,
+            Some(other) => {
+                return Err(TinyEtlError::Configuration(format!(
+                    "Invalid https '{}': expected true or false",
+                    other
+                )))
+            }
+        };
+        let mut path = url
+            .path_segments()
+            .into_iter()
+            .flatten()
+            .filter(|segment| !segment.is_empty())
+            .map(percent_decode);
+        let catalog = path.next();
+        let schema = path.next();
+
+        let password = url.password().map(percent_decode);
+        if password.is_some() && !https {
+            // The coordinator only accepts passwords over TLS
+            return Err(TinyEtlError::Configuration(
+                "Trino passwords are only sent over HTTPS; set the https option to true"
+                    .to_string(),
+            ));
+        }
+        let mut session: Vec<String> = options
+            .iter()
+            .filter_map(|(key, value)| {
+                key.strip_prefix("session.")
+                    .map(|name| format!("{}={}", name, value))
+            })
+            .collect();
+        session.sort();
+
+        Ok(Self {
+            url: url.to_string(),
+            base_url: format!(
+                "{}://{}:{}",
+                if https { "https" } else { "http" },
+                host,
+                url.port().unwrap_or(if https { 443 } else { DEFAULT_PORT })
+            ),
+            header_prefix: if url.scheme() == "presto" {
+                "Presto"
+            } else {
+                "Trino"
+            },
+            user: match url.username() {
+                "" => "tinyetl".to_string(),
+                user => percent_decode(user),
+            },
+            password,
+            catalog,
+            schema,
+            session,
+        })
+    }
+
+    fn request(
+        &self,
+        http: &reqwest::Client,
+        method: reqwest::Method,
+        uri: &str,
+    ) -> reqwest::RequestBuilder {
+        let header = |name: &str| format!("X-{}-{}", self.header_prefix, name);
+        let mut request = http
+            .request(method, uri)
+            .header(header("User"), &self.user)
+            .header(header("Source"), "tinyetl");
+        if let Some(password) = &self.password {
+            request = request.basic_auth(&self.user, Some(password));
+        }
+        if let Some(catalog) = &self.catalog {
+            request = request.header(header("Catalog"), catalog);
+        }
+        if let Some(schema) = &self.schema {
+            request = request.header(header("Schema"), schema);
+        }
+        if !self.session.is_empty() {
+            request = request.header(header("Session"), self.session.join(","));
+        }
+        request
+    }
+
+    /// Send a request, retrying while the coordinator is busy, and return
+    /// the result page it answers with
+    async fn page(
+        &self,
+        http: &reqwest::Client,
+        method: reqwest::Method,
+        uri: &str,
+        body: Option<&str>,
+    ) -> Result<serde_json::Value> {
+        let request_error = |e: reqwest::Error| {
+            TinyEtlError::Connection(format!(
+                "Trino request to {} failed: {}",
+                display_target(&self.url),
+                e
+            ))
+        };
+        let mut attempt = 1;
+        loop {
+            let mut request = self.request(http, method.clone(), uri);
+            if let Some(body) = body {
+                request = request.body(body.to_string());
+            }
+            let response = request.send().await.map_err(request_error)?;
+            let status = response.status().as_u16();
+            if matches!(status, 502..=504) && attempt < REQUEST_ATTEMPTS {
+                attempt += 1;
+                tokio::time::sleep(RETRY_DELAY * attempt).await;
+                continue;
+            }
+            let page: serde_json::Value = response
+                .error_for_status()
+                .map_err(request_error)?
+                .json()
+                .await
+                .map_err(request_error)?;
+            if let Some(error) = page.get("error").filter(|error| !error.is_null()) {
+                return Err(TinyEtlError::DataTransfer(format!(
+                    "Trino query failed: {}",
+                    error["message"].as_str().unwrap_or("unknown error")
+                )));
+            }
+            return Ok(page);
+        }
+    }
+}
+
+/// Source running a query on a Trino or Presto coordinator: the `#table`
+/// of the URL, the `query` option or `--query`. Result pages are fetched
+/// as rows are read, so only the rows of the current batch are held.
+pub struct TrinoSource {
+    server: TrinoServer,
+    query: String,
+    http: reqwest::Client,
+    /// Names and Trino types of the result columns
+    columns: Vec<(String, String)>,
+    next_uri: Option<String>,
+    started: bool,
+    /// Rows fetched and not yet handed out, plus those handed out since the
+    /// last time all were; a result can only be read once, so `reset`
+    /// rewinds to the first of them
+    rows: Vec<Row>,
+    position: usize,
+    coercion: Coercion,
+}
+
+impl TrinoSource {
+    fn new(url: &Url, options: &HashMap<String, String>) -> Result<Self> {
+        let server = TrinoServer::parse(url, options)?;
+        let table = url.fragment().map(percent_decode);
+        let query = match (options.get("query"), table) {
+            (Some(query), None) => query.clone(),
+            (None, Some(table)) => {
+                format!(
+                    "SELECT * FROM {}",
+                    quote_table(&table, QuoteStyle::DoubleQuote)
+                )
+            }
+            (Some(_), Some(_)) => {
+                return Err(TinyEtlError::Configuration(
+                    "A Trino source reads either a #table or a query, not both".to_string(),
+                ))
+            }
+            (None, None) => return Err(TinyEtlError::Configuration(
+                "Trino source needs a table (trino://host:8080/catalog/schema#table) or a query"
+                    .to_string(),
+            )),
+        };
+        Ok(Self {
+            server,
+            query,
+            http: reqwest::Client::new(),
+            columns: Vec::new(),
+            next_uri: None,
+            started: false,
+            rows: Vec::new(),
+            position: 0,
+            coercion: Coercion::default(),
+        })
+    }
+
+    /// Source running a custom query, for `--query`
+    pub fn from_query(connection_string: &str, query: &str) -> Result<Self> {
+        let mut url = Url::parse(connection_string)
+            .map_err(|e| TinyEtlError::Configuration(format!("Invalid Trino URL: {}", e)))?;
+        url.set_fragment(None);
+        let options = HashMap::from([("query".to_string(), query.to_string())]);
+        Self::new(&url, &options)
+    }
+
+    /// Take the columns, rows and next page of a result page
+    fn add_page(&mut self, page: serde_json::Value) -> Result<()> {
+        if self.columns.is_empty() {
+            if let Some(columns) = page["columns"].as_array() {
+                self.columns = columns
+                    .iter()
+                    .map(|column| {
+                        (
+                            column["name"].as_str().unwrap_or_default().to_string(),
+                            column["type"].as_str().unwrap_or("varchar").to_string(),
+                        )
+                    })
+                    .collect();
+            }
+        }
+        if let Some(data) = page["data"].as_array() {
+            for values in data {
+                let values = values.as_array().map(Vec::as_slice).unwrap_or_default();
+                let row = self
+                    .columns
+                    .iter()
+                    .zip(values)
+                    .map(|((name, type_name), json)| {
+                        Ok((
+                            name.clone(),
+                            json_value(name, type_name, json, self.coercion)?,
+                        ))
+                    })
+                    .collect::<Result<Row>>()?;
+                self.rows.push(row);
+            }
+        }
+        self.next_uri = page["nextUri"].as_str().map(str::to_string);
+        Ok(())
+    }
+
+    async fn fetch_page(&mut self) -> Result<()> {
+        if let Some(uri) = self.next_uri.take() {
+            let page = self
+                .server
+                .page(&self.http, reqwest::Method::GET, &uri, None)
+                .await?;
+            self.add_page(page)?;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Source for TrinoSource {
+    async fn connect(&mut self) -> Result<()> {
+        if self.started {
+            return Ok(());
+        }
+        let statement = format!("{}/v1/statement", self.server.base_url);
+        let page = self
+            .server
+            .page(
+                &self.http,
+                reqwest::Method::POST,
+                &statement,
+                Some(&self.query),
+            )
+            .await?;
+        info!("Running Trino query {}", page["id"].as_str().unwrap_or("?"));
+        self.started = true;
+        self.add_page(page)?;
+        // The columns are known once the query starts running
+        while self.columns.is_empty() && self.next_uri.is_some() {
+            self.fetch_page().await?;
+        }
+        Ok(())
+    }
+
+    async fn infer_schema(&mut self, _sample_size: usize) -> Result<Schema> {
+        if !self.started {
+            self.connect().await?;
+        }
+        Ok(Schema {
+            columns: self
+                .columns
+                .iter()
+                .map(|(name, type_name)| Column {
+                    name: name.clone(),
+                    data_type: data_type(type_name),
+                    nullable: true,
+                })
+                .collect(),
+            estimated_rows: None,
+            primary_key_candidate: None,
+        })
+    }
+
+    async fn read_batch(&mut self, batch_size: usize) -> Result<Vec<Row>> {
+        if !self.started {
+            self.connect().await?;
+        }
+        // Rows handed out can't be fetched again; keep them only until the
+        // transfer's sampling is over and everything fetched was read
+        if self.position > 0 && self.position == self.rows.len() {
+            self.rows.clear();
+            self.position = 0;
+        }
+        while self.rows.len() - self.position < batch_size && self.next_uri.is_some() {
+            self.fetch_page().await?;
+        }
+        let end = (self.position + batch_size).min(self.rows.len());
+        let batch = self.rows[self.position..end].to_vec();
+        self.position = end;
+        Ok(batch)
+    }
+
+    async fn estimated_row_count(&self) -> Result<Option<usize>> {
+        Ok(None)
+    }
+
+    fn set_coercion(&mut self, coercion: Coercion) {
+        self.coercion = coercion;
+    }
+
+    async fn has_stable_order(&mut self) -> Result<bool> {
+        Ok(false)
+    }
+
+    async fn reset(&mut self) -> Result<()> {
+        self.position = 0;
+        Ok(())
+    }
+
+    fn has_more(&self) -> bool {
+        self.position < self.rows.len() || self.next_uri.is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trino_types() {
+        assert_eq!(data_type("bigint"), DataType::Integer);
+        assert_eq!(data_type("decimal(12,2)"), DataType::Decimal);
+        assert_eq!(data_type("timestamp(3) with time zone"), DataType::DateTime);
+        assert_eq!(data_type("array(varchar)"), DataType::Json);
+        assert_eq!(data_type("varchar(20)"), DataType::String);
+
+        let coercion = Coercion::default();
+        assert_eq!(
+            text_value("amount", "decimal(12,2)", "1234.50", coercion).unwrap(),
+            Value::Decimal(Decimal::new(123450, 2))
+        );
+        assert_eq!(
+            text_value(
+                "at",
+                "timestamp(3) with time zone",
+                "2024-05-01 12:30:00.250 +02:00",
+                coercion
+            )
+            .unwrap(),
+            Value::Date(
+                Utc.with_ymd_and_hms(2024, 5, 1, 10, 30, 0).unwrap()
+                    + chrono::Duration::milliseconds(250)
+            )
+        );
+        assert_eq!(
+            json_value(
+                "tags",
+                "array(varchar)",
+                &serde_json::json!(["a", "b"]),
+                coercion
+            )
+            .unwrap(),
+            Value::Json(serde_json::json!(["a", "b"]))
+        );
+        assert!(text_value(
+            "at",
+            "timestamp(3) with time zone",
+            "2024-05-01 12:30:00.000 Europe/Paris",
+            Coercion::strict()
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_result_pages() {
+        let url = Url::parse("trino://analyst@trino:8080/hive/sales#orders").unwrap();
+        let mut source = TrinoSource::new(&url, &HashMap::new()).unwrap();
+        assert_eq!(source.query, "SELECT * FROM \"orders\"");
+        assert_eq!(source.server.base_url, "http://trino:8080");
+        assert_eq!(source.server.catalog.as_deref(), Some("hive"));
+
+        source
+            .add_page(serde_json::json!({
+                "id": "20240501_000001",
+                "nextUri": "http://trino:8080/v1/statement/executing/20240501_000001/1",
+                "columns": [
+                    {"name": "id", "type": "bigint"},
+                    {"name": "ordered_on", "type": "date"}
+                ],
+                "data": [[1, "2024-05-01"], [2, null]]
+            }))
+            .unwrap();
+        assert!(source.has_more());
+        assert_eq!(source.rows.len(), 2);
+        assert_eq!(source.rows[0]["id"], Value::Integer(1));
+        assert_eq!(source.rows[1]["ordered_on"], Value::Null);
+
+        source
+            .add_page(serde_json::json!({"id": "20240501_000001"}))
+            .unwrap();
+        assert!(source.next_uri.is_none());
+    }
+}
@@ -0,0 +1,183 @@
+use chrono::SecondsFormat;
+use serde_json::Value as Json;
+use sha2::{Digest, Sha256};
+
+use crate::{
+    schema::{BinaryEncoding, Column, DataType, Row, Schema, Value},
+    Result, TinyEtlError,
+};
+
+/// Adds a SHA-256 content hash to every row written and folds the hashes
+/// into a Merkle root for the run, so an export can later be checked for
+/// changed, added, removed or reordered rows.
+///
+/// A row's hash is the SHA-256 of its values in output column order as a
+/// compact JSON array: decimals as normalized strings (`"12.5"`), datetimes
+/// as RFC 3339 UTC strings, binary values base64-encoded. The root is that
+/// of a Merkle tree over the row hashes in write order, shaped as in
+/// RFC 6962 (the left subtree holds the largest power of two of rows below
+/// the total), each node the SHA-256 of a `0x01` byte followed by its two
+/// children.
+pub struct RowHasher {
+    column: String,
+    /// Columns hashed, in output order
+    columns: Vec<String>,
+    /// Roots of the complete subtrees hashed so far, with their heights,
+    /// largest first
+    subtrees: Vec<(u32, [u8; 32])>,
+}
+
+impl RowHasher {
+    pub fn new(column: &str, schema: &Schema) -> Result<Self> {
+        if schema.columns.iter().any(|c| c.name == column) {
+            return Err(TinyEtlError::Configuration(format!(
+                "Row hash column '{}' is already an output column",
+                column
+            )));
+        }
+        Ok(Self {
+            column: column.to_string(),
+            columns: schema.columns.iter().map(|c| c.name.clone()).collect(),
+            subtrees: Vec::new(),
+        })
+    }
+
+    /// Output schema with the hash column last
+    pub fn transform_schema(&self, schema: &Schema) -> Schema {
+        let mut schema = schema.clone();
+        schema.columns.push(Column {
+            name: self.column.clone(),
+            data_type: DataType::String,
+            nullable: false,
+        });
+        schema
+    }
+
+    /// Add the hash column to rows about to be written
+    pub fn hash_rows(&mut self, rows: &mut [Row]) {
+        for row in rows {
+            let hash = self.row_hash(row);
+            row.insert(self.column.clone(), Value::String(hex::encode(hash)));
+            self.push(hash);
+        }
+    }
+
+    pub fn row_hash(&self, row: &Row) -> [u8; 32] {
+        let values: Vec<Json> = self
+            .columns
+            .iter()
+            .map(|name| row.get(name).map_or(Json::Null, canonical_value))
+            .collect();
+        Sha256::digest(Json::Array(values).to_string().as_bytes()).into()
+    }
+
+    fn push(&mut self, hash: [u8; 32]) {
+        let mut node = (0, hash);
+        while let Some(&(height, left)) = self.subtrees.last() {
+            if height != node.0 {
+                break;
+            }
+            self.subtrees.pop();
+            node = (height + 1, parent(&left, &node.1));
+        }
+        self.subtrees.push(node);
+    }
+
+    /// Hex-encoded Merkle root of the rows hashed so far; the SHA-256 of
+    /// nothing when there are none
+    pub fn root(&self) -> String {
+        let mut subtrees = self.subtrees.iter().rev();
+        let root = match subtrees.next() {
+            Some(&(_, last)) => subtrees.fold(last, |right, (_, left)| parent(left, &right)),
+            None => Sha256::digest(b"").into(),
+        };
+        hex::encode(root)
+    }
+}
+
+fn parent(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update([0x01]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+/// A value as it is hashed, independent of how the target stores it
+fn canonical_value(value: &Value) -> Json {
+    match value {
+        Value::String(s) => Json::String(s.clone()),
+        Value::Integer(i) => Json::from(*i),
+        Value::Decimal(d) => Json::String(d.normalize().to_string()),
+        Value::Boolean(b) => Json::Bool(*b),
+        Value::Date(dt) => Json::String(dt.to_rfc3339_opts(SecondsFormat::AutoSi, true)),
+        Value::Json(json) => json.clone(),
+        Value::Bytes(bytes) => Json::String(BinaryEncoding::Base64.encode(bytes)),
+        Value::Null => Json::Null,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal::Decimal;
+    use std::str::FromStr;
+
+    fn schema() -> Schema {
+        let column = |name: &str| Column {
+            name: name.to_string(),
+            data_type: DataType::String,
+            nullable: true,
+        };
+        Schema {
+            columns: vec![column("id"), column("amount")],
+            estimated_rows: None,
+            primary_key_candidate: None,
+        }
+    }
+
+    fn row(id: i64) -> Row {
+        Row::from([
+            ("id".to_string(), Value::Integer(id)),
+            (
+                "amount".to_string(),
+                Value::Decimal(Decimal::from_str("12.50").unwrap()),
+            ),
+        ])
+    }
+
+    #[test]
+    fn test_row_hash_is_the_sha256_of_the_values() {
+        let mut hasher = RowHasher::new("_row_hash", &schema()).unwrap();
+        let mut rows = vec![row(1)];
+        hasher.hash_rows(&mut rows);
+        let expected = hex::encode(Sha256::digest(br#"[1,"12.5"]"#));
+        assert_eq!(rows[0]["_row_hash"], Value::String(expected));
+        assert_eq!(
+            hasher.transform_schema(&schema()).columns[2].name,
+            "_row_hash"
+        );
+        assert!(RowHasher::new("id", &schema()).is_err());
+    }
+
+    #[test]
+    fn test_root_matches_the_tree_over_all_rows() {
+        let mut hasher = RowHasher::new("_row_hash", &schema()).unwrap();
+        assert_eq!(
+            hasher.root(),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+
+        let mut rows: Vec<Row> = (1..=5).map(row).collect();
+        let leaves: Vec<[u8; 32]> = rows.iter().map(|row| hasher.row_hash(row)).collect();
+        hasher.hash_rows(&mut rows[..2]);
+        hasher.hash_rows(&mut rows[2..]);
+
+        // Five leaves: the first four make the left subtree
+        let left = parent(
+            &parent(&leaves[0], &leaves[1]),
+            &parent(&leaves[2], &leaves[3]),
+        );
+        assert_eq!(hasher.root(), hex::encode(parent(&left, &leaves[4])));
+    }
+}
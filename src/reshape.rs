@@ -0,0 +1,378 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tracing::warn;
+
+use crate::{
+    schema::{Column, DataType, Row, Schema, Value},
+    Result, TinyEtlError,
+};
+
+/// Long-to-wide reshaping: one output column per distinct value of `key`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PivotConfig {
+    /// Column whose values become the new column names
+    pub key: String,
+    /// Column holding the values spread across the new columns
+    pub value: String,
+    /// Output columns, in order; rows with other keys are dropped
+    pub columns: Vec<String>,
+    /// Columns identifying an output row (default: every other column)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub group_by: Vec<String>,
+}
+
+/// Wide-to-long reshaping (melt): one output row per listed column
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UnpivotConfig {
+    /// Columns folded into key/value pairs
+    pub columns: Vec<String>,
+    /// Name of the output column holding the original column name
+    #[serde(default = "default_unpivot_key")]
+    pub key: String,
+    /// Name of the output column holding the original value
+    #[serde(default = "default_unpivot_value")]
+    pub value: String,
+    /// Skip pairs whose value is null
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub drop_nulls: bool,
+}
+
+fn default_unpivot_key() -> String {
+    "variable".to_string()
+}
+
+fn default_unpivot_value() -> String {
+    "value".to_string()
+}
+
+/// Applies the configured unpivot and pivot steps (in that order) to
+/// transformed rows. Unpivot streams; pivot buffers rows until `finish`.
+pub struct Reshaper {
+    pivot: Option<PivotConfig>,
+    unpivot: Option<UnpivotConfig>,
+    groups: Vec<Row>,
+    group_index: HashMap<String, usize>,
+    dropped_keys: usize,
+}
+
+impl Reshaper {
+    pub fn new(pivot: Option<&PivotConfig>, unpivot: Option<&UnpivotConfig>) -> Result<Self> {
+        if let Some(pivot) = pivot {
+            if pivot.columns.is_empty() {
+                return Err(TinyEtlError::Configuration(
+                    "pivot requires at least one output column".to_string(),
+                ));
+            }
+            if pivot.key == pivot.value {
+                return Err(TinyEtlError::Configuration(
+                    "pivot key and value must be different columns".to_string(),
+                ));
+            }
+        }
+        if let Some(unpivot) = unpivot {
+            if unpivot.columns.is_empty() {
+                return Err(TinyEtlError::Configuration(
+                    "unpivot requires at least one column".to_string(),
+                ));
+            }
+            if unpivot.key == unpivot.value {
+                return Err(TinyEtlError::Configuration(
+                    "unpivot key and value names must be different".to_string(),
+                ));
+            }
+        }
+
+        Ok(Self {
+            pivot: pivot.cloned(),
+            unpivot: unpivot.cloned(),
+            groups: Vec::new(),
+            group_index: HashMap::new(),
+            dropped_keys: 0,
+        })
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.pivot.is_some() || self.unpivot.is_some()
+    }
+
+    /// Whether rows are held back until `finish` is called
+    pub fn is_buffering(&self) -> bool {
+        self.pivot.is_some()
+    }
+
+    /// Schema of the reshaped rows
+    pub fn transform_schema(&self, schema: &Schema) -> Schema {
+        let mut schema = schema.clone();
+
+        if let Some(unpivot) = &self.unpivot {
+            let folded: Vec<&Column> = schema
+                .columns
+                .iter()
+                .filter(|c| unpivot.columns.contains(&c.name))
+                .collect();
+            // Mixed types can only be represented as text
+            let value_type = match folded.first() {
+                Some(first) if folded.iter().all(|c| c.data_type == first.data_type) => {
+                    first.data_type.clone()
+                }
+                _ => DataType::String,
+            };
+
+            schema
+                .columns
+                .retain(|c| !unpivot.columns.contains(&c.name));
+            schema.columns.push(Column {
+                name: unpivot.key.clone(),
+                data_type: DataType::String,
+                nullable: false,
+            });
+            schema.columns.push(Column {
+                name: unpivot.value.clone(),
+                data_type: value_type,
+                nullable: true,
+            });
+        }
+
+        if let Some(pivot) = &self.pivot {
+            let value_type = schema
+                .columns
+                .iter()
+                .find(|c| c.name == pivot.value)
+                .map(|c| c.data_type.clone())
+                .unwrap_or(DataType::String);
+
+            schema.columns.retain(|c| {
+                if pivot.group_by.is_empty() {
+                    c.name != pivot.key && c.name != pivot.value
+                } else {
+                    pivot.group_by.contains(&c.name)
+                }
+            });
+            schema
+                .columns
+                .extend(pivot.columns.iter().map(|name| Column {
+                    name: name.clone(),
+                    data_type: value_type.clone(),
+                    nullable: true,
+                }));
+        }
+
+        schema.estimated_rows = None;
+        schema
+    }
+
+    /// Reshape a batch. With pivot enabled nothing is returned until `finish`.
+    pub fn process_batch(&mut self, rows: Vec<Row>) -> Vec<Row> {
+        let rows = match &self.unpivot {
+            Some(unpivot) => rows
+                .into_iter()
+                .flat_map(|row| Self::unpivot_row(unpivot, row))
+                .collect(),
+            None => rows,
+        };
+
+        if self.pivot.is_none() {
+            return rows;
+        }
+        for row in rows {
+            self.pivot_row(row);
+        }
+        Vec::new()
+    }
+
+    /// Emit the rows buffered by pivot
+    pub fn finish(&mut self) -> Vec<Row> {
+        if self.dropped_keys > 0 {
+            warn!(
+                "pivot dropped {} rows whose key is not a declared column",
+                self.dropped_keys
+            );
+        }
+        self.group_index.clear();
+        std::mem::take(&mut self.groups)
+    }
+
+    fn unpivot_row(unpivot: &UnpivotConfig, mut row: Row) -> Vec<Row> {
+        let pairs: Vec<(String, Value)> = unpivot
+            .columns
+            .iter()
+            .map(|column| (column.clone(), row.remove(column).unwrap_or(Value::Null)))
+            .collect();
+
+        pairs
+            .into_iter()
+            .filter(|(_, value)| !(unpivot.drop_nulls && matches!(value, Value::Null)))
+            .map(|(column, value)| {
+                let mut out = row.clone();
+                out.insert(unpivot.key.clone(), Value::String(column));
+                out.insert(unpivot.value.clone(), value);
+                out
+            })
+            .collect()
+    }
+
+    fn pivot_row(&mut self, mut row: Row) {
+        let Some(pivot) = &self.pivot else {
+            return;
+        };
+
+        let column = match row.remove(&pivot.key) {
+            Some(Value::Null) | None => None,
+            Some(value) => value.to_string_for_arrow(),
+        };
+        let value = row.remove(&pivot.value).unwrap_or(Value::Null);
+        let column = match column {
+            Some(column) if pivot.columns.contains(&column) => column,
+            _ => {
+                self.dropped_keys += 1;
+                return;
+            }
+        };
+
+        if !pivot.group_by.is_empty() {
+            row.retain(|name, _| pivot.group_by.contains(name));
+        }
+        let group_key = Self::group_key(&row);
+
+        let index = match self.group_index.get(&group_key) {
+            Some(&index) => index,
+            None => {
+                for name in &pivot.columns {
+                    row.insert(name.clone(), Value::Null);
+                }
+                self.groups.push(row);
+                self.group_index.insert(group_key, self.groups.len() - 1);
+                self.groups.len() - 1
+            }
+        };
+        // Later values for the same group and key win
+        self.groups[index].insert(column, value);
+    }
+
+    fn group_key(row: &Row) -> String {
+        let mut names: Vec<&String> = row.keys().collect();
+        names.sort();
+        names
+            .into_iter()
+            .map(|name| match row.get(name) {
+                Some(Value::Null) | None => format!("{}=\u{0}", name),
+                Some(value) => format!(
+                    "{}={}",
+                    name,
+                    value.to_string_for_arrow().unwrap_or_default()
+                ),
+            })
+            .collect::<Vec<_>>()
+            .join("\u{1f}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sale(region: &str, month: &str, amount: i64) -> Row {
+        let mut row = Row::new();
+        row.insert("region".to_string(), Value::String(region.to_string()));
+        row.insert("month".to_string(), Value::String(month.to_string()));
+        row.insert("amount".to_string(), Value::Integer(amount));
+        row
+    }
+
+    fn pivot_config() -> PivotConfig {
+        PivotConfig {
+            key: "month".to_string(),
+            value: "amount".to_string(),
+            columns: vec!["jan".to_string(), "feb".to_string()],
+            group_by: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_pivot_groups_rows() {
+        let mut reshaper = Reshaper::new(Some(&pivot_config()), None).unwrap();
+        let emitted = reshaper.process_batch(vec![
+            sale("north", "jan", 10),
+            sale("south", "jan", 5),
+            sale("north", "feb", 12),
+            sale("north", "mar", 99),
+        ]);
+        assert!(emitted.is_empty());
+
+        let rows = reshaper.finish();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(
+            rows[0].get("region"),
+            Some(&Value::String("north".to_string()))
+        );
+        assert_eq!(rows[0].get("jan"), Some(&Value::Integer(10)));
+        assert_eq!(rows[0].get("feb"), Some(&Value::Integer(12)));
+        assert_eq!(rows[1].get("feb"), Some(&Value::Null));
+        assert!(!rows[0].contains_key("month"));
+        assert!(!rows[0].contains_key("mar"));
+    }
+
+    #[test]
+    fn test_unpivot_melts_columns() {
+        let unpivot = UnpivotConfig {
+            columns: vec!["jan".to_string(), "feb".to_string()],
+            key: "month".to_string(),
+            value: "amount".to_string(),
+            drop_nulls: true,
+        };
+        let mut reshaper = Reshaper::new(None, Some(&unpivot)).unwrap();
+
+        let mut row = Row::new();
+        row.insert("region".to_string(), Value::String("north".to_string()));
+        row.insert("jan".to_string(), Value::Integer(10));
+        row.insert("feb".to_string(), Value::Null);
+
+        let rows = reshaper.process_batch(vec![row]);
+        assert_eq!(rows.len(), 1);
+        assert_eq!(
+            rows[0].get("month"),
+            Some(&Value::String("jan".to_string()))
+        );
+        assert_eq!(rows[0].get("amount"), Some(&Value::Integer(10)));
+        assert!(!rows[0].contains_key("jan"));
+    }
+
+    #[test]
+    fn test_pivot_schema() {
+        let schema = Schema {
+            columns: vec![
+                Column {
+                    name: "region".to_string(),
+                    data_type: DataType::String,
+                    nullable: false,
+                },
+                Column {
+                    name: "month".to_string(),
+                    data_type: DataType::String,
+                    nullable: false,
+                },
+                Column {
+                    name: "amount".to_string(),
+                    data_type: DataType::Decimal,
+                    nullable: false,
+                },
+            ],
+            estimated_rows: Some(24),
+            primary_key_candidate: None,
+        };
+
+        let reshaper = Reshaper::new(Some(&pivot_config()), None).unwrap();
+        let pivoted = reshaper.transform_schema(&schema);
+        let names: Vec<&str> = pivoted.columns.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["region", "jan", "feb"]);
+        assert_eq!(pivoted.columns[1].data_type, DataType::Decimal);
+        assert!(pivoted.columns[1].nullable);
+    }
+
+    #[test]
+    fn test_invalid_config_is_rejected() {
+        let mut config = pivot_config();
+        config.columns.clear();
+        assert!(Reshaper::new(Some(&config), None).is_err());
+    }
+}
@@ -0,0 +1,209 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::str::FromStr;
+use tracing::info;
+
+use crate::{
+    config::IfExists,
+    connectors::{create_target_from_url_with_options, Target},
+    output_names::{OutputNames, RenamedTarget},
+    schema::{Row, Schema},
+    transfer::TransferEngine,
+    transformer::{TransformConfig, TransformOptions, Transformer},
+    Result, TinyEtlError,
+};
+
+/// One routing rule: rows matching `when` are written to `target`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RouteConfig {
+    /// Target connection string for matching rows
+    pub target: String,
+    /// Lua expression over `row`; a route without one matches every row ("otherwise")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub when: Option<String>,
+    /// Connector-specific options for the route target
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub options: HashMap<String, String>,
+}
+
+/// Parses the CLI forms `<target> if <lua expression>` and `<target> otherwise`
+impl FromStr for RouteConfig {
+    type Err = String;
+
+    fn from_str(spec: &str) -> std::result::Result<Self, Self::Err> {
+        let (target, when) = match spec.split_once(" if ") {
+            Some((target, when)) => (target.trim(), Some(when.trim().to_string())),
+            None => {
+                let spec = spec.trim();
+                (spec.strip_suffix(" otherwise").unwrap_or(spec).trim(), None)
+            }
+        };
+
+        if target.is_empty() {
+            return Err("route needs a target".to_string());
+        }
+        if when.as_deref() == Some("") {
+            return Err(format!("route to '{}' has an empty condition", target));
+        }
+
+        Ok(RouteConfig {
+            target: target.to_string(),
+            when,
+            options: HashMap::new(),
+        })
+    }
+}
+
+/// Rows written through one route
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RouteStats {
+    pub target: String,
+    pub rows: usize,
+}
+
+struct Route {
+    config: RouteConfig,
+    target: Box<dyn Target>,
+    table_name: String,
+    rows: usize,
+}
+
+/// Splits rows across route targets. The first matching route wins; rows
+/// matching no route are handed back for the primary target.
+pub struct Router {
+    routes: Vec<Route>,
+    predicates: Transformer,
+}
+
+impl Router {
+    /// Connect and prepare every route target with the given schema
+    pub async fn connect(
+        routes: &[RouteConfig],
+        schema: &Schema,
+        options: &TransformOptions,
+        if_exists: IfExists,
+        output_names: Option<&OutputNames>,
+    ) -> Result<Self> {
+        let predicates = Transformer::with_options(&TransformConfig::None, options)?;
+        let mut connected = Vec::with_capacity(routes.len());
+
+        for (index, route) in routes.iter().enumerate() {
+            if let Some(when) = &route.when {
+                predicates.load_predicate(&index.to_string(), when)?;
+            }
+
+            let mut target =
+                create_target_from_url_with_options(&route.target, &route.options).await?;
+            if let Some(names) = output_names {
+                target = Box::new(RenamedTarget::new(target, names.clone()));
+            }
+            target.connect().await?;
+            let table_name = TransferEngine::extract_table_name(&route.target);
+            let table_exists = target.exists(&table_name).await?;
+            TransferEngine::prepare_target(
+                target.as_mut(),
+                &table_name,
+                schema,
+                table_exists,
+                if_exists,
+            )
+            .await?;
+            info!("→ Route {}: {}", index + 1, route.target);
+
+            connected.push(Route {
+                config: route.clone(),
+                target,
+                table_name,
+                rows: 0,
+            });
+        }
+
+        Ok(Self {
+            routes: connected,
+            predicates,
+        })
+    }
+
+    /// Write matching rows to their route targets and return the rest
+    pub async fn route_batch(&mut self, rows: Vec<Row>) -> Result<Vec<Row>> {
+        let mut routed: Vec<Vec<Row>> = vec![Vec::new(); self.routes.len()];
+        let mut unrouted = Vec::new();
+
+        for row in rows {
+            match self.matching_route(&row)? {
+                Some(index) => routed[index].push(row),
+                None => unrouted.push(row),
+            }
+        }
+
+        for (route, rows) in self.routes.iter_mut().zip(routed) {
+            if rows.is_empty() {
+                continue;
+            }
+            route.rows += route.target.write_batch(&rows).await.map_err(|e| {
+                TinyEtlError::DataTransfer(format!(
+                    "Failed to write to route target {} ({}): {}",
+                    route.config.target, route.table_name, e
+                ))
+            })?;
+        }
+
+        Ok(unrouted)
+    }
+
+    pub async fn finalize(&mut self) -> Result<()> {
+        for route in &mut self.routes {
+            route.target.finalize().await?;
+        }
+        Ok(())
+    }
+
+    /// Refresh planner statistics of every route target
+    pub async fn analyze(&mut self) -> Result<()> {
+        for route in &mut self.routes {
+            route.target.analyze(&route.table_name).await?;
+        }
+        Ok(())
+    }
+
+    pub fn stats(&self) -> Vec<RouteStats> {
+        self.routes
+            .iter()
+            .map(|route| RouteStats {
+                target: route.config.target.clone(),
+                rows: route.rows,
+            })
+            .collect()
+    }
+
+    fn matching_route(&self, row: &Row) -> Result<Option<usize>> {
+        for (index, route) in self.routes.iter().enumerate() {
+            let matches = match &route.config.when {
+                Some(_) => self.predicates.eval_predicate(&index.to_string(), row)?,
+                None => true,
+            };
+            if matches {
+                return Ok(Some(index));
+            }
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_route_spec() {
+        let route: RouteConfig = "us.csv if row.country == 'US'".parse().unwrap();
+        assert_eq!(route.target, "us.csv");
+        assert_eq!(route.when.as_deref(), Some("row.country == 'US'"));
+
+        let route: RouteConfig = "rest.csv otherwise".parse().unwrap();
+        assert_eq!(route.target, "rest.csv");
+        assert!(route.when.is_none());
+
+        assert!("us.csv if ".parse::<RouteConfig>().is_err());
+    }
+}
@@ -0,0 +1,220 @@
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use crate::{
+    schema::{Column, DataType, Row, Schema, Value},
+    Result, TinyEtlError,
+};
+
+/// Settings for the built-in `row_number()` step
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RowNumberConfig {
+    /// Output column receiving the sequence number
+    pub column: String,
+    /// Columns whose values restart the numbering
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub partition_by: Vec<String>,
+    /// Column the input is sorted by within each partition
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub order_by: Option<String>,
+}
+
+/// Parses the CLI form `rn [partition-by a,b] [order-by ts]`
+impl FromStr for RowNumberConfig {
+    type Err = String;
+
+    fn from_str(spec: &str) -> std::result::Result<Self, Self::Err> {
+        let mut words = spec.split_whitespace();
+        let column = words
+            .next()
+            .ok_or_else(|| "row number spec needs an output column name".to_string())?;
+
+        let mut config = RowNumberConfig {
+            column: column.to_string(),
+            partition_by: Vec::new(),
+            order_by: None,
+        };
+
+        while let Some(word) = words.next() {
+            let value = words
+                .next()
+                .ok_or_else(|| format!("'{}' needs a column name", word))?;
+            match word {
+                "partition-by" => {
+                    config.partition_by = value
+                        .split(',')
+                        .filter(|c| !c.is_empty())
+                        .map(String::from)
+                        .collect()
+                }
+                "order-by" => config.order_by = Some(value.to_string()),
+                other => {
+                    return Err(format!(
+                        "unknown row number clause '{}' (expected partition-by or order-by)",
+                        other
+                    ))
+                }
+            }
+        }
+
+        Ok(config)
+    }
+}
+
+/// Assigns sequence numbers as rows stream through, keeping one counter per
+/// partition. With `order_by` the input must already be sorted within each
+/// partition; out-of-order rows are rejected rather than silently misnumbered.
+pub struct RowNumberer {
+    config: RowNumberConfig,
+    counters: HashMap<String, (i64, Value)>,
+}
+
+impl RowNumberer {
+    pub fn new(config: &RowNumberConfig) -> Result<Self> {
+        if config.column.is_empty() {
+            return Err(TinyEtlError::Configuration(
+                "row number column name cannot be empty".to_string(),
+            ));
+        }
+        Ok(Self {
+            config: config.clone(),
+            counters: HashMap::new(),
+        })
+    }
+
+    /// Add the row number column to a schema
+    pub fn transform_schema(&self, schema: &Schema) -> Schema {
+        let mut schema = schema.clone();
+        schema.columns.retain(|c| c.name != self.config.column);
+        schema.columns.push(Column {
+            name: self.config.column.clone(),
+            data_type: DataType::Integer,
+            nullable: false,
+        });
+        schema
+    }
+
+    /// Number a batch of rows in place
+    pub fn process_batch(&mut self, rows: &mut [Row]) -> Result<()> {
+        for row in rows.iter_mut() {
+            let partition = self
+                .config
+                .partition_by
+                .iter()
+                .map(|column| match row.get(column) {
+                    Some(Value::Null) | None => "\u{0}".to_string(),
+                    Some(value) => value.to_string_for_arrow().unwrap_or_default(),
+                })
+                .collect::<Vec<_>>()
+                .join("\u{1f}");
+            let order_value = match &self.config.order_by {
+                Some(column) => row.get(column).cloned().unwrap_or(Value::Null),
+                None => Value::Null,
+            };
+
+            let number = match self.counters.get_mut(&partition) {
+                Some((count, last)) => {
+                    if let Some(order_by) = &self.config.order_by {
+                        if compare_values(&order_value, last) == Some(Ordering::Less) {
+                            return Err(TinyEtlError::DataValidation(format!(
+                                "row_number requires input sorted by '{}' within each partition; \
+                                 got {:?} after {:?}",
+                                order_by, order_value, last
+                            )));
+                        }
+                    }
+                    *count += 1;
+                    *last = order_value;
+                    *count
+                }
+                None => {
+                    self.counters.insert(partition, (1, order_value));
+                    1
+                }
+            };
+
+            row.insert(self.config.column.clone(), Value::Integer(number));
+        }
+        Ok(())
+    }
+}
+
+/// Ordering between two values of compatible types; `None` when the values
+/// cannot be compared (nulls or mismatched types)
+pub(crate) fn compare_values(a: &Value, b: &Value) -> Option<Ordering> {
+    match (a, b) {
+        (Value::Integer(a), Value::Integer(b)) => Some(a.cmp(b)),
+        (Value::Decimal(a), Value::Decimal(b)) => Some(a.cmp(b)),
+        (Value::Integer(a), Value::Decimal(b)) => Some(Decimal::from(*a).cmp(b)),
+        (Value::Decimal(a), Value::Integer(b)) => Some(a.cmp(&Decimal::from(*b))),
+        (Value::Date(a), Value::Date(b)) => Some(a.cmp(b)),
+        (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+        (Value::Boolean(a), Value::Boolean(b)) => Some(a.cmp(b)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(customer: &str, ts: i64) -> Row {
+        let mut row = Row::new();
+        row.insert(
+            "customer_id".to_string(),
+            Value::String(customer.to_string()),
+        );
+        row.insert("ts".to_string(), Value::Integer(ts));
+        row
+    }
+
+    #[test]
+    fn test_parse_spec() {
+        let config: RowNumberConfig = "rn partition-by customer_id,region order-by ts"
+            .parse()
+            .unwrap();
+        assert_eq!(config.column, "rn");
+        assert_eq!(config.partition_by, vec!["customer_id", "region"]);
+        assert_eq!(config.order_by.as_deref(), Some("ts"));
+
+        let config: RowNumberConfig = "seq".parse().unwrap();
+        assert!(config.partition_by.is_empty());
+        assert!(config.order_by.is_none());
+
+        assert!("rn sort-by ts".parse::<RowNumberConfig>().is_err());
+        assert!("rn partition-by".parse::<RowNumberConfig>().is_err());
+    }
+
+    #[test]
+    fn test_numbers_restart_per_partition_across_batches() {
+        let config: RowNumberConfig = "rn partition-by customer_id order-by ts".parse().unwrap();
+        let mut numberer = RowNumberer::new(&config).unwrap();
+
+        let mut first = vec![event("a", 1), event("b", 1), event("a", 2)];
+        let mut second = vec![event("a", 3)];
+        numberer.process_batch(&mut first).unwrap();
+        numberer.process_batch(&mut second).unwrap();
+
+        let numbers: Vec<_> = first.iter().map(|r| r.get("rn").cloned()).collect();
+        assert_eq!(
+            numbers,
+            vec![
+                Some(Value::Integer(1)),
+                Some(Value::Integer(1)),
+                Some(Value::Integer(2))
+            ]
+        );
+        assert_eq!(second[0].get("rn"), Some(&Value::Integer(3)));
+    }
+
+    #[test]
+    fn test_unsorted_input_is_rejected() {
+        let config: RowNumberConfig = "rn partition-by customer_id order-by ts".parse().unwrap();
+        let mut numberer = RowNumberer::new(&config).unwrap();
+        let mut rows = vec![event("a", 2), event("b", 1), event("a", 1)];
+        assert!(numberer.process_batch(&mut rows).is_err());
+    }
+}
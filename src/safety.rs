@@ -0,0 +1,405 @@
+use regex::RegexBuilder;
+use tracing::warn;
+use url::Url;
+
+use crate::{
+    config::{Config, IfExists},
+    connectors::{
+        connection_url::split_table,
+        sql_ident::{quote_style_for, QuoteStyle},
+        sql_params::sql_tokens,
+    },
+    redact::{redact, MASK},
+    transfer::TransferEngine,
+    Result, TinyEtlError,
+};
+
+/// Target URLs matching this are treated as production unless `production_pattern` is set
+pub const DEFAULT_PRODUCTION_PATTERN: &str = r"(^|[^a-z0-9])(prod|production|prd)([^a-z0-9]|$)";
+
+/// Statements a read-only query may start with
+const READ_STATEMENTS: &[&str] = &["SELECT", "WITH", "VALUES", "TABLE", "SHOW"];
+
+/// Keywords that suggest a statement writes, locks or changes the database,
+/// even inside a `WITH` or `SELECT` (e.g. data-modifying CTEs, `SELECT ...
+/// INTO`). Only a hint: the read-only session is what refuses the writes.
+const WRITE_KEYWORDS: &[&str] = &[
+    "INSERT", "UPDATE", "DELETE", "MERGE", "UPSERT", "INTO", "CREATE", "ALTER", "DROP", "TRUNCATE",
+    "RENAME", "GRANT", "REVOKE", "CALL", "EXEC", "EXECUTE", "COPY", "LOCK", "VACUUM", "ANALYZE",
+    "REINDEX", "CLUSTER", "REFRESH", "COMMENT",
+];
+
+/// Checks for `--read-only` runs, made before any connector is created
+pub fn check_read_only(config: &Config) -> Result<()> {
+    if !config.read_only {
+        return Ok(());
+    }
+
+    if let Some(query) = &config.query {
+        if !has_read_only_session(&config.source) {
+            return Err(TinyEtlError::Configuration(format!(
+                "read-only mode: --query is only supported for SQLite, PostgreSQL and MySQL sources, which run it in a read-only session, not {}",
+                redact(&config.source)
+            )));
+        }
+        check_read_only_query(query)?;
+    }
+
+    let source = location(&config.source);
+    let targets =
+        std::iter::once(&config.target).chain(config.routes.iter().map(|route| &route.target));
+    for target in targets {
+        if location(target) == source {
+            return Err(TinyEtlError::Configuration(format!(
+                "read-only mode: target '{}' is in the source database",
+                target
+            )));
+        }
+    }
+
+    let pattern = config
+        .production_pattern
+        .as_deref()
+        .unwrap_or(DEFAULT_PRODUCTION_PATTERN);
+    let production = RegexBuilder::new(pattern)
+        .case_insensitive(true)
+        .build()
+        .map_err(|e| {
+            TinyEtlError::Configuration(format!("Invalid production pattern '{}': {}", pattern, e))
+        })?;
+    let targets =
+        std::iter::once(&config.target).chain(config.routes.iter().map(|route| &route.target));
+    for target in targets {
+        if production.is_match(target) && !config.confirm_production {
+            return Err(TinyEtlError::Configuration(format!(
+                "read-only mode: target '{}' looks like production; pass --confirm-production to write to it",
+                target
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Fail runs that would truncate a table listed in `protect`
+pub fn check_protected_tables(config: &Config) -> Result<()> {
+    for (target, table) in truncated_tables(config) {
+        if is_protected(&config.protect, &table) {
+            return Err(TinyEtlError::Configuration(format!(
+                "table '{}' in {} is protected and will not be {}",
+                table,
+                display_target(target),
+                rewrite_verbs(config).1
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Ask before truncating database tables. Skipped with `yes`, when not
+/// running interactively, and for previews and dry runs (which never write).
+pub fn confirm_truncation<F>(config: &Config, interactive: bool, mut ask: F) -> Result<()>
+where
+    F: FnMut(&str) -> bool,
+{
+    if config.yes || !interactive || config.dry_run || config.preview.is_some() {
+        return Ok(());
+    }
+
+    for (target, table) in truncated_tables(config) {
+        let question = format!(
+            "{} table '{}' in {}?",
+            rewrite_verbs(config).0,
+            table,
+            display_target(target)
+        );
+        if !ask(&question) {
+            return Err(TinyEtlError::Configuration(format!(
+                "truncation of '{}' was not confirmed (use --yes to skip this prompt)",
+                table
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Database tables (with their target connection string) emptied or
+/// dropped by this run, or whose rows an SCD2 load updates
+fn truncated_tables(config: &Config) -> Vec<(&str, String)> {
+    if !config.scd2
+        && !matches!(
+            config.if_exists_policy(),
+            IfExists::Truncate | IfExists::Replace
+        )
+    {
+        return Vec::new();
+    }
+    std::iter::once(&config.target)
+        .chain(config.routes.iter().map(|route| &route.target))
+        .filter(|target| is_database(target))
+        .map(|target| (target.as_str(), TransferEngine::extract_table_name(target)))
+        .collect()
+}
+
+/// What the run does to the tables of `truncated_tables`, for the prompt
+/// and as a past participle
+fn rewrite_verbs(config: &Config) -> (&'static str, &'static str) {
+    if config.scd2 {
+        ("Update the SCD2 history of", "updated")
+    } else if config.if_exists_policy() == IfExists::Replace {
+        ("Drop and recreate", "replaced")
+    } else {
+        ("Truncate", "truncated")
+    }
+}
+
+/// `orders` protects `orders` in any schema; `sales.orders` only that one
+fn is_protected(protect: &[String], table: &str) -> bool {
+    let normalize = |name: &str| name.replace(['"', '`', '[', ']'], "").to_lowercase();
+    let table = normalize(table);
+    let unqualified = table.rsplit('.').next().unwrap_or_default();
+    protect
+        .iter()
+        .map(|name| normalize(name.trim()))
+        .any(|name| name == table || (!name.contains('.') && name == unqualified))
+}
+
+pub(crate) fn is_database(connection_string: &str) -> bool {
+    let lower = connection_string.to_lowercase();
+    [
+        "postgres://",
+        "postgresql://",
+        "redshift://",
+        "mysql://",
+        "mssql://",
+        "sqlserver://",
+        "sybase://",
+        "odbc://",
+        "sqlite:",
+        "duckdb:",
+    ]
+    .iter()
+    .any(|prefix| lower.starts_with(prefix))
+        || lower.contains(".db#")
+        || lower.contains(".duckdb#")
+}
+
+/// Connection string with any password masked, for prompts and messages
+pub(crate) fn display_target(target: &str) -> String {
+    match Url::parse(target) {
+        Ok(mut url) if url.password().is_some() => {
+            let _ = url.set_password(Some(MASK));
+            url.to_string()
+        }
+        _ => redact(target),
+    }
+}
+
+/// Sources that open their connections read-only with `--read-only`, so
+/// the database itself refuses writes from a custom query
+fn has_read_only_session(source: &str) -> bool {
+    matches!(
+        quote_style_for(source),
+        Some(QuoteStyle::DoubleQuote | QuoteStyle::Backtick)
+    ) && !source.starts_with("redshift://")
+}
+
+/// A query passes when it is a single statement starting with a read. Write
+/// keywords inside it are only reported, since the session refuses writes.
+fn check_read_only_query(query: &str) -> Result<()> {
+    let mut tokens = sql_tokens(query);
+    while tokens.last().map(String::as_str) == Some(";") {
+        tokens.pop();
+    }
+
+    let first = tokens.first().map(String::as_str).unwrap_or_default();
+    if !READ_STATEMENTS.contains(&first) {
+        return Err(TinyEtlError::Configuration(format!(
+            "read-only mode: query must start with SELECT or WITH, not '{}'",
+            first
+        )));
+    }
+    if tokens.iter().any(|token| token == ";") {
+        return Err(TinyEtlError::Configuration(
+            "read-only mode: query must be a single statement".to_string(),
+        ));
+    }
+    if let Some(keyword) = write_keyword(&tokens) {
+        warn!(
+            "read-only mode: query contains {}; the source will refuse it if it writes",
+            keyword
+        );
+    }
+
+    Ok(())
+}
+
+fn write_keyword(tokens: &[String]) -> Option<&str> {
+    tokens
+        .iter()
+        .map(String::as_str)
+        .find(|token| WRITE_KEYWORDS.contains(token))
+}
+
+/// Database or file a connection string points at, without the `#table` or
+/// `?table=` part
+fn location(connection_string: &str) -> String {
+    let base = split_table(connection_string)
+        .map(|(base, _)| base)
+        .unwrap_or_else(|_| connection_string.to_string());
+    let base = base.trim();
+    let base = base
+        .strip_prefix("file://")
+        .or_else(|| base.strip_prefix("sqlite://"))
+        .or_else(|| base.strip_prefix("sqlite:"))
+        .unwrap_or(base);
+    base.trim_end_matches('/').to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(source: &str, target: &str) -> Config {
+        Config {
+            source: source.to_string(),
+            target: target.to_string(),
+            read_only: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_queries_must_only_read() {
+        assert!(check_read_only_query("SELECT * FROM t WHERE note = 'delete me';").is_ok());
+        assert!(check_read_only_query("WITH x AS (SELECT 1) SELECT * FROM x").is_ok());
+        assert!(check_read_only_query("DELETE FROM t").is_err());
+        assert!(check_read_only_query("SELECT 1; DROP TABLE t").is_err());
+
+        // Write keywords are only a hint; the read-only session refuses writes
+        let hint = |sql: &str| write_keyword(&sql_tokens(sql)).map(str::to_string);
+        assert!(check_read_only_query("SELECT * INTO backup FROM t").is_ok());
+        assert_eq!(hint("SELECT * INTO backup FROM t").unwrap(), "INTO");
+        assert_eq!(
+            hint("WITH gone AS (DELETE FROM t RETURNING *) SELECT * FROM gone").unwrap(),
+            "DELETE"
+        );
+        assert_eq!(hint("SELECT * FROM t WHERE note = 'delete me'"), None);
+    }
+
+    #[test]
+    fn test_queries_need_a_read_only_session() {
+        let mut config = config("postgres://u@db/app", "out.csv");
+        config.query = Some("SELECT * FROM orders".to_string());
+        assert!(check_read_only(&config).is_ok());
+
+        config.source = "sqlite:app.db".to_string();
+        assert!(check_read_only(&config).is_ok());
+
+        for source in ["mssql://u@db/app", "redshift://u@db/app", "bigquery://p/d"] {
+            config.source = source.to_string();
+            assert!(check_read_only(&config).is_err(), "{}", source);
+        }
+    }
+
+    #[test]
+    fn test_target_in_source_database_is_rejected() {
+        let same = config(
+            "postgres://u@db/app#orders",
+            "postgres://u@db/app#orders_copy",
+        );
+        assert!(check_read_only(&same).is_err());
+
+        let other = config("postgres://u@db/app#orders", "products.csv");
+        assert!(check_read_only(&other).is_ok());
+
+        let mut not_read_only = same.clone();
+        not_read_only.read_only = false;
+        assert!(check_read_only(&not_read_only).is_ok());
+    }
+
+    #[test]
+    fn test_production_targets_need_confirmation() {
+        let mut config = config("orders.csv", "postgres://u@prod-db/app#orders");
+        assert!(check_read_only(&config).is_err());
+
+        config.confirm_production = true;
+        assert!(check_read_only(&config).is_ok());
+
+        config.confirm_production = false;
+        config.production_pattern = Some("live".to_string());
+        assert!(check_read_only(&config).is_ok());
+    }
+
+    #[test]
+    fn test_protected_tables_are_never_truncated() {
+        let mut config = Config {
+            source: "orders.csv".to_string(),
+            target: "postgres://u:secret@db/app#sales.orders".to_string(),
+            truncate: true,
+            protect: vec!["orders".to_string()],
+            ..Default::default()
+        };
+        let err = check_protected_tables(&config).unwrap_err().to_string();
+        assert!(err.contains("protected"));
+        assert!(!err.contains("secret"));
+
+        config.protect = vec!["archive.orders".to_string()];
+        assert!(check_protected_tables(&config).is_ok());
+
+        config.protect = vec!["orders".to_string()];
+        config.truncate = false;
+        assert!(check_protected_tables(&config).is_ok());
+
+        config.if_exists = IfExists::Replace;
+        let err = check_protected_tables(&config).unwrap_err().to_string();
+        assert!(err.contains("will not be replaced"));
+
+        config.if_exists = IfExists::Append;
+        config.scd2 = true;
+        let err = check_protected_tables(&config).unwrap_err().to_string();
+        assert!(err.contains("will not be updated"));
+    }
+
+    #[test]
+    fn test_truncation_needs_confirmation_when_interactive() {
+        let mut config = Config {
+            source: "orders.csv".to_string(),
+            target: "app.db#orders".to_string(),
+            truncate: true,
+            ..Default::default()
+        };
+
+        let mut asked = Vec::new();
+        let result = confirm_truncation(&config, true, |question| {
+            asked.push(question.to_string());
+            false
+        });
+        assert!(result.is_err());
+        assert_eq!(asked, vec!["Truncate table 'orders' in app.db#orders?"]);
+
+        config.truncate = false;
+        config.scd2 = true;
+        asked.clear();
+        let result = confirm_truncation(&config, true, |question| {
+            asked.push(question.to_string());
+            false
+        });
+        assert!(result.is_err());
+        assert_eq!(
+            asked,
+            vec!["Update the SCD2 history of table 'orders' in app.db#orders?"]
+        );
+        config.scd2 = false;
+        config.truncate = true;
+
+        assert!(confirm_truncation(&config, false, |_| false).is_ok());
+        config.yes = true;
+        assert!(confirm_truncation(&config, true, |_| false).is_ok());
+
+        // File targets are rewritten by design and never prompt
+        config.yes = false;
+        config.target = "orders.csv".to_string();
+        assert!(confirm_truncation(&config, true, |_| false).is_ok());
+    }
+}
@@ -0,0 +1,237 @@
+use std::collections::HashSet;
+
+use crate::{
+    connectors::Source,
+    safety::display_target,
+    schema::{DataType, Row, Schema, Value},
+    Result,
+};
+
+/// Sample values shown per column
+const MAX_SAMPLES: usize = 3;
+/// Longest sample value shown, in characters
+const MAX_SAMPLE_CHARS: usize = 40;
+
+/// Schema file drafted from a sample of a source, annotated with what the
+/// sample showed so it can be reviewed and then used with `--schema-file`
+#[derive(Debug, Clone)]
+pub struct SchemaScaffold {
+    source: String,
+    sampled_rows: usize,
+    columns: Vec<ScaffoldColumn>,
+}
+
+#[derive(Debug, Clone)]
+struct ScaffoldColumn {
+    name: String,
+    data_type: DataType,
+    nullable: bool,
+    nulls: usize,
+    samples: Vec<String>,
+    /// Never NULL and never repeated in the sample
+    unique: bool,
+}
+
+impl SchemaScaffold {
+    /// Infer the schema of `source` from its first `sample_size` rows and
+    /// read them again for the annotations
+    pub async fn from_source(
+        name: &str,
+        source: &mut dyn Source,
+        sample_size: usize,
+    ) -> Result<Self> {
+        source.connect().await?;
+        let schema = source.infer_schema(sample_size).await?;
+        source.reset().await?;
+        let rows = source.read_batch(sample_size).await?;
+        Ok(Self::from_sample(name, &schema, &rows))
+    }
+
+    pub fn from_sample(source: &str, schema: &Schema, rows: &[Row]) -> Self {
+        let columns = schema
+            .columns
+            .iter()
+            .map(|column| {
+                let mut nulls = 0;
+                let mut seen = HashSet::new();
+                let mut samples = Vec::new();
+                for row in rows {
+                    let Some(text) = row.get(&column.name).and_then(Value::to_string_for_arrow)
+                    else {
+                        nulls += 1;
+                        continue;
+                    };
+                    if seen.insert(text.clone()) && samples.len() < MAX_SAMPLES {
+                        samples.push(text);
+                    }
+                }
+                ScaffoldColumn {
+                    name: column.name.clone(),
+                    data_type: column.data_type.clone(),
+                    nullable: column.nullable || nulls > 0,
+                    nulls,
+                    samples,
+                    unique: !rows.is_empty() && nulls == 0 && seen.len() == rows.len(),
+                }
+            })
+            .collect();
+
+        Self {
+            source: display_target(source),
+            sampled_rows: rows.len(),
+            columns,
+        }
+    }
+
+    /// Columns that could identify a row: integer or text columns never NULL
+    /// and never repeated in the sample
+    pub fn suggested_keys(&self) -> Vec<&str> {
+        self.columns
+            .iter()
+            .filter(|column| {
+                column.unique && matches!(column.data_type, DataType::Integer | DataType::String)
+            })
+            .map(|column| column.name.as_str())
+            .collect()
+    }
+
+    /// The schema file, with the sample values, NULL counts and key
+    /// suggestions as comments
+    pub fn to_yaml(&self) -> String {
+        let keys = self.suggested_keys();
+        let mut lines = vec![
+            format!(
+                "# Schema of {} scaffolded from {} sampled rows.",
+                self.source, self.sampled_rows
+            ),
+            "# Review the types and nullability, then use it with --schema-file.".to_string(),
+        ];
+        if !keys.is_empty() {
+            lines.push(format!(
+                "# Suggested keys (never NULL or repeated in the sample): {}",
+                keys.join(", ")
+            ));
+        }
+        lines.push("columns:".to_string());
+
+        for column in &self.columns {
+            lines.push(format!("  - name: {}", yaml_scalar(&column.name)));
+            lines.push(format!("    type: {}", schema_type(&column.data_type)));
+            lines.push(format!("    nullable: {}", column.nullable));
+            if !column.samples.is_empty() {
+                let samples: Vec<String> = column.samples.iter().map(|s| shorten(s)).collect();
+                lines.push(format!("    # samples: {}", samples.join(" | ")));
+            }
+            if column.nulls > 0 {
+                lines.push(format!(
+                    "    # NULL in {} of {} sampled rows",
+                    column.nulls, self.sampled_rows
+                ));
+            }
+            if keys.contains(&column.name.as_str()) {
+                lines.push("    # suggested key".to_string());
+            }
+            lines.push("    # pattern: \"^...$\"".to_string());
+            lines.push("    # default: \"...\"".to_string());
+        }
+        lines.push(String::new());
+        lines.join("\n")
+    }
+}
+
+/// Name of a type in a schema file
+fn schema_type(data_type: &DataType) -> &'static str {
+    match data_type {
+        DataType::Integer => "integer",
+        DataType::Decimal => "decimal",
+        DataType::Boolean => "boolean",
+        DataType::Date => "date",
+        DataType::DateTime => "datetime",
+        DataType::Json => "json",
+        DataType::Binary => "binary",
+        // A column that was NULL throughout the sample
+        DataType::String | DataType::Null => "string",
+    }
+}
+
+/// `s` as a YAML scalar, quoted when it would otherwise parse as something else
+fn yaml_scalar(s: &str) -> String {
+    serde_yaml::to_string(s)
+        .map(|yaml| yaml.trim_end().to_string())
+        .unwrap_or_else(|_| format!("{:?}", s))
+}
+
+/// A sample value on one comment line
+fn shorten(value: &str) -> String {
+    let value = value.replace(['\r', '\n'], " ");
+    if value.chars().count() > MAX_SAMPLE_CHARS {
+        let short: String = value.chars().take(MAX_SAMPLE_CHARS).collect();
+        format!("{}...", short)
+    } else {
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::{Column, SchemaFile};
+
+    #[test]
+    fn test_scaffold_is_a_valid_schema_file() {
+        let schema = Schema {
+            columns: vec![
+                Column {
+                    name: "id".to_string(),
+                    data_type: DataType::Integer,
+                    nullable: false,
+                },
+                Column {
+                    name: "city: name".to_string(),
+                    data_type: DataType::String,
+                    nullable: false,
+                },
+                Column {
+                    name: "note".to_string(),
+                    data_type: DataType::Null,
+                    nullable: true,
+                },
+            ],
+            estimated_rows: None,
+            primary_key_candidate: None,
+        };
+        let rows: Vec<Row> = (1..=3)
+            .map(|id| {
+                Row::from([
+                    ("id".to_string(), Value::Integer(id)),
+                    ("city: name".to_string(), Value::String("Oslo".to_string())),
+                    ("note".to_string(), Value::Null),
+                ])
+            })
+            .collect();
+
+        let scaffold = SchemaScaffold::from_sample("cities.csv", &schema, &rows);
+        assert_eq!(scaffold.suggested_keys(), ["id"]);
+
+        let yaml = scaffold.to_yaml();
+        assert!(yaml.contains("# Suggested keys (never NULL or repeated in the sample): id"));
+        assert!(yaml.contains("# samples: 1 | 2 | 3"));
+        assert!(yaml.contains("# NULL in 3 of 3 sampled rows"));
+
+        let schema_file: SchemaFile = serde_yaml::from_str(&yaml).unwrap();
+        schema_file.validate().unwrap();
+        let columns: Vec<_> = schema_file
+            .columns
+            .iter()
+            .map(|c| (c.name.as_str(), c.data_type.as_str(), c.nullable))
+            .collect();
+        assert_eq!(
+            columns,
+            [
+                ("id", "integer", false),
+                ("city: name", "string", false),
+                ("note", "string", true)
+            ]
+        );
+    }
+}
@@ -0,0 +1,408 @@
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+
+use crate::{
+    connectors::sql_ident::{quote_ident, quote_table, QuoteStyle},
+    schema::{Column, DataType, Row, Schema, Value},
+    Result, TinyEtlError,
+};
+
+/// Column holding the time a record version became valid
+pub const VALID_FROM_COLUMN: &str = "valid_from";
+/// Column holding the time a record version was superseded (null while current)
+pub const VALID_TO_COLUMN: &str = "valid_to";
+/// Column flagging the current version of each key
+pub const IS_CURRENT_COLUMN: &str = "is_current";
+
+/// Settings for Slowly Changing Dimension Type 2 loads
+#[derive(Debug, Clone)]
+pub struct Scd2Config {
+    /// Business key columns identifying a dimension member
+    pub key_columns: Vec<String>,
+    /// Columns whose changes create a new version (empty = all non-key columns)
+    pub track_columns: Vec<String>,
+}
+
+/// Outcome counters of an SCD2 merge
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Scd2Stats {
+    /// Keys seen for the first time
+    pub inserted: usize,
+    /// Keys whose tracked columns changed (old version expired, new version added)
+    pub changed: usize,
+    /// Keys whose tracked columns were unchanged
+    pub unchanged: usize,
+}
+
+/// What a merge changes in a database table holding the history: the
+/// current versions to close and the versions to add
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scd2Changes {
+    pub key_columns: Vec<String>,
+    /// One row per closed version: its new `valid_to` and `is_current`
+    /// values followed by its key, in the order of `expire_columns`
+    pub expired: Vec<Row>,
+    pub added: Vec<Row>,
+}
+
+impl Scd2Changes {
+    /// Columns of the `expired` rows, in the order `expire_sql` binds them
+    pub fn expire_columns(&self) -> Vec<String> {
+        [VALID_TO_COLUMN, IS_CURRENT_COLUMN]
+            .iter()
+            .map(|column| column.to_string())
+            .chain(self.key_columns.iter().cloned())
+            .collect()
+    }
+
+    /// UPDATE closing the current version of one key; `placeholder` gives
+    /// the parameter marker of each 1-based position
+    pub fn expire_sql(
+        &self,
+        table: &str,
+        style: QuoteStyle,
+        placeholder: impl Fn(usize) -> String,
+    ) -> String {
+        let key_filter: Vec<String> = self
+            .key_columns
+            .iter()
+            .enumerate()
+            .map(|(index, column)| {
+                format!(
+                    "{} = {}",
+                    quote_ident(column, style),
+                    placeholder(index + 3)
+                )
+            })
+            .collect();
+        format!(
+            "UPDATE {} SET {} = {}, {} = {} WHERE {} AND {} IS NULL",
+            quote_table(table, style),
+            quote_ident(VALID_TO_COLUMN, style),
+            placeholder(1),
+            quote_ident(IS_CURRENT_COLUMN, style),
+            placeholder(2),
+            key_filter.join(" AND "),
+            quote_ident(VALID_TO_COLUMN, style)
+        )
+    }
+}
+
+/// Merges incoming rows into the existing dimension history, expiring changed
+/// records instead of overwriting them
+pub struct Scd2Merger {
+    config: Scd2Config,
+    history: Vec<Row>,
+    /// Rows of `history` read from the target; the rest are new versions
+    existing: usize,
+    /// Keys of the stored current versions this merge closed
+    expired: Vec<Row>,
+    current_by_key: HashMap<String, usize>,
+    load_time: DateTime<Utc>,
+    stats: Scd2Stats,
+}
+
+impl Scd2Merger {
+    /// Create a merger over the rows currently stored in the target
+    pub fn new(config: Scd2Config, existing: Vec<Row>, load_time: DateTime<Utc>) -> Result<Self> {
+        if config.key_columns.is_empty() {
+            return Err(TinyEtlError::Configuration(
+                "SCD2 mode requires at least one key column (--key)".to_string(),
+            ));
+        }
+
+        let mut merger = Self {
+            config,
+            history: Vec::with_capacity(existing.len()),
+            existing: existing.len(),
+            expired: Vec::new(),
+            current_by_key: HashMap::new(),
+            load_time,
+            stats: Scd2Stats::default(),
+        };
+
+        for row in existing {
+            if Self::is_current(&row) {
+                let key = merger.key_of(&row);
+                merger.current_by_key.insert(key, merger.history.len());
+            }
+            merger.history.push(row);
+        }
+
+        Ok(merger)
+    }
+
+    /// Add the SCD2 bookkeeping columns to a schema
+    pub fn extend_schema(schema: &Schema) -> Schema {
+        let mut extended = schema.clone();
+        extended.columns.retain(|c| {
+            c.name != VALID_FROM_COLUMN && c.name != VALID_TO_COLUMN && c.name != IS_CURRENT_COLUMN
+        });
+        extended.columns.push(Column {
+            name: VALID_FROM_COLUMN.to_string(),
+            data_type: DataType::DateTime,
+            nullable: false,
+        });
+        extended.columns.push(Column {
+            name: VALID_TO_COLUMN.to_string(),
+            data_type: DataType::DateTime,
+            nullable: true,
+        });
+        extended.columns.push(Column {
+            name: IS_CURRENT_COLUMN.to_string(),
+            data_type: DataType::Boolean,
+            nullable: false,
+        });
+        extended
+    }
+
+    /// Merge one incoming row into the history
+    pub fn apply(&mut self, mut row: Row) -> Result<()> {
+        for key_column in &self.config.key_columns {
+            if matches!(row.get(key_column), None | Some(Value::Null)) {
+                return Err(TinyEtlError::DataValidation(format!(
+                    "SCD2 key column '{}' is missing or null",
+                    key_column
+                )));
+            }
+        }
+
+        let key = self.key_of(&row);
+        if let Some(&index) = self.current_by_key.get(&key) {
+            if !self.has_changes(&self.history[index], &row) {
+                self.stats.unchanged += 1;
+                return Ok(());
+            }
+
+            let previous = &mut self.history[index];
+            previous.insert(VALID_TO_COLUMN.to_string(), Value::Date(self.load_time));
+            previous.insert(IS_CURRENT_COLUMN.to_string(), Value::Boolean(false));
+            if index < self.existing {
+                let mut expired = Row::new();
+                expired.insert(VALID_TO_COLUMN.to_string(), Value::Date(self.load_time));
+                expired.insert(IS_CURRENT_COLUMN.to_string(), Value::Boolean(false));
+                for column in &self.config.key_columns {
+                    let value = previous.get(column).cloned().unwrap_or(Value::Null);
+                    expired.insert(column.clone(), value);
+                }
+                self.expired.push(expired);
+            }
+            self.stats.changed += 1;
+        } else {
+            self.stats.inserted += 1;
+        }
+
+        row.insert(VALID_FROM_COLUMN.to_string(), Value::Date(self.load_time));
+        row.insert(VALID_TO_COLUMN.to_string(), Value::Null);
+        row.insert(IS_CURRENT_COLUMN.to_string(), Value::Boolean(true));
+        self.current_by_key.insert(key, self.history.len());
+        self.history.push(row);
+        Ok(())
+    }
+
+    pub fn stats(&self) -> &Scd2Stats {
+        &self.stats
+    }
+
+    /// The full dimension history to write back to the target
+    pub fn into_rows(self) -> Vec<Row> {
+        self.history
+    }
+
+    /// The changes to apply to a table holding the existing history, which
+    /// leave the rows of unchanged keys alone
+    pub fn into_changes(mut self) -> Scd2Changes {
+        Scd2Changes {
+            key_columns: self.config.key_columns,
+            expired: self.expired,
+            added: self.history.split_off(self.existing),
+        }
+    }
+
+    fn key_of(&self, row: &Row) -> String {
+        self.config
+            .key_columns
+            .iter()
+            .map(|column| Self::comparable(row.get(column)))
+            .collect::<Vec<_>>()
+            .join("\u{1f}")
+    }
+
+    fn has_changes(&self, current: &Row, incoming: &Row) -> bool {
+        let differs = |column: &String| {
+            Self::comparable(current.get(column)) != Self::comparable(incoming.get(column))
+        };
+
+        if self.config.track_columns.is_empty() {
+            incoming
+                .keys()
+                .filter(|column| !self.config.key_columns.contains(column))
+                .any(differs)
+        } else {
+            self.config.track_columns.iter().any(differs)
+        }
+    }
+
+    /// String form used for comparisons, so values read back from the target
+    /// (e.g. integers stored as text) compare equal to freshly parsed ones
+    fn comparable(value: Option<&Value>) -> String {
+        match value {
+            None | Some(Value::Null) => String::new(),
+            Some(Value::Decimal(d)) => d.normalize().to_string(),
+            Some(value) => value.to_string_for_arrow().unwrap_or_default(),
+        }
+    }
+
+    fn is_current(row: &Row) -> bool {
+        match row.get(IS_CURRENT_COLUMN) {
+            Some(Value::Boolean(b)) => *b,
+            Some(Value::Integer(i)) => *i != 0,
+            Some(Value::String(s)) => matches!(s.to_lowercase().as_str(), "true" | "1" | "t"),
+            // Rows written before SCD2 was enabled count as current
+            None | Some(Value::Null) => true,
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn customer(id: i64, name: &str, city: &str) -> Row {
+        let mut row = Row::new();
+        row.insert("customer_id".to_string(), Value::Integer(id));
+        row.insert("name".to_string(), Value::String(name.to_string()));
+        row.insert("city".to_string(), Value::String(city.to_string()));
+        row
+    }
+
+    fn config(track: &[&str]) -> Scd2Config {
+        Scd2Config {
+            key_columns: vec!["customer_id".to_string()],
+            track_columns: track.iter().map(|c| c.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_initial_load_marks_rows_current() {
+        let mut merger = Scd2Merger::new(config(&[]), Vec::new(), Utc::now()).unwrap();
+        merger.apply(customer(1, "Ada", "London")).unwrap();
+        merger.apply(customer(2, "Alan", "Wilmslow")).unwrap();
+
+        assert_eq!(merger.stats().inserted, 2);
+        let rows = merger.into_rows();
+        assert!(rows
+            .iter()
+            .all(|r| r.get(IS_CURRENT_COLUMN) == Some(&Value::Boolean(true))));
+        assert!(rows
+            .iter()
+            .all(|r| r.get(VALID_TO_COLUMN) == Some(&Value::Null)));
+    }
+
+    #[test]
+    fn test_changed_record_is_expired() {
+        let first_load = Utc::now() - chrono::Duration::days(1);
+        let mut merger = Scd2Merger::new(config(&["city"]), Vec::new(), first_load).unwrap();
+        merger.apply(customer(1, "Ada", "London")).unwrap();
+        merger.apply(customer(2, "Alan", "Wilmslow")).unwrap();
+        let existing = merger.into_rows();
+
+        let second_load = Utc::now();
+        let mut merger = Scd2Merger::new(config(&["city"]), existing, second_load).unwrap();
+        merger.apply(customer(1, "Ada", "Paris")).unwrap();
+        // Name is not tracked, so this is not a new version
+        merger.apply(customer(2, "Alan T.", "Wilmslow")).unwrap();
+        merger.apply(customer(3, "Grace", "Arlington")).unwrap();
+
+        assert_eq!(
+            merger.stats(),
+            &Scd2Stats {
+                inserted: 1,
+                changed: 1,
+                unchanged: 1
+            }
+        );
+
+        let rows = merger.into_rows();
+        assert_eq!(rows.len(), 4);
+
+        let ada: Vec<&Row> = rows
+            .iter()
+            .filter(|r| r.get("customer_id") == Some(&Value::Integer(1)))
+            .collect();
+        assert_eq!(ada.len(), 2);
+        assert_eq!(ada[0].get(IS_CURRENT_COLUMN), Some(&Value::Boolean(false)));
+        assert_eq!(ada[0].get(VALID_TO_COLUMN), Some(&Value::Date(second_load)));
+        assert_eq!(ada[1].get(IS_CURRENT_COLUMN), Some(&Value::Boolean(true)));
+        assert_eq!(
+            ada[1].get("city"),
+            Some(&Value::String("Paris".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_changes_close_stored_versions_only() {
+        let first_load = Utc::now() - chrono::Duration::days(1);
+        let mut merger = Scd2Merger::new(config(&["city"]), Vec::new(), first_load).unwrap();
+        merger.apply(customer(1, "Ada", "London")).unwrap();
+        merger.apply(customer(2, "Alan", "Wilmslow")).unwrap();
+        let existing = merger.into_rows();
+
+        let second_load = Utc::now();
+        let mut merger = Scd2Merger::new(config(&["city"]), existing, second_load).unwrap();
+        merger.apply(customer(1, "Ada", "Paris")).unwrap();
+        // A key changed twice in one load adds both versions; only the
+        // stored one is updated
+        merger.apply(customer(1, "Ada", "Rome")).unwrap();
+        merger.apply(customer(3, "Grace", "Arlington")).unwrap();
+
+        let changes = merger.into_changes();
+        assert_eq!(changes.expired.len(), 1);
+        assert_eq!(
+            changes.expired[0].get("customer_id"),
+            Some(&Value::Integer(1))
+        );
+        assert_eq!(
+            changes.expired[0].get(VALID_TO_COLUMN),
+            Some(&Value::Date(second_load))
+        );
+        assert_eq!(changes.added.len(), 3);
+        assert_eq!(
+            changes.added[0].get(IS_CURRENT_COLUMN),
+            Some(&Value::Boolean(false))
+        );
+        assert_eq!(
+            changes.expire_sql("dim.customers", QuoteStyle::DoubleQuote, |i| format!(
+                "${}",
+                i
+            )),
+            "UPDATE \"dim\".\"customers\" SET \"valid_to\" = $1, \"is_current\" = $2 \
+             WHERE \"customer_id\" = $3 AND \"valid_to\" IS NULL"
+        );
+        assert_eq!(
+            changes.expire_columns(),
+            vec!["valid_to", "is_current", "customer_id"]
+        );
+    }
+
+    #[test]
+    fn test_values_read_back_as_text_compare_equal() {
+        let mut stored = customer(1, "Ada", "London");
+        stored.insert("customer_id".to_string(), Value::String("1".to_string()));
+        stored.insert(IS_CURRENT_COLUMN.to_string(), Value::Integer(1));
+
+        let mut merger = Scd2Merger::new(config(&[]), vec![stored], Utc::now()).unwrap();
+        merger.apply(customer(1, "Ada", "London")).unwrap();
+        assert_eq!(merger.stats().unchanged, 1);
+    }
+
+    #[test]
+    fn test_requires_key_columns() {
+        let config = Scd2Config {
+            key_columns: Vec::new(),
+            track_columns: Vec::new(),
+        };
+        assert!(Scd2Merger::new(config, Vec::new(), Utc::now()).is_err());
+    }
+}
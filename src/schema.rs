@@ -1,10 +1,13 @@
+use crate::date_parser::DateParser;
 use crate::Result;
 use arrow::datatypes::{DataType as ArrowDataType, Field, TimeUnit};
+use base64::{engine::general_purpose, Engine as _};
 use chrono::{DateTime, Utc};
 use regex::Regex;
+use rust_decimal::prelude::ToPrimitive;
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::path::Path;
 
 /// Wrapper around Arrow DataType for schema definition
@@ -17,6 +20,7 @@ pub enum DataType {
     Date,
     DateTime,
     Json,
+    Binary,
     Null,
 }
 
@@ -31,6 +35,7 @@ impl DataType {
             DataType::Date => ArrowDataType::Date64,
             DataType::DateTime => ArrowDataType::Timestamp(TimeUnit::Nanosecond, None),
             DataType::Json => ArrowDataType::Utf8, // Store JSON as string in Arrow
+            DataType::Binary => ArrowDataType::Binary,
             DataType::Null => ArrowDataType::Null,
         }
     }
@@ -54,12 +59,93 @@ impl DataType {
             ArrowDataType::Boolean => DataType::Boolean,
             ArrowDataType::Date32 | ArrowDataType::Date64 => DataType::Date,
             ArrowDataType::Timestamp(_, _) => DataType::DateTime,
+            ArrowDataType::Binary
+            | ArrowDataType::LargeBinary
+            | ArrowDataType::FixedSizeBinary(_) => DataType::Binary,
             ArrowDataType::Null => DataType::Null,
             _ => DataType::String, // Default to string for complex types
         }
     }
 }
 
+/// Where the schema of a run comes from
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SchemaMode {
+    /// Sample the source (or use the schema file, if any)
+    #[default]
+    Inferred,
+    /// Use the declared schema verbatim: no sampling, strict type conversion
+    Declared,
+}
+
+impl std::fmt::Display for SchemaMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SchemaMode::Inferred => write!(f, "inferred"),
+            SchemaMode::Declared => write!(f, "declared"),
+        }
+    }
+}
+
+impl std::str::FromStr for SchemaMode {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "inferred" => Ok(SchemaMode::Inferred),
+            "declared" => Ok(SchemaMode::Declared),
+            _ => Err("Invalid schema mode. Valid values: inferred, declared"),
+        }
+    }
+}
+
+/// How binary values are written to text formats such as CSV and JSON
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum BinaryEncoding {
+    #[default]
+    Base64,
+    Hex,
+}
+
+impl BinaryEncoding {
+    pub fn encode(&self, bytes: &[u8]) -> String {
+        match self {
+            BinaryEncoding::Base64 => general_purpose::STANDARD.encode(bytes),
+            BinaryEncoding::Hex => hex::encode(bytes),
+        }
+    }
+
+    pub fn decode(&self, text: &str) -> Option<Vec<u8>> {
+        match self {
+            BinaryEncoding::Base64 => general_purpose::STANDARD.decode(text).ok(),
+            BinaryEncoding::Hex => hex::decode(text).ok(),
+        }
+    }
+}
+
+impl std::fmt::Display for BinaryEncoding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BinaryEncoding::Base64 => write!(f, "base64"),
+            BinaryEncoding::Hex => write!(f, "hex"),
+        }
+    }
+}
+
+impl std::str::FromStr for BinaryEncoding {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "base64" => Ok(BinaryEncoding::Base64),
+            "hex" => Ok(BinaryEncoding::Hex),
+            _ => Err("Invalid binary encoding. Valid values: base64, hex"),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SchemaFileColumn {
     pub name: String,
@@ -68,6 +154,11 @@ pub struct SchemaFileColumn {
     pub nullable: bool,
     pub pattern: Option<String>,
     pub default: Option<String>,
+    /// Reject a value seen in an earlier row (NULLs excepted)
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub unique: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub accepted_values: Option<Vec<String>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -76,8 +167,24 @@ pub struct SchemaFile {
 }
 
 impl SchemaFile {
+    /// Load a TinyETL schema file or a dbt properties file, whose model or
+    /// source table is selected with `FILE#NAME`
     pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let (path, model) = match path.to_str().and_then(|p| p.rsplit_once('#')) {
+            Some((file, model)) if !path.exists() => (Path::new(file), Some(model)),
+            _ => (path, None),
+        };
         let content = std::fs::read_to_string(path)?;
+        if crate::dbt::is_properties_file(&content) {
+            return crate::dbt::schema_file(&content, model);
+        }
+        if model.is_some() {
+            return Err(crate::TinyEtlError::Configuration(format!(
+                "Only dbt properties files have models to select, and {} is not one",
+                path.display()
+            )));
+        }
         let schema_file: SchemaFile = serde_yaml::from_str(&content).map_err(|e| {
             crate::TinyEtlError::Configuration(format!("Invalid schema file: {}", e))
         })?;
@@ -92,7 +199,8 @@ impl SchemaFile {
         for column in &self.columns {
             // Validate data type
             match column.data_type.to_lowercase().as_str() {
-                "string" | "integer" | "decimal" | "boolean" | "date" | "datetime" | "json" => {}
+                "string" | "integer" | "decimal" | "boolean" | "date" | "datetime" | "json"
+                | "binary" => {}
                 _ => {
                     return Err(crate::TinyEtlError::Configuration(format!(
                         "Invalid data type '{}' for column '{}'",
@@ -118,23 +226,10 @@ impl SchemaFile {
         let columns = self
             .columns
             .iter()
-            .map(|col| {
-                let data_type = match col.data_type.to_lowercase().as_str() {
-                    "string" => DataType::String,
-                    "integer" => DataType::Integer,
-                    "decimal" => DataType::Decimal,
-                    "boolean" => DataType::Boolean,
-                    "date" => DataType::Date,
-                    "datetime" => DataType::DateTime,
-                    "json" => DataType::Json,
-                    _ => DataType::String, // Already validated, so this shouldn't happen
-                };
-
-                Column {
-                    name: col.name.clone(),
-                    data_type,
-                    nullable: col.nullable,
-                }
+            .map(|col| Column {
+                name: col.name.clone(),
+                data_type: Self::declared_type(&col.data_type),
+                nullable: col.nullable,
             })
             .collect();
 
@@ -198,6 +293,102 @@ impl SchemaFile {
         Ok(())
     }
 
+    /// Strict conversion for declared-schema mode: the row is rebuilt with
+    /// exactly the declared columns, each value converted to its declared
+    /// type. Values that cannot be converted are errors.
+    pub fn conform_row(&self, row: &mut Row) -> Result<()> {
+        let mut conformed = Row::with_capacity(self.columns.len());
+        for column in &self.columns {
+            let data_type = Self::declared_type(&column.data_type);
+            let value = row.remove(&column.name).unwrap_or(Value::Null);
+            let mut value = Self::convert_value(value, &data_type).map_err(|found| {
+                crate::TinyEtlError::DataValidation(format!(
+                    "Column '{}' expected type {:?}, got '{}'",
+                    column.name, data_type, found
+                ))
+            })?;
+
+            if matches!(value, Value::Null) && !column.nullable {
+                match &column.default {
+                    Some(default) => {
+                        value = self.parse_default_value(default, &column.data_type)?
+                    }
+                    None => {
+                        return Err(crate::TinyEtlError::DataValidation(format!(
+                            "Required column '{}' is missing or null",
+                            column.name
+                        )))
+                    }
+                }
+            }
+
+            if let (Some(pattern), Value::String(s)) = (&column.pattern, &value) {
+                let regex = Regex::new(pattern).unwrap(); // Already validated in validate()
+                if !regex.is_match(s) {
+                    return Err(crate::TinyEtlError::DataValidation(format!(
+                        "Column '{}' value '{}' does not match pattern '{}'",
+                        column.name, s, pattern
+                    )));
+                }
+            }
+            Self::check_accepted_value(&value, column)?;
+
+            conformed.insert(column.name.clone(), value);
+        }
+        *row = conformed;
+        Ok(())
+    }
+
+    fn declared_type(name: &str) -> DataType {
+        match name.to_lowercase().as_str() {
+            "integer" => DataType::Integer,
+            "decimal" => DataType::Decimal,
+            "boolean" => DataType::Boolean,
+            "date" => DataType::Date,
+            "datetime" => DataType::DateTime,
+            "json" => DataType::Json,
+            "binary" => DataType::Binary,
+            _ => DataType::String, // Already validated, so this is "string"
+        }
+    }
+
+    /// Convert a value to `data_type` without losing information; the error
+    /// is the offending value as text
+    fn convert_value(value: Value, data_type: &DataType) -> std::result::Result<Value, String> {
+        let converted = match (&value, data_type) {
+            (Value::Null, _) => Some(Value::Null),
+            (Value::String(s), _) if s.is_empty() && *data_type != DataType::String => {
+                Some(Value::Null)
+            }
+            (Value::Json(_), DataType::String) => None,
+            (_, DataType::String) => value.to_string_for_arrow().map(Value::String),
+            (Value::Integer(_), DataType::Integer)
+            | (Value::Decimal(_), DataType::Decimal)
+            | (Value::Boolean(_), DataType::Boolean)
+            | (Value::Date(_), DataType::Date | DataType::DateTime)
+            | (Value::Json(_), DataType::Json)
+            | (Value::Bytes(_), DataType::Binary) => Some(value.clone()),
+            (Value::Integer(i), DataType::Decimal) => Some(Value::Decimal(Decimal::from(*i))),
+            (Value::Decimal(d), DataType::Integer) if d.fract().is_zero() => {
+                d.to_i64().map(Value::Integer)
+            }
+            (Value::String(s), DataType::Integer) => s.trim().parse().ok().map(Value::Integer),
+            (Value::String(s), DataType::Decimal) => s.trim().parse().ok().map(Value::Decimal),
+            (Value::String(s), DataType::Boolean) => {
+                s.trim().to_lowercase().parse().ok().map(Value::Boolean)
+            }
+            (Value::String(s), DataType::Date | DataType::DateTime) => {
+                DateParser::try_parse(s.trim()).filter(|v| matches!(v, Value::Date(_)))
+            }
+            (Value::String(s), DataType::Json) => serde_json::from_str(s).ok().map(Value::Json),
+            (Value::String(s), DataType::Binary) => {
+                BinaryEncoding::default().decode(s.trim()).map(Value::Bytes)
+            }
+            _ => None,
+        };
+        converted.ok_or_else(|| value.to_string_for_arrow().unwrap_or_default())
+    }
+
     fn validate_column_value(&self, value: &Value, schema_col: &SchemaFileColumn) -> Result<()> {
         // Skip null values if column is nullable
         if matches!(value, Value::Null) && schema_col.nullable {
@@ -213,6 +404,7 @@ impl SchemaFile {
             "date" => DataType::Date,
             "datetime" => DataType::DateTime,
             "json" => DataType::Json,
+            "binary" => DataType::Binary,
             _ => {
                 return Err(crate::TinyEtlError::DataValidation(format!(
                     "Unknown data type '{}' for column '{}'",
@@ -240,7 +432,23 @@ impl SchemaFile {
             }
         }
 
-        Ok(())
+        Self::check_accepted_value(value, schema_col)
+    }
+
+    fn check_accepted_value(value: &Value, column: &SchemaFileColumn) -> Result<()> {
+        let (Some(accepted), Some(text)) = (&column.accepted_values, value.to_string_for_arrow())
+        else {
+            return Ok(());
+        };
+        if accepted.contains(&text) {
+            return Ok(());
+        }
+        Err(crate::TinyEtlError::DataValidation(format!(
+            "Column '{}' value '{}' is not one of the accepted values: {}",
+            column.name,
+            text,
+            accepted.join(", ")
+        )))
     }
 
     fn parse_default_value(&self, default_str: &str, data_type: &str) -> Result<Value> {
@@ -298,6 +506,15 @@ impl SchemaFile {
                 })?;
                 Ok(Value::Json(parsed))
             }
+            "binary" => BinaryEncoding::default()
+                .decode(default_str)
+                .map(Value::Bytes)
+                .ok_or_else(|| {
+                    crate::TinyEtlError::Configuration(format!(
+                        "Invalid default binary value (base64 expected): '{}'",
+                        default_str
+                    ))
+                }),
             _ => Err(crate::TinyEtlError::Configuration(format!(
                 "Unsupported data type for default value: '{}'",
                 data_type
@@ -306,6 +523,45 @@ impl SchemaFile {
     }
 }
 
+/// Values seen so far in the columns a schema file declares `unique`
+#[derive(Debug, Default)]
+pub struct UniqueValues {
+    seen: HashMap<String, HashSet<String>>,
+}
+
+impl UniqueValues {
+    /// Fail if the row repeats a value of a unique column, otherwise
+    /// remember its values
+    pub fn check(&mut self, schema_file: &SchemaFile, row: &Row) -> Result<()> {
+        let values: Vec<(&str, String)> = schema_file
+            .columns
+            .iter()
+            .filter(|column| column.unique)
+            .filter_map(|column| {
+                let value = row.get(&column.name)?.to_string_for_arrow()?;
+                Some((column.name.as_str(), value))
+            })
+            .collect();
+
+        for (name, value) in &values {
+            if self
+                .seen
+                .get(*name)
+                .is_some_and(|seen| seen.contains(value))
+            {
+                return Err(crate::TinyEtlError::DataValidation(format!(
+                    "Column '{}' value '{}' is not unique",
+                    name, value
+                )));
+            }
+        }
+        for (name, value) in values {
+            self.seen.entry(name.to_string()).or_default().insert(value);
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Column {
     pub name: String,
@@ -370,6 +626,17 @@ impl Schema {
             primary_key_candidate: None,
         }
     }
+
+    /// Move the columns named in `order` to the front, in that order. The
+    /// other columns keep their relative order after them.
+    pub fn reorder_columns(&mut self, order: &[String]) {
+        self.columns.sort_by_key(|column| {
+            order
+                .iter()
+                .position(|name| name == &column.name)
+                .unwrap_or(order.len())
+        });
+    }
 }
 
 /// Value type compatible with Arrow data representation
@@ -382,6 +649,7 @@ pub enum Value {
     Boolean(bool),
     Date(DateTime<Utc>),
     Json(serde_json::Value),
+    Bytes(Vec<u8>),
     Null,
 }
 
@@ -395,6 +663,7 @@ impl Value {
             Value::Boolean(_) => ArrowDataType::Boolean,
             Value::Date(_) => ArrowDataType::Timestamp(TimeUnit::Nanosecond, None),
             Value::Json(_) => ArrowDataType::Utf8, // Store JSON as string in Arrow
+            Value::Bytes(_) => ArrowDataType::Binary,
             Value::Null => ArrowDataType::Null,
         }
     }
@@ -408,10 +677,22 @@ impl Value {
             Value::Boolean(b) => Some(b.to_string()),
             Value::Date(dt) => Some(dt.to_rfc3339()),
             Value::Json(j) => Some(serde_json::to_string(j).unwrap_or_else(|_| "{}".to_string())),
+            Value::Bytes(b) => Some(BinaryEncoding::default().encode(b)),
             Value::Null => None,
         }
     }
 
+    /// Value of a database integer wider than i64 (unsigned BIGINT, HUGEINT):
+    /// an integer when it fits, otherwise the exact number as a decimal, or
+    /// as text beyond the decimal range, so it is never wrapped
+    pub fn from_wide_integer(n: i128) -> Value {
+        match i64::try_from(n) {
+            Ok(i) => Value::Integer(i),
+            Err(_) => Decimal::try_from_i128_with_scale(n, 0)
+                .map_or_else(|_| Value::String(n.to_string()), Value::Decimal),
+        }
+    }
+
     /// Convert to i64 for Arrow array building
     pub fn to_i64(&self) -> Option<i64> {
         match self {
@@ -469,8 +750,9 @@ impl SchemaInferer {
 
         let mut column_types: HashMap<String, Vec<DataType>> = HashMap::new();
 
-        // Collect all unique column names
-        let mut all_columns = std::collections::HashSet::new();
+        // Collect all unique column names, sorted so the column order is the
+        // same on every run (rows are hash maps)
+        let mut all_columns = std::collections::BTreeSet::new();
         for row in rows {
             for key in row.keys() {
                 all_columns.insert(key.clone());
@@ -519,6 +801,7 @@ impl SchemaInferer {
             Value::Boolean(_) => DataType::Boolean,
             Value::Date(_) => DataType::DateTime,
             Value::Json(_) => DataType::Json,
+            Value::Bytes(_) => DataType::Binary,
             Value::Null => DataType::Null,
         }
     }
@@ -564,6 +847,7 @@ impl std::fmt::Display for DataType {
             DataType::Date => write!(f, "DATE"),
             DataType::DateTime => write!(f, "TIMESTAMP"),
             DataType::Json => write!(f, "JSON"),
+            DataType::Binary => write!(f, "BLOB"),
             DataType::Null => write!(f, "NULL"),
         }
     }
@@ -597,6 +881,22 @@ mod tests {
         assert!(id_col.nullable); // Always nullable when inferred from sample data
     }
 
+    #[test]
+    fn test_column_order_is_deterministic() {
+        let row: Row = ["name", "id", "active", "created"]
+            .iter()
+            .map(|name| (name.to_string(), Value::Null))
+            .collect();
+        let mut schema = SchemaInferer::infer_from_rows(&[row]).unwrap();
+        let names = |schema: &Schema| -> Vec<String> {
+            schema.columns.iter().map(|c| c.name.clone()).collect()
+        };
+        assert_eq!(names(&schema), ["active", "created", "id", "name"]);
+
+        schema.reorder_columns(&["id".to_string(), "name".to_string()]);
+        assert_eq!(names(&schema), ["id", "name", "active", "created"]);
+    }
+
     #[test]
     fn test_mixed_types_schema_inference() {
         let mut row1 = HashMap::new();
@@ -790,6 +1090,8 @@ mod tests {
                 nullable: false,
                 pattern: None,
                 default: Some(r#"{"enabled": true, "count": 0}"#.to_string()),
+                unique: false,
+                accepted_values: None,
             }],
         };
 
@@ -805,4 +1107,128 @@ mod tests {
             _ => panic!("Expected JSON value"),
         }
     }
+
+    #[test]
+    fn test_binary_encodings() {
+        let bytes = vec![0u8, 159, 255];
+        assert_eq!(BinaryEncoding::Base64.encode(&bytes), "AJ//");
+        assert_eq!(BinaryEncoding::Hex.encode(&bytes), "009fff");
+        assert_eq!(BinaryEncoding::Hex.decode("009fff"), Some(bytes.clone()));
+        assert_eq!("hex".parse::<BinaryEncoding>(), Ok(BinaryEncoding::Hex));
+
+        // Text formats carry binary as base64, which a declared binary column decodes
+        let converted =
+            SchemaFile::convert_value(Value::String("AJ//".to_string()), &DataType::Binary);
+        assert_eq!(converted, Ok(Value::Bytes(bytes)));
+        assert_eq!(
+            DataType::from_arrow(&ArrowDataType::LargeBinary),
+            DataType::Binary
+        );
+    }
+
+    #[test]
+    fn test_conform_row_to_declared_schema() {
+        let column = |name: &str, data_type: &str, nullable: bool| SchemaFileColumn {
+            name: name.to_string(),
+            data_type: data_type.to_string(),
+            nullable,
+            pattern: None,
+            default: None,
+            unique: false,
+            accepted_values: None,
+        };
+        let schema_file = SchemaFile {
+            columns: vec![
+                column("id", "integer", false),
+                column("zip", "string", true),
+                column("amount", "decimal", true),
+                column("shipped", "date", true),
+            ],
+        };
+
+        let mut row = Row::new();
+        row.insert("id".to_string(), Value::String("42".to_string()));
+        row.insert("zip".to_string(), Value::Integer(2134));
+        row.insert("amount".to_string(), Value::Integer(10));
+        row.insert(
+            "shipped".to_string(),
+            Value::String("2024-03-01".to_string()),
+        );
+        row.insert("extra".to_string(), Value::Boolean(true));
+        schema_file.conform_row(&mut row).unwrap();
+
+        assert_eq!(row.get("id"), Some(&Value::Integer(42)));
+        assert_eq!(row.get("zip"), Some(&Value::String("2134".to_string())));
+        assert_eq!(row.get("amount"), Some(&Value::Decimal(Decimal::from(10))));
+        assert!(matches!(row.get("shipped"), Some(Value::Date(_))));
+        assert!(!row.contains_key("extra"));
+
+        let mut row = Row::new();
+        row.insert("id".to_string(), Value::String("abc".to_string()));
+        let err = schema_file.conform_row(&mut row).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("Column 'id' expected type Integer, got 'abc'"));
+
+        // Required columns must be present
+        let mut row = Row::new();
+        row.insert("zip".to_string(), Value::String("02134".to_string()));
+        assert!(schema_file.conform_row(&mut row).is_err());
+    }
+
+    #[test]
+    fn test_unique_and_accepted_values() {
+        let schema_file = SchemaFile {
+            columns: vec![
+                SchemaFileColumn {
+                    name: "id".to_string(),
+                    data_type: "integer".to_string(),
+                    nullable: true,
+                    pattern: None,
+                    default: None,
+                    unique: true,
+                    accepted_values: None,
+                },
+                SchemaFileColumn {
+                    name: "status".to_string(),
+                    data_type: "string".to_string(),
+                    nullable: true,
+                    pattern: None,
+                    default: None,
+                    unique: false,
+                    accepted_values: Some(vec!["placed".to_string(), "shipped".to_string()]),
+                },
+            ],
+        };
+        let row = |id: Value, status: &str| {
+            let mut row = Row::new();
+            row.insert("id".to_string(), id);
+            row.insert("status".to_string(), Value::String(status.to_string()));
+            row
+        };
+
+        let mut unique = UniqueValues::default();
+        for id in [
+            Value::Integer(1),
+            Value::Integer(2),
+            Value::Null,
+            Value::Null,
+        ] {
+            let mut row = row(id, "placed");
+            schema_file.validate_and_transform_row(&mut row).unwrap();
+            unique.check(&schema_file, &row).unwrap();
+        }
+        let err = unique
+            .check(&schema_file, &row(Value::Integer(1), "placed"))
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("Column 'id' value '1' is not unique"));
+
+        let mut returned = row(Value::Integer(3), "returned");
+        assert!(schema_file
+            .validate_and_transform_row(&mut returned)
+            .is_err());
+        assert!(schema_file.conform_row(&mut returned).is_err());
+    }
 }
@@ -0,0 +1,329 @@
+use arrow::datatypes::{DataType as ArrowDataType, Field};
+use serde_json::{json, Value as JsonValue};
+
+use crate::{
+    connectors::{
+        avro::AvroTarget,
+        sql_ident::{quote_ident, quote_table, QuoteStyle},
+    },
+    schema::{DataType, Schema},
+    Result, TinyEtlError,
+};
+
+/// Format `tinyetl schema` writes an inferred schema in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SchemaFormat {
+    /// JSON Schema (draft 2020-12) of one row
+    JsonSchema,
+    /// Avro record schema, as written by Avro targets
+    Avro,
+    /// Arrow schema in the JSON form of the Arrow integration tests
+    Arrow,
+    /// `CREATE TABLE` statement of a SQL dialect
+    Sql(SqlDialect),
+}
+
+/// SQL dialect of the `CREATE TABLE` statement written by `--format sql:<dialect>`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SqlDialect {
+    Postgres,
+    Mysql,
+    Sqlite,
+    Mssql,
+    Duckdb,
+}
+
+impl std::str::FromStr for SchemaFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "jsonschema" | "json-schema" => Ok(SchemaFormat::JsonSchema),
+            "avro" => Ok(SchemaFormat::Avro),
+            "arrow" => Ok(SchemaFormat::Arrow),
+            format => match format.strip_prefix("sql:") {
+                Some(dialect) => dialect.parse().map(SchemaFormat::Sql),
+                None => Err(format!(
+                    "Invalid schema format '{}'. Valid values: jsonschema, avro, arrow, sql:<dialect>",
+                    s
+                )),
+            },
+        }
+    }
+}
+
+impl std::str::FromStr for SqlDialect {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "postgres" | "postgresql" => Ok(SqlDialect::Postgres),
+            "mysql" => Ok(SqlDialect::Mysql),
+            "sqlite" => Ok(SqlDialect::Sqlite),
+            "mssql" | "sqlserver" => Ok(SqlDialect::Mssql),
+            "duckdb" => Ok(SqlDialect::Duckdb),
+            _ => Err(format!(
+                "Invalid SQL dialect '{}'. Valid values: postgres, mysql, sqlite, mssql, duckdb",
+                s
+            )),
+        }
+    }
+}
+
+impl SqlDialect {
+    fn quote_style(self) -> QuoteStyle {
+        match self {
+            SqlDialect::Postgres | SqlDialect::Sqlite | SqlDialect::Duckdb => {
+                QuoteStyle::DoubleQuote
+            }
+            SqlDialect::Mysql => QuoteStyle::Backtick,
+            SqlDialect::Mssql => QuoteStyle::Bracket,
+        }
+    }
+
+    /// Column type the dialect's target creates for `data_type`
+    fn column_type(self, data_type: &DataType) -> &'static str {
+        match (self, data_type) {
+            (SqlDialect::Postgres, DataType::Integer) => "BIGINT",
+            (SqlDialect::Postgres, DataType::Decimal) => "DECIMAL",
+            (SqlDialect::Postgres, DataType::Boolean) => "BOOLEAN",
+            (SqlDialect::Postgres, DataType::Date | DataType::DateTime) => {
+                "TIMESTAMP WITH TIME ZONE"
+            }
+            (SqlDialect::Postgres, DataType::Binary) => "BYTEA",
+            (SqlDialect::Postgres, DataType::Json) => "JSONB",
+            (SqlDialect::Postgres, DataType::String | DataType::Null) => "TEXT",
+
+            (SqlDialect::Mysql, DataType::Integer) => "BIGINT",
+            (SqlDialect::Mysql, DataType::Decimal) => "DECIMAL(65,30)",
+            (SqlDialect::Mysql, DataType::Boolean) => "BOOLEAN",
+            (SqlDialect::Mysql, DataType::Date) => "DATE",
+            (SqlDialect::Mysql, DataType::DateTime) => "DATETIME",
+            (SqlDialect::Mysql, DataType::Binary) => "LONGBLOB",
+            (SqlDialect::Mysql, DataType::Json) => "JSON",
+            (SqlDialect::Mysql, DataType::String | DataType::Null) => "TEXT",
+
+            (SqlDialect::Sqlite, DataType::Integer | DataType::Boolean) => "INTEGER",
+            (SqlDialect::Sqlite, DataType::Decimal) => "REAL",
+            (SqlDialect::Sqlite, DataType::Binary) => "BLOB",
+            (SqlDialect::Sqlite, _) => "TEXT",
+
+            (SqlDialect::Mssql, DataType::Integer) => "BIGINT",
+            (SqlDialect::Mssql, DataType::Decimal) => "DECIMAL(18,6)",
+            (SqlDialect::Mssql, DataType::Boolean) => "BIT",
+            (SqlDialect::Mssql, DataType::Date) => "DATE",
+            (SqlDialect::Mssql, DataType::DateTime) => "DATETIME2",
+            (SqlDialect::Mssql, DataType::Binary) => "VARBINARY(MAX)",
+            (SqlDialect::Mssql, _) => "NVARCHAR(MAX)",
+
+            (SqlDialect::Duckdb, DataType::Integer) => "BIGINT",
+            (SqlDialect::Duckdb, DataType::Decimal) => "DOUBLE",
+            (SqlDialect::Duckdb, DataType::Boolean) => "BOOLEAN",
+            (SqlDialect::Duckdb, DataType::Date) => "DATE",
+            (SqlDialect::Duckdb, DataType::DateTime) => "TIMESTAMP",
+            (SqlDialect::Duckdb, DataType::Binary) => "BLOB",
+            (SqlDialect::Duckdb, DataType::Json) => "JSON",
+            (SqlDialect::Duckdb, DataType::String | DataType::Null) => "VARCHAR",
+        }
+    }
+}
+
+/// `schema` in `format`; `name` titles the JSON Schema and names the SQL table
+pub fn export_schema(schema: &Schema, name: &str, format: SchemaFormat) -> Result<String> {
+    let document = match format {
+        SchemaFormat::JsonSchema => json_schema(schema, name),
+        SchemaFormat::Avro => AvroTarget::schema_to_avro_json(schema),
+        SchemaFormat::Arrow => arrow_schema(schema),
+        SchemaFormat::Sql(dialect) => return Ok(create_table(schema, name, dialect)),
+    };
+    let mut text = serde_json::to_string_pretty(&document).map_err(|e| {
+        TinyEtlError::DataTransfer(format!("Failed to serialize the schema: {}", e))
+    })?;
+    text.push('\n');
+    Ok(text)
+}
+
+pub(crate) fn json_schema(schema: &Schema, name: &str) -> JsonValue {
+    let mut properties = serde_json::Map::new();
+    for column in &schema.columns {
+        let mut property = match column.data_type {
+            DataType::Integer => json!({ "type": "integer" }),
+            DataType::Decimal => json!({ "type": "number" }),
+            DataType::Boolean => json!({ "type": "boolean" }),
+            DataType::Date => json!({ "type": "string", "format": "date" }),
+            DataType::DateTime => json!({ "type": "string", "format": "date-time" }),
+            DataType::Binary => json!({ "type": "string", "contentEncoding": "base64" }),
+            // Any JSON value
+            DataType::Json => json!({}),
+            DataType::String | DataType::Null => json!({ "type": "string" }),
+        };
+        if column.nullable {
+            if let Some(data_type) = property.get("type").cloned() {
+                property["type"] = json!([data_type, "null"]);
+            }
+        }
+        properties.insert(column.name.clone(), property);
+    }
+    let required: Vec<&str> = schema
+        .columns
+        .iter()
+        .filter(|column| !column.nullable)
+        .map(|column| column.name.as_str())
+        .collect();
+
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": name,
+        "type": "object",
+        "properties": properties,
+        "required": required,
+    })
+}
+
+fn arrow_schema(schema: &Schema) -> JsonValue {
+    let fields: Vec<JsonValue> = schema
+        .to_arrow_schema()
+        .fields()
+        .iter()
+        .map(|field| arrow_field(field))
+        .collect();
+    json!({ "fields": fields })
+}
+
+fn arrow_field(field: &Field) -> JsonValue {
+    let data_type = match field.data_type() {
+        ArrowDataType::Int64 => json!({ "name": "int", "bitWidth": 64, "isSigned": true }),
+        ArrowDataType::Float64 => json!({ "name": "floatingpoint", "precision": "DOUBLE" }),
+        ArrowDataType::Boolean => json!({ "name": "bool" }),
+        ArrowDataType::Date64 => json!({ "name": "date", "unit": "MILLISECOND" }),
+        ArrowDataType::Timestamp(_, _) => json!({ "name": "timestamp", "unit": "NANOSECOND" }),
+        ArrowDataType::Binary => json!({ "name": "binary" }),
+        ArrowDataType::Null => json!({ "name": "null" }),
+        _ => json!({ "name": "utf8" }),
+    };
+    let mut json_field = json!({
+        "name": field.name(),
+        "nullable": field.is_nullable(),
+        "type": data_type,
+        "children": [],
+    });
+    if !field.metadata().is_empty() {
+        let mut metadata: Vec<(&String, &String)> = field.metadata().iter().collect();
+        metadata.sort();
+        json_field["metadata"] = metadata
+            .into_iter()
+            .map(|(key, value)| json!({ "key": key, "value": value }))
+            .collect();
+    }
+    json_field
+}
+
+fn create_table(schema: &Schema, table: &str, dialect: SqlDialect) -> String {
+    let style = dialect.quote_style();
+    let columns: Vec<String> = schema
+        .columns
+        .iter()
+        .map(|column| {
+            format!(
+                "  {} {}{}",
+                quote_ident(&column.name, style),
+                dialect.column_type(&column.data_type),
+                if column.nullable { "" } else { " NOT NULL" }
+            )
+        })
+        .collect();
+    format!(
+        "CREATE TABLE {} (\n{}\n);\n",
+        quote_table(table, style),
+        columns.join(",\n")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::Column;
+
+    fn schema() -> Schema {
+        Schema {
+            columns: vec![
+                Column {
+                    name: "id".to_string(),
+                    data_type: DataType::Integer,
+                    nullable: false,
+                },
+                Column {
+                    name: "ordered_at".to_string(),
+                    data_type: DataType::DateTime,
+                    nullable: true,
+                },
+                Column {
+                    name: "details".to_string(),
+                    data_type: DataType::Json,
+                    nullable: true,
+                },
+            ],
+            estimated_rows: None,
+            primary_key_candidate: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_schema_format() {
+        assert_eq!("jsonschema".parse(), Ok(SchemaFormat::JsonSchema));
+        assert_eq!("AVRO".parse(), Ok(SchemaFormat::Avro));
+        assert_eq!(
+            "sql:postgres".parse(),
+            Ok(SchemaFormat::Sql(SqlDialect::Postgres))
+        );
+        assert!("sql:oracle".parse::<SchemaFormat>().is_err());
+        assert!("xml".parse::<SchemaFormat>().is_err());
+    }
+
+    #[test]
+    fn test_export_schema_formats() {
+        let json_schema: JsonValue = serde_json::from_str(
+            &export_schema(&schema(), "orders", SchemaFormat::JsonSchema).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(json_schema["title"], "orders");
+        assert_eq!(json_schema["required"], json!(["id"]));
+        assert_eq!(
+            json_schema["properties"]["ordered_at"],
+            json!({ "type": ["string", "null"], "format": "date-time" })
+        );
+        assert_eq!(json_schema["properties"]["details"], json!({}));
+
+        let avro: JsonValue =
+            serde_json::from_str(&export_schema(&schema(), "orders", SchemaFormat::Avro).unwrap())
+                .unwrap();
+        assert_eq!(avro["fields"][0], json!({ "name": "id", "type": "long" }));
+
+        let arrow: JsonValue =
+            serde_json::from_str(&export_schema(&schema(), "orders", SchemaFormat::Arrow).unwrap())
+                .unwrap();
+        assert_eq!(
+            arrow["fields"][1]["type"],
+            json!({ "name": "timestamp", "unit": "NANOSECOND" })
+        );
+        assert_eq!(
+            arrow["fields"][2]["metadata"],
+            json!([{ "key": "tinyetl:type", "value": "json" }])
+        );
+
+        assert_eq!(
+            export_schema(
+                &schema(),
+                "sales.orders",
+                SchemaFormat::Sql(SqlDialect::Postgres)
+            )
+            .unwrap(),
+            "CREATE TABLE \"sales\".\"orders\" (\n  \"id\" BIGINT NOT NULL,\n  \"ordered_at\" TIMESTAMP WITH TIME ZONE,\n  \"details\" JSONB\n);\n"
+        );
+        assert!(
+            export_schema(&schema(), "orders", SchemaFormat::Sql(SqlDialect::Mysql))
+                .unwrap()
+                .contains("`details` JSON\n")
+        );
+    }
+}
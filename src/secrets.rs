@@ -31,6 +31,7 @@ pub fn resolve_secret(secret_id: &str) -> Result<String, String> {
     match env::var(&env_var) {
         Ok(value) => {
             info!("Successfully resolved secret for ID: {}", secret_id);
+            crate::redact::register_secret(&value);
             Ok(value)
         }
         Err(_) => Err(format!(
@@ -0,0 +1,666 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, warn};
+
+use crate::{
+    config::Config,
+    redact::redact,
+    transfer::{TransferObserver, TransferStats},
+    yaml_config::YamlConfig,
+    Result, TinyEtlError,
+};
+
+/// Largest request head (request line and headers) accepted
+const MAX_REQUEST_BYTES: usize = 64 * 1024;
+
+/// Finished runs kept for `GET /runs`; older ones are forgotten
+const MAX_FINISHED_RUNS: usize = 100;
+
+/// Runs listed by `GET /runs` without a `limit`
+const DEFAULT_RUN_LIMIT: usize = 20;
+
+/// Single-page dashboard served at `/`, polling the control API
+const DASHBOARD: &str = include_str!("dashboard.html");
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RunStatus {
+    Running,
+    Succeeded,
+    Failed,
+    Cancelled,
+}
+
+/// Progress of a run, updated after every batch
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct RunProgress {
+    pub rows: usize,
+    pub batches: usize,
+    pub rows_per_second: f64,
+}
+
+/// One triggered run of a job, as reported by `GET /runs/<id>`
+#[derive(Debug, Clone, Serialize)]
+pub struct RunRecord {
+    pub id: String,
+    pub job: String,
+    pub status: RunStatus,
+    pub started_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub finished_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub progress: Option<RunProgress>,
+    /// Transfer report of a finished run, as written by `--report-file`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stats: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip)]
+    cancel: CancellationToken,
+}
+
+type RunLog = Arc<Mutex<HashMap<String, RunRecord>>>;
+
+/// Observer recording the progress of a served run, for `GET /runs/<id>`
+pub struct RunObserver {
+    runs: RunLog,
+    id: String,
+}
+
+impl TransferObserver for RunObserver {
+    fn on_batch(&mut self, stats: &TransferStats) {
+        if let Some(record) = self.runs.lock().unwrap().get_mut(&self.id) {
+            record.progress = Some(RunProgress {
+                rows: stats.total_rows,
+                batches: stats.batches_processed,
+                rows_per_second: stats.rows_per_second,
+            });
+        }
+    }
+}
+
+/// The YAML jobs of a directory, each named after its file
+/// (`pipelines/orders.yaml` is the job `orders`). Files are read when a run
+/// starts, so edits apply without restarting the server.
+pub struct JobDirectory {
+    dir: PathBuf,
+}
+
+impl JobDirectory {
+    pub fn new(dir: &str) -> Result<Self> {
+        let dir = PathBuf::from(dir);
+        if !dir.is_dir() {
+            return Err(TinyEtlError::Configuration(format!(
+                "Job directory not found: {}",
+                dir.display()
+            )));
+        }
+        Ok(Self { dir })
+    }
+
+    /// Path of a job's file, `None` for unknown jobs and names that aren't
+    /// plain file stems
+    pub fn job_file(&self, job: &str) -> Option<PathBuf> {
+        let valid = !job.is_empty()
+            && job
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+            && !job.starts_with('.');
+        if !valid {
+            return None;
+        }
+        ["yaml", "yml"]
+            .iter()
+            .map(|ext| self.dir.join(format!("{}.{}", job, ext)))
+            .find(|path| path.is_file())
+    }
+
+    /// Names of the jobs in the directory, sorted
+    pub fn names(&self) -> Result<Vec<String>> {
+        let mut names: Vec<String> = std::fs::read_dir(&self.dir)?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.is_file()
+                    && matches!(
+                        path.extension().and_then(|ext| ext.to_str()),
+                        Some("yaml" | "yml")
+                    )
+            })
+            .filter_map(|path| Some(path.file_stem()?.to_str()?.to_string()))
+            .collect();
+        names.sort();
+        names.dedup();
+        Ok(names)
+    }
+
+    /// Load a job's configuration
+    pub fn load(&self, path: &Path) -> Result<Config> {
+        let path = path.to_string_lossy();
+        YamlConfig::from_file(&path)
+            .and_then(|yaml| yaml.into_config())
+            .map_err(|e| TinyEtlError::Configuration(format!("Invalid job file {}: {}", path, e)))
+    }
+}
+
+/// Status code and JSON body of a response
+type Response = (u16, serde_json::Value);
+
+/// HTTP front end starting jobs of a `JobDirectory` in the background and
+/// managing their runs
+pub struct Server<F> {
+    jobs: JobDirectory,
+    runs: RunLog,
+    run: Rc<F>,
+    /// Bearer token every control API request must carry
+    token: String,
+    /// Print the dashboard URL with the token once, for a generated token
+    /// nobody else knows
+    show_token: bool,
+}
+
+impl<F, Fut, E> Server<F>
+where
+    F: Fn(Config, RunObserver, CancellationToken) -> Fut + 'static,
+    Fut: Future<Output = std::result::Result<TransferStats, E>> + 'static,
+    E: Display,
+{
+    /// `run` executes a job's configuration, reporting progress to the
+    /// observer and stopping once the token is cancelled, and returns its stats.
+    /// Control API requests need an `Authorization: Bearer <token>` header.
+    pub fn new(jobs: JobDirectory, token: String, run: F) -> Self {
+        Self {
+            jobs,
+            runs: Arc::new(Mutex::new(HashMap::new())),
+            run: Rc::new(run),
+            token,
+            show_token: false,
+        }
+    }
+
+    /// Print the dashboard URL with the token to stderr once listening. It
+    /// never goes through the logs, where it would be masked and kept.
+    pub fn show_token(mut self) -> Self {
+        self.show_token = true;
+        self
+    }
+
+    /// Accept requests until the process stops. Runs are spawned on the
+    /// current thread, so this must be called inside a `LocalSet`. Addresses
+    /// other than loopback ones are refused unless `allow_remote` is set.
+    pub async fn listen(self, address: &str, allow_remote: bool) -> Result<()> {
+        let listener = TcpListener::bind(address).await.map_err(|e| {
+            TinyEtlError::Configuration(format!("Can't listen on {}: {}", address, e))
+        })?;
+        let local = listener.local_addr()?;
+        if !local.ip().is_loopback() && !allow_remote {
+            return Err(TinyEtlError::Configuration(format!(
+                "Refusing to listen on {}, which other hosts can reach; pass --allow-remote to serve jobs to them",
+                address
+            )));
+        }
+        info!(
+            "Serving jobs from {} on http://{}",
+            self.jobs.dir.display(),
+            address
+        );
+        info!("Dashboard: http://{}/", local);
+        if self.show_token {
+            eprintln!(
+                "Open the dashboard at http://{}/#token={}",
+                local, self.token
+            );
+        }
+
+        let server = Rc::new(self);
+        loop {
+            let (socket, peer) = listener.accept().await?;
+            let server = server.clone();
+            tokio::task::spawn_local(async move {
+                if let Err(e) = server.handle_connection(socket).await {
+                    warn!("Request from {} failed: {}", peer, e);
+                }
+            });
+        }
+    }
+
+    async fn handle_connection(&self, mut socket: TcpStream) -> Result<()> {
+        let request = read_request(&mut socket).await?;
+        let (status, content_type, body) = match request {
+            Some(request)
+                if request.method == "GET" && request.path.split('?').next() == Some("/") =>
+            {
+                (200, "text/html; charset=utf-8", DASHBOARD.to_string())
+            }
+            Some(request) if !self.authorized(request.authorization.as_deref()) => (
+                401,
+                "application/json",
+                serde_json::json!({ "error": "missing or wrong bearer token" }).to_string(),
+            ),
+            Some(request) => {
+                let (status, body) = self.handle(&request.method, &request.path);
+                (status, "application/json", body.to_string())
+            }
+            None => (
+                400,
+                "application/json",
+                serde_json::json!({ "error": "malformed request" }).to_string(),
+            ),
+        };
+        let response = format!(
+            "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            reason(status),
+            content_type,
+            body.len(),
+            body
+        );
+        socket.write_all(response.as_bytes()).await?;
+        socket.shutdown().await?;
+        Ok(())
+    }
+
+    /// Whether an `Authorization` header carries the server's token. Browsers
+    /// only send the header from scripts allowed to, so cross-site pages
+    /// can't start runs either.
+    pub fn authorized(&self, authorization: Option<&str>) -> bool {
+        let Some(token) = authorization.and_then(|value| value.strip_prefix("Bearer ")) else {
+            return false;
+        };
+        // Compare every byte, so the time taken doesn't tell how much matched
+        token.len() == self.token.len()
+            && token
+                .bytes()
+                .zip(self.token.bytes())
+                .fold(0, |diff, (a, b)| diff | (a ^ b))
+                == 0
+    }
+
+    /// Route an authorized request to the control API (the dashboard at `/` is served
+    /// by the connection handler):
+    ///
+    /// - `GET /jobs`: the jobs with their latest run
+    /// - `POST /run/<job>`: start a job
+    /// - `GET /runs?limit=N`: the most recent runs, newest first
+    /// - `GET /runs/<id>`: a run with its live progress
+    /// - `POST /runs/<id>/cancel`: stop a run at the next batch boundary
+    /// - `GET /runs/<id>/report`: the transfer report of a finished run
+    pub fn handle(&self, method: &str, path: &str) -> Response {
+        let (path, query) = path.split_once('?').unwrap_or((path, ""));
+        let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+        match (method, segments.as_slice()) {
+            ("GET", ["jobs"]) => self.list_jobs(),
+            ("POST", ["run", job]) => self.start(job),
+            ("GET", ["runs"]) => self.list_runs(query),
+            ("GET", ["runs", id]) => self.with_run(id, |record| {
+                (200, serde_json::to_value(record).unwrap_or_default())
+            }),
+            ("POST", ["runs", id, "cancel"]) => self.with_run(id, |record| {
+                if record.status != RunStatus::Running {
+                    return (
+                        409,
+                        serde_json::json!({ "error": format!("run {} is not running", id) }),
+                    );
+                }
+                info!("→ Cancelling run {} of job {}", id, record.job);
+                record.cancel.cancel();
+                (202, serde_json::to_value(record).unwrap_or_default())
+            }),
+            ("GET", ["runs", id, "report"]) => self.with_run(id, |record| match &record.stats {
+                Some(stats) => (200, stats.clone()),
+                None => (
+                    409,
+                    serde_json::json!({ "error": format!("run {} has no report yet", id) }),
+                ),
+            }),
+            _ => (404, serde_json::json!({ "error": "not found" })),
+        }
+    }
+
+    fn with_run(&self, id: &str, respond: impl FnOnce(&RunRecord) -> Response) -> Response {
+        match self.runs.lock().unwrap().get(id) {
+            Some(record) => respond(record),
+            None => (
+                404,
+                serde_json::json!({ "error": format!("no run {}", id) }),
+            ),
+        }
+    }
+
+    fn list_jobs(&self) -> Response {
+        let names = match self.jobs.names() {
+            Ok(names) => names,
+            Err(e) => return (500, serde_json::json!({ "error": redact(&e.to_string()) })),
+        };
+        let runs = self.runs.lock().unwrap();
+        let jobs: Vec<serde_json::Value> = names
+            .into_iter()
+            .map(|name| {
+                let last_run = runs
+                    .values()
+                    .filter(|record| record.job == name)
+                    .max_by_key(|record| record.started_at);
+                serde_json::json!({ "name": name, "last_run": last_run })
+            })
+            .collect();
+        (200, serde_json::json!({ "jobs": jobs }))
+    }
+
+    fn list_runs(&self, query: &str) -> Response {
+        let limit = url::form_urlencoded::parse(query.as_bytes())
+            .find(|(key, _)| key == "limit")
+            .map(|(_, value)| value.parse::<usize>());
+        let limit = match limit {
+            None => DEFAULT_RUN_LIMIT,
+            Some(Ok(limit)) => limit,
+            Some(Err(_)) => {
+                return (
+                    400,
+                    serde_json::json!({ "error": "limit must be a number" }),
+                )
+            }
+        };
+
+        let runs = self.runs.lock().unwrap();
+        let mut records: Vec<&RunRecord> = runs.values().collect();
+        records.sort_by_key(|record| std::cmp::Reverse(record.started_at));
+        records.truncate(limit);
+        (200, serde_json::json!({ "runs": records }))
+    }
+
+    /// Start a job in the background and return its run id
+    fn start(&self, job: &str) -> Response {
+        let Some(path) = self.jobs.job_file(job) else {
+            return (
+                404,
+                serde_json::json!({ "error": format!("no job {}", job) }),
+            );
+        };
+        let mut config = match self.jobs.load(&path) {
+            Ok(config) => config,
+            Err(e) => return (400, serde_json::json!({ "error": redact(&e.to_string()) })),
+        };
+        config.job_name.get_or_insert_with(|| job.to_string());
+
+        let record = RunRecord {
+            id: uuid::Uuid::new_v4().to_string(),
+            job: job.to_string(),
+            status: RunStatus::Running,
+            started_at: Utc::now(),
+            finished_at: None,
+            progress: None,
+            stats: None,
+            error: None,
+            cancel: CancellationToken::new(),
+        };
+        let id = record.id.clone();
+        let cancel = record.cancel.clone();
+        {
+            let mut runs = self.runs.lock().unwrap();
+            prune_finished_runs(&mut runs);
+            runs.insert(id.clone(), record.clone());
+        }
+        info!("→ Run {} of job {} started", id, job);
+
+        let runs = self.runs.clone();
+        let run = self.run.clone();
+        let observer = RunObserver {
+            runs: runs.clone(),
+            id: id.clone(),
+        };
+        tokio::task::spawn_local(async move {
+            let outcome = run(config, observer, cancel).await;
+            let mut runs = runs.lock().unwrap();
+            let Some(record) = runs.get_mut(&id) else {
+                return;
+            };
+            record.finished_at = Some(Utc::now());
+            match outcome {
+                Ok(stats) => {
+                    info!(
+                        "Run {} of job {} completed: {} rows",
+                        id, record.job, stats.total_rows
+                    );
+                    record.status = if stats.cancelled {
+                        RunStatus::Cancelled
+                    } else {
+                        RunStatus::Succeeded
+                    };
+                    record.stats = Some(stats.to_json());
+                }
+                Err(e) => {
+                    error!("Run {} of job {} failed: {}", id, record.job, e);
+                    record.status = RunStatus::Failed;
+                    record.error = Some(redact(&e.to_string()));
+                }
+            }
+        });
+
+        (202, serde_json::to_value(record).unwrap_or_default())
+    }
+}
+
+/// Forget the oldest finished runs beyond `MAX_FINISHED_RUNS`
+fn prune_finished_runs(runs: &mut HashMap<String, RunRecord>) {
+    let mut finished: Vec<(DateTime<Utc>, String)> = runs
+        .values()
+        .filter(|record| record.status != RunStatus::Running)
+        .map(|record| (record.started_at, record.id.clone()))
+        .collect();
+    if finished.len() <= MAX_FINISHED_RUNS {
+        return;
+    }
+    finished.sort();
+    for (_, id) in &finished[..finished.len() - MAX_FINISHED_RUNS] {
+        runs.remove(id);
+    }
+}
+
+/// Request line and the headers the server looks at
+struct Request {
+    method: String,
+    path: String,
+    authorization: Option<String>,
+}
+
+/// Read a request and return its method, path and `Authorization` header.
+/// The body, if any, is read and discarded.
+async fn read_request(socket: &mut TcpStream) -> Result<Option<Request>> {
+    let mut request = Vec::new();
+    let mut buffer = [0; 4096];
+    let head_end = loop {
+        if let Some(end) = request.windows(4).position(|w| w == b"\r\n\r\n") {
+            break end;
+        }
+        if request.len() > MAX_REQUEST_BYTES {
+            return Ok(None);
+        }
+        let read = socket.read(&mut buffer).await?;
+        if read == 0 {
+            return Ok(None);
+        }
+        request.extend_from_slice(&buffer[..read]);
+    };
+
+    let head = String::from_utf8_lossy(&request[..head_end]).into_owned();
+    let header = |wanted: &str| {
+        head.lines()
+            .skip(1)
+            .filter_map(|line| line.split_once(':'))
+            .find(|(name, _)| name.trim().eq_ignore_ascii_case(wanted))
+            .map(|(_, value)| value.trim().to_string())
+    };
+    let content_length = header("content-length")
+        .and_then(|value| value.parse::<usize>().ok())
+        .unwrap_or(0)
+        .min(MAX_REQUEST_BYTES);
+    let mut body_read = request.len() - head_end - 4;
+    while body_read < content_length {
+        let read = socket.read(&mut buffer).await?;
+        if read == 0 {
+            break;
+        }
+        body_read += read;
+    }
+
+    let mut parts = head.lines().next().unwrap_or_default().split_whitespace();
+    match (parts.next(), parts.next()) {
+        (Some(method), Some(path)) => Ok(Some(Request {
+            method: method.to_string(),
+            path: path.to_string(),
+            authorization: header("authorization"),
+        })),
+        _ => Ok(None),
+    }
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        202 => "Accepted",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        409 => "Conflict",
+        500 => "Internal Server Error",
+        _ => "Error",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn job_dir() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("orders.yaml"),
+            "version: 1\nsource:\n  uri: orders.csv\ntarget:\n  uri: orders.json\n",
+        )
+        .unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_jobs_are_named_after_their_files() {
+        let dir = job_dir();
+        let jobs = JobDirectory::new(dir.path().to_str().unwrap()).unwrap();
+        assert!(jobs.job_file("orders").is_some());
+        assert!(jobs.job_file("missing").is_none());
+        assert!(jobs.job_file("../orders").is_none());
+        assert!(JobDirectory::new("no/such/dir").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_api_needs_the_token_and_remote_listening_an_opt_in() {
+        let dir = job_dir();
+        let jobs = JobDirectory::new(dir.path().to_str().unwrap()).unwrap();
+        let server = Server::new(jobs, "secret".to_string(), |_: Config, _, _| async move {
+            Ok::<_, String>(TransferStats::default())
+        });
+        assert!(server.authorized(Some("Bearer secret")));
+        assert!(!server.authorized(Some("Bearer secreT")));
+        assert!(!server.authorized(Some("Bearer secret2")));
+        assert!(!server.authorized(Some("secret")));
+        assert!(!server.authorized(None));
+
+        let error = server.listen("0.0.0.0:0", false).await.unwrap_err();
+        assert!(error.to_string().contains("--allow-remote"));
+    }
+
+    #[tokio::test]
+    async fn test_run_is_started_and_reported() {
+        let dir = job_dir();
+        let jobs = JobDirectory::new(dir.path().to_str().unwrap()).unwrap();
+        let server = Server::new(
+            jobs,
+            "secret".to_string(),
+            |config: Config, _, _| async move {
+                assert_eq!(config.target, "orders.json");
+                Ok::<_, String>(TransferStats {
+                    total_rows: 3,
+                    ..Default::default()
+                })
+            },
+        );
+
+        tokio::task::LocalSet::new()
+            .run_until(async {
+                let (status, body) = server.handle("POST", "/run/orders");
+                assert_eq!(status, 202);
+                assert_eq!(body["status"], "running");
+                let id = body["id"].as_str().unwrap().to_string();
+
+                let path = format!("/runs/{}", id);
+                while server.handle("GET", &path).1["status"] == "running" {
+                    tokio::task::yield_now().await;
+                }
+                let (status, body) = server.handle("GET", &path);
+                assert_eq!(status, 200);
+                assert_eq!(body["status"], "succeeded");
+                assert_eq!(body["stats"]["total_rows"], 3);
+
+                let (status, report) = server.handle("GET", &format!("{}/report", path));
+                assert_eq!(status, 200);
+                assert_eq!(report["total_rows"], 3);
+                assert_eq!(server.handle("POST", &format!("{}/cancel", path)).0, 409);
+
+                let (_, body) = server.handle("GET", "/jobs");
+                assert_eq!(body["jobs"][0]["name"], "orders");
+                assert_eq!(body["jobs"][0]["last_run"]["id"], id.as_str());
+                let (_, body) = server.handle("GET", "/runs?limit=5");
+                assert_eq!(body["runs"].as_array().unwrap().len(), 1);
+
+                assert_eq!(server.handle("POST", "/run/missing").0, 404);
+                assert_eq!(server.handle("GET", "/runs/unknown").0, 404);
+            })
+            .await;
+    }
+
+    #[tokio::test]
+    async fn test_running_runs_report_progress_and_can_be_cancelled() {
+        let dir = job_dir();
+        let jobs = JobDirectory::new(dir.path().to_str().unwrap()).unwrap();
+        let server = Server::new(
+            jobs,
+            "secret".to_string(),
+            |_config: Config, mut observer: RunObserver, cancel: CancellationToken| async move {
+                let mut stats = TransferStats::default();
+                while !cancel.is_cancelled() {
+                    stats.total_rows += 10;
+                    stats.batches_processed += 1;
+                    observer.on_batch(&stats);
+                    tokio::task::yield_now().await;
+                }
+                stats.cancelled = true;
+                Ok::<_, String>(stats)
+            },
+        );
+
+        tokio::task::LocalSet::new()
+            .run_until(async {
+                let (_, body) = server.handle("POST", "/run/orders");
+                let path = format!("/runs/{}", body["id"].as_str().unwrap());
+                while server.handle("GET", &path).1["progress"].is_null() {
+                    tokio::task::yield_now().await;
+                }
+                assert_eq!(server.handle("GET", &path).1["progress"]["batches"], 1);
+
+                assert_eq!(server.handle("POST", &format!("{}/cancel", path)).0, 202);
+                while server.handle("GET", &path).1["status"] == "running" {
+                    tokio::task::yield_now().await;
+                }
+                assert_eq!(server.handle("GET", &path).1["status"], "cancelled");
+            })
+            .await;
+    }
+}
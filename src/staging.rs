@@ -0,0 +1,218 @@
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::OnceLock;
+use tracing::{info, warn};
+
+use crate::{hashing::Hasher, Result, TinyEtlError};
+
+/// Staging area of the process, set up by `configure` or on first use
+static STAGING: OnceLock<StagingArea> = OnceLock::new();
+
+/// Directory holding the temporary files of a run, such as downloaded
+/// sources. Downloads are stored under the SHA-256 of their content, so
+/// fetching the same file twice keeps one copy. The area is removed once the run succeeds and kept when it fails,
+/// so the files can be inspected.
+#[derive(Debug)]
+pub struct StagingArea {
+    dir: PathBuf,
+    max_bytes: Option<u64>,
+    used_bytes: AtomicU64,
+    next_file: AtomicUsize,
+}
+
+impl StagingArea {
+    /// Create a new area under `parent`, holding at most `max_bytes`
+    pub fn create(parent: &Path, max_bytes: Option<u64>) -> Result<Self> {
+        let dir = parent.join(format!(
+            "tinyetl-staging-{}-{}",
+            std::process::id(),
+            &uuid::Uuid::new_v4().simple().to_string()[..8]
+        ));
+        std::fs::create_dir_all(&dir).map_err(|e| {
+            TinyEtlError::Configuration(format!(
+                "Can't create staging directory {}: {}",
+                dir.display(),
+                e
+            ))
+        })?;
+        Ok(Self {
+            dir,
+            max_bytes,
+            used_bytes: AtomicU64::new(0),
+            next_file: AtomicUsize::new(0),
+        })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Bytes currently counted against the size cap
+    pub fn used_bytes(&self) -> u64 {
+        self.used_bytes.load(Ordering::SeqCst)
+    }
+
+    /// Count `bytes` about to be written against the size cap, failing
+    /// when they don't fit
+    pub fn reserve(&self, bytes: u64) -> Result<()> {
+        let used = self.used_bytes.fetch_add(bytes, Ordering::SeqCst) + bytes;
+        match self.max_bytes {
+            Some(max_bytes) if used > max_bytes => {
+                self.release(bytes);
+                Err(TinyEtlError::DataTransfer(format!(
+                    "Staging directory {} would exceed its {} byte limit (--staging-max-bytes)",
+                    self.dir.display(),
+                    max_bytes
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Give back bytes whose files were removed
+    pub fn release(&self, bytes: u64) {
+        let _ = self
+            .used_bytes
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |used| {
+                Some(used.saturating_sub(bytes))
+            });
+    }
+
+    /// Store `content` under its SHA-256, reusing the file an identical
+    /// earlier download left
+    pub fn store(&self, content: &[u8], extension: Option<&str>) -> Result<PathBuf> {
+        let path = self.content_path(&Hasher::sha256_hex(content), extension);
+        if !path.exists() {
+            self.reserve(content.len() as u64)?;
+            let temp = self.temp_path(None);
+            std::fs::write(&temp, content)?;
+            std::fs::rename(&temp, &path)?;
+        }
+        Ok(path)
+    }
+
+    /// Move a file a tool wrote to a `temp_path` to its content address,
+    /// counting it against the size cap
+    pub fn adopt(&self, temp: &Path, extension: Option<&str>) -> Result<PathBuf> {
+        let content = std::fs::read(temp)?;
+        let path = self.content_path(&Hasher::sha256_hex(&content), extension);
+        if path.exists() {
+            std::fs::remove_file(temp)?;
+        } else {
+            if let Err(e) = self.reserve(content.len() as u64) {
+                let _ = std::fs::remove_file(temp);
+                return Err(e);
+            }
+            std::fs::rename(temp, &path)?;
+        }
+        Ok(path)
+    }
+
+    /// Fresh path in the area for a file written by something else, e.g. scp
+    pub fn temp_path(&self, extension: Option<&str>) -> PathBuf {
+        let n = self.next_file.fetch_add(1, Ordering::SeqCst);
+        match extension {
+            Some(ext) => self.dir.join(format!("tmp-{}.{}", n, ext)),
+            None => self.dir.join(format!("tmp-{}", n)),
+        }
+    }
+
+    fn content_path(&self, hash: &str, extension: Option<&str>) -> PathBuf {
+        match extension {
+            Some(ext) => self.dir.join(format!("{}.{}", hash, ext)),
+            None => self.dir.join(hash),
+        }
+    }
+
+    /// Remove the area after a successful run, or keep it for debugging
+    pub fn finish(&self, succeeded: bool) {
+        if succeeded {
+            if let Err(e) = std::fs::remove_dir_all(&self.dir) {
+                warn!(
+                    "Failed to remove staging directory {}: {}",
+                    self.dir.display(),
+                    e
+                );
+            }
+        } else if std::fs::read_dir(&self.dir).is_ok_and(|mut entries| entries.next().is_some()) {
+            info!("Staging files kept in {}", self.dir.display());
+        } else {
+            let _ = std::fs::remove_dir(&self.dir);
+        }
+    }
+}
+
+/// Set up the staging area of the process under `dir` (the system temp
+/// directory by default). Must be called before anything is staged.
+pub fn configure(dir: Option<&str>, max_bytes: Option<u64>) -> Result<()> {
+    let parent = dir.map_or_else(std::env::temp_dir, PathBuf::from);
+    let area = StagingArea::create(&parent, max_bytes)?;
+    if let Err(area) = STAGING.set(area) {
+        let _ = std::fs::remove_dir(area.dir());
+        return Err(TinyEtlError::Configuration(
+            "The staging directory is already set up".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// The staging area of the process, created in the system temp directory
+/// when `configure` wasn't called
+pub fn staging() -> Result<&'static StagingArea> {
+    if let Some(area) = STAGING.get() {
+        return Ok(area);
+    }
+    let area = StagingArea::create(&std::env::temp_dir(), None)?;
+    if let Err(area) = STAGING.set(area) {
+        // Another thread set it up first
+        let _ = std::fs::remove_dir(area.dir());
+    }
+    Ok(STAGING.get().expect("staging area was just set"))
+}
+
+/// Clean up the staging area at the end of the process, if one was used
+pub fn finish(succeeded: bool) {
+    if let Some(area) = STAGING.get() {
+        area.finish(succeeded);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_identical_content_is_stored_once() {
+        let parent = tempfile::tempdir().unwrap();
+        let area = StagingArea::create(parent.path(), None).unwrap();
+
+        let first = area.store(b"id,name\n1,ann\n", Some("csv")).unwrap();
+        let second = area.store(b"id,name\n1,ann\n", Some("csv")).unwrap();
+        assert_eq!(first, second);
+        assert!(first.to_string_lossy().ends_with(".csv"));
+        assert_eq!(area.used_bytes(), 14);
+
+        let temp = area.temp_path(Some("csv"));
+        std::fs::write(&temp, b"id,name\n1,ann\n").unwrap();
+        assert_eq!(area.adopt(&temp, Some("csv")).unwrap(), first);
+        assert!(!temp.exists());
+
+        area.finish(true);
+        assert!(!area.dir().exists());
+    }
+
+    #[test]
+    fn test_size_cap_and_retain_on_failure() {
+        let parent = tempfile::tempdir().unwrap();
+        let area = StagingArea::create(parent.path(), Some(10)).unwrap();
+
+        area.store(b"12345678", None).unwrap();
+        assert!(area.store(b"too much", None).is_err());
+        assert_eq!(area.used_bytes(), 8);
+        area.release(8);
+        area.reserve(10).unwrap();
+
+        area.finish(false);
+        assert!(area.dir().exists());
+    }
+}
@@ -0,0 +1,255 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+
+use crate::{
+    connectors::sql_params::ParamType,
+    schema::{Column, DataType, Schema},
+    Result, TinyEtlError,
+};
+
+/// What to do when the source schema differs from the previous run
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SchemaDriftPolicy {
+    /// Log the differences and continue
+    #[default]
+    Warn,
+    /// Abort the run before anything is written
+    Fail,
+}
+
+impl fmt::Display for SchemaDriftPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SchemaDriftPolicy::Warn => write!(f, "warn"),
+            SchemaDriftPolicy::Fail => write!(f, "fail"),
+        }
+    }
+}
+
+impl std::str::FromStr for SchemaDriftPolicy {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "warn" => Ok(SchemaDriftPolicy::Warn),
+            "fail" => Ok(SchemaDriftPolicy::Fail),
+            _ => Err("Invalid schema drift policy. Valid values: warn, fail"),
+        }
+    }
+}
+
+/// State of a job kept between runs in its state file (JSON)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RunState {
+    /// Source columns seen by the last completed run
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema: Option<Vec<Column>>,
+    /// Files read so far by `--new-files-only`, by path relative to the folder
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub files: BTreeMap<String, FileFingerprint>,
+    /// Highest value of the `--incremental-column` loaded so far
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub watermark: Option<Watermark>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+/// What identifies a version of a file: it is read again once either changes
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileFingerprint {
+    pub size: u64,
+    pub modified: DateTime<Utc>,
+}
+
+/// Highest value of an incremental column, in the text form bound to the
+/// next run's query with its type
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Watermark {
+    pub column: String,
+    pub value: String,
+    #[serde(default, rename = "type")]
+    pub param_type: ParamType,
+}
+
+impl RunState {
+    /// Load the state file; a missing file is the state before the first run
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = std::fs::read_to_string(path)?;
+        serde_json::from_str(&content).map_err(|e| {
+            TinyEtlError::Configuration(format!("Invalid state file {}: {}", path.display(), e))
+        })
+    }
+
+    /// Write the state file, replacing it atomically
+    pub fn save<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        self.updated_at = Some(Utc::now());
+        let mut temp = path.as_os_str().to_owned();
+        temp.push(".tmp");
+        std::fs::write(&temp, serde_json::to_string_pretty(self)?)?;
+        std::fs::rename(&temp, path)?;
+        Ok(())
+    }
+
+    /// Differences between the recorded schema and `schema`, if any
+    pub fn schema_drift(&self, schema: &Schema) -> Option<SchemaDrift> {
+        let previous = self.schema.as_ref()?;
+        let drift = SchemaDrift::between(previous, &schema.columns);
+        (!drift.is_empty()).then_some(drift)
+    }
+
+    pub fn record_schema(&mut self, schema: &Schema) {
+        self.schema = Some(schema.columns.clone());
+    }
+
+    /// The watermark recorded for `column`; one kept for another column
+    /// doesn't apply, so the next run reads everything again
+    pub fn watermark_for(&self, column: &str) -> Option<&Watermark> {
+        self.watermark
+            .as_ref()
+            .filter(|watermark| watermark.column == column)
+    }
+}
+
+/// A column whose type changed between runs
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TypeChange {
+    pub column: String,
+    pub from: DataType,
+    pub to: DataType,
+}
+
+/// Columns added, removed or retyped since the previous run
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct SchemaDrift {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub type_changed: Vec<TypeChange>,
+}
+
+impl SchemaDrift {
+    pub fn between(previous: &[Column], current: &[Column]) -> Self {
+        let find = |columns: &[Column], name: &str| {
+            columns
+                .iter()
+                .find(|c| c.name == name)
+                .map(|c| c.data_type.clone())
+        };
+
+        let mut drift = SchemaDrift::default();
+        for column in current {
+            match find(previous, &column.name) {
+                None => drift.added.push(column.name.clone()),
+                // A column that was all null in the sample has no real type
+                Some(DataType::Null) => {}
+                Some(_) if column.data_type == DataType::Null => {}
+                Some(from) if from != column.data_type => drift.type_changed.push(TypeChange {
+                    column: column.name.clone(),
+                    from,
+                    to: column.data_type.clone(),
+                }),
+                Some(_) => {}
+            }
+        }
+        drift.removed = previous
+            .iter()
+            .filter(|c| find(current, &c.name).is_none())
+            .map(|c| c.name.clone())
+            .collect();
+        drift
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.type_changed.is_empty()
+    }
+}
+
+impl fmt::Display for SchemaDrift {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = Vec::new();
+        if !self.added.is_empty() {
+            parts.push(format!("added {}", self.added.join(", ")));
+        }
+        if !self.removed.is_empty() {
+            parts.push(format!("removed {}", self.removed.join(", ")));
+        }
+        if !self.type_changed.is_empty() {
+            let changes: Vec<String> = self
+                .type_changed
+                .iter()
+                .map(|c| format!("{} ({:?} → {:?})", c.column, c.from, c.to))
+                .collect();
+            parts.push(format!("type changed {}", changes.join(", ")));
+        }
+        write!(f, "{}", parts.join("; "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn column(name: &str, data_type: DataType) -> Column {
+        Column {
+            name: name.to_string(),
+            data_type,
+            nullable: true,
+        }
+    }
+
+    #[test]
+    fn test_schema_drift_between_runs() {
+        let previous = vec![
+            column("id", DataType::Integer),
+            column("amount", DataType::Integer),
+            column("legacy", DataType::String),
+            column("note", DataType::Null),
+        ];
+        let current = vec![
+            column("id", DataType::Integer),
+            column("amount", DataType::Decimal),
+            column("note", DataType::String),
+            column("region", DataType::String),
+        ];
+
+        let drift = SchemaDrift::between(&previous, &current);
+        assert_eq!(drift.added, vec!["region"]);
+        assert_eq!(drift.removed, vec!["legacy"]);
+        assert_eq!(drift.type_changed.len(), 1);
+        assert_eq!(
+            drift.to_string(),
+            "added region; removed legacy; type changed amount (Integer → Decimal)"
+        );
+        assert!(SchemaDrift::between(&current, &current).is_empty());
+    }
+
+    #[test]
+    fn test_state_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("job.state.json");
+        let schema = Schema {
+            columns: vec![column("id", DataType::Integer)],
+            estimated_rows: None,
+            primary_key_candidate: None,
+        };
+
+        let mut state = RunState::load(&path).unwrap();
+        assert!(state.schema_drift(&schema).is_none());
+        state.record_schema(&schema);
+        state.save(&path).unwrap();
+
+        let state = RunState::load(&path).unwrap();
+        assert!(state.schema_drift(&schema).is_none());
+        let mut changed = schema.clone();
+        changed.columns.push(column("name", DataType::String));
+        assert_eq!(state.schema_drift(&changed).unwrap().added, vec!["name"]);
+    }
+}
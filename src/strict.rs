@@ -0,0 +1,180 @@
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+
+use crate::{
+    schema::{DataType, Row, Schema, Value},
+    Result, TinyEtlError,
+};
+
+/// How a source handles values it can't read exactly. By default they are
+/// coerced (rounded, read as text or as NULL); with `--strict` each one is
+/// an error naming the column and value.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Coercion {
+    strict: bool,
+}
+
+impl Coercion {
+    pub fn strict() -> Self {
+        Self { strict: true }
+    }
+
+    pub fn is_strict(&self) -> bool {
+        self.strict
+    }
+
+    /// Decimal of a floating point value. Floats no decimal can hold (NaN,
+    /// infinity, above 7.9e28) are read as text; in strict mode they, and
+    /// floats the decimal would round, are errors.
+    pub fn decimal_from_f64(&self, f: f64, column: &str) -> Result<Value> {
+        match Decimal::try_from(f) {
+            Ok(d) if !self.strict || d.to_f64() == Some(f) => Ok(Value::Decimal(d)),
+            Ok(d) => Err(self.error(
+                column,
+                &f.to_string(),
+                &format!("would be rounded to {}", d),
+            )),
+            Err(_) => self.lossy(
+                column,
+                &f.to_string(),
+                "has no decimal equivalent",
+                Value::String(f.to_string()),
+            ),
+        }
+    }
+
+    /// Decimal of a single precision float, checked against its own precision
+    pub fn decimal_from_f32(&self, f: f32, column: &str) -> Result<Value> {
+        match Decimal::try_from(f) {
+            Ok(d) if !self.strict || d.to_f32() == Some(f) => Ok(Value::Decimal(d)),
+            _ => self.decimal_from_f64(f as f64, column),
+        }
+    }
+
+    /// `fallback` in place of a value that can't be read exactly, e.g. text
+    /// for a type TinyETL doesn't know or NULL for a timestamp out of range;
+    /// an error in strict mode
+    pub fn lossy(
+        &self,
+        column: &str,
+        value: &str,
+        problem: &str,
+        fallback: Value,
+    ) -> Result<Value> {
+        if self.strict {
+            Err(self.error(column, value, problem))
+        } else {
+            Ok(fallback)
+        }
+    }
+
+    fn error(&self, column: &str, value: &str, problem: &str) -> TinyEtlError {
+        TinyEtlError::DataValidation(format!(
+            "Column '{}' value {} {} (--strict)",
+            column, value, problem
+        ))
+    }
+}
+
+/// Checks under `--strict` that every value fits the type of its output
+/// column, since targets coerce or drop values that don't (a Parquet
+/// integer column stores text as NULL)
+pub struct StrictCheck {
+    columns: Vec<(String, DataType)>,
+}
+
+impl StrictCheck {
+    pub fn new(schema: &Schema) -> Self {
+        Self {
+            columns: schema
+                .columns
+                .iter()
+                .map(|column| (column.name.clone(), column.data_type.clone()))
+                .collect(),
+        }
+    }
+
+    pub fn check_row(&self, row: &Row) -> Result<()> {
+        for (name, data_type) in &self.columns {
+            let Some(value) = row.get(name) else {
+                continue;
+            };
+            if !Self::fits(value, data_type) {
+                return Err(TinyEtlError::DataValidation(format!(
+                    "Column '{}' expects {:?}, got '{}' (--strict)",
+                    name,
+                    data_type,
+                    value.to_string_for_arrow().unwrap_or_default()
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    fn fits(value: &Value, data_type: &DataType) -> bool {
+        matches!(
+            (value, data_type),
+            (Value::Null, _)
+                | (_, DataType::String | DataType::Null)
+                | (Value::Integer(_), DataType::Integer | DataType::Decimal)
+                | (Value::Decimal(_), DataType::Decimal)
+                | (Value::Boolean(_), DataType::Boolean)
+                | (Value::Date(_), DataType::Date | DataType::DateTime)
+                | (Value::Json(_) | Value::String(_), DataType::Json)
+                | (Value::Bytes(_), DataType::Binary)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schema::Column;
+
+    #[test]
+    fn test_coercions_fail_in_strict_mode() {
+        let lenient = Coercion::default();
+        assert_eq!(
+            lenient.decimal_from_f64(f64::NAN, "ratio").unwrap(),
+            Value::String("NaN".to_string())
+        );
+        assert!(Coercion::strict()
+            .decimal_from_f64(f64::NAN, "ratio")
+            .is_err());
+        assert_eq!(
+            Coercion::strict().decimal_from_f64(1.25, "ratio").unwrap(),
+            Value::Decimal(Decimal::new(125, 2))
+        );
+
+        let err = Coercion::strict()
+            .lossy(
+                "shape",
+                "POINT(1 2)",
+                "has an unsupported type",
+                Value::Null,
+            )
+            .unwrap_err();
+        assert!(err.to_string().contains("Column 'shape' value POINT(1 2)"));
+    }
+
+    #[test]
+    fn test_values_must_fit_their_column() {
+        let check = StrictCheck::new(&Schema {
+            columns: vec![Column {
+                name: "qty".to_string(),
+                data_type: DataType::Integer,
+                nullable: true,
+            }],
+            estimated_rows: None,
+            primary_key_candidate: None,
+        });
+        let row = |value| Row::from([("qty".to_string(), value)]);
+
+        assert!(check.check_row(&row(Value::Integer(3))).is_ok());
+        assert!(check.check_row(&row(Value::Null)).is_ok());
+        let err = check
+            .check_row(&row(Value::String("3 boxes".to_string())))
+            .unwrap_err();
+        assert!(err.to_string().contains("Column 'qty' expects Integer"));
+    }
+}
@@ -1,52 +1,368 @@
+use chrono::Utc;
 use indicatif::{ProgressBar, ProgressStyle};
-use std::time::Instant;
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
 use tracing::{info, warn};
 
 use crate::{
-    config::Config,
-    connectors::{Source, Target},
-    schema::{Schema, SchemaFile},
-    transformer::Transformer,
-    Result,
+    checkpoint::Checkpoint,
+    codecs::ColumnCodecs,
+    config::{Config, IfExists, RowErrorPolicy},
+    connectors::{
+        connection_url::split_table, create_source_from_url_with_type_and_options, Source, Target,
+    },
+    dead_letter::DeadLetterFile,
+    incremental::WatermarkTracker,
+    join::HashJoiner,
+    limits::{RecordLimits, SizeLimits},
+    null_fallback::NullFiller,
+    output_names::{OutputNames, RenamedTarget},
+    overflow::IntegerRangeCheck,
+    pipeline::{BatchReader, SourceBatch, TransformStage},
+    provenance::RowHasher,
+    reshape::Reshaper,
+    router::{RouteStats, Router},
+    row_number::RowNumberer,
+    scd2::{Scd2Config, Scd2Merger, IS_CURRENT_COLUMN, VALID_FROM_COLUMN, VALID_TO_COLUMN},
+    schema::{Row, Schema, SchemaFile, SchemaMode, UniqueValues},
+    secrets::process_connection_string,
+    state::{RunState, SchemaDrift, SchemaDriftPolicy},
+    strict::{Coercion, StrictCheck},
+    transformer::{TransformOptions, Transformer},
+    Result, TinyEtlError,
 };
 
 pub struct TransferEngine;
 
+/// What the engine keeps about a batch between reading and writing it
+struct BatchProgress {
+    started: Instant,
+    /// Source rows in the batch, before any were skipped or reshaped
+    rows_read: usize,
+    refined_row_count: Option<usize>,
+    bytes_read: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default)]
 pub struct TransferStats {
     pub total_rows: usize,
     pub total_time: std::time::Duration,
     pub rows_per_second: f64,
     pub batches_processed: usize,
+    /// Bytes consumed from the source, when the source can report it
+    pub bytes_read: Option<u64>,
+    /// Bytes written to the target, when the target can report it
+    pub bytes_written: Option<u64>,
+    /// Time spent reading batches from the source
+    pub read_time: Duration,
+    /// Time spent on schema validation and transformations
+    pub transform_time: Duration,
+    /// Time spent writing batches and finalizing the target
+    pub write_time: Duration,
+    pub batch_latencies: BatchLatencyHistogram,
+    /// Whether the transfer was cancelled before the source was exhausted
+    pub cancelled: bool,
+    /// Rows written to each route target (empty without routing)
+    pub routes: Vec<RouteStats>,
+    /// Source schema changes since the run recorded in the state file
+    pub schema_drift: Option<SchemaDrift>,
+    /// Rows dropped by `RowErrorPolicy::Skip`
+    pub rows_skipped: usize,
+    /// Values cut by `OversizePolicy::Truncate`
+    pub values_truncated: usize,
+    /// Rows written to the dead letter file by `OversizePolicy::DeadLetter`
+    pub rows_dead_lettered: usize,
+    /// Rows the target rejected, isolated by `bisect_failed_batches`
+    pub rows_rejected: usize,
+    /// NULLs replaced by their column's `null_fallback`
+    pub nulls_replaced: usize,
+    /// Merkle root of the rows' `row_hash` values
+    pub row_hash_root: Option<String>,
+}
+
+impl TransferStats {
+    /// Render the stats as a JSON report
+    pub fn to_json(&self) -> serde_json::Value {
+        let secs = self.total_time.as_secs_f64();
+        let per_second = |bytes: Option<u64>| {
+            bytes
+                .filter(|_| secs > 0.0)
+                .map(|bytes| bytes as f64 / secs)
+        };
+        let millis = |d: Option<Duration>| d.map(|d| d.as_secs_f64() * 1000.0);
+
+        serde_json::json!({
+            "total_rows": self.total_rows,
+            "batches_processed": self.batches_processed,
+            "total_time_secs": secs,
+            "rows_per_second": self.rows_per_second,
+            "cancelled": self.cancelled,
+            "routes": self.routes,
+            "schema_drift": self.schema_drift,
+            "rows_skipped": self.rows_skipped,
+            "values_truncated": self.values_truncated,
+            "rows_dead_lettered": self.rows_dead_lettered,
+            "rows_rejected": self.rows_rejected,
+            "nulls_replaced": self.nulls_replaced,
+            "row_hash_root": self.row_hash_root,
+            "bytes_read": self.bytes_read,
+            "bytes_written": self.bytes_written,
+            "bytes_read_per_second": per_second(self.bytes_read),
+            "bytes_written_per_second": per_second(self.bytes_written),
+            "phases": {
+                "read_secs": self.read_time.as_secs_f64(),
+                "transform_secs": self.transform_time.as_secs_f64(),
+                "write_secs": self.write_time.as_secs_f64(),
+            },
+            "batch_latency_ms": {
+                "p50": millis(self.batch_latencies.percentile(50.0)),
+                "p95": millis(self.batch_latencies.percentile(95.0)),
+                "max": millis(self.batch_latencies.max()),
+                "histogram": self
+                    .batch_latencies
+                    .buckets()
+                    .into_iter()
+                    .map(|(label, count)| (label, serde_json::Value::from(count)))
+                    .collect::<serde_json::Map<_, _>>(),
+            },
+        })
+    }
+
+    /// One-line summary of where time was spent and how many bytes moved
+    pub fn breakdown(&self) -> String {
+        let mut parts = vec![format!(
+            "read {:.2}s, transform {:.2}s, write {:.2}s",
+            self.read_time.as_secs_f64(),
+            self.transform_time.as_secs_f64(),
+            self.write_time.as_secs_f64()
+        )];
+        if let Some(bytes) = self.bytes_read {
+            parts.push(format!("{} read", format_bytes(bytes)));
+        }
+        if let Some(bytes) = self.bytes_written {
+            parts.push(format!("{} written", format_bytes(bytes)));
+        }
+        if let (Some(p50), Some(p95)) = (
+            self.batch_latencies.percentile(50.0),
+            self.batch_latencies.percentile(95.0),
+        ) {
+            parts.push(format!(
+                "batch p50 {:.1}ms, p95 {:.1}ms",
+                p50.as_secs_f64() * 1000.0,
+                p95.as_secs_f64() * 1000.0
+            ));
+        }
+        parts.join(" | ")
+    }
+}
+
+/// Histogram of per-batch latencies (read + transform + write)
+#[derive(Debug, Clone, Default)]
+pub struct BatchLatencyHistogram {
+    latencies: Vec<Duration>,
+}
+
+impl BatchLatencyHistogram {
+    /// Upper bounds (in milliseconds) of the reported buckets
+    pub const BUCKET_BOUNDS_MS: [u64; 7] = [1, 10, 50, 100, 500, 1_000, 5_000];
+
+    pub fn record(&mut self, latency: Duration) {
+        self.latencies.push(latency);
+    }
+
+    pub fn count(&self) -> usize {
+        self.latencies.len()
+    }
+
+    /// Latency at the given percentile (0-100), using nearest-rank
+    pub fn percentile(&self, percentile: f64) -> Option<Duration> {
+        if self.latencies.is_empty() {
+            return None;
+        }
+        let mut sorted = self.latencies.clone();
+        sorted.sort();
+        let rank = ((percentile / 100.0) * sorted.len() as f64).ceil() as usize;
+        Some(sorted[rank.clamp(1, sorted.len()) - 1])
+    }
+
+    pub fn max(&self) -> Option<Duration> {
+        self.latencies.iter().max().copied()
+    }
+
+    /// Bucket labels with the number of batches that fell into each
+    pub fn buckets(&self) -> Vec<(String, usize)> {
+        let mut counts = vec![0usize; Self::BUCKET_BOUNDS_MS.len() + 1];
+        for latency in &self.latencies {
+            let ms = latency.as_millis() as u64;
+            let index = Self::BUCKET_BOUNDS_MS
+                .iter()
+                .position(|bound| ms < *bound)
+                .unwrap_or(Self::BUCKET_BOUNDS_MS.len());
+            counts[index] += 1;
+        }
+
+        let mut labels: Vec<String> = Self::BUCKET_BOUNDS_MS
+            .iter()
+            .map(|bound| format!("<{}ms", bound))
+            .collect();
+        labels.push(format!(
+            ">={}ms",
+            Self::BUCKET_BOUNDS_MS[Self::BUCKET_BOUNDS_MS.len() - 1]
+        ));
+        labels.into_iter().zip(counts).collect()
+    }
+}
+
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+/// Hooks for applications embedding TinyETL as a library, e.g. to drive their
+/// own progress UI or implement custom checkpointing
+pub trait TransferObserver: Send {
+    /// Called after each batch is written, with the stats accumulated so far
+    fn on_batch(&mut self, _stats: &TransferStats) {}
+
+    /// Called when the transfer fails, with the rows of the batch being processed
+    /// (empty when the failure is not tied to a batch, e.g. a read error)
+    fn on_error(&mut self, _error: &TinyEtlError, _batch: &[Row]) {}
 }
 
+/// Observer that ignores all events
+pub struct NoopObserver;
+
+impl TransferObserver for NoopObserver {}
+
 impl TransferEngine {
     pub async fn execute(
+        config: &Config,
+        source: Box<dyn Source>,
+        target: Box<dyn Target>,
+    ) -> Result<TransferStats> {
+        Self::execute_with_observer(config, source, target, &mut NoopObserver).await
+    }
+
+    /// Execute a transfer, reporting per-batch progress and errors to `observer`
+    pub async fn execute_with_observer(
+        config: &Config,
+        source: Box<dyn Source>,
+        target: Box<dyn Target>,
+        observer: &mut dyn TransferObserver,
+    ) -> Result<TransferStats> {
+        Self::execute_cancellable(config, source, target, observer, &CancellationToken::new()).await
+    }
+
+    /// Execute a transfer that stops at the next batch boundary once `cancel` is
+    /// triggered. The target is still finalized and the partial stats are returned
+    /// with `cancelled` set.
+    pub async fn execute_cancellable(
         config: &Config,
         mut source: Box<dyn Source>,
         mut target: Box<dyn Target>,
+        observer: &mut dyn TransferObserver,
+        cancel: &CancellationToken,
     ) -> Result<TransferStats> {
         let start_time = Instant::now();
 
+        if config.truncate && !matches!(config.if_exists, IfExists::Append | IfExists::Truncate) {
+            return Err(TinyEtlError::Configuration(format!(
+                "--truncate cannot be combined with --if-exists {}",
+                config.if_exists
+            )));
+        }
+        let if_exists = config.if_exists_policy();
+        let resume_from = match &config.checkpoint_file {
+            Some(path) => Self::load_checkpoint(config, path)?,
+            None => None,
+        };
+
         // Step 1: Connect to source and target
         info!("→ Connecting to source: {}", config.source);
+        if let Some(seconds) = config.batch_timeout {
+            source.set_query_timeout(Duration::from_secs(seconds));
+        }
+        if config.read_only {
+            source.use_read_only_session()?;
+        }
+        if config.consistent_snapshot {
+            source.use_consistent_snapshot()?;
+        }
+        match config.fetch_size {
+            Some(0) => {
+                return Err(TinyEtlError::Configuration(
+                    "--fetch-size must be at least 1".to_string(),
+                ))
+            }
+            Some(rows) => source.set_fetch_size(rows),
+            None => {}
+        }
+        if let Some(column) = &config.keyset_column {
+            source.set_keyset_column(column)?;
+        }
+        if config.strict {
+            source.set_coercion(Coercion::strict());
+        }
+        source.set_size_limits(SizeLimits::from_config(config));
         source.connect().await?;
+        if source.is_up_to_date() {
+            info!("→ Nothing new to read since the last run; target left untouched");
+            return Ok(TransferStats {
+                total_time: start_time.elapsed(),
+                ..Default::default()
+            });
+        }
+
+        // File targets may be recreated on connect, so SCD2 history has to be
+        // read from them first
+        let scd2_file_history = if config.scd2 {
+            Self::read_scd2_file_history(config).await?
+        } else {
+            None
+        };
 
         info!("→ Connecting to target: {}", config.target);
+        if config.bisect_failed_batches {
+            target.use_atomic_batches()?;
+        }
         target.connect().await?;
 
         // Step 2: Infer or load schema
-        let schema = if let Some(schema_file_path) = &config.schema_file {
-            info!("→ Loading schema from file: {}", schema_file_path);
-            let schema_file = SchemaFile::from_file(schema_file_path)?;
+        let schema_file = Self::declared_schema(config)?;
+        let schema = if let Some(schema_file) = &schema_file {
             // Even when using a schema file, we need to initialize the source
-            // (e.g., for pagination state in SQLite)
-            let _ = source.infer_schema(1).await?;
+            // (e.g., for pagination state in SQLite); declared mode samples no rows
+            let sample_size = match config.schema_mode {
+                SchemaMode::Declared => 0,
+                SchemaMode::Inferred => 1,
+            };
+            let _ = source.infer_schema(sample_size).await?;
             schema_file.to_schema()?
         } else {
             info!("→ Inferring schema from source...");
             source.infer_schema(1000).await?
         };
         info!("→ {} columns detected", schema.columns.len());
+        if let Some(column) = &config.incremental_column {
+            if !schema.columns.iter().any(|c| &c.name == column) {
+                return Err(TinyEtlError::Configuration(format!(
+                    "Incremental column '{}' is not a source column",
+                    column
+                )));
+            }
+        }
 
         // Step 3: Handle preview mode
         if let Some(preview_rows) = config.preview {
@@ -58,14 +374,54 @@ impl TransferEngine {
             return Self::handle_dry_run(source, target, &schema, config).await;
         }
 
+        // Compare the source schema with the one recorded by the previous run
+        let mut run_state = match &config.state_file {
+            Some(path) => Some(RunState::load(path)?),
+            None => None,
+        };
+        let schema_drift = run_state
+            .as_ref()
+            .and_then(|state| state.schema_drift(&schema));
+        if let Some(drift) = &schema_drift {
+            match config.on_schema_drift {
+                SchemaDriftPolicy::Warn => {
+                    warn!("→ Source schema changed since the last run: {}", drift)
+                }
+                SchemaDriftPolicy::Fail => {
+                    return Err(TinyEtlError::DataValidation(format!(
+                        "source schema changed since the last run: {}",
+                        drift
+                    )));
+                }
+            }
+        }
+        let source_schema = schema.clone();
+        // Codec columns are decoded as they are read and encoded again as they are written
+        let codecs = ColumnCodecs::new(&config.codecs, &schema)?;
+
+        // Joined columns are added before the transform so it can use them
+        let joiner = match &config.join {
+            Some(join) => Some(HashJoiner::load(join, config.batch_size).await?),
+            None => None,
+        };
+        let schema = match &joiner {
+            Some(joiner) => joiner.transform_schema(&schema),
+            None => schema,
+        };
+
         // Step 5: Initialize transformer and determine final schema
-        let mut transformer = Transformer::new(&config.transform)?;
+        let mut transformer =
+            Transformer::with_options(&config.transform, &TransformOptions::from_config(config))?;
         let final_schema = if transformer.is_enabled() {
             info!("→ Transformation enabled");
 
             // Read a small sample to infer the transformed schema
             source.reset().await?;
             let sample_batch = source.read_batch(1).await?;
+            let sample_batch = match &joiner {
+                Some(joiner) => joiner.join_batch(sample_batch),
+                None => sample_batch,
+            };
 
             if !sample_batch.is_empty() {
                 transformer.transform_batch(&sample_batch)?;
@@ -74,7 +430,10 @@ impl TransferEngine {
                         "→ Schema updated by transformations: {} columns",
                         transform_schema.columns.len()
                     );
-                    transform_schema.clone()
+                    // Source columns keep their place, new ones follow them
+                    let mut transform_schema = transform_schema.clone();
+                    transform_schema.reorder_columns(&Self::column_names(&schema));
+                    transform_schema
                 } else {
                     schema.clone()
                 }
@@ -85,46 +444,158 @@ impl TransferEngine {
             schema.clone()
         };
 
+        // Row numbers are assigned after the transform, before any reshaping
+        let mut row_numberer = config
+            .row_number
+            .as_ref()
+            .map(RowNumberer::new)
+            .transpose()?;
+        let final_schema = match &row_numberer {
+            Some(numberer) => numberer.transform_schema(&final_schema),
+            None => final_schema,
+        };
+
+        // Pivot/unpivot run after the transform and reshape the output schema
+        let mut reshaper = Reshaper::new(config.pivot.as_ref(), config.unpivot.as_ref())?;
+        let final_schema = if reshaper.is_enabled() {
+            let reshaped = reshaper.transform_schema(&final_schema);
+            info!("→ Reshaping enabled: {} columns", reshaped.columns.len());
+            reshaped
+        } else {
+            final_schema
+        };
+
+        // Excluded columns (e.g. identity columns the target fills in) are never written
+        let final_schema = Self::exclude_from_schema(final_schema, &config.exclude_columns);
+        let final_schema = Self::order_schema(final_schema, &config.column_order)?;
+        let null_filler = NullFiller::new(&config.null_fallback, &final_schema)?;
+        let integer_ranges = IntegerRangeCheck::new(&final_schema);
+        let strict_check = config.strict.then(|| StrictCheck::new(&final_schema));
+
+        // Rows are hashed as written, after every other change to their values
+        let mut row_hasher = match &config.row_hash {
+            Some(column) => {
+                if config.scd2 || config.bisect_failed_batches || config.checkpoint_file.is_some() {
+                    return Err(TinyEtlError::Configuration(
+                        "--row-hash can't be combined with --scd2, --bisect-failed-batches or --checkpoint-file"
+                            .to_string(),
+                    ));
+                }
+                Some(RowHasher::new(column, &final_schema)?)
+            }
+            None => None,
+        };
+        let final_schema = match &row_hasher {
+            Some(hasher) => hasher.transform_schema(&final_schema),
+            None => final_schema,
+        };
+
+        // Output names only apply in the target; everything above uses the source names
+        let output_names = OutputNames::new(
+            config.output_header_case,
+            &config.rename_columns,
+            &final_schema,
+        )?;
+        if let Some(names) = &output_names {
+            if config.scd2 {
+                return Err(TinyEtlError::Configuration(
+                    "--output-header-case and --rename-column can't be combined with --scd2"
+                        .to_string(),
+                ));
+            }
+            target = Box::new(RenamedTarget::new(target, names.clone()));
+        }
+
         // Step 6: Extract table name from target
         let table_name = Self::extract_table_name(&config.target);
 
         // Step 7: Handle append-first logic or truncate mode
         let table_exists = target.exists(&table_name).await?;
 
-        if table_exists {
-            if config.truncate {
-                info!("→ Truncating existing target: {}", table_name);
-                target.truncate(&table_name).await?;
-                // After truncating, we need to create the table again
-                target.create_table(&table_name, &final_schema).await?;
-            } else if target.supports_append() {
-                info!("→ Appending to existing target: {}", table_name);
-                // For append mode, we still need to set the schema for the target
-                target.create_table(&table_name, &final_schema).await?;
-            } else {
-                // Target exists but doesn't support append - must truncate
-                info!(
-                    "→ Target exists but doesn't support append, truncating: {}",
-                    table_name
-                );
-                target.truncate(&table_name).await?;
-                // After truncating, we need to create the table again
-                target.create_table(&table_name, &final_schema).await?;
+        // A resumed run appends to what the interrupted one wrote
+        let if_exists = match &resume_from {
+            Some(checkpoint) => {
+                Self::check_resumable(target.as_ref(), &table_name, table_exists, checkpoint)?;
+                IfExists::Append
             }
+            None => if_exists,
+        };
+
+        // In SCD2 mode the merged history is written once all incoming rows
+        // have been seen
+        let mut scd2 = if config.scd2 {
+            let existing = match scd2_file_history {
+                Some(rows) => rows,
+                None if !target.supports_scd2_merge() => {
+                    return Err(TinyEtlError::Configuration(
+                        "--scd2 is only supported for file, SQLite, PostgreSQL and MySQL targets"
+                            .to_string(),
+                    ))
+                }
+                None if table_exists => {
+                    let rows = Self::read_target_rows(config).await?;
+                    Self::check_scd2_columns(&table_name, &rows)?;
+                    rows
+                }
+                None => Vec::new(),
+            };
+            info!(
+                "→ SCD2 mode: {} existing rows in {}",
+                existing.len(),
+                table_name
+            );
+            let scd2_config = Scd2Config {
+                key_columns: config.key_columns.clone(),
+                track_columns: config.track_columns.clone(),
+            };
+            Some(Scd2Merger::new(scd2_config, existing, Utc::now())?)
         } else {
-            // Step 8: Create target table with final schema if it doesn't exist
-            info!("→ Creating target table: {}", table_name);
-            target.create_table(&table_name, &final_schema).await?;
+            None
+        };
+
+        if scd2.is_some() {
+            // The table is (re)created once the history has been merged
+        } else {
+            Self::prepare_target(
+                target.as_mut(),
+                &table_name,
+                &final_schema,
+                table_exists,
+                if_exists,
+            )
+            .await?;
         }
 
+        let mut router = if config.routes.is_empty() {
+            None
+        } else {
+            if scd2.is_some() {
+                return Err(TinyEtlError::Configuration(
+                    "Routing cannot be combined with SCD2 mode".to_string(),
+                ));
+            }
+            let router = Router::connect(
+                &config.routes,
+                &final_schema,
+                &TransformOptions::from_config(config),
+                if_exists,
+                output_names.as_ref(),
+            )
+            .await?;
+            Some(router)
+        };
+
         // Step 9: Transfer data
-        let estimated_rows = source.estimated_row_count().await?.unwrap_or(0);
+        let mut estimated_rows = source.estimated_row_count().await?.unwrap_or(0);
+        if let Some(limit) = config.limit {
+            estimated_rows = estimated_rows.min(limit);
+        }
         info!("→ Copying {} rows", estimated_rows);
 
         let progress_bar = if estimated_rows > 0 {
             let pb = ProgressBar::new(estimated_rows as u64);
             let style = ProgressStyle::default_bar()
-                .template("{bar:40.cyan/blue} {percent}% ({pos}/{len}) {msg}")
+                .template("{bar:40.cyan/blue} {percent}% ({pos}/{len}) ETA {eta} {msg}")
                 .expect("Failed to create progress bar template")
                 .progress_chars("█▇▆▅▄▃▂▁  ");
             pb.set_style(style);
@@ -133,69 +604,489 @@ impl TransferEngine {
             None
         };
 
-        // Load schema file for validation if provided
-        let schema_file = if let Some(schema_file_path) = &config.schema_file {
-            Some(SchemaFile::from_file(schema_file_path)?)
-        } else {
-            None
+        let mut stats = TransferStats::default();
+        let limits = RecordLimits::from_config(config)?;
+        let mut dead_letter = match &config.dead_letter_file {
+            Some(path) => Some(DeadLetterFile::create(path)?),
+            None => None,
         };
+        // Routed rows and SCD2 changes are kept even when the primary target
+        // rejects the batch, so retrying its halves would apply them twice
+        if config.bisect_failed_batches && (router.is_some() || scd2.is_some()) {
+            return Err(TinyEtlError::Configuration(
+                "--bisect-failed-batches cannot be combined with routes or --scd2".to_string(),
+            ));
+        }
+        // Position of the next row sent to the target, for rejected row reports
+        let mut rows_sent = resume_from
+            .as_ref()
+            .map_or(0, |c| c.rows_written + c.rows_rejected);
 
-        let mut total_rows = 0;
-        let mut batches_processed = 0;
+        let mut watermark = config
+            .incremental_column
+            .as_deref()
+            .map(WatermarkTracker::new);
+        let mut unique_values = UniqueValues::default();
 
         source.reset().await?;
 
-        while source.has_more() {
-            let mut batch = source.read_batch(config.batch_size).await?;
-            if batch.is_empty() {
-                break;
+        let resumed_rows = resume_from.as_ref().map_or(0, |c| c.rows_written);
+        if let Some(resumed) = &resume_from {
+            // The rows already written are skipped by position
+            if !source.has_stable_order().await? {
+                return Err(TinyEtlError::Configuration(
+                    "Can't resume: the source reads rows in no fixed order, so the rows already \
+                     written can't be skipped; start the transfer over without --resume"
+                        .to_string(),
+                ));
+            }
+            info!(
+                "→ Resuming after {} rows written by the interrupted run",
+                resumed.rows_written
+            );
+            Self::skip_rows(
+                source.as_mut(),
+                resumed.rows_read,
+                config.batch_size,
+                &mut watermark,
+            )
+            .await?;
+        }
+        let mut checkpoint = config
+            .checkpoint_file
+            .as_ref()
+            .map(|_| resume_from.unwrap_or_else(|| Checkpoint::new(config)));
+
+        // With --parallelism the source is read ahead on its own task and
+        // batches are transformed by a pool of Lua workers, while this loop
+        // writes them in the order they were read
+        let (mut reader, mut stage) = if config.parallelism > 1 {
+            let reader = BatchReader::prefetch(
+                source,
+                config.batch_size,
+                config.limit.map(|limit| limit.saturating_sub(resumed_rows)),
+                config.batch_timeout,
+                config.parallelism,
+                cancel.clone(),
+            );
+            let stage = if transformer.is_enabled() {
+                TransformStage::workers(
+                    &config.transform,
+                    &TransformOptions::from_config(config),
+                    config.parallelism,
+                )?
+            } else {
+                TransformStage::inline()
+            };
+            (reader, stage)
+        } else {
+            let reader = BatchReader::inline(source, config.batch_timeout);
+            (reader, TransformStage::inline())
+        };
+
+        let mut reading_done = false;
+        loop {
+            // Read and prepare batches while the transform stage has room
+            if !reading_done && stage.has_room() {
+                if !reader.has_more() {
+                    reading_done = true;
+                    continue;
+                }
+                if cancel.is_cancelled() && !stats.cancelled {
+                    warn!("→ Transfer cancelled after {} rows", stats.total_rows);
+                    stats.cancelled = true;
+                }
+                if stats.cancelled && reader.stops_on_cancel() {
+                    reading_done = true;
+                    continue;
+                }
+
+                let remaining = config.limit.map_or(usize::MAX, |limit| {
+                    limit.saturating_sub(resumed_rows + stats.total_rows)
+                });
+                if remaining == 0 {
+                    info!("→ Stopping after --limit of {} rows", stats.total_rows);
+                    reading_done = true;
+                    continue;
+                }
+
+                let batch_start = Instant::now();
+                let batch_size = config.batch_size.min(remaining);
+                let next = match reader.next(batch_size).await {
+                    Ok(next) => next,
+                    Err(e) => {
+                        observer.on_error(&e, &[]);
+                        return Err(e);
+                    }
+                };
+                stats.read_time += batch_start.elapsed();
+                let Some(SourceBatch {
+                    rows: mut batch,
+                    refined_row_count,
+                    bytes_read,
+                }) = next.filter(|next| !next.rows.is_empty())
+                else {
+                    reading_done = true;
+                    continue;
+                };
+                let rows_read = batch.len();
+                if let Some(watermark) = watermark.as_mut() {
+                    watermark.observe(&batch);
+                }
+
+                let transform_start = Instant::now();
+
+                if let Some(limits) = &limits {
+                    match limits.check_batch(&mut batch, dead_letter.as_mut()) {
+                        Ok(outcome) => {
+                            stats.values_truncated += outcome.values_truncated;
+                            stats.rows_dead_lettered += outcome.rows_dead_lettered;
+                        }
+                        Err(e) => {
+                            observer.on_error(&e, &batch);
+                            return Err(e);
+                        }
+                    }
+                }
+
+                if let Some(codecs) = &codecs {
+                    let decoded =
+                        Self::retain_valid_rows(config, &mut batch, |row| codecs.decode_row(row));
+                    match decoded {
+                        Ok(skipped) => stats.rows_skipped += skipped,
+                        Err(e) => {
+                            observer.on_error(&e, &batch);
+                            return Err(e);
+                        }
+                    }
+                }
+
+                // Apply schema validation and defaults if schema file is provided.
+                // Null fallbacks come first so NOT NULL columns they cover pass.
+                if let Some(ref schema_file) = schema_file {
+                    if let Some(filler) = &null_filler {
+                        stats.nulls_replaced += filler.fill(&mut batch);
+                    }
+                    match Self::validate_batch(schema_file, &mut unique_values, config, &mut batch)
+                    {
+                        Ok(skipped) => stats.rows_skipped += skipped,
+                        Err(e) => {
+                            observer.on_error(&e, &batch);
+                            return Err(e);
+                        }
+                    }
+                }
+
+                let batch = match &joiner {
+                    Some(joiner) => joiner.join_batch(batch),
+                    None => batch,
+                };
+
+                let progress = BatchProgress {
+                    started: batch_start,
+                    rows_read,
+                    refined_row_count,
+                    bytes_read,
+                };
+                stage.submit(&mut transformer, batch, progress)?;
+                stats.transform_time += transform_start.elapsed();
+                continue;
             }
 
-            // Apply schema validation and defaults if schema file is provided
-            if let Some(ref schema_file) = schema_file {
-                for row in &mut batch {
-                    schema_file.validate_and_transform_row(row)?;
+            // Take the oldest batch, once transformed, and write it
+            let transform_start = Instant::now();
+            let (transformed, progress) = match stage.next().await {
+                Ok(Some(next)) => next,
+                Ok(None) => break,
+                Err(e) => {
+                    observer.on_error(&e, &[]);
+                    return Err(e);
+                }
+            };
+            let mut processed_batch = match transformed {
+                Ok(transformed) => transformed,
+                Err((e, batch)) => {
+                    observer.on_error(&e, &batch);
+                    return Err(e);
+                }
+            };
+
+            if let Some(numberer) = row_numberer.as_mut() {
+                if let Err(e) = numberer.process_batch(&mut processed_batch) {
+                    observer.on_error(&e, &processed_batch);
+                    return Err(e);
                 }
             }
 
-            // Apply transformations
-            let processed_batch = if transformer.is_enabled() {
-                transformer.transform_batch(&batch)?
+            let mut processed_batch = if reshaper.is_enabled() {
+                reshaper.process_batch(processed_batch)
             } else {
-                batch
+                processed_batch
+            };
+            // Transforms such as unpivot can emit more rows than were read
+            let remaining = config.limit.map_or(usize::MAX, |limit| {
+                limit.saturating_sub(resumed_rows + stats.total_rows)
+            });
+            processed_batch.truncate(remaining);
+            Self::exclude_from_rows(&mut processed_batch, &config.exclude_columns);
+            if let Some(filler) = &null_filler {
+                stats.nulls_replaced += filler.fill(&mut processed_batch);
+            }
+            if let Some(check) = &integer_ranges {
+                let checked = Self::retain_valid_rows(config, &mut processed_batch, |row| {
+                    check.check_row(row)
+                });
+                match checked {
+                    Ok(skipped) => stats.rows_skipped += skipped,
+                    Err(e) => {
+                        observer.on_error(&e, &processed_batch);
+                        return Err(e);
+                    }
+                }
+            }
+            if let Some(check) = &strict_check {
+                let checked = Self::retain_valid_rows(config, &mut processed_batch, |row| {
+                    check.check_row(row)
+                });
+                match checked {
+                    Ok(skipped) => stats.rows_skipped += skipped,
+                    Err(e) => {
+                        observer.on_error(&e, &processed_batch);
+                        return Err(e);
+                    }
+                }
+            }
+            if let Some(codecs) = &codecs {
+                let encoded = Self::retain_valid_rows(config, &mut processed_batch, |row| {
+                    codecs.encode_row(row)
+                });
+                match encoded {
+                    Ok(skipped) => stats.rows_skipped += skipped,
+                    Err(e) => {
+                        observer.on_error(&e, &processed_batch);
+                        return Err(e);
+                    }
+                }
+            }
+            if let Some(hasher) = row_hasher.as_mut() {
+                hasher.hash_rows(&mut processed_batch);
+            }
+
+            stats.transform_time += transform_start.elapsed();
+
+            let write_start = Instant::now();
+            let written =
+                Self::write_rows(target.as_mut(), &mut scd2, &mut router, &processed_batch).await;
+            let written = match written {
+                Err(e) if config.bisect_failed_batches && !processed_batch.is_empty() => {
+                    warn!(
+                        "→ Batch failed, isolating the rows the target rejects: {}",
+                        e
+                    );
+                    Self::write_bisecting(
+                        target.as_mut(),
+                        &mut scd2,
+                        &mut router,
+                        &processed_batch,
+                        rows_sent,
+                        &mut dead_letter,
+                    )
+                    .await
+                    .map(|(written, rejected)| {
+                        stats.rows_rejected += rejected;
+                        if let Some(checkpoint) = checkpoint.as_mut() {
+                            checkpoint.rows_rejected += rejected;
+                        }
+                        written
+                    })
+                }
+                written => written,
             };
+            let written = match written {
+                Ok(written) => written,
+                Err(e) => {
+                    observer.on_error(&e, &processed_batch);
+                    return Err(e);
+                }
+            };
+            rows_sent += processed_batch.len();
+            stats.write_time += write_start.elapsed();
+            stats.batch_latencies.record(progress.started.elapsed());
+            stats.total_rows += written;
+            stats.batches_processed += 1;
+            if let (Some(path), Some(checkpoint)) = (&config.checkpoint_file, checkpoint.as_mut()) {
+                checkpoint.rows_read += progress.rows_read;
+                checkpoint.rows_written += written;
+                checkpoint.bytes_written = target.bytes_written();
+                checkpoint.save(path)?;
+            }
+            stats.total_time = start_time.elapsed();
+            stats.rows_per_second = stats.total_rows as f64 / stats.total_time.as_secs_f64();
+            stats.bytes_read = progress.bytes_read;
 
-            let written = target.write_batch(&processed_batch).await?;
-            total_rows += written;
-            batches_processed += 1;
+            observer.on_batch(&stats);
 
             if let Some(ref pb) = progress_bar {
-                pb.set_position(total_rows as u64);
+                // Sampled estimates are refined as reading progresses
+                if let Some(refined) = progress.refined_row_count {
+                    pb.set_length(refined.max(stats.total_rows) as u64);
+                }
+                pb.set_position(stats.total_rows as u64);
                 pb.set_message(format!(
                     "{}k rows/sec",
-                    (total_rows as f64 / start_time.elapsed().as_secs_f64() / 1000.0) as u64
+                    (stats.rows_per_second / 1000.0) as u64
                 ));
             }
         }
+        let mut source = reader.into_source().await?;
+
+        // Pivot holds rows back until every input row has been seen
+        if reshaper.is_buffering() {
+            let write_start = Instant::now();
+            let mut pivoted = reshaper.finish();
+            if let Some(limit) = config.limit {
+                pivoted.truncate(limit.saturating_sub(stats.total_rows));
+            }
+            Self::exclude_from_rows(&mut pivoted, &config.exclude_columns);
+            if let Some(filler) = &null_filler {
+                stats.nulls_replaced += filler.fill(&mut pivoted);
+            }
+            if let Some(check) = &integer_ranges {
+                stats.rows_skipped +=
+                    Self::retain_valid_rows(config, &mut pivoted, |row| check.check_row(row))?;
+            }
+            if let Some(check) = &strict_check {
+                stats.rows_skipped +=
+                    Self::retain_valid_rows(config, &mut pivoted, |row| check.check_row(row))?;
+            }
+            if let Some(codecs) = &codecs {
+                stats.rows_skipped +=
+                    Self::retain_valid_rows(config, &mut pivoted, |row| codecs.encode_row(row))?;
+            }
+            if let Some(hasher) = row_hasher.as_mut() {
+                hasher.hash_rows(&mut pivoted);
+            }
+            for chunk in pivoted.chunks(config.batch_size.max(1)) {
+                match Self::write_rows(target.as_mut(), &mut scd2, &mut router, chunk).await {
+                    Ok(written) => stats.total_rows += written,
+                    Err(e) => {
+                        observer.on_error(&e, chunk);
+                        return Err(e);
+                    }
+                }
+            }
+            stats.write_time += write_start.elapsed();
+        }
 
         if let Some(pb) = progress_bar {
-            pb.finish_with_message("Complete");
+            if stats.cancelled {
+                pb.abandon_with_message("Cancelled");
+            } else {
+                pb.set_length(stats.total_rows as u64);
+                pb.finish_with_message("Complete");
+            }
         }
 
         // Step 10: Finalize
-        target.finalize().await?;
+        let finalize_start = Instant::now();
+        if let Some(merger) = scd2 {
+            let written = Self::write_scd2_history(
+                target.as_mut(),
+                &table_name,
+                &final_schema,
+                merger,
+                config,
+            )
+            .await;
+            if let Err(e) = written {
+                observer.on_error(&e, &[]);
+                return Err(e);
+            }
+        }
+        if let Some(router) = router.as_mut() {
+            if let Err(e) = router.finalize().await {
+                observer.on_error(&e, &[]);
+                return Err(e);
+            }
+            stats.routes = router.stats();
+            for route in &stats.routes {
+                info!("→ Route {}: {} rows", route.target, route.rows);
+            }
+        }
+        if let Err(e) = target.finalize().await {
+            observer.on_error(&e, &[]);
+            return Err(e);
+        }
+        if let Err(e) = source.commit().await {
+            observer.on_error(&e, &[]);
+            return Err(e);
+        }
+        if config.analyze && !stats.cancelled {
+            info!("→ Refreshing table statistics...");
+            let mut analyzed = target.analyze(&table_name).await;
+            if let (true, Some(router)) = (analyzed.is_ok(), router.as_mut()) {
+                analyzed = router.analyze().await;
+            }
+            if let Err(e) = analyzed {
+                observer.on_error(&e, &[]);
+                return Err(e);
+            }
+        }
+        stats.write_time += finalize_start.elapsed();
+
+        stats.total_time = start_time.elapsed();
+        stats.rows_per_second = stats.total_rows as f64 / stats.total_time.as_secs_f64();
+        stats.bytes_read = source.bytes_read();
+        stats.bytes_written = target.bytes_written();
+        if let Some(hasher) = &row_hasher {
+            let root = hasher.root();
+            info!("→ Row hash root: {}", root);
+            stats.row_hash_root = Some(root);
+        }
 
-        let total_time = start_time.elapsed();
-        let rows_per_second = total_rows as f64 / total_time.as_secs_f64();
+        if let (Some(path), Some(state)) = (&config.state_file, run_state.as_mut()) {
+            if !stats.cancelled {
+                state.record_schema(&source_schema);
+                source.record_state(state);
+                if let Some(watermark) = &watermark {
+                    watermark.record(state);
+                }
+                state.save(path)?;
+            }
+        }
+        stats.schema_drift = schema_drift;
+        // A cancelled run keeps its checkpoint so it can be resumed
+        if let (Some(path), false) = (&config.checkpoint_file, stats.cancelled) {
+            Checkpoint::remove(path)?;
+        }
 
-        info!("→ Done in {:.1}s", total_time.as_secs_f64());
+        if let Some(file) = dead_letter.as_mut() {
+            file.flush()?;
+        }
+        if stats.rows_skipped > 0 {
+            warn!("→ {} invalid rows skipped", stats.rows_skipped);
+        }
+        if stats.values_truncated > 0 {
+            warn!("→ {} oversized values truncated", stats.values_truncated);
+        }
+        if stats.nulls_replaced > 0 {
+            info!(
+                "→ {} NULLs replaced by their null fallback",
+                stats.nulls_replaced
+            );
+        }
+        if stats.rows_dead_lettered > 0 {
+            warn!(
+                "→ {} oversized rows written to the dead letter file",
+                stats.rows_dead_lettered
+            );
+        }
+        if stats.rows_rejected > 0 {
+            warn!("→ {} rows rejected by the target", stats.rows_rejected);
+        }
+        info!("→ Done in {:.1}s", stats.total_time.as_secs_f64());
 
-        Ok(TransferStats {
-            total_rows,
-            total_time,
-            rows_per_second,
-            batches_processed,
-        })
+        Ok(stats)
     }
 
     async fn handle_preview(
@@ -205,7 +1096,8 @@ impl TransferEngine {
         config: &Config,
     ) -> Result<TransferStats> {
         // Initialize transformer for preview
-        let mut transformer = Transformer::new(&config.transform)?;
+        let mut transformer =
+            Transformer::with_options(&config.transform, &TransformOptions::from_config(config))?;
 
         println!("\nOriginal Schema Preview:");
         Self::print_schema(schema);
@@ -231,12 +1123,7 @@ impl TransferEngine {
         println!("\nData Preview ({} rows):", preview_rows);
         Self::print_data_table(&final_data);
 
-        Ok(TransferStats {
-            total_rows: 0,
-            total_time: std::time::Duration::from_secs(0),
-            rows_per_second: 0.0,
-            batches_processed: 0,
-        })
+        Ok(TransferStats::default())
     }
 
     fn print_schema(schema: &Schema) {
@@ -329,7 +1216,8 @@ impl TransferEngine {
         info!("Estimated rows: {}", estimated_rows);
 
         // Test transformations if enabled
-        let mut transformer = Transformer::new(&config.transform)?;
+        let mut transformer =
+            Transformer::with_options(&config.transform, &TransformOptions::from_config(config))?;
         if transformer.is_enabled() {
             info!("Testing transformations...");
             source.reset().await?;
@@ -359,54 +1247,509 @@ impl TransferEngine {
 
         info!("Dry run completed successfully");
 
-        Ok(TransferStats {
-            total_rows: 0,
-            total_time: std::time::Duration::from_secs(0),
-            rows_per_second: 0.0,
-            batches_processed: 0,
-        })
+        Ok(TransferStats::default())
     }
 
-    fn extract_table_name(target: &str) -> String {
-        if target.contains('#') {
-            target.split('#').nth(1).unwrap_or("data").to_string()
-        } else {
-            // Extract filename without extension for file targets
-            std::path::Path::new(target)
-                .file_stem()
-                .and_then(|s| s.to_str())
-                .unwrap_or("data")
-                .to_string()
+    /// Read the existing rows of a file target for SCD2 mode. Returns `None`
+    /// for non-file targets, which are read after connecting instead.
+    async fn read_scd2_file_history(config: &Config) -> Result<Option<Vec<Row>>> {
+        let path = config
+            .target
+            .strip_prefix("file://")
+            .unwrap_or(&config.target);
+        let is_file = Path::new(path)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| {
+                matches!(
+                    ext.to_lowercase().as_str(),
+                    "csv" | "json" | "jsonl" | "ndjson" | "parquet" | "avro"
+                )
+            })
+            .unwrap_or(false);
+
+        if !is_file {
+            return Ok(None);
         }
+        if !Path::new(path).exists() {
+            return Ok(Some(Vec::new()));
+        }
+        Ok(Some(Self::read_target_rows(config).await?))
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::schema::{Column, DataType, Row, Value};
-    use async_trait::async_trait;
-    use std::collections::HashMap;
 
-    // Mock source for testing
-    struct MockSource {
-        data: Vec<Row>,
-        position: usize,
-        connected: bool,
+    /// Read every row currently stored in the target by opening it as a source
+    async fn read_target_rows(config: &Config) -> Result<Vec<Row>> {
+        let connection = process_connection_string(
+            &config.target,
+            config.dest_secret_id.as_ref(),
+            "destination",
+        )
+        .map_err(|e| TinyEtlError::Configuration(e.to_string()))?;
+
+        let mut reader =
+            create_source_from_url_with_type_and_options(&connection, None, &HashMap::new())
+                .await?;
+        reader.connect().await?;
+        // Initializes pagination state for database sources
+        let _ = reader.infer_schema(1).await?;
+        reader.reset().await?;
+
+        let mut rows = Vec::new();
+        while reader.has_more() {
+            let batch = reader.read_batch(config.batch_size).await?;
+            if batch.is_empty() {
+                break;
+            }
+            rows.extend(batch);
+        }
+        Ok(rows)
     }
 
-    impl MockSource {
-        fn new(data: Vec<Row>) -> Self {
-            Self {
-                data,
-                position: 0,
-                connected: false,
+    /// The checkpoint to continue from with `--resume`; without it, a run
+    /// starts over and replaces the checkpoint as it goes
+    fn load_checkpoint(config: &Config, path: &str) -> Result<Option<Checkpoint>> {
+        if config.scd2
+            || config.pivot.is_some()
+            || config.row_number.is_some()
+            || !config.routes.is_empty()
+        {
+            return Err(TinyEtlError::Configuration(
+                "--checkpoint-file can't be combined with --scd2, pivoting, row numbers or routes"
+                    .to_string(),
+            ));
+        }
+        match Checkpoint::load(path)? {
+            Some(checkpoint) if config.resume => {
+                checkpoint.check_run(config)?;
+                Ok(Some(checkpoint))
+            }
+            Some(checkpoint) => {
+                warn!(
+                    "→ Starting over instead of resuming the interrupted run in {} ({} rows written); pass --resume to continue it",
+                    path, checkpoint.rows_written
+                );
+                Ok(None)
+            }
+            None => {
+                if config.resume {
+                    info!("→ No interrupted run to resume, starting from the beginning");
+                }
+                Ok(None)
             }
         }
     }
 
-    #[async_trait]
-    impl Source for MockSource {
+    /// Fails when the target can't be continued where the checkpoint left it
+    fn check_resumable(
+        target: &dyn Target,
+        table_name: &str,
+        table_exists: bool,
+        checkpoint: &Checkpoint,
+    ) -> Result<()> {
+        if checkpoint.rows_written > 0 && !table_exists {
+            return Err(TinyEtlError::Configuration(format!(
+                "Can't resume: target '{}' no longer exists",
+                table_name
+            )));
+        }
+        if !target.supports_append() {
+            return Err(TinyEtlError::Configuration(format!(
+                "Can't resume: target '{}' can't be appended to",
+                table_name
+            )));
+        }
+        if let (Some(expected), Some(actual)) = (checkpoint.bytes_written, target.bytes_written()) {
+            if actual < expected {
+                return Err(TinyEtlError::DataValidation(format!(
+                    "Can't resume: target '{}' holds {} bytes, fewer than the {} checkpointed",
+                    table_name, actual, expected
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Read and drop the source rows a resumed run already transferred
+    async fn skip_rows(
+        source: &mut dyn Source,
+        rows: usize,
+        batch_size: usize,
+        watermark: &mut Option<WatermarkTracker>,
+    ) -> Result<()> {
+        let mut skipped = 0;
+        while skipped < rows && source.has_more() {
+            let batch = source.read_batch(batch_size.min(rows - skipped)).await?;
+            if batch.is_empty() {
+                break;
+            }
+            if let Some(watermark) = watermark.as_mut() {
+                watermark.observe(&batch);
+            }
+            skipped += batch.len();
+        }
+        if skipped < rows {
+            return Err(TinyEtlError::DataValidation(format!(
+                "Can't resume: the source has {} rows, fewer than the {} already read",
+                skipped, rows
+            )));
+        }
+        Ok(())
+    }
+
+    /// Create the target table, or handle an existing one as `if_exists` says
+    pub(crate) async fn prepare_target(
+        target: &mut dyn Target,
+        table_name: &str,
+        schema: &Schema,
+        table_exists: bool,
+        if_exists: IfExists,
+    ) -> Result<()> {
+        if table_exists {
+            if if_exists == IfExists::Fail {
+                return Err(TinyEtlError::Configuration(format!(
+                    "Target '{}' already exists (--if-exists fail)",
+                    table_name
+                )));
+            } else if if_exists == IfExists::Replace {
+                info!("→ Replacing existing target: {}", table_name);
+                target.drop_table(table_name).await?;
+                target.create_table(table_name, schema).await?;
+            } else if if_exists == IfExists::Truncate {
+                info!("→ Truncating existing target: {}", table_name);
+                target.truncate(table_name).await?;
+                // After truncating, we need to create the table again
+                target.create_table(table_name, schema).await?;
+            } else if target.supports_append() {
+                info!("→ Appending to existing target: {}", table_name);
+                // For append mode, we still need to set the schema for the target
+                target.create_table(table_name, schema).await?;
+            } else {
+                // Target exists but doesn't support append - must truncate
+                info!(
+                    "→ Target exists but doesn't support append, truncating: {}",
+                    table_name
+                );
+                target.truncate(table_name).await?;
+                // After truncating, we need to create the table again
+                target.create_table(table_name, schema).await?;
+            }
+        } else {
+            // Step 8: Create target table with final schema if it doesn't exist
+            info!("→ Creating target table: {}", table_name);
+            target.create_table(table_name, schema).await?;
+        }
+        Ok(())
+    }
+
+    /// Output schema without the `exclude_columns`
+    fn exclude_from_schema(mut schema: Schema, excluded: &[String]) -> Schema {
+        for name in excluded {
+            if !schema.columns.iter().any(|column| &column.name == name) {
+                warn!("→ Excluded column '{}' is not in the output", name);
+            }
+        }
+        schema
+            .columns
+            .retain(|column| !excluded.contains(&column.name));
+        schema
+    }
+
+    /// Output schema with the `column_order` columns first, in that order
+    fn order_schema(mut schema: Schema, order: &[String]) -> Result<Schema> {
+        if let Some(missing) = order
+            .iter()
+            .find(|name| !schema.columns.iter().any(|column| &column.name == *name))
+        {
+            return Err(TinyEtlError::Configuration(format!(
+                "Column '{}' in --column-order is not in the output schema",
+                missing
+            )));
+        }
+        schema.reorder_columns(order);
+        Ok(schema)
+    }
+
+    fn column_names(schema: &Schema) -> Vec<String> {
+        schema
+            .columns
+            .iter()
+            .map(|column| column.name.clone())
+            .collect()
+    }
+
+    fn exclude_from_rows(rows: &mut [Row], excluded: &[String]) {
+        if excluded.is_empty() {
+            return;
+        }
+        for row in rows {
+            for name in excluded {
+                row.remove(name);
+            }
+        }
+    }
+
+    /// Schema file or YAML-declared columns, if any
+    fn declared_schema(config: &Config) -> Result<Option<SchemaFile>> {
+        let schema_file = match (&config.schema_file, config.columns.is_empty()) {
+            (Some(_), false) => {
+                return Err(TinyEtlError::Configuration(
+                    "Declare columns either in a schema file or inline, not both".to_string(),
+                ))
+            }
+            (Some(path), true) => {
+                info!("→ Loading schema from file: {}", path);
+                Some(SchemaFile::from_file(path)?)
+            }
+            (None, false) => {
+                let schema_file = SchemaFile {
+                    columns: config.columns.clone(),
+                };
+                schema_file.validate()?;
+                Some(schema_file)
+            }
+            (None, true) => None,
+        };
+
+        if schema_file.is_none() && config.schema_mode == SchemaMode::Declared {
+            return Err(TinyEtlError::Configuration(
+                "Declared schema mode requires a schema file or declared columns".to_string(),
+            ));
+        }
+        Ok(schema_file)
+    }
+
+    /// Validate rows against the schema file (converting them strictly in
+    /// declared mode) and its unique columns. Invalid rows are dropped under
+    /// `RowErrorPolicy::Skip`; returns how many.
+    fn validate_batch(
+        schema_file: &SchemaFile,
+        unique_values: &mut UniqueValues,
+        config: &Config,
+        batch: &mut Vec<Row>,
+    ) -> Result<usize> {
+        Self::retain_valid_rows(config, batch, |row| {
+            match config.schema_mode {
+                SchemaMode::Declared => schema_file.conform_row(row)?,
+                SchemaMode::Inferred => schema_file.validate_and_transform_row(row)?,
+            }
+            unique_values.check(schema_file, row)
+        })
+    }
+
+    /// Run `check` on every row, dropping the rows it fails under
+    /// `RowErrorPolicy::Skip`; returns how many
+    fn retain_valid_rows(
+        config: &Config,
+        batch: &mut Vec<Row>,
+        mut check: impl FnMut(&mut Row) -> Result<()>,
+    ) -> Result<usize> {
+        let before = batch.len();
+        let mut failure = None;
+        batch.retain_mut(|row| {
+            if failure.is_some() {
+                return true;
+            }
+            match check(row) {
+                Ok(()) => true,
+                Err(e) if config.on_row_error == RowErrorPolicy::Skip => {
+                    warn!("→ Skipping row: {}", e);
+                    false
+                }
+                Err(e) => {
+                    failure = Some(e);
+                    true
+                }
+            }
+        });
+
+        match failure {
+            Some(e) => Err(e),
+            None => Ok(before - batch.len()),
+        }
+    }
+
+    /// Write rows to their route targets and the rest to the primary target,
+    /// or stage them in the SCD2 merger when enabled
+    async fn write_rows(
+        target: &mut dyn Target,
+        scd2: &mut Option<Scd2Merger>,
+        router: &mut Option<Router>,
+        rows: &[Row],
+    ) -> Result<usize> {
+        if rows.is_empty() {
+            return Ok(0);
+        }
+
+        let total = rows.len();
+        let unrouted;
+        let rows = match router {
+            Some(router) => {
+                unrouted = router.route_batch(rows.to_vec()).await?;
+                &unrouted[..]
+            }
+            None => rows,
+        };
+        let routed = total - rows.len();
+        if rows.is_empty() {
+            return Ok(routed);
+        }
+
+        let written = match scd2 {
+            Some(merger) => {
+                for row in rows {
+                    merger.apply(row.clone())?;
+                }
+                rows.len()
+            }
+            None => target.write_batch(rows).await?,
+        };
+        Ok(routed + written)
+    }
+
+    /// Retry a failed batch in halves until the rows the target rejects are
+    /// isolated. Rejected rows are logged with their position among the rows
+    /// sent (starting at `first_index`) and the driver error, and go to the
+    /// dead letter file when there is one. Returns rows written and rejected.
+    async fn write_bisecting(
+        target: &mut dyn Target,
+        scd2: &mut Option<Scd2Merger>,
+        router: &mut Option<Router>,
+        rows: &[Row],
+        first_index: usize,
+        dead_letter: &mut Option<DeadLetterFile>,
+    ) -> Result<(usize, usize)> {
+        let (first, second) = rows.split_at(rows.len() / 2);
+        let mut pending = vec![(first_index + first.len(), second), (first_index, first)];
+        let mut written = 0;
+        let mut rejected = 0;
+
+        while let Some((index, chunk)) = pending.pop() {
+            match Self::write_rows(target, scd2, router, chunk).await {
+                Ok(count) => written += count,
+                Err(e) if chunk.len() == 1 => {
+                    warn!("→ Row {} rejected by the target: {}", index, e);
+                    if let Some(file) = dead_letter.as_mut() {
+                        file.write(&e.to_string(), Some(index), &chunk[0])?;
+                    }
+                    rejected += 1;
+                }
+                Err(_) => {
+                    let (first, second) = chunk.split_at(chunk.len() / 2);
+                    pending.push((index + first.len(), second));
+                    pending.push((index, first));
+                }
+            }
+        }
+        Ok((written, rejected))
+    }
+
+    /// Fails when the stored rows of a database target lack the SCD2
+    /// columns, which its history would be updated in
+    fn check_scd2_columns(table_name: &str, rows: &[Row]) -> Result<()> {
+        let Some(row) = rows.first() else {
+            return Ok(());
+        };
+        let missing: Vec<&str> = [VALID_FROM_COLUMN, VALID_TO_COLUMN, IS_CURRENT_COLUMN]
+            .into_iter()
+            .filter(|column| !row.contains_key(*column))
+            .collect();
+        if missing.is_empty() {
+            return Ok(());
+        }
+        Err(TinyEtlError::Configuration(format!(
+            "Table '{}' has no {} column to keep the SCD2 history in; add it or load into a new table",
+            table_name,
+            missing.join(", ")
+        )))
+    }
+
+    /// Write the merged SCD2 history: database targets get the expired and
+    /// new versions in one transaction, file targets are rewritten whole
+    async fn write_scd2_history(
+        target: &mut dyn Target,
+        table_name: &str,
+        schema: &Schema,
+        merger: Scd2Merger,
+        config: &Config,
+    ) -> Result<()> {
+        let stats = merger.stats().clone();
+        info!(
+            "→ SCD2: {} new, {} changed, {} unchanged",
+            stats.inserted, stats.changed, stats.unchanged
+        );
+
+        if target.supports_scd2_merge() {
+            target
+                .create_table(table_name, &Scd2Merger::extend_schema(schema))
+                .await?;
+            return target.apply_scd2(&merger.into_changes()).await;
+        }
+
+        if target.exists(table_name).await? {
+            target.truncate(table_name).await?;
+        }
+        target
+            .create_table(table_name, &Scd2Merger::extend_schema(schema))
+            .await?;
+
+        let rows = merger.into_rows();
+        for chunk in rows.chunks(config.batch_size.max(1)) {
+            target.write_batch(chunk).await?;
+        }
+        Ok(())
+    }
+
+    pub fn extract_table_name(target: &str) -> String {
+        if let Ok((_, Some(table))) = split_table(target) {
+            table
+        } else {
+            // Extract filename without extension for file targets
+            std::path::Path::new(target)
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .unwrap_or("data")
+                .to_string()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::read_with_timeout;
+    use crate::schema::{Column, DataType, Value};
+    use crate::transformer::TransformConfig;
+    use async_trait::async_trait;
+    use std::collections::HashMap;
+
+    // Mock source for testing
+    struct MockSource {
+        data: Vec<Row>,
+        position: usize,
+        connected: bool,
+        ordered: bool,
+    }
+
+    impl MockSource {
+        fn new(data: Vec<Row>) -> Self {
+            Self {
+                data,
+                position: 0,
+                connected: false,
+                ordered: true,
+            }
+        }
+
+        fn unordered(data: Vec<Row>) -> Self {
+            Self {
+                ordered: false,
+                ..Self::new(data)
+            }
+        }
+    }
+
+    #[async_trait]
+    impl Source for MockSource {
         async fn connect(&mut self) -> Result<()> {
             self.connected = true;
             Ok(())
@@ -442,6 +1785,10 @@ mod tests {
             Ok(Some(self.data.len()))
         }
 
+        async fn has_stable_order(&mut self) -> Result<bool> {
+            Ok(self.ordered)
+        }
+
         async fn reset(&mut self) -> Result<()> {
             self.position = 0;
             Ok(())
@@ -595,4 +1942,1168 @@ mod tests {
         // Dry run should not transfer data
         assert_eq!(stats.total_rows, 0);
     }
+
+    #[test]
+    fn test_batch_latency_histogram() {
+        let mut histogram = BatchLatencyHistogram::default();
+        assert_eq!(histogram.percentile(50.0), None);
+
+        for ms in [2, 4, 6, 8, 200] {
+            histogram.record(Duration::from_millis(ms));
+        }
+
+        assert_eq!(histogram.count(), 5);
+        assert_eq!(histogram.percentile(50.0), Some(Duration::from_millis(6)));
+        assert_eq!(histogram.percentile(95.0), Some(Duration::from_millis(200)));
+        assert_eq!(histogram.max(), Some(Duration::from_millis(200)));
+
+        let buckets = histogram.buckets();
+        assert_eq!(buckets[1], ("<10ms".to_string(), 4));
+        assert_eq!(buckets[4], ("<500ms".to_string(), 1));
+        assert_eq!(buckets.last().unwrap().0, ">=5000ms");
+    }
+
+    #[tokio::test]
+    async fn test_transfer_stats_report() {
+        let test_data: Vec<Row> = (0..25)
+            .map(|i| {
+                let mut row = HashMap::new();
+                row.insert("id".to_string(), Value::Integer(i));
+                row.insert("name".to_string(), Value::String(format!("user{}", i)));
+                row
+            })
+            .collect();
+
+        let config = Config {
+            source: "test.csv".to_string(),
+            target: "test.db#users".to_string(),
+            batch_size: 10,
+            ..Default::default()
+        };
+
+        let stats = TransferEngine::execute(
+            &config,
+            Box::new(MockSource::new(test_data)),
+            Box::new(MockTarget::new()),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(stats.batches_processed, 3);
+        assert_eq!(stats.batch_latencies.count(), 3);
+        assert_eq!(stats.bytes_read, None);
+
+        let report = stats.to_json();
+        assert_eq!(report["total_rows"], 25);
+        assert!(report["phases"]["read_secs"].is_number());
+        assert!(report["bytes_written"].is_null());
+        assert!(report["batch_latency_ms"]["p95"].is_number());
+        assert!(stats.breakdown().contains("transform"));
+    }
+
+    #[derive(Default)]
+    struct RecordingObserver {
+        batch_rows: Vec<usize>,
+        errors: Vec<(String, usize)>,
+    }
+
+    impl TransferObserver for RecordingObserver {
+        fn on_batch(&mut self, stats: &TransferStats) {
+            self.batch_rows.push(stats.total_rows);
+        }
+
+        fn on_error(&mut self, error: &TinyEtlError, batch: &[Row]) {
+            self.errors.push((error.to_string(), batch.len()));
+        }
+    }
+
+    // Target that rejects every write
+    struct FailingTarget;
+
+    #[async_trait]
+    impl Target for FailingTarget {
+        async fn connect(&mut self) -> Result<()> {
+            Ok(())
+        }
+
+        async fn create_table(&mut self, _table_name: &str, _schema: &Schema) -> Result<()> {
+            Ok(())
+        }
+
+        async fn write_batch(&mut self, _rows: &[Row]) -> Result<usize> {
+            Err(TinyEtlError::DataTransfer("disk full".to_string()))
+        }
+
+        async fn finalize(&mut self) -> Result<()> {
+            Ok(())
+        }
+
+        async fn exists(&self, _table_name: &str) -> Result<bool> {
+            Ok(false)
+        }
+
+        async fn truncate(&mut self, _table_name: &str) -> Result<()> {
+            Ok(())
+        }
+
+        fn supports_append(&self) -> bool {
+            false
+        }
+    }
+
+    fn numbered_rows(count: i64) -> Vec<Row> {
+        (0..count)
+            .map(|i| {
+                let mut row = HashMap::new();
+                row.insert("id".to_string(), Value::Integer(i));
+                row.insert("name".to_string(), Value::String(format!("user{}", i)));
+                row
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_observer_receives_batch_progress() {
+        let config = Config {
+            source: "test.csv".to_string(),
+            target: "test.db#users".to_string(),
+            batch_size: 4,
+            ..Default::default()
+        };
+
+        let mut observer = RecordingObserver::default();
+        let stats = TransferEngine::execute_with_observer(
+            &config,
+            Box::new(MockSource::new(numbered_rows(10))),
+            Box::new(MockTarget::new()),
+            &mut observer,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(stats.total_rows, 10);
+        assert_eq!(observer.batch_rows, vec![4, 8, 10]);
+        assert!(observer.errors.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_limit_stops_after_n_rows() {
+        let config = Config {
+            source: "test.csv".to_string(),
+            target: "test.db#users".to_string(),
+            batch_size: 4,
+            limit: Some(6),
+            ..Default::default()
+        };
+
+        let mut observer = RecordingObserver::default();
+        let stats = TransferEngine::execute_with_observer(
+            &config,
+            Box::new(MockSource::new(numbered_rows(10))),
+            Box::new(MockTarget::new()),
+            &mut observer,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(stats.total_rows, 6);
+        assert_eq!(observer.batch_rows, vec![4, 6]);
+    }
+
+    #[tokio::test]
+    async fn test_excluded_columns_are_not_written() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("input.csv");
+        let output = dir.path().join("output.csv");
+        std::fs::write(&input, "id,name\n1,alice\n2,bob\n").unwrap();
+        let config = Config {
+            source: input.display().to_string(),
+            target: output.display().to_string(),
+            exclude_columns: vec!["id".to_string()],
+            ..Default::default()
+        };
+
+        let stats = TransferEngine::execute(
+            &config,
+            crate::connectors::create_source(&config.source).unwrap(),
+            crate::connectors::create_target(&config.target).unwrap(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(stats.total_rows, 2);
+        assert_eq!(
+            std::fs::read_to_string(&output).unwrap(),
+            "name\nalice\nbob\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_schema_drift_against_state_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let state_file = dir.path().join("state.json");
+        std::fs::write(
+            &state_file,
+            r#"{"schema": [{"name": "id", "data_type": "Integer", "nullable": false},
+                          {"name": "email", "data_type": "String", "nullable": true}]}"#,
+        )
+        .unwrap();
+
+        let mut config = Config {
+            source: "test.csv".to_string(),
+            target: "test.db#users".to_string(),
+            state_file: Some(state_file.to_string_lossy().to_string()),
+            on_schema_drift: SchemaDriftPolicy::Fail,
+            ..Default::default()
+        };
+        let result = TransferEngine::execute(
+            &config,
+            Box::new(MockSource::new(numbered_rows(3))),
+            Box::new(MockTarget::new()),
+        )
+        .await;
+        assert!(matches!(result, Err(TinyEtlError::DataValidation(_))));
+
+        config.on_schema_drift = SchemaDriftPolicy::Warn;
+        let stats = TransferEngine::execute(
+            &config,
+            Box::new(MockSource::new(numbered_rows(3))),
+            Box::new(MockTarget::new()),
+        )
+        .await
+        .unwrap();
+        let drift = stats.schema_drift.unwrap();
+        assert_eq!(drift.added, vec!["name"]);
+        assert_eq!(drift.removed, vec!["email"]);
+
+        // The new schema is recorded for the next run
+        let state = RunState::load(&state_file).unwrap();
+        assert_eq!(state.schema.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_declared_schema_skips_rows_failing_conversion() {
+        let mut rows = numbered_rows(3);
+        rows[1].insert("id".to_string(), Value::String("n/a".to_string()));
+        let config = Config {
+            source: "test.csv".to_string(),
+            target: "test.db#users".to_string(),
+            schema_mode: SchemaMode::Declared,
+            columns: serde_yaml::from_str(
+                "[{name: id, type: integer, nullable: false}, {name: name, type: string, nullable: true}]",
+            )
+            .unwrap(),
+            on_row_error: RowErrorPolicy::Skip,
+            ..Default::default()
+        };
+
+        let stats = TransferEngine::execute(
+            &config,
+            Box::new(MockSource::new(rows)),
+            Box::new(MockTarget::new()),
+        )
+        .await
+        .unwrap();
+        assert_eq!(stats.total_rows, 2);
+        assert_eq!(stats.rows_skipped, 1);
+    }
+
+    #[tokio::test]
+    async fn test_integer_overflow_follows_row_error_policy() {
+        let mut rows = numbered_rows(3);
+        rows[1].insert("id".to_string(), Value::from_wide_integer(u64::MAX.into()));
+        let config = Config {
+            source: "test.csv".to_string(),
+            target: "test.db#users".to_string(),
+            ..Default::default()
+        };
+
+        let err = TransferEngine::execute(
+            &config,
+            Box::new(MockSource::new(rows.clone())),
+            Box::new(MockTarget::new()),
+        )
+        .await
+        .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("Column 'id' value 18446744073709551615 overflows"));
+
+        let config = Config {
+            on_row_error: RowErrorPolicy::Skip,
+            ..config
+        };
+        let stats = TransferEngine::execute(
+            &config,
+            Box::new(MockSource::new(rows)),
+            Box::new(MockTarget::new()),
+        )
+        .await
+        .unwrap();
+        assert_eq!(stats.total_rows, 2);
+        assert_eq!(stats.rows_skipped, 1);
+    }
+
+    #[tokio::test]
+    async fn test_strict_rejects_values_not_fitting_their_column() {
+        let mut rows = numbered_rows(3);
+        rows[1].insert("id".to_string(), Value::String("2 boxes".to_string()));
+        let config = Config {
+            source: "test.csv".to_string(),
+            target: "test.db#users".to_string(),
+            ..Default::default()
+        };
+
+        let stats = TransferEngine::execute(
+            &config,
+            Box::new(MockSource::new(rows.clone())),
+            Box::new(MockTarget::new()),
+        )
+        .await
+        .unwrap();
+        assert_eq!(stats.total_rows, 3);
+
+        let config = Config {
+            strict: true,
+            ..config
+        };
+        let err = TransferEngine::execute(
+            &config,
+            Box::new(MockSource::new(rows)),
+            Box::new(MockTarget::new()),
+        )
+        .await
+        .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("Column 'id' expects Integer, got '2 boxes' (--strict)"));
+    }
+
+    #[tokio::test]
+    async fn test_null_fallback_fills_not_null_columns() {
+        let mut rows = numbered_rows(3);
+        rows[1].insert("name".to_string(), Value::Null);
+        let config = Config {
+            source: "test.csv".to_string(),
+            target: "test.db#users".to_string(),
+            schema_mode: SchemaMode::Declared,
+            columns: serde_yaml::from_str(
+                "[{name: id, type: integer, nullable: false}, {name: name, type: string, nullable: false}]",
+            )
+            .unwrap(),
+            null_fallback: serde_yaml::from_str::<crate::null_fallback::NullFallbackConfig>("empty")
+                .unwrap()
+                .into_columns(),
+            ..Default::default()
+        };
+
+        let stats = TransferEngine::execute(
+            &config,
+            Box::new(MockSource::new(rows)),
+            Box::new(MockTarget::new()),
+        )
+        .await
+        .unwrap();
+        assert_eq!(stats.total_rows, 3);
+        assert_eq!(stats.nulls_replaced, 1);
+    }
+
+    #[tokio::test]
+    async fn test_column_order_is_pinned() {
+        let schema = MockSource::new(Vec::new()).infer_schema(1).await.unwrap();
+        let ordered = TransferEngine::order_schema(schema.clone(), &["name".to_string()]).unwrap();
+        assert_eq!(TransferEngine::column_names(&ordered), ["name", "id"]);
+
+        assert!(TransferEngine::order_schema(schema, &["email".to_string()]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_observer_receives_failed_batch() {
+        let config = Config {
+            source: "test.csv".to_string(),
+            target: "test.db#users".to_string(),
+            batch_size: 3,
+            ..Default::default()
+        };
+
+        let mut observer = RecordingObserver::default();
+        let result = TransferEngine::execute_with_observer(
+            &config,
+            Box::new(MockSource::new(numbered_rows(5))),
+            Box::new(FailingTarget),
+            &mut observer,
+        )
+        .await;
+
+        assert!(result.is_err());
+        assert!(observer.batch_rows.is_empty());
+        assert_eq!(observer.errors.len(), 1);
+        assert!(observer.errors[0].0.contains("disk full"));
+        assert_eq!(observer.errors[0].1, 3);
+    }
+
+    // Target that rejects any batch holding a row whose id is in `bad_ids`
+    struct PickyTarget {
+        bad_ids: Vec<i64>,
+    }
+
+    #[async_trait]
+    impl Target for PickyTarget {
+        async fn connect(&mut self) -> Result<()> {
+            Ok(())
+        }
+
+        async fn create_table(&mut self, _table_name: &str, _schema: &Schema) -> Result<()> {
+            Ok(())
+        }
+
+        async fn write_batch(&mut self, rows: &[Row]) -> Result<usize> {
+            for row in rows {
+                if let Some(Value::Integer(id)) = row.get("id") {
+                    if self.bad_ids.contains(id) {
+                        return Err(TinyEtlError::DataTransfer(format!("duplicate key {}", id)));
+                    }
+                }
+            }
+            Ok(rows.len())
+        }
+
+        async fn finalize(&mut self) -> Result<()> {
+            Ok(())
+        }
+
+        async fn exists(&self, _table_name: &str) -> Result<bool> {
+            Ok(false)
+        }
+
+        async fn truncate(&mut self, _table_name: &str) -> Result<()> {
+            Ok(())
+        }
+
+        fn use_atomic_batches(&mut self) -> Result<()> {
+            Ok(())
+        }
+
+        fn supports_append(&self) -> bool {
+            false
+        }
+    }
+
+    #[tokio::test]
+    async fn test_bisecting_isolates_rejected_rows() {
+        let dir = tempfile::tempdir().unwrap();
+        let dead_letter = dir.path().join("rejected.jsonl");
+        let config = Config {
+            source: "test.csv".to_string(),
+            target: "test.db#users".to_string(),
+            batch_size: 4,
+            bisect_failed_batches: true,
+            dead_letter_file: Some(dead_letter.to_string_lossy().to_string()),
+            ..Default::default()
+        };
+
+        let stats = TransferEngine::execute(
+            &config,
+            Box::new(MockSource::new(numbered_rows(10))),
+            Box::new(PickyTarget {
+                bad_ids: vec![2, 7],
+            }),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(stats.total_rows, 8);
+        assert_eq!(stats.rows_rejected, 2);
+        let rejected: Vec<serde_json::Value> = std::fs::read_to_string(&dead_letter)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(rejected.len(), 2);
+        assert_eq!(rejected[0]["row_index"], 2);
+        assert_eq!(rejected[1]["row_index"], 7);
+        assert!(rejected[1]["reason"]
+            .as_str()
+            .unwrap()
+            .contains("duplicate key 7"));
+    }
+
+    // SQLite users table whose CHECK constraint rejects some ids
+    async fn checked_users_table(path: &std::path::Path, bad_ids: &[i64]) {
+        let pool = sqlx::SqlitePool::connect(&format!("sqlite:{}?mode=rwc", path.display()))
+            .await
+            .unwrap();
+        let checks: Vec<String> = bad_ids.iter().map(|id| format!("id <> {}", id)).collect();
+        let create_sql = format!(
+            "CREATE TABLE users (id INTEGER CHECK ({}), name TEXT)",
+            checks.join(" AND ")
+        );
+        sqlx::query(&create_sql).execute(&pool).await.unwrap();
+        pool.close().await;
+    }
+
+    async fn sqlite_user_ids(path: &std::path::Path) -> Vec<i64> {
+        let pool = sqlx::SqlitePool::connect(&format!("sqlite:{}", path.display()))
+            .await
+            .unwrap();
+        let ids = sqlx::query_scalar("SELECT id FROM users ORDER BY id")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        pool.close().await;
+        ids
+    }
+
+    #[tokio::test]
+    async fn test_bisecting_never_writes_a_row_twice() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = dir.path().join("users.db");
+        checked_users_table(&db, &[700]).await;
+        let config = Config {
+            source: "test.csv".to_string(),
+            target: format!("{}#users", db.display()),
+            batch_size: 1_000,
+            bisect_failed_batches: true,
+            ..Default::default()
+        };
+
+        // SQLite inserts two-column rows 450 at a time, so the batch fails
+        // after its first chunk has been written
+        let stats = TransferEngine::execute(
+            &config,
+            Box::new(MockSource::new(numbered_rows(1_000))),
+            crate::connectors::create_target(&config.target).unwrap(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(stats.total_rows, 999);
+        assert_eq!(stats.rows_rejected, 1);
+        let expected: Vec<i64> = (0..1_000).filter(|id| *id != 700).collect();
+        assert_eq!(sqlite_user_ids(&db).await, expected);
+    }
+
+    #[tokio::test]
+    async fn test_bisecting_needs_atomic_batches() {
+        let config = Config {
+            source: "test.csv".to_string(),
+            target: "test.db#users".to_string(),
+            bisect_failed_batches: true,
+            ..Default::default()
+        };
+
+        let result = TransferEngine::execute(
+            &config,
+            Box::new(MockSource::new(numbered_rows(10))),
+            Box::new(MockTarget::new()),
+        )
+        .await;
+        assert!(matches!(result, Err(TinyEtlError::Configuration(_))));
+    }
+
+    #[tokio::test]
+    async fn test_resume_after_bisected_batches() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = dir.path().join("users.db");
+        let checkpoint_file = dir.path().join("users.checkpoint.json");
+        let dead_letter = dir.path().join("rejected.jsonl");
+        checked_users_table(&db, &[3, 12, 20]).await;
+        let mut config = Config {
+            source: "test.csv".to_string(),
+            target: format!("{}#users", db.display()),
+            batch_size: 5,
+            bisect_failed_batches: true,
+            checkpoint_file: Some(checkpoint_file.display().to_string()),
+            ..Default::default()
+        };
+
+        let cancel = CancellationToken::new();
+        let mut observer = CancelAfter {
+            rows: 10,
+            cancel: cancel.clone(),
+        };
+        TransferEngine::execute_cancellable(
+            &config,
+            Box::new(MockSource::new(numbered_rows(25))),
+            crate::connectors::create_target(&config.target).unwrap(),
+            &mut observer,
+            &cancel,
+        )
+        .await
+        .unwrap();
+        let checkpoint = Checkpoint::load(&checkpoint_file).unwrap().unwrap();
+        assert_eq!(
+            (
+                checkpoint.rows_read,
+                checkpoint.rows_written,
+                checkpoint.rows_rejected
+            ),
+            (15, 13, 2)
+        );
+
+        config.resume = true;
+        config.dead_letter_file = Some(dead_letter.display().to_string());
+        let stats = TransferEngine::execute(
+            &config,
+            Box::new(MockSource::new(numbered_rows(25))),
+            crate::connectors::create_target(&config.target).unwrap(),
+        )
+        .await
+        .unwrap();
+        assert_eq!((stats.total_rows, stats.rows_rejected), (9, 1));
+
+        let expected: Vec<i64> = (0..25).filter(|id| ![3, 12, 20].contains(id)).collect();
+        assert_eq!(sqlite_user_ids(&db).await, expected);
+        // Rejected rows are numbered from the start of the interrupted run
+        let rejected: serde_json::Value =
+            serde_json::from_str(std::fs::read_to_string(&dead_letter).unwrap().trim()).unwrap();
+        assert_eq!(rejected["row_index"], 20);
+    }
+
+    // Observer that cancels the transfer once a number of rows has been written
+    struct CancelAfter {
+        rows: usize,
+        cancel: CancellationToken,
+    }
+
+    impl TransferObserver for CancelAfter {
+        fn on_batch(&mut self, stats: &TransferStats) {
+            if stats.total_rows >= self.rows {
+                self.cancel.cancel();
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_transfer_returns_partial_stats() {
+        let config = Config {
+            source: "test.csv".to_string(),
+            target: "test.db#users".to_string(),
+            batch_size: 5,
+            ..Default::default()
+        };
+
+        let cancel = CancellationToken::new();
+        let mut observer = CancelAfter {
+            rows: 10,
+            cancel: cancel.clone(),
+        };
+        let stats = TransferEngine::execute_cancellable(
+            &config,
+            Box::new(MockSource::new(numbered_rows(50))),
+            Box::new(MockTarget::new()),
+            &mut observer,
+            &cancel,
+        )
+        .await
+        .unwrap();
+
+        assert!(stats.cancelled);
+        assert_eq!(stats.total_rows, 10);
+        assert_eq!(stats.batches_processed, 2);
+    }
+
+    #[tokio::test]
+    async fn test_resume_continues_after_checkpoint() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("users.jsonl");
+        let checkpoint_file = dir.path().join("users.checkpoint.json");
+        let mut config = Config {
+            source: "test.csv".to_string(),
+            target: output.display().to_string(),
+            batch_size: 5,
+            checkpoint_file: Some(checkpoint_file.display().to_string()),
+            ..Default::default()
+        };
+
+        // The interrupted run keeps its checkpoint
+        let cancel = CancellationToken::new();
+        let mut observer = CancelAfter {
+            rows: 10,
+            cancel: cancel.clone(),
+        };
+        TransferEngine::execute_cancellable(
+            &config,
+            Box::new(MockSource::new(numbered_rows(25))),
+            crate::connectors::create_target(&config.target).unwrap(),
+            &mut observer,
+            &cancel,
+        )
+        .await
+        .unwrap();
+        let checkpoint = Checkpoint::load(&checkpoint_file).unwrap().unwrap();
+        assert_eq!((checkpoint.rows_read, checkpoint.rows_written), (10, 10));
+
+        config.resume = true;
+        let err = TransferEngine::execute(
+            &config,
+            Box::new(MockSource::unordered(numbered_rows(25))),
+            crate::connectors::create_target(&config.target).unwrap(),
+        )
+        .await
+        .unwrap_err();
+        assert!(err.to_string().contains("no fixed order"));
+        assert!(checkpoint_file.exists());
+
+        let stats = TransferEngine::execute(
+            &config,
+            Box::new(MockSource::new(numbered_rows(25))),
+            crate::connectors::create_target(&config.target).unwrap(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(stats.total_rows, 15);
+        assert!(!checkpoint_file.exists());
+
+        let ids: Vec<i64> = std::fs::read_to_string(&output)
+            .unwrap()
+            .lines()
+            .map(|line| {
+                serde_json::from_str::<serde_json::Value>(line).unwrap()["id"]
+                    .as_i64()
+                    .unwrap()
+            })
+            .collect();
+        assert_eq!(ids, (0..25).collect::<Vec<_>>());
+    }
+
+    #[tokio::test]
+    async fn test_parallel_transfer_keeps_row_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("users.jsonl");
+        let config = Config {
+            source: "test.csv".to_string(),
+            target: output.display().to_string(),
+            batch_size: 3,
+            limit: Some(40),
+            transform: TransformConfig::Inline("doubled=row.id * 2".to_string()),
+            parallelism: 4,
+            ..Default::default()
+        };
+
+        let stats = TransferEngine::execute(
+            &config,
+            Box::new(MockSource::new(numbered_rows(50))),
+            crate::connectors::create_target(&config.target).unwrap(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(stats.total_rows, 40);
+
+        let rows: Vec<serde_json::Value> = std::fs::read_to_string(&output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        let ids: Vec<i64> = rows.iter().map(|row| row["id"].as_i64().unwrap()).collect();
+        assert_eq!(ids, (0..40).collect::<Vec<_>>());
+        assert!(rows
+            .iter()
+            .all(|row| row["doubled"].as_i64() == row["id"].as_i64().map(|id| id * 2)));
+    }
+
+    #[tokio::test]
+    async fn test_pivot_rows_are_written_after_source_is_exhausted() {
+        let config = Config {
+            source: "test.csv".to_string(),
+            target: "test.db#users".to_string(),
+            batch_size: 2,
+            pivot: Some(crate::reshape::PivotConfig {
+                key: "name".to_string(),
+                value: "id".to_string(),
+                columns: vec!["user0".to_string(), "user1".to_string()],
+                group_by: Vec::new(),
+            }),
+            ..Default::default()
+        };
+
+        let stats = TransferEngine::execute(
+            &config,
+            Box::new(MockSource::new(numbered_rows(4))),
+            Box::new(MockTarget::new()),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(stats.total_rows, 1);
+        assert_eq!(stats.batches_processed, 2);
+    }
+
+    #[tokio::test]
+    async fn test_join_enriches_rows_from_secondary_source() {
+        let dir = tempfile::tempdir().unwrap();
+        let lookup = dir.path().join("cities.csv");
+        std::fs::write(&lookup, "id,city\n0,London\n1,Paris\n").unwrap();
+
+        let config = Config {
+            source: "test.csv".to_string(),
+            target: "test.db#users".to_string(),
+            join: Some(crate::join::JoinConfig {
+                source: lookup.to_str().unwrap().to_string(),
+                source_type: None,
+                secret_id: None,
+                options: HashMap::new(),
+                on: vec!["id".to_string()],
+                right_on: Vec::new(),
+                kind: crate::join::JoinKind::Inner,
+                columns: Vec::new(),
+                prefix: None,
+            }),
+            ..Default::default()
+        };
+
+        let stats = TransferEngine::execute(
+            &config,
+            Box::new(MockSource::new(numbered_rows(3))),
+            Box::new(MockTarget::new()),
+        )
+        .await
+        .unwrap();
+
+        // user2 has no matching city and is dropped by the inner join
+        assert_eq!(stats.total_rows, 2);
+    }
+
+    #[tokio::test]
+    async fn test_router_splits_rows_between_targets() {
+        let dir = tempfile::tempdir().unwrap();
+        let even = dir.path().join("even.csv");
+        let config = Config {
+            source: "test.csv".to_string(),
+            target: "test.db#users".to_string(),
+            routes: vec![format!("{} if row.id % 2 == 0", even.to_str().unwrap())
+                .parse()
+                .unwrap()],
+            ..Default::default()
+        };
+
+        let stats = TransferEngine::execute(
+            &config,
+            Box::new(MockSource::new(numbered_rows(5))),
+            Box::new(MockTarget::new()),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(stats.total_rows, 5);
+        assert_eq!(stats.routes.len(), 1);
+        assert_eq!(stats.routes[0].rows, 3);
+
+        let written = std::fs::read_to_string(&even).unwrap();
+        assert_eq!(written.lines().count(), 4); // header + ids 0, 2, 4
+    }
+
+    #[tokio::test]
+    async fn test_scd2_expires_changed_rows() {
+        use crate::connectors::csv::CsvTarget;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("customers.csv");
+        let path = path.to_str().unwrap();
+        let config = Config {
+            source: "test.csv".to_string(),
+            target: path.to_string(),
+            scd2: true,
+            key_columns: vec!["id".to_string()],
+            ..Default::default()
+        };
+
+        for name in ["Alice", "Alicia"] {
+            let mut rows = numbered_rows(2);
+            rows[1].insert("name".to_string(), Value::String(name.to_string()));
+            TransferEngine::execute(
+                &config,
+                Box::new(MockSource::new(rows)),
+                Box::new(CsvTarget::new(path).unwrap()),
+            )
+            .await
+            .unwrap();
+        }
+
+        let mut reader = csv::Reader::from_path(path).unwrap();
+        let headers = reader.headers().unwrap().clone();
+        let is_current = headers.iter().position(|h| h == "is_current").unwrap();
+        let records: Vec<csv::StringRecord> = reader.records().map(|r| r.unwrap()).collect();
+
+        assert_eq!(records.len(), 3);
+        let expired: Vec<_> = records
+            .iter()
+            .filter(|r| &r[is_current] == "false")
+            .collect();
+        assert_eq!(expired.len(), 1);
+        assert!(expired[0].iter().any(|field| field == "Alice"));
+    }
+
+    #[tokio::test]
+    async fn test_scd2_updates_database_history_in_place() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = format!("{}#customers", dir.path().join("dim.db").display());
+        let config = Config {
+            source: "test.csv".to_string(),
+            target: target.clone(),
+            scd2: true,
+            key_columns: vec!["id".to_string()],
+            ..Default::default()
+        };
+
+        for name in ["Alice", "Alicia"] {
+            let mut rows = numbered_rows(2);
+            rows[1].insert("name".to_string(), Value::String(name.to_string()));
+            TransferEngine::execute(
+                &config,
+                Box::new(MockSource::new(rows)),
+                crate::connectors::create_target(&target).unwrap(),
+            )
+            .await
+            .unwrap();
+        }
+
+        let history = TransferEngine::read_target_rows(&config).await.unwrap();
+        assert_eq!(history.len(), 3);
+        let expired: Vec<&Row> = history
+            .iter()
+            .filter(|row| row.get(VALID_TO_COLUMN) != Some(&Value::Null))
+            .collect();
+        assert_eq!(expired.len(), 1);
+        assert_eq!(
+            expired[0].get("name"),
+            Some(&Value::String("Alice".to_string()))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_scd2_refuses_tables_without_history_columns() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = format!("{}#customers", dir.path().join("dim.db").display());
+        let plain = Config {
+            source: "test.csv".to_string(),
+            target: target.clone(),
+            ..Default::default()
+        };
+        TransferEngine::execute(
+            &plain,
+            Box::new(MockSource::new(numbered_rows(2))),
+            crate::connectors::create_target(&target).unwrap(),
+        )
+        .await
+        .unwrap();
+
+        let config = Config {
+            scd2: true,
+            key_columns: vec!["id".to_string()],
+            ..plain.clone()
+        };
+        let err = TransferEngine::execute(
+            &config,
+            Box::new(MockSource::new(numbered_rows(3))),
+            crate::connectors::create_target(&target).unwrap(),
+        )
+        .await
+        .unwrap_err();
+        assert!(err.to_string().contains("valid_from"));
+        let rows = TransferEngine::read_target_rows(&plain).await.unwrap();
+        assert_eq!(rows.len(), 2);
+    }
+
+    // Source whose reads never finish, like a query the database is stuck on
+    struct StalledSource {
+        inner: MockSource,
+    }
+
+    #[async_trait]
+    impl Source for StalledSource {
+        async fn connect(&mut self) -> Result<()> {
+            self.inner.connect().await
+        }
+
+        async fn infer_schema(&mut self, sample_size: usize) -> Result<Schema> {
+            self.inner.infer_schema(sample_size).await
+        }
+
+        async fn read_batch(&mut self, _batch_size: usize) -> Result<Vec<Row>> {
+            std::future::pending().await
+        }
+
+        async fn estimated_row_count(&self) -> Result<Option<usize>> {
+            Ok(None)
+        }
+
+        async fn reset(&mut self) -> Result<()> {
+            Ok(())
+        }
+
+        fn has_more(&self) -> bool {
+            true
+        }
+    }
+
+    #[tokio::test]
+    async fn test_batch_timeout_aborts_stalled_reads() {
+        let config = Config {
+            source: "test.csv".to_string(),
+            target: "test.db#users".to_string(),
+            batch_timeout: Some(1),
+            ..Default::default()
+        };
+        let mut source = StalledSource {
+            inner: MockSource::new(numbered_rows(2)),
+        };
+
+        let read = read_with_timeout(&mut source, 10, config.batch_timeout).await;
+        let err = read.unwrap_err();
+        assert!(err.to_string().contains("--batch-timeout of 1s"));
+    }
+
+    #[tokio::test]
+    async fn test_consistent_snapshot_requires_supporting_source() {
+        let config = Config {
+            source: "test.csv".to_string(),
+            target: "test.db#users".to_string(),
+            consistent_snapshot: true,
+            ..Default::default()
+        };
+
+        let err = TransferEngine::execute(
+            &config,
+            Box::new(MockSource::new(numbered_rows(2))),
+            Box::new(MockTarget::new()),
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(err, TinyEtlError::Configuration(_)));
+        assert!(err.to_string().contains("--consistent-snapshot"));
+    }
+
+    #[tokio::test]
+    async fn test_keyset_column_requires_database_source() {
+        let config = Config {
+            source: "test.csv".to_string(),
+            target: "test.db#users".to_string(),
+            keyset_column: Some("id".to_string()),
+            ..Default::default()
+        };
+
+        let err = TransferEngine::execute(
+            &config,
+            Box::new(MockSource::new(numbered_rows(2))),
+            Box::new(MockTarget::new()),
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(err, TinyEtlError::Configuration(_)));
+        assert!(err.to_string().contains("--keyset-column"));
+    }
+
+    #[tokio::test]
+    async fn test_if_exists_policy_for_existing_targets() {
+        use crate::connectors::csv::CsvTarget;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("users.csv");
+        let path = path.to_str().unwrap();
+        std::fs::write(path, "old\n1\n").unwrap();
+
+        let run = |if_exists| async move {
+            let config = Config {
+                source: "test.csv".to_string(),
+                target: path.to_string(),
+                if_exists,
+                ..Default::default()
+            };
+            TransferEngine::execute(
+                &config,
+                Box::new(MockSource::new(numbered_rows(2))),
+                Box::new(CsvTarget::new(path).unwrap()),
+            )
+            .await
+        };
+
+        let err = run(IfExists::Fail).await.unwrap_err();
+        assert!(err.to_string().contains("already exists"));
+        assert_eq!(std::fs::read_to_string(path).unwrap(), "old\n1\n");
+
+        run(IfExists::Replace).await.unwrap();
+        let written = std::fs::read_to_string(path).unwrap();
+        assert_eq!(written.lines().count(), 3);
+        assert!(!written.contains("old"));
+
+        let config = Config {
+            truncate: true,
+            if_exists: IfExists::Replace,
+            ..Default::default()
+        };
+        let err = TransferEngine::execute(
+            &config,
+            Box::new(MockSource::new(numbered_rows(2))),
+            Box::new(MockTarget::new()),
+        )
+        .await
+        .unwrap_err();
+        assert!(err.to_string().contains("--truncate cannot be combined"));
+    }
+
+    #[tokio::test]
+    async fn test_output_names_apply_to_target_only() {
+        use crate::connectors::csv::CsvTarget;
+        use crate::output_names::HeaderCase;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("users.csv");
+        let path = path.to_str().unwrap();
+        let config = Config {
+            source: "test.csv".to_string(),
+            target: path.to_string(),
+            output_header_case: HeaderCase::Upper,
+            rename_columns: [("id".to_string(), "USER_NO".to_string())].into(),
+            column_order: vec!["id".to_string()],
+            ..Default::default()
+        };
+        TransferEngine::execute(
+            &config,
+            Box::new(MockSource::new(numbered_rows(2))),
+            Box::new(CsvTarget::new(path).unwrap()),
+        )
+        .await
+        .unwrap();
+
+        let written = std::fs::read_to_string(path).unwrap();
+        assert_eq!(written.lines().next(), Some("USER_NO,NAME"));
+    }
+
+    #[tokio::test]
+    async fn test_codecs_decode_on_read_and_encode_on_write() {
+        use crate::connectors::csv::CsvTarget;
+
+        let mut rows = numbered_rows(3);
+        rows[0].insert(
+            "name".to_string(),
+            Value::String("550E8400E29B41D4A716446655440000".to_string()),
+        );
+        rows[1].insert(
+            "name".to_string(),
+            Value::String("{6ba7b810-9dad-11d1-80b4-00c04fd430c8}".to_string()),
+        );
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("users.csv");
+        let path = path.to_str().unwrap();
+        let config = Config {
+            source: "test.csv".to_string(),
+            target: path.to_string(),
+            codecs: [("name".to_string(), "hex-uuid".to_string())].into(),
+            on_row_error: RowErrorPolicy::Skip,
+            ..Default::default()
+        };
+        let stats = TransferEngine::execute(
+            &config,
+            Box::new(MockSource::new(rows)),
+            Box::new(CsvTarget::new(path).unwrap()),
+        )
+        .await
+        .unwrap();
+        // user2 is not a UUID
+        assert_eq!(stats.rows_skipped, 1);
+
+        let written = std::fs::read_to_string(path).unwrap();
+        assert!(written.contains("0,550e8400e29b41d4a716446655440000"));
+        assert!(written.contains("1,6ba7b8109dad11d180b400c04fd430c8"));
+    }
 }
@@ -1,14 +1,101 @@
-use mlua::{Function, Lua, Table, Value as LuaValue};
+use mlua::{Function, HookTriggers, Lua, Table, Value as LuaValue, Variadic};
+use regex::Regex;
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
-use std::path::Path;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
 use tracing::{debug, warn};
 
 use crate::{
+    config::Config,
+    hashing::Hasher,
     schema::{Column, Row, Schema, SchemaInferer, Value},
     Result, TinyEtlError,
 };
 
+/// Default time a transform may spend on a single row before it is aborted
+pub const DEFAULT_ROW_TIMEOUT_MS: u64 = 1_000;
+
+/// Number of Lua VM instructions between row time limit checks
+const TIMEOUT_CHECK_INTERVAL: u32 = 10_000;
+
+/// Lua code run before user scripts when the sandbox is enabled. Removes file,
+/// process and module loading access while keeping harmless time helpers.
+const SANDBOX_PRELUDE: &str = r#"
+io = nil
+dofile = nil
+loadfile = nil
+require = nil
+package = nil
+local os_time, os_date, os_clock, os_difftime = os.time, os.date, os.clock, os.difftime
+os = { time = os_time, date = os_date, clock = os_clock, difftime = os_difftime }
+local text_load = load
+load = function(...)
+  local chunk, name, _, env = ...
+  if select('#', ...) >= 4 then return text_load(chunk, name, "t", env) end
+  return text_load(chunk, name, "t")
+end
+"#;
+
+/// Helper modules bundled into the binary, available to `require` in every transform
+const BUNDLED_MODULES: &[(&str, &str)] = &[
+    ("tinyetl.strings", include_str!("lua/strings.lua")),
+    ("tinyetl.dates", include_str!("lua/dates.lua")),
+    ("tinyetl.json", "return json"),
+];
+
+/// Maximum nesting depth when converting Lua tables to JSON (guards against cycles)
+const MAX_JSON_DEPTH: usize = 128;
+
+/// Maximum number of compiled patterns kept by the Lua `re` library
+const REGEX_CACHE_SIZE: usize = 256;
+
+/// Registry key of the table caching modules loaded by the sandboxed `require`
+const LOADED_MODULES_KEY: &str = "tinyetl_loaded_modules";
+
+/// Sandbox and resource limit settings for Lua transforms
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransformOptions {
+    /// Keep the `io`, `os` and module loading libraries available to scripts
+    pub allow_io: bool,
+    /// Maximum time a transform may spend on a single row (None disables the limit)
+    pub row_timeout: Option<Duration>,
+    /// Directories searched by `require` for shared helper modules
+    pub lua_path: Vec<PathBuf>,
+}
+
+impl Default for TransformOptions {
+    fn default() -> Self {
+        Self {
+            allow_io: false,
+            row_timeout: Some(Duration::from_millis(DEFAULT_ROW_TIMEOUT_MS)),
+            lua_path: Vec::new(),
+        }
+    }
+}
+
+impl TransformOptions {
+    /// Build transform options from the job configuration (a timeout of 0 disables the limit)
+    pub fn from_config(config: &Config) -> Self {
+        let timeout_ms = config.lua_timeout_ms.unwrap_or(DEFAULT_ROW_TIMEOUT_MS);
+        Self {
+            allow_io: config.lua_allow_io,
+            row_timeout: (timeout_ms > 0).then(|| Duration::from_millis(timeout_ms)),
+            lua_path: config.lua_path.iter().map(PathBuf::from).collect(),
+        }
+    }
+}
+
+/// Tracks the row currently being transformed so the instruction hook can enforce the time limit
+#[derive(Debug, Default)]
+struct RowClock {
+    started: Cell<Option<Instant>>,
+    timed_out: Cell<bool>,
+}
+
 /// Configuration for data transformation
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(tag = "type", content = "value", rename_all = "lowercase")]
@@ -33,16 +120,80 @@ pub struct Transformer {
     lua: Lua,
     has_transform: bool,
     inferred_schema: Option<Schema>,
+    row_clock: Rc<RowClock>,
+    row_timeout: Option<Duration>,
 }
 
 impl Transformer {
-    /// Create a new transformer with the given configuration
+    /// Create a new sandboxed transformer with the given configuration and default limits
     pub fn new(config: &TransformConfig) -> Result<Self> {
+        Self::with_options(config, &TransformOptions::default())
+    }
+
+    /// Create a new transformer with explicit sandbox and resource limit settings
+    pub fn with_options(config: &TransformConfig, options: &TransformOptions) -> Result<Self> {
         let lua = Lua::new();
+        let row_clock = Rc::new(RowClock::default());
+
+        if !options.allow_io {
+            lua.load(SANDBOX_PRELUDE).exec().map_err(|e| {
+                TinyEtlError::Configuration(format!("Failed to initialize Lua sandbox: {}", e))
+            })?;
+        }
+
+        // Transform files can require helpers that live next to them
+        let mut search_paths = options.lua_path.clone();
+        if let TransformConfig::File(path) = config {
+            match Path::new(path).parent() {
+                Some(parent) if !parent.as_os_str().is_empty() => {
+                    search_paths.push(parent.to_path_buf())
+                }
+                _ => search_paths.push(PathBuf::from(".")),
+            }
+        }
+        Self::install_module_loader(&lua, &search_paths, options.allow_io).map_err(|e| {
+            TinyEtlError::Configuration(format!("Failed to set up Lua module loading: {}", e))
+        })?;
+        Self::install_json_library(&lua).map_err(|e| {
+            TinyEtlError::Configuration(format!("Failed to set up Lua json library: {}", e))
+        })?;
+        Self::install_regex_library(&lua).map_err(|e| {
+            TinyEtlError::Configuration(format!("Failed to set up Lua re library: {}", e))
+        })?;
+        Self::install_hash_library(&lua).map_err(|e| {
+            TinyEtlError::Configuration(format!("Failed to set up Lua hash library: {}", e))
+        })?;
+
+        if let Some(timeout) = options.row_timeout {
+            let clock = row_clock.clone();
+            lua.set_hook(
+                HookTriggers {
+                    every_nth_instruction: Some(TIMEOUT_CHECK_INTERVAL),
+                    ..Default::default()
+                },
+                move |_lua, _debug| {
+                    if let Some(started) = clock.started.get() {
+                        if started.elapsed() > timeout {
+                            clock.timed_out.set(true);
+                            return Err(mlua::Error::RuntimeError(
+                                "row time limit exceeded".to_string(),
+                            ));
+                        }
+                    }
+                    Ok(())
+                },
+            )
+            .map_err(|e| {
+                TinyEtlError::Configuration(format!("Failed to install Lua time limit: {}", e))
+            })?;
+        }
+
         let mut transformer = Self {
             lua,
             has_transform: false,
             inferred_schema: None,
+            row_clock,
+            row_timeout: options.row_timeout,
         };
 
         match config {
@@ -63,6 +214,289 @@ impl Transformer {
         Ok(transformer)
     }
 
+    /// Make bundled helper modules and modules from `search_paths` available to `require`.
+    /// With io allowed the standard `package` searchers are extended; in the sandbox a
+    /// restricted `require` that only reads `.lua` files from the search paths is installed.
+    fn install_module_loader(
+        lua: &Lua,
+        search_paths: &[PathBuf],
+        allow_io: bool,
+    ) -> mlua::Result<()> {
+        if allow_io {
+            let package: Table = lua.globals().get("package")?;
+            let default_path: String = package.get("path")?;
+            let prefix: String = search_paths
+                .iter()
+                .map(|dir| format!("{0}/?.lua;{0}/?/init.lua;", dir.display()))
+                .collect();
+            package.set("path", prefix + &default_path)?;
+
+            let preload: Table = package.get("preload")?;
+            for (name, source) in BUNDLED_MODULES {
+                preload.set(*name, lua.load(*source).into_function()?)?;
+            }
+            return Ok(());
+        }
+
+        lua.set_named_registry_value(LOADED_MODULES_KEY, lua.create_table()?)?;
+        let search_paths = search_paths.to_vec();
+        let require = lua.create_function(move |lua, name: String| {
+            let loaded: Table = lua.named_registry_value(LOADED_MODULES_KEY)?;
+            let cached: LuaValue = loaded.get(name.as_str())?;
+            if !matches!(cached, LuaValue::Nil) {
+                return Ok(cached);
+            }
+
+            let source = match BUNDLED_MODULES.iter().find(|(bundled, _)| *bundled == name) {
+                Some((_, source)) => source.to_string(),
+                None => {
+                    let path = Self::find_module(&search_paths, &name).ok_or_else(|| {
+                        mlua::Error::RuntimeError(format!(
+                            "module '{}' not found in lua path ({})",
+                            name,
+                            search_paths
+                                .iter()
+                                .map(|p| p.display().to_string())
+                                .collect::<Vec<_>>()
+                                .join(", ")
+                        ))
+                    })?;
+                    std::fs::read_to_string(&path).map_err(|e| {
+                        mlua::Error::RuntimeError(format!(
+                            "failed to read module '{}' from {}: {}",
+                            name,
+                            path.display(),
+                            e
+                        ))
+                    })?
+                }
+            };
+
+            let module: LuaValue = lua.load(&source).call(name.clone())?;
+            // Like Lua's require, modules that return nothing are recorded as `true`
+            let module = match module {
+                LuaValue::Nil => LuaValue::Boolean(true),
+                other => other,
+            };
+            loaded.set(name.as_str(), module.clone())?;
+            Ok(module)
+        })?;
+        lua.globals().set("require", require)
+    }
+
+    /// Expose `json.decode(str)` and `json.encode(value)` to transforms
+    fn install_json_library(lua: &Lua) -> mlua::Result<()> {
+        let json = lua.create_table()?;
+        json.set(
+            "decode",
+            lua.create_function(|lua, text: String| {
+                let value: serde_json::Value = serde_json::from_str(&text)
+                    .map_err(|e| mlua::Error::RuntimeError(format!("json.decode: {}", e)))?;
+                json_to_lua(lua, &value)
+            })?,
+        )?;
+        json.set(
+            "encode",
+            lua.create_function(|_, value: LuaValue| {
+                let value = lua_to_json(value, 0)?;
+                serde_json::to_string(&value)
+                    .map_err(|e| mlua::Error::RuntimeError(format!("json.encode: {}", e)))
+            })?,
+        )?;
+        lua.globals().set("json", json)
+    }
+
+    /// Expose standard (non-Lua-pattern) regular expressions to transforms:
+    /// `re.match(s, pattern)` returns the first match or nil,
+    /// `re.capture(s, pattern)` returns the capture groups (by index and name) or nil,
+    /// `re.replace(s, pattern, replacement)` replaces all matches (`$1`/`${name}` expand groups),
+    /// `re.find_all(s, pattern)` returns all matches.
+    /// Nil subjects return nil so null columns pass through unchanged.
+    fn install_regex_library(lua: &Lua) -> mlua::Result<()> {
+        let cache: Rc<RefCell<HashMap<String, Regex>>> = Rc::new(RefCell::new(HashMap::new()));
+        let compile = move |pattern: &str| -> mlua::Result<Regex> {
+            let mut cache = cache.borrow_mut();
+            if let Some(regex) = cache.get(pattern) {
+                return Ok(regex.clone());
+            }
+            let regex = Regex::new(pattern).map_err(|e| {
+                mlua::Error::RuntimeError(format!("invalid regex '{}': {}", pattern, e))
+            })?;
+            if cache.len() >= REGEX_CACHE_SIZE {
+                cache.clear();
+            }
+            cache.insert(pattern.to_string(), regex.clone());
+            Ok(regex)
+        };
+        let compile = Rc::new(compile);
+
+        let re = lua.create_table()?;
+
+        let c = compile.clone();
+        re.set(
+            "match",
+            lua.create_function(move |_, (subject, pattern): (Option<String>, String)| {
+                let regex = c(&pattern)?;
+                Ok(subject.and_then(|s| regex.find(&s).map(|m| m.as_str().to_string())))
+            })?,
+        )?;
+
+        let c = compile.clone();
+        re.set(
+            "capture",
+            lua.create_function(move |lua, (subject, pattern): (Option<String>, String)| {
+                let regex = c(&pattern)?;
+                let subject = match subject {
+                    Some(subject) => subject,
+                    None => return Ok(LuaValue::Nil),
+                };
+                let captures = match regex.captures(&subject) {
+                    Some(captures) => captures,
+                    None => return Ok(LuaValue::Nil),
+                };
+                let table = lua.create_table()?;
+                for (i, group) in captures.iter().enumerate().skip(1) {
+                    if let Some(group) = group {
+                        table.raw_set(i, group.as_str())?;
+                    }
+                }
+                for name in regex.capture_names().flatten() {
+                    if let Some(group) = captures.name(name) {
+                        table.raw_set(name, group.as_str())?;
+                    }
+                }
+                Ok(LuaValue::Table(table))
+            })?,
+        )?;
+
+        let c = compile.clone();
+        re.set(
+            "replace",
+            lua.create_function(
+                move |_, (subject, pattern, replacement): (Option<String>, String, String)| {
+                    let regex = c(&pattern)?;
+                    Ok(subject.map(|s| regex.replace_all(&s, replacement.as_str()).into_owned()))
+                },
+            )?,
+        )?;
+
+        let c = compile;
+        re.set(
+            "find_all",
+            lua.create_function(move |lua, (subject, pattern): (Option<String>, String)| {
+                let regex = c(&pattern)?;
+                let table = lua.create_table()?;
+                if let Some(subject) = subject {
+                    for (i, m) in regex.find_iter(&subject).enumerate() {
+                        table.raw_set(i + 1, m.as_str())?;
+                    }
+                }
+                Ok(table)
+            })?,
+        )?;
+
+        lua.globals().set("re", re)
+    }
+
+    /// Expose deterministic hashing to transforms for surrogate keys and pseudonymization:
+    /// `hash.md5(s)`, `hash.sha256(s)`, `hash.hmac_sha256(s, key)`,
+    /// `hash.hmac_secret(s, secret_id)` (key read from TINYETL_SECRET_{id}) and
+    /// `hash.key(...)` (SHA-256 over several values). Nil subjects return nil.
+    fn install_hash_library(lua: &Lua) -> mlua::Result<()> {
+        let hash = lua.create_table()?;
+
+        hash.set(
+            "md5",
+            lua.create_function(|_, subject: Option<mlua::String>| {
+                Ok(subject.map(|s| Hasher::md5_hex(s.as_bytes())))
+            })?,
+        )?;
+        hash.set(
+            "sha256",
+            lua.create_function(|_, subject: Option<mlua::String>| {
+                Ok(subject.map(|s| Hasher::sha256_hex(s.as_bytes())))
+            })?,
+        )?;
+        hash.set(
+            "hmac_sha256",
+            lua.create_function(|_, (subject, key): (Option<mlua::String>, mlua::String)| {
+                Ok(subject.map(|s| Hasher::hmac_sha256_hex(key.as_bytes(), s.as_bytes())))
+            })?,
+        )?;
+
+        // Secrets are resolved once and kept for the lifetime of the transformer
+        let secrets: RefCell<HashMap<String, String>> = RefCell::new(HashMap::new());
+        hash.set(
+            "hmac_secret",
+            lua.create_function(
+                move |_, (subject, secret_id): (Option<mlua::String>, String)| {
+                    let subject = match subject {
+                        Some(subject) => subject,
+                        None => return Ok(None),
+                    };
+                    let mut secrets = secrets.borrow_mut();
+                    if !secrets.contains_key(&secret_id) {
+                        let key = crate::secrets::resolve_secret(&secret_id).map_err(|e| {
+                            mlua::Error::RuntimeError(format!("hash.hmac_secret: {}", e))
+                        })?;
+                        secrets.insert(secret_id.clone(), key);
+                    }
+                    let key = &secrets[&secret_id];
+                    Ok(Some(Hasher::hmac_sha256_hex(
+                        key.as_bytes(),
+                        subject.as_bytes(),
+                    )))
+                },
+            )?,
+        )?;
+
+        hash.set(
+            "key",
+            lua.create_function(|_, values: Variadic<LuaValue>| {
+                let parts = values
+                    .iter()
+                    .map(|value| match value {
+                        LuaValue::Nil => Ok(None),
+                        LuaValue::String(s) => Ok(Some(s.to_str()?.to_string())),
+                        LuaValue::Integer(i) => Ok(Some(i.to_string())),
+                        LuaValue::Number(f) => Ok(Some(f.to_string())),
+                        LuaValue::Boolean(b) => Ok(Some(b.to_string())),
+                        other => Err(mlua::Error::RuntimeError(format!(
+                            "hash.key: cannot hash value of type '{}'",
+                            other.type_name()
+                        ))),
+                    })
+                    .collect::<mlua::Result<Vec<_>>>()?;
+                Ok(Hasher::composite_key(&parts))
+            })?,
+        )?;
+
+        lua.globals().set("hash", hash)
+    }
+
+    /// Resolve a dotted module name to `<dir>/a/b.lua` or `<dir>/a/b/init.lua`
+    fn find_module(search_paths: &[PathBuf], name: &str) -> Option<PathBuf> {
+        let valid = name.split('.').all(|part| {
+            !part.is_empty()
+                && part
+                    .chars()
+                    .all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+        });
+        if !valid {
+            return None;
+        }
+
+        let relative: PathBuf = name.split('.').collect();
+        search_paths.iter().find_map(|dir| {
+            let file = dir.join(&relative).with_extension("lua");
+            if file.is_file() {
+                return Some(file);
+            }
+            let init = dir.join(&relative).join("init.lua");
+            init.is_file().then_some(init)
+        })
+    }
+
     /// Load transformation from a Lua file
     fn load_from_file(&mut self, path: &str) -> Result<()> {
         if !Path::new(path).exists() {
@@ -96,7 +530,6 @@ impl Transformer {
 
         self.has_transform = true;
 
-
         debug!("Loaded transform function from file: {}", path);
         Ok(())
     }
@@ -162,7 +595,6 @@ impl Transformer {
 
         self.has_transform = true;
 
-
         debug!("Created transform function from script: {}", script);
         Ok(())
     }
@@ -332,22 +764,25 @@ impl Transformer {
         // Convert Row to Lua table
         let lua_row = self.row_to_lua_table(row)?;
 
-        // Call the transform function
-        let result: LuaValue = transform_fn.call(lua_row).map_err(|e| {
-            TinyEtlError::Transform(format!("Lua transform function failed: {}", e))
-        })?;
+        let result = self.call_with_time_limit(&transform_fn, lua_row, "transform")?;
 
         // Handle filtering: if Lua returns nil, filter out this row
         match result {
             LuaValue::Nil => Ok(None),
             LuaValue::Table(table) => {
                 // Convert result back to Row
-                let row = self.lua_table_to_row(table)?;
+                let mut transformed = self.lua_table_to_row(table)?;
+                // Binary values come back from Lua as strings; keep them binary
+                for (key, value) in transformed.iter_mut() {
+                    if let (Some(Value::Bytes(_)), Value::String(s)) = (row.get(key), &*value) {
+                        *value = Value::Bytes(s.clone().into_bytes());
+                    }
+                }
                 // Also check if the table is empty (another way to filter)
-                if row.is_empty() {
+                if transformed.is_empty() {
                     Ok(None)
                 } else {
-                    Ok(Some(row))
+                    Ok(Some(transformed))
                 }
             }
             _ => Err(TinyEtlError::Transform(
@@ -356,6 +791,63 @@ impl Transformer {
         }
     }
 
+    /// Call a row-level Lua function, timing it for the row time limit hook
+    fn call_with_time_limit<'lua>(
+        &'lua self,
+        function: &Function<'lua>,
+        row: Table<'lua>,
+        what: &str,
+    ) -> Result<LuaValue<'lua>> {
+        self.row_clock.timed_out.set(false);
+        self.row_clock.started.set(Some(Instant::now()));
+        let result = function.call::<_, LuaValue>(row);
+        self.row_clock.started.set(None);
+
+        result.map_err(|e| {
+            if self.row_clock.timed_out.get() {
+                TinyEtlError::Transform(format!(
+                    "Lua {} exceeded the per-row time limit of {}ms (possible infinite loop)",
+                    what,
+                    self.row_timeout.unwrap_or_default().as_millis()
+                ))
+            } else {
+                TinyEtlError::Transform(format!("Lua {} function failed: {}", what, e))
+            }
+        })
+    }
+
+    /// Compile a Lua boolean expression over `row` (e.g. `row.country == 'US'`)
+    /// and register it under `name` for `eval_predicate`
+    pub fn load_predicate(&self, name: &str, expression: &str) -> Result<()> {
+        let code = format!("return function(row) return ({}) end", expression);
+        let function: Function = self.lua.load(&code).eval().map_err(|e| {
+            TinyEtlError::Configuration(format!("Invalid predicate '{}': {}", expression, e))
+        })?;
+        self.lua
+            .set_named_registry_value(&Self::predicate_key(name), function)
+            .map_err(|e| {
+                TinyEtlError::Configuration(format!("Failed to register predicate: {}", e))
+            })
+    }
+
+    /// Evaluate a predicate registered with `load_predicate` against a row.
+    /// Follows Lua truthiness: only `nil` and `false` are false.
+    pub fn eval_predicate(&self, name: &str, row: &Row) -> Result<bool> {
+        let function: Function = self
+            .lua
+            .named_registry_value(&Self::predicate_key(name))
+            .map_err(|e| {
+                TinyEtlError::Configuration(format!("Unknown predicate '{}': {}", name, e))
+            })?;
+        let lua_row = self.row_to_lua_table(row)?;
+        let result = self.call_with_time_limit(&function, lua_row, "predicate")?;
+        Ok(!matches!(result, LuaValue::Nil | LuaValue::Boolean(false)))
+    }
+
+    fn predicate_key(name: &str) -> String {
+        format!("tinyetl_predicate_{}", name)
+    }
+
     /// Convert a Row to a Lua table
     fn row_to_lua_table(&self, row: &Row) -> Result<Table<'_>> {
         let table = self
@@ -372,7 +864,7 @@ impl Transformer {
                     let f: f64 = (*d).try_into().unwrap_or(0.0);
                     LuaValue::Number(f)
                 }
-                
+
                 Value::Boolean(b) => LuaValue::Boolean(*b),
                 Value::Date(dt) => LuaValue::String(self.lua.create_string(&dt.to_rfc3339())?),
                 Value::Json(j) => {
@@ -380,6 +872,7 @@ impl Transformer {
                     let json_str = serde_json::to_string(j).unwrap_or_else(|_| "{}".to_string());
                     LuaValue::String(self.lua.create_string(&json_str)?)
                 }
+                Value::Bytes(b) => LuaValue::String(self.lua.create_string(b)?),
                 Value::Null => LuaValue::Nil,
             };
             table.set(key.as_str(), lua_value)?;
@@ -398,12 +891,11 @@ impl Transformer {
             })?;
 
             let value = match lua_value {
-                LuaValue::String(s) => {
-                    let str_val = s.to_str().map_err(|e| {
-                        TinyEtlError::Transform(format!("Failed to convert Lua string: {}", e))
-                    })?;
-                    Value::String(str_val.to_string())
-                }
+                LuaValue::String(s) => match s.to_str() {
+                    Ok(str_val) => Value::String(str_val.to_string()),
+                    // Lua strings are byte strings; anything not UTF-8 is binary
+                    Err(_) => Value::Bytes(s.as_bytes().to_vec()),
+                },
                 LuaValue::Integer(i) => Value::Integer(i),
                 LuaValue::Number(f) => {
                     // Convert f64 to Decimal
@@ -414,6 +906,15 @@ impl Transformer {
                 }
                 LuaValue::Boolean(b) => Value::Boolean(b),
                 LuaValue::Nil => Value::Null,
+                // Structured values (e.g. built from json.decode) become JSON columns
+                LuaValue::Table(table) => {
+                    Value::Json(lua_to_json(LuaValue::Table(table), 0).map_err(|e| {
+                        TinyEtlError::Transform(format!(
+                            "Failed to convert column '{}' to JSON: {}",
+                            key, e
+                        ))
+                    })?)
+                }
                 _ => {
                     warn!(
                         "Unsupported Lua value type for column '{}', converting to string",
@@ -431,7 +932,7 @@ impl Transformer {
 
     /// Infer schema from the first transformed row
     fn infer_schema_from_first_row(&mut self, row: &Row) -> Result<()> {
-        let columns: Vec<Column> = row
+        let mut columns: Vec<Column> = row
             .iter()
             .map(|(name, value)| {
                 let data_type = SchemaInferer::infer_type(value);
@@ -442,6 +943,8 @@ impl Transformer {
                 }
             })
             .collect();
+        // Rows are hash maps; name order keeps the output the same on every run
+        columns.sort_by(|a, b| a.name.cmp(&b.name));
 
         self.inferred_schema = Some(Schema {
             columns,
@@ -462,7 +965,6 @@ impl Transformer {
 
     /// Validate and filter a row based on the inferred schema
     fn validate_and_filter_row(&self, mut row: Row) -> Result<Row> {
-        
         let schema = self
             .inferred_schema
             .as_ref()
@@ -507,11 +1009,93 @@ impl Transformer {
     }
 }
 
+/// Convert a JSON value to the equivalent Lua value (objects and arrays become tables)
+fn json_to_lua<'lua>(lua: &'lua Lua, value: &serde_json::Value) -> mlua::Result<LuaValue<'lua>> {
+    Ok(match value {
+        serde_json::Value::Null => LuaValue::Nil,
+        serde_json::Value::Bool(b) => LuaValue::Boolean(*b),
+        serde_json::Value::Number(n) => match n.as_i64() {
+            Some(i) => LuaValue::Integer(i),
+            None => LuaValue::Number(n.as_f64().unwrap_or(0.0)),
+        },
+        serde_json::Value::String(s) => LuaValue::String(lua.create_string(s)?),
+        serde_json::Value::Array(items) => {
+            let table = lua.create_table()?;
+            for (i, item) in items.iter().enumerate() {
+                table.raw_set(i + 1, json_to_lua(lua, item)?)?;
+            }
+            LuaValue::Table(table)
+        }
+        serde_json::Value::Object(map) => {
+            let table = lua.create_table()?;
+            for (key, item) in map {
+                table.raw_set(key.as_str(), json_to_lua(lua, item)?)?;
+            }
+            LuaValue::Table(table)
+        }
+    })
+}
+
+/// Convert a Lua value to JSON. Tables with consecutive integer keys starting at 1
+/// become arrays, all other tables become objects.
+fn lua_to_json(value: LuaValue, depth: usize) -> mlua::Result<serde_json::Value> {
+    if depth > MAX_JSON_DEPTH {
+        return Err(mlua::Error::RuntimeError(
+            "json.encode: table nesting too deep (cyclic table?)".to_string(),
+        ));
+    }
+
+    Ok(match value {
+        LuaValue::Nil => serde_json::Value::Null,
+        LuaValue::Boolean(b) => serde_json::Value::Bool(b),
+        LuaValue::Integer(i) => serde_json::Value::from(i),
+        LuaValue::Number(f) => serde_json::Number::from_f64(f)
+            .map(serde_json::Value::Number)
+            .unwrap_or(serde_json::Value::Null),
+        LuaValue::String(s) => serde_json::Value::String(s.to_str()?.to_string()),
+        LuaValue::Table(table) => {
+            let len = table.raw_len() as usize;
+            let count = table.clone().pairs::<LuaValue, LuaValue>().count();
+            if len > 0 && len == count {
+                let items = table
+                    .sequence_values::<LuaValue>()
+                    .map(|item| lua_to_json(item?, depth + 1))
+                    .collect::<mlua::Result<Vec<_>>>()?;
+                serde_json::Value::Array(items)
+            } else {
+                let mut map = serde_json::Map::new();
+                for pair in table.pairs::<LuaValue, LuaValue>() {
+                    let (key, item) = pair?;
+                    let key = match key {
+                        LuaValue::String(s) => s.to_str()?.to_string(),
+                        LuaValue::Integer(i) => i.to_string(),
+                        LuaValue::Number(f) => f.to_string(),
+                        other => {
+                            return Err(mlua::Error::RuntimeError(format!(
+                                "json.encode: unsupported key type '{}'",
+                                other.type_name()
+                            )))
+                        }
+                    };
+                    map.insert(key, lua_to_json(item, depth + 1)?);
+                }
+                serde_json::Value::Object(map)
+            }
+        }
+        other => {
+            return Err(mlua::Error::RuntimeError(format!(
+                "json.encode: cannot encode value of type '{}'",
+                other.type_name()
+            )))
+        }
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::collections::HashMap;
     use crate::schema::{DataType, Value};
+    use std::collections::HashMap;
 
     #[test]
     fn test_transform_config_none_yaml() {
@@ -651,7 +1235,6 @@ mod tests {
         let result = transformer.transform_batch(&[row]).unwrap();
         assert_eq!(result.len(), 1);
 
-
         let transformed = &result[0];
         // Check new columns
         assert_eq!(
@@ -719,7 +1302,6 @@ years_service = current_year - hire_year
         let result = transformer.transform_batch(&[row]).unwrap();
         assert_eq!(result.len(), 1);
 
-
         let transformed = &result[0];
         // Check new columns
         assert_eq!(
@@ -776,7 +1358,6 @@ years_service = current_year - hire_year
         let schema = transformer.get_inferred_schema().unwrap();
         assert_eq!(schema.columns.len(), 2);
 
-
         let col_names: Vec<_> = schema.columns.iter().map(|c| &c.name).collect();
         assert!(col_names.contains(&&"new_col".to_string()));
         assert!(col_names.contains(&&"str_col".to_string()));
@@ -831,7 +1412,6 @@ years_service = current_year - hire_year
         let config = TransformConfig::Inline("new_col=42".to_string());
         let mut transformer = Transformer::new(&config).unwrap();
 
-
         let result = transformer.transform_batch(&[]).unwrap();
         assert!(result.is_empty());
     }
@@ -841,11 +1421,9 @@ years_service = current_year - hire_year
         let config = TransformConfig::None;
         let mut transformer = Transformer::new(&config).unwrap();
 
-
         let mut row = HashMap::new();
         row.insert("test".to_string(), Value::String("value".to_string()));
 
-
         let result = transformer.transform_batch(&[row.clone()]).unwrap();
         assert_eq!(result.len(), 1);
         assert_eq!(result[0], row);
@@ -881,7 +1459,6 @@ years_service = current_year - hire_year
         let config = TransformConfig::None;
         let transformer = Transformer::new(&config).unwrap();
 
-
         let base_schema = Schema {
             columns: vec![Column {
                 name: "test".to_string(),
@@ -892,7 +1469,6 @@ years_service = current_year - hire_year
             primary_key_candidate: None,
         };
 
-
         let merged = transformer.merge_with_base_schema(&base_schema).unwrap();
         assert_eq!(merged.columns.len(), 1);
         assert_eq!(merged.columns[0].name, "test");
@@ -903,17 +1479,14 @@ years_service = current_year - hire_year
         let config = TransformConfig::Inline("new_col=42".to_string());
         let mut transformer = Transformer::new(&config).unwrap();
 
-
         // First transform to establish schema
         let row = HashMap::new();
         transformer.transform_batch(&[row]).unwrap();
 
-
         // Test with a row missing expected columns
         let mut incomplete_row = HashMap::new();
         incomplete_row.insert("extra".to_string(), Value::String("extra".to_string()));
 
-
         let validated = transformer.validate_and_filter_row(incomplete_row).unwrap();
         assert!(validated.contains_key("new_col"));
         assert_eq!(validated.get("new_col"), Some(&Value::Null));
@@ -924,7 +1497,6 @@ years_service = current_year - hire_year
         let config = TransformConfig::Inline("result_col=row.test_col".to_string());
         let transformer = Transformer::new(&config).unwrap();
 
-
         let mut row = HashMap::new();
         row.insert(
             "test_col".to_string(),
@@ -939,7 +1511,6 @@ years_service = current_year - hire_year
         // Test conversion to Lua table
         let lua_table = transformer.row_to_lua_table(&row).unwrap();
 
-
         // Test conversion back to row
         let converted_row = transformer.lua_table_to_row(lua_table).unwrap();
         assert_eq!(
@@ -957,16 +1528,13 @@ years_service = current_year - hire_year
         let config = TransformConfig::Inline("date_col=row.input_date".to_string());
         let transformer = Transformer::new(&config).unwrap();
 
-
         let date = chrono::Utc::now();
         let mut row = HashMap::new();
         row.insert("input_date".to_string(), Value::Date(date));
 
-
         let lua_table = transformer.row_to_lua_table(&row).unwrap();
         let converted_row = transformer.lua_table_to_row(lua_table).unwrap();
 
-
         // Date should be converted to string in RFC3339 format
         match converted_row.get("input_date") {
             Some(Value::String(s)) => {
@@ -992,24 +1560,19 @@ function transform(row)
 end
 "#;
 
-
         let temp_file = "/tmp/test_transform.lua";
         fs::write(temp_file, lua_content).unwrap();
 
-
         let config = TransformConfig::File(temp_file.to_string());
         let mut transformer = Transformer::new(&config).unwrap();
         assert!(transformer.is_enabled());
 
-
         let mut row = HashMap::new();
         row.insert("name".to_string(), Value::String("john".to_string()));
 
-
         let result = transformer.transform_batch(&[row]).unwrap();
         assert_eq!(result.len(), 1);
 
-
         let transformed = &result[0];
         assert_eq!(
             transformed.get("upper_name"),
@@ -1021,7 +1584,6 @@ end
         );
         assert_eq!(transformed.get("name_length"), Some(&Value::Integer(4)));
 
-
         // Clean up
         let _ = fs::remove_file(temp_file);
     }
@@ -1030,17 +1592,14 @@ end
     fn test_lua_file_invalid_syntax() {
         use std::fs;
 
-
         let lua_content = "invalid lua syntax {{{";
         let temp_file = "/tmp/test_invalid.lua";
         fs::write(temp_file, lua_content).unwrap();
 
-
         let config = TransformConfig::File(temp_file.to_string());
         let result = Transformer::new(&config);
         assert!(result.is_err());
 
-
         let _ = fs::remove_file(temp_file);
     }
 
@@ -1048,7 +1607,6 @@ end
     fn test_lua_file_missing_transform_function() {
         use std::fs;
 
-
         let lua_content = r#"
 function other_function()
     return "not transform"
@@ -1057,7 +1615,6 @@ end
         let temp_file = "/tmp/test_no_transform.lua";
         fs::write(temp_file, lua_content).unwrap();
 
-
         let config = TransformConfig::File(temp_file.to_string());
         let result = Transformer::new(&config);
         assert!(result.is_err());
@@ -1075,23 +1632,19 @@ end
             TransformConfig::Inline("counter=1; batch_col=row.input or 'default'".to_string());
         let mut transformer = Transformer::new(&_config).unwrap();
 
-
         // First batch
         let mut row1 = HashMap::new();
         row1.insert("input".to_string(), Value::String("first".to_string()));
         let result1 = transformer.transform_batch(&[row1]).unwrap();
 
-
         // Second batch
         let mut row2: HashMap<String, Value> = HashMap::new();
         row2.insert("input".to_string(), Value::String("second".to_string()));
         let result2 = transformer.transform_batch(&[row2]).unwrap();
 
-
         assert_eq!(result1.len(), 1);
         assert_eq!(result2.len(), 1);
 
-
         // Both should have the schema applied
         assert!(result1[0].contains_key("counter"));
         assert!(result2[0].contains_key("counter"));
@@ -1104,17 +1657,14 @@ end
         );
         let mut transformer = Transformer::new(&config).unwrap();
 
-
         let mut row1 = HashMap::new();
         row1.insert("value".to_string(), Value::Integer(21));
         let result1 = transformer.transform_batch(&[row1]).unwrap();
 
-
         let mut row2 = HashMap::new();
         row2.insert("value".to_string(), Value::String("text".to_string()));
         let result2 = transformer.transform_batch(&[row2]).unwrap();
 
-
         assert_eq!(result1[0].get("mixed"), Some(&Value::Integer(42)));
         assert_eq!(
             result2[0].get("mixed"),
@@ -1139,7 +1689,6 @@ end
         let config = TransformConfig::None;
         let transformer = Transformer::new(&config).unwrap();
 
-
         let row = HashMap::new();
         let result = transformer.transform_row(&row);
         assert!(result.is_err());
@@ -1153,7 +1702,6 @@ end
     fn test_transform_function_returns_nil() {
         use std::fs;
 
-
         let lua_content = r#"
 function transform(row)
     -- Return nil to filter out the row
@@ -1163,19 +1711,15 @@ end
         let temp_file = "/tmp/test_filter_nil.lua";
         fs::write(temp_file, lua_content).unwrap();
 
-
         let config = TransformConfig::File(temp_file.to_string());
         let mut transformer = Transformer::new(&config).unwrap();
 
-
         let mut row = HashMap::new();
         row.insert("test".to_string(), Value::String("value".to_string()));
 
-
         let result = transformer.transform_batch(&[row]).unwrap();
         assert_eq!(result.len(), 0); // Row should be filtered out
 
-
         let _ = fs::remove_file(temp_file);
     }
 
@@ -1183,7 +1727,6 @@ end
     fn test_transform_function_returns_empty_table() {
         use std::fs;
 
-
         let lua_content = r#"
 function transform(row)
     -- Return empty table to filter out the row
@@ -1193,19 +1736,15 @@ end
         let temp_file = "/tmp/test_filter_empty.lua";
         fs::write(temp_file, lua_content).unwrap();
 
-
         let config = TransformConfig::File(temp_file.to_string());
         let mut transformer = Transformer::new(&config).unwrap();
 
-
         let mut row = HashMap::new();
         row.insert("test".to_string(), Value::String("value".to_string()));
 
-
         let result = transformer.transform_batch(&[row]).unwrap();
         assert_eq!(result.len(), 0); // Row should be filtered out due to empty table
 
-
         let _ = fs::remove_file(temp_file);
     }
 
@@ -1213,7 +1752,6 @@ end
     fn test_transform_function_returns_non_table() {
         use std::fs;
 
-
         let lua_content = r#"
 function transform(row)
     -- Return a string instead of table
@@ -1223,15 +1761,12 @@ end
         let temp_file = "/tmp/test_invalid_return.lua";
         fs::write(temp_file, lua_content).unwrap();
 
-
         let config = TransformConfig::File(temp_file.to_string());
         let mut transformer = Transformer::new(&config).unwrap();
 
-
         let mut row = HashMap::new();
         row.insert("test".to_string(), Value::String("value".to_string()));
 
-
         let result = transformer.transform_batch(&[row]);
         assert!(result.is_err());
         assert!(result
@@ -1246,7 +1781,6 @@ end
     fn test_unsupported_lua_value_type() {
         use std::fs;
 
-
         let lua_content = r#"
 function transform(row)
     local result = {}
@@ -1259,22 +1793,18 @@ end
         let temp_file = "/tmp/test_unsupported_types.lua";
         fs::write(temp_file, lua_content).unwrap();
 
-
         let config = TransformConfig::File(temp_file.to_string());
         let mut transformer = Transformer::new(&config).unwrap();
 
-
         let row = HashMap::new();
         let result = transformer.transform_batch(&[row]).unwrap();
         assert_eq!(result.len(), 1);
 
-
         // Unsupported types should be converted to string representations
         let transformed = &result[0];
         assert!(transformed.contains_key("func_value"));
         assert!(transformed.contains_key("thread_value"));
 
-
         // Values should be converted to strings with debug format
         if let Some(Value::String(s)) = transformed.get("func_value") {
             assert!(s.contains("Function"));
@@ -1282,7 +1812,6 @@ end
             panic!("Expected string representation of function");
         }
 
-
         let _ = fs::remove_file(temp_file);
     }
 
@@ -1292,7 +1821,6 @@ end
         let config = TransformConfig::Inline("test=42".to_string());
         let transformer = Transformer::new(&config).unwrap();
 
-
         let mut row = HashMap::new();
         row.insert("test".to_string(), Value::String("value".to_string()));
         row.insert("int_val".to_string(), Value::Integer(42));
@@ -1307,11 +1835,9 @@ end
         row.insert("bool_val".to_string(), Value::Boolean(true));
         row.insert("null_val".to_string(), Value::Null);
 
-
         let lua_table = transformer.row_to_lua_table(&row).unwrap();
         let converted_row = transformer.lua_table_to_row(lua_table).unwrap();
 
-
         // Verify all value types are handled correctly
         assert_eq!(
             converted_row.get("test"),
@@ -1332,7 +1858,6 @@ end
         );
         assert_eq!(converted_row.get("bool_val"), Some(&Value::Boolean(true)));
 
-
         // Note: In Lua, nil values don't create table entries, so null_val won't be preserved
         // This is correct behavior - null values are effectively filtered out in Lua tables
         assert_eq!(converted_row.get("null_val"), None);
@@ -1343,7 +1868,6 @@ end
     fn test_lua_explicit_nil_handling() {
         use std::fs;
 
-
         let lua_content = r#"
 function transform(row)
     local result = {}
@@ -1356,19 +1880,15 @@ end
         let temp_file = "/tmp/test_nil_handling.lua";
         fs::write(temp_file, lua_content).unwrap();
 
-
         let config = TransformConfig::File(temp_file.to_string());
         let mut transformer = Transformer::new(&config).unwrap();
 
-
         let mut row = HashMap::new();
         row.insert("input".to_string(), Value::String("test".to_string()));
 
-
         let result = transformer.transform_batch(&[row]).unwrap();
         assert_eq!(result.len(), 1);
 
-
         let transformed = &result[0];
         assert_eq!(
             transformed.get("keep_value"),
@@ -1389,7 +1909,6 @@ end
         );
         assert_eq!(transformed.len(), 2); // Only 2 entries should exist
 
-
         let _ = fs::remove_file(temp_file);
     }
 
@@ -1399,7 +1918,6 @@ end
         let config = TransformConfig::None;
         let transformer = Transformer::new(&config).unwrap();
 
-
         let base_schema = Schema {
             columns: vec![
                 Column {
@@ -1417,7 +1935,6 @@ end
             primary_key_candidate: Some("id".to_string()),
         };
 
-
         // Since no transformations were applied, should return base schema as-is
         let merged = transformer.merge_with_base_schema(&base_schema).unwrap();
         assert_eq!(merged.columns.len(), 2);
@@ -1433,13 +1950,11 @@ end
             TransformConfig::Inline("derived_col=row.value * 2; new_str='constant'".to_string());
         let mut transformer = Transformer::new(&_config).unwrap();
 
-
         // Process a row to infer schema
         let mut row = HashMap::new();
         row.insert("value".to_string(), Value::Integer(10));
         transformer.transform_batch(&[row]).unwrap();
 
-
         let base_schema = Schema {
             columns: vec![Column {
                 name: "value".to_string(),
@@ -1450,7 +1965,6 @@ end
             primary_key_candidate: None,
         };
 
-
         // Should return the inferred schema from transformations
         let merged = transformer.merge_with_base_schema(&base_schema).unwrap();
         let col_names: Vec<&String> = merged.columns.iter().map(|c| &c.name).collect();
@@ -1462,7 +1976,6 @@ end
     fn test_lua_string_creation_all_value_types() {
         use std::fs;
 
-
         let lua_content = r#"
 function transform(row)
     local result = {}
@@ -1480,29 +1993,345 @@ end
         let temp_file = "/tmp/test_string_creation.lua";
         fs::write(temp_file, lua_content).unwrap();
 
-
         let config = TransformConfig::File(temp_file.to_string());
         let mut transformer = Transformer::new(&config).unwrap();
 
-
         let date = chrono::Utc::now();
         let mut row = HashMap::new();
         row.insert("date_val".to_string(), Value::Date(date));
         row.insert("str_val".to_string(), Value::String("test".to_string()));
         row.insert("int_val".to_string(), Value::Integer(123));
 
-
         let result = transformer.transform_batch(&[row]).unwrap();
         assert_eq!(result.len(), 1);
 
-
         let transformed = &result[0];
         assert!(transformed.contains_key("formatted_date"));
         assert!(transformed.contains_key("date_val"));
         assert!(transformed.contains_key("str_val"));
         assert!(transformed.contains_key("int_val"));
 
-
         let _ = fs::remove_file(temp_file);
     }
+
+    #[test]
+    fn test_sandbox_blocks_io_by_default() {
+        let config =
+            TransformConfig::Inline("content=io.open('/etc/hostname'):read('*a')".to_string());
+        let mut transformer = Transformer::new(&config).unwrap();
+
+        let mut row = HashMap::new();
+        row.insert("id".to_string(), Value::Integer(1));
+
+        let result = transformer.transform_batch(&[row]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_sandbox_keeps_safe_os_functions() {
+        let config =
+            TransformConfig::Inline("year=os.date('%Y', 0); exec=os.execute == nil".to_string());
+        let mut transformer = Transformer::new(&config).unwrap();
+
+        let mut row = HashMap::new();
+        row.insert("id".to_string(), Value::Integer(1));
+
+        let result = transformer.transform_batch(&[row]).unwrap();
+        assert_eq!(result[0].get("exec"), Some(&Value::Boolean(true)));
+        assert!(matches!(result[0].get("year"), Some(Value::String(_))));
+    }
+
+    #[test]
+    fn test_sandbox_load_keeps_globals_without_env() {
+        let config = TransformConfig::Inline("floor=load('return math.floor(2.5)')()".to_string());
+        let mut transformer = Transformer::new(&config).unwrap();
+
+        let mut row = HashMap::new();
+        row.insert("id".to_string(), Value::Integer(1));
+
+        let result = transformer.transform_batch(&[row]).unwrap();
+        assert_eq!(result[0].get("floor"), Some(&Value::Integer(2)));
+    }
+
+    #[test]
+    fn test_allow_io_restores_io_library() {
+        let config = TransformConfig::Inline("has_io=io ~= nil and require ~= nil".to_string());
+        let options = TransformOptions {
+            allow_io: true,
+            ..Default::default()
+        };
+        let mut transformer = Transformer::with_options(&config, &options).unwrap();
+
+        let mut row = HashMap::new();
+        row.insert("id".to_string(), Value::Integer(1));
+
+        let result = transformer.transform_batch(&[row]).unwrap();
+        assert_eq!(result[0].get("has_io"), Some(&Value::Boolean(true)));
+    }
+
+    #[test]
+    fn test_row_time_limit_aborts_infinite_loop() {
+        let config =
+            TransformConfig::Script("spin = (function() while true do end end)()".to_string());
+        let options = TransformOptions {
+            allow_io: false,
+            row_timeout: Some(Duration::from_millis(50)),
+            ..Default::default()
+        };
+        let mut transformer = Transformer::with_options(&config, &options).unwrap();
+
+        let mut row = HashMap::new();
+        row.insert("id".to_string(), Value::Integer(1));
+
+        let err = transformer.transform_batch(&[row]).unwrap_err();
+        assert!(err.to_string().contains("per-row time limit of 50ms"));
+    }
+
+    #[test]
+    fn test_predicates() {
+        let transformer = Transformer::new(&TransformConfig::None).unwrap();
+        transformer
+            .load_predicate("us", "row.country == 'US'")
+            .unwrap();
+
+        let mut row = HashMap::new();
+        row.insert("country".to_string(), Value::String("US".to_string()));
+        assert!(transformer.eval_predicate("us", &row).unwrap());
+
+        row.insert("country".to_string(), Value::String("DE".to_string()));
+        assert!(!transformer.eval_predicate("us", &row).unwrap());
+
+        assert!(transformer.load_predicate("bad", "row.country ==").is_err());
+        assert!(transformer.eval_predicate("missing", &row).is_err());
+    }
+
+    #[test]
+    fn test_bundled_helper_modules() {
+        let expressions = "name=require('tinyetl.strings').title_case(require('tinyetl.strings').trim(row.name)); \
+            hire_year=require('tinyetl.dates').year(row.hired); \
+            next_day=require('tinyetl.dates').add_days(row.hired, 1)";
+        let config = TransformConfig::Inline(expressions.to_string());
+        let mut transformer = Transformer::new(&config).unwrap();
+
+        let mut row = HashMap::new();
+        row.insert(
+            "name".to_string(),
+            Value::String("  ada LOVELACE ".to_string()),
+        );
+        row.insert("hired".to_string(), Value::String("2024-02-28".to_string()));
+
+        let result = transformer.transform_batch(&[row]).unwrap();
+        assert_eq!(
+            result[0].get("name"),
+            Some(&Value::String("Ada Lovelace".to_string()))
+        );
+        assert_eq!(result[0].get("hire_year"), Some(&Value::Integer(2024)));
+        assert_eq!(
+            result[0].get("next_day"),
+            Some(&Value::String("2024-02-29".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_require_from_lua_path() {
+        use std::fs;
+
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("team")).unwrap();
+        fs::write(
+            dir.path().join("team").join("helpers.lua"),
+            "local M = {}\nfunction M.shout(s) return string.upper(s) .. '!' end\nreturn M\n",
+        )
+        .unwrap();
+
+        let config =
+            TransformConfig::Inline("loud=require('team.helpers').shout(row.name)".to_string());
+        let options = TransformOptions {
+            lua_path: vec![dir.path().to_path_buf()],
+            ..Default::default()
+        };
+        let mut transformer = Transformer::with_options(&config, &options).unwrap();
+
+        let mut row = HashMap::new();
+        row.insert("name".to_string(), Value::String("hi".to_string()));
+
+        let result = transformer.transform_batch(&[row]).unwrap();
+        assert_eq!(
+            result[0].get("loud"),
+            Some(&Value::String("HI!".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_require_rejects_path_traversal() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(Transformer::find_module(&[dir.path().to_path_buf()], "../secrets").is_none());
+        assert!(Transformer::find_module(&[dir.path().to_path_buf()], "missing").is_none());
+    }
+
+    #[test]
+    fn test_json_decode_and_encode() {
+        let script = r#"
+city = json.decode(row.payload).address.city
+tags = json.encode(json.decode(row.payload).tags)
+count = #json.decode(row.payload).tags
+"#;
+        let config = TransformConfig::Script(script.to_string());
+        let mut transformer = Transformer::new(&config).unwrap();
+
+        let mut row = HashMap::new();
+        row.insert(
+            "payload".to_string(),
+            Value::String(r#"{"address":{"city":"Oslo"},"tags":["a","b"]}"#.to_string()),
+        );
+
+        let result = transformer.transform_batch(&[row]).unwrap();
+        assert_eq!(
+            result[0].get("city"),
+            Some(&Value::String("Oslo".to_string()))
+        );
+        assert_eq!(
+            result[0].get("tags"),
+            Some(&Value::String(r#"["a","b"]"#.to_string()))
+        );
+        assert_eq!(result[0].get("count"), Some(&Value::Integer(2)));
+    }
+
+    #[test]
+    fn test_lua_table_result_becomes_json_column() {
+        let config = TransformConfig::Inline(
+            "meta=json.decode(row.payload); meta_ok=json.decode('null') == nil".to_string(),
+        );
+        let mut transformer = Transformer::new(&config).unwrap();
+
+        let mut row = HashMap::new();
+        row.insert(
+            "payload".to_string(),
+            Value::String(r#"{"id":7,"flags":[true,false]}"#.to_string()),
+        );
+
+        let result = transformer.transform_batch(&[row]).unwrap();
+        assert_eq!(
+            result[0].get("meta"),
+            Some(&Value::Json(
+                serde_json::json!({"id": 7, "flags": [true, false]})
+            ))
+        );
+        assert_eq!(result[0].get("meta_ok"), Some(&Value::Boolean(true)));
+    }
+
+    #[test]
+    fn test_json_decode_invalid_input_fails() {
+        let config = TransformConfig::Inline("bad=json.decode(row.payload)".to_string());
+        let mut transformer = Transformer::new(&config).unwrap();
+
+        let mut row = HashMap::new();
+        row.insert(
+            "payload".to_string(),
+            Value::String("{not json".to_string()),
+        );
+
+        let err = transformer.transform_batch(&[row]).unwrap_err();
+        assert!(err.to_string().contains("json.decode"));
+    }
+
+    #[test]
+    fn test_regex_helpers() {
+        let script = r#"
+phone = re.replace(row.phone, "\\D", "")
+domain = re.capture(row.email, "^[^@]+@(?P<domain>.+)$").domain
+user = re.capture(row.email, "^([^@]+)@")[1]
+valid = re.match(row.email, "^[\\w.+-]+@[\\w-]+\\.[\\w.]+$") ~= nil
+numbers = table.concat(re.find_all(row.phone, "\\d+"), "|")
+missing = re.replace(row.missing, "x", "y") == nil
+"#;
+        let config = TransformConfig::Script(script.to_string());
+        let mut transformer = Transformer::new(&config).unwrap();
+
+        let mut row = HashMap::new();
+        row.insert(
+            "phone".to_string(),
+            Value::String("(555) 123-4567".to_string()),
+        );
+        row.insert(
+            "email".to_string(),
+            Value::String("jane.doe@example.com".to_string()),
+        );
+        row.insert("missing".to_string(), Value::Null);
+
+        let result = transformer.transform_batch(&[row]).unwrap();
+        let row = &result[0];
+        assert_eq!(
+            row.get("phone"),
+            Some(&Value::String("5551234567".to_string()))
+        );
+        assert_eq!(
+            row.get("domain"),
+            Some(&Value::String("example.com".to_string()))
+        );
+        assert_eq!(
+            row.get("user"),
+            Some(&Value::String("jane.doe".to_string()))
+        );
+        assert_eq!(row.get("valid"), Some(&Value::Boolean(true)));
+        assert_eq!(
+            row.get("numbers"),
+            Some(&Value::String("555|123|4567".to_string()))
+        );
+        assert_eq!(row.get("missing"), Some(&Value::Boolean(true)));
+    }
+
+    #[test]
+    fn test_regex_invalid_pattern_fails() {
+        let config = TransformConfig::Inline("bad=re.match(row.name, '(')".to_string());
+        let mut transformer = Transformer::new(&config).unwrap();
+
+        let mut row = HashMap::new();
+        row.insert("name".to_string(), Value::String("x".to_string()));
+
+        let err = transformer.transform_batch(&[row]).unwrap_err();
+        assert!(err.to_string().contains("invalid regex"));
+    }
+
+    #[test]
+    fn test_hash_helpers() {
+        std::env::set_var("TINYETL_SECRET_test_hash_key", "key");
+        let script = r#"
+email_md5 = hash.md5(row.email)
+email_sha = hash.sha256(row.email)
+email_hmac = hash.hmac_sha256(row.email, "key")
+email_secret = hash.hmac_secret(row.email, "test_hash_key")
+customer_key = hash.key(row.id, row.email)
+missing = hash.sha256(row.missing) == nil
+"#;
+        let config = TransformConfig::Script(script.to_string());
+        let mut transformer = Transformer::new(&config).unwrap();
+
+        let mut row = HashMap::new();
+        row.insert("id".to_string(), Value::Integer(42));
+        row.insert("email".to_string(), Value::String("abc".to_string()));
+        row.insert("missing".to_string(), Value::Null);
+
+        let result = transformer.transform_batch(&[row]).unwrap();
+        let row = &result[0];
+        assert_eq!(
+            row.get("email_md5"),
+            Some(&Value::String(
+                "900150983cd24fb0d6963f7d28e17f72".to_string()
+            ))
+        );
+        assert_eq!(
+            row.get("email_sha"),
+            Some(&Value::String(Hasher::sha256_hex(b"abc")))
+        );
+        assert_eq!(row.get("email_hmac"), row.get("email_secret"));
+        assert_eq!(
+            row.get("customer_key"),
+            Some(&Value::String(Hasher::composite_key(&[
+                Some("42"),
+                Some("abc")
+            ])))
+        );
+        assert_eq!(row.get("missing"), Some(&Value::Boolean(true)));
+    }
 }